@@ -1,5 +1,6 @@
 use super::CmdResult;
 use crate::{
+    core::notifications::{self, NoticeRecord},
     feat, logging,
     utils::{dirs, logging::Type},
     wrap_err,
@@ -28,6 +29,27 @@ pub fn open_logs_dir() -> CmdResult<()> {
     wrap_err!(open::that(log_dir))
 }
 
+/// 打开托盘菜单可运行的脚本目录
+#[tauri::command]
+pub fn open_scripts_dir() -> CmdResult<()> {
+    let scripts_dir = wrap_err!(dirs::app_scripts_dir())?;
+    wrap_err!(std::fs::create_dir_all(&scripts_dir))?;
+    wrap_err!(open::that(scripts_dir))
+}
+
+/// 获取通知历史，最新的在前
+#[tauri::command]
+pub fn get_notice_history() -> CmdResult<Vec<NoticeRecord>> {
+    Ok(notifications::history())
+}
+
+/// 清空通知历史
+#[tauri::command]
+pub fn clear_notice_history() -> CmdResult<()> {
+    notifications::clear_history();
+    Ok(())
+}
+
 /// 打开网页链接
 #[tauri::command]
 pub fn open_web_url(url: String) -> CmdResult<()> {
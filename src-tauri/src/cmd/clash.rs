@@ -14,7 +14,11 @@ pub fn copy_clash_env() -> CmdResult {
 /// 获取Clash信息
 #[tauri::command]
 pub fn get_clash_info() -> CmdResult<ClashInfo> {
-    Ok(Config::clash().latest().get_client_info())
+    let info = Config::clash().latest().get_client_info();
+    Ok(info
+        .with_session_secret()
+        .with_controller_ipc()
+        .with_external_core())
 }
 
 /// 修改Clash配置
@@ -30,10 +34,108 @@ pub async fn patch_clash_mode(payload: String) -> CmdResult {
     Ok(())
 }
 
+/// 修改运行中内核的日志等级，无需重新加载配置
+#[tauri::command]
+pub async fn set_core_log_level(level: String) -> CmdResult {
+    wrap_err!(feat::change_core_log_level(level).await)
+}
+
+/// 获取第二个内核实例的混合端口，未运行时返回 null
+#[tauri::command]
+pub async fn get_secondary_core_port() -> CmdResult<Option<u16>> {
+    Ok(CoreManager::global().secondary_port().await)
+}
+
+/// 将当前的内核文件记录为可信，用于内核自行升级之后
+#[tauri::command]
+pub fn trust_core_binary(clash_core: String) -> CmdResult {
+    wrap_err!(integrity::record(&clash_core))
+}
+
+/// 获取 GeoIP/GeoSite 数据库的状态
+#[tauri::command]
+pub fn get_geodata_status() -> CmdResult<GeoDataStatus> {
+    Ok(GeoDataManager::global().status())
+}
+
+/// 立即检查并更新 GeoIP/GeoSite 数据库
+#[tauri::command]
+pub async fn update_geodata() -> CmdResult<GeoDataStatus> {
+    wrap_err!(GeoDataManager::global().update().await)?;
+    Ok(GeoDataManager::global().status())
+}
+
+/// 测试各 TUN 协议栈经过测试节点的延迟与速度
+#[tauri::command]
+pub async fn bench_tun_stacks(node: Option<String>) -> CmdResult<feat::TunBenchReport> {
+    wrap_err!(feat::bench_tun_stacks(node).await)
+}
+
+/// 获取上次的 TUN 协议栈测试结果
+#[tauri::command]
+pub fn get_tun_bench() -> CmdResult<Option<feat::TunBenchReport>> {
+    Ok(feat::last_tun_bench())
+}
+
+/// 应用推荐的 TUN 协议栈
+#[tauri::command]
+pub async fn apply_tun_stack(stack: String) -> CmdResult {
+    wrap_err!(feat::apply_tun_stack(stack).await)
+}
+
+/// 获取已安装的内核
+#[tauri::command]
+pub fn get_installed_cores() -> CmdResult<Vec<CoreInfo>> {
+    Ok(CoreManager::global().list_cores())
+}
+
+/// 检查内核更新
+#[tauri::command]
+pub async fn check_core_update(clash_core: String) -> CmdResult<feat::CoreRelease> {
+    wrap_err!(feat::check_core_update(&clash_core).await)
+}
+
+/// 从 GitHub 下载并替换内核
+#[tauri::command]
+pub async fn update_core(clash_core: String) -> CmdResult<feat::CoreRelease> {
+    wrap_err!(feat::update_core(&clash_core).await)
+}
+
+/// 恢复更新前的内核
+#[tauri::command]
+pub async fn rollback_core(clash_core: String) -> CmdResult {
+    wrap_err!(feat::rollback_core(&clash_core).await)
+}
+
+/// 重新生成控制接口 secret
+#[tauri::command]
+pub async fn rotate_clash_secret() -> CmdResult {
+    wrap_err!(feat::rotate_clash_secret().await)
+}
+
+/// 获取最近的内核资源采样
+#[tauri::command]
+pub fn get_core_telemetry() -> CmdResult<Vec<CoreSample>> {
+    Ok(CoreTelemetry::global().samples())
+}
+
+/// 获取内核健康状态
+#[tauri::command]
+pub fn get_core_health() -> CmdResult<CoreHealthStatus> {
+    Ok(CoreHealth::global().status())
+}
+
+/// 获取内核沙盒的实际状态
+#[tauri::command]
+pub fn get_core_sandbox_status() -> CmdResult<sandbox::SandboxStatus> {
+    Ok(sandbox::status())
+}
+
 /// 切换Clash核心
 #[tauri::command]
 pub async fn change_clash_core(clash_core: String) -> CmdResult<Option<String>> {
     log::info!(target: "app", "changing core to {clash_core}");
+    let previous = Config::verge().latest().get_valid_clash_core();
 
     match CoreManager::global()
         .change_core(Some(clash_core.clone()))
@@ -51,6 +153,19 @@ pub async fn change_clash_core(clash_core: String) -> CmdResult<Option<String>>
                 Err(err) => {
                     let error_msg = format!("Core changed but failed to restart: {err}");
                     log::error!(target: "app", "{error_msg}");
+                    // 新内核无法启动时回退到之前的内核
+                    if previous != clash_core
+                        && CoreManager::global()
+                            .change_core(Some(previous.clone()))
+                            .await
+                            .is_ok()
+                    {
+                        log::warn!(target: "app", "rolling back to core {previous}");
+                        if let Err(err) = CoreManager::global().restart_core().await {
+                            log::error!(target: "app", "failed to restart core {previous}: {err}");
+                        }
+                        handle::Handle::refresh_clash();
+                    }
                     handle::Handle::notice_message("config_core::change_error", &error_msg);
                     Ok(Some(error_msg))
                 }
@@ -95,6 +210,44 @@ pub async fn clash_api_get_proxy_delay(
         .await
 }
 
+/// 获取内核上次启动失败的原因
+#[tauri::command]
+pub fn get_core_diagnosis() -> CmdResult<Option<CoreDiagnosis>> {
+    Ok(CoreDiagnosis::last())
+}
+
+/// 控制接口走套接字时，前端的请求由这里转发
+#[tauri::command]
+pub async fn clash_api_request(
+    method: String,
+    path: String,
+    body: Option<String>,
+) -> CmdResult<serde_json::Value> {
+    let method = wrap_err!(reqwest::Method::from_bytes(
+        method.to_uppercase().as_bytes()
+    ))?;
+    let (status, body) = MihomoManager::global()
+        .request_raw(method, &path, body.map(String::into_bytes), None)
+        .await?;
+    Ok(serde_json::json!({
+        "status": status,
+        "body": String::from_utf8_lossy(&body),
+    }))
+}
+
+/// 打开转发的流式接口，数据通过 verge://controller-stream 推送
+#[tauri::command]
+pub fn clash_api_stream_open(path: String) -> CmdResult<u32> {
+    Ok(MihomoManager::open_stream(path))
+}
+
+/// 关闭转发的流式接口
+#[tauri::command]
+pub fn clash_api_stream_close(id: u32) -> CmdResult {
+    MihomoManager::close_stream(id);
+    Ok(())
+}
+
 /// 测试URL延迟
 #[tauri::command]
 pub async fn test_delay(url: String) -> CmdResult<u32> {
@@ -1,6 +1,16 @@
 use super::CmdResult;
-use crate::core::{async_proxy_query::AsyncProxyQuery, EventDrivenProxyManager};
-use crate::wrap_err;
+use crate::core::{
+    async_proxy_query::AsyncProxyQuery,
+    bypass::{self, BypassPreset},
+    captive_portal,
+    conflicts::{self, Conflict},
+    dns_check::{self, DnsHijackReport},
+    lan_share::{self, LanClient, LanShare},
+    proxy_networks,
+    sysopt::NetworkService,
+    EventDrivenProxyManager,
+};
+use crate::{feat, wrap_err};
 use network_interface::NetworkInterface;
 use serde_yaml::Mapping;
 
@@ -93,3 +103,86 @@ pub fn get_network_interfaces_info() -> CmdResult<Vec<NetworkInterface>> {
 
     Ok(result)
 }
+
+/// 系统代理绕过列表的预设
+#[tauri::command]
+pub fn get_bypass_presets() -> Vec<serde_json::Value> {
+    BypassPreset::ALL
+        .iter()
+        .map(|preset| {
+            serde_json::json!({
+                "id": preset.id(),
+                "entries": preset.entries(),
+            })
+        })
+        .collect()
+}
+
+/// 校验系统代理绕过列表，返回无效的条目
+#[tauri::command]
+pub fn validate_bypass(bypass: String) -> Vec<String> {
+    bypass::validate(&bypass)
+}
+
+/// 检查 TUN 模式下 DNS 是否被接管，`repair` 时尝试修复
+#[tauri::command]
+pub async fn check_dns_hijack(repair: bool) -> CmdResult<DnsHijackReport> {
+    Ok(dns_check::check(repair).await)
+}
+
+/// 清理崩溃后残留的代理、TUN 网卡与路由，并刷新 DNS
+#[tauri::command]
+pub async fn reset_network() -> CmdResult<Vec<feat::ResetStep>> {
+    Ok(feat::reset_network().await)
+}
+
+/// 查找可能与代理、TUN 冲突的软件
+#[tauri::command]
+pub async fn scan_conflicts() -> CmdResult<Vec<Conflict>> {
+    Ok(conflicts::scan().await)
+}
+
+/// 获取局域网共享状态与地址
+#[tauri::command]
+pub async fn get_lan_share() -> CmdResult<LanShare> {
+    Ok(lan_share::status().await)
+}
+
+/// 开启或关闭局域网共享
+#[tauri::command]
+pub async fn set_lan_share(enable: bool) -> CmdResult<LanShare> {
+    wrap_err!(lan_share::set(enable).await)
+}
+
+/// 获取最近通过局域网使用代理的设备
+#[tauri::command]
+pub async fn get_lan_clients() -> CmdResult<Vec<LanClient>> {
+    wrap_err!(lan_share::clients().await)
+}
+
+/// 获取 macOS 的网络服务，其他平台没有网络服务
+#[tauri::command]
+pub fn get_network_services() -> CmdResult<Vec<NetworkService>> {
+    #[cfg(target_os = "macos")]
+    return wrap_err!(crate::core::macos_proxy::list_services());
+    #[cfg(not(target_os = "macos"))]
+    Ok(vec![])
+}
+
+/// 是否正在为认证页面暂停系统代理与 TUN
+#[tauri::command]
+pub fn get_captive_portal_bypass() -> CmdResult<bool> {
+    Ok(captive_portal::is_bypassing())
+}
+
+/// 手动进入或退出认证页面的绕过模式
+#[tauri::command]
+pub async fn set_captive_portal_bypass(enable: bool) -> CmdResult {
+    wrap_err!(captive_portal::set_bypass(enable).await)
+}
+
+/// 当前连接的 Wi-Fi 名称与网卡名称
+#[tauri::command]
+pub async fn get_current_networks() -> CmdResult<Vec<String>> {
+    Ok(proxy_networks::current_networks().await)
+}
@@ -1,19 +1,19 @@
 use super::CmdResult;
 use crate::{
-    config::{Config, IProfiles, PrfItem, PrfOption},
+    config::{
+        self, Config, IProfiles, PrfAuth, PrfItem, PrfOption, ProfileComparison, ProfileDiff,
+        ProfileRevision,
+    },
     core::{handle, timer::Timer, tray::Tray, CoreManager},
     feat, logging, ret_err,
-    utils::{dirs, help, logging::Type},
+    utils::{dirs, help, logging::Type, share_link, singbox},
     wrap_err,
 };
-use base64::{engine::general_purpose::STANDARD, Engine as _};
-use percent_encoding::percent_decode_str;
+use futures::StreamExt;
 use serde_yaml::Value;
-use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
-use url::Url;
 
 // 全局互斥锁防止并发配置更新
 static PROFILE_UPDATE_MUTEX: Mutex<()> = Mutex::const_new(());
@@ -48,6 +48,8 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             IProfiles {
                 current: latest.current.clone(),
                 items: latest.items.clone(),
+                groups: latest.groups.clone(),
+                recent: latest.recent.clone(),
             }
         }),
     )
@@ -75,6 +77,8 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             IProfiles {
                 current: data.current.clone(),
                 items: data.items.clone(),
+                groups: data.groups.clone(),
+                recent: data.recent.clone(),
             }
         }),
     )
@@ -118,6 +122,8 @@ pub async fn get_profiles() -> CmdResult<IProfiles> {
             Ok(IProfiles {
                 current: None,
                 items: Some(vec![]),
+                groups: None,
+                recent: None,
             })
         }
     }
@@ -134,16 +140,7 @@ pub async fn enhance_profiles() -> CmdResult {
 /// 导入配置文件
 #[tauri::command]
 pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult {
-    let existing_uid = {
-        let profiles = Config::profiles();
-        let profiles = profiles.latest();
-
-        profiles
-            .items
-            .as_ref()
-            .and_then(|items| items.iter().find(|item| item.url.as_deref() == Some(&url)))
-            .and_then(|item| item.uid.clone())
-    };
+    let existing_uid = Config::profiles().latest().find_duplicate(Some(&url), None);
 
     if let Some(uid) = existing_uid {
         logging!(
@@ -170,6 +167,8 @@ pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult
             let _ = patch_profiles_config(IProfiles {
                 current: Some(new_uid),
                 items: None,
+                groups: None,
+                recent: None,
             })
             .await?;
         }
@@ -177,12 +176,282 @@ pub async fn import_profile(url: String, option: Option<PrfOption>) -> CmdResult
     }
 }
 
+/// 批量导入的单条结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProfileImportResult {
+    pub url: String,
+    pub uid: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 批量导入时的最大并发下载数
+const IMPORT_CONCURRENCY: usize = 4;
+
+/// 批量导入订阅，`urls` 为按行分隔的链接，或通过 `path` 指定包含链接的文本文件
+#[tauri::command]
+pub async fn import_profiles(
+    urls: Option<String>,
+    path: Option<String>,
+    option: Option<PrfOption>,
+) -> CmdResult<Vec<ProfileImportResult>> {
+    let text = match (urls, path) {
+        (Some(urls), _) => urls,
+        (None, Some(path)) => wrap_err!(std::fs::read_to_string(path))?,
+        (None, None) => ret_err!("no subscription urls provided"),
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let candidates: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| seen.insert(line.to_string()))
+        .map(str::to_string)
+        .collect();
+
+    let existing: std::collections::HashSet<String> = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        profiles
+            .items
+            .as_ref()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.url.as_deref().map(config::canonical_url))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    logging!(
+        info,
+        Type::Cmd,
+        true,
+        "Bulk importing {} subscription urls",
+        candidates.len()
+    );
+
+    let results: Vec<ProfileImportResult> = futures::stream::iter(candidates)
+        .map(|url| {
+            let option = option.clone();
+            let exists = existing.contains(&config::canonical_url(&url));
+            async move {
+                let failed = |error: String| ProfileImportResult {
+                    url: url.clone(),
+                    uid: None,
+                    success: false,
+                    error: Some(error),
+                };
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return failed("unsupported url".into());
+                }
+                if exists {
+                    return failed("profile already exists".into());
+                }
+
+                let item = match PrfItem::from_url(&url, None, None, option).await {
+                    Ok(item) => item,
+                    Err(err) => return failed(err.to_string()),
+                };
+                let uid = item.uid.clone();
+                match Config::profiles().data().append_item(item) {
+                    Ok(_) => ProfileImportResult {
+                        url: url.clone(),
+                        uid,
+                        success: true,
+                        error: None,
+                    },
+                    Err(err) => failed(err.to_string()),
+                }
+            }
+        })
+        .buffered(IMPORT_CONCURRENCY)
+        .collect()
+        .await;
+
+    let succeeded = results.iter().filter(|r| r.success).count();
+    logging!(
+        info,
+        Type::Cmd,
+        true,
+        "Bulk import finished, {} of {} succeeded",
+        succeeded,
+        results.len()
+    );
+
+    // 只在最后切换到第一个导入成功的订阅，核心仅刷新一次
+    if let Some(first_uid) = results
+        .iter()
+        .find(|r| r.success)
+        .and_then(|r| r.uid.clone())
+    {
+        patch_profiles_config(IProfiles {
+            current: Some(first_uid),
+            items: None,
+            groups: None,
+            recent: None,
+        })
+        .await?;
+    }
+
+    Ok(results)
+}
+
+/// 查找与待导入的链接或文件内容重复的已有配置，返回其 uid
+#[tauri::command]
+pub fn find_duplicate_profile(
+    url: Option<String>,
+    file_data: Option<String>,
+) -> CmdResult<Option<String>> {
+    Ok(Config::profiles()
+        .latest()
+        .find_duplicate(url.as_deref(), file_data.as_deref()))
+}
+
+/// 删除重复的配置，保留当前使用或最近更新的一份，返回被删除的 uid
+#[tauri::command]
+pub async fn deduplicate_profiles() -> CmdResult<Vec<String>> {
+    let duplicates = Config::profiles().latest().duplicate_items();
+    if duplicates.is_empty() {
+        return Ok(duplicates);
+    }
+
+    logging!(
+        info,
+        Type::Cmd,
+        true,
+        "Removing {} duplicate profiles",
+        duplicates.len()
+    );
+    {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.data();
+        for uid in duplicates.iter() {
+            wrap_err!(profiles.delete_item(uid.clone()))?;
+        }
+    }
+    let _ = Config::profiles().latest().auto_cleanup();
+
+    Ok(duplicates)
+}
+
+/// 导出订阅及其扩展配置，可选择写入文件
+#[tauri::command]
+pub fn export_profile_bundle(
+    index: String,
+    strip_secrets: Option<bool>,
+    path: Option<String>,
+) -> CmdResult<String> {
+    let bundle = wrap_err!(feat::export_profile_bundle(
+        &index,
+        strip_secrets.unwrap_or(true)
+    ))?;
+    if let Some(path) = path {
+        wrap_err!(std::fs::write(path, &bundle))?;
+    }
+    Ok(bundle)
+}
+
+/// 导入由 `export_profile_bundle` 导出的订阅
+#[tauri::command]
+pub async fn import_profile_bundle(data: String) -> CmdResult {
+    let item = wrap_err!(feat::import_profile_bundle(&data))?;
+    let new_uid = item.uid.clone().unwrap_or_default();
+    wrap_err!(Config::profiles().data().append_item(item))?;
+    if !new_uid.is_empty() {
+        patch_profiles_config(IProfiles {
+            current: Some(new_uid),
+            items: None,
+            groups: None,
+            recent: None,
+        })
+        .await?;
+    }
+    Ok(())
+}
+
 /// 重新排序配置文件
 #[tauri::command]
 pub async fn reorder_profile(active_id: String, over_id: String) -> CmdResult {
     wrap_err!(Config::profiles().data().reorder(active_id, over_id))
 }
 
+/// 设置订阅的 merge 模板顺序，当前订阅会重新生成配置
+#[tauri::command]
+pub async fn reorder_profile_merges(index: String, merges: Vec<String>) -> CmdResult {
+    let is_current = {
+        let mut profiles = Config::profiles().data();
+        wrap_err!(profiles.set_merges(&index, merges))?;
+        profiles.is_current_profile_index(index)
+    };
+
+    if is_current {
+        wrap_err!(CoreManager::global().update_config().await)?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(())
+}
+
+/// 查找并清理不再使用的配置文件，`mode` 为 report 时只报告
+#[tauri::command]
+pub fn vacuum_profiles(mode: config::VacuumMode) -> CmdResult<config::VacuumReport> {
+    let report = wrap_err!(Config::profiles().data().vacuum(mode))?;
+    if !report.dangling_items.is_empty() {
+        handle::Handle::notify_profile_changed(
+            Config::profiles()
+                .latest()
+                .get_current()
+                .unwrap_or_default(),
+        );
+    }
+    Ok(report)
+}
+
+/// 创建订阅分组
+#[tauri::command]
+pub fn create_profile_group(name: String) -> CmdResult<String> {
+    wrap_err!(Config::profiles().data().create_group(name))
+}
+
+/// 重命名订阅分组
+#[tauri::command]
+pub fn rename_profile_group(uid: String, name: String) -> CmdResult {
+    wrap_err!(Config::profiles().data().rename_group(&uid, name))
+}
+
+/// 删除订阅分组，分组内的订阅保留
+#[tauri::command]
+pub fn delete_profile_group(uid: String) -> CmdResult {
+    wrap_err!(Config::profiles().data().delete_group(&uid))
+}
+
+/// 将订阅移动到分组，`group` 为空时移出分组
+#[tauri::command]
+pub fn move_profile_to_group(index: String, group: Option<String>) -> CmdResult {
+    wrap_err!(Config::profiles().data().move_item_to_group(&index, group))
+}
+
+/// 设置订阅的标签
+#[tauri::command]
+pub fn set_profile_tags(index: String, tags: Vec<String>) -> CmdResult {
+    wrap_err!(Config::profiles().data().set_item_tags(&index, tags))
+}
+
+/// 设置订阅的排序位置，托盘和列表按此排序
+#[tauri::command]
+pub fn set_profile_sort_index(index: String, sort_index: Option<i64>) -> CmdResult {
+    wrap_err!(Config::profiles()
+        .data()
+        .set_item_sort_index(&index, sort_index))?;
+    if let Err(err) = Tray::global().update_menu() {
+        log::warn!(target: "app", "failed to update the tray menu: {err}");
+    }
+    Ok(())
+}
+
 /// 创建配置文件
 #[tauri::command]
 pub async fn create_profile(item: PrfItem, file_data: Option<String>) -> CmdResult {
@@ -194,6 +463,8 @@ pub async fn create_profile(item: PrfItem, file_data: Option<String>) -> CmdResu
         let _ = patch_profiles_config(IProfiles {
             current: Some(new_uid),
             items: None,
+            groups: None,
+            recent: None,
         })
         .await?;
     }
@@ -206,6 +477,94 @@ pub async fn update_profile(index: String, option: Option<PrfOption>) -> CmdResu
     wrap_err!(feat::update_profile(index, option, Some(true)).await)
 }
 
+/// 在不切换的情况下验证配置文件能否被内核加载
+#[tauri::command]
+pub async fn validate_profile(uid: String) -> CmdResult<feat::ProfileValidation> {
+    wrap_err!(feat::validate_profile(uid).await)
+}
+
+/// 批量更新所有远程订阅
+#[tauri::command]
+pub async fn update_all_profiles() -> CmdResult<Vec<feat::ProfileUpdateResult>> {
+    wrap_err!(feat::update_all_profiles().await)
+}
+
+/// 预览订阅更新的变化，确认后再应用
+#[tauri::command]
+pub async fn preview_profile_update(
+    index: String,
+    option: Option<PrfOption>,
+) -> CmdResult<ProfileDiff> {
+    wrap_err!(feat::preview_profile_update(index, option).await)
+}
+
+/// 应用已预览的订阅更新
+#[tauri::command]
+pub async fn apply_profile_update(index: String) -> CmdResult {
+    wrap_err!(feat::apply_profile_update(index).await)
+}
+
+/// 放弃已预览的订阅更新
+#[tauri::command]
+pub fn discard_profile_update(index: String) -> CmdResult {
+    feat::discard_profile_update(&index);
+    Ok(())
+}
+
+/// 复制配置文件及其关联的 merge 和脚本，返回新的 uid
+#[tauri::command]
+pub fn duplicate_profile(index: String) -> CmdResult<String> {
+    let uid = wrap_err!(feat::duplicate_profile(&index))?;
+    handle::Handle::notify_profile_changed(
+        Config::profiles()
+            .latest()
+            .get_current()
+            .unwrap_or_default(),
+    );
+    Ok(uid)
+}
+
+/// 对比两个配置文件的节点、代理组和规则
+#[tauri::command]
+pub fn diff_profiles(uid_a: String, uid_b: String) -> CmdResult<ProfileComparison> {
+    let (data_a, data_b) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item_a = wrap_err!(profiles.get_item(&uid_a))?;
+        let item_b = wrap_err!(profiles.get_item(&uid_b))?;
+        (
+            wrap_err!(item_a.read_file())?,
+            wrap_err!(item_b.read_file())?,
+        )
+    };
+
+    let a = wrap_err!(serde_yaml::from_str::<serde_yaml::Mapping>(&data_a))?;
+    let b = wrap_err!(serde_yaml::from_str::<serde_yaml::Mapping>(&data_b))?;
+    Ok(ProfileComparison::compute(&a, &b))
+}
+
+/// 按名称、地址和节点名搜索配置文件，用于快速切换
+#[tauri::command]
+pub fn search_profiles(
+    query: String,
+    regex: Option<bool>,
+    limit: Option<usize>,
+) -> CmdResult<Vec<feat::ProfileMatch>> {
+    wrap_err!(feat::search_profiles(&query, regex.unwrap_or(false), limit))
+}
+
+/// 获取订阅的历史版本
+#[tauri::command]
+pub fn list_profile_revisions(index: String) -> CmdResult<Vec<ProfileRevision>> {
+    wrap_err!(crate::config::list_profile_revisions(&index))
+}
+
+/// 回滚订阅到指定的历史版本
+#[tauri::command]
+pub async fn rollback_profile_revision(index: String, revision: String) -> CmdResult {
+    wrap_err!(feat::rollback_profile_revision(index, revision).await)
+}
+
 /// 删除配置文件
 #[tauri::command]
 pub async fn delete_profile(index: String) -> CmdResult {
@@ -219,9 +578,12 @@ pub async fn delete_profile(index: String) -> CmdResult {
             .map_err(|e| e.to_string())?;
 
         let was_last_profile = profiles_data.items.as_ref().is_none_or(|items| {
-            !items
-                .iter()
-                .any(|item| matches!(item.itype.as_deref(), Some("remote") | Some("local")))
+            !items.iter().any(|item| {
+                matches!(
+                    item.itype.as_deref(),
+                    Some("remote") | Some("local") | Some("composite")
+                )
+            })
         });
 
         if was_last_profile {
@@ -362,10 +724,10 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                 }
 
                 // 超时保护
-                let file_read_result = tokio::time::timeout(
-                    Duration::from_secs(5),
-                    tokio::fs::read_to_string(&file_path),
-                )
+                let file_read_result = tokio::time::timeout(Duration::from_secs(5), async {
+                    let content = tokio::fs::read_to_string(&file_path).await?;
+                    config::decrypt_profile(content)
+                })
                 .await;
 
                 match file_read_result {
@@ -425,6 +787,21 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                     }
                 }
             }
+
+            // 订阅要求的最低内核版本
+            let min_core_version = Config::profiles()
+                .latest()
+                .get_item(new_profile)
+                .ok()
+                .and_then(|item| item.option.as_ref()?.min_core_version.clone())
+                .filter(|version| !version.trim().is_empty());
+            if let Some(required) = min_core_version {
+                if let Err(err) = feat::ensure_core_version(&required).await {
+                    logging!(error, Type::Cmd, true, "Core version check failed: {}", err);
+                    handle::Handle::notice_message("config_core::version_error", err.to_string());
+                    return Ok(false);
+                }
+            }
         }
     }
 
@@ -578,6 +955,8 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                 let restore_profiles = IProfiles {
                     current: Some(prev_profile),
                     items: None,
+                    groups: None,
+                    recent: None,
                 };
                 // 静默恢复，不触发验证
                 wrap_err!({ Config::profiles().draft().patch_config(restore_profiles) })?;
@@ -640,6 +1019,8 @@ pub async fn patch_profiles_config(profiles: IProfiles) -> CmdResult<bool> {
                 let restore_profiles = IProfiles {
                     current: Some(prev_profile),
                     items: None,
+                    groups: None,
+                    recent: None,
                 };
                 wrap_err!({ Config::profiles().draft().patch_config(restore_profiles) })?;
                 Config::profiles().apply();
@@ -665,6 +1046,8 @@ pub async fn patch_profiles_config_by_profile_index(
     let profiles = IProfiles {
         current: Some(profile_index),
         items: None,
+        groups: None,
+        recent: None,
     };
     patch_profiles_config(profiles).await
 }
@@ -672,12 +1055,17 @@ pub async fn patch_profiles_config_by_profile_index(
 /// 修改某个profile item的
 #[tauri::command]
 pub fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
-    // 保存修改前检查是否有更新 update_interval
+    // 保存修改前检查是否有更新 update_interval / update_cron
     let update_interval_changed =
         if let Ok(old_profile) = Config::profiles().latest().get_item(&index) {
             let old_interval = old_profile.option.as_ref().and_then(|o| o.update_interval);
             let new_interval = profile.option.as_ref().and_then(|o| o.update_interval);
-            old_interval != new_interval
+            let old_cron = old_profile
+                .option
+                .as_ref()
+                .and_then(|o| o.update_cron.clone());
+            let new_cron = profile.option.as_ref().and_then(|o| o.update_cron.clone());
+            old_interval != new_interval || old_cron != new_cron
         } else {
             false
         };
@@ -702,6 +1090,15 @@ pub fn patch_profile(index: String, profile: PrfItem) -> CmdResult {
     Ok(())
 }
 
+/// 获取内置的 User-Agent 预设 (名称, User-Agent)
+#[tauri::command]
+pub fn get_user_agent_presets() -> CmdResult<Vec<(String, String)>> {
+    Ok(crate::config::USER_AGENT_PRESETS
+        .iter()
+        .map(|(name, ua)| (name.to_string(), ua.to_string()))
+        .collect())
+}
+
 /// 查看配置文件
 #[tauri::command]
 pub fn view_profile(app_handle: tauri::AppHandle, index: String) -> CmdResult {
@@ -755,9 +1152,11 @@ pub async fn update_profiles_on_startup() -> CmdResult {
             items
                 .iter()
                 .filter(|item| {
-                    item.option
-                        .as_ref()
-                        .is_some_and(|opt| opt.update_always == Some(true))
+                    !item.is_locked()
+                        && item
+                            .option
+                            .as_ref()
+                            .is_some_and(|opt| opt.update_always == Some(true))
                 })
                 .filter_map(|item| item.uid.clone())
                 .collect()
@@ -1171,106 +1570,18 @@ pub async fn create_profile_from_share_link(link: String, template_name: String)
         _ => DEFAULT_TEMPLATE,
     };
 
-    let parsed_url = Url::parse(&link).map_err(|e| e.to_string())?;
-    let scheme = parsed_url.scheme();
-    let proxy_name = parsed_url
-        .fragment()
-        .map(|f| percent_decode_str(f).decode_utf8_lossy().to_string())
-        .unwrap_or_else(|| "Proxy from Link".to_string());
-
-    let mut proxy_map: BTreeMap<String, Value> = BTreeMap::new();
-    proxy_map.insert("name".into(), proxy_name.clone().into());
-    proxy_map.insert("type".into(), scheme.into());
-    proxy_map.insert(
-        "server".into(),
-        parsed_url.host_str().unwrap_or_default().into(),
-    );
-    proxy_map.insert("port".into(), parsed_url.port().unwrap_or(443).into());
-    proxy_map.insert("udp".into(), true.into());
-
-    match scheme {
-        "vless" | "trojan" => {
-            proxy_map.insert("uuid".into(), parsed_url.username().into());
-            let mut reality_opts: BTreeMap<String, Value> = BTreeMap::new();
-            for (key, value) in parsed_url.query_pairs() {
-                match key.as_ref() {
-                    "security" if value == "reality" => {
-                        proxy_map.insert("tls".into(), true.into());
-                    }
-                    "security" if value == "tls" => {
-                        proxy_map.insert("tls".into(), true.into());
-                    }
-                    "flow" => {
-                        proxy_map.insert("flow".into(), value.to_string().into());
-                    }
-                    "sni" => {
-                        proxy_map.insert("servername".into(), value.to_string().into());
-                    }
-                    "fp" => {
-                        proxy_map.insert("client-fingerprint".into(), value.to_string().into());
-                    }
-                    "pbk" => {
-                        reality_opts.insert("public-key".into(), value.to_string().into());
-                    }
-                    "sid" => {
-                        reality_opts.insert("short-id".into(), value.to_string().into());
-                    }
-                    _ => {}
-                }
-            }
-            if !reality_opts.is_empty() {
-                proxy_map.insert(
-                    "reality-opts".into(),
-                    serde_yaml::to_value(reality_opts).map_err(|e| e.to_string())?,
-                );
-            }
-        }
-        "ss" => {
-            if let Ok(decoded_user) = STANDARD.decode(parsed_url.username()) {
-                if let Ok(user_str) = String::from_utf8(decoded_user) {
-                    if let Some((cipher, password)) = user_str.split_once(':') {
-                        proxy_map.insert("cipher".into(), cipher.into());
-                        proxy_map.insert("password".into(), password.into());
-                    }
-                }
-            }
-        }
-        "vmess" => {
-            if let Ok(decoded_bytes) = STANDARD.decode(parsed_url.host_str().unwrap_or_default()) {
-                if let Ok(json_str) = String::from_utf8(decoded_bytes) {
-                    if let Ok(vmess_params) =
-                        serde_json::from_str::<BTreeMap<String, Value>>(&json_str)
-                    {
-                        if let Some(add) = vmess_params.get("add") {
-                            proxy_map.insert("server".into(), add.clone());
-                        }
-                        if let Some(port) = vmess_params.get("port") {
-                            proxy_map.insert("port".into(), port.clone());
-                        }
-                        if let Some(id) = vmess_params.get("id") {
-                            proxy_map.insert("uuid".into(), id.clone());
-                        }
-                        if let Some(aid) = vmess_params.get("aid") {
-                            proxy_map.insert("alterId".into(), aid.clone());
-                        }
-                        if let Some(net) = vmess_params.get("net") {
-                            proxy_map.insert("network".into(), net.clone());
-                        }
-                        if let Some(ps) = vmess_params.get("ps") {
-                            proxy_map.insert("name".into(), ps.clone());
-                        }
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
+    let proxy = wrap_err!(share_link::parse_share_link(link.trim()))?;
+    let proxy_name = proxy
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or("Proxy from Link")
+        .to_string();
 
     let mut config: Value = serde_yaml::from_str(template_yaml).map_err(|e| e.to_string())?;
 
     if let Some(proxies) = config.get_mut("proxies").and_then(|v| v.as_sequence_mut()) {
         proxies.clear();
-        proxies.push(serde_yaml::to_value(proxy_map).map_err(|e| e.to_string())?);
+        proxies.push(Value::Mapping(proxy));
     }
 
     if let Some(groups) = config
@@ -1310,3 +1621,163 @@ pub async fn create_profile_from_share_link(link: String, template_name: String)
 
     wrap_err!(Config::profiles().data().append_item(item))
 }
+
+/// 从分享链接列表（或其 base64 编码）创建本地配置，返回无法转换的条目
+#[tauri::command]
+pub async fn import_share_links(data: String, name: Option<String>) -> CmdResult<Vec<String>> {
+    let result = share_link::parse_share_links(&data);
+    if result.proxies.is_empty() {
+        ret_err!("no valid share link found");
+    }
+
+    let config = share_link::build_share_link_profile(&result.proxies);
+    let content = wrap_err!(serde_yaml::to_string(&config))?;
+    let item = wrap_err!(PrfItem::from_local(
+        name.unwrap_or("Share Links".into()),
+        format!("Created from {} share links", result.proxies.len()),
+        Some(content),
+        None,
+    ))?;
+    wrap_err!(Config::profiles().data().append_item(item))?;
+
+    Ok(result.skipped)
+}
+
+/// 剪贴板导入的结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ClipboardImport {
+    pub format: share_link::ImportFormat,
+    /// 是否已导入，`dry_run` 时为 false
+    pub imported: bool,
+    /// 无法转换或导入失败的条目
+    pub skipped: Vec<String>,
+}
+
+/// 识别剪贴板内容的格式并交给对应的导入方式，`dry_run` 为 true 时只识别不导入
+#[tauri::command]
+pub async fn import_from_clipboard(dry_run: Option<bool>) -> CmdResult<ClipboardImport> {
+    use share_link::ImportFormat;
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let Some(app_handle) = handle::Handle::global().app_handle() else {
+        ret_err!("the app handle is not ready");
+    };
+    let text = wrap_err!(app_handle.clipboard().read_text())?;
+    let text = text.trim().to_string();
+
+    let format = share_link::detect_import_format(&text);
+    let mut result = ClipboardImport {
+        format,
+        imported: false,
+        skipped: vec![],
+    };
+    if dry_run.unwrap_or(false) {
+        return Ok(result);
+    }
+
+    match format {
+        ImportFormat::Url => import_profile(text, None).await?,
+        ImportFormat::Urls => {
+            let results = import_profiles(Some(text), None, None).await?;
+            result.skipped = results
+                .into_iter()
+                .filter(|r| !r.success)
+                .map(|r| format!("{}: {}", r.url, r.error.unwrap_or_default()))
+                .collect();
+        }
+        ImportFormat::ShareLinks => result.skipped = import_share_links(text, None).await?,
+        ImportFormat::SingBox => result.skipped = import_singbox_config(text, None).await?,
+        ImportFormat::ClashYaml => {
+            let item = wrap_err!(PrfItem::from_local(
+                "Clipboard".into(),
+                "Imported from clipboard".into(),
+                Some(text),
+                None,
+            ))?;
+            wrap_err!(Config::profiles().data().append_item(item))?;
+        }
+        ImportFormat::Unknown => {
+            ret_err!("the clipboard content is not a subscription or config")
+        }
+    }
+
+    result.imported = true;
+    Ok(result)
+}
+
+/// 从 sing-box 配置转换为本地配置，返回无法转换的出站和规则
+#[tauri::command]
+pub async fn import_singbox_config(data: String, name: Option<String>) -> CmdResult<Vec<String>> {
+    let result = wrap_err!(singbox::convert_singbox(&data))?;
+    let content = wrap_err!(serde_yaml::to_string(&result.config))?;
+    let item = wrap_err!(PrfItem::from_local(
+        name.unwrap_or("sing-box".into()),
+        "Converted from sing-box config".into(),
+        Some(content),
+        None,
+    ))?;
+    wrap_err!(Config::profiles().data().append_item(item))?;
+
+    Ok(result.unsupported)
+}
+
+/// 导入文件夹中的 Clash 配置为本地订阅，`watch` 为 true 时继续监视新文件
+#[tauri::command]
+pub fn import_profile_dir(dir: String, watch: Option<bool>) -> CmdResult<feat::FolderImport> {
+    let result = wrap_err!(feat::import_profile_dir(&dir))?;
+    if watch.unwrap_or(false) {
+        wrap_err!(feat::watch_profile_dir(dir, true))?;
+    }
+    Ok(result)
+}
+
+/// 停止监视导入文件夹，已导入的订阅保留
+#[tauri::command]
+pub fn unwatch_profile_dir(dir: String) -> CmdResult {
+    wrap_err!(feat::watch_profile_dir(dir, false))
+}
+
+/// 开启或关闭订阅文件加密，并迁移已有文件，返回处理的文件数
+#[tauri::command]
+pub fn migrate_profile_encryption(enable: bool) -> CmdResult<usize> {
+    let count = wrap_err!(feat::migrate_profile_encryption(enable))?;
+    handle::Handle::refresh_verge();
+    Ok(count)
+}
+
+/// 获取远程订阅的更新统计，用于标记过期或失效的订阅
+#[tauri::command]
+pub fn get_profiles_health() -> CmdResult<Vec<config::ProfileHealth>> {
+    Ok(Config::profiles().latest().profiles_health())
+}
+
+/// 保存订阅登录步骤的密码，密码为空时删除
+#[tauri::command]
+pub fn set_profile_auth_password(auth: PrfAuth, password: Option<String>) -> CmdResult {
+    let password = password.filter(|p| !p.is_empty());
+    wrap_err!(config::set_auth_password(&auth, password))
+}
+
+/// 获取当前订阅的按进程分流规则
+#[tauri::command]
+pub fn get_app_rules() -> CmdResult<Vec<feat::AppRule>> {
+    wrap_err!(feat::list_app_rules())
+}
+
+/// 添加按进程分流规则并热重载配置
+#[tauri::command]
+pub async fn add_app_rule(rule: feat::AppRule) -> CmdResult {
+    wrap_err!(feat::add_app_rule(rule).await)
+}
+
+/// 删除按进程分流规则并热重载配置
+#[tauri::command]
+pub async fn remove_app_rule(kind: String, value: String) -> CmdResult {
+    wrap_err!(feat::remove_app_rule(kind, value).await)
+}
+
+/// 获取正在运行的进程，用于选择要分流的应用
+#[tauri::command]
+pub async fn get_running_apps() -> CmdResult<Vec<feat::RunningApp>> {
+    wrap_err!(tokio::task::spawn_blocking(feat::running_apps).await)
+}
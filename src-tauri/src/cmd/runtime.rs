@@ -1,8 +1,8 @@
 use super::CmdResult;
-use crate::{config::*, wrap_err};
+use crate::{config::*, utils::dirs, wrap_err};
 use anyhow::Context;
 use serde_yaml::Mapping;
-use std::collections::HashMap;
+use std::{collections::HashMap, fs, path::PathBuf};
 
 /// 获取运行时配置
 #[tauri::command]
@@ -23,12 +23,41 @@ pub fn get_runtime_yaml() -> CmdResult<String> {
         ))
 }
 
+/// 导出交给内核的运行时配置
+/// 优先复制内核实际加载的文件，尚未生成时再序列化当前生效的配置
+#[tauri::command]
+pub fn export_runtime_config(path: String) -> CmdResult {
+    let target = PathBuf::from(path);
+    let run_path = wrap_err!(dirs::app_home_dir())?.join(RUNTIME_CONFIG);
+    if run_path.exists() {
+        wrap_err!(fs::copy(&run_path, &target))?;
+        return Ok(());
+    }
+
+    let yaml = {
+        let runtime = Config::runtime();
+        let runtime = runtime.data();
+        let config = runtime
+            .config
+            .as_ref()
+            .ok_or("runtime config is not generated yet")?;
+        wrap_err!(serde_yaml::to_string(config))?
+    };
+    wrap_err!(fs::write(&target, yaml))
+}
+
 /// 获取运行时存在的键
 #[tauri::command]
 pub fn get_runtime_exists() -> CmdResult<Vec<String>> {
     Ok(Config::runtime().latest().exists_keys.clone())
 }
 
+/// 获取每个顶层key最后由哪个merge模板修改
+#[tauri::command]
+pub fn get_runtime_merge_sources() -> CmdResult<HashMap<String, String>> {
+    Ok(Config::runtime().latest().merge_sources.clone())
+}
+
 /// 获取运行时日志
 #[tauri::command]
 pub fn get_runtime_logs() -> CmdResult<HashMap<String, Vec<(String, String)>>> {
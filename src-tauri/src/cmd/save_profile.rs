@@ -19,6 +19,7 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
     let (file_path, original_content, is_merge_file) = {
         let profiles = Config::profiles();
         let profiles_guard = profiles.latest();
+        wrap_err!(profiles_guard.check_unlocked(&index))?;
         let item = wrap_err!(profiles_guard.get_item(&index))?;
         // 确定是否为merge类型文件
         let is_merge = item.itype.as_ref().is_some_and(|t| t == "merge");
@@ -28,10 +29,21 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
         (profiles_dir.join(path), content, is_merge)
     };
 
-    // 保存新的配置文件
-    wrap_err!(fs::write(&file_path, file_data.clone().unwrap()))?;
+    // 保存新的配置文件，按设置加密
+    let file_data = file_data.unwrap_or_default();
+    wrap_err!(write_profile_file(&file_path, &file_data))?;
 
-    let file_path_str = file_path.to_string_lossy().to_string();
+    // 内核验证需要明文，只写到随机命名的临时文件中，验证结束即删除
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let check_file = wrap_err!(tempfile::Builder::new()
+        .suffix(&format!("-{file_name}"))
+        .tempfile())?;
+    wrap_err!(fs::write(check_file.path(), &file_data))?;
+
+    let file_path_str = check_file.path().to_string_lossy().to_string();
     logging!(
         info,
         Type::Config,
@@ -81,7 +93,7 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
                     error_msg
                 );
                 // 恢复原始配置文件
-                wrap_err!(fs::write(&file_path, original_content))?;
+                wrap_err!(write_profile_file(&file_path, &original_content))?;
                 // 发送合并文件专用错误通知
                 let result = (false, error_msg.clone());
                 crate::cmd::validate::handle_yaml_validation_notice(&result, "Merge config file");
@@ -96,7 +108,7 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
                     e
                 );
                 // 恢复原始配置文件
-                wrap_err!(fs::write(&file_path, original_content))?;
+                wrap_err!(write_profile_file(&file_path, &original_content))?;
                 return Err(e.to_string());
             }
         }
@@ -120,7 +132,7 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
                 error_msg
             );
             // 恢复原始配置文件
-            wrap_err!(fs::write(&file_path, original_content))?;
+            wrap_err!(write_profile_file(&file_path, &original_content))?;
 
             // 智能判断错误类型
             let is_script_error = file_path_str.ends_with(".js")
@@ -158,7 +170,7 @@ pub async fn save_profile_file(index: String, file_data: Option<String>) -> CmdR
                 e
             );
             // 恢复原始配置文件
-            wrap_err!(fs::write(&file_path, original_content))?;
+            wrap_err!(write_profile_file(&file_path, &original_content))?;
             Err(e.to_string())
         }
     }
@@ -26,7 +26,7 @@ pub async fn install_service() -> CmdResult {
 
 #[tauri::command]
 pub async fn uninstall_service() -> CmdResult {
-    execute_service_operation(service::uninstall_service(), "Uninstall").await
+    execute_service_operation(service::remove_service(), "Uninstall").await
 }
 
 #[tauri::command]
@@ -46,3 +46,9 @@ pub async fn is_service_available() -> CmdResult<bool> {
         .map(|_| true)
         .map_err(|e| e.to_string())
 }
+
+/// 服务的安装状态、握手与版本
+#[tauri::command]
+pub async fn get_service_status() -> CmdResult<service::ServiceStatus> {
+    Ok(service::service_status().await)
+}
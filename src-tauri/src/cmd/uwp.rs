@@ -1,4 +1,5 @@
 use super::CmdResult;
+use platform::UwpApp;
 
 /// Platform-specific implementation for UWP functionality
 #[cfg(windows)]
@@ -6,9 +7,34 @@ mod platform {
     use super::CmdResult;
     use crate::{core::win_uwp, wrap_err};
 
+    pub use win_uwp::UwpApp;
+
     pub async fn invoke_uwp_tool() -> CmdResult {
         wrap_err!(win_uwp::invoke_uwptools().await)
     }
+
+    pub async fn get_uwp_apps() -> CmdResult<Vec<UwpApp>> {
+        wrap_err!(tokio::task::spawn_blocking(win_uwp::list_apps)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|apps| apps))
+    }
+
+    pub async fn set_uwp_loopback(sids: Vec<String>, exempt: bool) -> CmdResult {
+        wrap_err!(
+            tokio::task::spawn_blocking(move || win_uwp::set_exempt(&sids, exempt))
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result)
+        )
+    }
+
+    pub async fn exempt_all_uwp_apps() -> CmdResult<usize> {
+        wrap_err!(tokio::task::spawn_blocking(win_uwp::exempt_all)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|count| count))
+    }
 }
 
 /// Stub implementation for non-Windows platforms
@@ -16,9 +42,24 @@ mod platform {
 mod platform {
     use super::CmdResult;
 
+    /// UWP apps only exist on Windows
+    pub type UwpApp = serde_json::Value;
+
     pub async fn invoke_uwp_tool() -> CmdResult {
         Ok(())
     }
+
+    pub async fn get_uwp_apps() -> CmdResult<Vec<UwpApp>> {
+        Ok(vec![])
+    }
+
+    pub async fn set_uwp_loopback(_sids: Vec<String>, _exempt: bool) -> CmdResult {
+        Ok(())
+    }
+
+    pub async fn exempt_all_uwp_apps() -> CmdResult<usize> {
+        Ok(0)
+    }
 }
 
 /// Command exposed to Tauri
@@ -26,3 +67,21 @@ mod platform {
 pub async fn invoke_uwp_tool() -> CmdResult {
     platform::invoke_uwp_tool().await
 }
+
+/// The installed UWP apps and their loopback exemption
+#[tauri::command]
+pub async fn get_uwp_apps() -> CmdResult<Vec<UwpApp>> {
+    platform::get_uwp_apps().await
+}
+
+/// Grant or revoke the loopback exemption of the given app container SIDs
+#[tauri::command]
+pub async fn set_uwp_loopback(sids: Vec<String>, exempt: bool) -> CmdResult {
+    platform::set_uwp_loopback(sids, exempt).await
+}
+
+/// Exempt every UWP app, returns how many were changed
+#[tauri::command]
+pub async fn exempt_all_uwp_apps() -> CmdResult<usize> {
+    platform::exempt_all_uwp_apps().await
+}
@@ -1,5 +1,6 @@
 use crate::utils::{dirs, help};
 use anyhow::Result;
+use parking_lot::{const_rwlock, RwLock};
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
 use std::{
@@ -7,6 +8,12 @@ use std::{
     str::FromStr,
 };
 
+/// 本次运行生成的 secret，未保持固定 secret 时替代 clash.yaml 中的值
+static SESSION_SECRET: RwLock<Option<String>> = const_rwlock(None);
+
+/// 轮换中的新 secret，内核加载成功前只写入生成的配置，内部客户端仍使用旧值
+static PENDING_SECRET: RwLock<Option<String>> = const_rwlock(None);
+
 #[derive(Default, Debug, Clone)]
 pub struct IClashTemp(pub Mapping);
 
@@ -82,13 +89,13 @@ impl IClashTemp {
         let mixed_port = Self::guard_mixed_port(&config);
         let socks_port = Self::guard_socks_port(&config);
         let port = Self::guard_port(&config);
-        
+
         // Only set external-controller if it doesn't exist or is invalid
         // Don't overwrite valid user-configured values
         if !config.contains_key("external-controller") {
             config.insert("external-controller".into(), "127.0.0.1:9097".into());
         }
-        
+
         #[cfg(not(target_os = "windows"))]
         config.insert("redir-port".into(), redir_port.into());
         #[cfg(target_os = "linux")]
@@ -147,6 +154,52 @@ impl IClashTemp {
         Self::guard_port(&self.0)
     }
 
+    /// 生成新的随机 secret，之后生成的配置使用它，`commit_secret` 后才替换本次运行的 secret
+    pub fn rotate_secret() -> Result<()> {
+        let mut bytes = [0u8; 32];
+        getrandom::fill(&mut bytes)?;
+        *PENDING_SECRET.write() = Some(hex::encode(bytes));
+        Ok(())
+    }
+
+    /// 内核加载新配置后，内部客户端切换到新的 secret
+    pub fn commit_secret() {
+        if let Some(secret) = PENDING_SECRET.write().take() {
+            *SESSION_SECRET.write() = Some(secret);
+        }
+    }
+
+    /// 内核没有加载新配置时丢弃新的 secret
+    pub fn discard_secret() {
+        PENDING_SECRET.write().take();
+    }
+
+    fn is_stable_secret() -> bool {
+        super::Config::verge()
+            .latest()
+            .enable_stable_secret
+            .unwrap_or(true)
+    }
+
+    /// 未保持固定 secret 时返回内部客户端使用的 secret
+    pub fn session_secret() -> Option<String> {
+        if Self::is_stable_secret() {
+            return None;
+        }
+        SESSION_SECRET.read().clone()
+    }
+
+    /// 未保持固定 secret 时返回写入生成配置的 secret，轮换中为新的值
+    pub fn config_secret() -> Option<String> {
+        if Self::is_stable_secret() {
+            return None;
+        }
+        PENDING_SECRET
+            .read()
+            .clone()
+            .or_else(|| SESSION_SECRET.read().clone())
+    }
+
     pub fn get_client_info(&self) -> ClashInfo {
         let config = &self.0;
 
@@ -161,6 +214,7 @@ impl IClashTemp {
                 Value::Number(val_num) => Some(val_num.to_string()),
                 _ => None,
             }),
+            ipc: None,
         }
     }
     #[cfg(not(target_os = "windows"))]
@@ -288,6 +342,40 @@ pub struct ClashInfo {
     pub server: String,
     /// clash secret
     pub secret: Option<String>,
+    /// unix socket or named pipe of the controller when it is used instead of tcp
+    pub ipc: Option<String>,
+}
+
+impl ClashInfo {
+    /// 未保持固定 secret 时使用本次运行生成的 secret
+    pub fn with_session_secret(mut self) -> Self {
+        if let Some(secret) = IClashTemp::session_secret() {
+            self.secret = Some(secret);
+        }
+        self
+    }
+
+    /// 启用后通过 unix socket 或命名管道连接控制接口
+    pub fn with_controller_ipc(mut self) -> Self {
+        if super::Config::verge()
+            .latest()
+            .enable_controller_ipc
+            .unwrap_or(false)
+        {
+            self.ipc = Some(dirs::controller_ipc().into());
+        }
+        self
+    }
+
+    /// 连接外部内核时使用其控制地址
+    pub fn with_external_core(mut self) -> Self {
+        if let Some((server, secret)) = super::Config::verge().latest().external_core() {
+            self.server = server;
+            self.secret = secret;
+            self.ipc = None;
+        }
+        self
+    }
 }
 
 #[test]
@@ -307,6 +395,7 @@ fn test_clash_info() {
             port: 7899,
             server: server.into(),
             secret: None,
+            ipc: None,
         }
     }
 
@@ -412,3 +501,27 @@ pub struct IClashFallbackFilter {
     pub ipcidr: Option<Vec<String>>,
     pub domain: Option<Vec<String>>,
 }
+
+#[test]
+fn test_rotate_secret() {
+    IClashTemp::rotate_secret().unwrap();
+    IClashTemp::commit_secret();
+    let first = SESSION_SECRET.read().clone().unwrap();
+    assert_eq!(first.len(), 64);
+    assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+
+    // 提交前内部客户端仍使用旧的 secret
+    IClashTemp::rotate_secret().unwrap();
+    let pending = PENDING_SECRET.read().clone().unwrap();
+    assert_ne!(pending, first);
+    assert_eq!(SESSION_SECRET.read().as_deref(), Some(first.as_str()));
+
+    IClashTemp::discard_secret();
+    assert!(PENDING_SECRET.read().is_none());
+    assert_eq!(SESSION_SECRET.read().as_deref(), Some(first.as_str()));
+
+    IClashTemp::rotate_secret().unwrap();
+    IClashTemp::commit_secret();
+    assert!(PENDING_SECRET.read().is_none());
+    assert_ne!(SESSION_SECRET.read().as_deref(), Some(first.as_str()));
+}
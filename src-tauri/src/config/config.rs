@@ -8,11 +8,13 @@ use crate::{
 };
 use anyhow::{anyhow, Result};
 use once_cell::sync::OnceCell;
+use serde_yaml::Mapping;
 use std::path::PathBuf;
 use tokio::time::{sleep, Duration};
 
 pub const RUNTIME_CONFIG: &str = "koala-clash.yaml";
 pub const CHECK_CONFIG: &str = "koala-clash-check.yaml";
+pub const SECONDARY_CONFIG: &str = "koala-clash-secondary.yaml";
 
 pub struct Config {
     clash_config: Draft<Box<IClashTemp>>,
@@ -145,14 +147,35 @@ impl Config {
         Ok(path)
     }
 
+    /// 写入第二个内核实例的配置，返回其工作目录和配置文件
+    /// 使用单独的目录，两个实例不能共用同一个缓存数据库
+    pub fn generate_secondary_file(config: &Mapping) -> Result<(PathBuf, PathBuf)> {
+        let home_dir = dirs::app_home_dir()?.join("secondary");
+        std::fs::create_dir_all(&home_dir)?;
+        let path = home_dir.join(SECONDARY_CONFIG);
+
+        help::save_yaml(&path, config, Some("# Generated by Koala Clash"))?;
+        Ok((home_dir, path))
+    }
+
+    /// 第二个内核实例的配置，由当前的运行时配置生成
+    pub fn secondary_config(mixed_port: u16) -> Result<Mapping> {
+        let interface_name = Config::verge().latest().secondary_interface_name.clone();
+        Config::runtime()
+            .latest()
+            .secondary_config(mixed_port, interface_name)
+            .ok_or(anyhow!("failed to get runtime config"))
+    }
+
     /// 生成订阅存好
     pub async fn generate() -> Result<()> {
-        let (config, exists_keys, logs) = enhance::enhance().await;
+        let (config, exists_keys, logs, merge_sources) = enhance::enhance().await;
 
         *Config::runtime().draft() = Box::new(IRuntime {
             config: Some(config),
             exists_keys,
             chain_logs: logs,
+            merge_sources,
         });
 
         Ok(())
@@ -1,3 +1,4 @@
+use super::Config;
 use crate::utils::dirs::get_encryption_key;
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -5,9 +6,13 @@ use aes_gcm::{
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fs, path::Path};
 
 const NONCE_LENGTH: usize = 12;
 
+/// First line of a profile file that is encrypted at rest
+const PROFILE_HEADER: &str = "#koala-clash-encrypted:v1\n";
+
 /// Encrypt data
 pub fn encrypt_data(data: &str) -> Result<String, Box<dyn std::error::Error>> {
     let encryption_key = get_encryption_key()?;
@@ -93,3 +98,47 @@ where
         Err(_) => Ok(T::default()),
     }
 }
+
+/// Whether the file content is an encrypted profile
+pub fn is_encrypted_profile(content: &str) -> bool {
+    content.starts_with(PROFILE_HEADER)
+}
+
+/// Encrypt profile content, the header keeps it recognizable on disk
+pub fn encrypt_profile(content: &str) -> anyhow::Result<String> {
+    let encrypted = encrypt_data(content).map_err(|e| anyhow::anyhow!("{e}"))?;
+    Ok(format!("{PROFILE_HEADER}{encrypted}"))
+}
+
+/// Decrypt profile content, plain content is returned as it is
+pub fn decrypt_profile(content: String) -> anyhow::Result<String> {
+    match content.strip_prefix(PROFILE_HEADER) {
+        Some(encrypted) => decrypt_data(encrypted.trim()).map_err(|e| anyhow::anyhow!("{e}")),
+        None => Ok(content),
+    }
+}
+
+/// Read a profile file, decrypting it when needed
+pub fn read_profile_file(path: &Path) -> anyhow::Result<String> {
+    decrypt_profile(fs::read_to_string(path)?)
+}
+
+/// Write a profile file, encrypted when `enable_profile_encryption` is on
+pub fn write_profile_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    let encrypt = Config::verge()
+        .latest()
+        .enable_profile_encryption
+        .unwrap_or(false);
+    if encrypt {
+        fs::write(path, encrypt_profile(content)?)?;
+    } else {
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Rewrite a profile file in the configured form, used after it was written in plain
+pub fn seal_profile_file(path: &Path) -> anyhow::Result<()> {
+    let content = read_profile_file(path)?;
+    write_profile_file(path, &content)
+}
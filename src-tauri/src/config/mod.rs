@@ -3,13 +3,18 @@ mod clash;
 mod config;
 mod draft;
 mod encrypt;
+mod prfauth;
+mod prfcomposite;
+mod prfdiff;
 mod prfitem;
+mod prfrevision;
 mod profiles;
 mod runtime;
 mod verge;
 
 pub use self::{
-    clash::*, config::*, draft::*, encrypt::*, prfitem::*, profiles::*, runtime::*, verge::*,
+    clash::*, config::*, draft::*, encrypt::*, prfauth::*, prfcomposite::*, prfdiff::*, prfitem::*,
+    prfrevision::*, profiles::*, runtime::*, verge::*,
 };
 
 pub const DEFAULT_PAC: &str = r#"function FindProxyForURL(url, host) {
@@ -0,0 +1,188 @@
+use super::{decrypt_data, encrypt_data};
+use crate::utils::{
+    dirs, help,
+    network::{NetworkManager, ProxyType},
+};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// passwords of the login steps, encrypted with the app key
+const CREDENTIALS_FILE: &str = "credentials.yaml";
+
+/// tokens obtained during this session, keyed by the credential key
+static TOKENS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Login step of a panel that only serves the config with a token
+/// The password is not part of the profile, it is kept in the credentials file
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfAuth {
+    /// endpoint that exchanges the credentials for a token
+    pub login_url: String,
+
+    pub username: Option<String>,
+
+    /// field of the login body holding the username
+    /// default is `username`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username_field: Option<String>,
+
+    /// dot separated path of the token in the login response, e.g. `data.auth_data`
+    /// default is `token`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_field: Option<String>,
+
+    /// header the token is sent in
+    /// default is `Authorization`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+
+    /// put before the token in the header, empty to send the token alone
+    /// default is `Bearer`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+}
+
+impl PrfAuth {
+    fn credential_key(&self) -> String {
+        format!(
+            "{}@{}",
+            self.username.as_deref().unwrap_or_default(),
+            self.login_url.trim()
+        )
+    }
+
+    /// Add the auth header to `headers`, logs in when no token is cached or `refresh` is set
+    pub async fn apply(
+        &self,
+        headers: &mut BTreeMap<String, String>,
+        proxy_type: ProxyType,
+        accept_invalid_certs: bool,
+        refresh: bool,
+    ) -> Result<()> {
+        let key = self.credential_key();
+        let cached = if refresh {
+            None
+        } else {
+            TOKENS.lock().get(&key).cloned()
+        };
+        let token = match cached {
+            Some(token) => token,
+            None => {
+                let token = self.login(proxy_type, accept_invalid_certs).await?;
+                TOKENS.lock().insert(key, token.clone());
+                token
+            }
+        };
+
+        let value = match self.scheme.as_deref().unwrap_or("Bearer").trim() {
+            "" => token,
+            scheme => format!("{scheme} {token}"),
+        };
+        headers.insert(
+            self.header
+                .clone()
+                .unwrap_or_else(|| "Authorization".into()),
+            value,
+        );
+        Ok(())
+    }
+
+    async fn login(&self, proxy_type: ProxyType, accept_invalid_certs: bool) -> Result<String> {
+        let password = get_password(&self.credential_key())?.unwrap_or_default();
+        let mut body = serde_json::Map::new();
+        body.insert(
+            self.username_field
+                .clone()
+                .unwrap_or_else(|| "username".into()),
+            self.username.clone().unwrap_or_default().into(),
+        );
+        body.insert("password".into(), password.into());
+
+        let resp = NetworkManager::global()
+            .post_json(
+                self.login_url.trim(),
+                proxy_type,
+                Some(20),
+                None,
+                accept_invalid_certs,
+                &serde_json::Value::Object(body),
+            )
+            .await
+            .map_err(|err| anyhow!(help::redact_urls(&err.to_string())))?;
+        let status = resp.status();
+        if !status.is_success() {
+            // 登录地址的查询参数可能带有令牌
+            bail!(
+                "login to {} failed with status {status}",
+                help::redact_urls(self.login_url.trim())
+            );
+        }
+
+        let resp: serde_json::Value = resp.json().await?;
+        let path = self.token_field.as_deref().unwrap_or("token");
+        extract_token(&resp, path)
+            .ok_or_else(|| anyhow!("no token at `{path}` in the login response"))
+    }
+}
+
+fn extract_token(value: &serde_json::Value, path: &str) -> Option<String> {
+    let token = path
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .try_fold(value, |value, part| value.get(part))?;
+    token
+        .as_str()
+        .map(|token| token.to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn read_credentials() -> Result<BTreeMap<String, String>> {
+    let path = dirs::app_home_dir()?.join(CREDENTIALS_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    help::read_yaml(&path)
+}
+
+fn get_password(key: &str) -> Result<Option<String>> {
+    match read_credentials()?.get(key) {
+        Some(encrypted) => decrypt_data(encrypted)
+            .map(Some)
+            .map_err(|err| anyhow!("failed to decrypt the password: {err}")),
+        None => Ok(None),
+    }
+}
+
+/// Store the password used by the login step, `None` removes it
+/// The cached token is dropped so the next update logs in again
+pub fn set_auth_password(auth: &PrfAuth, password: Option<String>) -> Result<()> {
+    let key = auth.credential_key();
+    let mut credentials = read_credentials()?;
+    match password {
+        Some(password) => {
+            let encrypted = encrypt_data(&password)
+                .map_err(|err| anyhow!("failed to encrypt the password: {err}"))?;
+            credentials.insert(key.clone(), encrypted);
+        }
+        None => {
+            credentials.remove(&key);
+        }
+    }
+    TOKENS.lock().remove(&key);
+
+    let path = dirs::app_home_dir()?.join(CREDENTIALS_FILE);
+    help::save_yaml(&path, &credentials, Some("# Koala Clash Credentials"))
+}
+
+#[test]
+fn test_extract_token() {
+    let resp: serde_json::Value =
+        serde_json::from_str(r#"{"token":"abc","data":{"auth_data":"xyz","empty":""}}"#).unwrap();
+    assert_eq!(extract_token(&resp, "token"), Some("abc".into()));
+    assert_eq!(extract_token(&resp, "data.auth_data"), Some("xyz".into()));
+    assert_eq!(extract_token(&resp, "data.empty"), None);
+    assert_eq!(extract_token(&resp, "data.missing"), None);
+}
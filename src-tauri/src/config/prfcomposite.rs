@@ -0,0 +1,131 @@
+use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::HashSet;
+
+/// Build the content of a composite profile from its sources
+///
+/// The first source is used as the base config. Proxies of all sources are
+/// concatenated, and a proxy whose name is already taken gets the source name
+/// appended. Every further source also gets a `select` group listing its
+/// proxies, which is added to the first proxy group of the base.
+pub fn build_composite(sources: &[(String, Mapping)]) -> Mapping {
+    let Some((_, base)) = sources.first() else {
+        return Mapping::new();
+    };
+
+    let mut config = base.clone();
+    let mut names = HashSet::new();
+    let mut proxies = Sequence::new();
+    let mut source_groups = Sequence::new();
+
+    for (index, (source_name, mapping)) in sources.iter().enumerate() {
+        let mut members = Sequence::new();
+        let Some(Value::Sequence(items)) = mapping.get("proxies") else {
+            continue;
+        };
+
+        for item in items {
+            let Some(proxy) = item.as_mapping() else {
+                continue;
+            };
+            let Some(name) = proxy.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+
+            let unique = unique_name(&mut names, name, source_name);
+            let mut proxy = proxy.clone();
+            proxy.insert("name".into(), unique.clone().into());
+            proxies.push(Value::Mapping(proxy));
+            members.push(unique.into());
+        }
+
+        if index > 0 && !members.is_empty() {
+            let group_name = unique_name(&mut names, source_name, "group");
+            let mut group = Mapping::new();
+            group.insert("name".into(), group_name.into());
+            group.insert("type".into(), "select".into());
+            group.insert("proxies".into(), Value::Sequence(members));
+            source_groups.push(Value::Mapping(group));
+        }
+    }
+
+    config.insert("proxies".into(), Value::Sequence(proxies));
+
+    if !source_groups.is_empty() {
+        let source_names: Sequence = source_groups
+            .iter()
+            .filter_map(|g| g.get("name").cloned())
+            .collect();
+
+        let mut groups = match config.get("proxy-groups") {
+            Some(Value::Sequence(groups)) => groups.clone(),
+            _ => Sequence::new(),
+        };
+        if let Some(Value::Mapping(first)) = groups.first_mut() {
+            match first.get_mut("proxies") {
+                Some(Value::Sequence(members)) => members.extend(source_names),
+                _ => {
+                    first.insert("proxies".into(), Value::Sequence(source_names));
+                }
+            }
+        }
+        groups.extend(source_groups);
+        config.insert("proxy-groups".into(), Value::Sequence(groups));
+    }
+
+    config
+}
+
+fn unique_name(names: &mut HashSet<String>, name: &str, suffix: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while names.contains(&candidate) {
+        candidate = if n == 1 {
+            format!("{name} ({suffix})")
+        } else {
+            format!("{name} ({suffix} {n})")
+        };
+        n += 1;
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+#[test]
+fn test_build_composite() {
+    let a: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "hk", type: "ss" }
+- { name: "jp", type: "ss" }
+proxy-groups:
+- { name: "PROXY", type: "select", proxies: ["hk", "jp"] }
+rules:
+- MATCH,PROXY
+"#,
+    )
+    .unwrap();
+    let b: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "hk", type: "vmess" }
+- { name: "us", type: "vmess" }
+"#,
+    )
+    .unwrap();
+
+    let config = build_composite(&[("A".into(), a), ("B".into(), b)]);
+
+    let names: Vec<&str> = config["proxies"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["hk", "jp", "hk (B)", "us"]);
+
+    let groups = config["proxy-groups"].as_sequence().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["proxies"].as_sequence().unwrap().len(), 3);
+    assert_eq!(groups[1]["name"].as_str(), Some("B"));
+    assert_eq!(config["rules"].as_sequence().unwrap().len(), 1);
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Structured difference between two profile contents
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProfileDiff {
+    pub added_proxies: Vec<String>,
+    pub removed_proxies: Vec<String>,
+    /// proxies with the same name but different settings
+    pub changed_proxies: Vec<String>,
+    pub added_groups: Vec<String>,
+    pub removed_groups: Vec<String>,
+    pub old_rules_count: usize,
+    pub new_rules_count: usize,
+}
+
+impl ProfileDiff {
+    /// compare two profile mappings, `old` is the content currently on disk
+    pub fn compute(old: &Mapping, new: &Mapping) -> Self {
+        let old_proxies = named_items(old, "proxies");
+        let new_proxies = named_items(new, "proxies");
+        let old_groups = named_items(old, "proxy-groups");
+        let new_groups = named_items(new, "proxy-groups");
+
+        Self {
+            added_proxies: missing_in(&new_proxies, &old_proxies),
+            removed_proxies: missing_in(&old_proxies, &new_proxies),
+            changed_proxies: changed_in(&old_proxies, &new_proxies),
+            added_groups: missing_in(&new_groups, &old_groups),
+            removed_groups: missing_in(&old_groups, &new_groups),
+            old_rules_count: rules_count(old),
+            new_rules_count: rules_count(new),
+        }
+    }
+
+    /// whether the two contents are equivalent on proxies, groups and rules count
+    pub fn is_empty(&self) -> bool {
+        self.added_proxies.is_empty()
+            && self.removed_proxies.is_empty()
+            && self.changed_proxies.is_empty()
+            && self.added_groups.is_empty()
+            && self.removed_groups.is_empty()
+            && self.old_rules_count == self.new_rules_count
+    }
+}
+
+/// Side-by-side comparison of two different profiles
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProfileComparison {
+    pub proxies_only_a: Vec<String>,
+    pub proxies_only_b: Vec<String>,
+    /// proxies present in both with different settings
+    pub changed_proxies: Vec<String>,
+    pub groups_only_a: Vec<String>,
+    pub groups_only_b: Vec<String>,
+    /// groups present in both with a different type or member list
+    pub changed_groups: Vec<String>,
+    /// rules of A missing in B, in the order of A
+    pub rules_only_a: Vec<String>,
+    /// rules of B missing in A, in the order of B
+    pub rules_only_b: Vec<String>,
+}
+
+impl ProfileComparison {
+    pub fn compute(a: &Mapping, b: &Mapping) -> Self {
+        let proxies_a = named_items(a, "proxies");
+        let proxies_b = named_items(b, "proxies");
+        let groups_a = named_items(a, "proxy-groups");
+        let groups_b = named_items(b, "proxy-groups");
+        let rules_a = rules(a);
+        let rules_b = rules(b);
+
+        Self {
+            proxies_only_a: missing_in(&proxies_a, &proxies_b),
+            proxies_only_b: missing_in(&proxies_b, &proxies_a),
+            changed_proxies: changed_in(&proxies_a, &proxies_b),
+            groups_only_a: missing_in(&groups_a, &groups_b),
+            groups_only_b: missing_in(&groups_b, &groups_a),
+            changed_groups: changed_in(&groups_a, &groups_b),
+            rules_only_a: rules_missing_in(&rules_a, &rules_b),
+            rules_only_b: rules_missing_in(&rules_b, &rules_a),
+        }
+    }
+}
+
+fn named_items<'a>(config: &'a Mapping, field: &str) -> BTreeMap<String, &'a Value> {
+    config
+        .get(field)
+        .and_then(Value::as_sequence)
+        .map(|seq| {
+            seq.iter()
+                .filter_map(|item| {
+                    let name = item.as_mapping()?.get("name")?.as_str()?;
+                    Some((name.to_string(), item))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn missing_in(from: &BTreeMap<String, &Value>, other: &BTreeMap<String, &Value>) -> Vec<String> {
+    let other: BTreeSet<&String> = other.keys().collect();
+    from.keys()
+        .filter(|k| !other.contains(k))
+        .cloned()
+        .collect()
+}
+
+fn changed_in(from: &BTreeMap<String, &Value>, other: &BTreeMap<String, &Value>) -> Vec<String> {
+    from.iter()
+        .filter(|(name, item)| other.get(*name).is_some_and(|o| o != *item))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+fn rules(config: &Mapping) -> Vec<String> {
+    config
+        .get("rules")
+        .and_then(Value::as_sequence)
+        .map(|seq| {
+            seq.iter()
+                .filter_map(Value::as_str)
+                .map(|rule| rule.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn rules_missing_in(from: &[String], other: &[String]) -> Vec<String> {
+    let other: BTreeSet<&String> = other.iter().collect();
+    from.iter()
+        .filter(|rule| !other.contains(rule))
+        .cloned()
+        .collect()
+}
+
+fn rules_count(config: &Mapping) -> usize {
+    config
+        .get("rules")
+        .and_then(Value::as_sequence)
+        .map_or(0, |seq| seq.len())
+}
+
+#[test]
+fn test_profile_diff() {
+    let old: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "a", type: "ss", port: 1 }
+- { name: "b", type: "ss", port: 2 }
+proxy-groups:
+- { name: "auto", type: "url-test", proxies: ["a", "b"] }
+rules:
+- MATCH,auto
+"#,
+    )
+    .unwrap();
+    let new: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "b", type: "ss", port: 3 }
+- { name: "c", type: "vmess", port: 4 }
+proxy-groups:
+- { name: "select", type: "select", proxies: ["b", "c"] }
+rules:
+- DOMAIN,example.com,select
+- MATCH,select
+"#,
+    )
+    .unwrap();
+
+    let diff = ProfileDiff::compute(&old, &new);
+    assert_eq!(diff.added_proxies, vec!["c"]);
+    assert_eq!(diff.removed_proxies, vec!["a"]);
+    assert_eq!(diff.changed_proxies, vec!["b"]);
+    assert_eq!(diff.added_groups, vec!["select"]);
+    assert_eq!(diff.removed_groups, vec!["auto"]);
+    assert_eq!((diff.old_rules_count, diff.new_rules_count), (1, 2));
+    assert!(!diff.is_empty());
+    assert!(ProfileDiff::compute(&old, &old).is_empty());
+}
+
+#[test]
+fn test_profile_comparison() {
+    let a: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "a", type: "ss", port: 1 }
+- { name: "b", type: "ss", port: 2 }
+proxy-groups:
+- { name: "auto", type: "url-test", proxies: ["a", "b"] }
+rules:
+- DOMAIN,example.com,auto
+- MATCH,auto
+"#,
+    )
+    .unwrap();
+    let b: Mapping = serde_yaml::from_str(
+        r#"
+proxies:
+- { name: "b", type: "ss", port: 2 }
+- { name: "c", type: "vmess", port: 4 }
+proxy-groups:
+- { name: "auto", type: "url-test", proxies: ["b", "c"] }
+rules:
+- DOMAIN,example.org,auto
+- MATCH,auto
+"#,
+    )
+    .unwrap();
+
+    let cmp = ProfileComparison::compute(&a, &b);
+    assert_eq!(cmp.proxies_only_a, vec!["a"]);
+    assert_eq!(cmp.proxies_only_b, vec!["c"]);
+    assert!(cmp.changed_proxies.is_empty());
+    assert_eq!(cmp.changed_groups, vec!["auto"]);
+    assert!(cmp.groups_only_a.is_empty() && cmp.groups_only_b.is_empty());
+    assert_eq!(cmp.rules_only_a, vec!["DOMAIN,example.com,auto"]);
+    assert_eq!(cmp.rules_only_b, vec!["DOMAIN,example.org,auto"]);
+}
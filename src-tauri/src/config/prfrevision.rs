@@ -0,0 +1,125 @@
+use super::{read_profile_file, seal_profile_file, write_profile_file, Config};
+use crate::utils::dirs;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// A saved revision of a profile file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProfileRevision {
+    /// revision id, the snapshot time in milliseconds
+    pub id: String,
+    /// snapshot time in seconds
+    pub created: i64,
+    /// file size in bytes
+    pub size: u64,
+}
+
+fn revisions_dir(uid: &str) -> Result<PathBuf> {
+    if uid.is_empty() || uid.contains(['/', '\\', '.']) {
+        bail!("invalid profile uid \"{uid}\"");
+    }
+    Ok(dirs::app_revisions_dir()?.join(uid))
+}
+
+fn revision_path(uid: &str, id: &str) -> Result<PathBuf> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        bail!("invalid revision id \"{id}\"");
+    }
+    Ok(revisions_dir(uid)?.join(format!("{id}.yaml")))
+}
+
+/// number of revisions kept for each profile
+fn revision_limit() -> usize {
+    Config::verge().latest().profile_revision_limit.unwrap_or(5)
+}
+
+/// Save `data` as the newest revision of the profile, then drop revisions over the limit
+pub fn snapshot_profile(uid: &str, data: &str) -> Result<()> {
+    let limit = revision_limit();
+    if limit == 0 || data.trim().is_empty() {
+        return Ok(());
+    }
+
+    let revisions = list_profile_revisions(uid)?;
+    if let Some(latest) = revisions.first() {
+        // skip identical consecutive snapshots
+        if read_profile_revision(uid, &latest.id).is_ok_and(|prev| prev == data) {
+            return Ok(());
+        }
+    }
+
+    let dir = revisions_dir(uid)?;
+    fs::create_dir_all(&dir).context("failed to create the revisions dir")?;
+    let id = chrono::Local::now().timestamp_millis().to_string();
+    // 与订阅文件一样按设置加密
+    write_profile_file(&revision_path(uid, &id)?, data)
+        .with_context(|| format!("failed to save revision \"{id}\""))?;
+
+    prune_profile_revisions(uid, limit)
+}
+
+/// List the revisions of a profile, newest first
+pub fn list_profile_revisions(uid: &str) -> Result<Vec<ProfileRevision>> {
+    let dir = revisions_dir(uid)?;
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut revisions = fs::read_dir(&dir)?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".yaml")?
+                .to_string();
+            let millis = id.parse::<i64>().ok()?;
+            let size = entry.metadata().ok()?.len();
+            Some(ProfileRevision {
+                id,
+                created: millis / 1000,
+                size,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    revisions.sort_by(|a, b| b.id.parse::<i64>().ok().cmp(&a.id.parse::<i64>().ok()));
+    Ok(revisions)
+}
+
+/// Read the content of a revision
+pub fn read_profile_revision(uid: &str, id: &str) -> Result<String> {
+    let path = revision_path(uid, id)?;
+    read_profile_file(&path).with_context(|| format!("failed to read revision \"{id}\""))
+}
+
+/// Rewrite the revisions of a profile in the configured form, returns how many were rewritten
+pub fn seal_profile_revisions(uid: &str) -> Result<usize> {
+    let mut count = 0;
+    for revision in list_profile_revisions(uid)? {
+        seal_profile_file(&revision_path(uid, &revision.id)?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Remove every revision of a profile
+pub fn remove_profile_revisions(uid: &str) -> Result<()> {
+    let dir = revisions_dir(uid)?;
+    if dir.exists() {
+        fs::remove_dir_all(dir).context("failed to remove the revisions dir")?;
+    }
+    Ok(())
+}
+
+fn prune_profile_revisions(uid: &str, keep: usize) -> Result<()> {
+    for revision in list_profile_revisions(uid)?.into_iter().skip(keep) {
+        let path = revision_path(uid, &revision.id)?;
+        if let Err(err) = fs::remove_file(&path) {
+            log::warn!(target: "app", "failed to remove revision {path:?}: {err}");
+        }
+    }
+    Ok(())
+}
@@ -1,9 +1,36 @@
-use super::{prfitem::PrfItem, PrfOption};
+use super::{
+    encrypt::{read_profile_file, write_profile_file},
+    prfitem::{PrfItem, PrfSelected},
+    prfrevision::{remove_profile_revisions, snapshot_profile},
+    PrfDns, PrfOption,
+};
 use crate::utils::{dirs, help};
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use serde_yaml::Mapping;
-use std::{collections::HashSet, fs, io::Write};
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+/// Update statistics of a remote profile
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileHealth {
+    pub uid: String,
+    pub name: Option<String>,
+    /// timestamp of the last successful update
+    pub last_update: Option<usize>,
+    /// consecutive failures since then
+    pub fail_count: u32,
+    pub last_failure: Option<usize>,
+    /// milliseconds the last successful update took
+    pub last_duration: Option<u64>,
+    /// first failure since the last successful update
+    pub stale_since: Option<usize>,
+    /// updates are failing, or not updated for more than twice the update interval
+    pub stale: bool,
+}
 
 /// Define the `profiles.yaml` schema
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -13,6 +40,24 @@ pub struct IProfiles {
 
     /// profile list
     pub items: Option<Vec<PrfItem>>,
+
+    /// profile groups (folders)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<PrfGroup>>,
+
+    /// uids of the recently used profiles, the latest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent: Option<Vec<String>>,
+}
+
+/// how many recently used profiles are remembered
+const RECENT_PROFILES_LIMIT: usize = 5;
+
+/// A named folder of profiles
+#[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PrfGroup {
+    pub uid: String,
+    pub name: String,
 }
 
 /// 清理结果
@@ -23,6 +68,26 @@ pub struct CleanupResult {
     pub failed_deletions: Vec<String>,
 }
 
+/// what `vacuum` does with the unused files it finds
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VacuumMode {
+    Report,
+    Delete,
+    Archive,
+}
+
+/// 清理未使用文件的报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VacuumReport {
+    /// files in the profiles folder no profile item points to
+    pub orphaned_files: Vec<String>,
+    /// uids of merge, script, rules, proxies and groups items no profile uses
+    pub dangling_items: Vec<String>,
+    /// folder the files were moved to in archive mode
+    pub archive_dir: Option<String>,
+}
+
 macro_rules! patch {
     ($lv: expr, $rv: expr, $key: tt) => {
         if ($rv.$key).is_some() {
@@ -81,12 +146,34 @@ impl IProfiles {
             let some_uid = Some(current);
             if items.iter().any(|e| e.uid == some_uid) {
                 self.current = some_uid;
+                self.record_recent();
             }
         }
 
         Ok(())
     }
 
+    /// Move the current profile to the front of the recently used ones
+    fn record_recent(&mut self) {
+        let Some(current) = self.current.clone() else {
+            return;
+        };
+        let recent = self.recent.get_or_insert_with(Vec::new);
+        recent.retain(|uid| *uid != current);
+        recent.insert(0, current);
+        recent.truncate(RECENT_PROFILES_LIMIT);
+    }
+
+    /// The recently used profiles that still exist, the latest first
+    pub fn recent_profiles(&self) -> Vec<String> {
+        self.recent
+            .iter()
+            .flatten()
+            .filter(|uid| self.get_item(uid).is_ok())
+            .cloned()
+            .collect()
+    }
+
     pub fn get_current(&self) -> Option<String> {
         self.current.clone()
     }
@@ -111,6 +198,14 @@ impl IProfiles {
         bail!("failed to get the profile item \"uid:{uid}\"");
     }
 
+    /// fail when the item is locked against changes
+    pub fn check_unlocked(&self, uid: &String) -> Result<()> {
+        if self.get_item(uid)?.is_locked() {
+            bail!("the profile \"uid:{uid}\" is locked");
+        }
+        Ok(())
+    }
+
     /// append new item
     /// if the file_data is some
     /// then should save the data to file
@@ -130,14 +225,16 @@ impl IProfiles {
             let file = item.file.clone().unwrap();
             let path = dirs::app_profiles_dir()?.join(&file);
 
-            fs::File::create(path)
-                .with_context(|| format!("failed to create file \"{file}\""))?
-                .write(file_data.as_bytes())
+            write_profile_file(&path, &file_data)
                 .with_context(|| format!("failed to write to file \"{file}\""))?;
         }
 
-        if item.itype == Some("remote".to_string()) || item.itype == Some("local".to_string()) {
+        if matches!(
+            item.itype.as_deref(),
+            Some("remote" | "local" | "composite")
+        ) {
             // Always switch current to the newly created remote/local profile
+            // 只有真正启用时才计入最近使用，调用方可能随后恢复原来的 current
             self.current = uid.clone();
         }
 
@@ -176,6 +273,26 @@ impl IProfiles {
         self.save_file()
     }
 
+    /// merge the selected proxies into the item, without saving the file
+    /// groups missing from `selected` keep their saved proxy
+    pub fn merge_selected(&mut self, uid: &str, selected: &[PrfSelected]) -> bool {
+        let Some(item) = self.items.as_mut().and_then(|items| {
+            items
+                .iter_mut()
+                .find(|each| each.uid.as_deref() == Some(uid))
+        }) else {
+            return false;
+        };
+        let saved = item.selected.get_or_insert_with(Vec::new);
+        for each in selected {
+            match saved.iter_mut().find(|s| s.name == each.name) {
+                Some(s) => s.now = each.now.clone(),
+                None => saved.push(each.clone()),
+            }
+        }
+        true
+    }
+
     /// update the item value
     pub fn patch_item(&mut self, uid: String, item: PrfItem) -> Result<()> {
         let mut items = self.items.take().unwrap_or_default();
@@ -191,6 +308,8 @@ impl IProfiles {
                 patch!(each, item, extra);
                 patch!(each, item, updated);
                 patch!(each, item, option);
+                patch!(each, item, locked);
+                patch!(each, item, tags);
 
                 self.items = Some(items);
                 return self.save_file();
@@ -225,6 +344,13 @@ impl IProfiles {
                     each.name = item.name;
                     each.url = item.url;
                     each.option = PrfOption::merge(each.option.clone(), item.option);
+                    each.etag = item.etag;
+                    each.last_modified = item.last_modified;
+                    each.last_duration = item.last_duration.or(each.last_duration);
+                    // a successful update resets the failure streak
+                    each.fail_count = None;
+                    each.last_failure = None;
+                    each.stale_since = None;
                     // save the file data
                     // move the field value after save
                     if let Some(file_data) = item.file_data.take() {
@@ -237,9 +363,27 @@ impl IProfiles {
 
                         let path = dirs::app_profiles_dir()?.join(&file);
 
-                        fs::File::create(path)
-                            .with_context(|| format!("failed to create file \"{file}\""))?
-                            .write(file_data.as_bytes())
+                        let proxies_only =
+                            each.option.as_ref().and_then(|o| o.update_mode.as_deref())
+                                == Some("proxies");
+
+                        // keep the previous content as a revision before overwriting it
+                        let mut file_data = file_data;
+                        if let Ok(previous) = read_profile_file(&path) {
+                            if let Err(err) = snapshot_profile(&uid, &previous) {
+                                log::warn!(target: "app", "failed to snapshot profile {uid}: {err}");
+                            }
+                            if proxies_only {
+                                match merge_proxy_sections(&previous, &file_data) {
+                                    Ok(merged) => file_data = merged,
+                                    Err(err) => {
+                                        log::warn!(target: "app", "failed to keep the local edits of {uid}, replacing the file: {err}");
+                                    }
+                                }
+                            }
+                        }
+
+                        write_profile_file(&path, &file_data)
                             .with_context(|| format!("failed to write to file \"{file}\""))?;
                     }
 
@@ -251,12 +395,223 @@ impl IProfiles {
         self.save_file()
     }
 
+    /// record an update that found the remote content unchanged
+    /// only the timestamps are refreshed, the file is left untouched
+    pub fn touch_item(&mut self, uid: &String, duration: Option<u64>) -> Result<()> {
+        let Some(item) = self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+        else {
+            bail!("failed to find the profile item \"uid:{uid}\"");
+        };
+
+        item.updated = Some(chrono::Local::now().timestamp() as usize);
+        item.fail_count = None;
+        item.last_failure = None;
+        item.stale_since = None;
+        item.last_duration = duration.or(item.last_duration);
+        self.save_file()
+    }
+
+    /// record a failed update of the item
+    /// returns the number of consecutive failures
+    pub fn record_update_failure(&mut self, uid: &String) -> Result<u32> {
+        let Some(item) = self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+        else {
+            bail!("failed to find the profile item \"uid:{uid}\"");
+        };
+
+        let now = chrono::Local::now().timestamp() as usize;
+        let count = item.fail_count.unwrap_or(0).saturating_add(1);
+        item.fail_count = Some(count);
+        item.last_failure = Some(now);
+        item.stale_since.get_or_insert(now);
+        self.save_file()?;
+        Ok(count)
+    }
+
+    /// update statistics of every remote profile
+    pub fn profiles_health(&self) -> Vec<ProfileHealth> {
+        let now = chrono::Local::now().timestamp() as usize;
+        self.get_items()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter(|item| item.itype.as_deref() == Some("remote"))
+                    .filter_map(|item| {
+                        let interval = item.option.as_ref().and_then(|o| o.update_interval);
+                        let overdue = match (item.updated, interval) {
+                            (Some(updated), Some(minutes)) if minutes > 0 => {
+                                now.saturating_sub(updated) > minutes as usize * 60 * 2
+                            }
+                            _ => false,
+                        };
+                        Some(ProfileHealth {
+                            uid: item.uid.clone()?,
+                            name: item.name.clone(),
+                            last_update: item.updated,
+                            fail_count: item.fail_count.unwrap_or(0),
+                            last_failure: item.last_failure,
+                            last_duration: item.last_duration,
+                            stale_since: item.stale_since,
+                            stale: overdue || item.stale_since.is_some(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// find an existing remote/local item with the same canonical url or the same content
+    pub fn find_duplicate(&self, url: Option<&str>, content: Option<&str>) -> Option<String> {
+        let url = url.map(canonical_url);
+        let hash = content.map(content_hash);
+        self.get_items()?
+            .iter()
+            .filter(|item| matches!(item.itype.as_deref(), Some("remote") | Some("local")))
+            .find(|item| {
+                let same_url = url.is_some()
+                    && item.url.as_deref().map(canonical_url).as_ref() == url.as_ref();
+                let same_content =
+                    hash.is_some() && item.read_file().ok().map(|data| content_hash(&data)) == hash;
+                same_url || same_content
+            })
+            .and_then(|item| item.uid.clone())
+    }
+
+    /// uids of the items that duplicate another one
+    /// in each set of duplicates the current item, or else the most recently updated one, is kept
+    /// locked items are never returned
+    pub fn duplicate_items(&self) -> Vec<String> {
+        let mut sets: HashMap<String, Vec<&PrfItem>> = HashMap::new();
+        for item in self.get_items().into_iter().flatten() {
+            let key = match (item.itype.as_deref(), item.url.as_deref()) {
+                (Some("remote"), Some(url)) => format!("url:{}", canonical_url(url)),
+                (Some("local"), _) => match item.read_file() {
+                    Ok(data) => format!("hash:{}", content_hash(&data)),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            sets.entry(key).or_default().push(item);
+        }
+
+        let mut duplicates = vec![];
+        for items in sets.into_values().filter(|items| items.len() > 1) {
+            let keep = items
+                .iter()
+                .find(|item| item.uid.is_some() && item.uid == self.current)
+                .or_else(|| items.iter().max_by_key(|item| item.updated.unwrap_or(0)))
+                .and_then(|item| item.uid.clone());
+            duplicates.extend(
+                items
+                    .into_iter()
+                    .filter(|item| !item.is_locked())
+                    .filter_map(|item| item.uid.clone())
+                    .filter(|uid| Some(uid) != keep.as_ref()),
+            );
+        }
+        duplicates
+    }
+
+    /// get the groups ref
+    pub fn get_groups(&self) -> &[PrfGroup] {
+        self.groups.as_deref().unwrap_or_default()
+    }
+
+    /// create a new group and return its uid
+    pub fn create_group(&mut self, name: String) -> Result<String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            bail!("the group name should not be empty");
+        }
+        if self.get_groups().iter().any(|g| g.name == name) {
+            bail!("the group \"{name}\" already exists");
+        }
+
+        let uid = help::get_uid("g");
+        self.groups.get_or_insert_with(Vec::new).push(PrfGroup {
+            uid: uid.clone(),
+            name,
+        });
+        self.save_file()?;
+        Ok(uid)
+    }
+
+    /// rename a group
+    pub fn rename_group(&mut self, uid: &str, name: String) -> Result<()> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            bail!("the group name should not be empty");
+        }
+        if self
+            .get_groups()
+            .iter()
+            .any(|g| g.name == name && g.uid != uid)
+        {
+            bail!("the group \"{name}\" already exists");
+        }
+
+        match self
+            .groups
+            .as_mut()
+            .and_then(|groups| groups.iter_mut().find(|g| g.uid == uid))
+        {
+            Some(group) => group.name = name,
+            None => bail!("failed to find the group \"uid:{uid}\""),
+        }
+        self.save_file()
+    }
+
+    /// delete a group, its profiles are moved out of the group but kept
+    pub fn delete_group(&mut self, uid: &str) -> Result<()> {
+        let groups = self.groups.get_or_insert_with(Vec::new);
+        let len = groups.len();
+        groups.retain(|g| g.uid != uid);
+        if groups.len() == len {
+            bail!("failed to find the group \"uid:{uid}\"");
+        }
+
+        for item in self.items.iter_mut().flatten() {
+            if item.group.as_deref() == Some(uid) {
+                item.group = None;
+            }
+        }
+        self.save_file()
+    }
+
+    /// move a profile into a group, `None` moves it out of any group
+    pub fn move_item_to_group(&mut self, uid: &String, group: Option<String>) -> Result<()> {
+        if let Some(group) = group.as_deref() {
+            if !self.get_groups().iter().any(|g| g.uid == group) {
+                bail!("failed to find the group \"uid:{group}\"");
+            }
+        }
+
+        match self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+        {
+            Some(item) => item.group = group,
+            None => bail!("failed to find the profile item \"uid:{uid}\""),
+        }
+        self.save_file()
+    }
+
     /// delete item
     /// if delete the current then return true
     pub fn delete_item(&mut self, uid: String) -> Result<bool> {
         let current = self.current.as_ref().unwrap_or(&uid);
         let current = current.clone();
         let item = self.get_item(&uid)?;
+        if item.is_locked() {
+            bail!("the profile \"uid:{uid}\" is locked");
+        }
         let merge_uid = item.option.as_ref().and_then(|e| e.merge.clone());
         let script_uid = item.option.as_ref().and_then(|e| e.script.clone());
         let rules_uid = item.option.as_ref().and_then(|e| e.rules.clone());
@@ -286,6 +641,7 @@ impl IProfiles {
                     }
                 });
             }
+            let _ = remove_profile_revisions(&uid);
         }
         // get the merge index
         for (i, _) in items.iter().enumerate() {
@@ -372,13 +728,17 @@ impl IProfiles {
                 });
             }
         }
+        if let Some(recent) = self.recent.as_mut() {
+            recent.retain(|recent_uid| *recent_uid != uid);
+        }
         // delete the original uid
         if current == uid {
             self.current = None;
             for item in items.iter() {
-                if item.itype == Some("remote".to_string())
-                    || item.itype == Some("local".to_string())
-                {
+                if matches!(
+                    item.itype.as_deref(),
+                    Some("remote" | "local" | "composite")
+                ) {
                     self.current = item.uid.clone();
                     break;
                 }
@@ -477,6 +837,85 @@ impl IProfiles {
         }
     }
 
+    /// the extra merge templates of the current profile, in application order
+    pub fn current_merges(&self) -> Vec<String> {
+        self.current
+            .as_ref()
+            .and_then(|current| self.get_item(current).ok())
+            .and_then(|item| item.option.as_ref()?.merges.clone())
+            .unwrap_or_default()
+    }
+
+    /// get the dns overrides of the current profile
+    pub fn current_dns(&self) -> Option<PrfDns> {
+        self.current
+            .as_ref()
+            .and_then(|current| self.get_item(current).ok())
+            .and_then(|item| item.option.as_ref()?.dns.clone())
+    }
+
+    /// replace the ordered merge template chain of a profile
+    pub fn set_merges(&mut self, uid: &String, merges: Vec<String>) -> Result<()> {
+        let mut chain: Vec<String> = vec![];
+        for merge in merges {
+            let item = self.get_item(&merge)?;
+            if item.itype.as_deref() != Some("merge") {
+                bail!("the profile \"uid:{merge}\" is not a merge template");
+            }
+            if !chain.contains(&merge) {
+                chain.push(merge);
+            }
+        }
+
+        let mut item = self.get_item(uid)?.clone();
+        let mut option = item.option.unwrap_or_default();
+        option.merges = if chain.is_empty() { None } else { Some(chain) };
+        item.option = Some(option);
+        self.patch_item(uid.clone(), item)
+    }
+
+    /// replace the tags of a profile, empty and repeated tags are dropped
+    pub fn set_item_tags(&mut self, uid: &String, tags: Vec<String>) -> Result<()> {
+        let mut cleaned: Vec<String> = vec![];
+        for tag in tags {
+            let tag = tag.trim().to_string();
+            if !tag.is_empty() && !cleaned.contains(&tag) {
+                cleaned.push(tag);
+            }
+        }
+
+        match self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+        {
+            Some(item) => item.tags = (!cleaned.is_empty()).then_some(cleaned),
+            None => bail!("failed to find the profile item \"uid:{uid}\""),
+        }
+        self.save_file()
+    }
+
+    /// set the sort index of a profile and reorder the list by it
+    pub fn set_item_sort_index(&mut self, uid: &String, sort_index: Option<i64>) -> Result<()> {
+        match self
+            .items
+            .as_mut()
+            .and_then(|items| items.iter_mut().find(|each| each.uid.as_ref() == Some(uid)))
+        {
+            Some(item) => item.sort_index = sort_index,
+            None => bail!("failed to find the profile item \"uid:{uid}\""),
+        }
+        self.sort_items();
+        self.save_file()
+    }
+
+    /// stable sort by sort index, profiles without one keep their order after the indexed ones
+    fn sort_items(&mut self) {
+        if let Some(items) = self.items.as_mut() {
+            items.sort_by_key(|item| (item.sort_index.is_none(), item.sort_index));
+        }
+    }
+
     /// 判断profile是否是current指向的
     pub fn is_current_profile_index(&self, index: String) -> bool {
         self.current == Some(index)
@@ -596,7 +1035,7 @@ impl IProfiles {
 
                 // 对于主 profile 类型（remote/local），还需要收集其关联的扩展文件
                 if let Some(itype) = &item.itype {
-                    if itype == "remote" || itype == "local" {
+                    if itype == "remote" || itype == "local" || itype == "composite" {
                         if let Some(option) = &item.option {
                             // 收集关联的扩展文件
                             if let Some(merge_uid) = &option.merge {
@@ -672,6 +1111,121 @@ impl IProfiles {
         })
     }
 
+    /// Find the files and chain items left behind by deleted profiles
+    /// In delete or archive mode the dangling items are also removed from the list
+    pub fn vacuum(&mut self, mode: VacuumMode) -> Result<VacuumReport> {
+        let profiles_dir = dirs::app_profiles_dir()?;
+        let mut report = VacuumReport::default();
+        if !profiles_dir.exists() {
+            return Ok(report);
+        }
+
+        let items = self.items.as_deref().unwrap_or_default();
+        let mut referenced = HashSet::new();
+        for option in items.iter().filter_map(|item| item.option.as_ref()) {
+            referenced.extend(
+                [
+                    &option.merge,
+                    &option.script,
+                    &option.rules,
+                    &option.proxies,
+                    &option.groups,
+                ]
+                .into_iter()
+                .flatten()
+                .cloned(),
+            );
+            referenced.extend(option.merges.iter().flatten().cloned());
+        }
+
+        let mut dangling_files = vec![];
+        for item in items {
+            let is_chunk = matches!(
+                item.itype.as_deref(),
+                Some("merge" | "script" | "rules" | "proxies" | "groups")
+            );
+            let Some(uid) = item.uid.as_ref() else {
+                continue;
+            };
+            // 全局的 Merge 和 Script 不属于任何订阅
+            if !is_chunk || referenced.contains(uid) || uid == "Merge" || uid == "Script" {
+                continue;
+            }
+            if item.is_locked() {
+                continue;
+            }
+            report.dangling_items.push(uid.clone());
+            dangling_files.extend(item.file.clone());
+        }
+
+        let active_files = self.get_all_active_files();
+        let protected_files = self.get_protected_global_files();
+        for entry in fs::read_dir(&profiles_dir)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let is_config = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| matches!(ext, "yaml" | "yml" | "js"));
+            if path.is_file()
+                && is_config
+                && !active_files.contains(file_name)
+                && !protected_files.contains(file_name)
+            {
+                report.orphaned_files.push(file_name.to_string());
+            }
+        }
+        report.orphaned_files.sort();
+
+        if mode == VacuumMode::Report {
+            return Ok(report);
+        }
+
+        let archive_dir = profiles_dir.join(format!(
+            "archive-{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        if mode == VacuumMode::Archive {
+            fs::create_dir_all(&archive_dir)?;
+            report.archive_dir = Some(archive_dir.to_string_lossy().to_string());
+        }
+        for file in report.orphaned_files.iter().chain(dangling_files.iter()) {
+            let path = profiles_dir.join(file);
+            if !path.exists() {
+                continue;
+            }
+            let result = match mode {
+                VacuumMode::Archive => fs::rename(&path, archive_dir.join(file)),
+                _ => fs::remove_file(&path),
+            };
+            if let Err(err) = result {
+                log::warn!(target: "app", "failed to vacuum {file}: {err}");
+            }
+        }
+
+        if !report.dangling_items.is_empty() {
+            if let Some(items) = self.items.as_mut() {
+                items.retain(|item| {
+                    !item
+                        .uid
+                        .as_ref()
+                        .is_some_and(|uid| report.dangling_items.contains(uid))
+                });
+            }
+            self.save_file()?;
+        }
+
+        log::info!(
+            target: "app",
+            "Vacuumed profiles: {} orphaned files, {} dangling items",
+            report.orphaned_files.len(),
+            report.dangling_items.len()
+        );
+        Ok(report)
+    }
+
     pub fn auto_cleanup(&self) -> Result<()> {
         match self.cleanup_orphaned_files() {
             Ok(result) => {
@@ -691,3 +1245,207 @@ impl IProfiles {
         }
     }
 }
+
+/// normalize a subscription url so that trivially different forms compare equal
+/// the fragment is dropped, the query is sorted and a trailing slash is removed
+pub fn canonical_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+
+    let mut pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if pairs.is_empty() {
+        parsed.set_query(None);
+    } else {
+        pairs.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let path = parsed.path().trim_end_matches('/').to_string();
+    parsed.set_path(&path);
+    parsed.to_string()
+}
+
+/// sections taken from the subscription when only the proxies are updated
+const PROXY_SECTIONS: &[&str] = &["proxies", "proxy-providers"];
+
+/// Replace the proxy sections of the local content with the ones of the fetched content
+/// Group members that were proxies and are gone from the subscription are dropped, groups that
+/// lost members get the proxies new in the subscription, so renamed nodes stay in their groups
+pub fn merge_proxy_sections(local: &str, remote: &str) -> Result<String> {
+    let mut local = serde_yaml::from_str::<Mapping>(local).context("invalid local content")?;
+    let remote = serde_yaml::from_str::<Mapping>(remote).context("invalid fetched content")?;
+
+    let proxy_names = |config: &Mapping| -> Vec<String> {
+        config
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .map(|seq| {
+                seq.iter()
+                    .filter_map(|p| p.get("name")?.as_str().map(|n| n.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let old_names = proxy_names(&local);
+    let new_names = proxy_names(&remote);
+    let removed: Vec<&String> = old_names
+        .iter()
+        .filter(|name| !new_names.contains(name))
+        .collect();
+    let added: Vec<&String> = new_names
+        .iter()
+        .filter(|name| !old_names.contains(name))
+        .collect();
+
+    for key in PROXY_SECTIONS {
+        match remote.get(*key) {
+            Some(value) => local.insert((*key).into(), value.clone()),
+            None => local.remove(*key),
+        };
+    }
+
+    if let Some(groups) = local
+        .get_mut("proxy-groups")
+        .and_then(Value::as_sequence_mut)
+    {
+        for group in groups.iter_mut().filter_map(Value::as_mapping_mut) {
+            let uses_provider = group.contains_key("use");
+            let Some(members) = group.get_mut("proxies").and_then(Value::as_sequence_mut) else {
+                continue;
+            };
+            let before = members.len();
+            members.retain(|m| !m.as_str().is_some_and(|m| removed.iter().any(|r| *r == m)));
+            if members.len() < before {
+                for name in &added {
+                    if !members.iter().any(|m| m.as_str() == Some(name.as_str())) {
+                        members.push(name.as_str().into());
+                    }
+                }
+            }
+            // an empty group is rejected by the core
+            if members.is_empty() && !uses_provider {
+                members.push("DIRECT".into());
+            }
+        }
+    }
+
+    Ok(serde_yaml::to_string(&local)?)
+}
+
+fn content_hash(data: &str) -> String {
+    let data = data.trim_start_matches('\u{feff}').trim();
+    hex::encode(Sha256::digest(data.as_bytes()))
+}
+
+#[test]
+fn test_canonical_url() {
+    assert_eq!(
+        canonical_url("HTTPS://Example.com:443/sub/?token=1&b=2#name"),
+        canonical_url("https://example.com/sub?b=2&token=1")
+    );
+    assert_ne!(
+        canonical_url("https://example.com/sub?token=1"),
+        canonical_url("https://example.com/sub?token=2")
+    );
+}
+
+#[test]
+fn test_merge_proxy_sections() {
+    let local = r#"
+proxies:
+- { name: a, type: ss }
+- { name: b, type: ss }
+proxy-groups:
+- { name: mine, type: select, proxies: [a, b, DIRECT] }
+- { name: only-a, type: select, proxies: [a] }
+rules:
+- DOMAIN,example.com,mine
+"#;
+    let remote = r#"
+proxies:
+- { name: b, type: ss }
+- { name: c, type: ss }
+proxy-groups:
+- { name: theirs, type: select, proxies: [b, c] }
+rules:
+- MATCH,theirs
+"#;
+
+    let merged: Mapping =
+        serde_yaml::from_str(&merge_proxy_sections(local, remote).unwrap()).unwrap();
+    let remote: Mapping = serde_yaml::from_str(remote).unwrap();
+    assert_eq!(merged.get("proxies"), remote.get("proxies"));
+
+    let expected: Mapping = serde_yaml::from_str(
+        r#"
+proxy-groups:
+- { name: mine, type: select, proxies: [b, DIRECT, c] }
+- { name: only-a, type: select, proxies: [c] }
+rules:
+- DOMAIN,example.com,mine
+"#,
+    )
+    .unwrap();
+    assert_eq!(merged.get("proxy-groups"), expected.get("proxy-groups"));
+    assert_eq!(merged.get("rules"), expected.get("rules"));
+
+    // 订阅重命名了所有节点，分组换成新的节点而不是 DIRECT
+    let local = r#"
+proxies:
+- { name: HK 01, type: ss }
+- { name: JP 01, type: ss }
+proxy-groups:
+- { name: auto, type: url-test, proxies: [HK 01, JP 01] }
+- { name: direct-only, type: select, proxies: [DIRECT] }
+"#;
+    let remote = r#"
+proxies:
+- { name: 🇭🇰 HK 01, type: ss }
+- { name: 🇯🇵 JP 01, type: ss }
+"#;
+    let merged: Mapping =
+        serde_yaml::from_str(&merge_proxy_sections(local, remote).unwrap()).unwrap();
+    let expected: Mapping = serde_yaml::from_str(
+        r#"
+proxy-groups:
+- { name: auto, type: url-test, proxies: [🇭🇰 HK 01, 🇯🇵 JP 01] }
+- { name: direct-only, type: select, proxies: [DIRECT] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(merged.get("proxy-groups"), expected.get("proxy-groups"));
+}
+
+#[test]
+fn test_recent_profiles() {
+    let item = |uid: &str| PrfItem {
+        uid: Some(uid.into()),
+        ..PrfItem::default()
+    };
+    let mut profiles = IProfiles {
+        items: Some((0..7).map(|i| item(&format!("p{i}"))).collect()),
+        ..IProfiles::default()
+    };
+    for uid in ["p0", "p1", "p2", "p1", "p3", "p4", "p5", "p6"] {
+        profiles
+            .patch_config(IProfiles {
+                current: Some(uid.into()),
+                ..IProfiles::default()
+            })
+            .unwrap();
+    }
+    assert_eq!(profiles.recent_profiles(), ["p6", "p5", "p4", "p3", "p1"]);
+
+    // 已删除的 profile 不再显示
+    profiles
+        .items
+        .as_mut()
+        .unwrap()
+        .retain(|item| item.uid.as_deref() != Some("p5"));
+    assert_eq!(profiles.recent_profiles(), ["p6", "p4", "p3", "p1"]);
+}
@@ -2,6 +2,20 @@ use crate::enhance::field::use_keys;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
+
+/// 第二个内核实例不监听这些端口和控制接口，避免与主实例冲突
+const SECONDARY_SKIP_KEYS: [&str; 9] = [
+    "port",
+    "socks-port",
+    "redir-port",
+    "tproxy-port",
+    "external-controller",
+    "external-controller-tls",
+    "external-controller-unix",
+    "external-controller-pipe",
+    "external-ui",
+];
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct IRuntime {
     pub config: Option<Mapping>,
@@ -9,6 +23,9 @@ pub struct IRuntime {
     // 这些keys不一定都生效
     pub exists_keys: Vec<String>,
     pub chain_logs: HashMap<String, Vec<(String, String)>>,
+    // 每个顶层key最后由哪个merge模板修改
+    #[serde(default)]
+    pub merge_sources: HashMap<String, String>,
 }
 
 impl IRuntime {
@@ -16,16 +33,22 @@ impl IRuntime {
         Self::default()
     }
 
-    // 这里只更改 allow-lan | ipv6 | log-level | tun
+    // 这里只更改 allow-lan | bind-address | ipv6 | log-level | tun
     pub fn patch_config(&mut self, patch: Mapping) {
         if let Some(config) = self.config.as_mut() {
-            ["allow-lan", "ipv6", "log-level", "unified-delay"]
-                .into_iter()
-                .for_each(|key| {
-                    if let Some(value) = patch.get(key).to_owned() {
-                        config.insert(key.into(), value.clone());
-                    }
-                });
+            [
+                "allow-lan",
+                "bind-address",
+                "ipv6",
+                "log-level",
+                "unified-delay",
+            ]
+            .into_iter()
+            .for_each(|key| {
+                if let Some(value) = patch.get(key).to_owned() {
+                    config.insert(key.into(), value.clone());
+                }
+            });
 
             let patch_tun = patch.get("tun");
             if patch_tun.is_some() {
@@ -46,4 +69,64 @@ impl IRuntime {
             }
         }
     }
+
+    /// 第二个内核实例的配置：只保留混合端口，关闭 TUN 和 DNS 监听
+    pub fn secondary_config(
+        &self,
+        mixed_port: u16,
+        interface_name: Option<String>,
+    ) -> Option<Mapping> {
+        let mut config = self.config.clone()?;
+        SECONDARY_SKIP_KEYS.into_iter().for_each(|key| {
+            config.remove(key);
+        });
+        config.insert("mixed-port".into(), mixed_port.into());
+
+        let mut tun = Mapping::new();
+        tun.insert("enable".into(), false.into());
+        config.insert("tun".into(), Value::from(tun));
+
+        if let Some(Value::Mapping(dns)) = config.get_mut("dns") {
+            dns.remove("listen");
+        }
+        if let Some(name) = interface_name.filter(|name| !name.is_empty()) {
+            config.insert("interface-name".into(), name.into());
+        }
+        Some(config)
+    }
+}
+
+#[test]
+fn test_secondary_config() {
+    let config: Mapping = serde_yaml::from_str(
+        r#"
+mixed-port: 7897
+socks-port: 7898
+external-controller: 127.0.0.1:9097
+tun:
+  enable: true
+  stack: mixed
+dns:
+  enable: true
+  listen: 0.0.0.0:53
+proxies: []
+"#,
+    )
+    .unwrap();
+    let runtime = IRuntime {
+        config: Some(config),
+        ..IRuntime::new()
+    };
+
+    let secondary = runtime.secondary_config(7899, Some("eth0".into())).unwrap();
+    assert_eq!(secondary.get("mixed-port"), Some(&Value::from(7899)));
+    assert!(secondary.get("socks-port").is_none());
+    assert!(secondary.get("external-controller").is_none());
+    assert_eq!(secondary["tun"]["enable"], Value::from(false));
+    assert!(secondary["dns"].get("listen").is_none());
+    assert_eq!(secondary["dns"]["enable"], Value::from(true));
+    assert_eq!(secondary.get("interface-name"), Some(&Value::from("eth0")));
+    assert!(secondary.get("proxies").is_some());
+
+    assert!(IRuntime::new().secondary_config(7899, None).is_none());
 }
@@ -23,6 +23,9 @@ pub struct IVerge {
     /// tray click event
     pub tray_event: Option<String>,
 
+    /// 托盘双击的操作，仅 Windows
+    pub tray_double_click_event: Option<String>,
+
     /// copy env type
     pub env_type: Option<String>,
 
@@ -43,10 +46,13 @@ pub struct IVerge {
     /// common tray icon
     pub common_tray_icon: Option<bool>,
 
-    /// tray icon
-    #[cfg(target_os = "macos")]
+    /// tray icon, monochrome | colorful
     pub tray_icon: Option<String>,
 
+    /// macOS menu bar rendering of the tray icon, auto | template | colored
+    /// auto: template for the built-in monochrome icons, custom icons keep their colors
+    pub tray_icon_render: Option<String>,
+
     /// menu icon
     pub menu_icon: Option<String>,
 
@@ -56,6 +62,12 @@ pub struct IVerge {
     /// tun tray icon
     pub tun_tray_icon: Option<bool>,
 
+    /// global mode tray icon
+    pub global_tray_icon: Option<bool>,
+
+    /// direct mode tray icon
+    pub direct_tray_icon: Option<bool>,
+
     /// clash tun mode
     pub enable_tun_mode: Option<bool>,
 
@@ -81,9 +93,27 @@ pub struct IVerge {
     /// always use default bypass
     pub use_default_bypass: Option<bool>,
 
+    /// keep link-local, CGNAT and multicast ranges out of the system proxy and TUN
+    pub bypass_reserved_ranges: Option<bool>,
+
     /// set system proxy bypass
     pub system_proxy_bypass: Option<String>,
 
+    /// bundled bypass lists added to the system proxy bypass,
+    /// `china_mainland`, `russia` or `intranet`
+    pub system_proxy_bypass_presets: Option<Vec<String>>,
+
+    /// macOS network services the system proxy is set on,
+    /// empty for the one carrying the default route
+    pub system_proxy_services: Option<Vec<String>>,
+
+    /// also set the WinHTTP proxy on Windows, needs administrator rights
+    pub enable_winhttp_proxy: Option<bool>,
+
+    /// Wi-Fi names (SSID) or interface names the system proxy is only set on,
+    /// empty for every network
+    pub sysproxy_networks: Option<Vec<String>>,
+
     /// proxy guard duration
     pub proxy_guard_duration: Option<u64>,
 
@@ -93,9 +123,31 @@ pub struct IVerge {
     /// pac script content
     pub pac_file_content: Option<String>,
 
+    /// generate the pac script from the active rules and the bypass list
+    /// instead of using `pac_file_content`
+    pub pac_from_rules: Option<bool>,
+
     /// proxy host address
     pub proxy_host: Option<String>,
 
+    /// 混合端口等监听的认证用户名，与密码都设置时启用 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub proxy_auth_username: Option<String>,
+
+    /// 监听的认证密码 (加密存储)
+    #[serde(
+        serialize_with = "serialize_encrypted",
+        deserialize_with = "deserialize_encrypted",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub proxy_auth_password: Option<String>,
+
     /// theme setting
     pub theme_setting: Option<IVergeTheme>,
 
@@ -197,6 +249,15 @@ pub struct IVerge {
 
     pub enable_tray_speed: Option<bool>,
 
+    /// 托盘网速的刷新间隔，单位秒
+    pub tray_speed_interval: Option<u64>,
+
+    /// 托盘菜单中的自定义项
+    pub tray_custom_items: Option<Vec<IVergeTrayItem>>,
+
+    /// 在托盘菜单中显示代理组及节点延迟
+    pub enable_tray_proxies: Option<bool>,
+
     pub enable_tray_icon: Option<bool>,
 
     /// 自动进入轻量模式
@@ -210,6 +271,140 @@ pub struct IVerge {
 
     /// 服务状态跟踪
     pub service_state: Option<crate::core::service::ServiceState>,
+
+    /// 订阅下载失败时的最大尝试次数
+    pub profile_retry_attempts: Option<u32>,
+
+    /// 订阅重试的初始退避时间（毫秒），每次重试翻倍
+    pub profile_retry_backoff_ms: Option<u64>,
+
+    /// 订阅重试退避的随机抖动上限（毫秒）
+    pub profile_retry_jitter_ms: Option<u64>,
+
+    /// 每个订阅保留的历史版本数量，0 表示不保留
+    pub profile_revision_limit: Option<usize>,
+
+    /// 本地订阅文件被外部修改时自动重新加载
+    pub enable_local_profile_watch: Option<bool>,
+
+    /// 监视的文件夹，新出现的 Clash 配置自动导入为本地订阅
+    pub profile_import_dirs: Option<Vec<String>>,
+
+    /// 订阅文件在磁盘上加密保存
+    pub enable_profile_encryption: Option<bool>,
+
+    /// 断网恢复或唤醒后自动更新当前订阅
+    pub enable_network_change_refresh: Option<bool>,
+
+    /// 按流量计费的网络下暂停定时更新订阅和 GeoData，恢复后补更新
+    pub skip_updates_on_metered: Option<bool>,
+
+    /// 检测到认证页面（Wi-Fi 登录页）时的处理，off 不检测，ask 提示，auto 自动暂停系统代理与 TUN
+    pub captive_portal_mode: Option<String>,
+
+    /// 订阅更新完成后 POST 结果的 Webhook 地址
+    pub profile_update_webhook: Option<String>,
+
+    /// 订阅更新成功（通过 Clash 代理回退）时是否通知
+    pub profile_notice_success: Option<bool>,
+
+    /// 订阅更新重试时是否通知
+    pub profile_notice_retry: Option<bool>,
+
+    /// 订阅更新失败时是否通知
+    pub profile_notice_failure: Option<bool>,
+
+    /// 订阅通知的免打扰时段，例如 22:00-07:00
+    pub profile_notice_quiet_hours: Option<String>,
+
+    /// 所有通知的免打扰时段，例如 22:00-07:00，期间的通知只记入历史
+    pub notice_quiet_hours: Option<String>,
+
+    /// 内核内存占用超过该值时提醒，单位 MB
+    pub core_memory_warning: Option<u64>,
+
+    /// 内核内存占用上限，单位 MB，0 表示不限制
+    pub core_memory_limit: Option<u64>,
+
+    /// 连续多少次采样超过上限后处理
+    pub core_memory_limit_samples: Option<u32>,
+
+    /// 超过上限后的处理方式，flush 关闭所有连接并清空 DNS 缓存，restart 在空闲时重启内核
+    pub core_memory_limit_action: Option<String>,
+
+    /// 保持 clash.yaml 中固定的控制接口 secret，关闭时每次启动随机生成，未设置时保持固定
+    pub enable_stable_secret: Option<bool>,
+
+    /// 连接外部运行的内核，不再启动和停止内核进程
+    pub enable_external_core: Option<bool>,
+
+    /// 外部内核的 external-controller 地址
+    pub external_controller: Option<String>,
+
+    /// 外部内核的 secret
+    pub external_secret: Option<String>,
+
+    /// 内核启动后等待控制接口就绪的时间，单位秒
+    pub core_startup_timeout: Option<u64>,
+
+    /// 内核健康检查间隔，单位秒，0 为关闭
+    pub core_health_interval: Option<u64>,
+
+    /// 健康检查请求的接口
+    pub core_health_endpoint: Option<String>,
+
+    /// 连续失败多少次后认为内核不健康
+    pub core_health_failures: Option<u32>,
+
+    /// 内核无响应或退出时暂时关闭系统代理，内核恢复后重新开启
+    pub core_down_disable_sysproxy: Option<bool>,
+
+    /// 内核无响应或退出时关闭 TUN 模式，不会自动重新开启
+    pub core_down_disable_tun: Option<bool>,
+
+    /// TUN 模式下 IPv6 的处理方式
+    /// off: 不处理; route: 由 TUN 接管 IPv6; disable: 关闭物理网卡的 IPv6，退出时恢复
+    pub tun_ipv6_mode: Option<String>,
+
+    /// 启动时端口被其他程序占用则自动改用下一个空闲端口，关闭时只提示占用的进程
+    pub enable_port_auto_resolve: Option<bool>,
+
+    /// 允许局域网连接时只放行这些地址或网段，为空时沿用订阅的设置或只放行本机所在的网段
+    pub lan_allowed_ips: Option<Vec<String>>,
+
+    /// 允许局域网连接时拒绝这些地址或网段，优先于放行列表
+    pub lan_disallowed_ips: Option<Vec<String>>,
+
+    /// 通过 unix socket 或命名管道连接内核的控制接口
+    pub enable_controller_ipc: Option<bool>,
+
+    /// 启用上一项时关闭 TCP 控制接口，外部面板将无法连接
+    pub disable_tcp_controller: Option<bool>,
+
+    /// 以受限权限运行内核，只保留 TUN 所需的权限
+    /// 仅在 Linux 上由应用以 sudo 或 pkexec 启动内核时生效，通过服务运行的内核不受限制
+    pub enable_core_sandbox: Option<bool>,
+
+    /// 同时运行第二个内核实例，只提供混合端口，不启用 TUN 和系统代理
+    pub enable_secondary_core: Option<bool>,
+
+    /// 第二个内核实例的混合端口
+    pub secondary_mixed_port: Option<u16>,
+
+    /// 第二个内核实例的出站网卡，绑定物理网卡可以绕过主实例的 TUN
+    pub secondary_interface_name: Option<String>,
+
+    /// 启动内核时追加的命令行参数
+    pub core_extra_args: Option<Vec<String>>,
+
+    /// 启动内核时追加的环境变量，格式为 KEY=VALUE
+    pub core_extra_env: Option<Vec<String>>,
+
+    /// GeoIP/GeoSite 数据库的自动更新间隔，单位小时，0 表示不自动更新
+    pub geodata_update_interval: Option<u64>,
+
+    /// GeoIP/GeoSite 数据库的下载源，按顺序尝试，为空时使用默认的 GitHub 和 jsDelivr
+    pub geodata_mirrors: Option<Vec<IVergeGeoMirror>>,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -220,6 +415,25 @@ pub struct IVergeTestItem {
     pub url: Option<String>,
 }
 
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct IVergeGeoMirror {
+    pub name: Option<String>,
+    /// 下载地址，其中的 {file} 会替换为文件名，否则把文件名追加在末尾
+    pub url: Option<String>,
+    /// auto | core | direct | system 或代理地址，默认先经过内核再直连
+    pub proxy: Option<String>,
+    pub enable: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct IVergeTrayItem {
+    pub label: Option<String>,
+    /// url | command | script
+    pub action: Option<String>,
+    /// 链接、内部命令名或脚本目录中的文件名
+    pub target: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct IVergeTheme {
     pub primary_color: Option<String>,
@@ -276,7 +490,12 @@ impl IVerge {
 
         // 修正后保存配置
         if needs_fix {
-            logging!(info, Type::Config, true, "Saving fixed configuration file...");
+            logging!(
+                info,
+                Type::Config,
+                true,
+                "Saving fixed configuration file..."
+            );
             help::save_yaml(&config_path, &config, Some("# Koala Clash Config"))?;
             logging!(
                 info,
@@ -318,6 +537,47 @@ impl IVerge {
         Ok(())
     }
 
+    /// 启用外部内核时返回其控制地址和 secret
+    pub fn external_core(&self) -> Option<(String, Option<String>)> {
+        if !self.enable_external_core.unwrap_or(false) {
+            return None;
+        }
+        let controller = self.external_controller.as_deref()?.trim();
+        let controller = controller
+            .trim_start_matches("http://")
+            .trim_end_matches('/');
+        if controller.is_empty() {
+            return None;
+        }
+        let secret = self.external_secret.clone().filter(|s| !s.is_empty());
+        Some((controller.to_string(), secret))
+    }
+
+    /// 监听的认证用户名和密码，两者都设置时才启用
+    pub fn proxy_auth(&self) -> Option<(String, String)> {
+        let username = self.proxy_auth_username.as_deref().unwrap_or_default();
+        let password = self.proxy_auth_password.as_deref().unwrap_or_default();
+        if username.is_empty() || password.is_empty() {
+            return None;
+        }
+        Some((username.to_string(), password.to_string()))
+    }
+
+    /// 代理地址中的 `user:pass@`，未启用认证时为空
+    pub fn proxy_userinfo(&self) -> String {
+        use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+        self.proxy_auth()
+            .map(|(username, password)| {
+                format!(
+                    "{}:{}@",
+                    utf8_percent_encode(&username, NON_ALPHANUMERIC),
+                    utf8_percent_encode(&password, NON_ALPHANUMERIC)
+                )
+            })
+            .unwrap_or_default()
+    }
+
     pub fn get_valid_clash_core(&self) -> String {
         self.clash_core
             .clone()
@@ -363,16 +623,21 @@ impl IVerge {
             enable_group_icon: Some(true),
             #[cfg(target_os = "macos")]
             tray_icon: Some("monochrome".into()),
+            #[cfg(not(target_os = "macos"))]
+            tray_icon: Some("colorful".into()),
             menu_icon: Some("monochrome".into()),
             common_tray_icon: Some(false),
             sysproxy_tray_icon: Some(false),
             tun_tray_icon: Some(false),
+            global_tray_icon: Some(false),
+            direct_tray_icon: Some(false),
             enable_auto_launch: Some(false),
             enable_silent_start: Some(false),
             enable_hover_jump_navigator: Some(true),
             enable_system_proxy: Some(false),
             proxy_auto_config: Some(false),
             pac_file_content: Some(DEFAULT_PAC.into()),
+            pac_from_rules: Some(false),
             proxy_host: Some("127.0.0.1".into()),
             enable_random_port: Some(false),
             #[cfg(not(target_os = "windows"))]
@@ -390,6 +655,7 @@ impl IVerge {
             verge_http_enabled: Some(false),
             enable_proxy_guard: Some(false),
             use_default_bypass: Some(true),
+            bypass_reserved_ranges: Some(true),
             proxy_guard_duration: Some(30),
             auto_close_connection: Some(true),
             auto_check_update: Some(true),
@@ -399,6 +665,8 @@ impl IVerge {
             webdav_username: None,
             webdav_password: None,
             enable_tray_speed: Some(false),
+            tray_speed_interval: Some(1),
+            enable_tray_proxies: Some(false),
             enable_tray_icon: Some(true),
             enable_global_hotkey: Some(true),
             enable_auto_light_weight_mode: Some(false),
@@ -408,6 +676,36 @@ impl IVerge {
             primary_action: Some("tun-mode".into()),
             home_cards: None,
             service_state: None,
+            profile_retry_attempts: Some(3),
+            profile_retry_backoff_ms: Some(1000),
+            profile_retry_jitter_ms: Some(500),
+            profile_revision_limit: Some(5),
+            enable_local_profile_watch: Some(true),
+            enable_network_change_refresh: Some(true),
+            captive_portal_mode: Some("ask".into()),
+            profile_notice_success: Some(true),
+            profile_notice_retry: Some(true),
+            profile_notice_failure: Some(true),
+            core_memory_warning: Some(1024),
+            core_memory_limit: Some(0),
+            core_memory_limit_samples: Some(5),
+            core_memory_limit_action: Some("flush".into()),
+            enable_stable_secret: Some(false),
+            enable_external_core: Some(false),
+            core_startup_timeout: Some(10),
+            core_health_interval: Some(30),
+            core_health_endpoint: Some("/version".into()),
+            core_health_failures: Some(3),
+            core_down_disable_sysproxy: Some(true),
+            core_down_disable_tun: Some(false),
+            tun_ipv6_mode: Some("off".into()),
+            enable_port_auto_resolve: Some(true),
+            enable_controller_ipc: Some(false),
+            disable_tcp_controller: Some(false),
+            enable_core_sandbox: Some(false),
+            enable_secondary_core: Some(false),
+            secondary_mixed_port: Some(7898),
+            geodata_update_interval: Some(0),
             ..Self::default()
         }
     }
@@ -432,18 +730,21 @@ impl IVerge {
         patch!(language);
         patch!(theme_mode);
         patch!(tray_event);
+        patch!(tray_double_click_event);
         patch!(env_type);
         patch!(start_page);
         patch!(startup_script);
         patch!(traffic_graph);
         patch!(enable_memory_usage);
         patch!(enable_group_icon);
-        #[cfg(target_os = "macos")]
         patch!(tray_icon);
+        patch!(tray_icon_render);
         patch!(menu_icon);
         patch!(common_tray_icon);
         patch!(sysproxy_tray_icon);
         patch!(tun_tray_icon);
+        patch!(global_tray_icon);
+        patch!(direct_tray_icon);
 
         patch!(enable_tun_mode);
         patch!(enable_auto_launch);
@@ -466,11 +767,19 @@ impl IVerge {
         patch!(enable_system_proxy);
         patch!(enable_proxy_guard);
         patch!(use_default_bypass);
+        patch!(bypass_reserved_ranges);
         patch!(system_proxy_bypass);
+        patch!(system_proxy_bypass_presets);
+        patch!(system_proxy_services);
+        patch!(enable_winhttp_proxy);
+        patch!(sysproxy_networks);
         patch!(proxy_guard_duration);
         patch!(proxy_auto_config);
         patch!(pac_file_content);
+        patch!(pac_from_rules);
         patch!(proxy_host);
+        patch!(proxy_auth_username);
+        patch!(proxy_auth_password);
         patch!(theme_setting);
         patch!(web_ui_list);
         patch!(clash_core);
@@ -490,6 +799,9 @@ impl IVerge {
         patch!(webdav_username);
         patch!(webdav_password);
         patch!(enable_tray_speed);
+        patch!(tray_speed_interval);
+        patch!(tray_custom_items);
+        patch!(enable_tray_proxies);
         patch!(enable_tray_icon);
         patch!(enable_auto_light_weight_mode);
         patch!(auto_light_weight_minutes);
@@ -498,6 +810,50 @@ impl IVerge {
         patch!(primary_action);
         patch!(home_cards);
         patch!(service_state);
+        patch!(profile_retry_attempts);
+        patch!(profile_retry_backoff_ms);
+        patch!(profile_retry_jitter_ms);
+        patch!(profile_revision_limit);
+        patch!(enable_local_profile_watch);
+        patch!(profile_import_dirs);
+        patch!(enable_profile_encryption);
+        patch!(enable_network_change_refresh);
+        patch!(skip_updates_on_metered);
+        patch!(captive_portal_mode);
+        patch!(profile_update_webhook);
+        patch!(profile_notice_success);
+        patch!(profile_notice_retry);
+        patch!(profile_notice_failure);
+        patch!(profile_notice_quiet_hours);
+        patch!(notice_quiet_hours);
+        patch!(core_memory_warning);
+        patch!(core_memory_limit);
+        patch!(core_memory_limit_samples);
+        patch!(core_memory_limit_action);
+        patch!(enable_stable_secret);
+        patch!(enable_external_core);
+        patch!(external_controller);
+        patch!(external_secret);
+        patch!(core_startup_timeout);
+        patch!(core_health_interval);
+        patch!(core_health_endpoint);
+        patch!(core_health_failures);
+        patch!(core_down_disable_sysproxy);
+        patch!(core_down_disable_tun);
+        patch!(tun_ipv6_mode);
+        patch!(enable_port_auto_resolve);
+        patch!(lan_allowed_ips);
+        patch!(lan_disallowed_ips);
+        patch!(enable_controller_ipc);
+        patch!(disable_tcp_controller);
+        patch!(enable_core_sandbox);
+        patch!(enable_secondary_core);
+        patch!(secondary_mixed_port);
+        patch!(secondary_interface_name);
+        patch!(core_extra_args);
+        patch!(core_extra_env);
+        patch!(geodata_update_interval);
+        patch!(geodata_mirrors);
     }
 
     /// 在初始化前尝试拿到单例端口的值
@@ -533,6 +889,7 @@ pub struct IVergeResponse {
     pub language: Option<String>,
     pub theme_mode: Option<String>,
     pub tray_event: Option<String>,
+    pub tray_double_click_event: Option<String>,
     pub env_type: Option<String>,
     pub start_page: Option<String>,
     pub startup_script: Option<String>,
@@ -540,11 +897,13 @@ pub struct IVergeResponse {
     pub enable_memory_usage: Option<bool>,
     pub enable_group_icon: Option<bool>,
     pub common_tray_icon: Option<bool>,
-    #[cfg(target_os = "macos")]
     pub tray_icon: Option<String>,
+    pub tray_icon_render: Option<String>,
     pub menu_icon: Option<String>,
     pub sysproxy_tray_icon: Option<bool>,
     pub tun_tray_icon: Option<bool>,
+    pub global_tray_icon: Option<bool>,
+    pub direct_tray_icon: Option<bool>,
     pub enable_tun_mode: Option<bool>,
     pub enable_auto_launch: Option<bool>,
     pub enable_silent_start: Option<bool>,
@@ -552,11 +911,20 @@ pub struct IVergeResponse {
     pub enable_proxy_guard: Option<bool>,
     pub enable_global_hotkey: Option<bool>,
     pub use_default_bypass: Option<bool>,
+    pub bypass_reserved_ranges: Option<bool>,
     pub system_proxy_bypass: Option<String>,
+    pub system_proxy_bypass_presets: Option<Vec<String>>,
+    pub system_proxy_services: Option<Vec<String>>,
+    pub enable_winhttp_proxy: Option<bool>,
+    pub sysproxy_networks: Option<Vec<String>>,
     pub proxy_guard_duration: Option<u64>,
     pub proxy_auto_config: Option<bool>,
     pub pac_file_content: Option<String>,
+    pub pac_from_rules: Option<bool>,
     pub proxy_host: Option<String>,
+    pub proxy_auth_username: Option<String>,
+    /// 密码不返回给前端，只告知是否已设置
+    pub proxy_auth_password_set: Option<bool>,
     pub theme_setting: Option<IVergeTheme>,
     pub web_ui_list: Option<Vec<String>>,
     pub clash_core: Option<String>,
@@ -587,6 +955,9 @@ pub struct IVergeResponse {
     pub webdav_username: Option<String>,
     pub webdav_password: Option<String>,
     pub enable_tray_speed: Option<bool>,
+    pub tray_speed_interval: Option<u64>,
+    pub tray_custom_items: Option<Vec<IVergeTrayItem>>,
+    pub enable_tray_proxies: Option<bool>,
     pub enable_tray_icon: Option<bool>,
     pub enable_auto_light_weight_mode: Option<bool>,
     pub auto_light_weight_minutes: Option<u64>,
@@ -596,6 +967,50 @@ pub struct IVergeResponse {
     pub home_cards: Option<serde_json::Value>,
     pub enable_hover_jump_navigator: Option<bool>,
     pub service_state: Option<crate::core::service::ServiceState>,
+    pub profile_retry_attempts: Option<u32>,
+    pub profile_retry_backoff_ms: Option<u64>,
+    pub profile_retry_jitter_ms: Option<u64>,
+    pub profile_revision_limit: Option<usize>,
+    pub enable_local_profile_watch: Option<bool>,
+    pub profile_import_dirs: Option<Vec<String>>,
+    pub enable_profile_encryption: Option<bool>,
+    pub enable_network_change_refresh: Option<bool>,
+    pub skip_updates_on_metered: Option<bool>,
+    pub captive_portal_mode: Option<String>,
+    pub profile_update_webhook: Option<String>,
+    pub profile_notice_success: Option<bool>,
+    pub profile_notice_retry: Option<bool>,
+    pub profile_notice_failure: Option<bool>,
+    pub profile_notice_quiet_hours: Option<String>,
+    pub notice_quiet_hours: Option<String>,
+    pub core_memory_warning: Option<u64>,
+    pub core_memory_limit: Option<u64>,
+    pub core_memory_limit_samples: Option<u32>,
+    pub core_memory_limit_action: Option<String>,
+    pub enable_stable_secret: Option<bool>,
+    pub enable_external_core: Option<bool>,
+    pub external_controller: Option<String>,
+    pub external_secret: Option<String>,
+    pub core_startup_timeout: Option<u64>,
+    pub core_health_interval: Option<u64>,
+    pub core_health_endpoint: Option<String>,
+    pub core_health_failures: Option<u32>,
+    pub core_down_disable_sysproxy: Option<bool>,
+    pub core_down_disable_tun: Option<bool>,
+    pub tun_ipv6_mode: Option<String>,
+    pub enable_port_auto_resolve: Option<bool>,
+    pub lan_allowed_ips: Option<Vec<String>>,
+    pub lan_disallowed_ips: Option<Vec<String>>,
+    pub enable_controller_ipc: Option<bool>,
+    pub disable_tcp_controller: Option<bool>,
+    pub enable_core_sandbox: Option<bool>,
+    pub enable_secondary_core: Option<bool>,
+    pub secondary_mixed_port: Option<u16>,
+    pub secondary_interface_name: Option<String>,
+    pub core_extra_args: Option<Vec<String>>,
+    pub core_extra_env: Option<Vec<String>>,
+    pub geodata_update_interval: Option<u64>,
+    pub geodata_mirrors: Option<Vec<IVergeGeoMirror>>,
 }
 
 impl From<IVerge> for IVergeResponse {
@@ -607,6 +1022,7 @@ impl From<IVerge> for IVergeResponse {
             language: verge.language,
             theme_mode: verge.theme_mode,
             tray_event: verge.tray_event,
+            tray_double_click_event: verge.tray_double_click_event,
             env_type: verge.env_type,
             start_page: verge.start_page,
             startup_script: verge.startup_script,
@@ -614,11 +1030,13 @@ impl From<IVerge> for IVergeResponse {
             enable_memory_usage: verge.enable_memory_usage,
             enable_group_icon: verge.enable_group_icon,
             common_tray_icon: verge.common_tray_icon,
-            #[cfg(target_os = "macos")]
             tray_icon: verge.tray_icon,
+            tray_icon_render: verge.tray_icon_render,
             menu_icon: verge.menu_icon,
             sysproxy_tray_icon: verge.sysproxy_tray_icon,
             tun_tray_icon: verge.tun_tray_icon,
+            global_tray_icon: verge.global_tray_icon,
+            direct_tray_icon: verge.direct_tray_icon,
             enable_tun_mode: verge.enable_tun_mode,
             enable_auto_launch: verge.enable_auto_launch,
             enable_silent_start: verge.enable_silent_start,
@@ -626,11 +1044,24 @@ impl From<IVerge> for IVergeResponse {
             enable_proxy_guard: verge.enable_proxy_guard,
             enable_global_hotkey: verge.enable_global_hotkey,
             use_default_bypass: verge.use_default_bypass,
+            bypass_reserved_ranges: verge.bypass_reserved_ranges,
             system_proxy_bypass: verge.system_proxy_bypass,
+            system_proxy_bypass_presets: verge.system_proxy_bypass_presets,
+            system_proxy_services: verge.system_proxy_services,
+            enable_winhttp_proxy: verge.enable_winhttp_proxy,
+            sysproxy_networks: verge.sysproxy_networks,
             proxy_guard_duration: verge.proxy_guard_duration,
             proxy_auto_config: verge.proxy_auto_config,
             pac_file_content: verge.pac_file_content,
+            pac_from_rules: verge.pac_from_rules,
             proxy_host: verge.proxy_host,
+            proxy_auth_username: verge.proxy_auth_username,
+            proxy_auth_password_set: Some(
+                verge
+                    .proxy_auth_password
+                    .as_deref()
+                    .is_some_and(|password| !password.is_empty()),
+            ),
             theme_setting: verge.theme_setting,
             web_ui_list: verge.web_ui_list,
             clash_core: Some(valid_clash_core),
@@ -661,6 +1092,9 @@ impl From<IVerge> for IVergeResponse {
             webdav_username: verge.webdav_username,
             webdav_password: verge.webdav_password,
             enable_tray_speed: verge.enable_tray_speed,
+            tray_speed_interval: verge.tray_speed_interval,
+            tray_custom_items: verge.tray_custom_items,
+            enable_tray_proxies: verge.enable_tray_proxies,
             enable_tray_icon: verge.enable_tray_icon,
             enable_auto_light_weight_mode: verge.enable_auto_light_weight_mode,
             auto_light_weight_minutes: verge.auto_light_weight_minutes,
@@ -670,6 +1104,50 @@ impl From<IVerge> for IVergeResponse {
             home_cards: verge.home_cards,
             enable_hover_jump_navigator: verge.enable_hover_jump_navigator,
             service_state: verge.service_state,
+            profile_retry_attempts: verge.profile_retry_attempts,
+            profile_retry_backoff_ms: verge.profile_retry_backoff_ms,
+            profile_retry_jitter_ms: verge.profile_retry_jitter_ms,
+            profile_revision_limit: verge.profile_revision_limit,
+            enable_local_profile_watch: verge.enable_local_profile_watch,
+            profile_import_dirs: verge.profile_import_dirs,
+            enable_profile_encryption: verge.enable_profile_encryption,
+            enable_network_change_refresh: verge.enable_network_change_refresh,
+            skip_updates_on_metered: verge.skip_updates_on_metered,
+            captive_portal_mode: verge.captive_portal_mode,
+            profile_update_webhook: verge.profile_update_webhook,
+            profile_notice_success: verge.profile_notice_success,
+            profile_notice_retry: verge.profile_notice_retry,
+            profile_notice_failure: verge.profile_notice_failure,
+            profile_notice_quiet_hours: verge.profile_notice_quiet_hours,
+            notice_quiet_hours: verge.notice_quiet_hours,
+            core_memory_warning: verge.core_memory_warning,
+            core_memory_limit: verge.core_memory_limit,
+            core_memory_limit_samples: verge.core_memory_limit_samples,
+            core_memory_limit_action: verge.core_memory_limit_action,
+            enable_stable_secret: verge.enable_stable_secret,
+            enable_external_core: verge.enable_external_core,
+            external_controller: verge.external_controller,
+            external_secret: verge.external_secret,
+            core_startup_timeout: verge.core_startup_timeout,
+            core_health_interval: verge.core_health_interval,
+            core_health_endpoint: verge.core_health_endpoint,
+            core_health_failures: verge.core_health_failures,
+            core_down_disable_sysproxy: verge.core_down_disable_sysproxy,
+            core_down_disable_tun: verge.core_down_disable_tun,
+            tun_ipv6_mode: verge.tun_ipv6_mode,
+            enable_port_auto_resolve: verge.enable_port_auto_resolve,
+            lan_allowed_ips: verge.lan_allowed_ips,
+            lan_disallowed_ips: verge.lan_disallowed_ips,
+            enable_controller_ipc: verge.enable_controller_ipc,
+            disable_tcp_controller: verge.disable_tcp_controller,
+            enable_core_sandbox: verge.enable_core_sandbox,
+            enable_secondary_core: verge.enable_secondary_core,
+            secondary_mixed_port: verge.secondary_mixed_port,
+            secondary_interface_name: verge.secondary_interface_name,
+            core_extra_args: verge.core_extra_args,
+            core_extra_env: verge.core_extra_env,
+            geodata_update_interval: verge.geodata_update_interval,
+            geodata_mirrors: verge.geodata_mirrors,
         }
     }
 }
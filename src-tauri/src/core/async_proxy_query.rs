@@ -2,6 +2,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tokio::time::{timeout, Duration};
 
+#[cfg(target_os = "linux")]
+use crate::core::linux_proxy::{self, Desktop, ProxyMode};
 #[cfg(target_os = "linux")]
 use anyhow::anyhow;
 #[cfg(not(target_os = "windows"))]
@@ -216,6 +218,14 @@ impl AsyncProxyQuery {
             }
         }
 
+        // KDE 的设置保存在 kioslaverc
+        if Desktop::current() == Desktop::Kde {
+            return Ok(match linux_proxy::read(Desktop::Kde) {
+                Some(ProxyMode::Auto { url }) => AsyncAutoproxy { enable: true, url },
+                _ => AsyncAutoproxy::default(),
+            });
+        }
+
         // 尝试使用 gsettings 获取 GNOME 代理设置
         let output = Command::new("gsettings")
             .args(["get", "org.gnome.system.proxy", "mode"])
@@ -441,6 +451,21 @@ impl AsyncProxyQuery {
             }
         }
 
+        // KDE 的设置保存在 kioslaverc
+        if Desktop::current() == Desktop::Kde {
+            return Ok(match linux_proxy::read(Desktop::Kde) {
+                Some(ProxyMode::Manual {
+                    host, port, bypass, ..
+                }) => AsyncSysproxy {
+                    enable: true,
+                    host,
+                    port,
+                    bypass: bypass.join(","),
+                },
+                _ => AsyncSysproxy::default(),
+            });
+        }
+
         // 尝试使用 gsettings 获取 GNOME 代理设置
         let mode_output = Command::new("gsettings")
             .args(["get", "org.gnome.system.proxy", "mode"])
@@ -0,0 +1,412 @@
+use crate::{config::Config, logging, utils::logging::Type};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+};
+
+#[cfg(target_os = "windows")]
+static DEFAULT_BYPASS: &str = "localhost;127.*;192.168.*;10.*;172.16.*;172.17.*;172.18.*;172.19.*;172.20.*;172.21.*;172.22.*;172.23.*;172.24.*;172.25.*;172.26.*;172.27.*;172.28.*;172.29.*;172.30.*;172.31.*;<local>";
+#[cfg(target_os = "linux")]
+static DEFAULT_BYPASS: &str =
+    "localhost,127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,::1";
+#[cfg(target_os = "macos")]
+static DEFAULT_BYPASS: &str =
+    "127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,localhost,*.local,*.crashlytics.com,<local>";
+
+/// Link-local, CGNAT and multicast ranges
+///
+/// Kept out of both the system proxy and the TUN routes unless turned off,
+/// proxying them breaks printers, Tailscale and local discovery.
+pub const RESERVED_RANGES: &[&str] = &[
+    "169.254.0.0/16",
+    "100.64.0.0/10",
+    "224.0.0.0/4",
+    "fe80::/10",
+];
+
+/// A bundled list of hosts that are usually reached directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BypassPreset {
+    ChinaMainland,
+    Russia,
+    Intranet,
+}
+
+impl BypassPreset {
+    pub const ALL: [BypassPreset; 3] = [Self::ChinaMainland, Self::Russia, Self::Intranet];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::ChinaMainland => "china_mainland",
+            Self::Russia => "russia",
+            Self::Intranet => "intranet",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.id() == id)
+    }
+
+    pub fn entries(&self) -> &'static [&'static str] {
+        match self {
+            Self::ChinaMainland => &[
+                "*.cn",
+                "*.baidu.com",
+                "*.qq.com",
+                "*.weixin.qq.com",
+                "*.taobao.com",
+                "*.tmall.com",
+                "*.alipay.com",
+                "*.aliyun.com",
+                "*.alicdn.com",
+                "*.jd.com",
+                "*.163.com",
+                "*.126.net",
+                "*.bilibili.com",
+                "*.hdslb.com",
+                "*.zhihu.com",
+                "*.weibo.com",
+                "*.douyin.com",
+            ],
+            Self::Russia => &[
+                "*.ru",
+                "*.su",
+                "*.xn--p1ai",
+                "*.yandex.net",
+                "*.yandex.com",
+                "*.yastatic.net",
+                "*.vk.com",
+                "*.vk.ru",
+                "*.userapi.com",
+                "*.vkuser.net",
+                "*.mycdn.me",
+            ],
+            Self::Intranet => &[
+                "<local>",
+                "*.local",
+                "*.lan",
+                "*.internal",
+                "*.intranet",
+                "*.corp",
+                "*.home.arpa",
+                "10.0.0.0/8",
+                "172.16.0.0/12",
+                "192.168.0.0/16",
+                "100.64.0.0/10",
+                "169.254.0.0/16",
+                "fc00::/7",
+            ],
+        }
+    }
+}
+
+/// The operating system the list is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// `;` separated, no CIDR, `<local>` for plain host names
+    Windows,
+    /// a list of hosts, wildcards and CIDRs
+    MacOS,
+    /// `no_proxy` style, suffixes start with a dot
+    Linux,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::MacOS
+        } else {
+            Self::Linux
+        }
+    }
+}
+
+/// One entry of the bypass list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BypassEntry {
+    /// `<local>`, host names without a dot
+    Local,
+    Host(String),
+    /// `*.example.com`, `192.168.*`
+    Wildcard(String),
+    Ip(IpAddr),
+    Cidr(IpAddr, u8),
+}
+
+impl BypassEntry {
+    pub fn parse(entry: &str) -> Result<Self> {
+        let entry = entry.trim();
+        if entry.eq_ignore_ascii_case("<local>") {
+            return Ok(Self::Local);
+        }
+        if let Some((addr, len)) = entry.split_once('/') {
+            let addr = addr.trim_matches(['[', ']']).parse::<IpAddr>();
+            let len = len.parse::<u8>();
+            return match (addr, len) {
+                (Ok(addr @ IpAddr::V4(_)), Ok(len)) if len <= 32 => Ok(Self::Cidr(addr, len)),
+                (Ok(addr @ IpAddr::V6(_)), Ok(len)) if len <= 128 => Ok(Self::Cidr(addr, len)),
+                _ => bail!("invalid network \"{entry}\""),
+            };
+        }
+        if let Ok(addr) = entry.trim_matches(['[', ']']).parse::<IpAddr>() {
+            return Ok(Self::Ip(addr));
+        }
+
+        let host = entry.to_lowercase();
+        // `.example.com` 是 no_proxy 的写法
+        let host = match host.strip_prefix('.') {
+            Some(suffix) => format!("*.{suffix}"),
+            None => host,
+        };
+        let valid_label = |label: &str| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '*')
+        };
+        if host.is_empty() || host.len() > 253 || !host.split('.').all(valid_label) {
+            bail!("invalid host \"{entry}\"");
+        }
+        if host.contains('*') {
+            Ok(Self::Wildcard(host))
+        } else {
+            Ok(Self::Host(host))
+        }
+    }
+
+    /// The entries for the platform, some can't be written on every platform
+    fn serialize(&self, platform: Platform) -> Vec<String> {
+        match (self, platform) {
+            // GNOME 与 KDE 都不支持 <local>
+            (Self::Local, Platform::Linux) => vec![],
+            (Self::Wildcard(w), Platform::Linux) => match wildcard_network(w) {
+                Some((addr, len)) => vec![format!("{addr}/{len}")],
+                None => vec![match w.strip_prefix("*.") {
+                    Some(suffix) => format!(".{suffix}"),
+                    None => w.clone(),
+                }],
+            },
+            (Self::Cidr(IpAddr::V4(addr), len), Platform::Windows) => cidr_wildcards(*addr, *len),
+            // Windows 无法表示 IPv6 网段
+            (Self::Cidr(IpAddr::V6(_), _), Platform::Windows) => vec![],
+            _ => vec![self.to_string()],
+        }
+    }
+}
+
+impl fmt::Display for BypassEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "<local>"),
+            Self::Host(host) | Self::Wildcard(host) => write!(f, "{host}"),
+            Self::Ip(addr) => write!(f, "{addr}"),
+            Self::Cidr(addr, len) => write!(f, "{addr}/{len}"),
+        }
+    }
+}
+
+/// The bypass list, without duplicates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BypassList(Vec<BypassEntry>);
+
+impl BypassList {
+    /// Entries are separated by `,`, `;` or whitespace, invalid ones are logged and skipped
+    pub fn parse(list: &str) -> Self {
+        let mut bypass = Self::default();
+        for entry in split(list) {
+            match BypassEntry::parse(entry) {
+                Ok(entry) => bypass.push(entry),
+                Err(err) => logging!(warn, Type::System, true, "Skip bypass entry: {}", err),
+            }
+        }
+        bypass
+    }
+
+    pub fn push(&mut self, entry: BypassEntry) {
+        if !self.0.contains(&entry) {
+            self.0.push(entry);
+        }
+    }
+
+    pub fn extend(&mut self, other: BypassList) {
+        for entry in other.0 {
+            self.push(entry);
+        }
+    }
+
+    pub fn serialize(&self, platform: Platform) -> String {
+        let mut entries = Vec::new();
+        for entry in self.0.iter().flat_map(|entry| entry.serialize(platform)) {
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        let separator = match platform {
+            Platform::Windows => ";",
+            Platform::MacOS | Platform::Linux => ",",
+        };
+        entries.join(separator)
+    }
+}
+
+impl fmt::Display for BypassList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.0.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+/// The invalid entries of a bypass list, one message each
+pub fn validate(list: &str) -> Vec<String> {
+    split(list)
+        .filter_map(|entry| BypassEntry::parse(entry).err())
+        .map(|err| err.to_string())
+        .collect()
+}
+
+/// The default list, the reserved ranges, the enabled presets and the custom list from the verge config
+pub fn current() -> BypassList {
+    let (use_default, reserved, custom, presets) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.use_default_bypass.unwrap_or(true),
+            verge.bypass_reserved_ranges.unwrap_or(true),
+            verge.system_proxy_bypass.clone().unwrap_or_default(),
+            verge
+                .system_proxy_bypass_presets
+                .clone()
+                .unwrap_or_default(),
+        )
+    };
+
+    let mut bypass = BypassList::default();
+    if use_default || custom.trim().is_empty() {
+        bypass.extend(BypassList::parse(DEFAULT_BYPASS));
+    }
+    if reserved {
+        for entry in RESERVED_RANGES {
+            if let Ok(entry) = BypassEntry::parse(entry) {
+                bypass.push(entry);
+            }
+        }
+    }
+    for preset in presets.iter().filter_map(|id| BypassPreset::from_id(id)) {
+        for entry in preset.entries() {
+            if let Ok(entry) = BypassEntry::parse(entry) {
+                bypass.push(entry);
+            }
+        }
+    }
+    bypass.extend(BypassList::parse(&custom));
+    bypass
+}
+
+fn split(list: &str) -> impl Iterator<Item = &str> {
+    list.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+}
+
+/// `192.168.*` → `192.168.0.0/16`
+fn wildcard_network(wildcard: &str) -> Option<(Ipv4Addr, u8)> {
+    let prefix = wildcard.strip_suffix(".*")?;
+    let octets = prefix
+        .split('.')
+        .map(|o| o.parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if octets.is_empty() || octets.len() > 3 {
+        return None;
+    }
+    let mut addr = [0u8; 4];
+    addr[..octets.len()].copy_from_slice(&octets);
+    Some((Ipv4Addr::from(addr), octets.len() as u8 * 8))
+}
+
+/// `172.16.0.0/12` → `172.16.*` … `172.31.*`
+fn cidr_wildcards(addr: Ipv4Addr, len: u8) -> Vec<String> {
+    if len == 32 {
+        return vec![addr.to_string()];
+    }
+    if len == 0 {
+        return vec!["*".into()];
+    }
+    // 向上取整到整字节，多出的位逐个展开
+    let octets = len.div_ceil(8);
+    let count = 1u32 << (octets * 8 - len);
+    let base = u32::from(addr) & (u32::MAX << (32 - len));
+    (0..count)
+        .map(|i| {
+            let network = Ipv4Addr::from(base + (i << (32 - octets * 8)));
+            let prefix = network.octets()[..octets as usize]
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            match octets {
+                4 => prefix,
+                _ => format!("{prefix}.*"),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_entry() {
+    assert_eq!(BypassEntry::parse("<local>").unwrap(), BypassEntry::Local);
+    assert_eq!(
+        BypassEntry::parse("Example.COM").unwrap(),
+        BypassEntry::Host("example.com".into())
+    );
+    assert_eq!(
+        BypassEntry::parse(".example.com").unwrap(),
+        BypassEntry::Wildcard("*.example.com".into())
+    );
+    assert_eq!(
+        BypassEntry::parse("10.0.0.0/8").unwrap(),
+        BypassEntry::Cidr("10.0.0.0".parse().unwrap(), 8)
+    );
+    assert_eq!(
+        BypassEntry::parse("::1").unwrap(),
+        BypassEntry::Ip("::1".parse().unwrap())
+    );
+    assert!(BypassEntry::parse("10.0.0.0/33").is_err());
+    assert!(BypassEntry::parse("exa mple.com").is_err());
+    assert!(BypassEntry::parse("example..com").is_err());
+    assert_eq!(validate("localhost;bad_host, 10.0.0.0/8").len(), 1);
+}
+
+#[test]
+fn test_serialize() {
+    let list = BypassList::parse("<local>,*.local,10.0.0.0/8,172.16.0.0/12,192.168.*,fc00::/7");
+    assert_eq!(
+        list.serialize(Platform::MacOS),
+        "<local>,*.local,10.0.0.0/8,172.16.0.0/12,192.168.*,fc00::/7"
+    );
+    assert_eq!(
+        list.serialize(Platform::Linux),
+        ".local,10.0.0.0/8,172.16.0.0/12,192.168.0.0/16,fc00::/7"
+    );
+    let windows = list.serialize(Platform::Windows);
+    assert!(windows.starts_with("<local>;*.local;10.*;172.16.*;172.17.*;"));
+    assert!(windows.ends_with("172.31.*;192.168.*"));
+    assert_eq!(windows.matches("172.").count(), 16);
+}
+
+#[test]
+fn test_cidr_wildcards() {
+    assert_eq!(
+        cidr_wildcards(Ipv4Addr::new(192, 168, 1, 0), 24),
+        vec!["192.168.1.*"]
+    );
+    assert_eq!(cidr_wildcards(Ipv4Addr::new(100, 64, 0, 0), 10).len(), 64);
+    assert_eq!(
+        cidr_wildcards(Ipv4Addr::new(10, 0, 0, 0), 7),
+        vec!["10.*", "11.*"]
+    );
+}
@@ -0,0 +1,253 @@
+//! Captive portals of hotel, airport and café Wi-Fi
+//!
+//! Behind a portal the `generate_204` probes get a login page or a redirect instead of an empty
+//! `204`, and no node is reachable until the user logs in. Bypass mode takes the system proxy
+//! off and turns TUN off in the running core so the portal page opens directly, the settings
+//! stay as they are. While bypassing the probes are repeated, and the previous state comes
+//! back once they pass or after [`BYPASS_LIMIT`].
+//!
+//! With TUN on, the probes would go into the core as well. They are bound to the physical
+//! interface then, and their hosts resolved by the core, the system resolver gets fake ips.
+
+use crate::{
+    config::Config,
+    core::{handle, network_watcher, sysopt::Sysopt, CoreManager, RunningMode},
+    logging, logging_error,
+    module::mihomo::MihomoManager,
+    process::AsyncHandler,
+    utils::logging::Type,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// plain HTTP, a portal can't answer HTTPS without a certificate error
+const PROBE_URLS: [&str; 2] = [
+    "http://connectivitycheck.gstatic.com/generate_204",
+    "http://cp.cloudflare.com/generate_204",
+];
+
+const PROBE_TIMEOUT_SECS: u64 = 5;
+
+/// give a new network a moment to get its address and DNS
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+/// how often the probes are repeated while bypassing
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// restore anyway when the login doesn't go through in this long
+const BYPASS_LIMIT: Duration = Duration::from_secs(15 * 60);
+
+static BYPASSING: AtomicBool = AtomicBool::new(false);
+
+/// serializes entering and leaving bypass mode
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// What the connectivity probes ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    Online,
+    Portal,
+    /// no answer at all, nothing to log in to
+    Offline,
+}
+
+/// Whether the system proxy and TUN are held off for a portal login
+pub fn is_bypassing() -> bool {
+    BYPASSING.load(Ordering::SeqCst)
+}
+
+/// Look for a portal after the network changed, per `captive_portal_mode`
+pub async fn check() {
+    let (mode, sysproxy, tun) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge
+                .captive_portal_mode
+                .clone()
+                .unwrap_or_else(|| "ask".into()),
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.enable_tun_mode.unwrap_or(false),
+        )
+    };
+    // 未使用系统代理与 TUN 时浏览器直接连接，能自己打开认证页面
+    if mode == "off" || is_bypassing() || !(sysproxy || tun) {
+        return;
+    }
+
+    tokio::time::sleep(SETTLE_DELAY).await;
+    if probe().await != Probe::Portal {
+        return;
+    }
+    logging!(info, Type::Network, true, "Captive portal detected");
+    if mode == "auto" {
+        logging_error!(Type::Network, true, set_bypass(true).await);
+    } else {
+        handle::Handle::notice_message("captive_portal::detected", "");
+    }
+}
+
+/// Probe the endpoints directly, one `204` is enough to be online
+pub async fn probe() -> Probe {
+    let Ok(client) = probe_client().await else {
+        return Probe::Offline;
+    };
+    let mut result = Probe::Offline;
+    for url in PROBE_URLS {
+        let Ok(mut response) = client.get(url).send().await else {
+            continue;
+        };
+        let redirected = response.url().as_str() != url;
+        let status = response.status().as_u16();
+        let has_body = response
+            .chunk()
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|chunk| !chunk.is_empty());
+        match classify(status, redirected, has_body) {
+            Probe::Online => return Probe::Online,
+            Probe::Portal => result = Probe::Portal,
+            Probe::Offline => {}
+        }
+    }
+    result
+}
+
+/// A client that goes around the system proxy and, when it is on, TUN
+async fn probe_client() -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .use_rustls_tls()
+        .no_proxy()
+        .timeout(Duration::from_secs(PROBE_TIMEOUT_SECS));
+
+    // 绕过期间内核的 TUN 已关闭
+    let tun = Config::verge().latest().enable_tun_mode.unwrap_or(false) && !is_bypassing();
+    if tun && CoreManager::global().get_running_mode().await != RunningMode::NotRunning {
+        if let Some((_name, _ip)) = network_watcher::physical_interface() {
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            {
+                builder = builder.interface(&_name);
+            }
+            #[cfg(windows)]
+            {
+                builder = builder.local_address(std::net::IpAddr::V4(_ip));
+            }
+        }
+        for url in PROBE_URLS {
+            let Some(host) = reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(String::from))
+            else {
+                continue;
+            };
+            if let Some(ip) = MihomoManager::global()
+                .dns_query(&host)
+                .await
+                .ok()
+                .and_then(|ips| ips.into_iter().next())
+            {
+                builder = builder.resolve(&host, SocketAddr::new(ip, 80));
+            }
+        }
+    }
+    Ok(builder.build()?)
+}
+
+/// A portal answers the probe with 511, a redirect or its own page
+///
+/// Other answers, such as 403 or 5xx, come from something else on the way and
+/// there is nothing to log in to.
+fn classify(status: u16, redirected: bool, has_body: bool) -> Probe {
+    match status {
+        204 if !redirected => Probe::Online,
+        // 511 Network Authentication Required 是认证页面专用的状态码
+        511 => Probe::Portal,
+        _ if redirected => Probe::Portal,
+        300..=399 => Probe::Portal,
+        200 if has_body => Probe::Portal,
+        _ => Probe::Offline,
+    }
+}
+
+/// Enter or leave bypass mode, the previous state stays when it fails
+pub async fn set_bypass(enable: bool) -> Result<()> {
+    let _guard = LOCK.lock().await;
+    if is_bypassing() == enable {
+        return Ok(());
+    }
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "{} captive portal bypass",
+        if enable { "Starting" } else { "Ending" }
+    );
+    Sysopt::global().set_portal_bypass(enable).await?;
+    let tun = Config::verge().latest().enable_tun_mode.unwrap_or(false);
+    if tun && CoreManager::global().get_running_mode().await != RunningMode::NotRunning {
+        let patch = serde_json::json!({ "tun": { "enable": !enable } });
+        if let Err(err) = MihomoManager::global().patch_configs(patch).await {
+            logging_error!(
+                Type::Network,
+                true,
+                Sysopt::global().set_portal_bypass(!enable).await
+            );
+            return Err(anyhow!(err));
+        }
+    }
+    // 重新生成配置时也保持关闭，见 enhance
+    BYPASSING.store(enable, Ordering::SeqCst);
+    handle::Handle::refresh_clash();
+
+    if enable {
+        handle::Handle::notice_message("captive_portal::bypassed", "");
+        AsyncHandler::spawn(wait_for_login);
+    } else {
+        handle::Handle::notice_message("captive_portal::restored", "");
+    }
+    Ok(())
+}
+
+/// Repeat the probes until the login went through, then restore
+async fn wait_for_login() {
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(RECHECK_INTERVAL).await;
+        if !is_bypassing() || handle::Handle::global().is_exiting() {
+            return;
+        }
+        let online = probe().await == Probe::Online;
+        if online || started.elapsed() >= BYPASS_LIMIT {
+            if !online {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Captive portal login not completed in time, restoring"
+                );
+            }
+            logging_error!(Type::Network, true, set_bypass(false).await);
+            return;
+        }
+    }
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(classify(204, false, false), Probe::Online);
+    assert_eq!(classify(204, true, false), Probe::Portal);
+    assert_eq!(classify(200, false, true), Probe::Portal);
+    assert_eq!(classify(200, true, true), Probe::Portal);
+    assert_eq!(classify(200, false, false), Probe::Offline);
+    assert_eq!(classify(302, false, false), Probe::Portal);
+    assert_eq!(classify(511, false, true), Probe::Portal);
+    assert_eq!(classify(403, false, true), Probe::Offline);
+    assert_eq!(classify(404, false, true), Probe::Offline);
+    assert_eq!(classify(503, false, true), Probe::Offline);
+}
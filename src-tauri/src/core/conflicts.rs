@@ -0,0 +1,268 @@
+use crate::{
+    config::{Config, IVerge},
+    core::{async_proxy_query::AsyncProxyQuery, handle},
+    logging,
+    utils::logging::Type,
+};
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use serde::Serialize;
+use serde_yaml::Value;
+use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// another Clash or V2Ray client fighting over the system proxy and TUN
+    ProxyClient,
+    /// DPI bypass tools that intercept the same traffic
+    DpiBypass,
+    /// VPN clients taking over the routes
+    Vpn,
+    /// a system proxy or PAC that isn't ours
+    ForeignProxy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// the product the user will recognize
+    pub name: String,
+    /// the process, interface or proxy address it was found by
+    pub detail: String,
+}
+
+/// Process names without extension, lowercase
+const KNOWN_PROCESSES: &[(&str, ConflictKind, &str)] = &[
+    ("clash-verge", ConflictKind::ProxyClient, "Clash Verge"),
+    ("verge-mihomo", ConflictKind::ProxyClient, "Clash Verge Rev"),
+    (
+        "clash for windows",
+        ConflictKind::ProxyClient,
+        "Clash for Windows",
+    ),
+    (
+        "clash-win64",
+        ConflictKind::ProxyClient,
+        "Clash for Windows",
+    ),
+    ("clashx", ConflictKind::ProxyClient, "ClashX"),
+    ("clashx pro", ConflictKind::ProxyClient, "ClashX Pro"),
+    ("flclash", ConflictKind::ProxyClient, "FlClash"),
+    ("clash-meta", ConflictKind::ProxyClient, "Clash Meta"),
+    ("mihomo", ConflictKind::ProxyClient, "Mihomo"),
+    ("v2rayn", ConflictKind::ProxyClient, "v2rayN"),
+    ("nekoray", ConflictKind::ProxyClient, "NekoRay"),
+    ("nekobox", ConflictKind::ProxyClient, "NekoBox"),
+    ("hiddify", ConflictKind::ProxyClient, "Hiddify"),
+    ("sing-box", ConflictKind::ProxyClient, "sing-box"),
+    ("xray", ConflictKind::ProxyClient, "Xray"),
+    ("v2ray", ConflictKind::ProxyClient, "V2Ray"),
+    ("winws", ConflictKind::DpiBypass, "Zapret"),
+    ("nfqws", ConflictKind::DpiBypass, "Zapret"),
+    ("goodbyedpi", ConflictKind::DpiBypass, "GoodbyeDPI"),
+    ("ciadpi", ConflictKind::DpiBypass, "ByeDPI"),
+    ("spoofdpi", ConflictKind::DpiBypass, "SpoofDPI"),
+    ("openvpn", ConflictKind::Vpn, "OpenVPN"),
+    ("openvpn-gui", ConflictKind::Vpn, "OpenVPN"),
+    ("wireguard", ConflictKind::Vpn, "WireGuard"),
+    ("amneziawg", ConflictKind::Vpn, "AmneziaWG"),
+    ("vpnagent", ConflictKind::Vpn, "Cisco AnyConnect"),
+    ("pangpa", ConflictKind::Vpn, "GlobalProtect"),
+    ("zsatunnel", ConflictKind::Vpn, "Zscaler"),
+    ("warp-svc", ConflictKind::Vpn, "Cloudflare WARP"),
+    ("protonvpn", ConflictKind::Vpn, "Proton VPN"),
+    ("nordvpn", ConflictKind::Vpn, "NordVPN"),
+    ("expressvpn", ConflictKind::Vpn, "ExpressVPN"),
+];
+
+/// Interface name prefixes, lowercase
+const KNOWN_INTERFACES: &[(&str, &str)] = &[
+    ("wg", "WireGuard"),
+    ("wireguard", "WireGuard"),
+    ("amnezia", "AmneziaWG"),
+    ("tap-windows", "OpenVPN"),
+    ("openvpn", "OpenVPN"),
+    ("cloudflarewarp", "Cloudflare WARP"),
+    ("proton", "Proton VPN"),
+    ("nordlynx", "NordVPN"),
+];
+
+/// Look for software that is known to break the system proxy or TUN
+///
+/// Only reports, nothing is changed. Runs at startup and before TUN is turned
+/// on, so a conflict is named up front instead of showing up as a core that
+/// starts fine but doesn't get any traffic.
+pub async fn scan() -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    conflicts.extend(scan_processes());
+    conflicts.extend(scan_interfaces());
+    conflicts.extend(scan_system_proxy().await);
+    #[cfg(windows)]
+    conflicts.extend(scan_registry());
+    conflicts
+}
+
+/// Scan and show a notice when something was found
+pub async fn warn(context: &str) {
+    let conflicts = scan().await;
+    if conflicts.is_empty() {
+        return;
+    }
+    let names = conflicts
+        .iter()
+        .map(|c| format!("{} ({})", c.name, c.detail))
+        .collect::<Vec<_>>()
+        .join(", ");
+    logging!(
+        warn,
+        Type::System,
+        true,
+        "Conflicting software found {}: {}",
+        context,
+        names
+    );
+    handle::Handle::notice_message("conflicts::found", names);
+}
+
+fn scan_processes() -> Vec<Conflict> {
+    let system = System::new_with_specifics(
+        RefreshKind::nothing().with_processes(ProcessRefreshKind::nothing()),
+    );
+    let mut conflicts = system
+        .processes()
+        .values()
+        .filter_map(|process| {
+            let name = process.name().to_string_lossy().into_owned();
+            match_process(&name).map(|(kind, product)| Conflict {
+                kind,
+                name: product.into(),
+                detail: name,
+            })
+        })
+        .collect::<Vec<_>>();
+    conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+    conflicts.dedup_by(|a, b| a.name == b.name);
+    conflicts
+}
+
+fn match_process(name: &str) -> Option<(ConflictKind, &'static str)> {
+    let name = name.to_lowercase();
+    let stem = name.strip_suffix(".exe").unwrap_or(&name);
+    KNOWN_PROCESSES
+        .iter()
+        .find(|(known, _, _)| *known == stem)
+        .map(|(_, kind, product)| (*kind, *product))
+}
+
+fn scan_interfaces() -> Vec<Conflict> {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return vec![];
+    };
+    let own = own_tun_device().to_lowercase();
+    let mut conflicts = interfaces
+        .into_iter()
+        .filter(|interface| interface.name.to_lowercase() != own)
+        .filter_map(|interface| {
+            match_interface(&interface.name).map(|product| Conflict {
+                kind: ConflictKind::Vpn,
+                name: product.into(),
+                detail: interface.name,
+            })
+        })
+        .collect::<Vec<_>>();
+    conflicts.sort_by(|a, b| a.detail.cmp(&b.detail));
+    conflicts.dedup_by(|a, b| a.detail == b.detail);
+    conflicts
+}
+
+fn match_interface(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase().replace(' ', "");
+    KNOWN_INTERFACES
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, product)| *product)
+}
+
+fn own_tun_device() -> String {
+    Config::clash()
+        .latest()
+        .0
+        .get("tun")
+        .and_then(|tun| tun.get("device"))
+        .and_then(Value::as_str)
+        .unwrap_or("Mihomo")
+        .to_string()
+}
+
+/// A proxy or PAC set by someone else, e.g. a corporate policy or another client
+async fn scan_system_proxy() -> Vec<Conflict> {
+    let (host, port, pac_port) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge
+                .proxy_host
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".into()),
+            verge
+                .verge_mixed_port
+                .unwrap_or(Config::clash().latest().get_mixed_port()),
+            IVerge::get_singleton_port(),
+        )
+    };
+
+    let mut conflicts = Vec::new();
+    let sysproxy = AsyncProxyQuery::get_system_proxy().await;
+    if sysproxy.enable && !(sysproxy.host == host && sysproxy.port == port) {
+        conflicts.push(Conflict {
+            kind: ConflictKind::ForeignProxy,
+            name: "System Proxy".into(),
+            detail: format!("{}:{}", sysproxy.host, sysproxy.port),
+        });
+    }
+    let autoproxy = AsyncProxyQuery::get_auto_proxy().await;
+    if autoproxy.enable && !autoproxy.url.contains(&format!(":{pac_port}/commands/pac")) {
+        conflicts.push(Conflict {
+            kind: ConflictKind::ForeignProxy,
+            name: "PAC".into(),
+            detail: autoproxy.url,
+        });
+    }
+    conflicts
+}
+
+/// Policies that make Windows ignore the proxy set for the current user
+#[cfg(windows)]
+fn scan_registry() -> Vec<Conflict> {
+    use winreg::{enums::HKEY_LOCAL_MACHINE, RegKey};
+
+    let policies = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(r"SOFTWARE\Policies\Microsoft\Windows\CurrentVersion\Internet Settings");
+    let per_user = policies
+        .and_then(|key| key.get_value::<u32, _>("ProxySettingsPerUser"))
+        .unwrap_or(1);
+    if per_user != 0 {
+        return vec![];
+    }
+    vec![Conflict {
+        kind: ConflictKind::ForeignProxy,
+        name: "Group Policy".into(),
+        detail: "ProxySettingsPerUser = 0".into(),
+    }]
+}
+
+#[test]
+fn test_match_known() {
+    assert_eq!(
+        match_process("winws.exe"),
+        Some((ConflictKind::DpiBypass, "Zapret"))
+    );
+    assert_eq!(
+        match_process("v2rayN.exe"),
+        Some((ConflictKind::ProxyClient, "v2rayN"))
+    );
+    assert_eq!(match_process("koala-mihomo"), None);
+    assert_eq!(match_interface("wg0"), Some("WireGuard"));
+    assert_eq!(match_interface("TAP-Windows Adapter V9"), Some("OpenVPN"));
+    assert_eq!(match_interface("eth0"), None);
+}
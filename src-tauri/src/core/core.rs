@@ -1,11 +1,16 @@
 use crate::{
     config::*,
     core::{
-        handle,
+        diagnose::{self, CoreDiagnosis},
+        failsafe, handle,
+        health::CoreHealth,
+        integrity, launch, ports, sandbox, selection,
         service::{self},
+        sysopt,
     },
     logging, logging_error,
     module::mihomo::MihomoManager,
+    process::AsyncHandler,
     utils::{
         dirs,
         help::{self},
@@ -14,21 +19,48 @@ use crate::{
 };
 use anyhow::Result;
 use chrono::Local;
-use once_cell::sync::OnceCell;
+use futures::future::{BoxFuture, FutureExt};
+use once_cell::sync::{Lazy, OnceCell};
+use serde_yaml::Mapping;
 use std::{
+    collections::VecDeque,
     fmt,
     fs::{create_dir_all, File},
     io::Write,
     path::PathBuf,
     sync::Arc,
+    time::{Duration, Instant},
+};
+use tauri::AppHandle;
+use tauri_plugin_shell::{
+    process::{Command, CommandChild, CommandEvent},
+    ShellExt,
+};
+use tokio::sync::{
+    oneshot::{self, error::TryRecvError},
+    Mutex,
 };
-use tauri_plugin_shell::{process::CommandChild, ShellExt};
-use tokio::sync::Mutex;
 
 #[derive(Debug)]
 pub struct CoreManager {
     running: Arc<Mutex<RunningMode>>,
     child_sidecar: Arc<Mutex<Option<CommandChild>>>,
+    /// 第二个内核实例
+    child_secondary: Arc<Mutex<Option<SecondaryCore>>>,
+}
+
+/// 运行中的第二个内核实例，以及启动它时的设置
+#[derive(Debug)]
+struct SecondaryCore {
+    child: CommandChild,
+    port: u16,
+    /// 分配端口时设置的端口，被占用时实际端口会顺延
+    preferred_port: u16,
+    clash_core: String,
+    config: Mapping,
+    launch: (Vec<String>, Vec<(String, String)>),
+    /// 进程退出后关闭
+    exited: oneshot::Receiver<()>,
 }
 
 /// 内核运行模式
@@ -38,6 +70,8 @@ pub enum RunningMode {
     Service,
     /// Sidecar 模式运行
     Sidecar,
+    /// 连接外部运行的内核
+    External,
     /// 未运行
     NotRunning,
 }
@@ -47,13 +81,65 @@ impl fmt::Display for RunningMode {
         match self {
             RunningMode::Service => write!(f, "Service"),
             RunningMode::Sidecar => write!(f, "Sidecar"),
+            RunningMode::External => write!(f, "External"),
             RunningMode::NotRunning => write!(f, "NotRunning"),
         }
     }
 }
 
+/// 配置是如何应用到内核的
+#[derive(Debug, Clone, Copy, serde::Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigApplyPath {
+    /// 通过 /configs 接口热重载
+    Api,
+    /// 接口重载失败后重启内核
+    Restart,
+}
+
+impl fmt::Display for ConfigApplyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigApplyPath::Api => write!(f, "api"),
+            ConfigApplyPath::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+/// 已安装的内核
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CoreInfo {
+    pub name: String,
+    pub path: String,
+    pub installed: bool,
+    /// 当前使用的内核
+    pub active: bool,
+}
+
 use crate::config::IVerge;
 
+/// 推送给外部内核时去掉的字段，保留其自身的控制接口
+const EXTERNAL_CORE_SKIP_KEYS: &[&str] = &[
+    "external-controller",
+    "external-controller-tls",
+    "external-controller-unix",
+    "external-controller-pipe",
+    "external-ui",
+    "secret",
+];
+
+/// 崩溃时保留的输出行数，mihomo 的日志（包括致命错误）写在 stdout
+const CRASH_OUTPUT_LINES: usize = 20;
+/// 崩溃后的最大重启次数
+const CRASH_MAX_RESTARTS: u32 = 5;
+const CRASH_BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// 距离上次崩溃超过该时间后重新计数
+const CRASH_RESET_AFTER: Duration = Duration::from_secs(120);
+
+/// 上次崩溃时的重启次数及时间
+static LAST_CRASH: Lazy<parking_lot::Mutex<Option<(u32, Instant)>>> =
+    Lazy::new(|| parking_lot::Mutex::new(None));
+
 impl CoreManager {
     /// 检查文件是否为脚本文件
     fn is_script_file(&self, path: &str) -> Result<bool> {
@@ -142,6 +228,7 @@ impl CoreManager {
             config: Some(Config::clash().latest().0.clone()),
             exists_keys: vec![],
             chain_logs: Default::default(),
+            merge_sources: Default::default(),
         });
         help::save_yaml(
             &runtime_path,
@@ -277,9 +364,7 @@ impl CoreManager {
         );
 
         // 使用子进程运行clash验证配置
-        let output = app_handle
-            .shell()
-            .sidecar(clash_core)?
+        let output = Self::core_command(&app_handle, clash_core)?
             .args(["-t", "-d", app_dir_str, "-f", config_path])
             .output()
             .await?;
@@ -395,13 +480,8 @@ impl CoreManager {
             path
         );
 
-        // 使用boa引擎进行基本语法检查
-        use boa_engine::{Context, Source};
-
-        let mut context = Context::default();
-        let result = context.eval(Source::from_bytes(&content));
-
-        match result {
+        // 只编译不执行，脚本的副作用留到增强时在受限的运行时中发生
+        match crate::enhance::check_script_syntax(&content) {
             Ok(_) => {
                 logging!(
                     debug,
@@ -469,8 +549,19 @@ impl CoreManager {
                 // 4. 验证通过后，生成正式的运行时配置
                 logging!(info, Type::Config, true, "Generating runtime configuration");
                 let run_path = Config::generate_file(ConfigType::Run)?;
-                logging_error!(Type::Config, true, self.put_configs_force(run_path).await);
-                Ok((true, "something".into()))
+                match self.apply_config(run_path).await {
+                    Ok(path) => {
+                        // 重启内核时第二个实例已随之更新，其余情况只在它的配置变化时重启
+                        if path == ConfigApplyPath::Api {
+                            logging_error!(Type::Core, true, self.start_secondary_core().await);
+                        }
+                        Ok((true, path.to_string()))
+                    }
+                    Err(err) => {
+                        logging!(error, Type::Config, true, "Failed to apply config: {}", err);
+                        Err(anyhow::anyhow!("failed to apply the config: {err}"))
+                    }
+                }
             }
             Ok((false, error_msg)) => {
                 logging!(
@@ -496,6 +587,128 @@ impl CoreManager {
             }
         }
     }
+    /// 优先通过 API 热重载配置，不会断开现有连接，失败时再重启内核
+    pub async fn apply_config(&self, path_buf: PathBuf) -> Result<ConfigApplyPath, String> {
+        selection::snapshot().await;
+        if Self::is_external() {
+            return match self.push_external_config().await {
+                Ok(_) => {
+                    Config::runtime().apply();
+                    selection::restore().await;
+                    Ok(ConfigApplyPath::Api)
+                }
+                Err(e) => {
+                    Config::runtime().discard();
+                    logging_error!(Type::Core, true, "Failed to update external core: {}", e);
+                    Err(e.to_string())
+                }
+            };
+        }
+        let run_path_str = dirs::path_to_str(&path_buf).map_err(|e| e.to_string())?;
+        let err = match MihomoManager::global()
+            .put_configs_force(run_path_str)
+            .await
+        {
+            Ok(_) => {
+                Config::runtime().apply();
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Configuration reloaded through the API"
+                );
+                selection::restore().await;
+                return Ok(ConfigApplyPath::Api);
+            }
+            Err(e) => e.to_string(),
+        };
+
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Reloading through the API failed, restarting core: {}",
+            err
+        );
+        match self.restart_core().await {
+            Ok(_) => {
+                Config::runtime().apply();
+                logging!(
+                    info,
+                    Type::Core,
+                    true,
+                    "Configuration applied by restarting core"
+                );
+                Ok(ConfigApplyPath::Restart)
+            }
+            Err(e) => {
+                Config::runtime().discard();
+                let msg = format!("reload failed: {err}, restart failed: {e}");
+                logging_error!(Type::Core, true, "Failed to update configuration: {}", msg);
+                Err(msg)
+            }
+        }
+    }
+
+    /// Move the proxy listeners to the ports in the config
+    ///
+    /// A full reload rebuilds every listener, provider and rule, and restarts
+    /// the core when it fails. PATCH /configs only restarts the listeners whose
+    /// port changed. Falls back to `update_config` when the core doesn't take
+    /// the patch, e.g. an external or older core.
+    pub async fn update_ports(&self) -> Result<()> {
+        Config::generate().await?;
+        let listeners = {
+            let runtime = Config::runtime();
+            let old = runtime.data().config.clone().unwrap_or_default();
+            let new = runtime.latest().config.clone().unwrap_or_default();
+            ports::changed_listeners(&old, &new)
+        };
+        if listeners.is_empty() {
+            Config::runtime().apply();
+            return Ok(());
+        }
+        // 占用时 mihomo 只记录日志，接口仍返回成功
+        if let Err(err) = ports::check_free(&listeners).await {
+            Config::runtime().discard();
+            return Err(err);
+        }
+
+        if !Self::is_external() && self.get_running_mode().await != RunningMode::NotRunning {
+            let patch = serde_json::Value::Object(listeners.clone());
+            match MihomoManager::global().patch_configs(patch).await {
+                Ok(_) => {
+                    Config::runtime().apply();
+                    // 之后重启内核时使用新端口
+                    Config::generate_file(ConfigType::Run)?;
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "Listeners moved through the API: {:?}",
+                        listeners
+                    );
+                    return Ok(());
+                }
+                Err(err) => {
+                    logging!(
+                        warn,
+                        Type::Core,
+                        true,
+                        "Moving listeners through the API failed, reloading config: {}",
+                        err
+                    );
+                }
+            }
+        }
+        Config::runtime().discard();
+        let (valid, msg) = self.update_config().await?;
+        if !valid {
+            anyhow::bail!(msg);
+        }
+        Ok(())
+    }
+
     pub async fn put_configs_force(&self, path_buf: PathBuf) -> Result<(), String> {
         let run_path_str = dirs::path_to_str(&path_buf).map_err(|e| {
             let msg = e.to_string();
@@ -838,9 +1051,8 @@ impl CoreManager {
         }
     }
 
-    async fn start_core_by_sidecar(&self) -> Result<()> {
+    async fn start_core_by_sidecar(&self, config_file: &PathBuf) -> Result<()> {
         logging!(trace, Type::Core, true, "Running core by sidecar");
-        let config_file = &Config::generate_file(ConfigType::Run)?;
         let app_handle = handle::Handle::global()
             .app_handle()
             .ok_or(anyhow::anyhow!("failed to get app handle"))?;
@@ -857,34 +1069,52 @@ impl CoreManager {
 
         let mut log_file = File::create(log_path)?;
 
-        let (mut rx, child) = app_handle
-            .shell()
-            .sidecar(&clash_core)?
+        let (mut rx, child) = Self::sidecar_command(&app_handle, &clash_core)?
             .args([
                 "-d",
                 dirs::path_to_str(&config_dir)?,
                 "-f",
                 dirs::path_to_str(config_file)?,
             ])
+            .args(Self::extra_launch_args())
+            .envs(launch::extra_env())
             .spawn()?;
 
+        let pid = child.pid();
         tokio::spawn(async move {
+            let mut output = VecDeque::with_capacity(CRASH_OUTPUT_LINES);
+            let keep = |output: &mut VecDeque<String>, line: &[u8]| {
+                if output.len() == CRASH_OUTPUT_LINES {
+                    output.pop_front();
+                }
+                output.push_back(String::from_utf8_lossy(line).trim_end().to_string());
+            };
             while let Some(event) = rx.recv().await {
-                if let tauri_plugin_shell::process::CommandEvent::Stdout(line) = event {
-                    if let Err(e) = writeln!(log_file, "{}", String::from_utf8_lossy(&line)) {
-                        logging!(
-                            error,
-                            Type::Core,
-                            true,
-                            "[Sidecar] Failed to write stdout to file: {}",
-                            e
-                        );
+                match event {
+                    CommandEvent::Stdout(line) => {
+                        keep(&mut output, &line);
+                        if let Err(e) = writeln!(log_file, "{}", String::from_utf8_lossy(&line)) {
+                            logging!(
+                                error,
+                                Type::Core,
+                                true,
+                                "[Sidecar] Failed to write stdout to file: {}",
+                                e
+                            );
+                        }
+                    }
+                    CommandEvent::Stderr(line) => keep(&mut output, &line),
+                    CommandEvent::Terminated(payload) => {
+                        CoreManager::global()
+                            .on_sidecar_terminated(pid, payload.code, output.into())
+                            .await;
+                        break;
                     }
+                    _ => {}
                 }
             }
         });
 
-        let pid = child.pid();
         logging!(
             trace,
             Type::Core,
@@ -894,8 +1124,30 @@ impl CoreManager {
         );
         *self.child_sidecar.lock().await = Some(child);
         self.set_running_mode(RunningMode::Sidecar).await;
+        if CoreHealth::global().wait_until_ready().await {
+            CoreDiagnosis::clear();
+            AsyncHandler::spawn(sandbox::verify);
+        }
         Ok(())
     }
+    /// 用户配置的额外参数和环境变量，启动前记录到日志便于排查
+    fn extra_launch_args() -> Vec<String> {
+        let args = launch::extra_args();
+        let env = launch::extra_env();
+        if !args.is_empty() || !env.is_empty() {
+            let names = env.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Starting core with extra args {:?} and env {:?}",
+                args,
+                names
+            );
+        }
+        args
+    }
+
     async fn stop_core_by_sidecar(&self) -> Result<()> {
         logging!(trace, Type::Core, true, "Stopping core by sidecar");
 
@@ -916,11 +1168,168 @@ impl CoreManager {
 }
 
 impl CoreManager {
-    async fn start_core_by_service(&self) -> Result<()> {
+    /// 内核意外退出时按指数退避重启，并重新应用系统代理
+    /// 能识别原因的退出（端口占用、配置错误等）每次重启都会重现，直接提示修复方法
+    /// 正常停止时 child_sidecar 已被取出，不会进入这里
+    /// 返回装箱的 future，因为重启时会再次创建监听该进程的任务
+    fn on_sidecar_terminated(
+        &'static self,
+        pid: u32,
+        code: Option<i32>,
+        output: Vec<String>,
+    ) -> BoxFuture<'static, ()> {
+        async move { self.recover_sidecar(pid, code, output).await }.boxed()
+    }
+
+    async fn recover_sidecar(&self, pid: u32, code: Option<i32>, output: Vec<String>) {
+        {
+            let mut child = self.child_sidecar.lock().await;
+            if child.as_ref().map(|c| c.pid()) != Some(pid) {
+                return;
+            }
+            *child = None;
+        }
+        self.set_running_mode(RunningMode::NotRunning).await;
+
+        let diagnosis = diagnose::diagnose(&output, code);
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Core exited unexpectedly, pid: {}, code: {:?}, cause: {:?}, output:\n{}",
+            pid,
+            code,
+            diagnosis.code,
+            output.join("\n")
+        );
+        failsafe::core_down(&diagnosis.message).await;
+        if diagnosis.is_known() {
+            logging_error!(
+                Type::Core,
+                true,
+                sysopt::Sysopt::global().reset_sysproxy().await
+            );
+            handle::Handle::notice_message(
+                "core_crash::diagnosed",
+                serde_json::to_string(&diagnosis).unwrap_or_default(),
+            );
+            return;
+        }
+        handle::Handle::notice_message("core_crash::exited", diagnosis.message.clone());
+
+        let first_attempt = {
+            let mut last_crash = LAST_CRASH.lock();
+            let attempt = match *last_crash {
+                Some((attempt, at)) if at.elapsed() < CRASH_RESET_AFTER => attempt + 1,
+                _ => 0,
+            };
+            *last_crash = Some((attempt, Instant::now()));
+            attempt
+        };
+
+        for attempt in first_attempt..CRASH_MAX_RESTARTS {
+            let delay = CRASH_BACKOFF_BASE * 2u32.pow(attempt);
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Restarting core in {:?}, attempt {}/{}",
+                delay,
+                attempt + 1,
+                CRASH_MAX_RESTARTS
+            );
+            tokio::time::sleep(delay).await;
+
+            // 等待期间用户可能已经手动启动了内核
+            if self.get_running_mode().await != RunningMode::NotRunning {
+                return;
+            }
+            match self.start_core().await {
+                Ok(_) => {
+                    logging_error!(
+                        Type::Core,
+                        true,
+                        sysopt::Sysopt::global().update_sysproxy().await
+                    );
+                    handle::Handle::refresh_clash();
+                    handle::Handle::notice_message(
+                        "core_crash::restarted",
+                        format!("{}", attempt + 1),
+                    );
+                    *LAST_CRASH.lock() = Some((attempt, Instant::now()));
+                    return;
+                }
+                Err(err) => {
+                    logging!(error, Type::Core, true, "Failed to restart core: {}", err);
+                }
+            }
+        }
+
+        // 不要让系统代理指向一个已经没有内核监听的端口
+        logging_error!(
+            Type::Core,
+            true,
+            sysopt::Sysopt::global().reset_sysproxy().await
+        );
+        handle::Handle::notice_message("core_crash::gave_up", diagnosis.message);
+    }
+
+    fn is_external() -> bool {
+        Config::verge().latest().external_core().is_some()
+    }
+
+    /// 外部内核可能运行在容器中无法读取本地文件，直接发送配置内容
+    async fn push_external_config(&self) -> Result<()> {
+        let mut config = Config::runtime()
+            .latest()
+            .config
+            .clone()
+            .ok_or(anyhow::anyhow!("failed to get runtime config"))?;
+        for key in EXTERNAL_CORE_SKIP_KEYS {
+            config.remove(*key);
+        }
+        let payload = serde_yaml::to_string(&config)?;
+        MihomoManager::global()
+            .put_configs_payload(&payload)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    async fn start_core_by_external(&self) -> Result<()> {
+        logging!(info, Type::Core, true, "Attaching to external core");
+        MihomoManager::global()
+            .is_mihomo_running()
+            .await
+            .map_err(|e| anyhow::anyhow!("external core is not reachable: {e}"))?;
+        self.push_external_config().await?;
+        Config::runtime().apply();
+        self.set_running_mode(RunningMode::External).await;
+        Ok(())
+    }
+
+    async fn start_core_by_service(&self, config_file: &PathBuf) -> Result<()> {
         logging!(trace, Type::Core, true, "Running core by service");
-        let config_file = &Config::generate_file(ConfigType::Run)?;
+        // 服务还不支持额外的启动参数和环境变量，告诉用户而不是悄悄忽略
+        if !launch::extra_args().is_empty() || !launch::extra_env().is_empty() {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Extra core arguments and environment are not applied in service mode"
+            );
+            handle::Handle::notice_message("core_launch::ignored_by_service", "");
+        }
         service::run_core_by_service(config_file).await?;
         self.set_running_mode(RunningMode::Service).await;
+        if !CoreHealth::global().wait_until_ready().await {
+            // 停掉没有响应的内核，调用方会回退到 Sidecar 模式
+            logging_error!(Type::Core, true, self.stop_core_by_service().await);
+            return Err(anyhow::anyhow!(
+                "the core started by the service is not answering"
+            ));
+        }
+        CoreDiagnosis::clear();
+        AsyncHandler::spawn(sandbox::verify);
         Ok(())
     }
     async fn stop_core_by_service(&self) -> Result<()> {
@@ -931,16 +1340,190 @@ impl CoreManager {
     }
 }
 
+/// 第二个实例的端口被占用时最多向后尝试的端口数
+const SECONDARY_PORT_ATTEMPTS: usize = 20;
+
+/// 等待旧的第二个实例退出、释放端口的最长时间
+const SECONDARY_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl CoreManager {
+    fn is_secondary_enabled() -> bool {
+        Config::verge()
+            .latest()
+            .enable_secondary_core
+            .unwrap_or(false)
+    }
+
+    fn preferred_secondary_port() -> u16 {
+        Config::verge()
+            .latest()
+            .secondary_mixed_port
+            .unwrap_or(7898)
+    }
+
+    /// 第二个实例的混合端口，与主实例的端口冲突或已被占用时顺延到下一个空闲端口
+    fn allocate_secondary_port(preferred: u16) -> Result<u16> {
+        let used = {
+            let clash = Config::clash();
+            let clash = clash.latest();
+            [
+                clash.get_mixed_port(),
+                clash.get_socks_port(),
+                clash.get_port(),
+            ]
+        };
+
+        (preferred..=u16::MAX)
+            .filter(|port| *port != 0 && !used.contains(port))
+            .take(SECONDARY_PORT_ATTEMPTS)
+            .find(|port| std::net::TcpListener::bind(("127.0.0.1", *port)).is_ok())
+            .ok_or_else(|| anyhow::anyhow!("no free port for the secondary core from {preferred}"))
+    }
+
+    /// 运行第二个内核实例，只提供混合端口，不启用 TUN，也不设置系统代理
+    /// 无论主实例以何种方式运行，第二个实例总是以 sidecar 运行
+    /// 已在运行时只有其配置、内核或启动参数变化才会重启，端口保持不变
+    pub async fn start_secondary_core(&self) -> Result<()> {
+        let mut secondary = self.child_secondary.lock().await;
+        if !Self::is_secondary_enabled() || Self::is_external() {
+            return Self::stop_secondary(secondary.take()).await;
+        }
+
+        let preferred_port = Self::preferred_secondary_port();
+        let clash_core = Config::verge().latest().get_valid_clash_core();
+        let launch = (launch::extra_args(), launch::extra_env());
+        if let Some(running) = secondary.as_mut() {
+            let unchanged = matches!(running.exited.try_recv(), Err(TryRecvError::Empty))
+                && running.preferred_port == preferred_port
+                && running.clash_core == clash_core
+                && running.launch == launch
+                && Config::secondary_config(running.port).is_ok_and(|c| c == running.config);
+            if unchanged {
+                return Ok(());
+            }
+        }
+        Self::stop_secondary(secondary.take()).await?;
+
+        let port = Self::allocate_secondary_port(preferred_port)?;
+        let config = Config::secondary_config(port)?;
+        let (home_dir, config_file) = Config::generate_secondary_file(&config)?;
+        let app_handle = handle::Handle::global()
+            .app_handle()
+            .ok_or(anyhow::anyhow!("failed to get app handle"))?;
+        integrity::verify(&clash_core)?;
+
+        let (mut rx, child) = Self::sidecar_command(&app_handle, &clash_core)?
+            .args([
+                "-d",
+                dirs::path_to_str(&home_dir)?,
+                "-f",
+                dirs::path_to_str(&config_file)?,
+            ])
+            .args(launch.0.clone())
+            .envs(launch.1.clone())
+            .spawn()?;
+
+        let pid = child.pid();
+        let (exited_tx, exited) = oneshot::channel();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let CommandEvent::Terminated(payload) = event {
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "Secondary core pid {} exited with code {:?}",
+                        pid,
+                        payload.code
+                    );
+                    break;
+                }
+            }
+            let _ = exited_tx.send(());
+        });
+
+        logging!(
+            info,
+            Type::Core,
+            true,
+            "Started secondary core pid {} on port {}",
+            pid,
+            port
+        );
+        *secondary = Some(SecondaryCore {
+            child,
+            port,
+            preferred_port,
+            clash_core,
+            config,
+            launch,
+            exited,
+        });
+        Ok(())
+    }
+
+    pub async fn stop_secondary_core(&self) -> Result<()> {
+        let secondary = self.child_secondary.lock().await.take();
+        Self::stop_secondary(secondary).await
+    }
+
+    /// 结束第二个实例并等它退出，新实例分配端口时旧进程不能还占着端口
+    async fn stop_secondary(secondary: Option<SecondaryCore>) -> Result<()> {
+        let Some(SecondaryCore {
+            child,
+            port,
+            exited,
+            ..
+        }) = secondary
+        else {
+            return Ok(());
+        };
+        let pid = child.pid();
+        child.kill()?;
+        if tokio::time::timeout(SECONDARY_STOP_TIMEOUT, exited)
+            .await
+            .is_err()
+        {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Secondary core pid {} did not exit in time",
+                pid
+            );
+        }
+        logging!(
+            info,
+            Type::Core,
+            true,
+            "Stopped secondary core pid {} on port {}",
+            pid,
+            port
+        );
+        Ok(())
+    }
+
+    /// 第二个实例实际使用的混合端口，未运行时为 None
+    pub async fn secondary_port(&self) -> Option<u16> {
+        self.child_secondary
+            .lock()
+            .await
+            .as_ref()
+            .map(|secondary| secondary.port)
+    }
+}
+
 impl CoreManager {
     pub fn global() -> &'static CoreManager {
         static CORE_MANAGER: OnceCell<CoreManager> = OnceCell::new();
         CORE_MANAGER.get_or_init(|| CoreManager {
             running: Arc::new(Mutex::new(RunningMode::NotRunning)),
             child_sidecar: Arc::new(Mutex::new(None)),
+            child_secondary: Arc::new(Mutex::new(None)),
         })
     }
     // 当服务安装失败时的回退逻辑
-    async fn attempt_service_init(&self) -> Result<()> {
+    async fn attempt_service_init(&self, config_file: &PathBuf) -> Result<()> {
         if service::check_service_needs_reinstall().await {
             logging!(
                 info,
@@ -967,7 +1550,7 @@ impl CoreManager {
             );
         }
 
-        if let Err(e) = self.start_core_by_service().await {
+        if let Err(e) = self.start_core_by_service(config_file).await {
             logging!(
                 warn,
                 Type::Core,
@@ -998,6 +1581,11 @@ impl CoreManager {
     pub async fn init(&self) -> Result<()> {
         logging!(trace, Type::Core, "Initializing core");
 
+        // 外部内核不由应用启动，也不能当作遗留进程清理
+        if Self::is_external() {
+            return self.start_core_by_external().await;
+        }
+
         // 应用启动时先清理任何遗留的 mihomo 进程
         if let Err(e) = self.cleanup_orphaned_mihomo_processes().await {
             logging!(
@@ -1009,6 +1597,17 @@ impl CoreManager {
             );
         }
 
+        if let Err(err) = ports::resolve_conflicts().await {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Port conflict not resolved: {}",
+                err
+            );
+        }
+
+        let config_file = &Self::prepare_start()?;
         let mut core_started_successfully = false;
 
         if service::is_service_available().await.is_ok() {
@@ -1018,7 +1617,7 @@ impl CoreManager {
                 true,
                 "Service currently available or appears available; attempting to start/reinstall via service mode"
             );
-            match self.attempt_service_init().await {
+            match self.attempt_service_init(config_file).await {
                 Ok(_) => {
                     logging!(
                         info,
@@ -1063,7 +1662,7 @@ impl CoreManager {
                     true,
                     "User prefers Sidecar mode or previous service start failed; starting with Sidecar mode"
                 );
-                self.start_core_by_sidecar().await?;
+                self.start_core_by_sidecar(config_file).await?;
                 // 如果 sidecar 启动成功，我们可以认为核心初始化流程到此结束
                 // 后续的 Tray::global().subscribe_traffic().await 仍然会执行
             } else {
@@ -1095,7 +1694,7 @@ impl CoreManager {
                                     true,
                                     "Newly installed service available; attempting to start"
                                 );
-                                if self.start_core_by_service().await.is_ok() {
+                                if self.start_core_by_service(config_file).await.is_ok() {
                                     logging!(
                                         info,
                                         Type::Core,
@@ -1114,7 +1713,7 @@ impl CoreManager {
                                     final_state.last_error =
                                         Some("Newly installed service failed to start".to_string());
                                     final_state.save()?;
-                                    self.start_core_by_sidecar().await?;
+                                    self.start_core_by_sidecar(config_file).await?;
                                 }
                             } else {
                                 logging!(
@@ -1130,7 +1729,7 @@ impl CoreManager {
                                         .to_string(),
                                 );
                                 final_state.save()?;
-                                self.start_core_by_sidecar().await?;
+                                self.start_core_by_sidecar(config_file).await?;
                             }
                         }
                         Err(err) => {
@@ -1147,7 +1746,7 @@ impl CoreManager {
                                 ..Default::default()
                             };
                             new_state.save()?;
-                            self.start_core_by_sidecar().await?;
+                            self.start_core_by_sidecar(config_file).await?;
                         }
                     }
                 } else {
@@ -1176,11 +1775,13 @@ impl CoreManager {
                             }));
                         final_state.save()?;
                     }
-                    self.start_core_by_sidecar().await?;
+                    self.start_core_by_sidecar(config_file).await?;
                 }
             }
         }
 
+        logging_error!(Type::Core, true, self.start_secondary_core().await);
+
         logging!(trace, Type::Core, "Initied core logic completed");
         // #[cfg(target_os = "macos")]
         // logging_error!(Type::Core, true, Tray::global().subscribe_traffic().await);
@@ -1197,8 +1798,23 @@ impl CoreManager {
         (*guard).clone()
     }
 
+    /// 校验内核并生成运行配置，每次启动只做一次，启动失败回退到 Sidecar 时沿用
+    fn prepare_start() -> Result<PathBuf> {
+        integrity::verify(&Config::verge().latest().get_valid_clash_core())?;
+        Config::generate_file(ConfigType::Run)
+    }
+
     /// 启动核心
     pub async fn start_core(&self) -> Result<()> {
+        if Self::is_external() {
+            self.start_core_by_external().await?;
+            selection::restore().await;
+            return Ok(());
+        }
+        self.start_prepared_core(&Self::prepare_start()?).await
+    }
+
+    async fn start_prepared_core(&self, config_file: &PathBuf) -> Result<()> {
         if service::is_service_available().await.is_ok() {
             if service::check_service_needs_reinstall().await {
                 service::reinstall_service().await?;
@@ -1209,7 +1825,7 @@ impl CoreManager {
                 true,
                 "Service available; starting in service mode"
             );
-            self.start_core_by_service().await?;
+            self.start_core_by_service(config_file).await?;
         } else {
             // 服务不可用，检查用户偏好
             let service_state = service::ServiceState::get();
@@ -1220,7 +1836,7 @@ impl CoreManager {
                     true,
                     "Service unavailable; starting in Sidecar mode per user preference"
                 );
-                self.start_core_by_sidecar().await?;
+                self.start_core_by_sidecar(config_file).await?;
             } else {
                 logging!(
                     info,
@@ -1228,27 +1844,130 @@ impl CoreManager {
                     true,
                     "Service unavailable; starting in Sidecar mode"
                 );
-                self.start_core_by_sidecar().await?;
+                self.start_core_by_sidecar(config_file).await?;
             }
         }
+        logging_error!(Type::Core, true, self.start_secondary_core().await);
+        // 重启后所有选择器都会回到第一个节点
+        selection::restore().await;
         Ok(())
     }
 
     /// 停止核心运行
     pub async fn stop_core(&self) -> Result<()> {
+        if self.get_running_mode().await != RunningMode::NotRunning {
+            selection::snapshot().await;
+        }
+        logging_error!(Type::Core, true, self.stop_secondary_core().await);
         match self.get_running_mode().await {
             RunningMode::Service => self.stop_core_by_service().await,
             RunningMode::Sidecar => self.stop_core_by_sidecar().await,
+            RunningMode::External => {
+                self.set_running_mode(RunningMode::NotRunning).await;
+                Ok(())
+            }
             RunningMode::NotRunning => Ok(()),
         }
     }
 
-    /// 重启内核
+    /// 重启内核进程，TUN 网卡和路由会随旧进程一起移除
+    /// 只有更换内核、启动参数或运行模式，或内核无响应时才需要，配置变化走 `update_config`
+    /// 停止旧内核前先完成校验并生成配置，缩短断网的时间，校验失败时旧内核继续运行
     pub async fn restart_core(&self) -> Result<()> {
+        if Self::is_external() {
+            self.stop_core().await?;
+            return self.start_core().await;
+        }
+        let config_file = Self::prepare_start()?;
         self.stop_core().await?;
 
-        self.start_core().await?;
-        Ok(())
+        self.start_prepared_core(&config_file).await
+    }
+
+    /// 内核可执行文件的路径，核心更新安装的内核优先，否则为与应用程序位于同一目录的内置内核
+    pub fn core_bin_path(clash_core: &str) -> Result<PathBuf> {
+        let installed = Self::installed_core_path(clash_core)?;
+        if installed.is_file() {
+            return Ok(installed);
+        }
+        Self::bundled_core_path(clash_core)
+    }
+
+    /// 随应用程序安装的内置内核
+    pub fn bundled_core_path(clash_core: &str) -> Result<PathBuf> {
+        let bin_ext = if cfg!(windows) { ".exe" } else { "" };
+        Ok(tauri::utils::platform::current_exe()?.with_file_name(format!("{clash_core}{bin_ext}")))
+    }
+
+    /// 核心更新安装内核的位置，在用户可写的应用目录中
+    pub fn installed_core_path(clash_core: &str) -> Result<PathBuf> {
+        let bin_ext = if cfg!(windows) { ".exe" } else { "" };
+        Ok(dirs::app_cores_dir()?.join(format!("{clash_core}{bin_ext}")))
+    }
+
+    /// 以 sidecar 运行内核的命令，开启沙盒时通过 setpriv 降低权限
+    fn sidecar_command(app_handle: &AppHandle, clash_core: &str) -> Result<Command> {
+        let Some(args) = sandbox::setpriv_args() else {
+            return Self::core_command(app_handle, clash_core);
+        };
+        logging!(
+            info,
+            Type::Core,
+            true,
+            "Dropping core privileges with setpriv"
+        );
+        Ok(app_handle
+            .shell()
+            .command("setpriv")
+            .args(args)
+            .arg(dirs::path_to_str(&Self::core_bin_path(clash_core)?)?))
+    }
+
+    /// 运行内核的命令，与 `core_bin_path` 选择同一个文件
+    fn core_command(app_handle: &AppHandle, clash_core: &str) -> Result<Command> {
+        let installed = Self::installed_core_path(clash_core)?;
+        if installed.is_file() {
+            return Ok(app_handle.shell().command(installed));
+        }
+        Ok(app_handle.shell().sidecar(clash_core)?)
+    }
+
+    /// 列出所有内核及其安装状态
+    pub fn list_cores(&self) -> Vec<CoreInfo> {
+        let active = Config::verge().latest().get_valid_clash_core();
+        IVerge::VALID_CLASH_CORES
+            .iter()
+            .map(|&name| {
+                let path = Self::core_bin_path(name).ok();
+                CoreInfo {
+                    name: name.to_string(),
+                    installed: path.as_ref().is_some_and(|p| p.is_file()),
+                    path: path
+                        .map(|p| p.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    active: name == active,
+                }
+            })
+            .collect()
+    }
+
+    /// 运行内核的 -v 获取版本，如 v1.19.10 或 alpha-1a2b3c4
+    pub async fn core_version(&self, clash_core: &str) -> Result<String> {
+        let app_handle = handle::Handle::global()
+            .app_handle()
+            .ok_or(anyhow::anyhow!("failed to get app handle"))?;
+        let output = Self::core_command(&app_handle, clash_core)?
+            .args(["-v"])
+            .output()
+            .await?;
+
+        // Mihomo Meta v1.19.10 linux amd64 with go1.24.2 ...
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stdout
+            .split_whitespace()
+            .nth(2)
+            .map(|version| version.to_string())
+            .ok_or_else(|| anyhow::anyhow!("unexpected version output: {stdout}"))
     }
 
     /// 切换核心
@@ -1264,6 +1983,11 @@ impl CoreManager {
             logging!(error, Type::Core, true, "{}", error_message);
             return Err(error_message);
         }
+        if !Self::core_bin_path(core).is_ok_and(|path| path.is_file()) {
+            let error_message = format!("Clash core is not installed: {core}");
+            logging!(error, Type::Core, true, "{}", error_message);
+            return Err(error_message);
+        }
 
         Config::verge().draft().clash_core = clash_core.clone();
         Config::verge().apply();
@@ -0,0 +1,234 @@
+use once_cell::sync::Lazy;
+use parking_lot::{const_rwlock, RwLock};
+use regex::Regex;
+use serde::Serialize;
+
+/// A known reason for the core failing to start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreErrorCode {
+    PortInUse,
+    TunPermissionDenied,
+    GeodataBroken,
+    InvalidConfig,
+    Unknown,
+}
+
+/// What to do about it, the frontend shows the matching hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreFix {
+    /// turn on `enable_port_auto_resolve` or pick another port
+    ChangePort,
+    /// install the service or grant the core permissions
+    InstallService,
+    /// download the geodata files again
+    UpdateGeodata,
+    /// fix the line in the profile, merge or script
+    EditProfile,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoreDiagnosis {
+    pub code: CoreErrorCode,
+    pub fix: Option<CoreFix>,
+    /// the port, the geodata file or the config line
+    pub detail: Option<String>,
+    pub exit_code: Option<i32>,
+    /// the log message the diagnosis was made from
+    pub message: String,
+}
+
+/// The last diagnosis, cleared when the core starts again
+static LAST_DIAGNOSIS: RwLock<Option<CoreDiagnosis>> = const_rwlock(None);
+
+static PORT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r":(\d{1,5}): bind").unwrap());
+static LINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bline (\d+)").unwrap());
+static MSG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"msg="((?:[^"\\]|\\.)*)""#).unwrap());
+
+const PORT_IN_USE: &[&str] = &[
+    "address already in use",
+    "only one usage of each socket address",
+];
+const PERMISSION_DENIED: &[&str] = &[
+    "operation not permitted",
+    "permission denied",
+    "access is denied",
+];
+const GEODATA_FILES: &[&str] = &[
+    "geoip.metadb",
+    "geoip.dat",
+    "geosite.dat",
+    "country.mmdb",
+    "asn.mmdb",
+];
+const GEODATA_ERRORS: &[&str] = &[
+    "can't initial geoip",
+    "can't initial geosite",
+    "can't initial mmdb",
+];
+const CONFIG_ERRORS: &[&str] = &["parse config error", "yaml: ", "unmarshal"];
+
+impl CoreDiagnosis {
+    pub fn last() -> Option<CoreDiagnosis> {
+        LAST_DIAGNOSIS.read().clone()
+    }
+
+    pub fn clear() {
+        *LAST_DIAGNOSIS.write() = None;
+    }
+
+    fn save(&self) {
+        *LAST_DIAGNOSIS.write() = Some(self.clone());
+    }
+
+    /// A known cause fails the same way on every restart
+    pub fn is_known(&self) -> bool {
+        self.code != CoreErrorCode::Unknown
+    }
+}
+
+/// Map the last lines the core printed before exiting to a known cause
+pub fn diagnose(output: &[String], exit_code: Option<i32>) -> CoreDiagnosis {
+    let messages = output
+        .iter()
+        .map(|line| log_message(line))
+        .filter(|msg| !msg.is_empty())
+        .collect::<Vec<_>>();
+
+    // 致命错误一般在最后，从后往前找
+    let diagnosis = messages
+        .iter()
+        .rev()
+        .find_map(|msg| match_message(msg))
+        .unwrap_or_else(|| CoreDiagnosis {
+            code: CoreErrorCode::Unknown,
+            fix: None,
+            detail: None,
+            exit_code: None,
+            message: output
+                .iter()
+                .rev()
+                .find(|line| is_error_line(line))
+                .map(|line| log_message(line))
+                .or_else(|| messages.last().cloned())
+                .unwrap_or_default(),
+        });
+    let diagnosis = CoreDiagnosis {
+        exit_code,
+        ..diagnosis
+    };
+    diagnosis.save();
+    diagnosis
+}
+
+fn match_message(msg: &str) -> Option<CoreDiagnosis> {
+    let lower = msg.to_lowercase();
+    let contains_any = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+    let diagnosis = |code, fix, detail| CoreDiagnosis {
+        code,
+        fix: Some(fix),
+        detail,
+        exit_code: None,
+        message: msg.to_string(),
+    };
+
+    if contains_any(PORT_IN_USE) {
+        let port = PORT_RE.captures(msg).map(|c| c[1].to_string());
+        return Some(diagnosis(
+            CoreErrorCode::PortInUse,
+            CoreFix::ChangePort,
+            port,
+        ));
+    }
+    if lower.contains("tun") && contains_any(PERMISSION_DENIED) {
+        return Some(diagnosis(
+            CoreErrorCode::TunPermissionDenied,
+            CoreFix::InstallService,
+            None,
+        ));
+    }
+    if contains_any(GEODATA_ERRORS) {
+        let file = GEODATA_FILES
+            .iter()
+            .find(|file| lower.contains(*file))
+            .map(|file| file.to_string());
+        return Some(diagnosis(
+            CoreErrorCode::GeodataBroken,
+            CoreFix::UpdateGeodata,
+            file,
+        ));
+    }
+    if contains_any(CONFIG_ERRORS) {
+        let line = LINE_RE.captures(msg).map(|c| c[1].to_string());
+        return Some(diagnosis(
+            CoreErrorCode::InvalidConfig,
+            CoreFix::EditProfile,
+            line,
+        ));
+    }
+    None
+}
+
+/// `time="..." level=fatal msg="Parse config error: ..."` → `Parse config error: ...`
+fn log_message(line: &str) -> String {
+    match MSG_RE.captures(line) {
+        Some(c) => c[1].replace("\\\"", "\""),
+        None => line.trim().to_string(),
+    }
+}
+
+fn is_error_line(line: &str) -> bool {
+    ["level=fatal", "level=error", "FATA", "panic:"]
+        .iter()
+        .any(|kw| line.contains(kw))
+}
+
+#[test]
+fn test_diagnose() {
+    let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+    let d = diagnose(
+        &lines(&[
+            r#"time="2025-01-01T00:00:00" level=info msg="Start initial configuration in progress""#,
+            r#"time="2025-01-01T00:00:00" level=fatal msg="Start Mixed(http+socks) server error: listen tcp 127.0.0.1:7897: bind: address already in use""#,
+        ]),
+        Some(1),
+    );
+    assert_eq!(d.code, CoreErrorCode::PortInUse);
+    assert_eq!(d.detail.as_deref(), Some("7897"));
+    assert_eq!(d.exit_code, Some(1));
+
+    let d = diagnose(
+        &lines(&[
+            r#"level=error msg="Start TUN listening error: configure tun interface: operation not permitted""#,
+        ]),
+        None,
+    );
+    assert_eq!(d.code, CoreErrorCode::TunPermissionDenied);
+
+    let d = diagnose(
+        &lines(&[
+            r#"level=fatal msg="Parse config error: rules[3] [GEOSITE,cn,DIRECT] error: can't initial GeoSite: invalid data in GeoSite.dat""#,
+        ]),
+        Some(1),
+    );
+    assert_eq!(d.code, CoreErrorCode::GeodataBroken);
+    assert_eq!(d.detail.as_deref(), Some("geosite.dat"));
+
+    let d = diagnose(
+        &lines(&[
+            r#"level=fatal msg="Parse config error: yaml: line 42: did not find expected key""#,
+        ]),
+        Some(1),
+    );
+    assert_eq!(d.code, CoreErrorCode::InvalidConfig);
+    assert_eq!(d.detail.as_deref(), Some("42"));
+
+    let d = diagnose(
+        &lines(&["panic: something", "goroutine 1 [running]:"]),
+        Some(2),
+    );
+    assert_eq!(d.code, CoreErrorCode::Unknown);
+    assert_eq!(d.message, "panic: something");
+}
@@ -0,0 +1,246 @@
+use crate::{
+    config::Config,
+    core::{handle, CoreManager, RunningMode},
+    logging,
+    utils::{help, logging::Type},
+};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+/// a random label is put in front, so no resolver has it cached
+const CANARY_DOMAIN: &str = "dns-check.example.com";
+
+/// how long one lookup through the system resolver may take
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// wait for the core to bring up the TUN device before checking
+const TUN_SETTLE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsHijackStatus {
+    /// the system resolver got a fake ip from the core
+    Ok,
+    /// it was broken and one of the fixes helped
+    Repaired,
+    /// still broken after all fixes
+    Broken,
+    /// TUN is off or the dns is not in fake-ip mode, nothing to check
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsFix {
+    FlushCache,
+    ReloadConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsHijackReport {
+    pub status: DnsHijackStatus,
+    /// the fixes that were tried, in order
+    pub fixes: Vec<DnsFix>,
+    /// what the system resolver answered last
+    pub detail: String,
+}
+
+/// Check that the queries of the system resolver end up at the core
+///
+/// With TUN on and the dns in fake-ip mode, any domain resolves to the fake
+/// ip range. Getting a real answer or none means the queries bypass the TUN
+/// device, usually because of a stale resolver cache or a dns/tun section the
+/// core did not pick up. With `repair` the cache is flushed and then the
+/// config reloaded, checking again after each.
+pub async fn check(repair: bool) -> DnsHijackReport {
+    let Some(range) = fake_ip_range() else {
+        return DnsHijackReport {
+            status: DnsHijackStatus::Skipped,
+            fixes: vec![],
+            detail: String::new(),
+        };
+    };
+
+    let (mut hijacked, mut detail) = probe(range).await;
+    let mut fixes = Vec::new();
+    if hijacked || !repair {
+        return report(hijacked, fixes, detail);
+    }
+
+    for fix in [DnsFix::FlushCache, DnsFix::ReloadConfig] {
+        logging!(
+            warn,
+            Type::Network,
+            true,
+            "DNS is not hijacked ({}), trying {:?}",
+            detail,
+            fix
+        );
+        let applied = match fix {
+            DnsFix::FlushCache => flush_cache().await,
+            DnsFix::ReloadConfig => CoreManager::global()
+                .update_config()
+                .await
+                .map(|(ok, _)| ok)
+                .unwrap_or(false),
+        };
+        if !applied {
+            continue;
+        }
+        fixes.push(fix);
+        (hijacked, detail) = probe(range).await;
+        if hijacked {
+            break;
+        }
+    }
+    report(hijacked, fixes, detail)
+}
+
+/// Check after TUN was turned on and tell the user what was fixed
+pub async fn check_after_tun_enabled() {
+    tokio::time::sleep(TUN_SETTLE).await;
+    let report = check(true).await;
+    let msg = serde_json::to_string(&report).unwrap_or_default();
+    match report.status {
+        DnsHijackStatus::Repaired => handle::Handle::notice_message("dns_hijack::repaired", msg),
+        DnsHijackStatus::Broken => handle::Handle::notice_message("dns_hijack::broken", msg),
+        DnsHijackStatus::Ok | DnsHijackStatus::Skipped => {}
+    }
+}
+
+fn report(hijacked: bool, fixes: Vec<DnsFix>, detail: String) -> DnsHijackReport {
+    let status = match (hijacked, !fixes.is_empty()) {
+        (true, true) => DnsHijackStatus::Repaired,
+        (true, false) => DnsHijackStatus::Ok,
+        (false, _) => DnsHijackStatus::Broken,
+    };
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "DNS hijack check: {:?} ({})",
+        status,
+        detail
+    );
+    DnsHijackReport {
+        status,
+        fixes,
+        detail,
+    }
+}
+
+/// The fake ip range of the running config, if TUN hijacks dns in fake-ip mode
+fn fake_ip_range() -> Option<(Ipv4Addr, u8)> {
+    let tun_mode = Config::verge().latest().enable_tun_mode.unwrap_or(false);
+    if !tun_mode {
+        return None;
+    }
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    let config = runtime.config.as_ref()?;
+    let dns = config.get("dns")?;
+    let tun = config.get("tun")?;
+    let enabled = |v: &Value| v.get("enable").and_then(Value::as_bool).unwrap_or(false);
+    let hijacks = tun
+        .get("dns-hijack")
+        .and_then(Value::as_sequence)
+        .is_some_and(|s| !s.is_empty());
+    let fake_ip = dns
+        .get("enhanced-mode")
+        .and_then(Value::as_str)
+        .is_some_and(|m| m == "fake-ip");
+    if !enabled(dns) || !enabled(tun) || !hijacks || !fake_ip {
+        return None;
+    }
+    let range = dns
+        .get("fake-ip-range")
+        .and_then(Value::as_str)
+        .unwrap_or("198.18.0.1/16");
+    parse_range(range)
+}
+
+fn parse_range(range: &str) -> Option<(Ipv4Addr, u8)> {
+    let (addr, len) = range.split_once('/')?;
+    let len = len.parse::<u8>().ok().filter(|len| *len <= 32)?;
+    Some((addr.parse().ok()?, len))
+}
+
+fn in_range(ip: Ipv4Addr, (net, len): (Ipv4Addr, u8)) -> bool {
+    let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+    u32::from(ip) & mask == u32::from(net) & mask
+}
+
+/// Resolve a fresh canary name through the system resolver
+async fn probe(range: (Ipv4Addr, u8)) -> (bool, String) {
+    if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+        return (false, "core is not running".into());
+    }
+    let name = format!("{}.{CANARY_DOMAIN}", help::get_uid("").to_lowercase());
+    let lookup = tokio::net::lookup_host((name.as_str(), 80));
+    match tokio::time::timeout(LOOKUP_TIMEOUT, lookup).await {
+        Ok(Ok(addrs)) => {
+            let ips = addrs.map(|a| a.ip()).collect::<Vec<_>>();
+            let hijacked = ips
+                .iter()
+                .any(|ip| matches!(ip, IpAddr::V4(v4) if in_range(*v4, range)));
+            let ips = ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+            (hijacked, format!("{name} → {}", ips.join(", ")))
+        }
+        Ok(Err(err)) => (false, format!("{name}: {err}")),
+        Err(_) => (false, format!("{name}: timed out")),
+    }
+}
+
+/// Drop the cached answers of the system resolver
+pub async fn flush_cache() -> bool {
+    #[cfg(windows)]
+    let commands: &[&[&str]] = &[&["ipconfig", "/flushdns"]];
+    #[cfg(target_os = "macos")]
+    let commands: &[&[&str]] = &[
+        &["dscacheutil", "-flushcache"],
+        &["killall", "-HUP", "mDNSResponder"],
+    ];
+    #[cfg(target_os = "linux")]
+    let commands: &[&[&str]] = &[&["resolvectl", "flush-caches"]];
+
+    let mut flushed = false;
+    for command in commands {
+        let mut cmd = tokio::process::Command::new(command[0]);
+        cmd.args(&command[1..]);
+        #[cfg(windows)]
+        {
+            #[allow(unused_imports)] // creation_flags必须
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        match cmd.output().await {
+            Ok(output) if output.status.success() => flushed = true,
+            Ok(output) => logging!(
+                debug,
+                Type::Network,
+                true,
+                "{:?} failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(err) => logging!(debug, Type::Network, true, "{:?} failed: {}", command, err),
+        }
+    }
+    flushed
+}
+
+#[test]
+fn test_fake_ip_range() {
+    let range = parse_range("198.18.0.1/16").unwrap();
+    assert!(in_range(Ipv4Addr::new(198, 18, 3, 7), range));
+    assert!(!in_range(Ipv4Addr::new(93, 184, 216, 34), range));
+    assert_eq!(parse_range("198.18.0.1/40"), None);
+    assert!(in_range(
+        Ipv4Addr::new(1, 2, 3, 4),
+        parse_range("0.0.0.0/0").unwrap()
+    ));
+}
@@ -4,8 +4,9 @@ use tokio::sync::{mpsc, oneshot};
 use tokio::time::{sleep, timeout, Duration};
 
 use crate::config::{Config, IVerge};
-use crate::core::{async_proxy_query::AsyncProxyQuery, handle};
+use crate::core::{async_proxy_query::AsyncProxyQuery, handle, proxy_watcher, sysopt};
 use crate::logging_error;
+use crate::process::AsyncHandler;
 use crate::utils::logging::Type;
 use once_cell::sync::Lazy;
 use sysproxy::{Autoproxy, Sysproxy};
@@ -15,7 +16,6 @@ pub enum ProxyEvent {
     /// 配置变更事件
     ConfigChanged,
     /// 强制检查代理状态
-    #[allow(dead_code)]
     ForceCheck,
     /// 启用系统代理
     #[allow(dead_code)]
@@ -159,7 +159,6 @@ impl EventDrivenProxyManager {
     }
 
     /// Force check proxy status
-    #[allow(dead_code)]
     pub fn force_check(&self) {
         self.send_event(ProxyEvent::ForceCheck);
     }
@@ -312,6 +311,7 @@ impl EventDrivenProxyManager {
             return;
         }
 
+        let previous = state.read().auto_proxy.clone();
         let current = Self::get_auto_proxy_with_timeout().await;
         let expected = Self::get_expected_pac_config();
 
@@ -323,6 +323,12 @@ impl EventDrivenProxyManager {
             log::info!(target: "app", "PAC proxy setting abnormal, recovering...");
             Self::restore_pac_proxy(&expected.url).await;
 
+            // 上次检查时还是我们的设置，说明是被其他程序改掉的
+            if previous.enable && previous.url == expected.url {
+                let found = current.enable.then_some(current.url);
+                AsyncHandler::spawn(move || proxy_watcher::report_tampering(found));
+            }
+
             sleep(Duration::from_millis(500)).await;
             let restored = Self::get_auto_proxy_with_timeout().await;
 
@@ -339,6 +345,7 @@ impl EventDrivenProxyManager {
             return;
         }
 
+        let previous = state.read().sys_proxy.clone();
         let current = Self::get_sys_proxy_with_timeout().await;
         let expected = Self::get_expected_sys_proxy();
 
@@ -350,6 +357,14 @@ impl EventDrivenProxyManager {
             log::info!(target: "app", "System proxy setting abnormal, recovering...");
             Self::restore_sys_proxy(&expected).await;
 
+            // 上次检查时还是我们的设置，说明是被其他程序改掉的
+            if previous.enable && previous.host == expected.host && previous.port == expected.port {
+                let found = current
+                    .enable
+                    .then(|| format!("{}:{}", current.host, current.port));
+                AsyncHandler::spawn(move || proxy_watcher::report_tampering(found));
+            }
+
             sleep(Duration::from_millis(500)).await;
             let restored = Self::get_sys_proxy_with_timeout().await;
 
@@ -458,7 +473,8 @@ impl EventDrivenProxyManager {
         let verge_config = Config::verge();
         let verge = verge_config.latest();
         ProxyConfig {
-            sys_enabled: verge.enable_system_proxy.unwrap_or(false),
+            sys_enabled: verge.enable_system_proxy.unwrap_or(false)
+                && !sysopt::Sysopt::global().is_suspended(),
             pac_enabled: verge.proxy_auto_config.unwrap_or(false),
             guard_enabled: verge.enable_proxy_guard.unwrap_or(false),
         }
@@ -500,28 +516,7 @@ impl EventDrivenProxyManager {
     }
 
     fn get_bypass_config() -> String {
-        let verge_config = Config::verge();
-        let verge = verge_config.latest();
-        let use_default = verge.use_default_bypass.unwrap_or(true);
-        let custom_bypass = verge.system_proxy_bypass.clone().unwrap_or_default();
-
-        #[cfg(target_os = "windows")]
-        let default_bypass = "localhost;127.*;192.168.*;10.*;172.16.*;172.17.*;172.18.*;172.19.*;172.20.*;172.21.*;172.22.*;172.23.*;172.24.*;172.25.*;172.26.*;172.27.*;172.28.*;172.29.*;172.30.*;172.31.*;<local>";
-
-        #[cfg(target_os = "linux")]
-        let default_bypass =
-            "localhost,127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,::1";
-
-        #[cfg(target_os = "macos")]
-        let default_bypass = "127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,localhost,*.local,*.crashlytics.com,<local>";
-
-        if custom_bypass.is_empty() {
-            default_bypass.to_string()
-        } else if use_default {
-            format!("{default_bypass},{custom_bypass}")
-        } else {
-            custom_bypass
-        }
+        sysopt::get_bypass()
     }
 
     async fn restore_pac_proxy(expected_url: &str) {
@@ -0,0 +1,107 @@
+use crate::{
+    config::{Config, IVerge},
+    core::{handle, sysopt::Sysopt, tray},
+    logging, logging_error,
+    utils::logging::Type,
+};
+use anyhow::Result;
+
+/// Called when the core stops answering or exits
+///
+/// A system proxy pointing at a port nobody listens on breaks all browsing,
+/// so it is suspended until the core answers again. The user's setting is
+/// kept. TUN is turned off for good, since the core may be failing because
+/// of it.
+pub async fn core_down(reason: &str) {
+    let (sysproxy, disable_sysproxy, tun, disable_tun) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.enable_system_proxy.unwrap_or(false),
+            verge.core_down_disable_sysproxy.unwrap_or(true),
+            verge.enable_tun_mode.unwrap_or(false),
+            verge.core_down_disable_tun.unwrap_or(false),
+        )
+    };
+
+    if sysproxy && disable_sysproxy {
+        match Sysopt::global().suspend_sysproxy().await {
+            Ok(true) => {
+                logging!(
+                    warn,
+                    Type::System,
+                    true,
+                    "Core is down, system proxy suspended: {}",
+                    reason
+                );
+                handle::Handle::notice_message("core_failsafe::sysproxy_suspended", reason);
+            }
+            Ok(false) => {}
+            Err(err) => logging!(
+                error,
+                Type::System,
+                true,
+                "Failed to suspend system proxy: {}",
+                err
+            ),
+        }
+    }
+
+    if tun && disable_tun {
+        match disable_tun_mode() {
+            Ok(_) => {
+                logging!(
+                    warn,
+                    Type::System,
+                    true,
+                    "Core is down, TUN mode disabled: {}",
+                    reason
+                );
+                handle::Handle::notice_message("core_failsafe::tun_disabled", reason);
+            }
+            Err(err) => logging!(
+                error,
+                Type::System,
+                true,
+                "Failed to disable TUN mode: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Called when the core answers again, restores a suspended system proxy
+pub async fn core_up() {
+    match Sysopt::global().resume_sysproxy().await {
+        Ok(true) => {
+            logging!(
+                info,
+                Type::System,
+                true,
+                "Core is up, system proxy restored"
+            );
+            handle::Handle::notice_message("core_failsafe::sysproxy_restored", "");
+        }
+        Ok(false) => {}
+        Err(err) => logging!(
+            error,
+            Type::System,
+            true,
+            "Failed to restore system proxy: {}",
+            err
+        ),
+    }
+}
+
+/// Save `enable_tun_mode: false`, the next start runs without TUN
+fn disable_tun_mode() -> Result<()> {
+    Config::verge().draft().patch_config(IVerge {
+        enable_tun_mode: Some(false),
+        ..IVerge::default()
+    });
+    Config::verge().apply();
+    Config::verge().data().save_file()?;
+    handle::Handle::refresh_verge();
+    logging_error!(Type::Tray, true, tray::Tray::global().update_part());
+    Ok(())
+}
@@ -0,0 +1,276 @@
+//! The inbound firewall rule letting LAN devices reach the proxy
+//!
+//! Windows asks whether to allow a program the first time it listens on the LAN, and a
+//! dismissed prompt silently blocks it from then on. While `allow-lan` is on, a rule scoped
+//! to the core binary and its listener ports is kept instead, and removed again when
+//! `allow-lan` is turned off. The uninstaller removes it too.
+//!
+//! Other platforms have no firewall on by default or ask the user themselves, there this
+//! module does nothing.
+
+use crate::{config::Config, core::ports, logging, utils::logging::Type};
+use anyhow::Result;
+use serde_yaml::Value;
+use tokio::sync::Mutex;
+
+/// Name of the inbound rule, also removed by the uninstaller
+#[cfg(windows)]
+const RULE_NAME: &str = "Koala Clash LAN";
+
+/// serializes the updates, each may wait on a UAC prompt
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Keep the rule in line with `allow-lan` and the listener ports
+pub async fn update() {
+    let _guard = LOCK.lock().await;
+    let allow_lan = Config::clash()
+        .latest()
+        .0
+        .get("allow-lan")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let ports = if allow_lan {
+        ports::listener_ports()
+    } else {
+        vec![]
+    };
+    if let Err(err) = set_rule(&ports).await {
+        logging!(
+            warn,
+            Type::Network,
+            true,
+            "Failed to update the firewall rule for LAN access: {}",
+            err
+        );
+    }
+}
+
+/// Whether the rule lets the port in, none where the firewall isn't managed
+#[cfg(windows)]
+pub async fn is_open(port: u16) -> Option<bool> {
+    let rule = tokio::task::spawn_blocking(com::read_rule).await.ok()?;
+    Some(rule.is_ok_and(|rule| rule.is_some_and(|(ports, _)| rule_has_port(&ports, port))))
+}
+
+#[cfg(not(windows))]
+pub async fn is_open(_port: u16) -> Option<bool> {
+    None
+}
+
+/// Replace the rule with one for the core and `ports`, or remove it when empty
+///
+/// Changing the firewall needs administrator rights. Without them the service
+/// changes it, and when the service isn't installed the same COM calls run
+/// behind a single UAC prompt.
+#[cfg(windows)]
+async fn set_rule(ports: &[u16]) -> Result<()> {
+    use crate::core::{service, CoreManager};
+    use deelevate::{PrivilegeLevel, Token};
+
+    let program = {
+        let clash_core = Config::verge().latest().get_valid_clash_core();
+        CoreManager::core_bin_path(&clash_core)?
+            .to_string_lossy()
+            .into_owned()
+    };
+    let current = tokio::task::spawn_blocking(com::read_rule).await??;
+    let up_to_date = match &current {
+        None => ports.is_empty(),
+        Some((rule_ports, rule_program)) => {
+            !ports.is_empty()
+                && ports.iter().all(|port| rule_has_port(rule_ports, *port))
+                && rule_program.eq_ignore_ascii_case(&program)
+        }
+    };
+    if up_to_date {
+        return Ok(());
+    }
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "{} the firewall rule for LAN access",
+        if ports.is_empty() {
+            "Removing"
+        } else {
+            "Updating"
+        }
+    );
+
+    let elevated =
+        Token::with_current_process()?.privilege_level()? != PrivilegeLevel::NotPrivileged;
+    if elevated {
+        let ports = ports.to_vec();
+        return tokio::task::spawn_blocking(move || com::write_rule(&program, &ports)).await?;
+    }
+    let Err(err) = service::set_firewall_rule_by_service(RULE_NAME, &program, ports).await else {
+        return Ok(());
+    };
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Service can't change the firewall, asking for administrator rights: {}",
+        err
+    );
+    let script = com::write_rule_script(&program, ports);
+    tokio::task::spawn_blocking(move || crate::utils::elevate::run_powershell(&script))
+        .await?
+        .map_err(|err| anyhow::anyhow!("failed to update the firewall rule: {err}"))
+}
+
+#[cfg(not(windows))]
+async fn set_rule(_ports: &[u16]) -> Result<()> {
+    Ok(())
+}
+
+/// The rule through the Windows Firewall COM API, `INetFwPolicy2`
+///
+/// There is one rule for TCP and one for UDP, both with the same name, ports and program.
+#[cfg(windows)]
+mod com {
+    use super::RULE_NAME;
+    use anyhow::Result;
+    use windows::{
+        core::BSTR,
+        Win32::{
+            Foundation::VARIANT_TRUE,
+            NetworkManagement::WindowsFirewall::{
+                INetFwPolicy2, INetFwRule, NetFwPolicy2, NetFwRule, NET_FW_ACTION_ALLOW,
+                NET_FW_IP_PROTOCOL_TCP, NET_FW_IP_PROTOCOL_UDP, NET_FW_PROFILE2_DOMAIN,
+                NET_FW_PROFILE2_PRIVATE, NET_FW_RULE_DIR_IN,
+            },
+            System::Com::{
+                CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+                COINIT_MULTITHREADED,
+            },
+        },
+    };
+
+    /// at most this many rules with our name are removed
+    const MAX_RULES: usize = 16;
+
+    /// COM stays initialized on this thread while it lives
+    struct Com;
+
+    impl Com {
+        fn init() -> Result<Self> {
+            unsafe { CoInitializeEx(None, COINIT_MULTITHREADED).ok()? };
+            Ok(Com)
+        }
+    }
+
+    impl Drop for Com {
+        fn drop(&mut self) {
+            unsafe { CoUninitialize() };
+        }
+    }
+
+    fn policy() -> Result<INetFwPolicy2> {
+        Ok(unsafe { CoCreateInstance(&NetFwPolicy2, None, CLSCTX_INPROC_SERVER)? })
+    }
+
+    /// The local ports, `7897,7898`, and the program of the rule, none when it doesn't exist
+    pub fn read_rule() -> Result<Option<(String, String)>> {
+        let _com = Com::init()?;
+        let rules = unsafe { policy()?.Rules()? };
+        let Ok(rule) = (unsafe { rules.Item(&BSTR::from(RULE_NAME)) }) else {
+            return Ok(None);
+        };
+        let (ports, program) = unsafe { (rule.LocalPorts()?, rule.ApplicationName()?) };
+        Ok(Some((ports.to_string(), program.to_string())))
+    }
+
+    /// Needs administrator rights
+    pub fn write_rule(program: &str, ports: &[u16]) -> Result<()> {
+        let _com = Com::init()?;
+        let rules = unsafe { policy()?.Rules()? };
+        let name = BSTR::from(RULE_NAME);
+        // Remove 每次只删除一条同名规则
+        for _ in 0..MAX_RULES {
+            if unsafe { rules.Item(&name) }.is_err() {
+                break;
+            }
+            unsafe { rules.Remove(&name)? };
+        }
+        if ports.is_empty() {
+            return Ok(());
+        }
+
+        let program = BSTR::from(program);
+        let local_ports = BSTR::from(join_ports(ports));
+        for protocol in [NET_FW_IP_PROTOCOL_TCP, NET_FW_IP_PROTOCOL_UDP] {
+            unsafe {
+                let rule: INetFwRule = CoCreateInstance(&NetFwRule, None, CLSCTX_INPROC_SERVER)?;
+                rule.SetName(&name)?;
+                rule.SetApplicationName(&program)?;
+                rule.SetProtocol(protocol.0)?;
+                rule.SetLocalPorts(&local_ports)?;
+                rule.SetDirection(NET_FW_RULE_DIR_IN)?;
+                rule.SetAction(NET_FW_ACTION_ALLOW)?;
+                rule.SetProfiles(NET_FW_PROFILE2_PRIVATE.0 | NET_FW_PROFILE2_DOMAIN.0)?;
+                rule.SetEnabled(VARIANT_TRUE)?;
+                rules.Add(&rule)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `write_rule` as a PowerShell script, for running it elevated
+    pub fn write_rule_script(program: &str, ports: &[u16]) -> String {
+        use crate::utils::elevate::quote;
+
+        let mut script = format!(
+            "$rules = (New-Object -ComObject HNetCfg.FwPolicy2).Rules\r\n\
+             $name = {}\r\n\
+             foreach ($i in 1..{MAX_RULES}) {{ if (-not ($rules | Where-Object Name -eq $name)) {{ break }}; $rules.Remove($name) }}",
+            quote(RULE_NAME)
+        );
+        if !ports.is_empty() {
+            script.push_str(&format!(
+                "\r\nforeach ($protocol in {}, {}) {{\r\n\
+                 $rule = New-Object -ComObject HNetCfg.FWRule\r\n\
+                 $rule.Name = $name\r\n\
+                 $rule.ApplicationName = {}\r\n\
+                 $rule.Protocol = $protocol\r\n\
+                 $rule.LocalPorts = '{}'\r\n\
+                 $rule.Direction = {}\r\n\
+                 $rule.Action = {}\r\n\
+                 $rule.Profiles = {}\r\n\
+                 $rule.Enabled = $true\r\n\
+                 $rules.Add($rule)\r\n}}",
+                NET_FW_IP_PROTOCOL_TCP.0,
+                NET_FW_IP_PROTOCOL_UDP.0,
+                quote(program),
+                join_ports(ports),
+                NET_FW_RULE_DIR_IN.0,
+                NET_FW_ACTION_ALLOW.0,
+                NET_FW_PROFILE2_PRIVATE.0 | NET_FW_PROFILE2_DOMAIN.0,
+            ));
+        }
+        script
+    }
+
+    fn join_ports(ports: &[u16]) -> String {
+        ports
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Whether the local ports of the rule, `7897,7898`, include the port
+#[cfg(any(windows, test))]
+fn rule_has_port(ports: &str, port: u16) -> bool {
+    let port = port.to_string();
+    ports.split(',').any(|p| p.trim() == port)
+}
+
+#[test]
+fn test_rule_has_port() {
+    assert!(rule_has_port("7897,7898", 7897));
+    assert!(rule_has_port("7897, 7898", 7898));
+    assert!(!rule_has_port("7897,7898", 7890));
+    assert!(!rule_has_port("", 7897));
+}
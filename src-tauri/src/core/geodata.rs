@@ -0,0 +1,530 @@
+use crate::{
+    config::{Config, IVergeGeoMirror},
+    core::{handle, CoreManager, RunningMode},
+    logging,
+    module::mihomo::MihomoManager,
+    process::{AsyncHandler, StartOnce},
+    utils::{
+        dirs,
+        logging::Type,
+        metered,
+        network::{NetworkManager, ProxyType},
+    },
+};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_yaml::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
+
+/// how often the loop checks whether an update is due
+const CHECK_POLL: Duration = Duration::from_secs(10 * 60);
+
+/// timeout of one download, in seconds
+const DOWNLOAD_TIMEOUT: u64 = 120;
+
+/// where MaxMind databases keep their metadata, searched for the marker
+const MMDB_METADATA_WINDOW: usize = 128 * 1024;
+
+const MMDB_METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeoKind {
+    Dat,
+    Mmdb,
+}
+
+struct GeoFile {
+    name: &'static str,
+    /// key in `geox-url`
+    key: &'static str,
+    kind: GeoKind,
+}
+
+/// the databases mihomo reads from its home directory
+const GEO_FILES: [GeoFile; 3] = [
+    GeoFile {
+        name: "geoip.dat",
+        key: "geoip",
+        kind: GeoKind::Dat,
+    },
+    GeoFile {
+        name: "geosite.dat",
+        key: "geosite",
+        kind: GeoKind::Dat,
+    },
+    GeoFile {
+        name: "country.mmdb",
+        key: "mmdb",
+        kind: GeoKind::Mmdb,
+    },
+];
+
+/// used when no mirror is configured, the same files as the core's defaults
+const DEFAULT_MIRRORS: [(&str, &str); 2] = [
+    (
+        "GitHub",
+        "https://github.com/MetaCubeX/meta-rules-dat/releases/download/latest",
+    ),
+    (
+        "jsDelivr",
+        "https://cdn.jsdelivr.net/gh/MetaCubeX/meta-rules-dat@release",
+    ),
+];
+
+/// One place a database can be downloaded from
+#[derive(Debug, Clone, PartialEq)]
+struct GeoSource {
+    name: String,
+    url: String,
+    proxies: Vec<ProxyType>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoFileStatus {
+    pub name: String,
+    pub size: u64,
+    /// unix time the file was last replaced
+    pub updated: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GeoDataStatus {
+    /// the databases present in the core directory
+    pub files: Vec<GeoFileStatus>,
+    /// unix time of the last check
+    pub last_check: Option<i64>,
+    pub last_error: Option<String>,
+    pub updating: bool,
+}
+
+/// Keeps the geo databases of the core up to date
+///
+/// Only databases already in the core directory are updated, the core downloads the others
+/// itself the first time a rule needs them. Files are downloaded through the core when it is
+/// running, validated and swapped in with a rename, then the core reloads its config.
+pub struct GeoDataManager {
+    initialized: StartOnce,
+    updating: AtomicBool,
+    status: Mutex<GeoDataStatus>,
+}
+
+impl GeoDataManager {
+    pub fn global() -> &'static GeoDataManager {
+        static GEODATA: OnceCell<GeoDataManager> = OnceCell::new();
+
+        GEODATA.get_or_init(|| GeoDataManager {
+            initialized: StartOnce::new(),
+            updating: AtomicBool::new(false),
+            status: Mutex::new(GeoDataStatus::default()),
+        })
+    }
+
+    /// Start the schedule loop, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Core, true, "Starting geodata update schedule");
+        AsyncHandler::spawn(move || async move {
+            loop {
+                tokio::time::sleep(CHECK_POLL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if !self.is_due() {
+                    continue;
+                }
+                // 仍然到期，网络不再计费后的下一轮会补上
+                if metered::should_defer_updates().await {
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "Deferring geodata update on metered network"
+                    );
+                    continue;
+                }
+
+                match self.update().await {
+                    Ok(true) => handle::Handle::notice_message("geodata::updated", ""),
+                    Ok(false) => {}
+                    Err(err) => {
+                        logging!(warn, Type::Core, true, "Geodata update failed: {}", err)
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> GeoDataStatus {
+        let mut status = self.status.lock().clone();
+        status.files = dirs::app_home_dir()
+            .map(|dir| {
+                GEO_FILES
+                    .iter()
+                    .filter_map(|file| file_status(&find_file(&dir, file.name)?))
+                    .collect()
+            })
+            .unwrap_or_default();
+        status.updating = self.updating.load(Ordering::SeqCst);
+        status
+    }
+
+    /// Due when the interval passed since both the last check and the oldest file
+    fn is_due(&self) -> bool {
+        let hours = Config::verge()
+            .latest()
+            .geodata_update_interval
+            .unwrap_or(0);
+        if hours == 0 {
+            return false;
+        }
+        let interval = hours as i64 * 3600;
+        let now = chrono::Local::now().timestamp();
+
+        let status = self.status();
+        let checked = status.last_check.is_some_and(|last| now - last < interval);
+        let fresh = status
+            .files
+            .iter()
+            .filter_map(|file| file.updated)
+            .min()
+            .is_some_and(|oldest| now - oldest < interval);
+        !checked && !fresh
+    }
+
+    /// Check every database and replace the outdated ones
+    /// Returns whether any file was replaced
+    pub async fn update(&self) -> Result<bool> {
+        if self.updating.swap(true, Ordering::SeqCst) {
+            bail!("a geodata update is already running");
+        }
+        let result = update_files().await;
+        self.updating.store(false, Ordering::SeqCst);
+
+        let mut status = self.status.lock();
+        status.last_check = Some(chrono::Local::now().timestamp());
+        status.last_error = result.as_ref().err().map(|err| err.to_string());
+        result
+    }
+}
+
+async fn update_files() -> Result<bool> {
+    let home_dir = dirs::app_home_dir()?;
+    let mut changed = false;
+    let mut first_err = None;
+
+    for file in &GEO_FILES {
+        let Some(path) = find_file(&home_dir, file.name) else {
+            continue;
+        };
+        // 一个文件失败不影响其他文件，已替换的文件仍然要让内核重新加载
+        match update_file(file, &path).await {
+            Ok(true) => {
+                logging!(info, Type::Core, true, "Updated geodata file {}", file.name);
+                changed = true;
+            }
+            Ok(false) => {}
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Failed to update geodata file {}: {}",
+                    file.name,
+                    err
+                );
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+
+    if changed && CoreManager::global().get_running_mode().await != RunningMode::NotRunning {
+        MihomoManager::global()
+            .update_geo_databases()
+            .await
+            .map_err(|err| anyhow!("failed to reload geodata: {err}"))?;
+    }
+    match first_err {
+        Some(err) => Err(err),
+        None => Ok(changed),
+    }
+}
+
+/// Replace one database when the source has a different one, returns whether it was replaced
+async fn update_file(file: &GeoFile, path: &Path) -> Result<bool> {
+    let local_hash = hex::encode(Sha256::digest(fs::read(path)?));
+    let Some(data) = fetch_file(file, &local_hash).await? else {
+        return Ok(false);
+    };
+
+    // 同一目录下重命名是原子操作，内核不会读到写了一半的文件
+    let new_path = path.with_extension("new");
+    fs::write(&new_path, &data)?;
+    fs::rename(&new_path, path)?;
+    Ok(true)
+}
+
+/// mihomo matches the file names case-insensitively
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .eq_ignore_ascii_case(name)
+        })
+        .map(|entry| entry.path())
+}
+
+fn file_status(path: &Path) -> Option<GeoFileStatus> {
+    let meta = fs::metadata(path).ok()?;
+    let updated = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|since| since.as_secs() as i64);
+    Some(GeoFileStatus {
+        name: path.file_name()?.to_string_lossy().into(),
+        size: meta.len(),
+        updated,
+    })
+}
+
+/// Try the sources in order until one serves a valid file
+/// Returns `None` when the file is already up to date
+async fn fetch_file(file: &GeoFile, local_hash: &str) -> Result<Option<Vec<u8>>> {
+    let mut last_err = None;
+    for source in sources(file) {
+        match fetch_from(file, &source, local_hash).await {
+            Ok(data) => return Ok(data),
+            Err(err) => {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Failed to fetch {} from {}: {}",
+                    file.name,
+                    source.name,
+                    err
+                );
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow!("no source for {}", file.name)))
+}
+
+async fn fetch_from(
+    file: &GeoFile,
+    source: &GeoSource,
+    local_hash: &str,
+) -> Result<Option<Vec<u8>>> {
+    let remote_hash = fetch_checksum(&source.url, &source.proxies).await;
+    if remote_hash.as_deref() == Some(local_hash) {
+        return Ok(None);
+    }
+
+    let data = download(&source.url, &source.proxies).await?;
+    validate(file.kind, &data)?;
+    let hash = hex::encode(Sha256::digest(&data));
+    if remote_hash.is_some_and(|expected| expected != hash) {
+        bail!("checksum mismatch");
+    }
+    Ok((hash != local_hash).then_some(data))
+}
+
+/// The `geox-url` of the profile first, then the configured mirrors or the defaults
+fn sources(file: &GeoFile) -> Vec<GeoSource> {
+    let mirrors = Config::verge()
+        .latest()
+        .geodata_mirrors
+        .clone()
+        .filter(|mirrors| !mirrors.is_empty());
+
+    let mut sources = Vec::new();
+    if let Some(url) = geox_url(file.key) {
+        sources.push(GeoSource {
+            name: "geox-url".into(),
+            url,
+            proxies: proxy_types(None),
+        });
+    }
+    match mirrors {
+        Some(mirrors) => sources.extend(
+            mirrors
+                .iter()
+                .filter_map(|mirror| mirror_source(mirror, file.name)),
+        ),
+        None => sources.extend(DEFAULT_MIRRORS.iter().map(|(name, url)| GeoSource {
+            name: name.to_string(),
+            url: mirror_url(url, file.name),
+            proxies: proxy_types(None),
+        })),
+    }
+    sources.dedup_by(|a, b| a.url == b.url);
+    sources
+}
+
+fn mirror_source(mirror: &IVergeGeoMirror, file_name: &str) -> Option<GeoSource> {
+    if !mirror.enable.unwrap_or(true) {
+        return None;
+    }
+    let url = mirror.url.as_deref().filter(|url| !url.trim().is_empty())?;
+    Some(GeoSource {
+        name: mirror.name.clone().unwrap_or_else(|| url.to_string()),
+        url: mirror_url(url.trim(), file_name),
+        proxies: proxy_types(mirror.proxy.as_deref()),
+    })
+}
+
+/// `{file}` in the url is replaced by the file name, otherwise the name is appended
+fn mirror_url(url: &str, file_name: &str) -> String {
+    if url.contains("{file}") {
+        url.replace("{file}", file_name)
+    } else {
+        format!("{}/{file_name}", url.trim_end_matches('/'))
+    }
+}
+
+/// `core`, `direct`, `system` or a proxy url, by default through the core then directly
+fn proxy_types(proxy: Option<&str>) -> Vec<ProxyType> {
+    match proxy.map(str::trim).unwrap_or_default() {
+        "" | "auto" => vec![ProxyType::Localhost, ProxyType::None],
+        "core" => vec![ProxyType::Localhost],
+        "direct" => vec![ProxyType::None],
+        "system" => vec![ProxyType::System],
+        url => vec![ProxyType::Custom(url.to_string())],
+    }
+}
+
+/// The url set by the profile in `geox-url`
+fn geox_url(key: &str) -> Option<String> {
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    match runtime.config.as_ref()?.get("geox-url")?.get(key)? {
+        Value::String(url) if !url.is_empty() => Some(url.clone()),
+        _ => None,
+    }
+}
+
+/// meta-rules-dat publishes `<file>.sha256sum` next to each file
+async fn fetch_checksum(url: &str, proxies: &[ProxyType]) -> Option<String> {
+    let resp = get(&format!("{url}.sha256sum"), proxies, Some(30))
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let text = resp.text().await.ok()?;
+    let hash = text.split_whitespace().next()?.to_lowercase();
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then_some(hash)
+}
+
+async fn get(url: &str, proxies: &[ProxyType], timeout: Option<u64>) -> Result<reqwest::Response> {
+    let mut last_err = None;
+    for proxy_type in proxies {
+        match NetworkManager::global()
+            .get_with_interrupt(url, proxy_type.clone(), timeout, None, false, false, None)
+            .await
+        {
+            Ok(resp) => return Ok(resp),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow!("failed to fetch {url}")))
+}
+
+async fn download(url: &str, proxies: &[ProxyType]) -> Result<Vec<u8>> {
+    let resp = get(url, proxies, Some(DOWNLOAD_TIMEOUT)).await?;
+    if !resp.status().is_success() {
+        bail!("failed to download {url}: {}", resp.status());
+    }
+    Ok(resp.bytes().await?.to_vec())
+}
+
+/// A cheap structural check so an error page or a truncated file is never swapped in
+fn validate(kind: GeoKind, data: &[u8]) -> Result<()> {
+    match kind {
+        // a protobuf list whose first field is a length-delimited entry
+        GeoKind::Dat => {
+            if data.len() < 1024 || data[0] != 0x0a {
+                bail!("not a geodata file");
+            }
+        }
+        GeoKind::Mmdb => {
+            let tail = &data[data.len().saturating_sub(MMDB_METADATA_WINDOW)..];
+            if !tail
+                .windows(MMDB_METADATA_MARKER.len())
+                .any(|window| window == MMDB_METADATA_MARKER)
+            {
+                bail!("not a MaxMind database");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_validate() {
+    let mut dat = vec![0x0a; 2048];
+    assert!(validate(GeoKind::Dat, &dat).is_ok());
+    dat[0] = b'<';
+    assert!(validate(GeoKind::Dat, &dat).is_err());
+    assert!(validate(GeoKind::Dat, &[0x0a; 16]).is_err());
+
+    let mut mmdb = vec![0u8; 4096];
+    assert!(validate(GeoKind::Mmdb, &mmdb).is_err());
+    mmdb.extend_from_slice(MMDB_METADATA_MARKER);
+    mmdb.extend_from_slice(&[0u8; 64]);
+    assert!(validate(GeoKind::Mmdb, &mmdb).is_ok());
+    assert!(validate(GeoKind::Mmdb, &[]).is_err());
+}
+
+#[test]
+fn test_mirror_source() {
+    assert_eq!(
+        mirror_url("https://example.com/geo/", "geoip.dat"),
+        "https://example.com/geo/geoip.dat"
+    );
+    assert_eq!(
+        mirror_url("https://example.com/{file}?raw=1", "country.mmdb"),
+        "https://example.com/country.mmdb?raw=1"
+    );
+
+    let mirror = IVergeGeoMirror {
+        name: Some("self-hosted".into()),
+        url: Some(" https://geo.example.com ".into()),
+        proxy: Some("direct".into()),
+        enable: None,
+    };
+    let source = mirror_source(&mirror, "geosite.dat").unwrap();
+    assert_eq!(source.url, "https://geo.example.com/geosite.dat");
+    assert_eq!(source.proxies, vec![ProxyType::None]);
+
+    let disabled = IVergeGeoMirror {
+        enable: Some(false),
+        ..mirror.clone()
+    };
+    assert!(mirror_source(&disabled, "geosite.dat").is_none());
+    assert!(mirror_source(&IVergeGeoMirror::default(), "geosite.dat").is_none());
+
+    assert_eq!(
+        proxy_types(Some("socks5://127.0.0.1:1080")),
+        vec![ProxyType::Custom("socks5://127.0.0.1:1080".into())]
+    );
+    assert_eq!(proxy_types(None).len(), 2);
+}
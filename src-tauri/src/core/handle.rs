@@ -10,6 +10,7 @@ use std::{
 };
 use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 
+use super::notifications;
 use crate::{logging, utils::logging::Type};
 
 /// 不同类型的前端通知
@@ -23,6 +24,8 @@ enum FrontendEvent {
     StartupCompleted,
     ProfileUpdateStarted { uid: String },
     ProfileUpdateCompleted { uid: String },
+    CoreTelemetry { sample: serde_json::Value },
+    ControllerStream { id: u32, data: Option<String> },
 }
 
 /// 事件发送统计和监控
@@ -145,6 +148,14 @@ impl NotificationSystem {
                                         FrontendEvent::ProfileUpdateCompleted { uid } => {
                                             ("profile-update-completed", Ok(serde_json::json!({ "uid": uid })))
                                         }
+                                        FrontendEvent::CoreTelemetry { sample } => {
+                                            ("verge://core-telemetry", Ok(sample))
+                                        }
+                                        FrontendEvent::ControllerStream { id, data } => {
+                                            let data =
+                                                serde_json::json!({ "id": id, "data": data });
+                                            ("verge://controller-stream", Ok(data))
+                                        }
                                     };
 
                                     if let Ok(payload) = payload_result {
@@ -412,11 +423,40 @@ impl Handle {
         }
     }
 
-    /// 通知前端显示消息队列
+    /// 推送内核资源采样
+    pub fn notify_core_telemetry(sample: serde_json::Value) {
+        let handle = Self::global();
+        if handle.is_exiting() {
+            return;
+        }
+
+        let system_opt = handle.notification_system.read();
+        if let Some(system) = system_opt.as_ref() {
+            system.send_event(FrontendEvent::CoreTelemetry { sample });
+        }
+    }
+
+    /// 推送通过套接字转发的流式接口的一行，`None` 表示连接已断开
+    pub fn notify_controller_stream(id: u32, data: Option<String>) {
+        let handle = Self::global();
+        if handle.is_exiting() {
+            return;
+        }
+
+        let system_opt = handle.notification_system.read();
+        if let Some(system) = system_opt.as_ref() {
+            system.send_event(FrontendEvent::ControllerStream { id, data });
+        }
+    }
+
+    /// 通知前端显示消息，重复的消息合并，免打扰时段内不显示
     pub fn notice_message<S: Into<String>, M: Into<String>>(status: S, msg: M) {
+        notifications::notify(status.into(), msg.into());
+    }
+
+    /// 通知前端显示消息队列
+    pub(crate) fn send_notice(status_str: String, msg_str: String) {
         let handle = Self::global();
-        let status_str = status.into();
-        let msg_str = msg.into();
 
         // If startup not completed, buffer messages (existing behavior)
         if !*handle.startup_completed.read() {
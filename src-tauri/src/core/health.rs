@@ -0,0 +1,184 @@
+use crate::{
+    config::Config,
+    core::{failsafe, handle, CoreManager, RunningMode},
+    logging,
+    module::mihomo::MihomoManager,
+    process::{AsyncHandler, StartOnce},
+    utils::logging::Type,
+};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// how long one ping may take
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// how often the controller is probed while waiting for the core to start
+const STARTUP_POLL: Duration = Duration::from_millis(200);
+
+/// the loop wakes up this often when the health check is turned off
+const DISABLED_POLL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CoreHealthStatus {
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// unix time of the last ping
+    pub last_check: Option<i64>,
+}
+
+/// Pings the core controller and reports when it stops answering
+///
+/// Failures are only reported after `core_health_failures` pings in a row,
+/// a recovery is reported once.
+pub struct CoreHealth {
+    initialized: StartOnce,
+    status: Mutex<CoreHealthStatus>,
+}
+
+impl CoreHealth {
+    pub fn global() -> &'static CoreHealth {
+        static HEALTH: OnceCell<CoreHealth> = OnceCell::new();
+
+        HEALTH.get_or_init(|| CoreHealth {
+            initialized: StartOnce::new(),
+            status: Mutex::new(CoreHealthStatus {
+                healthy: true,
+                ..Default::default()
+            }),
+        })
+    }
+
+    /// Start the ping loop, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Core, true, "Starting core health check");
+        AsyncHandler::spawn(move || async move {
+            loop {
+                let interval = Config::verge().latest().core_health_interval.unwrap_or(30);
+                if interval == 0 {
+                    tokio::time::sleep(DISABLED_POLL).await;
+                    continue;
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+                    continue;
+                }
+
+                let result = ping().await;
+                self.record(result);
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> CoreHealthStatus {
+        self.status.lock().clone()
+    }
+
+    /// Wait until the controller answers, gives up after `core_startup_timeout`
+    pub async fn wait_until_ready(&self) -> bool {
+        let timeout = Config::verge().latest().core_startup_timeout.unwrap_or(10);
+        let started = Instant::now();
+        loop {
+            match ping().await {
+                Ok(_) => {
+                    self.record(Ok(()));
+                    failsafe::core_up().await;
+                    logging!(
+                        info,
+                        Type::Core,
+                        true,
+                        "Core ready after {:?}",
+                        started.elapsed()
+                    );
+                    return true;
+                }
+                Err(err) if started.elapsed() >= Duration::from_secs(timeout) => {
+                    let msg = format!("core did not answer within {timeout}s: {err}");
+                    logging!(warn, Type::Core, true, "{}", msg);
+                    self.mark_unhealthy(msg);
+                    return false;
+                }
+                Err(_) => tokio::time::sleep(STARTUP_POLL).await,
+            }
+        }
+    }
+
+    fn record(&self, result: Result<(), String>) {
+        let threshold = Config::verge()
+            .latest()
+            .core_health_failures
+            .unwrap_or(3)
+            .max(1);
+        let mut status = self.status.lock();
+        status.last_check = Some(chrono::Local::now().timestamp());
+
+        match result {
+            Ok(_) => {
+                if !status.healthy {
+                    logging!(info, Type::Core, true, "Core is answering again");
+                    handle::Handle::notice_message("core_health::recovered", "");
+                    AsyncHandler::spawn(failsafe::core_up);
+                }
+                status.healthy = true;
+                status.consecutive_failures = 0;
+                status.last_error = None;
+            }
+            Err(err) => {
+                status.consecutive_failures += 1;
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Core health check failed ({}/{}): {}",
+                    status.consecutive_failures,
+                    threshold,
+                    err
+                );
+                status.last_error = Some(err.clone());
+                if status.healthy && status.consecutive_failures >= threshold {
+                    status.healthy = false;
+                    handle::Handle::notice_message("core_health::unhealthy", err.clone());
+                    AsyncHandler::spawn(move || async move { failsafe::core_down(&err).await });
+                }
+            }
+        }
+    }
+
+    fn mark_unhealthy(&self, err: String) {
+        let mut status = self.status.lock();
+        status.last_check = Some(chrono::Local::now().timestamp());
+        status.last_error = Some(err.clone());
+        if status.healthy {
+            status.healthy = false;
+            handle::Handle::notice_message("core_health::unhealthy", err.clone());
+            AsyncHandler::spawn(move || async move { failsafe::core_down(&err).await });
+        }
+    }
+}
+
+async fn ping() -> Result<(), String> {
+    let endpoint = Config::verge()
+        .latest()
+        .core_health_endpoint
+        .clone()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "/version".into());
+    let endpoint = if endpoint.starts_with('/') {
+        endpoint
+    } else {
+        format!("/{endpoint}")
+    };
+    MihomoManager::global().ping(&endpoint, PING_TIMEOUT).await
+}
@@ -0,0 +1,257 @@
+//! Integrity check of the core binary
+//!
+//! The SHA-256 of the core is compared before every launch with the one recorded in a hashes
+//! file signed with the app key, so a binary and a hash replaced together behind the app's
+//! back are still caught. Bundled cores are trusted as installed on their first launch with
+//! an app version, the bundling may sign them and change their bytes, so their hashes are
+//! recorded again after every app update. Cores installed later through the core updater, or
+//! trusted by the user after the core upgraded itself, must have been recorded when they were
+//! installed. A binary replaced e.g. by an antivirus quarantine or a broken download is not
+//! started.
+
+use crate::{
+    core::{handle, CoreManager},
+    logging,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{anyhow, bail, Result};
+use hmac::{Hmac, Mac};
+use parking_lot::{const_mutex, Mutex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+const HASHES_FILE: &str = "core_hashes.yaml";
+
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// serializes reading and writing the hashes file
+static LOCK: Mutex<()> = const_mutex(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CoreHashes {
+    /// app version the bundled hashes were recorded with
+    app_version: String,
+    /// core name to sha256 hex of the cores bundled with the app
+    #[serde(default)]
+    bundled: HashMap<String, String>,
+    /// core name to sha256 hex of the cores in the cores dir, kept across app updates
+    #[serde(default)]
+    installed: HashMap<String, String>,
+    /// HMAC-SHA256 of the fields above with the app key
+    #[serde(default)]
+    signature: String,
+}
+
+impl CoreHashes {
+    fn sign(&self) -> Result<String> {
+        let key = dirs::get_encryption_key()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key).map_err(|err| anyhow!("{err}"))?;
+        mac.update(self.app_version.as_bytes());
+        for (kind, hashes) in [("bundled", &self.bundled), ("installed", &self.installed)] {
+            for (name, hash) in hashes.iter().collect::<BTreeMap<_, _>>() {
+                mac.update(format!("\n{kind}:{name}={hash}").as_bytes());
+            }
+        }
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn hashes(&mut self, installed: bool) -> &mut HashMap<String, String> {
+        if installed {
+            &mut self.installed
+        } else {
+            &mut self.bundled
+        }
+    }
+}
+
+/// The recorded hashes, the bundled ones are dropped after an app update since it installs
+/// new bundled cores, all of them when the signature does not match
+fn load() -> CoreHashes {
+    let empty = || CoreHashes {
+        app_version: APP_VERSION.into(),
+        ..Default::default()
+    };
+    let mut hashes: CoreHashes = dirs::app_home_dir()
+        .and_then(|dir| help::read_yaml(&dir.join(HASHES_FILE)))
+        .unwrap_or_default();
+    if !hashes
+        .sign()
+        .is_ok_and(|signature| signature == hashes.signature)
+    {
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Ignoring recorded core hashes with an invalid signature"
+        );
+        return empty();
+    }
+    if hashes.app_version != APP_VERSION {
+        hashes.app_version = APP_VERSION.into();
+        hashes.bundled.clear();
+    }
+    hashes
+}
+
+fn save(hashes: &mut CoreHashes) -> Result<()> {
+    hashes.signature = hashes.sign()?;
+    help::save_yaml(&dirs::app_home_dir()?.join(HASHES_FILE), hashes, None)
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    Ok(hex::encode(Sha256::digest(fs::read(path)?)))
+}
+
+/// Whether the binary in use is the one in the cores dir rather than the bundled one
+fn is_installed(clash_core: &str, bin_path: &Path) -> Result<bool> {
+    Ok(bin_path == CoreManager::installed_core_path(clash_core)?)
+}
+
+/// Record the current binary of `clash_core` as the trusted one
+pub fn record(clash_core: &str) -> Result<()> {
+    let bin_path = CoreManager::core_bin_path(clash_core)?;
+    let hash = file_sha256(&bin_path)?;
+    let installed = is_installed(clash_core, &bin_path)?;
+    let _guard = LOCK.lock();
+    let mut hashes = load();
+    hashes.hashes(installed).insert(clash_core.into(), hash);
+    save(&mut hashes)?;
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Recorded hash of core {}",
+        clash_core
+    );
+    Ok(())
+}
+
+/// What [`check`] found
+#[derive(Debug, PartialEq, Eq)]
+enum Checked {
+    Matched,
+    /// first launch of a bundled core with this app version, its hash is recorded now
+    Trusted,
+    Mismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+fn check(
+    hashes: &mut CoreHashes,
+    clash_core: &str,
+    bin_path: &Path,
+    installed: bool,
+) -> Result<Checked> {
+    if !bin_path.is_file() {
+        bail!("core binary not found: {}", bin_path.display());
+    }
+    let actual = file_sha256(bin_path)?;
+
+    match hashes.hashes(installed).get(clash_core) {
+        Some(expected) if *expected == actual => Ok(Checked::Matched),
+        Some(expected) => Ok(Checked::Mismatch {
+            expected: expected.clone(),
+            actual,
+        }),
+        // 内核目录中的内核只能由核心更新安装，安装时已记录
+        None if installed => bail!(
+            "core {clash_core} in {} was not installed by the app",
+            bin_path.display()
+        ),
+        None => {
+            hashes.bundled.insert(clash_core.into(), actual);
+            Ok(Checked::Trusted)
+        }
+    }
+}
+
+/// Compare the binary of `clash_core` with the recorded hash
+/// fails when they differ or an installed core was never recorded
+pub fn verify(clash_core: &str) -> Result<()> {
+    let bin_path = CoreManager::core_bin_path(clash_core)?;
+    let installed = is_installed(clash_core, &bin_path)?;
+
+    let checked = {
+        let _guard = LOCK.lock();
+        let mut hashes = load();
+        let checked = check(&mut hashes, clash_core, &bin_path, installed);
+        if matches!(checked, Ok(Checked::Trusted)) {
+            save(&mut hashes)?;
+        }
+        checked
+    };
+    let (expected, actual) = match checked {
+        Ok(Checked::Matched) => return Ok(()),
+        Ok(Checked::Trusted) => {
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Recorded hash of bundled core {} for app {}",
+                clash_core,
+                APP_VERSION
+            );
+            return Ok(());
+        }
+        Ok(Checked::Mismatch { expected, actual }) => (expected, actual),
+        Err(err) => {
+            handle::Handle::notice_message("core_integrity::mismatch", clash_core);
+            return Err(err);
+        }
+    };
+
+    logging!(
+        error,
+        Type::Core,
+        true,
+        "Core {} failed the integrity check: expected {}, got {}",
+        clash_core,
+        expected,
+        actual
+    );
+    handle::Handle::notice_message("core_integrity::mismatch", clash_core);
+    bail!("core {clash_core} was modified after it was installed");
+}
+
+#[test]
+fn test_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let bin_path = dir.path().join("koala-mihomo");
+    fs::write(&bin_path, b"core v1").unwrap();
+    let mut hashes = CoreHashes::default();
+
+    // 内置内核首次启动时记录，之后与记录比较
+    assert_eq!(
+        check(&mut hashes, "koala-mihomo", &bin_path, false).unwrap(),
+        Checked::Trusted
+    );
+    assert_eq!(
+        check(&mut hashes, "koala-mihomo", &bin_path, false).unwrap(),
+        Checked::Matched
+    );
+    fs::write(&bin_path, b"core v1 patched").unwrap();
+    assert!(matches!(
+        check(&mut hashes, "koala-mihomo", &bin_path, false).unwrap(),
+        Checked::Mismatch { .. }
+    ));
+
+    // 内核目录中未记录的内核不被信任
+    assert!(check(&mut hashes, "koala-mihomo", &bin_path, true).is_err());
+    hashes
+        .installed
+        .insert("koala-mihomo".into(), file_sha256(&bin_path).unwrap());
+    assert_eq!(
+        check(&mut hashes, "koala-mihomo", &bin_path, true).unwrap(),
+        Checked::Matched
+    );
+
+    let missing = dir.path().join("missing");
+    assert!(check(&mut hashes, "koala-mihomo", &missing, false).is_err());
+}
@@ -0,0 +1,275 @@
+//! Turning IPv6 off on physical adapters while TUN is on
+//!
+//! On a dual-stack network the system prefers IPv6, and with a profile that has `ipv6: false`
+//! those connections go around the TUN device. With the `disable` IPv6 mode the adapters that
+//! have IPv6 are switched to IPv4 only while TUN is on, and switched back when TUN is turned off
+//! or the app quits.
+//!
+//! The adapters are written to a file before they are changed, so they are restored on the
+//! next start after a crash too. The service changes them without a prompt, asking for
+//! administrator rights is only the fallback when it isn't installed.
+
+use crate::{
+    config::Config,
+    core::service,
+    logging,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const STATE_FILE: &str = "ipv6_disabled.yaml";
+
+/// serializes changing the adapters
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ipv6State {
+    /// adapters (network services on macOS) whose IPv6 this app turned off
+    adapters: Vec<String>,
+}
+
+fn load() -> Ipv6State {
+    dirs::app_home_dir()
+        .and_then(|dir| help::read_yaml(&dir.join(STATE_FILE)))
+        .unwrap_or_default()
+}
+
+fn save(state: &Ipv6State) -> Result<()> {
+    help::save_yaml(&dirs::app_home_dir()?.join(STATE_FILE), state, None)
+}
+
+/// Follow the TUN switch and the IPv6 mode
+pub async fn update() {
+    let disable = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        verge.enable_tun_mode.unwrap_or(false) && verge.tun_ipv6_mode.as_deref() == Some("disable")
+    };
+    let result = if disable {
+        disable_ipv6().await
+    } else {
+        restore().await
+    };
+    if let Err(err) = result {
+        logging!(
+            warn,
+            Type::Network,
+            true,
+            "Failed to {} IPv6 on physical adapters: {}",
+            if disable { "disable" } else { "restore" },
+            err
+        );
+    }
+}
+
+async fn disable_ipv6() -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let adapters = platform::ipv6_adapters().await?;
+    if adapters.is_empty() {
+        return Ok(());
+    }
+
+    let mut state = load();
+    for adapter in &adapters {
+        if !state.adapters.contains(adapter) {
+            state.adapters.push(adapter.clone());
+        }
+    }
+    save(&state)?;
+
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Disabling IPv6 on {}",
+        adapters.join(", ")
+    );
+    set_ipv6(&adapters, false).await
+}
+
+/// Turn IPv6 back on where it was turned off, on quit and when TUN is turned off
+pub async fn restore() -> Result<()> {
+    let _guard = LOCK.lock().await;
+    let state = load();
+    if state.adapters.is_empty() {
+        return Ok(());
+    }
+
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Restoring IPv6 on {}",
+        state.adapters.join(", ")
+    );
+    set_ipv6(&state.adapters, true).await?;
+    save(&Ipv6State::default())
+}
+
+async fn set_ipv6(adapters: &[String], enable: bool) -> Result<()> {
+    let Err(err) = service::set_ipv6_by_service(adapters, enable).await else {
+        return Ok(());
+    };
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Service can't change IPv6, asking for administrator rights: {}",
+        err
+    );
+    platform::set_ipv6(adapters, enable).await
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    #[allow(unused_imports)] // creation_flags必须
+    use std::os::windows::process::CommandExt;
+
+    /// Physical adapters with the IPv6 binding on
+    pub async fn ipv6_adapters() -> Result<Vec<String>> {
+        let output = tokio::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-NetAdapter -Physical | Get-NetAdapterBinding -ComponentID ms_tcpip6 | Where-Object Enabled | ForEach-Object Name",
+            ])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output()
+            .await?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// Changing the bindings needs administrator rights, without them the
+    /// commands run behind a single UAC prompt
+    pub async fn set_ipv6(adapters: &[String], enable: bool) -> Result<()> {
+        use crate::utils::elevate;
+
+        let verb = if enable { "Enable" } else { "Disable" };
+        let script = adapters
+            .iter()
+            .map(|name| {
+                format!(
+                    "{verb}-NetAdapterBinding -Name {} -ComponentID ms_tcpip6",
+                    elevate::quote(name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n");
+
+        tokio::task::spawn_blocking(move || elevate::run_powershell(&script))
+            .await?
+            .map_err(|err| anyhow::anyhow!("failed to change the IPv6 binding: {err}"))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::path::Path;
+
+    /// Interfaces backed by a device with IPv6 on, virtual ones have no `device` link
+    pub async fn ipv6_adapters() -> Result<Vec<String>> {
+        let mut adapters = Vec::new();
+        for entry in std::fs::read_dir("/sys/class/net")? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if !Path::new("/sys/class/net")
+                .join(&name)
+                .join("device")
+                .exists()
+            {
+                continue;
+            }
+            let disabled =
+                std::fs::read_to_string(format!("/proc/sys/net/ipv6/conf/{name}/disable_ipv6"))
+                    .map(|value| value.trim() == "1")
+                    .unwrap_or(true);
+            if !disabled {
+                adapters.push(name);
+            }
+        }
+        adapters.sort();
+        Ok(adapters)
+    }
+
+    pub async fn set_ipv6(adapters: &[String], enable: bool) -> Result<()> {
+        let value = if enable { 0 } else { 1 };
+        // 斜杠形式的键名兼容带点的网卡名，如 eth0.100
+        let keys = adapters
+            .iter()
+            .map(|name| format!("net/ipv6/conf/{name}/disable_ipv6={value}"))
+            .collect::<Vec<_>>();
+
+        let mut command = if unsafe { libc::geteuid() } == 0 {
+            tokio::process::Command::new("sysctl")
+        } else {
+            let mut command = tokio::process::Command::new(help::linux_elevator());
+            command.arg("sysctl");
+            command
+        };
+        let status = command.arg("-w").args(&keys).status().await?;
+        if !status.success() {
+            bail!("sysctl failed with {status}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    /// Network services of Ethernet and Wi-Fi ports with IPv6 on
+    pub async fn ipv6_adapters() -> Result<Vec<String>> {
+        let mut adapters = Vec::new();
+        for service in crate::core::macos_proxy::list_services()? {
+            if !service.device.starts_with("en") {
+                continue;
+            }
+            let info = tokio::process::Command::new("networksetup")
+                .args(["-getinfo", &service.name])
+                .output()
+                .await?;
+            if !String::from_utf8_lossy(&info.stdout).contains("IPv6: Off") {
+                adapters.push(service.name);
+            }
+        }
+        Ok(adapters)
+    }
+
+    pub async fn set_ipv6(adapters: &[String], enable: bool) -> Result<()> {
+        let flag = if enable {
+            "-setv6automatic"
+        } else {
+            "-setv6off"
+        };
+        let script = adapters
+            .iter()
+            .map(|service| format!("networksetup {flag} '{}'", service.replace('\'', r"'\''")))
+            .collect::<Vec<_>>()
+            .join("; ")
+            .replace('\\', r"\\")
+            .replace('"', r#"\""#);
+        let status = tokio::process::Command::new("osascript")
+            .args([
+                "-e",
+                &format!(r#"do shell script "{script}" with administrator privileges"#),
+            ])
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("networksetup failed with {status}");
+        }
+        Ok(())
+    }
+}
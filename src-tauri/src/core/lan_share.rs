@@ -0,0 +1,305 @@
+use crate::{
+    config::Config,
+    core::{firewall, handle, CoreManager},
+    feat, logging,
+    module::mihomo::MihomoManager,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{anyhow, Result};
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use parking_lot::Mutex;
+use qrcode::{render::svg, QrCode};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{collections::BTreeMap, net::IpAddr, time::Duration};
+
+/// the bind address from before sharing was turned on
+const STATE_FILE: &str = "lan_share.yaml";
+
+/// clients not seen for this long are dropped from the list
+const CLIENT_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// how often the connections are looked at while sharing is on
+const CLIENT_POLL: Duration = Duration::from_secs(2);
+
+/// Every LAN client seen so far, by address
+static CLIENTS: Mutex<BTreeMap<String, LanClient>> = Mutex::new(BTreeMap::new());
+
+/// Sharing the proxy with phones, TVs and other devices on the LAN
+#[derive(Debug, Clone, Serialize)]
+pub struct LanShare {
+    pub enabled: bool,
+    pub port: u16,
+    /// `http://192.168.1.5:7897` for every LAN address of this machine
+    pub urls: Vec<String>,
+    /// an SVG QR code of the first url
+    pub qr_code: Option<String>,
+    /// whether the firewall lets the port in, none where it isn't managed
+    pub firewall_open: Option<bool>,
+}
+
+/// A device on the LAN that used the proxy
+#[derive(Debug, Clone, Serialize)]
+pub struct LanClient {
+    pub ip: String,
+    /// open connections right now
+    pub connections: usize,
+    /// unix time in seconds
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LanShareState {
+    /// `bind-address` of the clash config before sharing, none when it wasn't set
+    bind_address: Option<String>,
+}
+
+/// None when sharing wasn't turned on here
+fn load() -> Option<LanShareState> {
+    dirs::app_home_dir()
+        .and_then(|dir| help::read_yaml(&dir.join(STATE_FILE)))
+        .ok()
+}
+
+fn save(state: &LanShareState) -> Result<()> {
+    help::save_yaml(&dirs::app_home_dir()?.join(STATE_FILE), state, None)
+}
+
+fn clear() -> Result<()> {
+    let path = dirs::app_home_dir()?.join(STATE_FILE);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The current state, with the addresses other devices should use
+pub async fn status() -> LanShare {
+    let (enabled, port) = {
+        let clash = Config::clash();
+        let clash = clash.latest();
+        let allow_lan = clash
+            .0
+            .get("allow-lan")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let bind_all = clash
+            .0
+            .get("bind-address")
+            .and_then(Value::as_str)
+            .is_none_or(|address| matches!(address, "*" | "0.0.0.0" | "::"));
+        (allow_lan && bind_all, clash.get_mixed_port())
+    };
+    let urls = lan_addresses()
+        .into_iter()
+        .map(|ip| format!("http://{ip}:{port}"))
+        .collect::<Vec<_>>();
+    let qr_code = urls.first().and_then(|url| render_qr_code(url));
+
+    LanShare {
+        enabled,
+        port,
+        urls,
+        qr_code,
+        firewall_open: firewall::is_open(port).await,
+    }
+}
+
+/// Turn sharing on or off
+///
+/// Turning it on sets `allow-lan` with the mixed port bound to every address,
+/// so a profile's `bind-address` can't keep it on loopback. The previous
+/// `bind-address` is kept on disk and put back when sharing is turned off.
+/// The firewall rule follows `allow-lan`, see [`firewall`].
+pub async fn set(enable: bool) -> Result<LanShare> {
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "{} sharing the proxy to the LAN",
+        if enable { "Start" } else { "Stop" }
+    );
+    let mut patch = Mapping::new();
+    patch.insert("allow-lan".into(), enable.into());
+    let previous = load();
+    if enable {
+        // 已在共享时保留最初的地址
+        if previous.is_none() {
+            let bind_address = Config::clash()
+                .latest()
+                .0
+                .get("bind-address")
+                .and_then(Value::as_str)
+                .map(String::from);
+            save(&LanShareState { bind_address })?;
+        }
+        patch.insert("bind-address".into(), "*".into());
+    } else if let Some(previous) = &previous {
+        match &previous.bind_address {
+            Some(address) => {
+                patch.insert("bind-address".into(), address.as_str().into());
+            }
+            // 之前没有设置时去掉，由订阅决定；失败时 patch_clash 会丢弃草稿
+            None => {
+                Config::clash().draft().0.remove("bind-address");
+            }
+        }
+    }
+    feat::patch_clash(patch).await?;
+    if !enable {
+        clear()?;
+    }
+    // 防火墙提示后仍被拦截时可以再次开启来补上规则
+    firewall::update().await;
+    Ok(status().await)
+}
+
+/// Regenerate the config after the addresses changed, the allowed LAN
+/// addresses default to the subnets of this machine
+pub async fn refresh_acl() {
+    let default_acl = Config::verge()
+        .latest()
+        .lan_allowed_ips
+        .as_ref()
+        .is_none_or(Vec::is_empty);
+    if !default_acl || !allow_lan() {
+        return;
+    }
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "Addresses changed, updating the allowed LAN subnets"
+    );
+    if let Err(err) = CoreManager::global().update_config().await {
+        logging!(
+            warn,
+            Type::Network,
+            true,
+            "Failed to update the allowed LAN subnets: {}",
+            err
+        );
+    }
+}
+
+/// Record the LAN devices every few seconds while sharing is on
+///
+/// A device that only made a short request is listed too, not only the ones
+/// with connections open when the list is asked for.
+pub async fn watch_clients() {
+    loop {
+        tokio::time::sleep(CLIENT_POLL).await;
+        if handle::Handle::global().is_exiting() {
+            break;
+        }
+        if allow_lan() {
+            // 内核未运行时下次再试
+            let _ = poll_clients().await;
+        }
+    }
+}
+
+fn allow_lan() -> bool {
+    Config::clash()
+        .latest()
+        .0
+        .get("allow-lan")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// The LAN devices with open connections now and the ones seen recently
+pub async fn clients() -> Result<Vec<LanClient>> {
+    poll_clients().await?;
+    let mut list = CLIENTS.lock().values().cloned().collect::<Vec<_>>();
+    list.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then(a.ip.cmp(&b.ip)));
+    Ok(list)
+}
+
+async fn poll_clients() -> Result<()> {
+    let connections = MihomoManager::global()
+        .get_connections()
+        .await
+        .map_err(|err| anyhow!(err))?;
+    let own = lan_addresses();
+    let now = chrono::Local::now().timestamp();
+
+    let mut open = BTreeMap::<String, usize>::new();
+    for connection in connections["connections"].as_array().into_iter().flatten() {
+        let metadata = &connection["metadata"];
+        // TUN 与内核自身的连接来源是本机
+        if matches!(metadata["type"].as_str(), Some("Tun" | "Inner")) {
+            continue;
+        }
+        let Some(ip) = metadata["sourceIP"].as_str() else {
+            continue;
+        };
+        if is_remote_client(ip, &own) {
+            *open.entry(ip.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut clients = CLIENTS.lock();
+    clients.retain(|_, client| now - client.last_seen < CLIENT_TTL_SECS);
+    for client in clients.values_mut() {
+        client.connections = 0;
+    }
+    for (ip, count) in open {
+        clients.insert(
+            ip.clone(),
+            LanClient {
+                ip,
+                connections: count,
+                last_seen: now,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Not this machine, `own` being its LAN addresses
+fn is_remote_client(ip: &str, own: &[String]) -> bool {
+    ip.parse::<IpAddr>()
+        .is_ok_and(|addr| !addr.is_loopback() && !addr.is_unspecified())
+        && !own.iter().any(|own| own == ip)
+}
+
+/// The private IPv4 addresses of this machine, the ones a phone can reach
+fn lan_addresses() -> Vec<String> {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return vec![];
+    };
+    let mut addresses = interfaces
+        .into_iter()
+        .flat_map(|interface| interface.addr)
+        .filter_map(|addr| match addr {
+            Addr::V4(v4) if v4.ip.is_private() => Some(v4.ip),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // 家用路由器常见的 192.168 网段优先
+    addresses.sort_by_key(|ip| (ip.octets()[0] != 192, *ip));
+    addresses.dedup();
+    addresses.into_iter().map(|ip| ip.to_string()).collect()
+}
+
+fn render_qr_code(data: &str) -> Option<String> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    Some(
+        code.render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .dark_color(svg::Color("#000000"))
+            .light_color(svg::Color("#ffffff"))
+            .build(),
+    )
+}
+
+#[test]
+fn test_is_remote_client() {
+    let own = vec!["192.168.1.5".to_string()];
+    assert!(is_remote_client("192.168.1.20", &own));
+    assert!(!is_remote_client("192.168.1.5", &own));
+    assert!(!is_remote_client("127.0.0.1", &own));
+    assert!(!is_remote_client("::1", &own));
+    assert!(!is_remote_client("", &own));
+}
@@ -0,0 +1,103 @@
+//! Extra arguments and environment for the core process
+//!
+//! Users can tune the core without wrapping the binary, e.g. `GOGC=50` or `-ext-ctl-unix`.
+//! Flags and variables the app manages itself are rejected, changing them would leave the
+//! app unable to find the config or to reach the controller.
+
+use crate::config::Config;
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+
+/// flags set by the app or that replace the controller the app talks to
+const RESERVED_ARGS: &[&str] = &["-d", "-f", "-t", "-v", "-ext-ctl", "-secret"];
+
+/// variables mihomo reads in place of the flags above
+const RESERVED_ENV: &[&str] = &[
+    "CLASH_HOME_DIR",
+    "CLASH_CONFIG_FILE",
+    "CLASH_OVERRIDE_EXTERNAL_CONTROLLER",
+    "CLASH_OVERRIDE_SECRET",
+];
+
+/// Check the extra arguments and `KEY=VALUE` entries before they are saved
+pub fn validate(args: &[String], env: &[String]) -> Result<()> {
+    for arg in args {
+        if arg.trim().is_empty() {
+            bail!("empty core argument");
+        }
+        // go flags accept both `-f` and `--f`, with the value after `=` or as the next argument
+        let flag = arg.split('=').next().unwrap_or_default();
+        let flag = flag
+            .strip_prefix('-')
+            .filter(|f| f.starts_with('-'))
+            .unwrap_or(flag);
+        if RESERVED_ARGS.contains(&flag) {
+            bail!("core argument {flag} is managed by the app");
+        }
+    }
+
+    for entry in env {
+        let Some((key, _)) = entry.split_once('=') else {
+            bail!("core environment entry {entry} is not KEY=VALUE");
+        };
+        let valid = key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            bail!("invalid core environment name: {key}");
+        }
+        if RESERVED_ENV.contains(&key) {
+            bail!("core environment {key} is managed by the app");
+        }
+    }
+    Ok(())
+}
+
+/// Extra arguments appended after the ones set by the app
+pub fn extra_args() -> Vec<String> {
+    Config::verge()
+        .latest()
+        .core_extra_args
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Extra environment of the core, entries that are not `KEY=VALUE` are skipped
+pub fn extra_env() -> Vec<(String, String)> {
+    Config::verge()
+        .latest()
+        .core_extra_env
+        .iter()
+        .flatten()
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Fields added to the `StartClash` payload
+/// The service does not apply them yet, `start_core_by_service` warns the user when they are set
+pub fn service_payload() -> Value {
+    json!({
+        "extra_args": extra_args(),
+        "extra_env": extra_env().into_iter().collect::<std::collections::HashMap<_, _>>(),
+    })
+}
+
+#[test]
+fn test_validate() {
+    let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    assert!(validate(&args(&["-ext-ctl-unix", "/tmp/mihomo.sock"]), &[]).is_ok());
+    assert!(validate(&args(&["-m"]), &args(&["GOGC=50", "_X1="])).is_ok());
+
+    assert!(validate(&args(&["-d", "/tmp"]), &[]).is_err());
+    assert!(validate(&args(&["--f=/tmp/config.yaml"]), &[]).is_err());
+    assert!(validate(&args(&["-ext-ctl=0.0.0.0:9090"]), &[]).is_err());
+    assert!(validate(&args(&[" "]), &[]).is_err());
+
+    assert!(validate(&[], &args(&["GOGC"])).is_err());
+    assert!(validate(&[], &args(&["1GOGC=50"])).is_err());
+    assert!(validate(&[], &args(&["CLASH_HOME_DIR=/tmp"])).is_err());
+}
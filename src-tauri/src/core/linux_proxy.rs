@@ -0,0 +1,481 @@
+use crate::{config::Config, core::sysopt, logging, utils::logging::Type};
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+use sysproxy::{Autoproxy, Sysproxy};
+
+const GNOME_SCHEMA: &str = "org.gnome.system.proxy";
+/// dconf path of `org.gnome.system.proxy.http`
+const GNOME_HTTP_PATH: &str = "/system/proxy/http/";
+const KDE_GROUP: &str = "Proxy Settings";
+
+/// Desktops whose proxy settings we write ourselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    /// everything configured through `org.gnome.system.proxy`
+    Gnome,
+    /// `kioslaverc`
+    Kde,
+    Other,
+}
+
+impl Desktop {
+    pub fn current() -> Self {
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        Self::parse(&desktop)
+    }
+
+    fn parse(desktop: &str) -> Self {
+        let desktops = desktop
+            .split(':')
+            .map(|d| d.trim().to_uppercase())
+            .collect::<Vec<_>>();
+        if desktops.iter().any(|d| d == "KDE") {
+            return Self::Kde;
+        }
+        const GSETTINGS: &[&str] = &["GNOME", "UNITY", "CINNAMON", "MATE", "BUDGIE", "PANTHEON"];
+        if desktops
+            .iter()
+            .any(|d| GSETTINGS.iter().any(|g| d.contains(g)))
+        {
+            return Self::Gnome;
+        }
+        Self::Other
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyMode {
+    None,
+    Manual {
+        /// the http proxy
+        host: String,
+        port: u16,
+        /// the https and socks proxies, `None` when not set
+        https: Option<(String, u16)>,
+        socks: Option<(String, u16)>,
+        bypass: Vec<String>,
+        /// username and password, only GNOME keeps them
+        auth: Option<(String, String)>,
+    },
+    Auto {
+        url: String,
+    },
+}
+
+/// The desktop settings before we first changed them, restored on exit
+/// Kept on disk as well, so they are still put back after a crash
+static ORIGINAL: Lazy<Mutex<Option<ProxyMode>>> =
+    Lazy::new(|| Mutex::new(sysopt::load_original_proxy()));
+
+/// Set the system proxy through the desktop's own settings
+///
+/// The sysproxy crate only covers part of what GNOME and KDE read, so both are
+/// written directly, other desktops still go through the crate. The settings
+/// found before the first change are kept and put back when the proxy is
+/// turned off or the app exits.
+pub fn apply(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    let mode = if sys.enable {
+        let server = Some((sys.host.clone(), sys.port));
+        ProxyMode::Manual {
+            host: sys.host.clone(),
+            port: sys.port,
+            https: server.clone(),
+            socks: server,
+            bypass: split_bypass(&sys.bypass),
+            auth: Config::verge().latest().proxy_auth(),
+        }
+    } else if auto.enable {
+        ProxyMode::Auto {
+            url: auto.url.clone(),
+        }
+    } else {
+        return restore(sys, auto);
+    };
+
+    let desktop = Desktop::current();
+    if desktop == Desktop::Other {
+        return apply_fallback(sys, auto);
+    }
+
+    {
+        let mut original = ORIGINAL.lock();
+        if original.is_none() {
+            let current = read(desktop).unwrap_or(ProxyMode::None);
+            // 上次异常退出残留的是自己的设置，不作为原始设置
+            let current = if is_own(&current, sys, auto) {
+                ProxyMode::None
+            } else {
+                current
+            };
+            sysopt::save_original_proxy(Some(&current));
+            *original = Some(current);
+        }
+    }
+    write(desktop, &mode)
+}
+
+/// Put back the settings found before the first change
+///
+/// `sys` and `auto` are our own settings. Without a change of ours only
+/// leftovers of a crashed run are cleared, a proxy set by someone else is
+/// left alone.
+pub fn restore(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    let desktop = Desktop::current();
+    if desktop == Desktop::Other {
+        return apply_fallback(sys, auto);
+    }
+    let original = ORIGINAL.lock().take();
+    let original = match original {
+        Some(original) => {
+            sysopt::save_original_proxy::<ProxyMode>(None);
+            original
+        }
+        // 没有改动过，只清理残留的自己的设置
+        None => match read(desktop) {
+            Some(current) if is_own(&current, sys, auto) => ProxyMode::None,
+            _ => return Ok(()),
+        },
+    };
+    // 原设置可能带有密码，不写入日志
+    logging!(
+        info,
+        Type::System,
+        true,
+        "Restoring {:?} proxy settings",
+        desktop
+    );
+    write(desktop, &original)
+}
+
+/// The proxy settings of the current desktop
+pub fn read(desktop: Desktop) -> Option<ProxyMode> {
+    match desktop {
+        Desktop::Gnome => read_gnome(),
+        Desktop::Kde => {
+            let path = ::dirs::config_dir()?.join("kioslaverc");
+            let content = std::fs::read_to_string(path).ok()?;
+            Some(parse_kioslaverc(&content))
+        }
+        Desktop::Other => None,
+    }
+}
+
+fn write(desktop: Desktop, mode: &ProxyMode) -> Result<()> {
+    match desktop {
+        Desktop::Gnome => write_gnome(mode),
+        Desktop::Kde => write_kde(mode),
+        Desktop::Other => Ok(()),
+    }
+}
+
+fn apply_fallback(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    // 先关闭再开启，避免两者同时生效
+    if sys.enable {
+        auto.set_auto_proxy()?;
+        sys.set_system_proxy()?;
+    } else {
+        sys.set_system_proxy()?;
+        auto.set_auto_proxy()?;
+    }
+    Ok(())
+}
+
+fn is_own(mode: &ProxyMode, sys: &Sysproxy, auto: &Autoproxy) -> bool {
+    match mode {
+        ProxyMode::None => false,
+        ProxyMode::Manual { host, port, .. } => *host == sys.host && *port == sys.port,
+        ProxyMode::Auto { url } => *url == auto.url,
+    }
+}
+
+fn split_bypass(bypass: &str) -> Vec<String> {
+    bypass
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn in_path(bin: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(bin).is_file()))
+}
+
+fn gsettings_get(schema: &str, key: &str) -> Option<String> {
+    let value = run("gsettings", &["get", schema, key]).ok()?;
+    Some(value.trim_matches('\'').to_string())
+}
+
+fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<()> {
+    run("gsettings", &["set", schema, key, value]).map(|_| ())
+}
+
+/// Write a string key through `dconf load`, which reads it from stdin, so a
+/// password isn't on a command line other users can see
+fn dconf_set_secret(path: &str, key: &str, value: &str) -> Result<()> {
+    let value = value.replace('\\', r"\\").replace('\'', r"\'");
+    let mut child = Command::new("dconf")
+        .args(["load", path])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run dconf")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        write!(stdin, "[/]\n{key}='{value}'\n")?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "dconf load {path} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn read_gnome() -> Option<ProxyMode> {
+    let mode = gsettings_get(GNOME_SCHEMA, "mode")?;
+    Some(match mode.as_str() {
+        "manual" => {
+            let schema = format!("{GNOME_SCHEMA}.http");
+            let ignore = gsettings_get(GNOME_SCHEMA, "ignore-hosts").unwrap_or_default();
+            let auth = (gsettings_get(&schema, "use-authentication").as_deref() == Some("true"))
+                .then(|| {
+                    Some((
+                        gsettings_get(&schema, "authentication-user")?,
+                        gsettings_get(&schema, "authentication-password")?,
+                    ))
+                })
+                .flatten();
+            let (host, port) = gnome_server("http").unwrap_or_default();
+            ProxyMode::Manual {
+                host,
+                port,
+                https: gnome_server("https"),
+                socks: gnome_server("socks"),
+                bypass: parse_gvariant_list(&ignore),
+                auth,
+            }
+        }
+        "auto" => ProxyMode::Auto {
+            url: gsettings_get(GNOME_SCHEMA, "autoconfig-url")?,
+        },
+        _ => ProxyMode::None,
+    })
+}
+
+/// The host and port of one protocol, `None` when it has no host
+fn gnome_server(protocol: &str) -> Option<(String, u16)> {
+    let schema = format!("{GNOME_SCHEMA}.{protocol}");
+    let host = gsettings_get(&schema, "host").filter(|host| !host.is_empty())?;
+    let port = gsettings_get(&schema, "port")?.parse().ok()?;
+    Some((host, port))
+}
+
+fn write_gnome(mode: &ProxyMode) -> Result<()> {
+    match mode {
+        ProxyMode::None => gsettings_set(GNOME_SCHEMA, "mode", "none"),
+        ProxyMode::Manual {
+            host,
+            port,
+            https,
+            socks,
+            bypass,
+            auth,
+        } => {
+            let http = Some((host.clone(), *port));
+            for (protocol, server) in [("http", &http), ("https", https), ("socks", socks)] {
+                let schema = format!("{GNOME_SCHEMA}.{protocol}");
+                let (host, port) = server.clone().unwrap_or_default();
+                gsettings_set(&schema, "host", &host)?;
+                gsettings_set(&schema, "port", &port.to_string())?;
+            }
+            // 只有 http 代理可以保存认证信息
+            let schema = format!("{GNOME_SCHEMA}.http");
+            match auth {
+                Some((username, password)) => {
+                    gsettings_set(&schema, "authentication-user", username)?;
+                    dconf_set_secret(GNOME_HTTP_PATH, "authentication-password", password)?;
+                    gsettings_set(&schema, "use-authentication", "true")?;
+                }
+                None => gsettings_set(&schema, "use-authentication", "false")?,
+            }
+            gsettings_set(GNOME_SCHEMA, "ignore-hosts", &to_gvariant_list(bypass))?;
+            gsettings_set(GNOME_SCHEMA, "mode", "manual")
+        }
+        ProxyMode::Auto { url } => {
+            gsettings_set(GNOME_SCHEMA, "autoconfig-url", url)?;
+            gsettings_set(GNOME_SCHEMA, "mode", "auto")
+        }
+    }
+}
+
+/// `['localhost', '127.0.0.0/8']`
+fn to_gvariant_list(items: &[String]) -> String {
+    let items = items
+        .iter()
+        .map(|item| format!("'{}'", item.replace('\'', "")))
+        .collect::<Vec<_>>();
+    format!("[{}]", items.join(", "))
+}
+
+fn parse_gvariant_list(list: &str) -> Vec<String> {
+    list.trim_start_matches("@as ")
+        .trim_matches(['[', ']'])
+        .split(',')
+        .map(|item| item.trim().trim_matches('\'').to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn write_kde(mode: &ProxyMode) -> Result<()> {
+    // Plasma 6 只带 kwriteconfig6
+    let kwriteconfig = ["kwriteconfig6", "kwriteconfig5"]
+        .into_iter()
+        .find(|bin| in_path(bin))
+        .context("kwriteconfig not found")?;
+    let set = |key: &str, value: &str| {
+        run(
+            kwriteconfig,
+            &[
+                "--file",
+                "kioslaverc",
+                "--group",
+                KDE_GROUP,
+                "--key",
+                key,
+                value,
+            ],
+        )
+        .map(|_| ())
+    };
+
+    match mode {
+        ProxyMode::None => set("ProxyType", "0")?,
+        ProxyMode::Manual {
+            host,
+            port,
+            https,
+            socks,
+            bypass,
+            ..
+        } => {
+            let server = |scheme: &str, server: &Option<(String, u16)>| match server {
+                Some((host, port)) => format!("{scheme}://{host} {port}"),
+                None => String::new(),
+            };
+            set("httpProxy", &format!("http://{host} {port}"))?;
+            set("httpsProxy", &server("http", https))?;
+            set("socksProxy", &server("socks", socks))?;
+            set("NoProxyFor", &bypass.join(","))?;
+            set("ProxyType", "1")?;
+        }
+        ProxyMode::Auto { url } => {
+            set("Proxy Config Script", url)?;
+            set("ProxyType", "2")?;
+        }
+    }
+
+    // 通知正在运行的程序重新读取
+    let _ = run(
+        "dbus-send",
+        &[
+            "--type=signal",
+            "/KIO/Scheduler",
+            "org.kde.KIO.Scheduler.reparseSlaveConfiguration",
+            "string:",
+        ],
+    );
+    Ok(())
+}
+
+fn parse_kioslaverc(content: &str) -> ProxyMode {
+    let mut in_group = false;
+    let mut values = std::collections::HashMap::new();
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == format!("[{KDE_GROUP}]");
+            continue;
+        }
+        if let (true, Some((key, value))) = (in_group, line.split_once('=')) {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    let get = |key: &str| values.get(key).cloned().unwrap_or_default();
+
+    match get("ProxyType").as_str() {
+        "1" => {
+            let Some((host, port)) = parse_kde_server(&get("httpProxy")) else {
+                return ProxyMode::None;
+            };
+            ProxyMode::Manual {
+                host,
+                port,
+                https: parse_kde_server(&get("httpsProxy")),
+                socks: parse_kde_server(&get("socksProxy")),
+                bypass: split_bypass(&get("NoProxyFor")),
+                auth: None,
+            }
+        }
+        "2" => ProxyMode::Auto {
+            url: get("Proxy Config Script"),
+        },
+        _ => ProxyMode::None,
+    }
+}
+
+/// `http://127.0.0.1 7897`, the host and port are separated by a space or a colon
+fn parse_kde_server(proxy: &str) -> Option<(String, u16)> {
+    let proxy = proxy.split_once("://").map_or(proxy, |(_, p)| p);
+    let (host, port) = proxy.rsplit_once([' ', ':'])?;
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+#[test]
+fn test_desktop_and_kioslaverc() {
+    assert_eq!(Desktop::parse("KDE"), Desktop::Kde);
+    assert_eq!(Desktop::parse("ubuntu:GNOME"), Desktop::Gnome);
+    assert_eq!(Desktop::parse("X-Cinnamon"), Desktop::Gnome);
+    assert_eq!(Desktop::parse("sway"), Desktop::Other);
+
+    let kioslaverc = "[Proxy Settings]\nProxyType=1\nhttpProxy=http://127.0.0.1 7897\nsocksProxy=socks://10.0.0.2:1080\nNoProxyFor=localhost,127.0.0.1\n\n[Other]\nProxyType=2\n";
+    assert_eq!(
+        parse_kioslaverc(kioslaverc),
+        ProxyMode::Manual {
+            host: "127.0.0.1".into(),
+            port: 7897,
+            https: None,
+            socks: Some(("10.0.0.2".into(), 1080)),
+            bypass: vec!["localhost".into(), "127.0.0.1".into()],
+            auth: None,
+        }
+    );
+    assert_eq!(
+        parse_gvariant_list("['localhost', '127.0.0.0/8']"),
+        vec!["localhost", "127.0.0.0/8"]
+    );
+    assert_eq!(parse_gvariant_list("@as []"), Vec::<String>::new());
+}
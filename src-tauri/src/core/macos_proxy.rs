@@ -0,0 +1,425 @@
+use crate::{
+    config::Config,
+    core::sysopt::{self, NetworkService},
+    logging,
+    utils::logging::Type,
+};
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    process::{Command, Stdio},
+};
+use sysproxy::{Autoproxy, Sysproxy};
+
+/// The proxy settings of one network service
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct ServiceProxy {
+    web: Option<(String, u16)>,
+    secure_web: Option<(String, u16)>,
+    socks: Option<(String, u16)>,
+    auto_url: Option<String>,
+    bypass: Vec<String>,
+    /// username and password of the proxies, the password can't be read back
+    auth: Option<(String, String)>,
+}
+
+/// The settings of each service before we first changed it, restored on exit
+/// Kept on disk as well, so they are still put back after a crash. The lock is
+/// never held across networksetup calls.
+static ORIGINAL: Lazy<Mutex<BTreeMap<String, ServiceProxy>>> =
+    Lazy::new(|| Mutex::new(sysopt::load_original_proxy().unwrap_or_default()));
+
+fn save_original(original: &BTreeMap<String, ServiceProxy>) {
+    sysopt::save_original_proxy((!original.is_empty()).then_some(original));
+}
+
+/// The enabled network services in service order
+pub fn list_services() -> Result<Vec<NetworkService>> {
+    let order = networksetup(&["-listnetworkserviceorder"])?;
+    let active = default_interface();
+    Ok(parse_service_order(&order)
+        .into_iter()
+        .map(|(name, device)| NetworkService {
+            active: !device.is_empty() && active.as_deref() == Some(device.as_str()),
+            name,
+            device,
+        })
+        .collect())
+}
+
+/// The services picked by the user, or the one carrying the default route
+pub fn selected_services() -> Vec<String> {
+    let picked = Config::verge()
+        .latest()
+        .system_proxy_services
+        .clone()
+        .unwrap_or_default();
+    let services = list_services().unwrap_or_default();
+    let selected = services
+        .iter()
+        .filter(|s| picked.contains(&s.name))
+        .map(|s| s.name.clone())
+        .collect::<Vec<_>>();
+    if !selected.is_empty() {
+        return selected;
+    }
+    services
+        .iter()
+        .find(|s| s.active)
+        .or_else(|| services.first())
+        .map(|s| vec![s.name.clone()])
+        .unwrap_or_default()
+}
+
+/// Set the system proxy on the selected network services
+///
+/// The sysproxy crate writes every service, which also hijacks VPN and
+/// tethering services the user wants to keep direct. Each service's own
+/// settings are saved before the first change and put back when it is
+/// deselected, the proxy is turned off or the app exits.
+pub fn apply(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    if !sys.enable && !auto.enable {
+        return restore(sys, auto);
+    }
+    let selected = selected_services();
+    if selected.is_empty() {
+        bail!("no network service to set the proxy on");
+    }
+
+    // 取消选择的服务恢复原设置
+    let (deselected, known) = {
+        let mut original = ORIGINAL.lock();
+        let names = original
+            .keys()
+            .filter(|name| !selected.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let deselected = names
+            .into_iter()
+            .filter_map(|name| original.remove(&name).map(|proxy| (name, proxy)))
+            .collect::<Vec<_>>();
+        save_original(&original);
+        (deselected, original.keys().cloned().collect::<Vec<_>>())
+    };
+    for (name, proxy) in deselected {
+        logging_error(&name, write_service(&name, &proxy));
+    }
+
+    let bypass = sys
+        .bypass
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let server = Some((sys.host.clone(), sys.port));
+    let proxy = if sys.enable {
+        ServiceProxy {
+            web: server.clone(),
+            secure_web: server.clone(),
+            socks: server,
+            auto_url: None,
+            bypass,
+            auth: Config::verge().latest().proxy_auth(),
+        }
+    } else {
+        ServiceProxy {
+            auto_url: Some(auto.url.clone()),
+            bypass,
+            ..ServiceProxy::default()
+        }
+    };
+
+    // 先在锁外读取新选择的服务的原设置
+    let fresh = selected
+        .iter()
+        .filter(|name| !known.contains(name))
+        .map(|name| {
+            let current = read_service(name).unwrap_or_default();
+            // 上次异常退出残留的是自己的设置，不作为原始设置
+            let current = if is_own(&current, sys, auto) {
+                ServiceProxy::default()
+            } else {
+                current
+            };
+            (name.clone(), current)
+        })
+        .collect::<Vec<_>>();
+    if !fresh.is_empty() {
+        let mut original = ORIGINAL.lock();
+        for (name, current) in fresh {
+            original.entry(name).or_insert(current);
+        }
+        save_original(&original);
+    }
+
+    for name in &selected {
+        write_service(name, &proxy)?;
+    }
+    Ok(())
+}
+
+/// Put back the settings of every service we changed
+///
+/// `sys` and `auto` are our own settings. Without a change of ours only
+/// leftovers of a crashed run are cleared, a proxy set by someone else is
+/// left alone.
+pub fn restore(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    let original = std::mem::take(&mut *ORIGINAL.lock());
+    save_original(&BTreeMap::new());
+    if original.is_empty() {
+        for name in selected_services() {
+            if read_service(&name).is_ok_and(|proxy| is_own(&proxy, sys, auto)) {
+                write_service(&name, &ServiceProxy::default())?;
+            }
+        }
+        return Ok(());
+    }
+    for (name, proxy) in original {
+        logging!(
+            info,
+            Type::System,
+            true,
+            "Restoring proxy settings of {}",
+            name
+        );
+        logging_error(&name, write_service(&name, &proxy));
+    }
+    Ok(())
+}
+
+fn logging_error(service: &str, result: Result<()>) {
+    if let Err(err) = result {
+        logging!(
+            warn,
+            Type::System,
+            true,
+            "Failed to restore proxy settings of {}: {}",
+            service,
+            err
+        );
+    }
+}
+
+fn is_own(proxy: &ServiceProxy, sys: &Sysproxy, auto: &Autoproxy) -> bool {
+    let own = Some((sys.host.clone(), sys.port));
+    proxy.web == own || proxy.auto_url.as_deref() == Some(auto.url.as_str())
+}
+
+fn networksetup(args: &[&str]) -> Result<String> {
+    let output = Command::new("networksetup")
+        .args(args)
+        .output()
+        .context("failed to run networksetup")?;
+    if !output.status.success() {
+        bail!(
+            "networksetup {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The interface of the default route
+fn default_interface() -> Option<String> {
+    let output = Command::new("route")
+        .args(["-n", "get", "default"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))
+        .map(|device| device.trim().to_string())
+}
+
+/// `(1) Wi-Fi` followed by `(Hardware Port: Wi-Fi, Device: en0)`, disabled services start with `(*)`
+fn parse_service_order(output: &str) -> Vec<(String, String)> {
+    let mut services = Vec::new();
+    let mut name: Option<String> = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("(Hardware Port:") {
+            if let Some(name) = name.take() {
+                let device = rest
+                    .split_once("Device:")
+                    .map(|(_, device)| device.trim_end_matches(')').trim().to_string())
+                    .unwrap_or_default();
+                services.push((name, device));
+            }
+        } else if let Some((index, rest)) = line.strip_prefix('(').and_then(|l| l.split_once(')')) {
+            name = (index != "*" && index.parse::<u32>().is_ok()).then(|| rest.trim().to_string());
+        }
+    }
+    services
+}
+
+fn read_service(name: &str) -> Result<ServiceProxy> {
+    let server = |flag: &str| -> Result<Option<(String, u16)>> {
+        let output = networksetup(&[flag, name])?;
+        let fields = parse_fields(&output);
+        if fields.get("Enabled").map(String::as_str) != Some("Yes") {
+            return Ok(None);
+        }
+        let host = fields.get("Server").cloned().unwrap_or_default();
+        let port = fields
+            .get("Port")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or_default();
+        Ok(Some((host, port)))
+    };
+    let auto = parse_fields(&networksetup(&["-getautoproxyurl", name])?);
+    let auto_enabled = auto.get("Enabled").map(String::as_str) == Some("Yes");
+    let auto_url = auto.get("URL").filter(|_| auto_enabled).cloned();
+    let bypass = networksetup(&["-getproxybypassdomains", name])?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("There aren't any"))
+        .map(str::to_string)
+        .collect();
+
+    Ok(ServiceProxy {
+        web: server("-getwebproxy")?,
+        secure_web: server("-getsecurewebproxy")?,
+        socks: server("-getsocksfirewallproxy")?,
+        auto_url,
+        bypass,
+        auth: None,
+    })
+}
+
+fn write_service(name: &str, proxy: &ServiceProxy) -> Result<()> {
+    for (set, state, protocol, server) in [
+        ("-setwebproxy", "-setwebproxystate", "htpx", &proxy.web),
+        (
+            "-setsecurewebproxy",
+            "-setsecurewebproxystate",
+            "htsx",
+            &proxy.secure_web,
+        ),
+        (
+            "-setsocksfirewallproxy",
+            "-setsocksfirewallproxystate",
+            "sox ",
+            &proxy.socks,
+        ),
+    ] {
+        match server {
+            Some((host, port)) => {
+                let port_arg = port.to_string();
+                let mut args: Vec<&str> = vec![set, name, host, &port_arg];
+                // 密码不放在命令行中，单独写入钥匙串
+                match &proxy.auth {
+                    Some((username, _)) => args.extend(["on", username.as_str(), ""]),
+                    None => args.push("off"),
+                }
+                networksetup(&args)?;
+                if let Some((username, password)) = &proxy.auth {
+                    save_proxy_password(protocol, host, *port, username, password)?;
+                }
+                networksetup(&[state, name, "on"])?;
+            }
+            None => {
+                networksetup(&[state, name, "off"])?;
+            }
+        }
+    }
+    match &proxy.auto_url {
+        Some(url) => {
+            networksetup(&["-setautoproxyurl", name, url])?;
+            networksetup(&["-setautoproxystate", name, "on"])?;
+        }
+        None => {
+            networksetup(&["-setautoproxystate", name, "off"])?;
+        }
+    }
+    let mut args = vec!["-setproxybypassdomains", name];
+    if proxy.bypass.is_empty() {
+        args.push("Empty");
+    } else {
+        args.extend(proxy.bypass.iter().map(String::as_str));
+    }
+    networksetup(&args)?;
+    Ok(())
+}
+
+/// Store the password of a proxy in the login keychain, where the system
+/// looks up proxy credentials. `security -i` reads the command from stdin,
+/// so the password isn't on a command line other processes can see.
+fn save_proxy_password(
+    protocol: &str,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+) -> Result<()> {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', r"\\").replace('"', r#"\""#));
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run security")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(
+            stdin,
+            "add-internet-password -U -r {} -s {} -P {port} -a {} -w {}",
+            quote(protocol),
+            quote(host),
+            quote(username),
+            quote(password)
+        )?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "security add-internet-password failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// `Key: Value` lines
+fn parse_fields(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[test]
+fn test_parse_networksetup() {
+    let order = "An asterisk (*) denotes that a network service is disabled.
+(1) Wi-Fi
+(Hardware Port: Wi-Fi, Device: en0)
+
+(2) USB 10/100/1000 LAN
+(Hardware Port: USB 10/100/1000 LAN, Device: en7)
+
+(*) Thunderbolt Bridge
+(Hardware Port: Thunderbolt Bridge, Device: bridge0)
+
+(3) Tailscale
+(Hardware Port: com.tailscale.ipn.macsys, Device: )
+";
+    assert_eq!(
+        parse_service_order(order),
+        vec![
+            ("Wi-Fi".to_string(), "en0".to_string()),
+            ("USB 10/100/1000 LAN".to_string(), "en7".to_string()),
+            ("Tailscale".to_string(), String::new()),
+        ]
+    );
+
+    let fields = parse_fields(
+        "Enabled: Yes\nServer: 127.0.0.1\nPort: 7897\nAuthenticated Proxy Enabled: 0\n",
+    );
+    assert_eq!(fields.get("Server").map(String::as_str), Some("127.0.0.1"));
+    assert_eq!(fields.get("Port").map(String::as_str), Some("7897"));
+}
@@ -1,15 +1,56 @@
 pub mod async_proxy_query;
 pub mod backup;
+pub mod bypass;
+pub mod captive_portal;
+pub mod conflicts;
 #[allow(clippy::module_inception)]
 mod core;
+pub mod diagnose;
+pub mod dns_check;
 pub mod event_driven_proxy;
+pub mod failsafe;
+pub mod firewall;
+pub mod geodata;
 pub mod handle;
+pub mod health;
 pub mod hotkey;
+pub mod integrity;
+pub mod ipv6_guard;
+pub mod lan_share;
+pub mod launch;
+#[cfg(target_os = "linux")]
+pub mod linux_proxy;
+#[cfg(target_os = "macos")]
+pub mod macos_proxy;
+pub mod network_watcher;
+pub mod notifications;
+pub mod pac;
+pub mod ports;
+pub mod profile_watcher;
+pub mod proxy_networks;
+pub mod proxy_watcher;
+pub mod resume;
+pub mod sandbox;
+pub mod selection;
 pub mod service;
 pub mod service_ipc;
 pub mod sysopt;
+pub mod telemetry;
 pub mod timer;
 pub mod tray;
 pub mod win_uwp;
+#[cfg(target_os = "windows")]
+pub mod winhttp_proxy;
 
-pub use self::{core::*, event_driven_proxy::EventDrivenProxyManager, timer::Timer};
+pub use self::{
+    core::*,
+    diagnose::CoreDiagnosis,
+    event_driven_proxy::EventDrivenProxyManager,
+    geodata::{GeoDataManager, GeoDataStatus},
+    health::{CoreHealth, CoreHealthStatus},
+    network_watcher::NetworkWatcher,
+    profile_watcher::ProfileWatcher,
+    proxy_watcher::ProxyWatcher,
+    telemetry::{CoreSample, CoreTelemetry},
+    timer::Timer,
+};
@@ -0,0 +1,445 @@
+use crate::{
+    config::Config,
+    core::{captive_portal, handle, lan_share, proxy_networks, Timer},
+    feat, logging, logging_error,
+    process::{AsyncHandler, StartOnce},
+    utils::{logging::Type, metered},
+};
+use anyhow::Result;
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use once_cell::sync::OnceCell;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::Instant,
+};
+
+/// how often the network interfaces are checked when changes can't be watched
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// switching networks sends a burst of changes, wait for the last one
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// connectivity has to stay up this long before the refresh runs
+const DEBOUNCE: Duration = Duration::from_secs(10);
+
+/// how often updates deferred on a metered network are retried
+const DEFERRED_INTERVAL: Duration = Duration::from_secs(60);
+
+/// skip the refresh when the profile was updated this recently
+const MIN_PROFILE_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// interfaces of tunnels, VPNs and virtual machines, which have addresses without a network
+const VIRTUAL_PREFIXES: &[&str] = &[
+    "utun",
+    "tun",
+    "tap",
+    "wg",
+    "docker",
+    "br-",
+    "veth",
+    "virbr",
+    "vmnet",
+    "vboxnet",
+    "zt",
+    "tailscale",
+    "awdl",
+    "llw",
+    "bridge",
+    "vethernet",
+    "virtualbox",
+    "vmware",
+    "mihomo",
+    "meta",
+    "zerotier",
+    "wireguard",
+];
+
+/// Refreshes the current subscription when connectivity returns after being offline
+///
+/// Address changes are reported by the OS: `NotifyAddrChange` on Windows, a netlink socket on
+/// Linux and a routing socket on macOS. When that fails the interfaces are polled instead.
+/// Waking up from sleep, reported by `resume`, counts as going offline.
+/// The loop also retries the timer updates deferred on a metered network, and
+/// checks the networks the system proxy is limited to, looks for a captive portal and
+/// updates the allowed LAN subnets when the addresses change.
+pub struct NetworkWatcher {
+    /// set on wake-up, taken when the change is handled
+    woke: AtomicBool,
+
+    /// wakes the loop up, set once started
+    changes: OnceCell<UnboundedSender<()>>,
+
+    initialized: StartOnce,
+}
+
+impl NetworkWatcher {
+    pub fn global() -> &'static NetworkWatcher {
+        static WATCHER: OnceCell<NetworkWatcher> = OnceCell::new();
+
+        WATCHER.get_or_init(|| NetworkWatcher {
+            woke: AtomicBool::new(false),
+            changes: OnceCell::new(),
+            initialized: StartOnce::new(),
+        })
+    }
+
+    /// The system woke up from sleep, connectivity is checked as if it was lost
+    pub fn woke_up(&self) {
+        self.woke.store(true, Ordering::SeqCst);
+        if let Some(changes) = self.changes.get() {
+            let _ = changes.send(());
+        }
+    }
+
+    /// Start watching, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Network, true, "Starting network change watcher");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _ = self.changes.set(tx.clone());
+        std::thread::spawn(move || {
+            let result = watch_blocking(|| {
+                let _ = tx.send(());
+            });
+            if let Err(err) = result {
+                logging!(
+                    warn,
+                    Type::Network,
+                    true,
+                    "Can't watch network changes, checking periodically: {}",
+                    err
+                );
+                AsyncHandler::spawn(move || async move {
+                    while tx.send(()).is_ok() {
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                });
+            }
+        });
+
+        AsyncHandler::spawn(move || async move {
+            proxy_networks::update().await;
+            let mut addresses = address_snapshot();
+            let mut online = is_online();
+            // 恢复连接的时间，等待稳定后再更新
+            let mut reconnected: Option<Instant> = None;
+            let mut deferred =
+                tokio::time::interval_at(Instant::now() + DEFERRED_INTERVAL, DEFERRED_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    changed = rx.recv() => {
+                        if changed.is_none() {
+                            break;
+                        }
+                        tokio::time::sleep(SETTLE).await;
+                        while rx.try_recv().is_ok() {}
+                    }
+                    _ = sleep_until(reconnected.map(|at| at + DEBOUNCE)) => {
+                        reconnected = None;
+                        let enabled = Config::verge()
+                            .latest()
+                            .enable_network_change_refresh
+                            .unwrap_or(true);
+                        if online && enabled {
+                            Self::refresh_current().await;
+                        }
+                        continue;
+                    }
+                    _ = deferred.tick() => {
+                        if online {
+                            Timer::global().run_deferred().await;
+                        }
+                        continue;
+                    }
+                }
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+
+                let woke_up = self.woke.swap(false, Ordering::SeqCst);
+                // 切换 Wi-Fi 或插拔网线时地址会变化
+                let current = address_snapshot();
+                if current != addresses || woke_up {
+                    addresses = current;
+                    AsyncHandler::spawn(proxy_networks::update);
+                    AsyncHandler::spawn(captive_portal::check);
+                    AsyncHandler::spawn(lan_share::refresh_acl);
+                }
+
+                let was_online = online && !woke_up;
+                online = is_online();
+                if !online {
+                    reconnected = None;
+                } else if !was_online {
+                    logging!(
+                        info,
+                        Type::Network,
+                        true,
+                        "Network connectivity restored{}",
+                        if woke_up { " after sleep" } else { "" }
+                    );
+                    reconnected = Some(Instant::now());
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn refresh_current() {
+        let uid = {
+            let profiles = Config::profiles();
+            let profiles = profiles.latest();
+            let Some(uid) = profiles.get_current() else {
+                return;
+            };
+            let Ok(item) = profiles.get_item(&uid) else {
+                return;
+            };
+            if item.itype.as_deref() != Some("remote") || item.is_locked() {
+                return;
+            }
+            let now = chrono::Local::now().timestamp() as usize;
+            let age = now.saturating_sub(item.updated.unwrap_or(0));
+            if age < MIN_PROFILE_AGE.as_secs() as usize {
+                return;
+            }
+            uid
+        };
+        if metered::should_defer_updates().await {
+            logging!(
+                info,
+                Type::Network,
+                true,
+                "Deferring refresh of {} on metered network",
+                uid
+            );
+            Timer::global().deferred.lock().insert(uid);
+            return;
+        }
+
+        logging!(
+            info,
+            Type::Network,
+            true,
+            "Refreshing subscription {} after reconnect",
+            uid
+        );
+        logging_error!(
+            Type::Network,
+            true,
+            feat::update_profile(uid, None, Some(true)).await
+        );
+    }
+}
+
+/// Whether any interface has an address usable for outside traffic
+fn is_online() -> bool {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return true;
+    };
+
+    interfaces
+        .iter()
+        .filter(|interface| !is_virtual(&interface.name))
+        .flat_map(|interface| interface.addr.iter())
+        .any(|addr| {
+            let ip = match addr {
+                Addr::V4(v4) => IpAddr::V4(v4.ip),
+                Addr::V6(v6) => IpAddr::V6(v6.ip),
+            };
+            is_routable(ip)
+        })
+}
+
+/// The first physical interface with a routable IPv4 address, to reach the network around TUN
+pub fn physical_interface() -> Option<(String, Ipv4Addr)> {
+    NetworkInterface::show()
+        .ok()?
+        .into_iter()
+        .filter(|interface| !is_virtual(&interface.name))
+        .find_map(|interface| {
+            let ip = interface.addr.iter().find_map(|addr| match addr {
+                Addr::V4(v4) if is_routable(IpAddr::V4(v4.ip)) => Some(v4.ip),
+                _ => None,
+            })?;
+            Some((interface.name, ip))
+        })
+}
+
+/// Every interface with its addresses, to notice a change of network
+fn address_snapshot() -> Vec<String> {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return vec![];
+    };
+    let mut snapshot = interfaces
+        .iter()
+        .filter(|interface| !is_virtual(&interface.name))
+        .flat_map(|interface| {
+            interface.addr.iter().map(move |addr| {
+                let ip = match addr {
+                    Addr::V4(v4) => IpAddr::V4(v4.ip),
+                    Addr::V6(v6) => IpAddr::V6(v6.ip),
+                };
+                format!("{}/{ip}", interface.name)
+            })
+        })
+        .collect::<Vec<_>>();
+    snapshot.sort();
+    snapshot
+}
+
+/// Interfaces of tunnels and virtual machines, TUN devices of this and other proxy apps among them
+fn is_virtual(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    VIRTUAL_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Block until changes can't be followed anymore, calling `on_change` for every change
+#[cfg(windows)]
+fn watch_blocking(on_change: impl Fn()) -> Result<()> {
+    use anyhow::bail;
+    use winapi::um::iphlpapi::NotifyAddrChange;
+
+    loop {
+        // 不传句柄时同步等待下一次地址变化
+        let status = unsafe { NotifyAddrChange(std::ptr::null_mut(), std::ptr::null_mut()) };
+        if status != 0 {
+            bail!("NotifyAddrChange failed with {status}");
+        }
+        on_change();
+    }
+}
+
+/// Block until changes can't be followed anymore, calling `on_change` for every change
+#[cfg(unix)]
+fn watch_blocking(on_change: impl Fn()) -> Result<()> {
+    use anyhow::bail;
+    use std::{io, os::fd::AsRawFd};
+
+    let socket = open_change_socket()?;
+    let mut buf = [0u8; 8192];
+    loop {
+        let len = unsafe { libc::read(socket.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                // 消息太多时内核会丢弃，仍然说明有变化
+                Some(libc::ENOBUFS) => {}
+                _ => bail!("reading network changes failed: {err}"),
+            }
+        }
+        on_change();
+    }
+}
+
+/// A netlink socket receiving link and address changes
+#[cfg(target_os = "linux")]
+fn open_change_socket() -> Result<std::os::fd::OwnedFd> {
+    use anyhow::bail;
+    use std::{
+        io,
+        os::fd::{FromRawFd, OwnedFd},
+    };
+
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        bail!("netlink socket failed: {}", io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as u16;
+    addr.nl_groups =
+        (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+    let bound = unsafe {
+        libc::bind(
+            fd,
+            (&addr as *const libc::sockaddr_nl).cast(),
+            std::mem::size_of::<libc::sockaddr_nl>() as u32,
+        )
+    };
+    if bound < 0 {
+        bail!("netlink bind failed: {}", io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+/// A routing socket, which receives every change of addresses, interfaces and routes
+#[cfg(not(target_os = "linux"))]
+#[cfg(unix)]
+fn open_change_socket() -> Result<std::os::fd::OwnedFd> {
+    use anyhow::bail;
+    use std::{
+        io,
+        os::fd::{FromRawFd, OwnedFd},
+    };
+
+    let fd = unsafe { libc::socket(libc::PF_ROUTE, libc::SOCK_RAW, 0) };
+    if fd < 0 {
+        bail!("routing socket failed: {}", io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Loopback, link-local and the TUN fake-ip range don't count as connectivity
+pub(crate) fn is_routable(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[test]
+fn test_is_routable() {
+    assert!(is_routable("192.168.1.10".parse().unwrap()));
+    assert!(is_routable("2001:db8::1".parse().unwrap()));
+    assert!(!is_routable("127.0.0.1".parse().unwrap()));
+    assert!(!is_routable("169.254.3.4".parse().unwrap()));
+    assert!(!is_routable("198.18.0.1".parse().unwrap()));
+    assert!(!is_routable("fe80::1".parse().unwrap()));
+}
+
+#[test]
+fn test_is_virtual() {
+    assert!(is_virtual("utun4"));
+    assert!(is_virtual("docker0"));
+    assert!(is_virtual("vEthernet (WSL)"));
+    assert!(is_virtual("Mihomo"));
+    assert!(!is_virtual("en0"));
+    assert!(!is_virtual("wlan0"));
+    assert!(!is_virtual("Wi-Fi"));
+    assert!(!is_virtual("Ethernet 2"));
+}
@@ -0,0 +1,194 @@
+//! Notices shown in the app
+//!
+//! Every notice passes through here on its way to the frontend. A notice repeating the same
+//! status and message within [`COALESCE_WINDOW`] is shown once, the repeats are summed up in
+//! one more notice when the window closes, so ten failed updates in a row make two notices
+//! instead of ten. The answers to something the user just did are never merged, and they are
+//! the only notices shown during the `notice_quiet_hours`. Either way every notice lands in a
+//! short history that can be read in the app.
+
+use crate::{config::Config, core::handle, process::AsyncHandler};
+use chrono::Timelike;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+/// repeats of a notice within this time after it was shown are summed up
+const COALESCE_WINDOW: Duration = Duration::from_secs(5);
+
+/// notices kept in the history, the oldest are dropped first
+const HISTORY_LIMIT: usize = 200;
+
+/// answers to the user's own actions, shown every time and even during quiet hours
+const ALWAYS_SHOWN: [&str; 2] = ["import_sub_url::", "set_config::"];
+
+/// the status and message of the notices within their window, with the repeats seen so far
+static PENDING: Lazy<Mutex<HashMap<(String, String), usize>>> = Lazy::new(Default::default);
+
+static HISTORY: Mutex<VecDeque<NoticeRecord>> = Mutex::new(VecDeque::new());
+
+/// What became of a notice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeState {
+    Shown,
+    /// a repeat, counted in the summary of the notice
+    Merged,
+    /// held back during quiet hours
+    Quiet,
+}
+
+/// A notice as kept in the history
+#[derive(Debug, Clone, Serialize)]
+pub struct NoticeRecord {
+    pub status: String,
+    pub message: String,
+    /// unix time in ms
+    pub time: i64,
+    pub state: NoticeState,
+}
+
+/// Show a notice unless it repeats a recent one or it's quiet hours
+pub fn notify(status: String, message: String) {
+    if is_always_shown(&status) {
+        record(&status, &message, NoticeState::Shown);
+        handle::Handle::send_notice(status, message);
+        return;
+    }
+
+    let key = (status.clone(), message.clone());
+    let repeated = {
+        let mut pending = PENDING.lock();
+        match pending.get_mut(&key) {
+            Some(count) => {
+                *count += 1;
+                true
+            }
+            None => {
+                pending.insert(key.clone(), 0);
+                false
+            }
+        }
+    };
+    if repeated {
+        record(&status, &message, NoticeState::Merged);
+        return;
+    }
+
+    if is_quiet(&status) {
+        log::debug!(target: "app", "notice {status} held back during quiet hours");
+        record(&status, &message, NoticeState::Quiet);
+    } else {
+        record(&status, &message, NoticeState::Shown);
+        handle::Handle::send_notice(status, message);
+    }
+
+    AsyncHandler::spawn(move || async move {
+        tokio::time::sleep(COALESCE_WINDOW).await;
+        let count = PENDING.lock().remove(&key);
+        if let Some(count) = count.filter(|count| *count > 0) {
+            let (status, message) = key;
+            if !is_quiet(&status) {
+                handle::Handle::send_notice(status, summarize(&message, count));
+            }
+        }
+    });
+}
+
+/// The notices so far, the latest first
+pub fn history() -> Vec<NoticeRecord> {
+    HISTORY.lock().iter().rev().cloned().collect()
+}
+
+pub fn clear_history() {
+    HISTORY.lock().clear();
+}
+
+fn record(status: &str, message: &str, state: NoticeState) {
+    let mut history = HISTORY.lock();
+    if history.len() >= HISTORY_LIMIT {
+        history.pop_front();
+    }
+    history.push_back(NoticeRecord {
+        status: status.to_string(),
+        message: message.to_string(),
+        time: chrono::Local::now().timestamp_millis(),
+        state,
+    });
+}
+
+/// The message with the number of its repeats, `Update failed: timeout (+9)`
+fn summarize(message: &str, count: usize) -> String {
+    if message.is_empty() {
+        return message.to_string();
+    }
+    format!("{message} (+{count})")
+}
+
+fn is_always_shown(status: &str) -> bool {
+    ALWAYS_SHOWN.iter().any(|prefix| status.starts_with(prefix))
+}
+
+fn is_quiet(status: &str) -> bool {
+    if is_always_shown(status) {
+        return false;
+    }
+    let Some(spec) = Config::verge().latest().notice_quiet_hours.clone() else {
+        return false;
+    };
+    let now = chrono::Local::now();
+    in_quiet_hours(&spec, now.hour() * 60 + now.minute())
+}
+
+/// `spec` is `HH:MM-HH:MM`, a range ending before it starts spans midnight
+pub fn in_quiet_hours(spec: &str, minute: u32) -> bool {
+    let Some((start, end)) = parse_quiet_hours(spec) else {
+        return false;
+    };
+
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+/// The start and end of `HH:MM-HH:MM` in minutes of the day
+pub fn parse_quiet_hours(spec: &str) -> Option<(u32, u32)> {
+    let (start, end) = spec.split_once('-')?;
+    Some((parse_minute(start)?, parse_minute(end)?))
+}
+
+fn parse_minute(time: &str) -> Option<u32> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+#[test]
+fn test_in_quiet_hours() {
+    assert!(in_quiet_hours("22:00-07:00", 23 * 60));
+    assert!(in_quiet_hours("22:00-07:00", 6 * 60 + 59));
+    assert!(!in_quiet_hours("22:00-07:00", 7 * 60));
+    assert!(in_quiet_hours("12:30-13:30", 13 * 60));
+    assert!(!in_quiet_hours("12:30-13:30", 14 * 60));
+    assert!(!in_quiet_hours("25:00-07:00", 23 * 60));
+    assert!(!in_quiet_hours("", 0));
+    assert_eq!(
+        parse_quiet_hours(" 22:00 - 7:30 "),
+        Some((22 * 60, 7 * 60 + 30))
+    );
+    assert_eq!(parse_quiet_hours("22:00"), None);
+
+    assert_eq!(
+        summarize("Update failed: timeout", 9),
+        "Update failed: timeout (+9)"
+    );
+    assert_eq!(summarize("", 3), "");
+    assert!(is_always_shown("set_config::error"));
+    assert!(!is_always_shown("update_profile::error"));
+}
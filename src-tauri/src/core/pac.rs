@@ -0,0 +1,236 @@
+use crate::{
+    config::{Config, DEFAULT_PAC},
+    core::bypass,
+};
+use serde_yaml::Value;
+use std::{fmt::Write, net::Ipv4Addr};
+
+/// The pac script served on `/commands/pac`
+pub fn script() -> String {
+    let (from_rules, content, host, port) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.pac_from_rules.unwrap_or(false),
+            verge
+                .pac_file_content
+                .clone()
+                .unwrap_or(DEFAULT_PAC.to_string()),
+            verge
+                .proxy_host
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".into()),
+            verge
+                .verge_mixed_port
+                .unwrap_or(Config::clash().data().get_mixed_port()),
+        )
+    };
+    if !from_rules {
+        return content.replace("%mixed-port%", &format!("{port}"));
+    }
+
+    let (mode, rules) = {
+        let runtime = Config::runtime();
+        let runtime = runtime.latest();
+        let config = runtime.config.as_ref();
+        let mode = config
+            .and_then(|c| c.get("mode"))
+            .and_then(Value::as_str)
+            .unwrap_or("rule")
+            .to_lowercase();
+        let rules = config
+            .and_then(|c| c.get("rules"))
+            .and_then(Value::as_sequence)
+            .map(|rules| {
+                rules
+                    .iter()
+                    .filter_map(|rule| rule.as_str().map(String::from))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        (mode, rules)
+    };
+    let proxy = format!("PROXY {host}:{port}; SOCKS5 {host}:{port}; DIRECT");
+    generate(&mode, &rules, &bypass::current().to_string(), &proxy)
+}
+
+/// What a rule sends the matching hosts to
+#[derive(Debug, PartialEq)]
+enum Target {
+    Direct,
+    Proxy,
+}
+
+/// Build a pac script from the rules of the running config
+///
+/// Only the domain and IPv4 rules can be checked in a pac script. The
+/// other rules are skipped as long as they send traffic directly, since
+/// proxied traffic still goes through the core's rules. A rule that cannot
+/// be checked and sends traffic to a proxy ends the list, the rules after it
+/// could send the wrong hosts directly.
+fn generate(mode: &str, rules: &[String], bypass: &str, proxy: &str) -> String {
+    let mut body = String::new();
+    for entry in bypass
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+    {
+        if let Some(cond) = bypass_condition(entry) {
+            let _ = writeln!(body, "  if ({cond}) return \"DIRECT\";");
+        }
+    }
+
+    let fallback = match mode {
+        "direct" => Target::Direct,
+        "global" => Target::Proxy,
+        _ => translate_rules(rules, &mut body),
+    };
+    let fallback = match fallback {
+        Target::Direct => "\"DIRECT\"",
+        Target::Proxy => "proxy",
+    };
+
+    format!(
+        "// Generated from the active rules, changes are overwritten\n\
+         function FindProxyForURL(url, host) {{\n\
+         \x20 var proxy = {};\n\
+         \x20 var isIp = /^\\d+\\.\\d+\\.\\d+\\.\\d+$/.test(host);\n\
+         {body}\
+         \x20 return {fallback};\n\
+         }}\n",
+        js_string(proxy),
+    )
+}
+
+/// Append the checkable rules to `body` and return where the rest goes
+fn translate_rules(rules: &[String], body: &mut String) -> Target {
+    for rule in rules {
+        let parts = rule.split(',').map(str::trim).collect::<Vec<_>>();
+        let kind = parts[0].to_uppercase();
+        if kind == "MATCH" || kind == "FINAL" {
+            return parts.get(1).map_or(Target::Proxy, |t| target(t));
+        }
+        // 逻辑规则的参数中也有逗号，取最后一个不是选项的字段作为目标
+        let rule_target = parts
+            .iter()
+            .rev()
+            .find(|p| !matches!(**p, "no-resolve" | "src"))
+            .map_or(Target::Proxy, |t| target(t));
+        let no_resolve = parts.contains(&"no-resolve");
+
+        let cond = match (kind.as_str(), parts.len()) {
+            (_, len) if len < 3 => None,
+            ("DOMAIN", _) => Some(format!("host === {}", js_string(parts[1]))),
+            ("DOMAIN-SUFFIX", _) => Some(format!(
+                "(host === {0} || dnsDomainIs(host, {1}))",
+                js_string(parts[1]),
+                js_string(&format!(".{}", parts[1])),
+            )),
+            ("DOMAIN-KEYWORD", _) => Some(format!("host.indexOf({}) !== -1", js_string(parts[1]))),
+            ("IP-CIDR", _) => cidr_condition(parts[1]),
+            _ => None,
+        };
+        let return_value = match rule_target {
+            Target::Direct => "\"DIRECT\"",
+            Target::Proxy => "proxy",
+        };
+        match cond {
+            Some(cond) => {
+                let _ = writeln!(body, "  if ({cond}) return {return_value};");
+                // 未设置 no-resolve 时域名也会被解析后匹配，无法在 pac 中判断
+                if kind == "IP-CIDR" && !no_resolve && rule_target == Target::Proxy {
+                    return Target::Proxy;
+                }
+            }
+            None if rule_target == Target::Direct => {}
+            None => return Target::Proxy,
+        }
+    }
+    Target::Proxy
+}
+
+fn target(name: &str) -> Target {
+    if name.eq_ignore_ascii_case("DIRECT") {
+        Target::Direct
+    } else {
+        Target::Proxy
+    }
+}
+
+/// `localhost`, `192.168.*`, `*.local`, `10.0.0.0/8` and `<local>`
+fn bypass_condition(entry: &str) -> Option<String> {
+    if entry == "<local>" {
+        return Some("isPlainHostName(host)".into());
+    }
+    if entry.contains('/') {
+        return cidr_condition(entry);
+    }
+    if entry.contains(':') {
+        // IPv6 地址在 pac 中以原样出现
+        return Some(format!("host === {}", js_string(entry)));
+    }
+    Some(format!("shExpMatch(host, {})", js_string(entry)))
+}
+
+/// `isInNet` for an IPv4 network, only for hosts that are IP addresses
+fn cidr_condition(cidr: &str) -> Option<String> {
+    let (addr, len) = cidr.split_once('/')?;
+    let addr = addr.parse::<Ipv4Addr>().ok()?;
+    let len = len.parse::<u32>().ok().filter(|len| *len <= 32)?;
+    let mask = Ipv4Addr::from(u32::MAX.checked_shl(32 - len).unwrap_or(0));
+    Some(format!("isIp && isInNet(host, \"{addr}\", \"{mask}\")"))
+}
+
+fn js_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "\"\"".into())
+}
+
+#[test]
+fn test_generate() {
+    let rules = [
+        "DOMAIN,a.example.com,Proxy",
+        "DOMAIN-SUFFIX,example.com,DIRECT",
+        "GEOIP,CN,DIRECT",
+        "IP-CIDR,192.168.0.0/16,DIRECT,no-resolve",
+        "AND,((DOMAIN,b.test),(NETWORK,UDP)),DIRECT",
+        "DOMAIN-KEYWORD,google,Proxy",
+        "MATCH,DIRECT",
+    ]
+    .map(String::from);
+    let script = generate("rule", &rules, "localhost,<local>", "PROXY 127.0.0.1:7897");
+    assert!(script.contains("if (shExpMatch(host, \"localhost\")) return \"DIRECT\";"));
+    assert!(script.contains("if (isPlainHostName(host)) return \"DIRECT\";"));
+    assert!(script.contains("if (host === \"a.example.com\") return proxy;"));
+    assert!(script.contains(
+        "if ((host === \"example.com\" || dnsDomainIs(host, \".example.com\"))) return \"DIRECT\";"
+    ));
+    assert!(script.contains("isInNet(host, \"192.168.0.0\", \"255.255.0.0\")"));
+    assert!(script.contains("return \"DIRECT\";\n}"));
+
+    // 无法判断的代理规则之后的直连规则不能生效
+    let rules = [
+        "RULE-SET,ads,REJECT",
+        "DOMAIN-SUFFIX,example.com,DIRECT",
+        "MATCH,DIRECT",
+    ]
+    .map(String::from);
+    let script = generate("rule", &rules, "", "PROXY 127.0.0.1:7897");
+    assert!(!script.contains("example.com"));
+    assert!(script.contains("return proxy;\n}"));
+
+    let script = generate("direct", &rules, "", "PROXY 127.0.0.1:7897");
+    assert!(script.contains("return \"DIRECT\";\n}"));
+}
+
+#[test]
+fn test_cidr_condition() {
+    assert_eq!(
+        cidr_condition("10.0.0.0/8").as_deref(),
+        Some("isIp && isInNet(host, \"10.0.0.0\", \"255.0.0.0\")")
+    );
+    assert_eq!(
+        cidr_condition("0.0.0.0/0").as_deref(),
+        Some("isIp && isInNet(host, \"0.0.0.0\", \"0.0.0.0\")")
+    );
+    assert_eq!(cidr_condition("fc00::/7"), None);
+}
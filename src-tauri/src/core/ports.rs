@@ -0,0 +1,489 @@
+use crate::{
+    config::{Config, IClashTemp, IVerge},
+    core::handle,
+    logging,
+    utils::logging::Type,
+};
+use anyhow::{bail, Result};
+use serde_yaml::{Mapping, Value};
+use std::net::{SocketAddr, TcpListener};
+use sysinfo::{Pid, ProcessesToUpdate, System};
+
+/// ports tried after a taken one before giving up
+const PORT_ATTEMPTS: u16 = 100;
+
+/// The proxy listeners mihomo can move through PATCH /configs
+pub const LISTENER_KEYS: [&str; 5] = [
+    "mixed-port",
+    "socks-port",
+    "port",
+    "redir-port",
+    "tproxy-port",
+];
+
+/// A port the core listens on, keyed by its field in the clash config
+#[derive(Debug, Clone, PartialEq)]
+struct CorePort {
+    key: &'static str,
+    port: u16,
+}
+
+/// A taken port and the port it was moved to
+#[derive(Debug)]
+struct Resolved {
+    key: &'static str,
+    from: u16,
+    to: u16,
+    owner: String,
+}
+
+/// Check the ports the core is about to listen on
+///
+/// With `enable_port_auto_resolve` a taken port is moved to the next free one,
+/// saved to the clash and verge configs and the runtime config is regenerated.
+/// Otherwise an error naming the process holding the port is returned.
+pub async fn resolve_conflicts() -> Result<()> {
+    let auto_resolve = Config::verge()
+        .latest()
+        .enable_port_auto_resolve
+        .unwrap_or(true);
+    let ports = core_ports();
+    let mut used = ports.iter().map(|p| p.port).collect::<Vec<_>>();
+    let mut resolved = Vec::new();
+
+    for CorePort { key, port } in ports {
+        if is_free(port) {
+            continue;
+        }
+        let owner = port_owner(port)
+            .await
+            .unwrap_or_else(|| "another process".into());
+        if !auto_resolve {
+            let msg = format!(
+                "{key} {port} is already in use by {owner}, close it or choose another port"
+            );
+            logging!(error, Type::Core, true, "{}", msg);
+            handle::Handle::notice_message("port_conflict::error", &msg);
+            bail!(msg);
+        }
+
+        let Some(to) = next_free_port(port, &used) else {
+            let msg =
+                format!("{key} {port} is already in use by {owner} and no free port was found");
+            logging!(error, Type::Core, true, "{}", msg);
+            handle::Handle::notice_message("port_conflict::error", &msg);
+            bail!(msg);
+        };
+        used.push(to);
+        resolved.push(Resolved {
+            key,
+            from: port,
+            to,
+            owner,
+        });
+    }
+
+    if resolved.is_empty() {
+        return Ok(());
+    }
+    apply(&resolved).await?;
+
+    let msg = resolved
+        .iter()
+        .map(|r| format!("{} {} → {} ({})", r.key, r.from, r.to, r.owner))
+        .collect::<Vec<_>>()
+        .join(", ");
+    logging!(warn, Type::Core, true, "Moved ports in use: {}", msg);
+    handle::Handle::notice_message("port_conflict::resolved", msg);
+    Ok(())
+}
+
+/// The controller and the enabled proxy ports
+fn core_ports() -> Vec<CorePort> {
+    let verge = Config::verge();
+    let clash = Config::clash();
+    configured_ports(&verge.latest(), &clash.latest())
+}
+
+/// The enabled proxy listener ports, without the controller
+pub fn listener_ports() -> Vec<u16> {
+    core_ports()
+        .into_iter()
+        .filter(|p| p.key != "external-controller")
+        .map(|p| p.port)
+        .collect()
+}
+
+/// The ports set in verge for each listener, verge overrides clash.yaml
+fn verge_ports(verge: &IVerge) -> Vec<(&'static str, Option<u16>, bool)> {
+    #[allow(unused_mut)]
+    let mut ports = vec![
+        ("mixed-port", verge.verge_mixed_port, true),
+        (
+            "socks-port",
+            verge.verge_socks_port,
+            verge.verge_socks_enabled.unwrap_or(false),
+        ),
+        (
+            "port",
+            verge.verge_port,
+            verge.verge_http_enabled.unwrap_or(false),
+        ),
+    ];
+    #[cfg(not(target_os = "windows"))]
+    ports.push((
+        "redir-port",
+        verge.verge_redir_port,
+        verge.verge_redir_enabled.unwrap_or(false),
+    ));
+    #[cfg(target_os = "linux")]
+    ports.push((
+        "tproxy-port",
+        verge.verge_tproxy_port,
+        verge.verge_tproxy_enabled.unwrap_or(false),
+    ));
+    ports
+}
+
+/// Overwrite the listener ports of a clash config with the ones set in verge
+pub fn use_verge_ports(config: &mut Mapping, verge: &IVerge) {
+    for (key, port, _) in verge_ports(verge) {
+        if let Some(port) = port {
+            config.insert(key.into(), port.into());
+        }
+    }
+}
+
+/// The verge fields of the listener ports in a clash patch
+pub fn verge_patch(patch: &Mapping) -> IVerge {
+    let mut verge = IVerge::default();
+    for (key, value) in patch {
+        let port = value.as_u64().and_then(|port| u16::try_from(port).ok());
+        match (key.as_str(), port) {
+            (Some("mixed-port"), Some(port)) => verge.verge_mixed_port = Some(port),
+            (Some("socks-port"), Some(port)) => {
+                verge.verge_socks_enabled = Some(port != 0);
+                if port != 0 {
+                    verge.verge_socks_port = Some(port);
+                }
+            }
+            (Some("port"), Some(port)) => {
+                verge.verge_http_enabled = Some(port != 0);
+                if port != 0 {
+                    verge.verge_port = Some(port);
+                }
+            }
+            #[cfg(not(target_os = "windows"))]
+            (Some("redir-port"), Some(port)) => {
+                verge.verge_redir_enabled = Some(port != 0);
+                if port != 0 {
+                    verge.verge_redir_port = Some(port);
+                }
+            }
+            #[cfg(target_os = "linux")]
+            (Some("tproxy-port"), Some(port)) => {
+                verge.verge_tproxy_enabled = Some(port != 0);
+                if port != 0 {
+                    verge.verge_tproxy_port = Some(port);
+                }
+            }
+            _ => {}
+        }
+    }
+    verge
+}
+
+/// Check the enabled listeners and the controller don't share a port
+pub fn validate() -> Result<()> {
+    if let Some(msg) = find_conflict(&core_ports()) {
+        bail!(msg);
+    }
+    Ok(())
+}
+
+fn find_conflict(ports: &[CorePort]) -> Option<String> {
+    if let Some(CorePort { key, .. }) = ports.iter().find(|p| p.port == 0) {
+        return Some(format!("{key} needs a port between 1 and 65535"));
+    }
+    ports.iter().enumerate().find_map(|(i, a)| {
+        ports[i + 1..]
+            .iter()
+            .find(|b| b.port == a.port)
+            .map(|b| format!("{} and {} can't both use port {}", a.key, b.key, a.port))
+    })
+}
+
+/// The enabled proxy listeners and the controller
+fn configured_ports(verge: &IVerge, clash: &IClashTemp) -> Vec<CorePort> {
+    let mut config = clash.0.clone();
+    use_verge_ports(&mut config, verge);
+    let mut ports = verge_ports(verge)
+        .into_iter()
+        .filter(|(_, _, enabled)| *enabled)
+        .map(|(key, _, _)| CorePort {
+            key,
+            port: config
+                .get(key)
+                .and_then(Value::as_u64)
+                .and_then(|port| u16::try_from(port).ok())
+                .unwrap_or(0),
+        })
+        .collect::<Vec<_>>();
+
+    // 连接外部内核时控制接口由对方监听
+    let tcp_controller = verge.external_core().is_none()
+        && !(verge.enable_controller_ipc.unwrap_or(false)
+            && verge.disable_tcp_controller.unwrap_or(false));
+    if tcp_controller {
+        if let Ok(addr) = IClashTemp::guard_server_ctrl(&clash.0).parse::<SocketAddr>() {
+            ports.push(CorePort {
+                key: "external-controller",
+                port: addr.port(),
+            });
+        }
+    }
+    ports
+}
+
+/// Save the new ports and regenerate the runtime config
+async fn apply(resolved: &[Resolved]) -> Result<()> {
+    let mut mapping = Mapping::new();
+    let mut patch = IVerge::default();
+    for r in resolved {
+        match r.key {
+            "external-controller" => {
+                let server = IClashTemp::guard_server_ctrl(&Config::clash().latest().0);
+                let mut addr = server.parse::<SocketAddr>()?;
+                addr.set_port(r.to);
+                mapping.insert(r.key.into(), Value::from(addr.to_string()));
+            }
+            key => {
+                mapping.insert(key.into(), r.to.into());
+            }
+        }
+        match r.key {
+            "mixed-port" => patch.verge_mixed_port = Some(r.to),
+            "socks-port" => patch.verge_socks_port = Some(r.to),
+            "port" => patch.verge_port = Some(r.to),
+            #[cfg(not(target_os = "windows"))]
+            "redir-port" => patch.verge_redir_port = Some(r.to),
+            #[cfg(target_os = "linux")]
+            "tproxy-port" => patch.verge_tproxy_port = Some(r.to),
+            _ => {}
+        }
+    }
+
+    Config::clash().data().patch_config(mapping);
+    Config::clash().data().save_config()?;
+    Config::verge().draft().patch_config(patch);
+    Config::verge().apply();
+    Config::verge().data().save_file()?;
+
+    Config::generate().await?;
+    Config::runtime().apply();
+    Ok(())
+}
+
+/// The listener ports that differ between two configs, as a PATCH /configs body
+///
+/// A listener missing from `new` is turned off with port 0.
+pub fn changed_listeners(
+    old: &Mapping,
+    new: &Mapping,
+) -> serde_json::Map<String, serde_json::Value> {
+    let port = |config: &Mapping, key: &str| config.get(key).and_then(Value::as_u64).unwrap_or(0);
+    LISTENER_KEYS
+        .iter()
+        .filter(|key| port(old, key) != port(new, key))
+        .map(|key| (key.to_string(), port(new, key).into()))
+        .collect()
+}
+
+/// Fail with the owner when one of the new listener ports is taken
+pub async fn check_free(listeners: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+    for (key, port) in listeners {
+        let port = port.as_u64().unwrap_or(0) as u16;
+        if port == 0 || is_free(port) {
+            continue;
+        }
+        let owner = port_owner(port)
+            .await
+            .unwrap_or_else(|| "another process".into());
+        bail!("{key} {port} is already in use by {owner}, close it or choose another port");
+    }
+    Ok(())
+}
+
+/// Whether nothing listens on the port, on loopback or on all interfaces
+fn is_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok() && TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+fn next_free_port(port: u16, used: &[u16]) -> Option<u16> {
+    (port.saturating_add(1)..=u16::MAX)
+        .take(PORT_ATTEMPTS as usize)
+        .filter(|port| !used.contains(port))
+        .find(|port| is_free(*port))
+}
+
+/// The process listening on the port, as `name (pid 123)`
+pub(crate) async fn port_owner(port: u16) -> Option<String> {
+    let pid = listening_pid(port).await?;
+    let mut system = System::new();
+    let sys_pid = Pid::from_u32(pid);
+    system.refresh_processes(ProcessesToUpdate::Some(&[sys_pid]), true);
+    let name = system
+        .process(sys_pid)
+        .map(|process| process.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".into());
+    Some(format!("{name} (pid {pid})"))
+}
+
+#[cfg(windows)]
+async fn listening_pid(port: u16) -> Option<u32> {
+    #[allow(unused_imports)] // creation_flags必须
+    use std::os::windows::process::CommandExt;
+
+    let output = tokio::process::Command::new("netstat")
+        .args(["-ano", "-p", "TCP"])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .await
+        .ok()?;
+    parse_netstat(&String::from_utf8_lossy(&output.stdout), port)
+}
+
+#[cfg(not(windows))]
+async fn listening_pid(port: u16) -> Option<u32> {
+    let output = tokio::process::Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-t"])
+        .output()
+        .await;
+    if let Some(pid) = output.ok().and_then(|output| {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().parse().ok())
+    }) {
+        return Some(pid);
+    }
+
+    // lsof 不是所有发行版都自带
+    #[cfg(target_os = "linux")]
+    {
+        let output = tokio::process::Command::new("ss")
+            .args(["-ltnpH", &format!("sport = :{port}")])
+            .output()
+            .await
+            .ok()?;
+        parse_ss(&String::from_utf8_lossy(&output.stdout))
+    }
+    #[cfg(not(target_os = "linux"))]
+    None
+}
+
+/// `TCP    127.0.0.1:7897    0.0.0.0:0    LISTENING    1234`
+/// The state column is localized, a listening socket is recognized by its remote port 0
+#[cfg(windows)]
+fn parse_netstat(output: &str, port: u16) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let cols = line.split_whitespace().collect::<Vec<_>>();
+        if cols.len() < 5 || !cols[0].eq_ignore_ascii_case("TCP") {
+            return None;
+        }
+        let local_port = cols[1].rsplit(':').next()?.parse::<u16>().ok()?;
+        (local_port == port && cols[2].ends_with(":0"))
+            .then(|| cols[4].parse().ok())
+            .flatten()
+    })
+}
+
+/// `LISTEN 0 4096 127.0.0.1:7897 0.0.0.0:* users:(("nginx",pid=1234,fd=6))`
+#[cfg(target_os = "linux")]
+fn parse_ss(output: &str) -> Option<u32> {
+    let start = output.find("pid=")? + 4;
+    let digits = output[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    digits.parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_parse_ss() {
+    assert_eq!(
+        parse_ss("LISTEN 0 4096 127.0.0.1:7897 0.0.0.0:* users:((\"nginx\",pid=1234,fd=6))\n"),
+        Some(1234)
+    );
+    assert_eq!(parse_ss(""), None);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_parse_netstat() {
+    let output = "
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1000
+  TCP    127.0.0.1:7897         0.0.0.0:0              LISTENING       1234
+  TCP    127.0.0.1:50000        127.0.0.1:7897         ESTABLISHED     4321
+";
+    assert_eq!(parse_netstat(output, 7897), Some(1234));
+    assert_eq!(parse_netstat(output, 9097), None);
+}
+
+#[test]
+fn test_next_free_port() {
+    let taken = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = taken.local_addr().unwrap().port();
+    assert!(!is_free(port));
+    let next = next_free_port(port, &[port.saturating_add(1)]).unwrap();
+    assert!(next > port.saturating_add(1));
+}
+
+#[test]
+fn test_changed_listeners() {
+    let old: Mapping =
+        serde_yaml::from_str("mixed-port: 7897\nsocks-port: 7898\nport: 7899").unwrap();
+    let new: Mapping = serde_yaml::from_str("mixed-port: 7890\nsocks-port: 7898").unwrap();
+    let changes = changed_listeners(&old, &new);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes["mixed-port"], 7890);
+    assert_eq!(changes["port"], 0);
+    assert!(changed_listeners(&new, &new).is_empty());
+}
+
+#[test]
+fn test_find_conflict() {
+    let port = |key, port| CorePort { key, port };
+    assert_eq!(
+        find_conflict(&[port("mixed-port", 7897), port("external-controller", 9097)]),
+        None
+    );
+    assert_eq!(
+        find_conflict(&[
+            port("mixed-port", 7897),
+            port("socks-port", 7898),
+            port("external-controller", 7898)
+        ])
+        .unwrap(),
+        "socks-port and external-controller can't both use port 7898"
+    );
+    assert!(find_conflict(&[port("port", 0)]).is_some());
+}
+
+#[test]
+fn test_verge_patch() {
+    let patch: Mapping =
+        serde_yaml::from_str("mixed-port: 7890\nsocks-port: 0\nport: 7891").unwrap();
+    let verge = verge_patch(&patch);
+    assert_eq!(verge.verge_mixed_port, Some(7890));
+    assert_eq!(verge.verge_socks_enabled, Some(false));
+    assert_eq!(verge.verge_socks_port, None);
+    assert_eq!(verge.verge_http_enabled, Some(true));
+    assert_eq!(verge.verge_port, Some(7891));
+
+    let mut config: Mapping = serde_yaml::from_str("mixed-port: 7897\nport: 7899").unwrap();
+    use_verge_ports(&mut config, &verge);
+    assert_eq!(config.get("mixed-port"), Some(&Value::from(7890)));
+    assert_eq!(config.get("port"), Some(&Value::from(7891)));
+}
@@ -0,0 +1,212 @@
+use crate::{
+    config::Config,
+    core::{handle, CoreManager},
+    feat, logging, logging_error,
+    process::{AsyncHandler, StartOnce},
+    utils::{dirs, logging::Type},
+};
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::{path::PathBuf, time::Duration};
+use tokio::{sync::mpsc, time::Instant};
+
+/// wait for writes to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// wait for new configs in the import folders to settle, longer than the folder import's own check
+const IMPORT_DEBOUNCE: Duration = Duration::from_secs(3);
+
+/// Watches the files of `local` profiles and reloads the core when the current one changes
+///
+/// The profiles directory and the watched import folders are watched with the filesystem
+/// notifier of the OS, new configs in the import folders are imported.
+pub struct ProfileWatcher {
+    /// kept alive while watching, `None` until started
+    watcher: Mutex<Option<RecommendedWatcher>>,
+
+    /// the import folders currently watched
+    import_dirs: Mutex<Vec<PathBuf>>,
+
+    initialized: StartOnce,
+}
+
+impl ProfileWatcher {
+    pub fn global() -> &'static ProfileWatcher {
+        static WATCHER: OnceCell<ProfileWatcher> = OnceCell::new();
+
+        WATCHER.get_or_init(|| ProfileWatcher {
+            watcher: Mutex::new(None),
+            import_dirs: Mutex::new(Vec::new()),
+            initialized: StartOnce::new(),
+        })
+    }
+
+    /// Start watching, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Config, true, "Starting local profile watcher");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = tx.send(event);
+            }
+        })?;
+        watcher.watch(&dirs::app_profiles_dir()?, RecursiveMode::NonRecursive)?;
+        *self.watcher.lock() = Some(watcher);
+        self.watch_import_dirs();
+
+        AsyncHandler::spawn(move || async move {
+            // 应用关闭期间放入导入目录的配置
+            feat::import_watched_dirs();
+
+            // 各自等到写入停止后再处理
+            let mut reload_at: Option<Instant> = None;
+            let mut import_at: Option<Instant> = None;
+            loop {
+                let next = reload_at.into_iter().chain(import_at).min();
+                let received = match next {
+                    Some(deadline) => tokio::time::timeout_at(deadline, rx.recv()).await.ok(),
+                    None => Some(rx.recv().await),
+                };
+                match received {
+                    Some(None) => break,
+                    Some(Some(event)) => {
+                        let (profile, import) = self.affected(&event);
+                        if profile {
+                            reload_at = Some(Instant::now() + DEBOUNCE);
+                        }
+                        if import {
+                            import_at = Some(Instant::now() + IMPORT_DEBOUNCE);
+                        }
+                    }
+                    None => {}
+                }
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+
+                let now = Instant::now();
+                if import_at.is_some_and(|at| at <= now) {
+                    import_at = None;
+                    feat::import_watched_dirs();
+                }
+                if reload_at.is_some_and(|at| at <= now) {
+                    reload_at = None;
+                    Self::reload().await;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Watch the folders in `profile_import_dirs`, called again when the list changes
+    pub fn watch_import_dirs(&self) {
+        let folders: Vec<PathBuf> = Config::verge()
+            .latest()
+            .profile_import_dirs
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let mut watcher = self.watcher.lock();
+        let Some(watcher) = watcher.as_mut() else {
+            return;
+        };
+        let mut watched = self.import_dirs.lock();
+        for dir in watched.iter().filter(|dir| !folders.contains(dir)) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in folders.iter().filter(|dir| !watched.contains(dir)) {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                logging!(
+                    warn,
+                    Type::Config,
+                    true,
+                    "Can't watch import folder {}: {}",
+                    dir.display(),
+                    err
+                );
+            }
+        }
+        *watched = folders;
+    }
+
+    /// Whether an event touches the file of the current local profile and an import folder
+    fn affected(&self, event: &Event) -> (bool, bool) {
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return (false, false);
+        }
+
+        let import = {
+            let watched = self.import_dirs.lock();
+            event.paths.iter().any(|path| {
+                path.parent()
+                    .is_some_and(|parent| watched.iter().any(|dir| dir == parent))
+            })
+        };
+
+        let enabled = Config::verge()
+            .latest()
+            .enable_local_profile_watch
+            .unwrap_or(true);
+        let current_file = enabled.then(|| {
+            let profiles = Config::profiles();
+            let profiles = profiles.latest();
+            let item = profiles.get_item(&profiles.get_current()?).ok()?;
+            if item.itype.as_deref() != Some("local") {
+                return None;
+            }
+            Some(dirs::app_profiles_dir().ok()?.join(item.file.as_ref()?))
+        });
+        let profile = current_file
+            .flatten()
+            .is_some_and(|file| event.paths.contains(&file));
+        if profile {
+            logging!(
+                info,
+                Type::Config,
+                true,
+                "Local profile {} changed on disk",
+                event.paths[0].display()
+            );
+        }
+
+        (profile, import)
+    }
+
+    async fn reload() {
+        logging!(
+            info,
+            Type::Config,
+            true,
+            "Reloading core after local profile change"
+        );
+        match CoreManager::global().update_config().await {
+            Ok((true, _)) => handle::Handle::refresh_clash(),
+            Ok((false, msg)) => {
+                logging_error!(
+                    Type::Config,
+                    true,
+                    "Changed local profile failed validation: {}",
+                    msg
+                );
+                handle::Handle::notice_message("config_validate::error", msg);
+            }
+            Err(err) => {
+                logging_error!(
+                    Type::Config,
+                    true,
+                    "Failed to reload local profile: {}",
+                    err
+                );
+            }
+        }
+    }
+}
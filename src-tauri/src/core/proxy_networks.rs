@@ -0,0 +1,181 @@
+//! Setting the system proxy only on chosen networks
+//!
+//! With `sysproxy_networks` set, the system proxy is only applied while one of them is
+//! connected, matched by Wi-Fi name (SSID) or interface name. Elsewhere it is taken off
+//! like a suspended proxy, the setting itself stays on. The network watcher checks again
+//! whenever the interface addresses change.
+
+use crate::{
+    config::Config,
+    core::{network_watcher, sysopt::Sysopt},
+    logging,
+    utils::logging::Type,
+};
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use std::net::IpAddr;
+use tokio::sync::Mutex;
+
+/// serializes the checks, the watcher and a settings change may overlap
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Apply or take off the system proxy for the networks connected now
+pub async fn update() {
+    let _guard = LOCK.lock().await;
+    let rules = Config::verge()
+        .latest()
+        .sysproxy_networks
+        .clone()
+        .unwrap_or_default();
+    let allowed = rules.is_empty() || matches(&rules, &current_networks().await);
+
+    let sysopt = Sysopt::global();
+    if sysopt.is_off_network() == !allowed {
+        return;
+    }
+    logging!(
+        info,
+        Type::Network,
+        true,
+        "{} the system proxy on this network",
+        if allowed { "Applying" } else { "Not applying" }
+    );
+    if let Err(err) = sysopt.set_off_network(!allowed).await {
+        logging!(
+            warn,
+            Type::Network,
+            true,
+            "Failed to update the system proxy for this network: {}",
+            err
+        );
+    }
+}
+
+/// Names of the connected Wi-Fi networks and of the interfaces with a usable address
+pub async fn current_networks() -> Vec<String> {
+    let mut networks = wifi_names().await;
+    if let Ok(interfaces) = NetworkInterface::show() {
+        for interface in interfaces {
+            let connected = interface.addr.iter().any(|addr| {
+                let ip = match addr {
+                    Addr::V4(v4) => IpAddr::V4(v4.ip),
+                    Addr::V6(v6) => IpAddr::V6(v6.ip),
+                };
+                network_watcher::is_routable(ip)
+            });
+            if connected && !networks.contains(&interface.name) {
+                networks.push(interface.name);
+            }
+        }
+    }
+    networks
+}
+
+/// Whether any rule names a current network, case doesn't matter
+fn matches(rules: &[String], current: &[String]) -> bool {
+    rules.iter().any(|rule| {
+        let rule = rule.trim();
+        !rule.is_empty() && current.iter().any(|name| name.eq_ignore_ascii_case(rule))
+    })
+}
+
+#[cfg(target_os = "windows")]
+async fn wifi_names() -> Vec<String> {
+    #[allow(unused_imports)] // creation_flags必须
+    use std::os::windows::process::CommandExt;
+
+    let Ok(output) = tokio::process::Command::new("netsh")
+        .args(["wlan", "show", "interfaces"])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .await
+    else {
+        return vec![];
+    };
+    parse_netsh_ssids(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "linux")]
+async fn wifi_names() -> Vec<String> {
+    let Ok(output) = tokio::process::Command::new("nmcli")
+        .args(["-t", "-f", "active,ssid", "dev", "wifi"])
+        .output()
+        .await
+    else {
+        return vec![];
+    };
+    parse_nmcli_ssids(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "macos")]
+async fn wifi_names() -> Vec<String> {
+    let Ok(services) = crate::core::macos_proxy::list_services() else {
+        return vec![];
+    };
+    let mut names = Vec::new();
+    for service in services
+        .iter()
+        .filter(|service| service.device.starts_with("en"))
+    {
+        let Ok(output) = tokio::process::Command::new("networksetup")
+            .args(["-getairportnetwork", &service.device])
+            .output()
+            .await
+        else {
+            continue;
+        };
+        // 有线网卡输出的是错误信息，没有这个前缀
+        if let Some(name) = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix("Current Wi-Fi Network: ")
+        {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// `netsh wlan show interfaces`, the `SSID` field isn't translated
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_netsh_ssids(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.trim() == "SSID")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// `nmcli -t -f active,ssid dev wifi`, colons in the name are escaped
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_nmcli_ssids(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("yes:"))
+        .map(|name| name.replace("\\:", ":"))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+#[test]
+fn test_proxy_networks() {
+    let netsh = "
+There is 1 interface on the system:
+
+    Name                   : Wi-Fi
+    State                  : connected
+    SSID                   : Home: 5G
+    BSSID                  : aa:bb:cc:dd:ee:ff
+    Profile                : Home: 5G
+";
+    assert_eq!(parse_netsh_ssids(netsh), ["Home: 5G"]);
+    assert_eq!(
+        parse_nmcli_ssids("no:Neighbour\nyes:Home\\: 5G\n"),
+        ["Home: 5G"]
+    );
+
+    let current = vec!["Home: 5G".to_string(), "eth0".to_string()];
+    assert!(matches(&["home: 5g".into()], &current));
+    assert!(matches(&[" eth0 ".into()], &current));
+    assert!(!matches(&["Office".into(), "".into()], &current));
+}
@@ -0,0 +1,298 @@
+use crate::{
+    config::Config,
+    core::{handle, ports, EventDrivenProxyManager},
+    logging,
+    process::{AsyncHandler, StartOnce},
+    utils::logging::Type,
+};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// wait for the other app to finish writing before checking
+#[cfg(any(windows, target_os = "linux"))]
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// the same change is announced at most this often, some apps keep fighting
+const NOTICE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The last announced change and when
+static LAST_NOTICE: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Re-applies the system proxy as soon as another app changes it
+///
+/// On Windows the Internet Settings registry key is watched, on Linux the
+/// dconf database and `kioslaverc`. Elsewhere, or when the watch can't be
+/// set up, the settings are checked every `proxy_guard_duration` seconds.
+/// The guard itself only acts while `enable_proxy_guard` is on.
+pub struct ProxyWatcher {
+    initialized: StartOnce,
+}
+
+impl ProxyWatcher {
+    pub fn global() -> &'static ProxyWatcher {
+        static WATCHER: OnceCell<ProxyWatcher> = OnceCell::new();
+
+        WATCHER.get_or_init(|| ProxyWatcher {
+            initialized: StartOnce::new(),
+        })
+    }
+
+    /// Start watching, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        #[cfg(any(windows, target_os = "linux"))]
+        std::thread::spawn(|| {
+            logging!(info, Type::System, true, "Watching system proxy settings");
+            let result = watch_blocking(|| {
+                std::thread::sleep(SETTLE);
+                if !handle::Handle::global().is_exiting() {
+                    EventDrivenProxyManager::global().force_check();
+                }
+            });
+            if let Err(err) = result {
+                logging!(
+                    warn,
+                    Type::System,
+                    true,
+                    "Can't watch system proxy settings, checking periodically: {}",
+                    err
+                );
+                Self::poll();
+            }
+        });
+        #[cfg(not(any(windows, target_os = "linux")))]
+        Self::poll();
+
+        Ok(())
+    }
+
+    fn poll() {
+        AsyncHandler::spawn(|| async {
+            loop {
+                let interval = Config::verge()
+                    .latest()
+                    .proxy_guard_duration
+                    .unwrap_or(30)
+                    .max(1);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                EventDrivenProxyManager::global().force_check();
+            }
+        });
+    }
+}
+
+/// Log and announce that another app changed the system proxy
+///
+/// `found` is the proxy address or PAC url that was set instead of ours,
+/// `None` when the proxy was turned off. An app is only named when it is the
+/// one listening on the local proxy address, the OS doesn't record who wrote
+/// the setting.
+pub async fn report_tampering(found: Option<String>) {
+    let culprit = find_culprit(found.as_deref()).await;
+    let found = found.unwrap_or_else(|| "off".into());
+    logging!(
+        warn,
+        Type::System,
+        true,
+        "System proxy changed to {} by {}, re-applying",
+        found,
+        culprit.as_deref().unwrap_or("an unknown app")
+    );
+
+    let message = match &culprit {
+        Some(culprit) => format!("{culprit} → {found}"),
+        None => found,
+    };
+    {
+        let mut last = LAST_NOTICE.lock();
+        if last
+            .as_ref()
+            .is_some_and(|(m, at)| *m == message && at.elapsed() < NOTICE_INTERVAL)
+        {
+            return;
+        }
+        *last = Some((message.clone(), Instant::now()));
+    }
+    let status = if culprit.is_some() {
+        "proxy_guard::tampered"
+    } else {
+        "proxy_guard::restored"
+    };
+    handle::Handle::notice_message(status, message);
+}
+
+async fn find_culprit(found: Option<&str>) -> Option<String> {
+    ports::port_owner(found.and_then(local_port)?).await
+}
+
+/// The port of `host:port` or a PAC url when the host is this machine
+fn local_port(address: &str) -> Option<u16> {
+    let address = address.split_once("://").map_or(address, |(_, rest)| rest);
+    let authority = address.split('/').next()?;
+    let (host, port) = authority.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let local = host.eq_ignore_ascii_case("localhost") || host == "::1" || host.starts_with("127.");
+    local.then(|| port.parse().ok()).flatten()
+}
+
+/// Block until the key can't be watched anymore, calling `on_change` for every change
+#[cfg(windows)]
+fn watch_blocking(on_change: impl Fn()) -> Result<()> {
+    use anyhow::bail;
+    use winapi::um::{winnt::REG_NOTIFY_CHANGE_LAST_SET, winreg::RegNotifyChangeKeyValue};
+    use winreg::{
+        enums::{HKEY_CURRENT_USER, KEY_NOTIFY},
+        RegKey,
+    };
+
+    let key = RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(
+        r"Software\Microsoft\Windows\CurrentVersion\Internet Settings",
+        KEY_NOTIFY,
+    )?;
+    loop {
+        // 同步等待，子键 Connections 中保存了 PAC 与代理设置
+        let status = unsafe {
+            RegNotifyChangeKeyValue(
+                key.raw_handle() as _,
+                1,
+                REG_NOTIFY_CHANGE_LAST_SET,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if status != 0 {
+            bail!("RegNotifyChangeKeyValue failed with {status}");
+        }
+        on_change();
+    }
+}
+
+/// Block until the files can't be watched anymore, calling `on_change` for every change
+#[cfg(target_os = "linux")]
+fn watch_blocking(on_change: impl Fn()) -> Result<()> {
+    use anyhow::{bail, Context};
+    use std::{
+        ffi::CString,
+        io,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::ffi::OsStrExt,
+        },
+    };
+
+    let config = ::dirs::config_dir().context("no config directory")?;
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        bail!("inotify_init1 failed: {}", io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // 两者都是写入临时文件后重命名
+    let mut watches = Vec::new();
+    for (dir, file) in [
+        (config.join("dconf"), "user"),
+        (config.clone(), "kioslaverc"),
+    ] {
+        let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+            continue;
+        };
+        let wd = unsafe {
+            libc::inotify_add_watch(
+                fd.as_raw_fd(),
+                path.as_ptr(),
+                libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE,
+            )
+        };
+        if wd >= 0 {
+            watches.push((wd, file));
+        }
+    }
+    if watches.is_empty() {
+        bail!("neither dconf nor kioslaverc can be watched");
+    }
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            bail!("reading inotify events failed: {err}");
+        }
+        if is_watched(&buf[..len as usize], &watches) {
+            on_change();
+        }
+    }
+}
+
+/// Whether a batch of `inotify_event`s touches one of the watched files
+#[cfg(target_os = "linux")]
+fn is_watched(events: &[u8], watches: &[(i32, &str)]) -> bool {
+    let header = std::mem::size_of::<libc::inotify_event>();
+    let mut offset = 0;
+    while offset + header <= events.len() {
+        let event = unsafe {
+            std::ptr::read_unaligned(events[offset..].as_ptr().cast::<libc::inotify_event>())
+        };
+        let start = offset + header;
+        let end = (start + event.len as usize).min(events.len());
+        // 文件名以 \0 结尾并补齐
+        let name = events[start..end]
+            .split(|b| *b == 0)
+            .next()
+            .unwrap_or_default();
+        if watches
+            .iter()
+            .any(|(wd, file)| *wd == event.wd && name == file.as_bytes())
+        {
+            return true;
+        }
+        offset = end;
+    }
+    false
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_is_watched() {
+    let event = |wd: i32, name: &str| {
+        let mut name = name.as_bytes().to_vec();
+        name.resize(16, 0);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&wd.to_ne_bytes());
+        bytes.extend_from_slice(&libc::IN_MOVED_TO.to_ne_bytes());
+        bytes.extend_from_slice(&0u32.to_ne_bytes());
+        bytes.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+        bytes.extend_from_slice(&name);
+        bytes
+    };
+    let watches = [(1, "user"), (2, "kioslaverc")];
+
+    let mut events = event(1, "user.ABC123");
+    assert!(!is_watched(&events, &watches));
+    events.extend(event(2, "kioslaverc"));
+    assert!(is_watched(&events, &watches));
+    assert!(!is_watched(&event(2, "user"), &watches));
+}
+
+#[test]
+fn test_local_port() {
+    assert_eq!(local_port("127.0.0.1:7890"), Some(7890));
+    assert_eq!(
+        local_port("http://localhost:33331/commands/pac"),
+        Some(33331)
+    );
+    assert_eq!(local_port("http://[::1]:1080/proxy.pac"), Some(1080));
+    assert_eq!(local_port("proxy.corp.example:3128"), None);
+    assert_eq!(local_port("http://wpad/wpad.dat"), None);
+}
@@ -0,0 +1,222 @@
+//! Revalidation of the core after the system wakes up from sleep
+//!
+//! Sleep can leave the core wedged, drop the routes of the TUN device or let the system proxy
+//! settings be reset. Wake-ups come from the power events of the OS: `WM_POWERBROADCAST` on
+//! the session window of `utils::shutdown` on Windows, `NSWorkspaceDidWakeNotification` on
+//! macOS and logind's `PrepareForSleep` signal on Linux.
+
+use crate::{
+    config::Config,
+    core::{handle, sysopt, CoreManager, NetworkWatcher, RunningMode},
+    logging, logging_error,
+    module::mihomo::MihomoManager,
+    process::AsyncHandler,
+    utils::logging::Type,
+};
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// give the network stack a moment to come back before probing
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// pings tried before the core is considered wedged
+const PING_ATTEMPTS: u32 = 3;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Handle a wake-up reported by the OS
+pub fn woke_up() {
+    logging!(info, Type::System, true, "System woke up from sleep");
+    NetworkWatcher::global().woke_up();
+    AsyncHandler::spawn(revalidate);
+}
+
+/// Subscribe to the wake-up notification of macOS
+#[cfg(target_os = "macos")]
+pub fn listen() {
+    use block2::RcBlock;
+    use objc2_app_kit::{NSWorkspace, NSWorkspaceDidWakeNotification};
+    use objc2_foundation::NSNotification;
+    use std::ptr::NonNull;
+
+    let Some(app_handle) = handle::Handle::global().app_handle() else {
+        return;
+    };
+    // 通知中心的观察者要在主线程注册
+    let result = app_handle.run_on_main_thread(|| unsafe {
+        let block = RcBlock::new(|_: NonNull<NSNotification>| woke_up());
+        let center = NSWorkspace::sharedWorkspace().notificationCenter();
+        let observer = center.addObserverForName_object_queue_usingBlock(
+            Some(NSWorkspaceDidWakeNotification),
+            None,
+            None,
+            &block,
+        );
+        // 应用运行期间一直观察
+        std::mem::forget(observer);
+    });
+    if let Err(err) = result {
+        logging!(warn, Type::System, true, "Can't observe wake-ups: {}", err);
+    }
+}
+
+/// Follow logind's `PrepareForSleep` signal, which is `false` after waking up
+#[cfg(target_os = "linux")]
+pub fn listen() {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+    };
+
+    std::thread::spawn(|| {
+        let child = Command::new("gdbus")
+            .args([
+                "monitor",
+                "--system",
+                "--dest",
+                "org.freedesktop.login1",
+                "--object-path",
+                "/org/freedesktop/login1",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Some(stdout) = child.ok().and_then(|mut child| child.stdout.take()) else {
+            logging!(
+                warn,
+                Type::System,
+                true,
+                "Can't monitor logind for wake-ups"
+            );
+            return;
+        };
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if is_wake_signal(&line) {
+                woke_up();
+            }
+        }
+    });
+}
+
+/// `/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)`
+#[cfg(target_os = "linux")]
+fn is_wake_signal(line: &str) -> bool {
+    line.contains(".PrepareForSleep (false")
+}
+
+/// Ping the core and restart it when it does not answer, then re-apply TUN and the system proxy
+/// A wake-up noticed while the previous one is still handled is ignored
+pub async fn revalidate() {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    revalidate_core().await;
+    RUNNING.store(false, Ordering::SeqCst);
+}
+
+async fn revalidate_core() {
+    tokio::time::sleep(SETTLE_DELAY).await;
+    let manager = CoreManager::global();
+    let mode = manager.get_running_mode().await;
+    if handle::Handle::global().is_exiting() || mode == RunningMode::NotRunning {
+        return;
+    }
+    logging!(info, Type::Core, true, "Checking core after resume");
+
+    if !responds().await {
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Core not answering after resume, restarting"
+        );
+        // 重启会重新创建 TUN 设备
+        if let Err(err) = manager.restart_core().await {
+            logging!(
+                error,
+                Type::Core,
+                true,
+                "Failed to restart core after resume: {}",
+                err
+            );
+            handle::Handle::notice_message("core_resume::failed", format!("{err}"));
+            return;
+        }
+        handle::Handle::notice_message("core_resume::restarted", "");
+    } else if mode != RunningMode::External
+        && Config::verge().latest().enable_tun_mode.unwrap_or(false)
+        && !tun_device_up()
+    {
+        reapply_tun().await;
+    }
+
+    logging_error!(
+        Type::Core,
+        true,
+        sysopt::Sysopt::global().update_sysproxy().await
+    );
+    handle::Handle::refresh_clash();
+}
+
+async fn responds() -> bool {
+    for attempt in 1..=PING_ATTEMPTS {
+        match MihomoManager::global().ping("/version", PING_TIMEOUT).await {
+            Ok(_) => return true,
+            Err(err) => logging!(
+                warn,
+                Type::Core,
+                true,
+                "Core ping {}/{} after resume failed: {}",
+                attempt,
+                PING_ATTEMPTS,
+                err
+            ),
+        }
+    }
+    false
+}
+
+/// Whether the TUN device survived the sleep with its address in the fake-ip range
+fn tun_device_up() -> bool {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return true;
+    };
+    interfaces
+        .iter()
+        .flat_map(|interface| interface.addr.iter())
+        .any(|addr| match addr {
+            Addr::V4(v4) => matches!(v4.ip.octets(), [198, 18 | 19, ..]),
+            Addr::V6(_) => false,
+        })
+}
+
+/// The device is gone or lost its address, re-creating it also restores the routes
+async fn reapply_tun() {
+    for enable in [false, true] {
+        let patch = serde_json::json!({ "tun": { "enable": enable } });
+        if let Err(err) = MihomoManager::global().patch_configs(patch).await {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Failed to re-create TUN after resume: {}",
+                err
+            );
+            return;
+        }
+    }
+    logging!(info, Type::Core, true, "TUN re-created after resume");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_is_wake_signal() {
+    let signal = "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep";
+    assert!(is_wake_signal(&format!("{signal} (false,)")));
+    assert!(!is_wake_signal(&format!("{signal} (true,)")));
+}
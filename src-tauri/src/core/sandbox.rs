@@ -0,0 +1,172 @@
+//! Reduced privileges for the core process
+//!
+//! The core only needs to create the TUN device, set routes and bind low ports.
+//! The sandbox is only offered on Linux: when the app runs as root through `sudo` or
+//! `pkexec`, the core is started with `setpriv` as the user who ran it, keeping only those
+//! capabilities. Elsewhere there is no way to keep TUN working without full privileges, and
+//! the service starts the core on its own terms, so the option does nothing there. Whether the
+//! running core really has reduced privileges is checked after every start and shown to the
+//! user.
+
+use crate::{config::Config, core::handle, logging, utils::logging::Type};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// linux capabilities the core keeps, enough for TUN, routing and ports below 1024
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub const CORE_CAPABILITIES: &[&str] = &["net_admin", "net_bind_service", "net_raw"];
+
+/// Whether the sandbox is on and what the running core actually got
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SandboxStatus {
+    pub enabled: bool,
+    /// the running core does not run as root
+    pub applied: bool,
+    /// why the core runs unrestricted while the sandbox is on
+    pub reason: Option<String>,
+}
+
+static STATUS: Mutex<SandboxStatus> = Mutex::new(SandboxStatus {
+    enabled: false,
+    applied: false,
+    reason: None,
+});
+
+/// The option is on and this platform has a sandbox
+pub fn is_enabled() -> bool {
+    cfg!(target_os = "linux")
+        && Config::verge()
+            .latest()
+            .enable_core_sandbox
+            .unwrap_or(false)
+}
+
+/// The state found by the last [`verify`]
+pub fn status() -> SandboxStatus {
+    STATUS.lock().clone()
+}
+
+/// Check the privileges of the running core, the user is told when the sandbox is on
+/// but the core still runs unrestricted
+pub async fn verify() {
+    let enabled = is_enabled();
+    let (applied, reason) = if enabled {
+        match tokio::task::spawn_blocking(core_is_restricted).await {
+            Ok(Ok(())) => (true, None),
+            Ok(Err(reason)) => (false, Some(reason)),
+            Err(err) => (false, Some(err.to_string())),
+        }
+    } else {
+        (false, None)
+    };
+
+    // 每次重启内核都会检查，原因不变时只提醒一次
+    let repeated = STATUS.lock().reason == reason;
+    if let Some(reason) = reason.as_ref().filter(|_| !repeated) {
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Core sandbox not applied: {}",
+            reason
+        );
+        handle::Handle::notice_message("core_sandbox::not_applied", reason);
+    }
+    *STATUS.lock() = SandboxStatus {
+        enabled,
+        applied,
+        reason,
+    };
+}
+
+/// Look the core process up by its binary name, so the service mode is covered too
+#[cfg(target_os = "linux")]
+fn core_is_restricted() -> Result<(), String> {
+    use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let name = Config::verge().latest().get_valid_clash_core();
+    let pid = system
+        .processes_by_exact_name(std::ffi::OsStr::new(&name))
+        .next()
+        .map(|process| process.pid())
+        .ok_or("the core process was not found")?;
+
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status"))
+        .map_err(|err| format!("can't read the core process status: {err}"))?;
+    match effective_uid(&status) {
+        Some(0) => Err("the core runs as root".into()),
+        Some(_) => Ok(()),
+        None => Err("can't read the user of the core process".into()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn core_is_restricted() -> Result<(), String> {
+    Err("the core sandbox is only available on Linux".into())
+}
+
+/// The effective uid from the `Uid:` line of `/proc/<pid>/status`: real, effective, saved, fs
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn effective_uid(status: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// `setpriv` arguments that run the sidecar as the user who started the app through sudo or pkexec
+/// `None` when the sandbox is off or the app is not running as root
+#[cfg(target_os = "linux")]
+pub fn setpriv_args() -> Option<Vec<String>> {
+    if !is_enabled() || unsafe { libc::geteuid() } != 0 {
+        return None;
+    }
+    let env_id = |name: &str| -> Option<u32> { std::env::var(name).ok()?.parse().ok() };
+    let (uid, gid) = match (env_id("SUDO_UID"), env_id("SUDO_GID")) {
+        (Some(uid), Some(gid)) => (uid, gid),
+        _ => {
+            // pkexec 只提供 uid，组取该用户的主组
+            let uid = env_id("PKEXEC_UID")?;
+            let passwd = unsafe { libc::getpwuid(uid) };
+            if passwd.is_null() {
+                return None;
+            }
+            (uid, unsafe { (*passwd).pw_gid })
+        }
+    };
+    if uid == 0 {
+        return None;
+    }
+
+    let caps = CORE_CAPABILITIES
+        .iter()
+        .map(|cap| format!("+{cap}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    Some(vec![
+        format!("--reuid={uid}"),
+        format!("--regid={gid}"),
+        "--clear-groups".into(),
+        format!("--inh-caps=-all,{caps}"),
+        format!("--ambient-caps=-all,{caps}"),
+        format!("--bounding-set=-all,{caps}"),
+        "--".into(),
+    ])
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn setpriv_args() -> Option<Vec<String>> {
+    None
+}
+
+#[test]
+fn test_effective_uid() {
+    let status = "Name:\tmihomo\nUmask:\t0022\nUid:\t1000\t65534\t65534\t65534\nGid:\t100\n";
+    assert_eq!(effective_uid(status), Some(65534));
+    assert_eq!(effective_uid("Name:\tmihomo\n"), None);
+}
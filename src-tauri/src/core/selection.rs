@@ -0,0 +1,186 @@
+use crate::{
+    config::{Config, PrfSelected},
+    logging,
+    module::mihomo::MihomoManager,
+    utils::logging::Type,
+};
+use anyhow::Result;
+use parking_lot::{const_rwlock, RwLock};
+use serde_json::Value;
+
+/// The profile whose selections the core is running with
+static ACTIVE_PROFILE: RwLock<Option<String>> = const_rwlock(None);
+
+/// Save the proxies selected in the core into the profile it is running
+///
+/// Called before the config is reloaded or the core is stopped, when the
+/// selections are still live. The profile may already have been switched in
+/// the draft, so the profile that was restored last is used.
+pub async fn snapshot() {
+    let Some(uid) = ACTIVE_PROFILE.read().clone() else {
+        return;
+    };
+    let proxies = match MihomoManager::global().get_refresh_proxies().await {
+        Ok(proxies) => proxies,
+        Err(err) => {
+            logging!(
+                debug,
+                Type::Core,
+                true,
+                "Skip saving selected proxies: {}",
+                err
+            );
+            return;
+        }
+    };
+    let selected = selectors(&proxies)
+        .into_iter()
+        .map(|(name, now, _)| PrfSelected {
+            name: Some(name),
+            now: Some(now),
+        })
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        return;
+    }
+    if let Err(err) = save(&uid, &selected) {
+        logging!(
+            warn,
+            Type::Core,
+            true,
+            "Failed to save selected proxies: {}",
+            err
+        );
+    }
+}
+
+/// Select the saved proxies of the current profile again
+///
+/// A reload or a restart resets every selector to its first proxy, and the
+/// core's own cache is keyed by group name only, so it mixes up profiles
+/// sharing a group name.
+pub async fn restore() {
+    let (uid, saved) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let Some(uid) = profiles.get_current() else {
+            return;
+        };
+        let saved = profiles
+            .get_item(&uid)
+            .ok()
+            .and_then(|item| item.selected.clone())
+            .unwrap_or_default();
+        (uid, saved)
+    };
+    *ACTIVE_PROFILE.write() = Some(uid);
+    if saved.is_empty() {
+        return;
+    }
+
+    let proxies = match MihomoManager::global().get_refresh_proxies().await {
+        Ok(proxies) => proxies,
+        Err(err) => {
+            logging!(
+                warn,
+                Type::Core,
+                true,
+                "Failed to restore selected proxies: {}",
+                err
+            );
+            return;
+        }
+    };
+    let mut restored = 0;
+    for (group, now, all) in selectors(&proxies) {
+        let Some(target) = saved
+            .iter()
+            .find(|s| s.name.as_deref() == Some(group.as_str()))
+            .and_then(|s| s.now.as_deref())
+        else {
+            continue;
+        };
+        // 订阅更新后节点可能已不存在
+        if target == now || !all.iter().any(|name| name == target) {
+            continue;
+        }
+        match MihomoManager::global().select_proxy(&group, target).await {
+            Ok(_) => restored += 1,
+            Err(err) => logging!(
+                warn,
+                Type::Core,
+                true,
+                "Failed to select {} in {}: {}",
+                target,
+                group,
+                err
+            ),
+        }
+    }
+    if restored > 0 {
+        logging!(
+            info,
+            Type::Core,
+            true,
+            "Restored {} selected proxies",
+            restored
+        );
+    }
+}
+
+/// Write to the saved profiles and to a pending draft, which would otherwise
+/// overwrite them when applied
+fn save(uid: &str, selected: &[PrfSelected]) -> Result<()> {
+    Config::profiles().latest().merge_selected(uid, selected);
+    let profiles = Config::profiles();
+    let mut data = profiles.data();
+    if data.merge_selected(uid, selected) {
+        data.save_file()?;
+    }
+    Ok(())
+}
+
+/// `(group, now, all)` of every selector group in a `/proxies` response
+fn selectors(proxies: &Value) -> Vec<(String, String, Vec<String>)> {
+    let Some(proxies) = proxies["proxies"].as_object() else {
+        return Vec::new();
+    };
+    proxies
+        .iter()
+        .filter(|(_, proxy)| proxy["type"].as_str() == Some("Selector"))
+        .filter_map(|(name, proxy)| {
+            let now = proxy["now"].as_str()?.to_string();
+            let all = proxy["all"]
+                .as_array()?
+                .iter()
+                .filter_map(|name| name.as_str().map(String::from))
+                .collect();
+            Some((name.clone(), now, all))
+        })
+        .collect()
+}
+
+#[test]
+fn test_selectors() {
+    let proxies = serde_json::json!({
+        "proxies": {
+            "GLOBAL": { "type": "Selector", "now": "Proxy", "all": ["DIRECT", "Proxy"] },
+            "Proxy": { "type": "Selector", "now": "HK", "all": ["HK", "JP"] },
+            "Auto": { "type": "URLTest", "now": "JP", "all": ["HK", "JP"] },
+            "HK": { "type": "Shadowsocks" }
+        }
+    });
+    let mut groups = selectors(&proxies);
+    groups.sort();
+    assert_eq!(
+        groups,
+        vec![
+            (
+                "GLOBAL".into(),
+                "Proxy".into(),
+                vec!["DIRECT".into(), "Proxy".into()]
+            ),
+            ("Proxy".into(), "HK".into(), vec!["HK".into(), "JP".into()]),
+        ]
+    );
+}
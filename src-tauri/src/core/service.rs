@@ -1,13 +1,15 @@
 use crate::{
-    config::Config,
-    core::service_ipc::{send_ipc_request, IpcCommand},
-    logging,
+    config::{Config, IVerge},
+    core::{
+        handle,
+        service_ipc::{send_ipc_request, IpcCommand},
+    },
+    feat, logging,
     utils::{dirs, logging::Type},
 };
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use std::{
-    env::current_exe,
     path::PathBuf,
     process::Command as StdCommand,
     time::{SystemTime, UNIX_EPOCH},
@@ -795,9 +797,7 @@ pub(super) async fn start_with_existing_service(config_file: &PathBuf) -> Result
 
     let clash_core = Config::verge().latest().get_valid_clash_core();
 
-    let bin_ext = if cfg!(windows) { ".exe" } else { "" };
-    let clash_bin = format!("{clash_core}{bin_ext}");
-    let bin_path = current_exe()?.with_file_name(clash_bin);
+    let bin_path = super::CoreManager::core_bin_path(&clash_core)?;
     let bin_path = dirs::path_to_str(&bin_path)?;
 
     let config_dir = dirs::app_home_dir()?;
@@ -809,13 +809,18 @@ pub(super) async fn start_with_existing_service(config_file: &PathBuf) -> Result
     let config_file = dirs::path_to_str(config_file)?;
 
     // 构建启动参数
-    let payload = serde_json::json!({
+    let mut payload = serde_json::json!({
         "core_type": clash_core,
         "bin_path": bin_path,
         "config_dir": config_dir,
         "config_file": config_file,
         "log_file": log_path,
     });
+    if let (Some(payload), serde_json::Value::Object(launch)) =
+        (payload.as_object_mut(), super::launch::service_payload())
+    {
+        payload.extend(launch);
+    }
 
     // log::info!(target:"app", "启动服务参数: {:?}", payload);
     // logging!(info, Type::Service, true, "发送StartClash请求");
@@ -974,6 +979,45 @@ pub(super) async fn run_core_by_service(config_file: &PathBuf) -> Result<()> {
     }
 }
 
+/// 通过服务开关网卡的 IPv6，服务以管理员身份运行，不需要每次提权
+///
+/// 网卡在 Windows 上是适配器名，macOS 上是网络服务名，Linux 上是接口名
+pub async fn set_ipv6_by_service(adapters: &[String], enable: bool) -> Result<()> {
+    let payload = serde_json::json!({
+        "adapters": adapters,
+        "enable": enable,
+    });
+    let response = send_ipc_request(IpcCommand::SetIpv6, payload)
+        .await
+        .context("Unable to connect to Koala Clash Service")?;
+
+    if !response.success {
+        bail!(response
+            .error
+            .unwrap_or_else(|| "Failed to change the IPv6 binding".to_string()));
+    }
+    Ok(())
+}
+
+/// 通过服务替换局域网访问的防火墙规则，`ports` 为空时只删除
+pub async fn set_firewall_rule_by_service(name: &str, program: &str, ports: &[u16]) -> Result<()> {
+    let payload = serde_json::json!({
+        "name": name,
+        "program": program,
+        "ports": ports,
+    });
+    let response = send_ipc_request(IpcCommand::SetFirewallRule, payload)
+        .await
+        .context("Unable to connect to Koala Clash Service")?;
+
+    if !response.success {
+        bail!(response
+            .error
+            .unwrap_or_else(|| "Failed to update the firewall rule".to_string()));
+    }
+    Ok(())
+}
+
 /// 通过服务停止core
 pub(super) async fn stop_core_by_service() -> Result<()> {
     logging!(info, Type::Service, true, "Stopping core via service (IPC)");
@@ -1073,6 +1117,123 @@ pub async fn force_reinstall_service() -> Result<()> {
         }
     }
 }
+
+/// 服务的健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceHealth {
+    /// 未安装或已卸载
+    NotInstalled,
+    /// 握手成功且版本符合
+    Healthy,
+    /// 握手成功但版本不符
+    Outdated,
+    /// 已安装但无法握手
+    Unreachable,
+}
+
+/// 服务状态，供界面显示与修复
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub health: ServiceHealth,
+    pub version: Option<String>,
+    pub required_version: String,
+    pub last_error: Option<String>,
+}
+
+/// 通过 IPC 握手检查服务的安装状态与版本
+pub async fn service_status() -> ServiceStatus {
+    let state = ServiceState::get();
+    let (health, version) = match check_service_version().await {
+        Ok(version) if version == REQUIRED_SERVICE_VERSION => {
+            (ServiceHealth::Healthy, Some(version))
+        }
+        Ok(version) => (ServiceHealth::Outdated, Some(version)),
+        // 没有安装记录时握手失败视为未安装
+        Err(_) if state.last_install_time == 0 => (ServiceHealth::NotInstalled, None),
+        Err(_) => (ServiceHealth::Unreachable, None),
+    };
+    ServiceStatus {
+        health,
+        version,
+        required_version: REQUIRED_SERVICE_VERSION.to_string(),
+        last_error: state.last_error,
+    }
+}
+
+/// 启动时校验服务，握手失败或版本不符时提示修复
+pub async fn verify_at_startup() {
+    let status = service_status().await;
+    let detail = match status.health {
+        ServiceHealth::NotInstalled | ServiceHealth::Healthy => return,
+        ServiceHealth::Outdated => format!(
+            "version {} (required {})",
+            status.version.unwrap_or_default(),
+            status.required_version
+        ),
+        ServiceHealth::Unreachable => status
+            .last_error
+            .unwrap_or_else(|| "IPC handshake failed".to_string()),
+    };
+    logging!(
+        warn,
+        Type::Service,
+        true,
+        "Service needs repair: {:?}, {}",
+        status.health,
+        detail
+    );
+    handle::Handle::notice_message("service::repair_needed", detail);
+}
+
+/// 卸载服务并回到 Sidecar 模式
+///
+/// 没有服务且非管理员时 TUN 无法运行，先关闭 TUN；
+/// 同时清除安装记录并偏好 Sidecar，下次启动不会自动重装
+pub async fn remove_service() -> Result<()> {
+    let tun = Config::verge().latest().enable_tun_mode.unwrap_or(false);
+    if tun && !is_elevated() {
+        logging!(
+            info,
+            Type::Service,
+            true,
+            "Disabling TUN mode before uninstalling service"
+        );
+        // 走完整的设置流程，TUN 的关闭与核心更新都在其中
+        feat::patch_verge(
+            IVerge {
+                enable_tun_mode: Some(false),
+                ..IVerge::default()
+            },
+            false,
+        )
+        .await?;
+    }
+
+    uninstall_service().await?;
+
+    ServiceState {
+        prefer_sidecar: true,
+        ..ServiceState::default()
+    }
+    .save()
+}
+
+/// 应用自身是否以管理员身份运行
+fn is_elevated() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        use deelevate::{PrivilegeLevel, Token};
+        Token::with_current_process()
+            .and_then(|token| token.privilege_level())
+            .map(|level| level != PrivilegeLevel::NotPrivileged)
+            .unwrap_or(false)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        unsafe { libc::geteuid() == 0 }
+    }
+}
 /*
 /// 彻底诊断服务状态，检查安装状态、IPC通信和服务版本
  pub async fn diagnose_service() -> Result<()> {
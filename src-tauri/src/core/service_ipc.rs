@@ -12,12 +12,20 @@ const IPC_SOCKET_NAME: &str = if cfg!(windows) {
 };
 
 // 定义命令类型
+// 权限划分：服务以 root/SYSTEM 运行，负责启动和停止内核；应用只发送请求，不需要管理员权限
+// StartClash 的 payload 中 restricted 为 true 时，服务应以受限身份启动内核，
+// Linux 上只保留 capabilities 中的权限，Windows 上使用 restricted_token 启动，见 sandbox.rs
+// SetIpv6 的 payload 为 { adapters, enable }，由服务开关物理网卡的 IPv6，见 ipv6_guard.rs
+// SetFirewallRule 的 payload 为 { name, program, ports }，由服务替换同名的入站规则，
+// ports 为空时只删除，见 firewall.rs
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IpcCommand {
     GetClash,
     GetVersion,
     StartClash,
     StopClash,
+    SetIpv6,
+    SetFirewallRule,
 }
 
 // IPC消息格式
@@ -1,52 +1,136 @@
+#[cfg(target_os = "linux")]
+use crate::core::linux_proxy;
+#[cfg(target_os = "macos")]
+use crate::core::macos_proxy;
+#[cfg(target_os = "windows")]
+use crate::core::winhttp_proxy;
 #[cfg(target_os = "windows")]
 use crate::utils::autostart as startup_shortcut;
 use crate::{
-    config::{Config, IVerge},
-    core::{handle::Handle, EventDrivenProxyManager},
+    config::{decrypt_data, encrypt_data, Config, IVerge},
+    core::{
+        bypass::{self, Platform},
+        handle::Handle,
+        EventDrivenProxyManager,
+    },
     logging, logging_error,
-    utils::logging::Type,
+    utils::{dirs, logging::Type},
 };
 use anyhow::Result;
 use once_cell::sync::OnceCell;
-use std::sync::Arc;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 #[cfg(not(target_os = "windows"))]
 use sysproxy::{Autoproxy, Sysproxy};
 use tauri::async_runtime::Mutex as TokioMutex;
 use tauri_plugin_autostart::ManagerExt;
 
+/// A macOS network service the system proxy can be set on, other platforms have none
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NetworkService {
+    pub name: String,
+    /// `en0`, empty for services without hardware such as VPNs
+    pub device: String,
+    /// carries the default route
+    pub active: bool,
+}
+
 pub struct Sysopt {
     update_sysproxy: Arc<TokioMutex<bool>>,
     reset_sysproxy: Arc<TokioMutex<bool>>,
+    /// 内核不可用时暂时关闭系统代理，不修改用户设置
+    suspended: AtomicBool,
+    /// 不在设置的网络中时不设置系统代理
+    off_network: AtomicBool,
+    /// 登录认证页面期间不设置系统代理
+    portal_bypass: AtomicBool,
 }
 
-#[cfg(target_os = "windows")]
-static DEFAULT_BYPASS: &str = "localhost;127.*;192.168.*;10.*;172.16.*;172.17.*;172.18.*;172.19.*;172.20.*;172.21.*;172.22.*;172.23.*;172.24.*;172.25.*;172.26.*;172.27.*;172.28.*;172.29.*;172.30.*;172.31.*;<local>";
-#[cfg(target_os = "linux")]
-static DEFAULT_BYPASS: &str =
-    "localhost,127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,::1";
-#[cfg(target_os = "macos")]
-static DEFAULT_BYPASS: &str =
-    "127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,172.29.0.0/16,localhost,*.local,*.crashlytics.com,<local>";
-
-fn get_bypass() -> String {
-    let use_default = Config::verge().latest().use_default_bypass.unwrap_or(true);
-    let res = {
-        let verge = Config::verge();
-        let verge = verge.latest();
-        verge.system_proxy_bypass.clone()
-    };
-    let custom_bypass = match res {
-        Some(bypass) => bypass,
-        None => "".to_string(),
-    };
-
-    if custom_bypass.is_empty() {
-        DEFAULT_BYPASS.to_string()
-    } else if use_default {
-        format!("{DEFAULT_BYPASS},{custom_bypass}")
-    } else {
-        custom_bypass
-    }
+/// Write the proxy settings, on Linux through the desktop's own settings and
+/// on macOS only to the selected network services
+#[cfg(not(target_os = "windows"))]
+fn apply_proxy(sys: &Sysproxy, auto: &Autoproxy) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    return linux_proxy::apply(sys, auto);
+    #[cfg(target_os = "macos")]
+    return macos_proxy::apply(sys, auto);
+}
+
+/// Our proxy and PAC settings, both turned off
+#[cfg(not(target_os = "windows"))]
+fn own_proxy() -> (Sysproxy, Autoproxy) {
+    let port = Config::verge()
+        .latest()
+        .verge_mixed_port
+        .unwrap_or(Config::clash().data().get_mixed_port());
+    let pac_port = IVerge::get_singleton_port();
+    let proxy_host = Config::verge()
+        .latest()
+        .proxy_host
+        .clone()
+        .unwrap_or_else(|| String::from("127.0.0.1"));
+    (
+        Sysproxy {
+            enable: false,
+            host: proxy_host.clone(),
+            port,
+            bypass: get_bypass(),
+        },
+        Autoproxy {
+            enable: false,
+            url: format!("http://{proxy_host}:{pac_port}/commands/pac"),
+        },
+    )
+}
+
+/// The proxy settings found before our first change, see `save_original_proxy`
+const ORIGINAL_PROXY_FILE: &str = ".sysproxy-original";
+
+/// Keep the proxy settings found before our first change on disk, so they are
+/// put back even after a crash. They may hold a proxy password and are encrypted.
+/// `None` removes them once restored.
+pub(crate) fn save_original_proxy<T: Serialize>(original: Option<&T>) {
+    let result = (|| -> Result<()> {
+        let path = dirs::app_home_dir()?.join(ORIGINAL_PROXY_FILE);
+        match original {
+            Some(original) => {
+                let json = serde_json::to_string(original)?;
+                let encrypted = encrypt_data(&json).map_err(|e| anyhow::anyhow!("{e}"))?;
+                std::fs::write(path, encrypted)?;
+            }
+            None if path.exists() => std::fs::remove_file(path)?,
+            None => {}
+        }
+        Ok(())
+    })();
+    logging_error!(Type::System, true, result);
+}
+
+/// The proxy settings kept by `save_original_proxy`
+pub(crate) fn load_original_proxy<T: DeserializeOwned>() -> Option<T> {
+    let path = dirs::app_home_dir().ok()?.join(ORIGINAL_PROXY_FILE);
+    let encrypted = std::fs::read_to_string(path).ok()?;
+    let json = decrypt_data(encrypted.trim()).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Put back the desktop proxy settings found before our first change, also
+/// called directly while exiting
+#[cfg(not(target_os = "windows"))]
+pub fn restore_original_proxy() -> Result<()> {
+    let (sysproxy, autoproxy) = own_proxy();
+    #[cfg(target_os = "linux")]
+    return linux_proxy::restore(&sysproxy, &autoproxy);
+    #[cfg(target_os = "macos")]
+    return macos_proxy::restore(&sysproxy, &autoproxy);
+}
+
+/// The bypass list in the format of the current platform
+pub(crate) fn get_bypass() -> String {
+    bypass::current().serialize(Platform::current())
 }
 
 impl Sysopt {
@@ -55,6 +139,9 @@ impl Sysopt {
         SYSOPT.get_or_init(|| Sysopt {
             update_sysproxy: Arc::new(TokioMutex::new(false)),
             reset_sysproxy: Arc::new(TokioMutex::new(false)),
+            suspended: AtomicBool::new(false),
+            off_network: AtomicBool::new(false),
+            portal_bypass: AtomicBool::new(false),
         })
     }
 
@@ -67,6 +154,60 @@ impl Sysopt {
         Ok(())
     }
 
+    /// 暂停系统代理，返回之前是否未暂停
+    pub async fn suspend_sysproxy(&self) -> Result<bool> {
+        if self.suspended.swap(true, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        self.update_sysproxy().await?;
+        Ok(true)
+    }
+
+    /// 恢复暂停的系统代理，返回之前是否已暂停
+    pub async fn resume_sysproxy(&self) -> Result<bool> {
+        if !self.suspended.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        self.update_sysproxy().await?;
+        Ok(true)
+    }
+
+    /// 暂停、不在设置的网络中或正在登录认证页面
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::SeqCst)
+            || self.is_off_network()
+            || self.portal_bypass.load(Ordering::SeqCst)
+    }
+
+    /// 离开或回到设置的网络
+    pub async fn set_off_network(&self, off: bool) -> Result<()> {
+        self.set_flag(&self.off_network, off).await
+    }
+
+    /// 进入或退出认证页面的绕过模式
+    pub async fn set_portal_bypass(&self, bypass: bool) -> Result<()> {
+        self.set_flag(&self.portal_bypass, bypass).await
+    }
+
+    /// 标记变化时重新设置，系统代理关闭时不修改
+    async fn set_flag(&self, flag: &AtomicBool, on: bool) -> Result<()> {
+        if flag.swap(on, Ordering::SeqCst) == on {
+            return Ok(());
+        }
+        if !Config::verge()
+            .latest()
+            .enable_system_proxy
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+        self.update_sysproxy().await
+    }
+
+    pub fn is_off_network(&self) -> bool {
+        self.off_network.load(Ordering::SeqCst)
+    }
+
     /// init the sysproxy
     pub async fn update_sysproxy(&self) -> Result<()> {
         if Handle::global().is_exiting() {
@@ -85,7 +226,7 @@ impl Sysopt {
             let verge = Config::verge();
             let verge = verge.latest();
             (
-                verge.enable_system_proxy.unwrap_or(false),
+                verge.enable_system_proxy.unwrap_or(false) && !self.is_suspended(),
                 verge.proxy_auto_config.unwrap_or(false),
                 verge
                     .proxy_host
@@ -108,8 +249,7 @@ impl Sysopt {
             };
 
             if !sys_enable {
-                sys.set_system_proxy()?;
-                auto.set_auto_proxy()?;
+                apply_proxy(&sys, &auto)?;
                 let proxy_manager = EventDrivenProxyManager::global();
                 proxy_manager.notify_config_changed();
                 return Ok(());
@@ -118,8 +258,7 @@ impl Sysopt {
             if pac_enable {
                 sys.enable = false;
                 auto.enable = true;
-                sys.set_system_proxy()?;
-                auto.set_auto_proxy()?;
+                apply_proxy(&sys, &auto)?;
                 let proxy_manager = EventDrivenProxyManager::global();
                 proxy_manager.notify_config_changed();
                 return Ok(());
@@ -128,8 +267,7 @@ impl Sysopt {
             if sys_enable {
                 auto.enable = false;
                 sys.enable = true;
-                auto.set_auto_proxy()?;
-                sys.set_system_proxy()?;
+                apply_proxy(&sys, &auto)?;
                 let proxy_manager = EventDrivenProxyManager::global();
                 proxy_manager.notify_config_changed();
                 return Ok(());
@@ -180,6 +318,28 @@ impl Sysopt {
             if !output.status.success() {
                 bail!("sysproxy exe run failed");
             }
+
+            // WinHTTP 没有 PAC 设置，只跟随全局代理
+            let address = format!("{proxy_host}:{port}");
+            let winhttp = Config::verge()
+                .latest()
+                .enable_winhttp_proxy
+                .unwrap_or(false);
+            if winhttp && !pac_enable {
+                // 没有管理员权限时写入失败，告诉用户而不只是记录日志
+                if let Err(err) = winhttp_proxy::apply(&address, &get_bypass()) {
+                    logging!(
+                        error,
+                        Type::System,
+                        true,
+                        "Failed to set WinHTTP proxy: {}",
+                        err
+                    );
+                    Handle::notice_message("winhttp_proxy::error", err.to_string());
+                }
+            } else {
+                logging_error!(Type::System, true, winhttp_proxy::restore(&address));
+            }
         }
         let proxy_manager = EventDrivenProxyManager::global();
         proxy_manager.notify_config_changed();
@@ -188,38 +348,34 @@ impl Sysopt {
     }
 
     /// reset the sysproxy
+    /// Turn the system proxy off, also while exiting, which is when it matters most
     pub async fn reset_sysproxy(&self) -> Result<()> {
-        if Handle::global().is_exiting() {
-            log::debug!(target: "app", "Application is exiting, skip resetting sysproxy");
-            return Ok(());
-        }
         let _lock = self.reset_sysproxy.lock().await;
         //直接关闭所有代理
         #[cfg(not(target_os = "windows"))]
-        {
-            let mut sysproxy: Sysproxy = Sysproxy::get_system_proxy()?;
-            let mut autoproxy = match Autoproxy::get_auto_proxy() {
-                Ok(ap) => ap,
-                Err(e) => {
-                    log::warn!(target: "app", "Failed to get auto proxy config while resetting: {e}, using default config");
-                    Autoproxy {
-                        enable: false,
-                        url: "".to_string(),
-                    }
-                }
-            };
-            sysproxy.enable = false;
-            autoproxy.enable = false;
-            autoproxy.set_auto_proxy()?;
-            sysproxy.set_system_proxy()?;
-        }
+        restore_original_proxy()?;
 
         #[cfg(target_os = "windows")]
         {
-            use crate::{core::handle::Handle, utils::dirs};
             use anyhow::bail;
             use tauri_plugin_shell::ShellExt;
 
+            // 先恢复 WinHTTP 代理，sysproxy.exe 失败时也不会跳过
+            let port = Config::verge()
+                .latest()
+                .verge_mixed_port
+                .unwrap_or(Config::clash().data().get_mixed_port());
+            let proxy_host = Config::verge()
+                .latest()
+                .proxy_host
+                .clone()
+                .unwrap_or_else(|| String::from("127.0.0.1"));
+            logging_error!(
+                Type::System,
+                true,
+                winhttp_proxy::restore(&format!("{proxy_host}:{port}"))
+            );
+
             let app_handle = Handle::global().app_handle().unwrap();
 
             let binary_path = dirs::service_path()?;
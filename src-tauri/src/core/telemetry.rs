@@ -0,0 +1,304 @@
+use crate::{
+    config::Config,
+    core::{handle, CoreManager, RunningMode},
+    logging, logging_error,
+    module::mihomo::MihomoManager,
+    process::{AsyncHandler, StartOnce},
+    utils::logging::Type,
+};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{collections::VecDeque, ffi::OsString, time::Duration};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// how often the core is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// samples kept for the graph, ten minutes at the default interval
+const MAX_SAMPLES: usize = 300;
+
+/// the memory warning is shown again once usage dropped below this share of the limit
+const WARNING_RESET_RATIO: f64 = 0.9;
+
+/// a pending restart waits for a sample with less traffic than this
+const IDLE_TRAFFIC_BYTES: u64 = 64 * 1024;
+
+/// What to do once the core stayed over `core_memory_limit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LimitAction {
+    /// close all connections and drop the DNS cache so the core can release them
+    Flush,
+    /// restart the core once it is idle
+    Restart,
+}
+
+impl LimitAction {
+    fn from_config(action: Option<&str>) -> Self {
+        match action {
+            Some("restart") => LimitAction::Restart,
+            _ => LimitAction::Flush,
+        }
+    }
+}
+
+/// Consecutive samples over the memory limit and the state of a pending restart
+#[derive(Debug, Default)]
+struct LimitState {
+    over: u32,
+    restart_pending: bool,
+    /// total traffic of the core at the previous sample
+    traffic: Option<u64>,
+}
+
+/// One resource sample of the running core
+/// mihomo does not expose goroutine counts without its debug build, so they are not sampled
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreSample {
+    /// unix time in milliseconds
+    pub timestamp: i64,
+    /// bytes in use reported by the core
+    pub memory: Option<u64>,
+    /// resident memory of the core process
+    pub rss: Option<u64>,
+    /// cpu usage of the core process, 100 is one full core
+    pub cpu: Option<f32>,
+}
+
+/// Samples the memory reported by the core and the process stats while the core is running
+pub struct CoreTelemetry {
+    initialized: StartOnce,
+    samples: Mutex<VecDeque<CoreSample>>,
+}
+
+impl CoreTelemetry {
+    pub fn global() -> &'static CoreTelemetry {
+        static TELEMETRY: OnceCell<CoreTelemetry> = OnceCell::new();
+
+        TELEMETRY.get_or_init(|| CoreTelemetry {
+            initialized: StartOnce::new(),
+            samples: Mutex::new(VecDeque::with_capacity(MAX_SAMPLES)),
+        })
+    }
+
+    /// Start the sampling loop, only the first call has an effect
+    pub fn init(&'static self) -> Result<()> {
+        if !self.initialized.first() {
+            return Ok(());
+        }
+
+        logging!(info, Type::Core, true, "Starting core telemetry sampler");
+        AsyncHandler::spawn(move || async move {
+            let mut system = System::new();
+            let mut pid: Option<Pid> = None;
+            let mut warned = false;
+            let mut limit = LimitState::default();
+
+            loop {
+                tokio::time::sleep(SAMPLE_INTERVAL).await;
+                if handle::Handle::global().is_exiting() {
+                    break;
+                }
+                if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+                    pid = None;
+                    continue;
+                }
+
+                let memory = MihomoManager::global().get_memory().await.ok();
+                let (rss, cpu) = sample_process(&mut system, &mut pid);
+                let sample = CoreSample {
+                    timestamp: chrono::Local::now().timestamp_millis(),
+                    memory,
+                    rss,
+                    cpu,
+                };
+
+                warned = self.check_memory(&sample, warned);
+                self.check_limit(&sample, &mut limit).await;
+                if let Ok(value) = serde_json::to_value(&sample) {
+                    handle::Handle::notify_core_telemetry(value);
+                }
+
+                let mut samples = self.samples.lock();
+                if samples.len() == MAX_SAMPLES {
+                    samples.pop_front();
+                }
+                samples.push_back(sample);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// The recent samples, oldest first
+    pub fn samples(&self) -> Vec<CoreSample> {
+        self.samples.lock().iter().cloned().collect()
+    }
+
+    /// Notice once when the core goes over `core_memory_warning`, returns whether it is still over
+    fn check_memory(&self, sample: &CoreSample, warned: bool) -> bool {
+        let Some(limit) = Config::verge()
+            .latest()
+            .core_memory_warning
+            .filter(|l| *l > 0)
+        else {
+            return false;
+        };
+        let Some(used) = sample.rss.or(sample.memory) else {
+            return warned;
+        };
+
+        let limit = limit * 1024 * 1024;
+        if used > limit {
+            if !warned {
+                logging!(
+                    warn,
+                    Type::Core,
+                    true,
+                    "Core memory usage {} MB is over the limit",
+                    used / 1024 / 1024
+                );
+                handle::Handle::notice_message(
+                    "core_telemetry::memory_warning",
+                    format!("{} MB", used / 1024 / 1024),
+                );
+            }
+            true
+        } else {
+            warned && (used as f64) > limit as f64 * WARNING_RESET_RATIO
+        }
+    }
+
+    /// Remediate once the core stayed over `core_memory_limit` for `core_memory_limit_samples` samples
+    async fn check_limit(&self, sample: &CoreSample, state: &mut LimitState) {
+        let (limit, samples, action) = {
+            let verge = Config::verge();
+            let verge = verge.latest();
+            (
+                verge.core_memory_limit.unwrap_or(0),
+                verge.core_memory_limit_samples.unwrap_or(5).max(1),
+                LimitAction::from_config(verge.core_memory_limit_action.as_deref()),
+            )
+        };
+        if limit == 0 {
+            *state = LimitState::default();
+            return;
+        }
+
+        if state.restart_pending && action == LimitAction::Restart {
+            if is_idle(state).await {
+                self.restart_core().await;
+                *state = LimitState::default();
+            }
+            return;
+        }
+        state.restart_pending = false;
+
+        let Some(used) = sample.rss.or(sample.memory) else {
+            return;
+        };
+        if used <= limit * 1024 * 1024 {
+            state.over = 0;
+            return;
+        }
+        state.over += 1;
+        if state.over < samples {
+            return;
+        }
+
+        state.over = 0;
+        let used = format!("{} MB", used / 1024 / 1024);
+        match action {
+            LimitAction::Flush => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Core memory usage {} stayed over the limit of {} MB, closing all connections",
+                    used,
+                    limit
+                );
+                logging_error!(
+                    Type::Core,
+                    true,
+                    MihomoManager::global().close_all_connections().await
+                );
+                if let Err(err) = MihomoManager::global().flush_dns_cache().await {
+                    logging!(warn, Type::Core, true, "Failed to flush DNS cache: {}", err);
+                }
+                handle::Handle::notice_message("core_telemetry::memory_flushed", used);
+            }
+            LimitAction::Restart => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Core memory usage {} stayed over the limit of {} MB, restarting once idle",
+                    used,
+                    limit
+                );
+                state.restart_pending = true;
+                state.traffic = None;
+            }
+        }
+    }
+
+    async fn restart_core(&self) {
+        logging!(warn, Type::Core, true, "Restarting core to release memory");
+        match CoreManager::global().restart_core().await {
+            Ok(_) => {
+                self.samples.lock().clear();
+                handle::Handle::notice_message("core_telemetry::memory_restarted", "");
+            }
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Core,
+                    true,
+                    "Failed to restart core over the memory limit: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Whether the core moved less than `IDLE_TRAFFIC_BYTES` since the previous sample
+async fn is_idle(state: &mut LimitState) -> bool {
+    let Ok(connections) = MihomoManager::global().get_connections().await else {
+        return false;
+    };
+    let total = connections["uploadTotal"].as_u64().unwrap_or(0)
+        + connections["downloadTotal"].as_u64().unwrap_or(0);
+    let previous = state.traffic.replace(total);
+    previous.is_some_and(|previous| total.saturating_sub(previous) < IDLE_TRAFFIC_BYTES)
+}
+
+/// Resident memory and cpu usage of the core process
+/// The pid is looked up by the binary name so the service mode is covered too
+fn sample_process(system: &mut System, pid: &mut Option<Pid>) -> (Option<u64>, Option<f32>) {
+    let refresh = ProcessRefreshKind::nothing().with_memory().with_cpu();
+    if let Some(known) = *pid {
+        system.refresh_processes_specifics(ProcessesToUpdate::Some(&[known]), true, refresh);
+        if system.process(known).is_none() {
+            *pid = None;
+        }
+    }
+
+    if pid.is_none() {
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+        let mut name = OsString::from(Config::verge().latest().get_valid_clash_core());
+        if cfg!(windows) {
+            name.push(".exe");
+        }
+        *pid = system
+            .processes_by_exact_name(&name)
+            .next()
+            .map(|process| process.pid());
+    }
+
+    pid.and_then(|pid| system.process(pid))
+        .map(|process| (Some(process.memory()), Some(process.cpu_usage())))
+        .unwrap_or((None, None))
+}
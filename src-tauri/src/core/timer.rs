@@ -1,16 +1,32 @@
-use crate::{config::Config, feat, logging, logging_error, utils::logging::Type};
+use crate::{
+    config::Config,
+    core::CoreManager,
+    feat, logging, logging_error,
+    utils::{logging::Type, metered},
+};
 use anyhow::{Context, Result};
 use delay_timer::prelude::{DelayTimer, DelayTimerBuilder, TaskBuilder};
 use once_cell::sync::OnceCell;
 use parking_lot::{Mutex, RwLock};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 type TaskID = u64;
 
+/// seconds of slack before an interval task is considered not yet due
+const SKIP_TOLERANCE_SECS: i64 = 60;
+
+/// upper bound of the failure backoff, as a power of two of the interval
+const MAX_BACKOFF_SHIFT: u32 = 5;
+
 #[derive(Debug, Clone)]
 pub struct TimerTask {
     pub task_id: TaskID,
     pub interval_minutes: u64,
+    /// cron expression, takes precedence over `interval_minutes`
+    pub cron: Option<String>,
     #[allow(unused)]
     pub last_run: i64, // Timestamp of last execution
 }
@@ -27,6 +43,9 @@ pub struct Timer {
 
     /// Flag to mark if timer is initialized - atomic for better performance
     pub initialized: Arc<std::sync::atomic::AtomicBool>,
+
+    /// profiles whose update was deferred on a metered network
+    pub deferred: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Timer {
@@ -38,6 +57,7 @@ impl Timer {
             timer_map: Arc::new(RwLock::new(HashMap::new())),
             timer_count: Arc::new(Mutex::new(1)),
             initialized: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            deferred: Arc::new(Mutex::new(HashSet::new())),
         })
     }
 
@@ -81,9 +101,10 @@ impl Timer {
             logging!(
                 info,
                 Type::Timer,
-                "Registered timer task - uid={}, interval={}min, task_id={}",
+                "Registered timer task - uid={}, interval={}min, cron={:?}, task_id={}",
                 uid,
                 task.interval_minutes,
+                task.cron,
                 task.task_id
             );
         }
@@ -95,7 +116,12 @@ impl Timer {
             items
                 .iter()
                 .filter_map(|item| {
-                    let interval = item.option.as_ref()?.update_interval? as i64;
+                    let option = item.option.as_ref()?;
+                    // cron schedules fire at their own time points
+                    if option.update_cron.is_some() {
+                        return None;
+                    }
+                    let interval = option.update_interval? as i64;
                     let updated = item.updated? as i64;
                     let uid = item.uid.as_ref()?;
 
@@ -179,23 +205,24 @@ impl Timer {
                         logging!(debug, Type::Timer, "Removed task {} for uid {}", tid, uid);
                     }
                 }
-                DiffFlag::Add(tid, interval) => {
+                DiffFlag::Add(tid, schedule) => {
                     let task = TimerTask {
                         task_id: tid,
-                        interval_minutes: interval,
+                        interval_minutes: schedule.interval_minutes(),
+                        cron: schedule.cron(),
                         last_run: chrono::Local::now().timestamp(),
                     };
 
                     timer_map.insert(uid.clone(), task);
 
-                    if let Err(e) = self.add_task(&mut delay_timer, uid.clone(), tid, interval) {
+                    if let Err(e) = self.add_task(&mut delay_timer, uid.clone(), tid, &schedule) {
                         logging_error!(Type::Timer, "Failed to add task for uid {}: {}", uid, e);
                         timer_map.remove(&uid); // Rollback on failure
                     } else {
                         logging!(debug, Type::Timer, "Added task {} for uid {}", tid, uid);
                     }
                 }
-                DiffFlag::Mod(tid, interval) => {
+                DiffFlag::Mod(tid, schedule) => {
                     // Remove old task first
                     if let Err(e) = delay_timer.remove_task(tid) {
                         logging!(
@@ -211,13 +238,14 @@ impl Timer {
                     // Then add the new one
                     let task = TimerTask {
                         task_id: tid,
-                        interval_minutes: interval,
+                        interval_minutes: schedule.interval_minutes(),
+                        cron: schedule.cron(),
                         last_run: chrono::Local::now().timestamp(),
                     };
 
                     timer_map.insert(uid.clone(), task);
 
-                    if let Err(e) = self.add_task(&mut delay_timer, uid.clone(), tid, interval) {
+                    if let Err(e) = self.add_task(&mut delay_timer, uid.clone(), tid, &schedule) {
                         logging_error!(Type::Timer, "Failed to update task for uid {}: {}", uid, e);
                         timer_map.remove(&uid); // Rollback on failure
                     } else {
@@ -230,24 +258,43 @@ impl Timer {
         Ok(())
     }
 
-    /// Generate map of profile UIDs to update intervals
-    fn gen_map(&self) -> HashMap<String, u64> {
+    /// Generate map of profile UIDs to update schedules
+    fn gen_map(&self) -> HashMap<String, TaskSchedule> {
         let mut new_map = HashMap::new();
 
         if let Some(items) = Config::profiles().latest().get_items() {
             for item in items.iter() {
-                if let Some(option) = item.option.as_ref() {
-                    if let (Some(interval), Some(uid)) = (option.update_interval, &item.uid) {
-                        if interval > 0 {
-                            logging!(
-                                debug,
-                                Type::Timer,
-                                "Found scheduled update config: uid={}, interval={}min",
-                                uid,
-                                interval
-                            );
-                            new_map.insert(uid.clone(), interval);
-                        }
+                let (Some(option), Some(uid)) = (item.option.as_ref(), &item.uid) else {
+                    continue;
+                };
+                if item.is_locked() {
+                    continue;
+                }
+
+                if let Some(cron) = option.update_cron.as_deref().map(str::trim) {
+                    if !cron.is_empty() {
+                        logging!(
+                            debug,
+                            Type::Timer,
+                            "Found scheduled update config: uid={}, cron={}",
+                            uid,
+                            cron
+                        );
+                        new_map.insert(uid.clone(), TaskSchedule::Cron(cron.to_string()));
+                        continue;
+                    }
+                }
+
+                if let Some(interval) = option.update_interval {
+                    if interval > 0 {
+                        logging!(
+                            debug,
+                            Type::Timer,
+                            "Found scheduled update config: uid={}, interval={}min",
+                            uid,
+                            interval
+                        );
+                        new_map.insert(uid.clone(), TaskSchedule::Interval(interval));
                     }
                 }
             }
@@ -279,17 +326,18 @@ impl Timer {
         // Find tasks to modify or delete
         for (uid, task) in timer_map.iter() {
             match new_map.get(uid) {
-                Some(&interval) if interval != task.interval_minutes => {
-                    // Task exists but interval changed
+                Some(schedule) if !schedule.matches(task) => {
+                    // Task exists but schedule changed
                     logging!(
                         debug,
                         Type::Timer,
-                        "Timer task interval changed: uid={}, old={}, new={}",
+                        "Timer task schedule changed: uid={}, old={}min/{:?}, new={:?}",
                         uid,
                         task.interval_minutes,
-                        interval
+                        task.cron,
+                        schedule
                     );
-                    diff_map.insert(uid.clone(), DiffFlag::Mod(task.task_id, interval));
+                    diff_map.insert(uid.clone(), DiffFlag::Mod(task.task_id, schedule.clone()));
                 }
                 None => {
                     // Task no longer needed
@@ -297,7 +345,7 @@ impl Timer {
                     diff_map.insert(uid.clone(), DiffFlag::Del(task.task_id));
                 }
                 _ => {
-                    // Task exists with same interval, no change needed
+                    // Task exists with same schedule, no change needed
                     logging!(debug, Type::Timer, "Timer task unchanged: uid={}", uid);
                 }
             }
@@ -306,16 +354,16 @@ impl Timer {
         // Find new tasks to add
         let mut next_id = *self.timer_count.lock();
 
-        for (uid, &interval) in new_map.iter() {
+        for (uid, schedule) in new_map.iter() {
             if !timer_map.contains_key(uid) {
                 logging!(
                     debug,
                     Type::Timer,
-                    "Added timer task: uid={}, interval={}min",
+                    "Added timer task: uid={}, schedule={:?}",
                     uid,
-                    interval
+                    schedule
                 );
-                diff_map.insert(uid.clone(), DiffFlag::Add(next_id, interval));
+                diff_map.insert(uid.clone(), DiffFlag::Add(next_id, schedule.clone()));
                 next_id += 1;
             }
         }
@@ -341,22 +389,35 @@ impl Timer {
         delay_timer: &mut DelayTimer,
         uid: String,
         tid: TaskID,
-        minutes: u64,
+        schedule: &TaskSchedule,
     ) -> Result<()> {
         logging!(
             info,
             Type::Timer,
-            "Adding task: uid={}, id={}, interval={}min",
+            "Adding task: uid={}, id={}, schedule={:?}",
             uid,
             tid,
-            minutes
+            schedule
         );
 
+        // The builder borrows the cron expression, so it has to outlive it
+        let cron_expr = match schedule {
+            TaskSchedule::Cron(expr) => Some(normalize_cron(expr)),
+            TaskSchedule::Interval(_) => None,
+        };
+
         // Create a task with reasonable retries and backoff
-        let task = TaskBuilder::default()
+        let mut builder = TaskBuilder::default();
+        builder
             .set_task_id(tid)
-            .set_maximum_parallel_runnable_num(1)
-            .set_frequency_repeated_by_minutes(minutes)
+            .set_maximum_parallel_runnable_num(1);
+        if let Some(expr) = cron_expr.as_deref() {
+            builder.set_frequency_repeated_by_cron_str(expr);
+        } else {
+            builder.set_frequency_repeated_by_minutes(schedule.interval_minutes());
+        }
+
+        let task = builder
             .spawn_async_routine(move || {
                 let uid = uid.clone();
                 async move {
@@ -416,6 +477,17 @@ impl Timer {
 
         let updated = profile.updated.unwrap_or(0) as i64;
 
+        // Cron schedules are driven by the timer itself, there is no fixed interval to add
+        if task.cron.is_some() {
+            logging!(
+                debug,
+                Type::Timer,
+                "Profile uses cron schedule, next update time is not derived, uid={}",
+                uid
+            );
+            return None;
+        }
+
         // Calculate next update time
         if updated > 0 && task.interval_minutes > 0 {
             let next_time = updated + (task.interval_minutes as i64 * 60);
@@ -439,6 +511,45 @@ impl Timer {
         }
     }
 
+    /// Run the updates deferred on a metered network, once the network is no longer metered
+    pub async fn run_deferred(&self) {
+        if metered::should_defer_updates().await {
+            return;
+        }
+
+        // 重新生成配置，恢复内核的 GeoData 自动更新
+        if metered::take_deferred_geo_auto_update() {
+            logging!(
+                info,
+                Type::Timer,
+                "Restoring GeoData auto update after metered network"
+            );
+            if let Err(err) = CoreManager::global().update_config().await {
+                logging!(
+                    warn,
+                    Type::Timer,
+                    "Failed to restore GeoData auto update: {}",
+                    err
+                );
+            }
+        }
+
+        if self.deferred.lock().is_empty() {
+            return;
+        }
+
+        let uids: Vec<String> = self.deferred.lock().drain().collect();
+        logging!(
+            info,
+            Type::Timer,
+            "Running {} updates deferred on metered network",
+            uids.len()
+        );
+        for uid in uids {
+            Self::async_task(uid).await;
+        }
+    }
+
     /// Emit update events for frontend notification
     fn emit_update_event(_uid: &str, _is_start: bool) {
         #[cfg(any(feature = "verge-dev", feature = "default"))]
@@ -451,12 +562,90 @@ impl Timer {
         }
     }
 
+    /// Whether an interval-scheduled profile was refreshed recently enough to skip this run,
+    /// e.g. after a manual update, or is still backing off after repeated failures
+    fn should_skip(uid: &str) -> bool {
+        let interval = {
+            let timer_map = Timer::global().timer_map.read();
+            match timer_map.get(uid) {
+                Some(task) if task.cron.is_none() => task.interval_minutes,
+                _ => return false,
+            }
+        };
+
+        let (updated, fail_count, last_failure) = {
+            let profiles = Config::profiles();
+            let profiles = profiles.latest();
+            match profiles.get_item(&uid.to_string()) {
+                Ok(item) => (
+                    item.updated.unwrap_or(0) as i64,
+                    item.fail_count.unwrap_or(0),
+                    item.last_failure.unwrap_or(0) as i64,
+                ),
+                Err(_) => return false,
+            }
+        };
+
+        // allow a small tolerance so that timer jitter does not skip a due update
+        let now = chrono::Local::now().timestamp();
+        let interval_secs = interval as i64 * 60;
+        if updated > 0 && now - updated + SKIP_TOLERANCE_SECS < interval_secs {
+            return true;
+        }
+
+        // double the effective interval for each consecutive failure
+        if fail_count > 1 && last_failure > 0 {
+            let factor = 1_i64 << (fail_count - 1).min(MAX_BACKOFF_SHIFT);
+            if now - last_failure + SKIP_TOLERANCE_SECS < interval_secs * factor {
+                logging!(
+                    info,
+                    Type::Timer,
+                    "Profile {} failed {} times in a row, backing off",
+                    uid,
+                    fail_count
+                );
+                return true;
+            }
+        }
+
+        false
+    }
+
     /// Async task with better error handling and logging
     async fn async_task(uid: String) {
         let task_start = std::time::Instant::now();
         logging!(info, Type::Timer, "Running timer task for profile: {}", uid);
 
-        match tokio::time::timeout(std::time::Duration::from_secs(40), async {
+        if Self::should_skip(&uid) {
+            logging!(
+                info,
+                Type::Timer,
+                "Skipping timer task, profile is not due yet: {}",
+                uid
+            );
+            return;
+        }
+
+        if metered::should_defer_updates().await {
+            logging!(
+                info,
+                Type::Timer,
+                "Deferring timer task on metered network: {}",
+                uid
+            );
+            Timer::global().deferred.lock().insert(uid);
+            return;
+        }
+
+        // leave room for every download retry configured by the user
+        let attempts = Config::verge()
+            .latest()
+            .profile_retry_attempts
+            .unwrap_or(3)
+            .clamp(1, 10) as u64;
+        let timeout = std::time::Duration::from_secs(20 + 20 * attempts);
+
+        match tokio::time::timeout(timeout, async {
             Self::emit_update_event(&uid, true);
 
             let is_current = Config::profiles().latest().current.as_ref() == Some(&uid);
@@ -497,9 +686,48 @@ impl Timer {
     }
 }
 
+/// Update schedule of a profile timer task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskSchedule {
+    /// repeat every N minutes
+    Interval(u64),
+    /// repeat following a cron expression
+    Cron(String),
+}
+
+impl TaskSchedule {
+    fn interval_minutes(&self) -> u64 {
+        match self {
+            TaskSchedule::Interval(minutes) => *minutes,
+            TaskSchedule::Cron(_) => 0,
+        }
+    }
+
+    fn cron(&self) -> Option<String> {
+        match self {
+            TaskSchedule::Interval(_) => None,
+            TaskSchedule::Cron(expr) => Some(expr.clone()),
+        }
+    }
+
+    fn matches(&self, task: &TimerTask) -> bool {
+        self.interval_minutes() == task.interval_minutes && self.cron() == task.cron
+    }
+}
+
+/// delay_timer expects a seconds field in front of the usual five cron fields
+fn normalize_cron(expr: &str) -> String {
+    let expr = expr.trim();
+    if expr.starts_with('@') || expr.split_whitespace().count() != 5 {
+        expr.to_string()
+    } else {
+        format!("0 {expr}")
+    }
+}
+
 #[derive(Debug)]
 enum DiffFlag {
     Del(TaskID),
-    Add(TaskID, u64),
-    Mod(TaskID, u64),
+    Add(TaskID, TaskSchedule),
+    Mod(TaskID, TaskSchedule),
 }
@@ -0,0 +1,213 @@
+//! User-defined entries of the tray menu
+//!
+//! Every entry of `tray_custom_items` opens a link, runs one of the internal actions also
+//! available as hotkeys, or runs a script. Only scripts in the `scripts` folder of the app
+//! directory can be run, an entry names the file and nothing else.
+
+use crate::{
+    config::{Config, IVergeTrayItem},
+    feat, logging,
+    module::lightweight,
+    process::AsyncHandler,
+    utils::{dirs, logging::Type},
+};
+use anyhow::{bail, Result};
+
+/// menu ids are this prefix followed by the index of the entry
+pub const MENU_PREFIX: &str = "custom_";
+
+/// the internal actions an entry can run, named like the hotkey functions
+const COMMANDS: [&str; 9] = [
+    "open_or_close_dashboard",
+    "clash_mode_rule",
+    "clash_mode_global",
+    "clash_mode_direct",
+    "toggle_system_proxy",
+    "toggle_tun_mode",
+    "entry_lightweight_mode",
+    "restart_clash",
+    "restart_app",
+];
+
+/// Check the entries before they are saved
+pub fn validate(items: &[IVergeTrayItem]) -> Result<()> {
+    for item in items {
+        let label = item.label.as_deref().unwrap_or_default();
+        if label.trim().is_empty() {
+            bail!("a tray menu entry needs a label");
+        }
+        if let Err(err) = check(item) {
+            bail!("tray menu entry \"{label}\": {err}");
+        }
+    }
+    Ok(())
+}
+
+fn check(item: &IVergeTrayItem) -> Result<(), String> {
+    let target = item.target.as_deref().unwrap_or_default().trim();
+    match item.action.as_deref().unwrap_or_default() {
+        "url" => {
+            if !(target.starts_with("http://") || target.starts_with("https://")) {
+                return Err(format!("\"{target}\" is not an http(s) link"));
+            }
+        }
+        "command" => {
+            if !COMMANDS.contains(&target) {
+                return Err(format!("unknown command \"{target}\""));
+            }
+        }
+        "script" => {
+            // 只能是脚本目录中的文件名，不允许路径
+            if matches!(target, "" | "." | "..") || target.contains(['/', '\\']) {
+                return Err(format!("\"{target}\" is not a file name"));
+            }
+        }
+        action => return Err(format!("unknown action \"{action}\"")),
+    }
+    Ok(())
+}
+
+/// The labels of the entries, in menu order
+pub fn labels() -> Vec<String> {
+    Config::verge()
+        .latest()
+        .tray_custom_items
+        .iter()
+        .flatten()
+        .map(|item| item.label.clone().unwrap_or_default())
+        .collect()
+}
+
+/// Run the entry behind a clicked menu id
+pub fn run(id: &str) {
+    let Some(item) = id
+        .strip_prefix(MENU_PREFIX)
+        .and_then(|index| index.parse::<usize>().ok())
+        .and_then(|index| {
+            Config::verge()
+                .latest()
+                .tray_custom_items
+                .as_ref()
+                .and_then(|items| items.get(index).cloned())
+        })
+    else {
+        return;
+    };
+    // 配置文件可能被手动修改过，运行前再检查一次
+    if let Err(err) = check(&item) {
+        logging!(warn, Type::Tray, true, "Invalid tray menu entry: {}", err);
+        return;
+    }
+
+    let target = item.target.unwrap_or_default().trim().to_string();
+    match item.action.as_deref().unwrap_or_default() {
+        "url" => {
+            if let Err(err) = open::that(&target) {
+                logging!(warn, Type::Tray, true, "Failed to open {}: {}", target, err);
+            }
+        }
+        "command" => run_command(&target),
+        "script" => AsyncHandler::spawn(move || async move {
+            if let Err(err) = run_script(&target).await {
+                logging!(
+                    warn,
+                    Type::Tray,
+                    true,
+                    "Failed to run script {}: {}",
+                    target,
+                    err
+                );
+            }
+        }),
+        _ => {}
+    }
+}
+
+fn run_command(name: &str) {
+    match name {
+        "open_or_close_dashboard" => feat::open_or_close_dashboard_hotkey(),
+        "clash_mode_rule" => feat::change_clash_mode("rule".into()),
+        "clash_mode_global" => feat::change_clash_mode("global".into()),
+        "clash_mode_direct" => feat::change_clash_mode("direct".into()),
+        "toggle_system_proxy" => feat::toggle_system_proxy(),
+        "toggle_tun_mode" => feat::toggle_tun_mode(None),
+        "entry_lightweight_mode" => lightweight::entry_lightweight_mode(),
+        "restart_clash" => feat::restart_clash_core(),
+        "restart_app" => feat::restart_app(),
+        _ => {}
+    }
+}
+
+async fn run_script(name: &str) -> Result<()> {
+    let dir = dirs::app_scripts_dir()?;
+    let path = dir.join(name);
+    if !path.is_file() {
+        bail!("{} not found", path.display());
+    }
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        #[allow(unused_imports)] // creation_flags必须
+        use std::os::windows::process::CommandExt;
+
+        let mut command = match extension.as_str() {
+            "bat" | "cmd" => {
+                let mut command = tokio::process::Command::new("cmd");
+                command.arg("/C").arg(&path);
+                command
+            }
+            "ps1" => {
+                let mut command = tokio::process::Command::new("powershell");
+                command
+                    .args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"])
+                    .arg(&path);
+                command
+            }
+            _ => tokio::process::Command::new(&path),
+        };
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = match extension.as_str() {
+        "sh" => {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg(&path);
+            command
+        }
+        _ => tokio::process::Command::new(&path),
+    };
+
+    logging!(info, Type::Tray, true, "Running script {}", name);
+    let status = command.current_dir(&dir).status().await?;
+    if !status.success() {
+        bail!("exited with {status}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_custom_items() {
+    let item = |action: &str, target: &str| IVergeTrayItem {
+        label: Some("Item".into()),
+        action: Some(action.into()),
+        target: Some(target.into()),
+    };
+    assert!(check(&item("url", "https://example.com")).is_ok());
+    assert!(check(&item("url", "file:///etc/passwd")).is_err());
+    assert!(check(&item("command", "toggle_tun_mode")).is_ok());
+    assert!(check(&item("command", "quit_all")).is_err());
+    assert!(check(&item("script", "backup.sh")).is_ok());
+    assert!(check(&item("script", "../backup.sh")).is_err());
+    assert!(check(&item("script", "..")).is_err());
+    assert!(check(&item("exec", "ls")).is_err());
+
+    let mut unnamed = item("url", "https://example.com");
+    unnamed.label = Some(" ".into());
+    assert!(validate(&[unnamed]).is_err());
+}
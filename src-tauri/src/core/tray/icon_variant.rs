@@ -0,0 +1,84 @@
+//! Tray icons for the global and direct modes
+//!
+//! There are no bundled icons for these modes, they are drawn from the icon of the current
+//! proxy state: global mode adds a dot in the corner, direct mode fades the icon out. Both
+//! stay visible on the macOS template icons, which only keep the alpha channel, the dot is
+//! cut out of the icon with a clear gap for that.
+
+use anyhow::Result;
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+
+/// the dot of global mode, also drawn in the template icon color on macOS
+const BADGE_COLOR: Rgba<u8> = Rgba([0x3b, 0x82, 0xf6, 0xff]);
+
+/// clear gap around the dot, relative to the icon size
+const BADGE_GAP: f32 = 0.06;
+
+/// share of the original opacity left in direct mode
+const DIRECT_OPACITY: f32 = 0.45;
+
+/// The icon with a dot in the lower right corner, as a PNG
+pub fn global(icon: &[u8]) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(icon)?.to_rgba8();
+    draw_badge(&mut image);
+    encode(&image)
+}
+
+/// The icon in gray and faded, as a PNG
+pub fn direct(icon: &[u8]) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(icon)?.to_rgba8();
+    fade(&mut image);
+    encode(&image)
+}
+
+fn draw_badge(image: &mut RgbaImage) {
+    let size = image.width().min(image.height()) as f32;
+    let radius = size * 0.22;
+    let gap_radius = radius + (size * BADGE_GAP).max(1.0);
+    let (cx, cy) = (
+        image.width() as f32 - radius - 1.0,
+        image.height() as f32 - radius - 1.0,
+    );
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        let distance = dx * dx + dy * dy;
+        if distance <= radius * radius {
+            *pixel = BADGE_COLOR;
+        } else if distance <= gap_radius * gap_radius {
+            // 模板图像只保留透明度，没有间隙时圆点会与图标连成一片
+            *pixel = Rgba([0, 0, 0, 0]);
+        }
+    }
+}
+
+fn fade(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let gray = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as u8;
+        *pixel = Rgba([gray, gray, gray, (a as f32 * DIRECT_OPACITY) as u8]);
+    }
+}
+
+fn encode(image: &RgbaImage) -> Result<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png)?;
+    Ok(bytes.into_inner())
+}
+
+#[test]
+fn test_icon_variant() {
+    let mut image = RgbaImage::from_pixel(32, 32, Rgba([200, 100, 0, 255]));
+    draw_badge(&mut image);
+    assert_eq!(*image.get_pixel(31 - 7, 31 - 7), BADGE_COLOR);
+    assert_eq!(image.get_pixel(31 - 16, 31 - 7).0[3], 0);
+    assert_eq!(*image.get_pixel(0, 0), Rgba([200, 100, 0, 255]));
+
+    fade(&mut image);
+    let [r, g, b, a] = image.get_pixel(0, 0).0;
+    assert!(r == g && g == b);
+    assert_eq!(a, 114);
+
+    let png = encode(&image).unwrap();
+    assert_eq!(image::load_from_memory(&png).unwrap().to_rgba8(), image);
+}
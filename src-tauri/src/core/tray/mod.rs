@@ -1,18 +1,23 @@
 use once_cell::sync::OnceCell;
 use tauri::tray::TrayIconBuilder;
-#[cfg(target_os = "macos")]
+pub mod custom_items;
+pub mod icon_variant;
+pub mod proxy_menu;
 pub mod speed_rate;
 use crate::{
-    config::Config,
-    feat, logging,
+    config::{Config, IVerge},
+    feat, logging, logging_error,
     module::{lightweight::is_in_lightweight_mode, mihomo::Rate},
+    process::{AsyncHandler, LoopGeneration},
     utils::{dirs::find_target_icons, i18n::t, resolve::VERSION},
     Type,
 };
 
 use anyhow::Result;
 use parking_lot::Mutex;
+use proxy_menu::ProxyGroup;
 use std::{
+    collections::HashMap,
     fs,
     sync::atomic::{AtomicBool, Ordering},
     time::{Duration, Instant},
@@ -51,16 +56,99 @@ fn should_handle_tray_click() -> bool {
     }
 }
 
+// 等待中的单击，双击时取消
+static PENDING_CLICK: LoopGeneration = LoopGeneration::new();
+
+/// How long a single click waits for a second one, `None` runs it at once
+///
+/// Only Windows reports double clicks, and only a configured double-click action needs the wait.
+fn click_delay() -> Option<Duration> {
+    #[cfg(target_os = "windows")]
+    {
+        let action = Config::verge().latest().tray_double_click_event.clone();
+        if action.is_some_and(|action| action != "disable") {
+            let ms = unsafe { winapi::um::winuser::GetDoubleClickTime() };
+            return Some(Duration::from_millis(ms.into()));
+        }
+    }
+    None
+}
+
+/// 托盘单击或双击对应的操作
+fn run_tray_action(action: &str) {
+    match action {
+        "system_proxy" => feat::toggle_system_proxy(),
+        "tun_mode" => feat::toggle_tun_mode(None),
+        "main_window" => {
+            use crate::utils::window_manager::WindowManager;
+            log::info!(target: "app", "Tray click: show main window");
+            if crate::module::lightweight::is_in_lightweight_mode() {
+                log::info!(target: "app", "Currently in lightweight mode, exiting lightweight mode");
+                crate::module::lightweight::exit_lightweight_mode();
+            }
+            let result = WindowManager::show_main_window();
+            log::info!(target: "app", "Window show result: {result:?}");
+        }
+        "web_ui" => open_web_ui(),
+        _ => {}
+    }
+}
+
+/// 默认的面板，与前端 Web UI 列表的第一项相同
+const DEFAULT_WEB_UI: &str =
+    "https://metacubex.github.io/metacubexd/#/setup?http=true&hostname=%host&port=%port&secret=%secret";
+
+/// 在浏览器中打开 Web UI 列表中的第一个面板
+fn open_web_ui() {
+    let template = Config::verge()
+        .latest()
+        .web_ui_list
+        .as_ref()
+        .and_then(|list| list.first().cloned())
+        .unwrap_or_else(|| DEFAULT_WEB_UI.into());
+    let info = Config::clash().latest().get_client_info();
+    let url = web_ui_url(&template, &info.server, info.secret.as_deref());
+    log::info!(target: "app", "Tray click: open web ui");
+    if let Err(err) = open::that(url) {
+        log::warn!(target: "app", "Failed to open web ui: {err}");
+    }
+}
+
+/// 替换面板地址中的 %host、%port 和 %secret
+fn web_ui_url(template: &str, server: &str, secret: Option<&str>) -> String {
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+    let port = server
+        .rsplit_once(':')
+        .map(|(_, port)| port.trim())
+        .filter(|port| !port.is_empty())
+        .unwrap_or("9097");
+    let secret = utf8_percent_encode(secret.unwrap_or_default(), NON_ALPHANUMERIC).to_string();
+    template
+        .trim()
+        .replace("%host", "127.0.0.1")
+        .replace("%port", port)
+        .replace("%secret", &secret)
+}
+
 #[cfg(target_os = "macos")]
 pub struct Tray {
-    last_menu_update: Mutex<Option<Instant>>,
-    menu_updating: AtomicBool,
+    /// 已安排、尚未执行的菜单更新
+    menu_pending: AtomicBool,
+    /// 当前菜单中随状态变化的项
+    menu_items: Mutex<Option<MenuItems>>,
+    /// 上次构建菜单时的代理模式
+    last_mode: Mutex<Option<String>>,
 }
 
 #[cfg(not(target_os = "macos"))]
 pub struct Tray {
-    last_menu_update: Mutex<Option<Instant>>,
-    menu_updating: AtomicBool,
+    /// 已安排、尚未执行的菜单更新
+    menu_pending: AtomicBool,
+    /// 当前菜单中随状态变化的项
+    menu_items: Mutex<Option<MenuItems>>,
+    /// 上次构建菜单时的代理模式
+    last_mode: Mutex<Option<String>>,
 }
 
 impl TrayState {
@@ -73,24 +161,12 @@ impl TrayState {
                 return (true, icon_data);
             }
         }
-        #[cfg(target_os = "macos")]
-        {
-            let tray_icon_colorful = verge.tray_icon.unwrap_or("monochrome".to_string());
-            if tray_icon_colorful == "monochrome" {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon-mono.ico").to_vec(),
-                )
-            } else {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon.ico").to_vec(),
-                )
-            }
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        {
+        if is_monochrome(&verge) {
+            (
+                false,
+                include_bytes!("../../../icons/tray-icon-mono.ico").to_vec(),
+            )
+        } else {
             (
                 false,
                 include_bytes!("../../../icons/tray-icon.ico").to_vec(),
@@ -107,24 +183,12 @@ impl TrayState {
                 return (true, icon_data);
             }
         }
-        #[cfg(target_os = "macos")]
-        {
-            let tray_icon_colorful = verge.tray_icon.clone().unwrap_or("monochrome".to_string());
-            if tray_icon_colorful == "monochrome" {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon-sys-mono-new.ico").to_vec(),
-                )
-            } else {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon-sys.ico").to_vec(),
-                )
-            }
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        {
+        if is_monochrome(&verge) {
+            (
+                false,
+                include_bytes!("../../../icons/tray-icon-sys-mono-new.ico").to_vec(),
+            )
+        } else {
             (
                 false,
                 include_bytes!("../../../icons/tray-icon-sys.ico").to_vec(),
@@ -141,29 +205,254 @@ impl TrayState {
                 return (true, icon_data);
             }
         }
-        #[cfg(target_os = "macos")]
-        {
-            let tray_icon_colorful = verge.tray_icon.clone().unwrap_or("monochrome".to_string());
-            if tray_icon_colorful == "monochrome" {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon-tun-mono-new.ico").to_vec(),
-                )
-            } else {
-                (
-                    false,
-                    include_bytes!("../../../icons/tray-icon-tun.ico").to_vec(),
-                )
-            }
-        }
-        #[cfg(not(target_os = "macos"))]
-        {
+        if is_monochrome(&verge) {
+            (
+                false,
+                include_bytes!("../../../icons/tray-icon-tun-mono-new.ico").to_vec(),
+            )
+        } else {
             (
                 false,
                 include_bytes!("../../../icons/tray-icon-tun.ico").to_vec(),
             )
         }
     }
+
+    /// 全局模式图标，未设置时在当前状态的图标上加一个圆点
+    pub fn get_global_tray_icon(system_proxy: bool) -> (bool, Vec<u8>) {
+        let verge = Config::verge().latest().clone();
+        if verge.global_tray_icon.unwrap_or(false) {
+            if let Ok(Some(global_icon_path)) = find_target_icons("global") {
+                if let Ok(icon_data) = fs::read(global_icon_path) {
+                    return (true, icon_data);
+                }
+            }
+        }
+        let (is_custom, base) = Self::get_proxy_tray_icon(system_proxy);
+        match icon_variant::global(&base) {
+            Ok(icon_data) => (false, icon_data),
+            Err(_) => (is_custom, base),
+        }
+    }
+
+    /// 直连模式图标，未设置时把当前状态的图标变灰变淡
+    pub fn get_direct_tray_icon(system_proxy: bool) -> (bool, Vec<u8>) {
+        let verge = Config::verge().latest().clone();
+        if verge.direct_tray_icon.unwrap_or(false) {
+            if let Ok(Some(direct_icon_path)) = find_target_icons("direct") {
+                if let Ok(icon_data) = fs::read(direct_icon_path) {
+                    return (true, icon_data);
+                }
+            }
+        }
+        let (is_custom, base) = Self::get_proxy_tray_icon(system_proxy);
+        match icon_variant::direct(&base) {
+            Ok(icon_data) => (false, icon_data),
+            Err(_) => (is_custom, base),
+        }
+    }
+
+    fn get_proxy_tray_icon(system_proxy: bool) -> (bool, Vec<u8>) {
+        if system_proxy {
+            Self::get_sysproxy_tray_icon()
+        } else {
+            Self::get_common_tray_icon()
+        }
+    }
+
+    /// 按 TUN、代理模式与系统代理选择图标
+    pub fn get_current_tray_icon() -> (bool, Vec<u8>) {
+        let (system_proxy, tun_mode) = {
+            let verge = Config::verge();
+            let verge = verge.latest();
+            (
+                verge.enable_system_proxy.unwrap_or(false),
+                verge.enable_tun_mode.unwrap_or(false),
+            )
+        };
+        if tun_mode {
+            return Self::get_tun_tray_icon();
+        }
+        match current_mode().as_str() {
+            "global" => Self::get_global_tray_icon(system_proxy),
+            "direct" => Self::get_direct_tray_icon(system_proxy),
+            _ => Self::get_proxy_tray_icon(system_proxy),
+        }
+    }
+}
+
+/// 单色图标，macOS 默认单色，其他平台默认彩色
+fn is_monochrome(verge: &IVerge) -> bool {
+    #[cfg(target_os = "macos")]
+    const DEFAULT_STYLE: &str = "monochrome";
+    #[cfg(not(target_os = "macos"))]
+    const DEFAULT_STYLE: &str = "colorful";
+
+    verge.tray_icon.as_deref().unwrap_or(DEFAULT_STYLE) == "monochrome"
+}
+
+/// macOS 菜单栏图标是否作为模板图像，随菜单栏深浅与强调色着色
+#[cfg(target_os = "macos")]
+fn is_template(verge: &IVerge, is_custom_icon: bool) -> bool {
+    match verge.tray_icon_render.as_deref() {
+        Some("template") => true,
+        Some("colored") => false,
+        // 自定义图标多为彩色，作为模板只剩轮廓
+        _ => is_monochrome(verge) && !is_custom_icon,
+    }
+}
+
+fn current_mode() -> String {
+    Config::clash()
+        .latest()
+        .0
+        .get("mode")
+        .map(|val| val.as_str().unwrap_or("rule"))
+        .unwrap_or("rule")
+        .to_owned()
+}
+
+/// 这段时间内的菜单变化（profile、模式、代理组等）合并为一次更新
+const MENU_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// 构建托盘菜单所需的状态
+struct MenuState {
+    mode: String,
+    system_proxy: bool,
+    tun_mode: bool,
+    lightweight_mode: bool,
+    language: Option<String>,
+    hotkeys: HashMap<String, String>,
+    profiles: Vec<(String, String)>,
+    /// 显示在 profile 列表前面，只有一个时不必重复显示，为空
+    recent_profiles: Vec<(String, String)>,
+    current_profile: Option<String>,
+    proxy_groups: Vec<ProxyGroup>,
+    custom_items: Vec<String>,
+}
+
+impl MenuState {
+    fn load() -> Self {
+        let verge = Config::verge().latest().clone();
+        let hotkeys = verge
+            .hotkeys
+            .as_ref()
+            .map(|h| {
+                h.iter()
+                    .filter_map(|item| {
+                        let mut parts = item.split(',');
+                        match (parts.next(), parts.next()) {
+                            (Some(func), Some(key)) => Some((func.to_string(), key.to_string())),
+                            _ => None,
+                        }
+                    })
+                    .collect::<HashMap<String, String>>()
+            })
+            .unwrap_or_default();
+
+        let (profiles, recent_profiles, current_profile) = {
+            let profiles = Config::profiles();
+            let profiles = profiles.data();
+            (
+                profiles.all_profile_uid_and_name().unwrap_or_default(),
+                profiles.recent_profiles(),
+                profiles.get_current(),
+            )
+        };
+        let mut recent_profiles: Vec<(String, String)> = recent_profiles
+            .iter()
+            .filter_map(|uid| profiles.iter().find(|(profile_uid, _)| profile_uid == uid))
+            .cloned()
+            .collect();
+        if recent_profiles.len() < 2 {
+            recent_profiles.clear();
+        }
+
+        Self {
+            mode: current_mode(),
+            system_proxy: verge.enable_system_proxy.unwrap_or(false),
+            tun_mode: verge.enable_tun_mode.unwrap_or(false),
+            lightweight_mode: is_in_lightweight_mode(),
+            language: verge.language.clone(),
+            hotkeys,
+            profiles,
+            recent_profiles,
+            current_profile,
+            proxy_groups: proxy_menu::groups(),
+            custom_items: custom_items::labels(),
+        }
+    }
+
+    /// 决定菜单结构的部分，不变时只需原地更新
+    fn layout(&self) -> MenuLayout {
+        MenuLayout {
+            language: self.language.clone(),
+            hotkeys: self.hotkeys.clone(),
+            profiles: self.profiles.clone(),
+            recent_profiles: self.recent_profiles.clone(),
+            proxy_groups: self
+                .proxy_groups
+                .iter()
+                .map(|group| {
+                    let nodes = group.nodes.iter().map(|node| node.name.clone()).collect();
+                    (group.name.clone(), nodes, group.more)
+                })
+                .collect(),
+            custom_items: self.custom_items.clone(),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+struct MenuLayout {
+    language: Option<String>,
+    hotkeys: HashMap<String, String>,
+    profiles: Vec<(String, String)>,
+    recent_profiles: Vec<(String, String)>,
+    /// 组名、节点名与未列出的节点数
+    proxy_groups: Vec<(String, Vec<String>, usize)>,
+    custom_items: Vec<String>,
+}
+
+/// 当前菜单中随状态变化的项
+struct MenuItems {
+    layout: MenuLayout,
+    rule_mode: CheckMenuItem<Wry>,
+    global_mode: CheckMenuItem<Wry>,
+    system_proxy: CheckMenuItem<Wry>,
+    tun_mode: CheckMenuItem<Wry>,
+    lightweight_mode: CheckMenuItem<Wry>,
+    /// 包括最近使用中的项
+    profiles: Vec<(String, CheckMenuItem<Wry>)>,
+    /// 按组与节点的索引，连同显示的文字
+    proxy_nodes: Vec<Vec<(CheckMenuItem<Wry>, String)>>,
+}
+
+impl MenuItems {
+    fn apply(&mut self, state: &MenuState) {
+        // 点击勾选项时系统会自行切换勾选，总是重新设置
+        let _ = self.rule_mode.set_checked(state.mode == "rule");
+        let _ = self.global_mode.set_checked(state.mode == "global");
+        let _ = self.system_proxy.set_checked(state.system_proxy);
+        let _ = self.tun_mode.set_checked(state.tun_mode);
+        let _ = self.lightweight_mode.set_checked(state.lightweight_mode);
+        for (uid, item) in &self.profiles {
+            let _ = item.set_checked(state.current_profile.as_ref() == Some(uid));
+        }
+
+        // 延迟变化时只更新对应节点的文字
+        let timeout = t("Timeout");
+        for (group, items) in state.proxy_groups.iter().zip(self.proxy_nodes.iter_mut()) {
+            for (node, (item, label)) in group.nodes.iter().zip(items.iter_mut()) {
+                let _ = item.set_checked(node.name == group.now);
+                let text = node.label(&timeout);
+                if *label != text {
+                    let _ = item.set_text(&text);
+                    *label = text;
+                }
+            }
+        }
+    }
 }
 
 impl Tray {
@@ -172,14 +461,16 @@ impl Tray {
 
         #[cfg(target_os = "macos")]
         return TRAY.get_or_init(|| Tray {
-            last_menu_update: Mutex::new(None),
-            menu_updating: AtomicBool::new(false),
+            menu_pending: AtomicBool::new(false),
+            menu_items: Mutex::new(None),
+            last_mode: Mutex::new(None),
         });
 
         #[cfg(not(target_os = "macos"))]
         return TRAY.get_or_init(|| Tray {
-            last_menu_update: Mutex::new(None),
-            menu_updating: AtomicBool::new(false),
+            menu_pending: AtomicBool::new(false),
+            menu_items: Mutex::new(None),
+            last_mode: Mutex::new(None),
         });
     }
 
@@ -209,95 +500,71 @@ impl Tray {
     }
 
     /// 更新托盘菜单
+    ///
+    /// 短时间内的多次变化合并为一次更新，菜单结构不变时只原地更新勾选与文字
     pub fn update_menu(&self) -> Result<()> {
         if handle::Handle::global().is_exiting() {
             log::debug!(target: "app", "Application is exiting, skip tray menu update");
             return Ok(());
         }
-        // 调整最小更新间隔，确保状态及时刷新
-        const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
-
-        // 检查是否正在更新
-        if self.menu_updating.load(Ordering::Acquire) {
+        // 已有更新在等待，本次的变化会一并生效
+        if self.menu_pending.swap(true, Ordering::AcqRel) {
             return Ok(());
         }
 
-        // 检查更新频率，但允许重要事件跳过频率限制
-        let should_force_update = match std::thread::current().name() {
-            Some("main") => true,
-            _ => {
-                let last_update = self.last_menu_update.lock();
-                if let Some(last_time) = *last_update {
-                    last_time.elapsed() >= MIN_UPDATE_INTERVAL
-                } else {
-                    true
+        AsyncHandler::spawn(|| async {
+            tokio::time::sleep(MENU_DEBOUNCE).await;
+            let tray = Tray::global();
+            // 先清除标记，更新期间的变化会再安排一次更新
+            tray.menu_pending.store(false, Ordering::Release);
+            if handle::Handle::global().is_exiting() {
+                return;
+            }
+            match handle::Handle::global().app_handle() {
+                Some(app_handle) => {
+                    logging_error!(Type::Tray, true, tray.update_menu_internal(&app_handle));
+                }
+                None => {
+                    log::warn!(target: "app", "Failed to update tray menu: app_handle not found");
                 }
             }
-        };
+        });
+        Ok(())
+    }
 
-        if !should_force_update {
+    fn update_menu_internal(&self, app_handle: &AppHandle) -> Result<()> {
+        let Some(tray) = app_handle.tray_by_id("main") else {
+            log::warn!(target: "app", "Failed to update tray menu: tray not found");
             return Ok(());
-        }
-
-        let app_handle = match handle::Handle::global().app_handle() {
-            Some(handle) => handle,
-            None => {
-                log::warn!(target: "app", "Failed to update tray menu: app_handle not found");
-                return Ok(());
-            }
         };
-
-        // 设置更新状态
-        self.menu_updating.store(true, Ordering::Release);
-
-        let result = self.update_menu_internal(&app_handle);
+        let state = MenuState::load();
+        // 代理模式变化时（包括在面板中切换）一并切换图标
+        let mode_changed = self
+            .last_mode
+            .lock()
+            .replace(state.mode.clone())
+            .is_some_and(|last_mode| last_mode != state.mode);
 
         {
-            let mut last_update = self.last_menu_update.lock();
-            *last_update = Some(Instant::now());
+            let mut menu_items = self.menu_items.lock();
+            match menu_items.as_mut() {
+                Some(items) if items.layout == state.layout() => {
+                    items.apply(&state);
+                    log::debug!(target: "app", "Tray menu updated in place");
+                }
+                _ => {
+                    let (menu, items) = create_tray_menu(app_handle, &state)?;
+                    let _ = tray.set_menu(Some(menu));
+                    *menu_items = Some(items);
+                    log::debug!(target: "app", "Tray menu rebuilt");
+                }
+            }
         }
-        self.menu_updating.store(false, Ordering::Release);
-
-        result
-    }
-
-    fn update_menu_internal(&self, app_handle: &AppHandle) -> Result<()> {
-        let verge = Config::verge().latest().clone();
-        let system_proxy = verge.enable_system_proxy.as_ref().unwrap_or(&false);
-        let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
-        let mode = {
-            Config::clash()
-                .latest()
-                .0
-                .get("mode")
-                .map(|val| val.as_str().unwrap_or("rule"))
-                .unwrap_or("rule")
-                .to_owned()
-        };
-        let profile_uid_and_name = Config::profiles()
-            .data()
-            .all_profile_uid_and_name()
-            .unwrap_or_default();
-        let is_lightweight_mode = is_in_lightweight_mode();
 
-        match app_handle.tray_by_id("main") {
-            Some(tray) => {
-                let _ = tray.set_menu(Some(create_tray_menu(
-                    app_handle,
-                    Some(mode.as_str()),
-                    *system_proxy,
-                    *tun_mode,
-                    profile_uid_and_name,
-                    is_lightweight_mode,
-                )?));
-                log::debug!(target: "app", "Tray menu updated successfully");
-                Ok(())
-            }
-            None => {
-                log::warn!(target: "app", "Failed to update tray menu: tray not found");
-                Ok(())
-            }
+        if mode_changed {
+            self.update_icon(None)?;
         }
+        Ok(())
     }
 
     /// 更新托盘图标
@@ -323,22 +590,11 @@ impl Tray {
             }
         };
 
-        let verge = Config::verge().latest().clone();
-        let system_mode = verge.enable_system_proxy.as_ref().unwrap_or(&false);
-        let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
-
-        let (_is_custom_icon, icon_bytes) = match (*system_mode, *tun_mode) {
-            (true, true) => TrayState::get_tun_tray_icon(),
-            (true, false) => TrayState::get_sysproxy_tray_icon(),
-            (false, true) => TrayState::get_tun_tray_icon(),
-            (false, false) => TrayState::get_common_tray_icon(),
-        };
-
-        let colorful = verge.tray_icon.clone().unwrap_or("monochrome".to_string());
-        let is_colorful = colorful == "colorful";
+        let (is_custom_icon, icon_bytes) = TrayState::get_current_tray_icon();
+        let is_template = is_template(&Config::verge().latest(), is_custom_icon);
 
         let _ = tray.set_icon(Some(tauri::image::Image::from_bytes(&icon_bytes)?));
-        let _ = tray.set_icon_as_template(!is_colorful);
+        let _ = tray.set_icon_as_template(is_template);
         Ok(())
     }
 
@@ -364,16 +620,7 @@ impl Tray {
             }
         };
 
-        let verge = Config::verge().latest().clone();
-        let system_mode = verge.enable_system_proxy.as_ref().unwrap_or(&false);
-        let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
-
-        let (_is_custom_icon, icon_bytes) = match (*system_mode, *tun_mode) {
-            (true, true) => TrayState::get_tun_tray_icon(),
-            (true, false) => TrayState::get_sysproxy_tray_icon(),
-            (false, true) => TrayState::get_tun_tray_icon(),
-            (false, false) => TrayState::get_common_tray_icon(),
-        };
+        let (_is_custom_icon, icon_bytes) = TrayState::get_current_tray_icon();
 
         let _ = tray.set_icon(Some(tauri::image::Image::from_bytes(&icon_bytes)?));
         Ok(())
@@ -420,10 +667,11 @@ impl Tray {
         let system_proxy = verge.enable_system_proxy.as_ref().unwrap_or(&false);
         let tun_mode = verge.enable_tun_mode.as_ref().unwrap_or(&false);
 
+        // 开关状态也随界面语言显示
         let switch_map = {
             let mut map = std::collections::HashMap::new();
-            map.insert(true, "on");
-            map.insert(false, "off");
+            map.insert(true, t("Enabled"));
+            map.insert(false, t("Disabled"));
             map
         };
 
@@ -439,9 +687,14 @@ impl Tray {
             }
         };
 
+        // 开启托盘网速时附加当前速率
+        let speed = speed_rate::current()
+            .map(|rate| format!("\n{}", speed_rate::format_rate(&rate)))
+            .unwrap_or_default();
+
         if let Some(tray) = app_handle.tray_by_id("main") {
             let _ = tray.set_tooltip(Some(&format!(
-                "Koala Clash {version}\n{}: {}\n{}: {}\n{}: {}",
+                "Koala Clash {version}\n{}: {}\n{}: {}\n{}: {}{speed}",
                 t("SysProxy"),
                 switch_map[system_proxy],
                 t("TUN"),
@@ -469,9 +722,24 @@ impl Tray {
         Ok(())
     }
 
+    /// 更新托盘的网速，macOS 菜单栏与 Linux 的托盘在图标旁显示标题
+    pub fn update_speed(&self) -> Result<()> {
+        self.update_tooltip()?;
+        #[cfg(any(target_os = "macos", target_os = "linux"))]
+        if let Some(tray) = handle::Handle::global()
+            .app_handle()
+            .and_then(|app_handle| app_handle.tray_by_id("main"))
+        {
+            let title = speed_rate::current().map(|rate| speed_rate::format_rate(&rate));
+            let _ = tray.set_title(title);
+        }
+        Ok(())
+    }
+
     /// 取消订阅 traffic 数据
-    #[cfg(target_os = "macos")]
-    pub fn unsubscribe_traffic(&self) {}
+    pub fn unsubscribe_traffic(&self) {
+        speed_rate::stop();
+    }
 
     pub fn create_tray_from_handle(&self, app_handle: &AppHandle) -> Result<()> {
         if handle::Handle::global().is_exiting() {
@@ -481,18 +749,24 @@ impl Tray {
         log::info!(target: "app", "Creating system tray from AppHandle");
 
         // 获取图标
-        let icon_bytes = TrayState::get_common_tray_icon().1;
+        let (_is_custom_icon, icon_bytes) = TrayState::get_current_tray_icon();
         let icon = tauri::image::Image::from_bytes(&icon_bytes)?;
 
+        // 创建时即按设置渲染，避免启动时先显示为彩色
+        #[cfg(target_os = "macos")]
+        let icon_as_template = is_template(&Config::verge().latest(), _is_custom_icon);
+        #[cfg(not(target_os = "macos"))]
+        let icon_as_template = false;
+
         #[cfg(target_os = "linux")]
         let builder = TrayIconBuilder::with_id("main")
             .icon(icon)
-            .icon_as_template(false);
+            .icon_as_template(icon_as_template);
 
         #[cfg(not(target_os = "linux"))]
         let mut builder = TrayIconBuilder::with_id("main")
             .icon(icon)
-            .icon_as_template(false);
+            .icon_as_template(icon_as_template);
 
         #[cfg(any(target_os = "macos", target_os = "windows"))]
         {
@@ -504,12 +778,22 @@ impl Tray {
         }
 
         let tray = builder.build(app_handle)?;
+        // 新的托盘还没有菜单，下次更新时重新构建
+        *self.menu_items.lock() = None;
 
         tray.on_tray_icon_event(|_, event| {
             let tray_event = { Config::verge().latest().tray_event.clone() };
             let tray_event: String = tray_event.unwrap_or("main_window".into());
             log::debug!(target: "app","tray event: {tray_event:?}");
 
+            // 打开托盘菜单前刷新代理组与延迟
+            if matches!(
+                event,
+                TrayIconEvent::Enter { .. } | TrayIconEvent::Click { .. }
+            ) {
+                proxy_menu::request_refresh();
+            }
+
             if let TrayIconEvent::Click {
                 button: MouseButton::Left,
                 button_state: MouseButtonState::Down,
@@ -521,22 +805,31 @@ impl Tray {
                     return;
                 }
 
-                match tray_event.as_str() {
-                    "system_proxy" => feat::toggle_system_proxy(),
-                    "tun_mode" => feat::toggle_tun_mode(None),
-                    "main_window" => {
-                        use crate::utils::window_manager::WindowManager;
-                        log::info!(target: "app", "Tray click: show main window");
-                        if crate::module::lightweight::is_in_lightweight_mode() {
-                            log::info!(target: "app", "Currently in lightweight mode, exiting lightweight mode");
-                            crate::module::lightweight::exit_lightweight_mode();
-                        }
-                        let result = WindowManager::show_main_window();
-                        log::info!(target: "app", "Window show result: {result:?}");
+                // 设置了双击操作时，单击等过系统的双击时间才执行
+                let generation = PENDING_CLICK.next();
+                match click_delay() {
+                    Some(delay) => {
+                        AsyncHandler::spawn(move || async move {
+                            tokio::time::sleep(delay).await;
+                            if PENDING_CLICK.is_current(generation) {
+                                run_tray_action(&tray_event);
+                            }
+                        });
                     }
-                    _ => {}
+                    None => run_tray_action(&tray_event),
                 }
             }
+
+            // 只有 Windows 会发出双击事件，双击取消等待中的单击
+            if let TrayIconEvent::DoubleClick {
+                button: MouseButton::Left,
+                ..
+            } = event
+            {
+                PENDING_CLICK.stop();
+                let action = Config::verge().latest().tray_double_click_event.clone();
+                run_tray_action(action.as_deref().unwrap_or("disable"));
+            }
         });
         tray.on_menu_event(on_menu_event);
         log::info!(target: "app", "System tray created successfully");
@@ -561,55 +854,58 @@ impl Tray {
 
 fn create_tray_menu(
     app_handle: &AppHandle,
-    mode: Option<&str>,
-    system_proxy_enabled: bool,
-    tun_mode_enabled: bool,
-    profile_uid_and_name: Vec<(String, String)>,
-    is_lightweight_mode: bool,
-) -> Result<tauri::menu::Menu<Wry>> {
-    let mode = mode.unwrap_or("");
+    state: &MenuState,
+) -> Result<(tauri::menu::Menu<Wry>, MenuItems)> {
+    let mode = state.mode.as_str();
 
     let unknown_version = String::from("unknown");
     let version = VERSION.get().unwrap_or(&unknown_version);
 
-    let hotkeys = Config::verge()
-        .latest()
-        .hotkeys
-        .as_ref()
-        .map(|h| {
-            h.iter()
-                .filter_map(|item| {
-                    let mut parts = item.split(',');
-                    match (parts.next(), parts.next()) {
-                        (Some(func), Some(key)) => Some((func.to_string(), key.to_string())),
-                        _ => None,
-                    }
-                })
-                .collect::<std::collections::HashMap<String, String>>()
+    let hotkeys = &state.hotkeys;
+
+    let profile_check_item = |id: String, uid: &String, profile_name: &str| {
+        let item = CheckMenuItem::with_id(
+            app_handle,
+            id,
+            t(profile_name),
+            true,
+            state.current_profile.as_ref() == Some(uid),
+            None::<&str>,
+        )
+        .unwrap();
+        (uid.clone(), item)
+    };
+    let profile_menu_items: Vec<(String, CheckMenuItem<Wry>)> = state
+        .profiles
+        .iter()
+        .map(|(profile_uid, profile_name)| {
+            profile_check_item(format!("profiles_{profile_uid}"), profile_uid, profile_name)
         })
-        .unwrap_or_default();
-
-    let profile_menu_items: Vec<CheckMenuItem<Wry>> = profile_uid_and_name
+        .collect();
+    // 最近使用的 profile 放在列表前面
+    let recent_menu_items: Vec<(String, CheckMenuItem<Wry>)> = state
+        .recent_profiles
         .iter()
         .map(|(profile_uid, profile_name)| {
-            let is_current_profile = Config::profiles()
-                .data()
-                .is_current_profile_index(profile_uid.to_string());
-            CheckMenuItem::with_id(
-                app_handle,
-                format!("profiles_{profile_uid}"),
-                t(profile_name),
-                true,
-                is_current_profile,
-                None::<&str>,
+            profile_check_item(
+                format!("recent_profiles_{profile_uid}"),
+                profile_uid,
+                profile_name,
             )
-            .unwrap()
         })
         .collect();
-    let profile_menu_items: Vec<&dyn IsMenuItem<Wry>> = profile_menu_items
+    let recent_separator = PredefinedMenuItem::separator(app_handle).unwrap();
+    let mut profile_menu_list: Vec<&dyn IsMenuItem<Wry>> = profile_menu_items
         .iter()
-        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .map(|(_, item)| item as &dyn IsMenuItem<Wry>)
         .collect();
+    if !recent_menu_items.is_empty() {
+        let recent = recent_menu_items
+            .iter()
+            .map(|(_, item)| item as &dyn IsMenuItem<Wry>)
+            .chain([&recent_separator as &dyn IsMenuItem<Wry>]);
+        profile_menu_list.splice(0..0, recent);
+    }
 
     let open_window = &MenuItem::with_id(
         app_handle,
@@ -645,7 +941,7 @@ fn create_tray_menu(
         "profiles",
         t("Profiles"),
         true,
-        &profile_menu_items,
+        &profile_menu_list,
     )
     .unwrap();
 
@@ -654,7 +950,7 @@ fn create_tray_menu(
         "system_proxy",
         t("System Proxy"),
         true,
-        system_proxy_enabled,
+        state.system_proxy,
         hotkeys.get("toggle_system_proxy").map(|s| s.as_str()),
     )
     .unwrap();
@@ -664,7 +960,7 @@ fn create_tray_menu(
         "tun_mode",
         t("TUN Mode"),
         true,
-        tun_mode_enabled,
+        state.tun_mode,
         hotkeys.get("toggle_tun_mode").map(|s| s.as_str()),
     )
     .unwrap();
@@ -674,7 +970,7 @@ fn create_tray_menu(
         "entry_lightweight_mode",
         t("LightWeight Mode"),
         true,
-        is_lightweight_mode,
+        state.lightweight_mode,
         hotkeys.get("entry_lightweight_mode").map(|s| s.as_str()),
     )
     .unwrap();
@@ -720,26 +1016,112 @@ fn create_tray_menu(
 
     let separator = &PredefinedMenuItem::separator(app_handle).unwrap();
 
+    // 自定义项放在退出之前
+    let custom_items: Vec<MenuItem<Wry>> = state
+        .custom_items
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            MenuItem::with_id(
+                app_handle,
+                format!("{}{index}", custom_items::MENU_PREFIX),
+                label,
+                true,
+                None::<&str>,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    // 每个代理组一个子菜单，菜单 id 由组名与节点名编码而成
+    let timeout = t("Timeout");
+    let mut proxy_nodes = Vec::with_capacity(state.proxy_groups.len());
+    let proxy_groups: Vec<Submenu<Wry>> = state
+        .proxy_groups
+        .iter()
+        .map(|group| {
+            let submenu = Submenu::with_id(
+                app_handle,
+                proxy_menu::menu_id(&group.name, None),
+                &group.name,
+                true,
+            )
+            .unwrap();
+            let mut nodes = Vec::with_capacity(group.nodes.len());
+            for node in group.nodes.iter() {
+                let label = node.label(&timeout);
+                let item = CheckMenuItem::with_id(
+                    app_handle,
+                    proxy_menu::menu_id(&group.name, Some(&node.name)),
+                    &label,
+                    true,
+                    node.name == group.now,
+                    None::<&str>,
+                )
+                .unwrap();
+                let _ = submenu.append(&item);
+                nodes.push((item, label));
+            }
+            proxy_nodes.push(nodes);
+            if group.more > 0 {
+                let more = MenuItem::new(
+                    app_handle,
+                    format!("{} (+{})", t("More"), group.more),
+                    false,
+                    None::<&str>,
+                )
+                .unwrap();
+                let _ = submenu.append(&more);
+            }
+            submenu
+        })
+        .collect();
+
+    let mut items: Vec<&dyn IsMenuItem<Wry>> = vec![
+        open_window,
+        separator,
+        rule_mode,
+        global_mode,
+        separator,
+        profiles,
+        separator,
+    ];
+    if !proxy_groups.is_empty() {
+        items.extend(proxy_groups.iter().map(|item| item as &dyn IsMenuItem<Wry>));
+        items.push(separator);
+    }
+    items.extend([
+        system_proxy as &dyn IsMenuItem<Wry>,
+        tun_mode,
+        separator,
+        lighteweight_mode,
+        more,
+        separator,
+    ]);
+    if !custom_items.is_empty() {
+        items.extend(custom_items.iter().map(|item| item as &dyn IsMenuItem<Wry>));
+        items.push(separator);
+    }
+    items.push(quit);
+
     let menu = tauri::menu::MenuBuilder::new(app_handle)
-        .items(&[
-            open_window,
-            separator,
-            rule_mode,
-            global_mode,
-            separator,
-            profiles,
-            separator,
-            system_proxy,
-            tun_mode,
-            separator,
-            lighteweight_mode,
-            more,
-            separator,
-            quit,
-        ])
+        .items(&items)
         .build()
         .unwrap();
-    Ok(menu)
+    let menu_items = MenuItems {
+        layout: state.layout(),
+        rule_mode: rule_mode.clone(),
+        global_mode: global_mode.clone(),
+        system_proxy: system_proxy.clone(),
+        tun_mode: tun_mode.clone(),
+        lightweight_mode: lighteweight_mode.clone(),
+        profiles: profile_menu_items
+            .into_iter()
+            .chain(recent_menu_items)
+            .collect(),
+        proxy_nodes,
+    };
+    Ok((menu, menu_items))
 }
 
 fn on_menu_event(_: &AppHandle, event: MenuEvent) {
@@ -799,10 +1181,16 @@ fn on_menu_event(_: &AppHandle, event: MenuEvent) {
         "quit" => {
             feat::quit();
         }
+        id if id.starts_with("recent_profiles_") => {
+            let profile_index = &id["recent_profiles_".len()..];
+            feat::toggle_proxy_profile(profile_index.into());
+        }
         id if id.starts_with("profiles_") => {
             let profile_index = &id["profiles_".len()..];
             feat::toggle_proxy_profile(profile_index.into());
         }
+        id if id.starts_with(custom_items::MENU_PREFIX) => custom_items::run(id),
+        id if id.starts_with(proxy_menu::MENU_PREFIX) => proxy_menu::select(id),
         _ => {}
     }
 
@@ -810,3 +1198,62 @@ fn on_menu_event(_: &AppHandle, event: MenuEvent) {
         log::warn!(target: "app", "Failed to update tray state: {e}");
     }
 }
+
+#[test]
+fn test_web_ui_url() {
+    assert_eq!(
+        web_ui_url(
+            "http://%host:%port/ui/#/setup?secret=%secret",
+            "127.0.0.1:9097",
+            Some("a b&c")
+        ),
+        "http://127.0.0.1:9097/ui/#/setup?secret=a%20b%26c"
+    );
+    assert_eq!(
+        web_ui_url("https://yacd.example/?port=%port&secret=%secret", "", None),
+        "https://yacd.example/?port=9097&secret="
+    );
+}
+
+#[test]
+fn test_menu_layout() {
+    use proxy_menu::ProxyNode;
+
+    let node = |name: &str, delay| ProxyNode {
+        name: name.into(),
+        delay,
+    };
+    let mut state = MenuState {
+        mode: "rule".into(),
+        system_proxy: false,
+        tun_mode: false,
+        lightweight_mode: false,
+        language: Some("en".into()),
+        hotkeys: HashMap::new(),
+        profiles: vec![("a".into(), "Profile A".into())],
+        recent_profiles: Vec::new(),
+        current_profile: Some("a".into()),
+        proxy_groups: vec![ProxyGroup {
+            name: "Proxy".into(),
+            now: "HK".into(),
+            nodes: vec![node("HK", Some(120)), node("JP", None)],
+            more: 0,
+        }],
+        custom_items: Vec::new(),
+    };
+    let layout = state.layout();
+
+    // 模式、开关、选择与延迟只需原地更新
+    state.mode = "global".into();
+    state.tun_mode = true;
+    state.current_profile = None;
+    state.proxy_groups[0].now = "JP".into();
+    state.proxy_groups[0].nodes[1].delay = Some(0);
+    assert!(state.layout() == layout);
+
+    state.proxy_groups[0].nodes.push(node("US", None));
+    assert!(state.layout() != layout);
+    state.proxy_groups[0].nodes.pop();
+    state.language = Some("ru".into());
+    assert!(state.layout() != layout);
+}
@@ -0,0 +1,309 @@
+//! Proxy groups in the tray menu
+//!
+//! While `enable_tray_proxies` is on, every selector group of the running core is a submenu
+//! of the tray listing its nodes with their last tested delay, and clicking a node selects it
+//! through the core API. The menu is only ever built from a cached snapshot, which is
+//! refreshed in the background and updates the menu only when something changed. Groups
+//! longer than [`MAX_NODES`] are cut so the menu stays quick to build and to open.
+
+use super::Tray;
+use crate::{
+    config::Config,
+    core::{handle, CoreManager, RunningMode},
+    logging, logging_error,
+    module::mihomo::MihomoManager,
+    process::{AsyncHandler, LoopGeneration},
+    utils::logging::Type,
+};
+use parking_lot::Mutex;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// menu ids are this prefix followed by the encoded group and node name, `proxy_Proxy_HK%2001`
+pub const MENU_PREFIX: &str = "proxy_";
+
+/// nodes listed per group, the rest are left to the dashboard
+pub const MAX_NODES: usize = 100;
+
+/// picks up selections and delay tests made in the dashboard
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// requests arriving together are merged into one refresh
+const REFRESH_DELAY: Duration = Duration::from_millis(300);
+
+static GENERATION: LoopGeneration = LoopGeneration::new();
+
+/// the groups shown now, empty while stopped or the core isn't running
+static GROUPS: Mutex<Vec<ProxyGroup>> = Mutex::new(Vec::new());
+
+static WAKE: Notify = Notify::const_new();
+
+/// A selector group as shown in the tray
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyGroup {
+    pub name: String,
+    pub now: String,
+    pub nodes: Vec<ProxyNode>,
+    /// nodes left out after [`MAX_NODES`]
+    pub more: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyNode {
+    pub name: String,
+    /// last tested delay in ms, 0 when the test timed out
+    pub delay: Option<u64>,
+}
+
+impl ProxyNode {
+    /// `HK 01  120 ms`
+    pub fn label(&self, timeout: &str) -> String {
+        match self.delay {
+            Some(0) => format!("{}  {timeout}", self.name),
+            Some(delay) => format!("{}  {delay} ms", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// The groups to show in the tray
+pub fn groups() -> Vec<ProxyGroup> {
+    GROUPS.lock().clone()
+}
+
+/// Start or stop the refreshes per `enable_tray_proxies`
+pub fn update() {
+    let enabled = Config::verge()
+        .latest()
+        .enable_tray_proxies
+        .unwrap_or(false);
+    let generation = GENERATION.next();
+    set(Vec::new());
+    if enabled {
+        AsyncHandler::spawn(move || run(generation));
+    }
+}
+
+/// Refresh soon, e.g. when the tray is about to be opened
+pub fn request_refresh() {
+    WAKE.notify_one();
+}
+
+async fn run(generation: u64) {
+    while GENERATION.is_current(generation) {
+        if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+            set(Vec::new());
+        } else {
+            refresh(generation).await;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(REFRESH_INTERVAL) => {}
+            _ = WAKE.notified() => tokio::time::sleep(REFRESH_DELAY).await,
+        }
+    }
+}
+
+async fn refresh(generation: u64) {
+    let proxies = match MihomoManager::global().get_refresh_proxies().await {
+        Ok(proxies) => proxies,
+        Err(err) => {
+            logging!(debug, Type::Tray, "Skip refreshing tray proxies: {}", err);
+            return;
+        }
+    };
+    let global = Config::clash()
+        .latest()
+        .0
+        .get("mode")
+        .and_then(|mode| mode.as_str())
+        == Some("global");
+    // 等待请求期间可能已被关闭
+    if GENERATION.is_current(generation) {
+        set(parse_groups(&proxies, global));
+    }
+}
+
+/// Replace the snapshot, the menu is only updated when it changed
+fn set(groups: Vec<ProxyGroup>) {
+    {
+        let mut current = GROUPS.lock();
+        if *current == groups {
+            return;
+        }
+        *current = groups;
+    }
+    logging_error!(Type::Tray, true, Tray::global().update_menu());
+}
+
+/// The menu id of a group submenu, or of a node in it
+///
+/// Names are encoded so the id still points at the same node after the snapshot changed.
+pub fn menu_id(group: &str, node: Option<&str>) -> String {
+    let group = utf8_percent_encode(group, NON_ALPHANUMERIC);
+    match node {
+        Some(node) => format!(
+            "{MENU_PREFIX}{group}_{}",
+            utf8_percent_encode(node, NON_ALPHANUMERIC)
+        ),
+        None => format!("{MENU_PREFIX}{group}"),
+    }
+}
+
+fn parse_menu_id(id: &str) -> Option<(String, String)> {
+    let (group, node) = id.strip_prefix(MENU_PREFIX)?.split_once('_')?;
+    Some((
+        percent_decode_str(group).decode_utf8().ok()?.into_owned(),
+        percent_decode_str(node).decode_utf8().ok()?.into_owned(),
+    ))
+}
+
+/// Select the node behind a clicked menu id
+pub fn select(id: &str) {
+    let Some((group, node)) = parse_menu_id(id) else {
+        return;
+    };
+    // 先更新快照，随后的菜单刷新即显示新的选择
+    {
+        let mut groups = GROUPS.lock();
+        let Some(entry) = groups.iter_mut().find(|entry| entry.name == group) else {
+            return;
+        };
+        if !entry.nodes.iter().any(|entry| entry.name == node) || entry.now == node {
+            return;
+        }
+        entry.now = node.clone();
+    }
+
+    AsyncHandler::spawn(move || async move {
+        logging!(info, Type::Tray, true, "Select {} in {}", node, group);
+        if let Err(err) = MihomoManager::global().select_proxy(&group, &node).await {
+            logging!(
+                warn,
+                Type::Tray,
+                true,
+                "Failed to select {} in {}: {}",
+                node,
+                group,
+                err
+            );
+        }
+        // 选择失败时恢复为内核中实际的节点
+        request_refresh();
+        handle::Handle::refresh_clash();
+    });
+}
+
+/// The selector groups of a `/proxies` response, in the order of the config
+///
+/// `GLOBAL` lists every group in config order, it is shown itself only in global mode.
+fn parse_groups(proxies: &Value, include_global: bool) -> Vec<ProxyGroup> {
+    let proxies = &proxies["proxies"];
+    let order = proxies["GLOBAL"]["all"]
+        .as_array()
+        .map(|all| {
+            all.iter()
+                .filter_map(|name| name.as_str())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let names = include_global.then_some("GLOBAL").into_iter().chain(order);
+
+    names
+        .filter_map(|name| {
+            let group = &proxies[name];
+            if group["type"].as_str() != Some("Selector") || group["hidden"] == true {
+                return None;
+            }
+            let all = group["all"].as_array()?;
+            let nodes = all
+                .iter()
+                .filter_map(|node| node.as_str())
+                .take(MAX_NODES)
+                .map(|node| ProxyNode {
+                    name: node.to_string(),
+                    delay: last_delay(&proxies[node]),
+                })
+                .collect();
+            Some(ProxyGroup {
+                name: name.to_string(),
+                now: group["now"].as_str().unwrap_or_default().to_string(),
+                nodes,
+                more: all.len().saturating_sub(MAX_NODES),
+            })
+        })
+        .collect()
+}
+
+fn last_delay(proxy: &Value) -> Option<u64> {
+    proxy["history"].as_array()?.last()?["delay"].as_u64()
+}
+
+#[test]
+fn test_parse_groups() {
+    let proxies = serde_json::json!({
+        "proxies": {
+            "GLOBAL": { "type": "Selector", "now": "DIRECT", "all": ["Proxy", "Auto", "Hidden", "DIRECT"] },
+            "Proxy": { "type": "Selector", "now": "JP", "all": ["HK", "JP", "Auto"] },
+            "Auto": { "type": "URLTest", "now": "HK", "all": ["HK", "JP"], "history": [{ "delay": 80 }] },
+            "Hidden": { "type": "Selector", "now": "HK", "all": ["HK"], "hidden": true },
+            "HK": { "type": "Shadowsocks", "history": [{ "delay": 200 }, { "delay": 120 }] },
+            "JP": { "type": "Trojan", "history": [{ "delay": 0 }] },
+            "DIRECT": { "type": "Direct", "history": [] }
+        }
+    });
+
+    let groups = parse_groups(&proxies, false);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name, "Proxy");
+    assert_eq!(groups[0].now, "JP");
+    assert_eq!(
+        groups[0].nodes,
+        vec![
+            ProxyNode {
+                name: "HK".into(),
+                delay: Some(120)
+            },
+            ProxyNode {
+                name: "JP".into(),
+                delay: Some(0)
+            },
+            ProxyNode {
+                name: "Auto".into(),
+                delay: Some(80)
+            },
+        ]
+    );
+    assert_eq!(groups[0].nodes[0].label("Timeout"), "HK  120 ms");
+    assert_eq!(groups[0].nodes[1].label("Timeout"), "JP  Timeout");
+
+    let groups = parse_groups(&proxies, true);
+    assert_eq!(groups[0].name, "GLOBAL");
+    assert_eq!(groups[0].nodes[3].label("Timeout"), "DIRECT");
+    assert_eq!(groups[1].name, "Proxy");
+
+    let all = (0..MAX_NODES + 5)
+        .map(|i| format!("node {i}"))
+        .collect::<Vec<_>>();
+    let proxies = serde_json::json!({
+        "proxies": {
+            "GLOBAL": { "type": "Selector", "now": "Big", "all": ["Big"] },
+            "Big": { "type": "Selector", "now": "node 0", "all": all }
+        }
+    });
+    let groups = parse_groups(&proxies, false);
+    assert_eq!(groups[0].nodes.len(), MAX_NODES);
+    assert_eq!(groups[0].more, 5);
+}
+
+#[test]
+fn test_menu_id() {
+    let id = menu_id("Proxy_1", Some("HK 01 🇭🇰"));
+    assert!(!id[MENU_PREFIX.len()..].contains(' '));
+    assert_eq!(
+        parse_menu_id(&id),
+        Some(("Proxy_1".to_string(), "HK 01 🇭🇰".to_string()))
+    );
+    assert_eq!(parse_menu_id(&menu_id("Proxy", None)), None);
+}
@@ -1 +1,155 @@
+//! Live upload and download rates in the tray
+//!
+//! While `enable_tray_speed` is on, the core's `/traffic` stream is read in the background and
+//! the rates go into the tray tooltip, and next to the icon where the tray shows a title
+//! (the macOS menu bar, Linux app indicators). A stream that stops sending is dropped and
+//! opened again later, the stale rates are cleared in the meantime.
 
+use super::Tray;
+use crate::{
+    config::Config,
+    core::{CoreManager, RunningMode},
+    logging, logging_error,
+    module::mihomo::{MihomoManager, Rate},
+    process::{AsyncHandler, LoopGeneration},
+    utils::logging::Type,
+};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::time::Duration;
+
+/// the core sends a sample every second, a longer silence means the stream stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// first wait before opening the stream again, doubled on every failure
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+static GENERATION: LoopGeneration = LoopGeneration::new();
+
+/// the rates shown now, none while stopped or stalled
+static RATE: Mutex<Option<Rate>> = Mutex::new(None);
+
+/// The rates shown in the tray
+pub fn current() -> Option<Rate> {
+    RATE.lock().clone()
+}
+
+/// Start or stop reading the stream per `enable_tray_speed`
+pub fn update() {
+    let enabled = Config::verge().latest().enable_tray_speed.unwrap_or(false);
+    let generation = GENERATION.next();
+    show(None);
+    if enabled {
+        AsyncHandler::spawn(move || run(generation));
+    }
+}
+
+/// Stop reading the stream
+pub fn stop() {
+    GENERATION.stop();
+    show(None);
+}
+
+async fn run(generation: u64) {
+    let mut delay = RETRY_DELAY;
+    while GENERATION.is_current(generation) {
+        if CoreManager::global().get_running_mode().await == RunningMode::NotRunning {
+            tokio::time::sleep(RETRY_DELAY).await;
+            continue;
+        }
+        if let Err(err) = follow(generation, &mut delay).await {
+            logging!(debug, Type::Tray, "Traffic stream interrupted: {}", err);
+        }
+        if GENERATION.is_current(generation) {
+            show(None);
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Read the stream until it ends or stalls, or a newer loop takes over
+async fn follow(generation: u64, delay: &mut Duration) -> Result<(), String> {
+    let mut stream = MihomoManager::global().stream("/traffic").await?;
+    let mut samples: u64 = 0;
+    while GENERATION.is_current(generation) {
+        let line = tokio::time::timeout(STALL_TIMEOUT, stream.next_line())
+            .await
+            .map_err(|_| "no sample in time".to_string())??;
+        let Some(line) = line else {
+            return Ok(());
+        };
+        let Some(rate) = parse_rate(&line) else {
+            continue;
+        };
+        *delay = RETRY_DELAY;
+        // 每秒一个采样，按设置的间隔刷新
+        let interval = Config::verge()
+            .latest()
+            .tray_speed_interval
+            .unwrap_or(1)
+            .max(1);
+        if samples % interval == 0 {
+            show(Some(rate));
+        }
+        samples += 1;
+    }
+    Ok(())
+}
+
+fn show(rate: Option<Rate>) {
+    {
+        let mut current = RATE.lock();
+        if *current == rate {
+            return;
+        }
+        *current = rate;
+    }
+    logging_error!(Type::Tray, true, Tray::global().update_speed());
+}
+
+/// A `/traffic` sample, `{"up":1024,"down":2048}`
+fn parse_rate(line: &str) -> Option<Rate> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    Some(Rate {
+        up: value["up"].as_u64()?,
+        down: value["down"].as_u64()?,
+    })
+}
+
+/// `↑ 1.2 KB/s ↓ 3.4 MB/s`
+pub fn format_rate(rate: &Rate) -> String {
+    format!("↑ {} ↓ {}", format_speed(rate.up), format_speed(rate.down))
+}
+
+fn format_speed(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB/s", "MB/s", "GB/s", "TB/s"];
+    if bytes < 1024 {
+        return format!("{bytes} B/s");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[test]
+fn test_speed_rate() {
+    assert_eq!(
+        parse_rate(r#"{"up":1536,"down":0}"#),
+        Some(Rate { up: 1536, down: 0 })
+    );
+    assert_eq!(parse_rate("{}"), None);
+    assert_eq!(format_speed(512), "512 B/s");
+    assert_eq!(format_speed(1536), "1.5 KB/s");
+    assert_eq!(format_speed(3 * 1024 * 1024), "3.0 MB/s");
+    assert_eq!(
+        format_rate(&Rate { up: 0, down: 2048 }),
+        "↑ 0 B/s ↓ 2.0 KB/s"
+    );
+}
@@ -1,10 +1,83 @@
 #![cfg(target_os = "windows")]
 
-use crate::utils::dirs;
+use crate::{
+    logging,
+    utils::{dirs, elevate, logging::Type},
+};
 use anyhow::{bail, Result};
 use deelevate::{PrivilegeLevel, Token};
 use runas::Command as RunasCommand;
-use std::process::Command as StdCommand;
+use serde::Serialize;
+use std::{io, process::Command as StdCommand, ptr};
+use winapi::{
+    shared::{
+        minwindef::DWORD,
+        winerror::{ERROR_SUCCESS, S_OK},
+    },
+    um::{
+        heapapi::{GetProcessHeap, HeapFree},
+        sddl::{ConvertSidToStringSidW, ConvertStringSidToSidW},
+        shlwapi::SHLoadIndirectString,
+        winbase::LocalFree,
+        winnt::{LPWSTR, PSID, SID_AND_ATTRIBUTES},
+    },
+};
+
+/// An installed UWP / AppX package
+#[derive(Debug, Clone, Serialize)]
+pub struct UwpApp {
+    /// the app container SID, `S-1-15-2-…`
+    pub sid: String,
+    /// the package family name
+    pub name: String,
+    pub display_name: String,
+    /// allowed to reach 127.0.0.1, where the proxy listens
+    pub exempt: bool,
+}
+
+#[repr(C)]
+#[allow(dead_code)] // 仅用于匹配内存布局
+struct AppContainerCapabilities {
+    count: DWORD,
+    capabilities: *mut SID_AND_ATTRIBUTES,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct AppContainerBinaries {
+    count: DWORD,
+    binaries: *mut LPWSTR,
+}
+
+/// `INET_FIREWALL_APP_CONTAINER`
+#[repr(C)]
+#[allow(dead_code)]
+struct AppContainer {
+    app_container_sid: PSID,
+    user_sid: PSID,
+    app_container_name: LPWSTR,
+    display_name: LPWSTR,
+    description: LPWSTR,
+    capabilities: AppContainerCapabilities,
+    binaries: AppContainerBinaries,
+    working_directory: LPWSTR,
+    package_full_name: LPWSTR,
+}
+
+#[link(name = "FirewallAPI")]
+extern "system" {
+    fn NetworkIsolationEnumAppContainers(
+        flags: DWORD,
+        count: *mut DWORD,
+        containers: *mut *mut AppContainer,
+    ) -> DWORD;
+    fn NetworkIsolationFreeAppContainers(containers: *mut AppContainer) -> DWORD;
+    fn NetworkIsolationGetAppContainerConfig(
+        count: *mut DWORD,
+        sids: *mut *mut SID_AND_ATTRIBUTES,
+    ) -> DWORD;
+    fn NetworkIsolationSetAppContainerConfig(count: DWORD, sids: *mut SID_AND_ATTRIBUTES) -> DWORD;
+}
 
 pub async fn invoke_uwptools() -> Result<()> {
     let resource_dir = dirs::app_resources_dir()?;
@@ -24,3 +97,239 @@ pub async fn invoke_uwptools() -> Result<()> {
 
     Ok(())
 }
+
+/// The installed UWP apps and whether each may use the local proxy
+///
+/// Store apps are blocked from loopback by default, so they ignore a proxy on
+/// 127.0.0.1 until exempted, the same list `CheckNetIsolation LoopbackExempt`
+/// manages.
+pub fn list_apps() -> Result<Vec<UwpApp>> {
+    let exempt = exempt_sids()?;
+    let mut count: DWORD = 0;
+    let mut containers: *mut AppContainer = ptr::null_mut();
+    check("NetworkIsolationEnumAppContainers", unsafe {
+        NetworkIsolationEnumAppContainers(0, &mut count, &mut containers)
+    })?;
+    if containers.is_null() {
+        return Ok(vec![]);
+    }
+
+    let mut apps = unsafe { std::slice::from_raw_parts(containers, count as usize) }
+        .iter()
+        .filter_map(|container| {
+            let sid = sid_to_string(container.app_container_sid)?;
+            let name = from_wide(container.app_container_name);
+            let display_name = resolve_name(&from_wide(container.display_name));
+            Some(UwpApp {
+                exempt: exempt.contains(&sid),
+                display_name: if display_name.is_empty() {
+                    name.clone()
+                } else {
+                    display_name
+                },
+                name,
+                sid,
+            })
+        })
+        .collect::<Vec<_>>();
+    unsafe { NetworkIsolationFreeAppContainers(containers) };
+
+    apps.sort_by_key(|app| app.display_name.to_lowercase());
+    Ok(apps)
+}
+
+/// Grant or revoke the loopback exemption of the given apps
+///
+/// Changing the list needs administrator rights, without them the change goes
+/// through CheckNetIsolation behind a single UAC prompt.
+pub fn set_exempt(sids: &[String], exempt: bool) -> Result<()> {
+    if let Some(sid) = sids.iter().find(|sid| !is_app_container_sid(sid)) {
+        bail!("invalid app container SID \"{sid}\"");
+    }
+    if sids.is_empty() {
+        return Ok(());
+    }
+    logging!(
+        info,
+        Type::System,
+        true,
+        "{} loopback exemption of {} UWP apps",
+        if exempt { "Granting" } else { "Revoking" },
+        sids.len()
+    );
+
+    let elevated =
+        Token::with_current_process()?.privilege_level()? != PrivilegeLevel::NotPrivileged;
+    if !elevated {
+        return run_check_net_isolation(sids, exempt);
+    }
+
+    let mut list = exempt_sids()?;
+    if exempt {
+        for sid in sids {
+            if !list.contains(sid) {
+                list.push(sid.clone());
+            }
+        }
+    } else {
+        list.retain(|sid| !sids.contains(sid));
+    }
+    write_exempt(&list)
+}
+
+/// Exempt every app that isn't yet, returns how many were changed
+pub fn exempt_all() -> Result<usize> {
+    let sids = list_apps()?
+        .into_iter()
+        .filter(|app| !app.exempt)
+        .map(|app| app.sid)
+        .collect::<Vec<_>>();
+    set_exempt(&sids, true)?;
+    Ok(sids.len())
+}
+
+fn exempt_sids() -> Result<Vec<String>> {
+    let mut count: DWORD = 0;
+    let mut sids: *mut SID_AND_ATTRIBUTES = ptr::null_mut();
+    check("NetworkIsolationGetAppContainerConfig", unsafe {
+        NetworkIsolationGetAppContainerConfig(&mut count, &mut sids)
+    })?;
+    if sids.is_null() {
+        return Ok(vec![]);
+    }
+    let entries = unsafe { std::slice::from_raw_parts(sids, count as usize) };
+    let list = entries
+        .iter()
+        .filter_map(|entry| sid_to_string(entry.Sid))
+        .collect();
+    // 数组与其中的 SID 都由调用方从进程堆释放
+    unsafe {
+        let heap = GetProcessHeap();
+        for entry in entries {
+            HeapFree(heap, 0, entry.Sid);
+        }
+        HeapFree(heap, 0, sids as _);
+    }
+    Ok(list)
+}
+
+fn write_exempt(sids: &[String]) -> Result<()> {
+    let psids = sids
+        .iter()
+        .filter_map(|sid| string_to_sid(sid))
+        .collect::<Vec<_>>();
+    let mut entries = psids
+        .iter()
+        .map(|&sid| SID_AND_ATTRIBUTES {
+            Sid: sid,
+            Attributes: 0,
+        })
+        .collect::<Vec<_>>();
+    let result = unsafe {
+        NetworkIsolationSetAppContainerConfig(entries.len() as DWORD, entries.as_mut_ptr())
+    };
+    for sid in psids {
+        unsafe { LocalFree(sid) };
+    }
+    check("NetworkIsolationSetAppContainerConfig", result)
+}
+
+fn run_check_net_isolation(sids: &[String], exempt: bool) -> Result<()> {
+    // CheckNetIsolation 每次只接受一个 SID，放在同一个提权进程中只弹一次 UAC
+    let flag = if exempt { "-a" } else { "-d" };
+    let script = sids
+        .iter()
+        .map(|sid| {
+            format!(
+                "CheckNetIsolation.exe LoopbackExempt {flag} -p={sid}\r\n\
+                 if ($LASTEXITCODE) {{ exit $LASTEXITCODE }}"
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    elevate::run_powershell(&script)
+        .map_err(|err| anyhow::anyhow!("CheckNetIsolation failed: {err}"))
+}
+
+fn check(function: &str, result: DWORD) -> Result<()> {
+    if result != ERROR_SUCCESS {
+        bail!(
+            "{function} failed: {}",
+            io::Error::from_raw_os_error(result as i32)
+        );
+    }
+    Ok(())
+}
+
+/// `S-1-15-2-` followed by numbers, nothing that could reach the script
+fn is_app_container_sid(sid: &str) -> bool {
+    sid.strip_prefix("S-1-15-2-")
+        .is_some_and(|rest| !rest.is_empty() && rest.split('-').all(|n| n.parse::<u32>().is_ok()))
+}
+
+fn sid_to_string(sid: PSID) -> Option<String> {
+    if sid.is_null() {
+        return None;
+    }
+    let mut string: LPWSTR = ptr::null_mut();
+    if unsafe { ConvertSidToStringSidW(sid, &mut string) } == 0 {
+        return None;
+    }
+    let value = from_wide(string);
+    unsafe { LocalFree(string as _) };
+    Some(value)
+}
+
+fn string_to_sid(sid: &str) -> Option<PSID> {
+    let wide = to_wide(sid);
+    let mut psid: PSID = ptr::null_mut();
+    if unsafe { ConvertStringSidToSidW(wide.as_ptr(), &mut psid) } == 0 {
+        return None;
+    }
+    Some(psid)
+}
+
+/// Resolve `@{Package?ms-resource://…}` display names
+fn resolve_name(name: &str) -> String {
+    if !name.starts_with("@{") {
+        return name.to_string();
+    }
+    let source = to_wide(name);
+    let mut buffer = vec![0u16; 512];
+    let result = unsafe {
+        SHLoadIndirectString(
+            source.as_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len() as u32,
+            ptr::null_mut(),
+        )
+    };
+    if result != S_OK {
+        return String::new();
+    }
+    from_wide(buffer.as_mut_ptr())
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn from_wide(ptr: LPWSTR) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe {
+        let len = (0..).take_while(|&i| *ptr.add(i) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len))
+    }
+}
+
+#[test]
+fn test_app_container_sid() {
+    assert!(is_app_container_sid(
+        "S-1-15-2-1609473798-1231923017-684268153-4268514328-882773646-2760585773-1760938157"
+    ));
+    assert!(!is_app_container_sid("S-1-5-18"));
+    assert!(!is_app_container_sid("S-1-15-2-1 & calc"));
+    assert!(!is_app_container_sid("S-1-15-2-"));
+}
@@ -0,0 +1,150 @@
+use crate::{core::sysopt, logging, utils::logging::Type};
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::ptr;
+use winapi::{
+    shared::minwindef::DWORD,
+    um::{
+        winbase::GlobalFree,
+        winerror::ERROR_ACCESS_DENIED,
+        winhttp::{
+            WinHttpGetDefaultProxyConfiguration, WinHttpSetDefaultProxyConfiguration,
+            WINHTTP_ACCESS_TYPE_NAMED_PROXY, WINHTTP_ACCESS_TYPE_NO_PROXY, WINHTTP_PROXY_INFO,
+        },
+        winnt::LPWSTR,
+    },
+};
+
+/// The machine wide WinHTTP proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WinHttpProxy {
+    /// `host:port`, none for a direct connection
+    pub server: Option<String>,
+    /// `;` separated, the same format as WinINET
+    pub bypass: Option<String>,
+}
+
+/// The setting before we first changed it, restored on exit
+/// Kept on disk as well, so it is still put back after a crash
+static ORIGINAL: Lazy<Mutex<Option<WinHttpProxy>>> =
+    Lazy::new(|| Mutex::new(sysopt::load_original_proxy()));
+
+/// Point the WinHTTP proxy at ours
+///
+/// Windows Update, BITS and many command line tools only read the WinHTTP
+/// proxy, not the WinINET one sysproxy.exe sets. WinHTTP has no PAC setting, so
+/// this is only used for the global proxy. The setting found before the first
+/// change is kept and put back when the proxy is turned off or the app exits.
+/// Writing it needs administrator rights.
+pub fn apply(server: &str, bypass: &str) -> Result<()> {
+    {
+        let mut original = ORIGINAL.lock();
+        if original.is_none() {
+            let current = read()?;
+            // 上次异常退出残留的是自己的设置，不作为原始设置
+            let current = if current.server.as_deref() == Some(server) {
+                WinHttpProxy::default()
+            } else {
+                current
+            };
+            sysopt::save_original_proxy(Some(&current));
+            *original = Some(current);
+        }
+    }
+    write(&WinHttpProxy {
+        server: Some(server.into()),
+        bypass: (!bypass.is_empty()).then(|| bypass.into()),
+    })
+}
+
+/// Put back the setting found before the first change
+///
+/// `server` is our own proxy. Without a change of ours only leftovers of a
+/// crashed run are cleared, a proxy set by someone else is left alone.
+pub fn restore(server: &str) -> Result<()> {
+    let original = ORIGINAL.lock().take();
+    let original = match original {
+        Some(original) => {
+            sysopt::save_original_proxy::<WinHttpProxy>(None);
+            original
+        }
+        // 没有改动过，只清理残留的自己的设置
+        None => match read() {
+            Ok(current) if current.server.as_deref() == Some(server) => WinHttpProxy::default(),
+            _ => return Ok(()),
+        },
+    };
+    logging!(
+        info,
+        Type::System,
+        true,
+        "Restoring WinHTTP proxy: {:?}",
+        original
+    );
+    write(&original)
+}
+
+/// The current WinHTTP proxy, same as `netsh winhttp show proxy`
+pub fn read() -> Result<WinHttpProxy> {
+    let mut info = WINHTTP_PROXY_INFO {
+        dwAccessType: 0,
+        lpszProxy: ptr::null_mut(),
+        lpszProxyBypass: ptr::null_mut(),
+    };
+    if unsafe { WinHttpGetDefaultProxyConfiguration(&mut info) } == 0 {
+        bail!(
+            "WinHttpGetDefaultProxyConfiguration failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    // 字符串由 WinHTTP 分配，无论是否使用都要释放
+    let server = take_string(info.lpszProxy);
+    let bypass = take_string(info.lpszProxyBypass);
+    if info.dwAccessType != WINHTTP_ACCESS_TYPE_NAMED_PROXY {
+        return Ok(WinHttpProxy::default());
+    }
+    Ok(WinHttpProxy { server, bypass })
+}
+
+fn write(proxy: &WinHttpProxy) -> Result<()> {
+    let mut server = proxy.server.as_deref().map(to_wide);
+    let mut bypass = proxy.bypass.as_deref().map(to_wide);
+    let access_type: DWORD = if server.is_some() {
+        WINHTTP_ACCESS_TYPE_NAMED_PROXY
+    } else {
+        WINHTTP_ACCESS_TYPE_NO_PROXY
+    };
+    let mut info = WINHTTP_PROXY_INFO {
+        dwAccessType: access_type,
+        lpszProxy: server.as_mut().map_or(ptr::null_mut(), |s| s.as_mut_ptr()),
+        lpszProxyBypass: bypass.as_mut().map_or(ptr::null_mut(), |s| s.as_mut_ptr()),
+    };
+    if unsafe { WinHttpSetDefaultProxyConfiguration(&mut info) } == 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+            bail!("only an administrator can set the WinHTTP proxy");
+        }
+        bail!("WinHttpSetDefaultProxyConfiguration failed: {}", err);
+    }
+    Ok(())
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Copy a string allocated by WinHTTP and free it
+fn take_string(ptr: LPWSTR) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let value = unsafe {
+        let len = (0..).take_while(|&i| *ptr.add(i) != 0).count();
+        let value = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+        GlobalFree(ptr as _);
+        value
+    };
+    Some(value).filter(|v| !v.is_empty())
+}
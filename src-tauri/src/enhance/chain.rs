@@ -1,10 +1,9 @@
 use super::SeqMap;
 use crate::{
-    config::PrfItem,
+    config::{read_profile_file, PrfItem},
     utils::{dirs, help},
 };
 use serde_yaml::Mapping;
-use std::fs;
 
 #[derive(Debug, Clone)]
 pub struct ChainItem {
@@ -43,7 +42,7 @@ impl From<&PrfItem> for Option<ChainItem> {
         match itype {
             "script" => Some(ChainItem {
                 uid,
-                data: ChainType::Script(fs::read_to_string(path).ok()?),
+                data: ChainType::Script(read_profile_file(&path).ok()?),
             }),
             "merge" => Some(ChainItem {
                 uid,
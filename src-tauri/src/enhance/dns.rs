@@ -0,0 +1,73 @@
+use crate::config::PrfDns;
+use serde_yaml::{Mapping, Value};
+
+/// Apply the DNS overrides of a profile to the `dns` section
+/// The server lists are replaced, the fake-ip filter entries are appended
+pub fn use_dns_override(mut config: Mapping, dns: &PrfDns) -> Mapping {
+    let mut dns_val = config
+        .get("dns")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+
+    let replace = [
+        ("nameserver", &dns.nameserver),
+        ("fallback", &dns.fallback),
+        ("default-nameserver", &dns.default_nameserver),
+    ];
+    for (key, servers) in replace {
+        if let Some(servers) = servers.as_ref().filter(|s| !s.is_empty()) {
+            dns_val.insert(key.into(), servers.clone().into());
+        }
+    }
+
+    if let Some(filters) = dns.fake_ip_filter.as_ref() {
+        let mut fake_ip_filter = dns_val
+            .get("fake-ip-filter")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        for filter in filters {
+            let filter = Value::from(filter.as_str());
+            if !fake_ip_filter.contains(&filter) {
+                fake_ip_filter.push(filter);
+            }
+        }
+        dns_val.insert("fake-ip-filter".into(), fake_ip_filter.into());
+    }
+
+    config.insert("dns".into(), dns_val.into());
+    config
+}
+
+#[test]
+fn test_use_dns_override() {
+    let config: Mapping = serde_yaml::from_str(
+        r#"
+dns:
+  enable: true
+  nameserver: [223.5.5.5]
+  fallback: [8.8.8.8]
+  fake-ip-filter: ["*.lan"]
+"#,
+    )
+    .unwrap();
+    let dns = PrfDns {
+        nameserver: Some(vec!["https://dns.example.com/dns-query".into()]),
+        fallback: Some(vec![]),
+        fake_ip_filter: Some(vec!["*.lan".into(), "+.corp.example.com".into()]),
+        ..PrfDns::default()
+    };
+
+    let config = use_dns_override(config, &dns);
+    let expected: Mapping = serde_yaml::from_str(
+        r#"
+enable: true
+nameserver: ["https://dns.example.com/dns-query"]
+fallback: [8.8.8.8]
+fake-ip-filter: ["*.lan", "+.corp.example.com"]
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.get("dns"), Some(&Value::from(expected)));
+}
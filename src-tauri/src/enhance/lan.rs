@@ -0,0 +1,188 @@
+use crate::config::Config;
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use serde_yaml::{Mapping, Value};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// 本机与本地工具通过回环地址连接，始终放行
+const LOOPBACK: [&str; 2] = ["127.0.0.0/8", "::1/128"];
+
+/// 允许局域网连接时限制可以使用代理的设备
+///
+/// 放行列表替换订阅的放行列表，为空时沿用订阅的设置，订阅也没有时只放行本机所在的网段。
+/// 拒绝列表与订阅的合并，且优先于放行列表。未开启 allow-lan 时不修改
+pub fn use_lan_acl(mut config: Mapping) -> Mapping {
+    let allow_lan = config
+        .get("allow-lan")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    if !allow_lan {
+        return config;
+    }
+    let (allowed, disallowed) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.lan_allowed_ips.clone().unwrap_or_default(),
+            verge.lan_disallowed_ips.clone().unwrap_or_default(),
+        )
+    };
+    apply_acl(&mut config, &allowed, &disallowed, local_subnets);
+    config
+}
+
+fn apply_acl(
+    config: &mut Mapping,
+    allowed: &[String],
+    disallowed: &[String],
+    local_subnets: impl FnOnce() -> Vec<String>,
+) {
+    let normalize = |list: &[String]| {
+        list.iter()
+            .filter_map(|entry| parse_cidr(entry).ok())
+            .collect::<Vec<_>>()
+    };
+    let merge = |list: &mut Vec<String>, entries: Vec<String>| {
+        for entry in entries {
+            if !list.contains(&entry) {
+                list.push(entry);
+            }
+        }
+    };
+    let profile_list = |config: &Mapping, key: &str| {
+        config.get(key).and_then(Value::as_sequence).map(|list| {
+            list.iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let allowed = normalize(allowed);
+    // 订阅与设置都没有放行列表时内核放行所有地址
+    let allowed = match profile_list(config, "lan-allowed-ips") {
+        _ if !allowed.is_empty() => Some(allowed),
+        Some(_) => None,
+        None => Some(local_subnets()),
+    };
+    if let Some(allowed) = allowed {
+        let mut list = LOOPBACK.map(String::from).to_vec();
+        merge(&mut list, allowed);
+        config.insert("lan-allowed-ips".into(), list.into());
+    }
+
+    let disallowed = normalize(disallowed);
+    if !disallowed.is_empty() {
+        let mut list = profile_list(config, "lan-disallowed-ips").unwrap_or_default();
+        merge(&mut list, disallowed);
+        config.insert("lan-disallowed-ips".into(), list.into());
+    }
+}
+
+/// 本机局域网地址所在的网段，网络变化后需要重新生成配置
+fn local_subnets() -> Vec<String> {
+    let Ok(interfaces) = NetworkInterface::show() else {
+        return vec![];
+    };
+    let mut subnets = interfaces
+        .into_iter()
+        .flat_map(|interface| interface.addr)
+        .filter_map(|addr| match addr {
+            Addr::V4(v4) if v4.ip.is_private() || v4.ip.is_link_local() => {
+                let mask = v4.netmask.unwrap_or(Ipv4Addr::new(255, 255, 255, 0));
+                let network = Ipv4Addr::from(v4.ip.to_bits() & mask.to_bits());
+                Some(format!("{network}/{}", mask.to_bits().count_ones()))
+            }
+            Addr::V6(v6) if is_local_v6(&v6.ip) => {
+                let mask = v6.netmask.unwrap_or(Ipv6Addr::from_bits(!0 << 64));
+                let network = Ipv6Addr::from(v6.ip.to_bits() & mask.to_bits());
+                Some(format!("{network}/{}", mask.to_bits().count_ones()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    subnets.sort();
+    subnets.dedup();
+    subnets
+}
+
+/// 唯一本地地址 fc00::/7 与链路本地地址 fe80::/10
+fn is_local_v6(ip: &Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+}
+
+/// 解析地址或网段，单个地址补全为 /32 或 /128
+pub fn parse_cidr(entry: &str) -> Result<String, String> {
+    let entry = entry.trim();
+    let (addr, prefix) = entry.split_once('/').unwrap_or((entry, ""));
+    let addr = addr
+        .parse::<IpAddr>()
+        .map_err(|_| format!("invalid address \"{entry}\""))?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = if prefix.is_empty() {
+        max
+    } else {
+        prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= max)
+            .ok_or_else(|| format!("invalid prefix in \"{entry}\""))?
+    };
+    Ok(format!("{addr}/{prefix}"))
+}
+
+#[test]
+fn test_lan_acl() {
+    assert_eq!(parse_cidr("192.168.1.20").unwrap(), "192.168.1.20/32");
+    assert_eq!(parse_cidr(" 10.0.0.0/8 ").unwrap(), "10.0.0.0/8");
+    assert_eq!(parse_cidr("fd00::/8").unwrap(), "fd00::/8");
+    assert!(parse_cidr("192.168.1.0/33").is_err());
+    assert!(parse_cidr("printer.lan").is_err());
+
+    let subnets = || vec!["192.168.1.0/24".to_string()];
+    let list = |config: &Mapping, key: &str| {
+        config
+            .get(key)
+            .and_then(Value::as_sequence)
+            .map(|list| {
+                list.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut config = Mapping::new();
+    apply_acl(
+        &mut config,
+        &["10.0.0.0/8".into(), "bad".into()],
+        &["192.168.1.1".into()],
+        subnets,
+    );
+    assert_eq!(
+        list(&config, "lan-allowed-ips"),
+        ["127.0.0.0/8", "::1/128", "10.0.0.0/8"]
+    );
+    assert_eq!(list(&config, "lan-disallowed-ips"), ["192.168.1.1/32"]);
+
+    // 都没有设置时只放行本机所在的网段
+    let mut config = Mapping::new();
+    apply_acl(&mut config, &[], &[], subnets);
+    assert_eq!(
+        list(&config, "lan-allowed-ips"),
+        ["127.0.0.0/8", "::1/128", "192.168.1.0/24"]
+    );
+    assert!(!config.contains_key("lan-disallowed-ips"));
+
+    // 订阅的放行列表保留，拒绝列表合并
+    let mut config = Mapping::new();
+    config.insert("lan-allowed-ips".into(), vec!["172.16.0.0/12"].into());
+    config.insert("lan-disallowed-ips".into(), vec!["172.16.0.1/32"].into());
+    apply_acl(&mut config, &[], &["172.16.0.2".into()], subnets);
+    assert_eq!(list(&config, "lan-allowed-ips"), ["172.16.0.0/12"]);
+    assert_eq!(
+        list(&config, "lan-disallowed-ips"),
+        ["172.16.0.1/32", "172.16.0.2/32"]
+    );
+}
@@ -1,29 +1,56 @@
 mod chain;
+mod dns;
 pub mod field;
+pub mod lan;
 mod merge;
 mod script;
 pub mod seq;
 mod tun;
 
-use self::{chain::*, field::*, merge::*, script::*, seq::*, tun::*};
-use crate::{config::Config, utils::tmpl};
+pub use self::script::check_script_syntax;
+use self::{chain::*, dns::*, field::*, lan::*, merge::*, script::*, seq::*, tun::*};
+use crate::{
+    config::{Config, IClashTemp, IProfiles},
+    core::{captive_portal, handle, ports},
+    utils::{dirs, metered, tmpl},
+};
 use serde_yaml::Mapping;
 use std::collections::{HashMap, HashSet};
 
 type ResultLog = Vec<(String, String)>;
 
+/// top-level key -> uid of the merge template that last set it
+pub type MergeSources = HashMap<String, String>;
+
+type EnhanceResult = (
+    Mapping,
+    Vec<String>,
+    HashMap<String, ResultLog>,
+    MergeSources,
+);
+
 /// Enhance mode
-/// 返回最终订阅、该订阅包含的键、和script执行的结果
-pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
-    // config.yaml 的订阅
-    let clash_config = { Config::clash().latest().0.clone() };
+/// 返回最终订阅、该订阅包含的键、script执行的结果和merge模板的来源
+pub async fn enhance() -> EnhanceResult {
+    enhance_profile(None).await
+}
+
+/// Enhance the given profile instead of the current one
+/// used to check a profile before switching to it
+pub async fn enhance_profile(uid: Option<String>) -> EnhanceResult {
+    // config.yaml 的订阅，监听端口以 verge 的设置为准
+    let mut clash_config = { Config::clash().latest().0.clone() };
+    // 预检时脚本错误只作为结果返回，不打扰用户
+    let dry_run = uid.is_some();
+    ports::use_verge_ports(&mut clash_config, &Config::verge().latest());
 
     let (clash_core, enable_tun, enable_builtin, socks_enabled, http_enabled, enable_dns_settings) = {
         let verge = Config::verge();
         let verge = verge.latest();
         (
             Some(verge.get_valid_clash_core()),
-            verge.enable_tun_mode.unwrap_or(false),
+            // 登录认证页面期间 TUN 保持关闭
+            verge.enable_tun_mode.unwrap_or(false) && !captive_portal::is_bypassing(),
             verge.enable_builtin_enhanced.unwrap_or(true),
             verge.verge_socks_enabled.unwrap_or(false),
             verge.verge_http_enabled.unwrap_or(false),
@@ -42,11 +69,21 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         let verge = verge.latest();
         verge.verge_tproxy_enabled.unwrap_or(false)
     };
+    // 外部内核不由应用启动，不改动其控制接口
+    let (controller_ipc, disable_tcp_controller) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.enable_controller_ipc.unwrap_or(false) && verge.external_core().is_none(),
+            verge.disable_tcp_controller.unwrap_or(false),
+        )
+    };
 
     // 从profiles里拿东西
     let (
         mut config,
         merge_item,
+        merge_chain,
         script_item,
         rules_item,
         proxies_item,
@@ -54,9 +91,16 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         global_merge,
         global_script,
         profile_name,
+        dns_override,
     ) = {
         let profiles = Config::profiles();
-        let profiles = profiles.latest();
+        let latest = profiles.latest();
+        // 在副本上替换 current，不影响正在使用的配置
+        let preview = uid.map(|uid| IProfiles {
+            current: Some(uid),
+            ..(**latest).clone()
+        });
+        let profiles = preview.as_ref().unwrap_or(&**latest);
 
         let current = profiles.current_mapping().unwrap_or_default();
         let merge = profiles
@@ -67,6 +111,12 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
                 uid: "".into(),
                 data: ChainType::Merge(Mapping::new()),
             });
+        let merge_chain: Vec<ChainItem> = profiles
+            .current_merges()
+            .iter()
+            .filter_map(|uid| profiles.get_item(uid).ok())
+            .filter_map(<Option<ChainItem>>::from)
+            .collect();
         let script = profiles
             .get_item(&profiles.current_script().unwrap_or_default())
             .ok()
@@ -127,6 +177,7 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         (
             current,
             merge,
+            merge_chain,
             script,
             rules,
             proxies,
@@ -134,15 +185,18 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             global_merge,
             global_script,
             name,
+            profiles.current_dns(),
         )
     };
 
     let mut result_map = HashMap::new(); // 保存脚本日志
     let mut exists_keys = use_keys(&config); // 保存出现过的keys
+    let mut merge_sources = MergeSources::new(); // 保存每个key最后由哪个merge修改
 
     // 全局Merge和Script
     if let ChainType::Merge(merge) = global_merge.data {
         exists_keys.extend(use_keys(&merge));
+        track_merge_sources(&mut merge_sources, &merge, &global_merge.uid);
         config = use_merge(merge, config.to_owned());
     }
 
@@ -158,6 +212,9 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             Err(err) => logs.push(("exception".into(), err.to_string())),
         }
 
+        if !dry_run {
+            notice_script_errors(&logs);
+        }
         result_map.insert(global_script.uid, logs);
     }
 
@@ -176,9 +233,19 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
 
     if let ChainType::Merge(merge) = merge_item.data {
         exists_keys.extend(use_keys(&merge));
+        track_merge_sources(&mut merge_sources, &merge, &merge_item.uid);
         config = use_merge(merge, config.to_owned());
     }
 
+    // 按顺序应用额外的merge模板，后面的覆盖前面的
+    for item in merge_chain {
+        if let ChainType::Merge(merge) = item.data {
+            exists_keys.extend(use_keys(&merge));
+            track_merge_sources(&mut merge_sources, &merge, &item.uid);
+            config = use_merge(merge, config.to_owned());
+        }
+    }
+
     if let ChainType::Script(script) = script_item.data {
         let mut logs = vec![];
 
@@ -191,6 +258,9 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             Err(err) => logs.push(("exception".into(), err.to_string())),
         }
 
+        if !dry_run {
+            notice_script_errors(&logs);
+        }
         result_map.insert(script_item.uid, logs);
     }
 
@@ -233,6 +303,29 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             config.insert(key, value);
         }
     }
+    // 监听需要认证时覆盖订阅的设置
+    let proxy_auth = Config::verge().latest().proxy_auth();
+    if let Some((username, password)) = proxy_auth {
+        config.insert(
+            "authentication".into(),
+            vec![format!("{username}:{password}")].into(),
+        );
+    }
+    if let Some(secret) = IClashTemp::config_secret() {
+        config.insert("secret".into(), secret.into());
+    }
+    if controller_ipc {
+        let key = if cfg!(windows) {
+            "external-controller-pipe"
+        } else {
+            "external-controller-unix"
+        };
+        config.insert(key.into(), dirs::controller_ipc().into());
+        // 空地址表示不监听 TCP
+        if disable_tcp_controller {
+            config.insert("external-controller".into(), "".into());
+        }
+    }
 
     // 内建脚本最后跑
     if enable_builtin {
@@ -256,11 +349,17 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
     }
 
     config = use_tun(config, enable_tun).await;
+    config = use_lan_acl(config);
+
+    // 按流量计费的网络下关闭 GeoData 自动更新
+    if metered::should_defer_updates().await {
+        config.insert("geo-auto-update".into(), false.into());
+        metered::defer_geo_auto_update();
+    }
     config = use_sort(config);
 
     // 应用独立的DNS配置（如果启用）
     if enable_dns_settings {
-        use crate::utils::dirs;
         use std::fs;
 
         if let Ok(app_dir) = dirs::app_home_dir() {
@@ -292,9 +391,31 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         }
     }
 
+    // 订阅自己的DNS设置优先于全局DNS配置
+    if let Some(dns) = dns_override {
+        config = use_dns_override(config, &dns);
+    }
+
     let mut exists_set = HashSet::new();
     exists_set.extend(exists_keys);
     exists_keys = exists_set.into_iter().collect();
 
-    (config, exists_keys, result_map)
+    (config, exists_keys, result_map, merge_sources)
+}
+
+/// record `uid` as the last template touching each top-level key of `merge`
+fn track_merge_sources(sources: &mut MergeSources, merge: &Mapping, uid: &str) {
+    if uid.is_empty() {
+        return;
+    }
+    for key in use_keys(merge) {
+        sources.insert(key, uid.to_string());
+    }
+}
+
+/// 将脚本执行异常推送到前端
+fn notice_script_errors(logs: &ResultLog) {
+    for (_, msg) in logs.iter().filter(|(level, _)| level == "exception") {
+        handle::Handle::notice_message("config_validate::script_error", msg);
+    }
 }
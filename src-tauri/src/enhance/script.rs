@@ -1,38 +1,125 @@
 use super::use_lowercase;
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Error, Result};
+use rquickjs::{convert::Coerced, CatchResultExt, Context, Ctx, Function, Runtime};
 use serde_yaml::Mapping;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 脚本最长执行时间，超时后运行时被中断
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+/// 中断检查的总次数上限，引擎约每一万条指令检查一次，不论指令在哪个循环中
+const SCRIPT_MAX_TICKS: u64 = 100_000;
+/// 脚本运行时的堆内存上限
+const SCRIPT_MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+const SCRIPT_STACK_LIMIT: usize = 1024 * 1024;
+/// 限制返回结果的大小和日志条数
+const SCRIPT_MAX_OUTPUT: usize = 32 * 1024 * 1024;
+const SCRIPT_MAX_LOGS: usize = 1000;
+
+const ERROR_FLAG: &str = "__error_flag__ ";
 
 pub fn use_script(
     script: String,
     config: Mapping,
     name: String,
 ) -> Result<(Mapping, Vec<(String, String)>)> {
-    use boa_engine::{native_function::NativeFunction, Context, JsValue, Source};
-    use std::sync::{Arc, Mutex};
-    let mut context = Context::default();
+    let config = use_lowercase(config.clone());
+    let config_str = serde_json::to_string(&config)?;
+
+    // 在独立线程中执行，运行时到时间后自行中断，线程随之退出
+    let (tx, rx) = mpsc::channel();
+    thread::Builder::new()
+        .name("enhance-script".into())
+        .spawn(move || {
+            let _ = tx.send(run_script(&script, &config_str, &name));
+        })?;
+
+    // 多等一会儿，让中断后的错误先返回
+    let (result, mut out) = match rx.recv_timeout(SCRIPT_TIMEOUT + Duration::from_secs(1)) {
+        Ok(Ok(res)) => res,
+        Ok(Err(err)) => return Ok((config, vec![("exception".into(), err.to_string())])),
+        Err(_) => {
+            let msg = format!(
+                "script timed out after {} seconds",
+                SCRIPT_TIMEOUT.as_secs()
+            );
+            return Ok((config, vec![("exception".into(), msg)]));
+        }
+    };
+
+    if let Some(err) = result.trim_matches('"').strip_prefix(ERROR_FLAG) {
+        out.push(("exception".into(), err.to_string()));
+        return Ok((config, out));
+    }
+    if result.len() > SCRIPT_MAX_OUTPUT {
+        out.push((
+            "exception".into(),
+            format!("script result exceeds {} bytes", SCRIPT_MAX_OUTPUT),
+        ));
+        return Ok((config, out));
+    }
+
+    // 直接解析JSON结果,不做其他解析
+    match parse_json_safely(&result) {
+        Ok(config) => Ok((use_lowercase(config), out)),
+        Err(err) => {
+            out.push(("exception".into(), err.to_string()));
+            Ok((config, out))
+        }
+    }
+}
+
+/// Check the syntax of a script without running it
+pub fn check_script_syntax(script: &str) -> Result<()> {
+    let source = serde_json::to_string(script)?;
+    with_limited_context(|ctx| {
+        ctx.eval::<(), _>(format!("new Function({source})"))
+            .catch(&ctx)
+            .map_err(|err| anyhow!("{err}"))
+    })
+}
+
+/// Run `f` in a fresh runtime with the time, instruction and memory limits of scripts
+fn with_limited_context<T>(f: impl FnOnce(Ctx<'_>) -> Result<T>) -> Result<T> {
+    let runtime = Runtime::new()?;
+    runtime.set_memory_limit(SCRIPT_MEMORY_LIMIT);
+    runtime.set_max_stack_size(SCRIPT_STACK_LIMIT);
+    let deadline = Instant::now() + SCRIPT_TIMEOUT;
+    let mut ticks = 0u64;
+    runtime.set_interrupt_handler(Some(Box::new(move || {
+        ticks += 1;
+        ticks > SCRIPT_MAX_TICKS || Instant::now() >= deadline
+    })));
 
+    let context = Context::full(&runtime)?;
+    context.with(f)
+}
+
+/// 执行脚本，返回 main 的 JSON 结果和 console 输出
+fn run_script(
+    script: &str,
+    config_str: &str,
+    name: &str,
+) -> Result<(String, Vec<(String, String)>)> {
     let outputs = Arc::new(Mutex::new(vec![]));
 
     let copy_outputs = outputs.clone();
-    unsafe {
-        let _ = context.register_global_builtin_callable(
-            "__verge_log__".into(),
-            2,
-            NativeFunction::from_closure(
-                move |_: &JsValue, args: &[JsValue], context: &mut Context| {
-                    let level = args.first().unwrap().to_string(context)?;
-                    let level = level.to_std_string().unwrap();
-                    let data = args.get(1).unwrap().to_string(context)?;
-                    let data = data.to_std_string().unwrap();
-                    let mut out = copy_outputs.lock().unwrap();
-                    out.push((level, data));
-                    Ok(JsValue::undefined())
-                },
-            ),
-        );
-    }
-    let _ = context.eval(Source::from_bytes(
-        r#"var console = Object.freeze({
+    let result = with_limited_context(|ctx| {
+        let log = Function::new(
+            ctx.clone(),
+            move |level: Coerced<String>, data: Coerced<String>| {
+                let mut out = copy_outputs.lock().unwrap();
+                if out.len() < SCRIPT_MAX_LOGS {
+                    out.push((level.0, data.0));
+                }
+            },
+        )?;
+        ctx.globals().set("__verge_log__", log)?;
+        ctx.eval::<(), _>(
+            r#"var console = Object.freeze({
         log(data){__verge_log__("log",JSON.stringify(data, null, 2))},
         info(data){__verge_log__("info",JSON.stringify(data, null, 2))},
         error(data){__verge_log__("error",JSON.stringify(data, null, 2))},
@@ -40,44 +127,33 @@ pub fn use_script(
         warn(data){__verge_log__("warn",JSON.stringify(data, null, 2))},
         table(data){__verge_log__("table",JSON.stringify(data, null, 2))},
       });"#,
-    ));
-
-    let config = use_lowercase(config.clone());
-    let config_str = serde_json::to_string(&config)?;
+        )?;
 
-    // 仅处理 name 参数中的特殊字符
-    let safe_name = escape_js_string_for_single_quote(&name);
+        // 仅处理 name 参数中的特殊字符
+        let safe_name = escape_js_string_for_single_quote(name);
 
-    let code = format!(
-        r#"try{{
+        let code = format!(
+            r#"try{{
         {script};
         JSON.stringify(main({config_str},'{safe_name}')||'')
       }} catch(err) {{
-        `__error_flag__ ${{err.toString()}}`
+        `{ERROR_FLAG}${{err.toString()}}`
       }}"#
-    );
+        );
 
-    if let Ok(result) = context.eval(Source::from_bytes(code.as_str())) {
-        if !result.is_string() {
-            anyhow::bail!("main function should return object");
-        }
-        let result = result.to_string(&mut context).unwrap();
-        let result = result.to_std_string().unwrap();
-
-        // 直接解析JSON结果,不做其他解析
-        let res: Result<Mapping, Error> = parse_json_safely(&result);
-
-        let mut out = outputs.lock().unwrap();
-        match res {
-            Ok(config) => Ok((use_lowercase(config), out.to_vec())),
-            Err(err) => {
-                out.push(("exception".into(), err.to_string()));
-                Ok((config, out.to_vec()))
-            }
+        // 中断和超出限制的错误无法在脚本内捕获
+        let result = ctx
+            .eval::<rquickjs::Value, _>(code)
+            .catch(&ctx)
+            .map_err(|err| anyhow!("script aborted: {err}"))?;
+        match result.as_string() {
+            Some(result) => Ok(result.to_string()?),
+            None => anyhow::bail!("main function should return object"),
         }
-    } else {
-        anyhow::bail!("main function should return object");
-    }
+    })?;
+
+    let out = outputs.lock().unwrap().to_vec();
+    Ok((result, out))
 }
 
 fn parse_json_safely(json_str: &str) -> Result<Mapping, Error> {
@@ -156,3 +232,44 @@ fn test_escape_unescape() {
     assert!(parsed_quoted.contains_key("key"));
     assert!(parsed_quoted.contains_key("nested"));
 }
+
+#[test]
+fn test_script_limits() {
+    let script = r#"
+    function main(config) {
+      while (true) {}
+      return config;
+    }
+  "#;
+
+    let config: Mapping = serde_yaml::from_str("rules: []").unwrap();
+    // 无限循环被指令总数限制或超时中断，不会返回修改后的配置
+    let (res_config, logs) = use_script(script.into(), config.clone(), "".to_string()).unwrap();
+    assert_eq!(res_config, use_lowercase(config.clone()));
+    assert!(logs.iter().any(|(level, _)| level == "exception"));
+
+    // 依次执行的循环共用同一个上限
+    let script = r#"
+    function main(config) {
+      for (let i = 0; i < 1000; i++) {
+        for (let j = 0; j < 10000000; j++) {}
+      }
+      return config;
+    }
+  "#;
+    let (_, logs) = use_script(script.into(), config.clone(), "".to_string()).unwrap();
+    assert!(logs.iter().any(|(level, _)| level == "exception"));
+
+    let script = r#"
+    function main(config) {
+      const chunks = [];
+      while (true) chunks.push("x".repeat(1024 * 1024));
+    }
+  "#;
+    let (res_config, logs) = use_script(script.into(), config.clone(), "".to_string()).unwrap();
+    assert_eq!(res_config, use_lowercase(config));
+    assert!(logs.iter().any(|(level, _)| level == "exception"));
+
+    assert!(check_script_syntax("function main(config) { return config; }").is_ok());
+    assert!(check_script_syntax("function main(config) { return config; ").is_err());
+}
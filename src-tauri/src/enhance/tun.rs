@@ -1,3 +1,4 @@
+use crate::{config::Config, core::bypass::RESERVED_RANGES};
 use serde_yaml::{Mapping, Value};
 
 macro_rules! revise {
@@ -64,8 +65,22 @@ pub async fn use_tun(mut config: Mapping, enable: bool) -> Mapping {
             }
         }
 
+        let (reserved, ipv6_mode) = {
+            let verge = Config::verge();
+            let verge = verge.latest();
+            (
+                verge.bypass_reserved_ranges.unwrap_or(true),
+                verge.tun_ipv6_mode.clone().unwrap_or("off".into()),
+            )
+        };
+        apply_ipv6_mode(&mut config, &mut dns_val, &mut tun_val, &ipv6_mode);
+
         // 当TUN启用时，将修改后的DNS配置写回
         revise!(config, "dns", dns_val);
+
+        if reserved {
+            exclude_routes(&mut tun_val, RESERVED_RANGES);
+        }
     } else {
         // TUN未启用时，仅恢复系统DNS，不修改配置文件中的DNS设置
         #[cfg(target_os = "macos")]
@@ -78,3 +93,98 @@ pub async fn use_tun(mut config: Mapping, enable: bool) -> Mapping {
 
     config
 }
+
+/// 处理 TUN 之外的 IPv6 流量
+///
+/// route: 开启 IPv6 并让 TUN 接管 ::/0，双栈网络下不会绕过 TUN 直连；
+/// disable: 关闭内核与 DNS 的 IPv6，物理网卡的 IPv6 由 ipv6_guard 关闭；
+/// off: 保持订阅的设置
+fn apply_ipv6_mode(config: &mut Mapping, dns: &mut Mapping, tun: &mut Mapping, mode: &str) {
+    match mode {
+        "route" => {
+            revise!(config, "ipv6", true);
+            revise!(dns, "ipv6", true);
+            if !tun.contains_key("inet6-address") {
+                revise!(tun, "inet6-address", vec!["fdfe:dcba:9876::1/126"]);
+            }
+            // 自定义了 route-address 时内核只接管列出的网段
+            let key = Value::from("route-address");
+            if let Some(routes) = tun.get_mut(&key).and_then(Value::as_sequence_mut) {
+                let all = Value::from("::/0");
+                if !routes.is_empty() && !routes.contains(&all) {
+                    routes.push(all);
+                }
+            }
+        }
+        "disable" => {
+            revise!(config, "ipv6", false);
+            revise!(dns, "ipv6", false);
+        }
+        _ => {}
+    }
+}
+
+/// 将网段追加到 route-exclude-address，保留用户已有的条目
+fn exclude_routes(tun: &mut Mapping, ranges: &[&str]) {
+    let key = Value::from("route-exclude-address");
+    let mut routes = tun
+        .get(&key)
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    for range in ranges {
+        let range = Value::from(*range);
+        if !routes.contains(&range) {
+            routes.push(range);
+        }
+    }
+    tun.insert(key, Value::Sequence(routes));
+}
+
+#[test]
+fn test_exclude_routes() {
+    let mut tun: Mapping =
+        serde_yaml::from_str("route-exclude-address: [10.0.0.0/8, 100.64.0.0/10]").unwrap();
+    exclude_routes(&mut tun, RESERVED_RANGES);
+    let routes = tun
+        .get("route-exclude-address")
+        .and_then(Value::as_sequence)
+        .unwrap()
+        .iter()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        routes,
+        vec![
+            "10.0.0.0/8",
+            "100.64.0.0/10",
+            "169.254.0.0/16",
+            "224.0.0.0/4",
+            "fe80::/10"
+        ]
+    );
+}
+
+#[test]
+fn test_apply_ipv6_mode() {
+    let mut config = Mapping::new();
+    let mut dns = Mapping::new();
+    let mut tun: Mapping = serde_yaml::from_str("route-address: [0.0.0.0/1, 128.0.0.0/1]").unwrap();
+    apply_ipv6_mode(&mut config, &mut dns, &mut tun, "route");
+    assert_eq!(config.get("ipv6"), Some(&Value::from(true)));
+    assert_eq!(dns.get("ipv6"), Some(&Value::from(true)));
+    assert!(tun.contains_key("inet6-address"));
+    let routes = tun
+        .get("route-address")
+        .and_then(Value::as_sequence)
+        .unwrap();
+    assert_eq!(routes.last(), Some(&Value::from("::/0")));
+
+    apply_ipv6_mode(&mut config, &mut dns, &mut tun, "disable");
+    assert_eq!(config.get("ipv6"), Some(&Value::from(false)));
+    assert_eq!(dns.get("ipv6"), Some(&Value::from(false)));
+
+    let mut config: Mapping = serde_yaml::from_str("ipv6: true").unwrap();
+    apply_ipv6_mode(&mut config, &mut dns, &mut tun, "off");
+    assert_eq!(config.get("ipv6"), Some(&Value::from(true)));
+}
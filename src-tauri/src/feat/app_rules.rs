@@ -0,0 +1,179 @@
+use crate::{
+    config::{Config, PrfItem},
+    core::{handle, CoreManager},
+    enhance::seq::SeqMap,
+    logging,
+    utils::logging::Type,
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
+
+/// 按进程匹配的规则类型
+const APP_RULE_KINDS: &[&str] = &["PROCESS-NAME", "PROCESS-PATH"];
+
+/// 当前订阅中按进程分流的规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppRule {
+    /// `PROCESS-NAME` 或 `PROCESS-PATH`
+    pub kind: String,
+    pub value: String,
+    /// 策略组名、`DIRECT` 或 `REJECT`
+    pub target: String,
+}
+
+impl AppRule {
+    fn parse(rule: &str) -> Option<Self> {
+        let mut parts = rule.split(',').map(str::trim);
+        let kind = parts.next()?.to_uppercase();
+        if !APP_RULE_KINDS.contains(&kind.as_str()) {
+            return None;
+        }
+        Some(Self {
+            kind,
+            value: parts.next()?.to_string(),
+            target: parts.next()?.to_string(),
+        })
+    }
+
+    fn to_rule(&self) -> String {
+        format!("{},{},{}", self.kind, self.value, self.target)
+    }
+
+    fn same_app(&self, other: &AppRule) -> bool {
+        self.kind == other.kind && self.value == other.value
+    }
+}
+
+/// 正在运行的进程，供前端选择
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningApp {
+    pub name: String,
+    pub path: Option<String>,
+}
+
+/// 当前订阅的规则增强文件中的进程规则
+pub fn list_app_rules() -> Result<Vec<AppRule>> {
+    let (_, seq) = current_rules()?;
+    Ok(seq
+        .prepend
+        .iter()
+        .filter_map(Value::as_str)
+        .filter_map(AppRule::parse)
+        .collect())
+}
+
+/// 添加或替换进程规则，放在最前面以优先于订阅自带的规则
+pub async fn add_app_rule(rule: AppRule) -> Result<()> {
+    let kind = rule.kind.to_uppercase();
+    if !APP_RULE_KINDS.contains(&kind.as_str()) {
+        bail!("unsupported rule type \"{}\"", rule.kind);
+    }
+    let rule = AppRule {
+        kind,
+        value: rule.value.trim().to_string(),
+        target: rule.target.trim().to_string(),
+    };
+    if rule.value.is_empty() || rule.target.is_empty() {
+        bail!("the process and the target should not be empty");
+    }
+    if rule.value.contains(',') || rule.target.contains(',') {
+        bail!("the process and the target should not contain commas");
+    }
+
+    update_rules(|seq| {
+        seq.prepend.retain(|item| {
+            item.as_str()
+                .and_then(AppRule::parse)
+                .is_none_or(|other| !other.same_app(&rule))
+        });
+        seq.prepend.insert(0, rule.to_rule().into());
+    })
+    .await
+}
+
+pub async fn remove_app_rule(kind: String, value: String) -> Result<()> {
+    let kind = kind.to_uppercase();
+    update_rules(|seq| {
+        seq.prepend.retain(|item| {
+            item.as_str()
+                .and_then(AppRule::parse)
+                .is_none_or(|rule| rule.kind != kind || rule.value != value)
+        });
+    })
+    .await
+}
+
+/// 正在运行的进程，按名称去重
+pub fn running_apps() -> Vec<RunningApp> {
+    let system = System::new_with_specifics(
+        RefreshKind::nothing()
+            .with_processes(ProcessRefreshKind::nothing().with_exe(UpdateKind::OnlyIfNotSet)),
+    );
+    let mut apps = BTreeMap::new();
+    for process in system.processes().values() {
+        let name = process.name().to_string_lossy().into_owned();
+        if name.is_empty() {
+            continue;
+        }
+        let path = process.exe().map(|exe| exe.to_string_lossy().into_owned());
+        apps.entry(name.to_lowercase())
+            .or_insert_with(|| RunningApp { name, path });
+    }
+    apps.into_values().collect()
+}
+
+/// 当前订阅的规则增强文件
+fn current_rules() -> Result<(PrfItem, SeqMap)> {
+    let item = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let uid = profiles
+            .current_rules()
+            .context("the current profile has no rules file")?;
+        profiles.get_item(&uid)?.clone()
+    };
+    let content = item.read_file()?;
+    let seq = serde_yaml::from_str::<Option<SeqMap>>(&content)?.unwrap_or_default();
+    Ok((item, seq))
+}
+
+/// 修改规则文件并热重载，配置校验失败时恢复原文件
+async fn update_rules(f: impl FnOnce(&mut SeqMap)) -> Result<()> {
+    let (item, mut seq) = current_rules()?;
+    let original = item.read_file()?;
+    f(&mut seq);
+    item.save_file(serde_yaml::to_string(&seq)?)?;
+
+    match CoreManager::global().update_config().await {
+        Ok((true, _)) => {
+            handle::Handle::refresh_clash();
+            Ok(())
+        }
+        Ok((false, msg)) => {
+            item.save_file(original)?;
+            logging!(warn, Type::Config, true, "App rule rejected: {}", msg);
+            bail!(msg)
+        }
+        Err(err) => {
+            item.save_file(original)?;
+            Err(err)
+        }
+    }
+}
+
+#[test]
+fn test_parse_app_rule() {
+    assert_eq!(
+        AppRule::parse("PROCESS-NAME,Telegram.exe,Proxy"),
+        Some(AppRule {
+            kind: "PROCESS-NAME".into(),
+            value: "Telegram.exe".into(),
+            target: "Proxy".into(),
+        })
+    );
+    assert_eq!(AppRule::parse("DOMAIN,example.com,Proxy"), None);
+    assert_eq!(AppRule::parse("PROCESS-NAME,Telegram"), None);
+}
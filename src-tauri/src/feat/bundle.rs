@@ -0,0 +1,226 @@
+use crate::{
+    config::{Config, IProfiles, PrfItem, PrfOption},
+    utils::help,
+};
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_PREFIX: &str = "data:application/vnd.koala-clash.bundle+json;base64,";
+
+/// A profile with its enhance chain, portable between instances
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ProfileBundle {
+    version: u32,
+    /// `remote`, `local` or `composite`, older bundles only tell remote and local apart by the url
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    itype: Option<String>,
+    name: Option<String>,
+    desc: Option<String>,
+    /// removed when exporting without secrets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    option: Option<PrfOption>,
+    content: String,
+    /// chain kind (merge/script/rules/proxies/groups) -> file content
+    chain: BTreeMap<String, String>,
+    /// the extra merge templates in application order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    merges: Vec<BundledMerge>,
+    /// the profiles a composite profile is built from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<ProfileBundle>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct BundledMerge {
+    name: Option<String>,
+    content: String,
+}
+
+fn chain_uids(option: Option<&PrfOption>) -> Vec<(&'static str, Option<String>)> {
+    vec![
+        ("merge", option.and_then(|o| o.merge.clone())),
+        ("script", option.and_then(|o| o.script.clone())),
+        ("rules", option.and_then(|o| o.rules.clone())),
+        ("proxies", option.and_then(|o| o.proxies.clone())),
+        ("groups", option.and_then(|o| o.groups.clone())),
+    ]
+}
+
+/// Export a profile and its chain as a data URI
+/// `strip_secrets` drops the subscription url and custom headers, which usually carry the access token
+pub fn export_profile_bundle(uid: &String, strip_secrets: bool) -> Result<String> {
+    let profiles = Config::profiles();
+    let bundle = bundle_item(&profiles.latest(), uid, strip_secrets)?;
+
+    let json = serde_json::to_vec(&bundle)?;
+    Ok(format!("{BUNDLE_PREFIX}{}", STANDARD.encode(json)))
+}
+
+fn bundle_item(profiles: &IProfiles, uid: &String, strip_secrets: bool) -> Result<ProfileBundle> {
+    let item = profiles.get_item(uid)?;
+    let option = item.option.as_ref();
+
+    let mut chain = BTreeMap::new();
+    for (kind, chain_uid) in chain_uids(option) {
+        let Some(chain_uid) = chain_uid else {
+            continue;
+        };
+        if let Ok(content) = profiles.get_item(&chain_uid).and_then(|i| i.read_file()) {
+            chain.insert(kind.to_string(), content);
+        }
+    }
+
+    let merges = option
+        .and_then(|o| o.merges.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|merge_uid| profiles.get_item(merge_uid).ok())
+        .map(|merge| {
+            Ok(BundledMerge {
+                name: merge.name.clone(),
+                content: merge.read_file()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let sources = option
+        .and_then(|o| o.sources.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|source_uid| bundle_item(profiles, source_uid, strip_secrets))
+        .collect::<Result<Vec<_>>>()?;
+
+    // the chain uids only make sense on this instance
+    let option = item.option.clone().map(|mut o| {
+        o.merge = None;
+        o.merges = None;
+        o.script = None;
+        o.rules = None;
+        o.proxies = None;
+        o.groups = None;
+        o.sources = None;
+        o.source_path = None;
+        if strip_secrets {
+            o.headers = None;
+            o.download_proxy = None;
+            o.mirrors = None;
+            o.auth = None;
+        }
+        o
+    });
+
+    Ok(ProfileBundle {
+        version: BUNDLE_VERSION,
+        itype: item.itype.clone(),
+        name: item.name.clone(),
+        desc: item.desc.clone(),
+        url: if strip_secrets {
+            None
+        } else {
+            item.url.clone()
+        },
+        option,
+        content: item.read_file()?,
+        chain,
+        merges,
+        sources,
+    })
+}
+
+/// Create a profile from a bundle produced by `export_profile_bundle`
+/// Bundles without url become local profiles, the sources of a composite profile are added too
+pub fn import_profile_bundle(data: &str) -> Result<PrfItem> {
+    let encoded = data
+        .trim()
+        .strip_prefix(BUNDLE_PREFIX)
+        .unwrap_or(data.trim());
+    let json = STANDARD
+        .decode(encoded)
+        .context("the bundle is not valid base64")?;
+    let bundle: ProfileBundle =
+        serde_json::from_slice(&json).context("the bundle is not a valid profile bundle")?;
+    if bundle.version > BUNDLE_VERSION {
+        bail!("unsupported bundle version {}", bundle.version);
+    }
+
+    unbundle_item(bundle)
+}
+
+fn unbundle_item(bundle: ProfileBundle) -> Result<PrfItem> {
+    let itype = match (bundle.itype.as_deref(), &bundle.url) {
+        (Some("composite"), _) => "composite",
+        (_, Some(_)) => "remote",
+        _ => "local",
+    };
+    let mut option = bundle.option.unwrap_or_default();
+
+    // 每个订阅都有自己的一套链式配置
+    let chain = [
+        ("merge", PrfItem::from_merge(None)?),
+        ("script", PrfItem::from_script(None)?),
+        ("rules", PrfItem::from_rules()?),
+        ("proxies", PrfItem::from_proxies()?),
+        ("groups", PrfItem::from_groups()?),
+    ];
+    for (kind, mut chain_item) in chain {
+        if let Some(content) = bundle.chain.get(kind) {
+            chain_item.file_data = Some(content.clone());
+        }
+        let chain_uid = chain_item.uid.clone();
+        Config::profiles().data().append_item(chain_item)?;
+        match kind {
+            "merge" => option.merge = chain_uid,
+            "script" => option.script = chain_uid,
+            "rules" => option.rules = chain_uid,
+            "proxies" => option.proxies = chain_uid,
+            _ => option.groups = chain_uid,
+        }
+    }
+
+    let mut merges = vec![];
+    for merge in bundle.merges {
+        let mut merge_item = PrfItem::from_merge(None)?;
+        merge_item.name = merge.name;
+        merge_item.file_data = Some(merge.content);
+        merges.extend(merge_item.uid.clone());
+        Config::profiles().data().append_item(merge_item)?;
+    }
+    option.merges = (!merges.is_empty()).then_some(merges);
+
+    let mut sources = vec![];
+    for source in bundle.sources {
+        let source_item = unbundle_item(source)?;
+        sources.extend(source_item.uid.clone());
+        let profiles = Config::profiles();
+        let mut profiles = profiles.data();
+        // append_item activates new profiles, only the imported profile itself should be
+        let current = profiles.current.clone();
+        profiles.append_item(source_item)?;
+        profiles.current = current;
+        profiles.save_file()?;
+    }
+    option.sources = (!sources.is_empty()).then_some(sources);
+
+    let prefix = match itype {
+        "remote" => "R",
+        "composite" => "C",
+        _ => "L",
+    };
+    let uid = help::get_uid(prefix);
+    Ok(PrfItem {
+        file: Some(format!("{uid}.yaml")),
+        uid: Some(uid),
+        itype: Some(itype.into()),
+        name: Some(bundle.name.unwrap_or("Imported".into())),
+        desc: Some(bundle.desc.unwrap_or_default()),
+        url: bundle.url.filter(|_| itype == "remote"),
+        option: Some(option),
+        updated: Some(chrono::Local::now().timestamp() as usize),
+        file_data: Some(bundle.content),
+        ..PrfItem::default()
+    })
+}
@@ -1,10 +1,10 @@
 use crate::{
-    config::Config,
-    core::{handle, tray, CoreManager},
+    config::{Config, ConfigType, IClashTemp},
+    core::{handle, tray, CoreManager, RunningMode},
     logging_error,
     module::mihomo::MihomoManager,
     process::AsyncHandler,
-    utils::{logging::Type, resolve},
+    utils::{dirs, logging::Type, resolve},
 };
 use serde_yaml::{Mapping, Value};
 use tauri::Manager;
@@ -89,6 +89,76 @@ pub fn change_clash_mode(mode: String) {
     });
 }
 
+/// Log levels accepted by mihomo
+pub const CORE_LOG_LEVELS: [&str; 5] = ["silent", "error", "warning", "info", "debug"];
+
+/// Change the log level of the running core without reloading the config
+/// The level is saved as well so the next generated config keeps it
+pub async fn change_core_log_level(level: String) -> anyhow::Result<()> {
+    if !CORE_LOG_LEVELS.contains(&level.as_str()) {
+        anyhow::bail!("invalid log level: {level}");
+    }
+
+    if CoreManager::global().get_running_mode().await != RunningMode::NotRunning {
+        MihomoManager::global()
+            .patch_configs(serde_json::json!({ "log-level": level }))
+            .await
+            .map_err(|err| anyhow::anyhow!("failed to set the core log level: {err}"))?;
+    }
+    log::info!(target: "app", "core log level changed to {level}");
+
+    let mut mapping = Mapping::new();
+    mapping.insert(Value::from("log-level"), level.into());
+    Config::clash().data().patch_config(mapping.clone());
+    Config::clash().data().save_config()?;
+    Config::runtime().latest().patch_config(mapping);
+
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// Generate a new controller secret and apply it to the running core
+/// The new secret only goes into the generated config, the internal clients keep the
+/// old one until the core has loaded it, and it is dropped on failure
+pub async fn rotate_clash_secret() -> anyhow::Result<()> {
+    if Config::verge()
+        .latest()
+        .enable_stable_secret
+        .unwrap_or(true)
+    {
+        anyhow::bail!("the controller secret is kept stable");
+    }
+
+    IClashTemp::rotate_secret()?;
+    let res = async {
+        Config::generate().await?;
+        let run_path = Config::generate_file(ConfigType::Run)?;
+        if CoreManager::global().get_running_mode().await != RunningMode::NotRunning {
+            MihomoManager::global()
+                .put_configs_force(dirs::path_to_str(&run_path)?)
+                .await
+                .map_err(|err| anyhow::anyhow!("failed to apply the new secret: {err}"))?;
+        }
+        anyhow::Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(()) => {
+            IClashTemp::commit_secret();
+            Config::runtime().apply();
+            log::info!(target: "app", "controller secret rotated");
+            handle::Handle::refresh_clash();
+            Ok(())
+        }
+        Err(err) => {
+            IClashTemp::discard_secret();
+            Config::runtime().discard();
+            Err(err)
+        }
+    }
+}
+
 /// Test connection delay to a URL
 pub async fn test_delay(url: String) -> anyhow::Result<u32> {
     use crate::utils::network::{NetworkManager, ProxyType};
@@ -108,7 +178,7 @@ pub async fn test_delay(url: String) -> anyhow::Result<u32> {
     let start = Instant::now();
 
     let response = NetworkManager::global()
-        .get_with_interrupt(&url, proxy_type, Some(10), user_agent, false, false)
+        .get_with_interrupt(&url, proxy_type, Some(10), user_agent, false, false, None)
         .await;
 
     match response {
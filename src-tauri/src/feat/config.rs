@@ -1,22 +1,49 @@
 use crate::{
     config::{Config, IVerge},
-    core::{handle, hotkey, sysopt, tray, CoreManager},
-    logging_error,
+    core::{
+        bypass, conflicts, dns_check, firewall, handle, hotkey, ipv6_guard, launch, notifications,
+        ports, proxy_networks, sysopt,
+        tray::{self, proxy_menu, speed_rate},
+        CoreManager,
+    },
+    enhance, logging_error,
     module::lightweight,
-    utils::logging::Type,
+    process::AsyncHandler,
+    utils::{logging::Type, network::NetworkManager},
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde_yaml::Mapping;
 
 /// Patch Clash configuration
 pub async fn patch_clash(patch: Mapping) -> Result<()> {
     Config::clash().draft().patch_config(patch.clone());
+    let listener_patch = is_listener_patch(&patch);
+    // 局域网连接的防火墙规则随 allow-lan 与端口更新
+    let firewall_patch = listener_patch || patch.contains_key("allow-lan");
+    // verge 的端口覆盖 clash.yaml 的端口，两边一起修改
+    if listener_patch {
+        Config::verge()
+            .draft()
+            .patch_config(ports::verge_patch(&patch));
+    }
 
-    let res = {
-        // 激活订阅
+    // 异步块中的 ? 只结束这个块，失败时丢弃草稿
+    let res: Result<()> = async {
+        if listener_patch || patch.get("external-controller").is_some() {
+            ports::validate()?;
+        }
+        // 控制器和 secret 也能通过接口重新加载，不重启内核，TUN 网卡保持不变
         if patch.get("secret").is_some() || patch.get("external-controller").is_some() {
-            Config::generate().await?;
-            CoreManager::global().restart_core().await?;
+            let (valid, msg) = CoreManager::global().update_config().await?;
+            if !valid {
+                bail!(msg);
+            }
+        } else if listener_patch {
+            // 只移动端口变化的监听，不重新加载整个配置
+            CoreManager::global().update_ports().await?;
+            if patch.contains_key("mixed-port") {
+                sysopt::Sysopt::global().update_sysproxy().await?;
+            }
         } else {
             if patch.get("mode").is_some() {
                 logging_error!(Type::Tray, true, tray::Tray::global().update_menu());
@@ -26,21 +53,41 @@ pub async fn patch_clash(patch: Mapping) -> Result<()> {
             CoreManager::global().update_config().await?;
         }
         handle::Handle::refresh_clash();
-        <Result<()>>::Ok(())
-    };
+        Ok(())
+    }
+    .await;
     match res {
         Ok(()) => {
             Config::clash().apply();
             Config::clash().data().save_config()?;
+            if listener_patch {
+                Config::verge().apply();
+                Config::verge().data().save_file()?;
+            }
+            if firewall_patch {
+                AsyncHandler::spawn(firewall::update);
+            }
             Ok(())
         }
         Err(err) => {
             Config::clash().discard();
+            if listener_patch {
+                Config::verge().discard();
+            }
             Err(err)
         }
     }
 }
 
+/// Whether the patch only changes proxy listener ports
+fn is_listener_patch(patch: &Mapping) -> bool {
+    !patch.is_empty()
+        && patch.keys().all(|key| {
+            key.as_str()
+                .is_some_and(|key| ports::LISTENER_KEYS.contains(&key))
+        })
+}
+
 // Define update flags as bitflags for better performance
 #[derive(Clone, Copy)]
 enum UpdateFlags {
@@ -56,24 +103,69 @@ enum UpdateFlags {
     SystrayTooltip = 1 << 8,
     SystrayClickBehavior = 1 << 9,
     LighteWeight = 1 << 10,
+    SecondaryCore = 1 << 11,
+    Ports = 1 << 12,
 }
 
 /// Patch Verge configuration
 pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
+    if patch.core_extra_args.is_some() || patch.core_extra_env.is_some() {
+        launch::validate(
+            patch.core_extra_args.as_deref().unwrap_or_default(),
+            patch.core_extra_env.as_deref().unwrap_or_default(),
+        )?;
+    }
+    if let Some(bypass) = &patch.system_proxy_bypass {
+        if let Some(err) = bypass::validate(bypass).into_iter().next() {
+            bail!(err);
+        }
+    }
+    if let Some(items) = &patch.tray_custom_items {
+        tray::custom_items::validate(items)?;
+    }
+    for spec in [&patch.notice_quiet_hours, &patch.profile_notice_quiet_hours]
+        .into_iter()
+        .flatten()
+    {
+        if !spec.trim().is_empty() && notifications::parse_quiet_hours(spec).is_none() {
+            bail!("\"{spec}\" is not a time range like 22:00-07:00");
+        }
+    }
+    if patch
+        .proxy_auth_username
+        .as_deref()
+        .is_some_and(|username| username.contains(':'))
+    {
+        bail!("the proxy username can't contain \":\"");
+    }
+    for entry in patch
+        .lan_allowed_ips
+        .iter()
+        .chain(patch.lan_disallowed_ips.iter())
+        .flatten()
+    {
+        enhance::lan::parse_cidr(entry).map_err(anyhow::Error::msg)?;
+    }
     Config::verge().draft().patch_config(patch.clone());
 
+    // 修改的端口同步到 clash.yaml
+    let mut port_patch = Mapping::new();
+    ports::use_verge_ports(&mut port_patch, &patch);
+
     let tun_mode = patch.enable_tun_mode;
     let auto_launch = patch.enable_auto_launch;
     let system_proxy = patch.enable_system_proxy;
     let pac = patch.proxy_auto_config;
     let pac_content = patch.pac_file_content;
+    let pac_from_rules = patch.pac_from_rules;
     let proxy_bypass = patch.system_proxy_bypass;
+    let bypass_presets = patch.system_proxy_bypass_presets.clone();
+    let proxy_services = patch.system_proxy_services.clone();
+    let winhttp_proxy = patch.enable_winhttp_proxy;
+    let reserved_ranges = patch.bypass_reserved_ranges;
     let language = patch.language;
     let mixed_port = patch.verge_mixed_port;
-    #[cfg(target_os = "macos")]
     let tray_icon = patch.tray_icon;
-    #[cfg(not(target_os = "macos"))]
-    let tray_icon: Option<String> = None;
     let common_tray_icon = patch.common_tray_icon;
     let sysproxy_tray_icon = patch.sysproxy_tray_icon;
     let tun_tray_icon = patch.tun_tray_icon;
@@ -95,32 +187,63 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
     let tray_event = patch.tray_event;
     let home_cards = patch.home_cards.clone();
     let enable_auto_light_weight = patch.enable_auto_light_weight_mode;
-    let res: std::result::Result<(), anyhow::Error> = {
+    let secondary_core = patch.enable_secondary_core.is_some()
+        || patch.secondary_mixed_port.is_some()
+        || patch.secondary_interface_name.is_some();
+    let res: Result<()> = async {
         // Initialize with no flags set
         let mut update_flags: i32 = UpdateFlags::None as i32;
 
+        if tun_mode == Some(true) {
+            conflicts::warn("before enabling TUN").await;
+        }
         if tun_mode.is_some() {
             update_flags |= UpdateFlags::ClashConfig as i32;
             update_flags |= UpdateFlags::SystrayMenu as i32;
             update_flags |= UpdateFlags::SystrayTooltip as i32;
             update_flags |= UpdateFlags::SystrayIcon as i32;
         }
+        if patch.tun_ipv6_mode.is_some() {
+            update_flags |= UpdateFlags::ClashConfig as i32;
+        }
+        if patch.lan_allowed_ips.is_some() || patch.lan_disallowed_ips.is_some() {
+            update_flags |= UpdateFlags::ClashConfig as i32;
+        }
+        // 系统代理能保存认证信息的平台一并写入
+        if patch.proxy_auth_username.is_some() || patch.proxy_auth_password.is_some() {
+            update_flags |= UpdateFlags::ClashConfig as i32;
+            update_flags |= UpdateFlags::SysProxy as i32;
+            NetworkManager::global().reset_clients();
+        }
         if enable_global_hotkey.is_some() || home_cards.is_some() {
             update_flags |= UpdateFlags::VergeConfig as i32;
         }
+        // 端口只需移动对应的监听，不重启内核，TUN 网卡保持不变
         #[cfg(not(target_os = "windows"))]
         if redir_enabled.is_some() || redir_port.is_some() {
-            update_flags |= UpdateFlags::RestartCore as i32;
+            update_flags |= UpdateFlags::Ports as i32;
         }
         #[cfg(target_os = "linux")]
         if tproxy_enabled.is_some() || tproxy_port.is_some() {
-            update_flags |= UpdateFlags::RestartCore as i32;
+            update_flags |= UpdateFlags::Ports as i32;
         }
         if socks_enabled.is_some()
             || http_enabled.is_some()
             || socks_port.is_some()
             || http_port.is_some()
-            || mixed_port.is_some()
+        {
+            update_flags |= UpdateFlags::Ports as i32;
+        }
+        // 系统代理紧随其后指向新端口
+        if mixed_port.is_some() {
+            update_flags |= UpdateFlags::Ports as i32;
+            update_flags |= UpdateFlags::SysProxy as i32;
+        }
+        if patch.core_extra_args.is_some()
+            || patch.core_extra_env.is_some()
+            || patch.enable_stable_secret.is_some()
+            || patch.enable_controller_ipc.is_some()
+            || patch.disable_tcp_controller.is_some()
         {
             update_flags |= UpdateFlags::RestartCore as i32;
         }
@@ -135,18 +258,34 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             update_flags |= UpdateFlags::SystrayIcon as i32;
         }
 
-        if proxy_bypass.is_some() || pac_content.is_some() || pac.is_some() {
+        // 同时影响系统代理的绕过列表与 TUN 的排除路由
+        if reserved_ranges.is_some() {
+            update_flags |= UpdateFlags::SysProxy as i32;
+            update_flags |= UpdateFlags::ClashConfig as i32;
+        }
+        if proxy_bypass.is_some()
+            || bypass_presets.is_some()
+            || proxy_services.is_some()
+            || winhttp_proxy.is_some()
+            || pac_content.is_some()
+            || pac.is_some()
+            || pac_from_rules.is_some()
+        {
             update_flags |= UpdateFlags::SysProxy as i32;
         }
 
+        // 托盘菜单与提示随界面语言立即切换
         if language.is_some() {
             update_flags |= UpdateFlags::SystrayMenu as i32;
+            update_flags |= UpdateFlags::SystrayTooltip as i32;
         }
         if common_tray_icon.is_some()
             || sysproxy_tray_icon.is_some()
             || tun_tray_icon.is_some()
+            || patch.global_tray_icon.is_some()
+            || patch.direct_tray_icon.is_some()
             || tray_icon.is_some()
-            || enable_tray_speed.is_some()
+            || patch.tray_icon_render.is_some()
             || enable_tray_icon.is_some()
         {
             update_flags |= UpdateFlags::SystrayIcon as i32;
@@ -156,6 +295,9 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             update_flags |= UpdateFlags::Hotkey as i32;
             update_flags |= UpdateFlags::SystrayMenu as i32;
         }
+        if patch.tray_custom_items.is_some() {
+            update_flags |= UpdateFlags::SystrayMenu as i32;
+        }
 
         if tray_event.is_some() {
             update_flags |= UpdateFlags::SystrayClickBehavior as i32;
@@ -165,14 +307,39 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             update_flags |= UpdateFlags::LighteWeight as i32;
         }
 
+        if secondary_core {
+            update_flags |= UpdateFlags::SecondaryCore as i32;
+        }
+
+        if (update_flags & (UpdateFlags::Ports as i32)) != 0 {
+            ports::validate()?;
+        }
+
         // Process updates based on flags
         if (update_flags & (UpdateFlags::RestartCore as i32)) != 0 {
             Config::generate().await?;
             CoreManager::global().restart_core().await?;
         }
-        if (update_flags & (UpdateFlags::ClashConfig as i32)) != 0 {
+        // 重启内核时第二个实例已随之重启
+        if (update_flags & (UpdateFlags::SecondaryCore as i32)) != 0
+            && (update_flags & (UpdateFlags::RestartCore as i32)) == 0
+        {
+            CoreManager::global().start_secondary_core().await?;
+        }
+        // 重启内核时已使用新生成的配置
+        if (update_flags & (UpdateFlags::ClashConfig as i32)) != 0
+            && (update_flags & (UpdateFlags::RestartCore as i32)) == 0
+        {
             CoreManager::global().update_config().await?;
             handle::Handle::refresh_clash();
+        } else if (update_flags & (UpdateFlags::Ports as i32)) != 0
+            && (update_flags & (UpdateFlags::RestartCore as i32)) == 0
+        {
+            CoreManager::global().update_ports().await?;
+            handle::Handle::refresh_clash();
+        }
+        if (update_flags & (UpdateFlags::Ports as i32)) != 0 {
+            AsyncHandler::spawn(firewall::update);
         }
         if (update_flags & (UpdateFlags::VergeConfig as i32)) != 0 {
             Config::verge().draft().enable_global_hotkey = enable_global_hotkey;
@@ -207,14 +374,41 @@ pub async fn patch_verge(patch: IVerge, not_save_file: bool) -> Result<()> {
             }
         }
 
-        <Result<()>>::Ok(())
-    };
+        // 确认 DNS 确实被 TUN 接管
+        if tun_mode == Some(true) {
+            AsyncHandler::spawn(dns_check::check_after_tun_enabled);
+        }
+        // 物理网卡的 IPv6 随 TUN 开关
+        if tun_mode.is_some() || patch.tun_ipv6_mode.is_some() {
+            AsyncHandler::spawn(ipv6_guard::update);
+        }
+        // 托盘网速随开关启动或停止读取
+        if enable_tray_speed.is_some() {
+            speed_rate::update();
+        }
+        // 托盘中的代理组随开关显示或移除
+        if patch.enable_tray_proxies.is_some() {
+            proxy_menu::update();
+        }
+        // 只在设置的网络中设置系统代理
+        if patch.sysproxy_networks.is_some() || system_proxy == Some(true) {
+            AsyncHandler::spawn(proxy_networks::update);
+        }
+
+        Ok(())
+    }
+    .await;
     match res {
         Ok(()) => {
             Config::verge().apply();
             if !not_save_file {
                 Config::verge().data().save_file()?;
             }
+            if !port_patch.is_empty() {
+                Config::clash().data().patch_config(port_patch);
+                Config::clash().data().save_config()?;
+                handle::Handle::refresh_clash();
+            }
 
             Ok(())
         }
@@ -0,0 +1,358 @@
+use crate::{
+    config::{Config, IVerge},
+    core::{handle, integrity, CoreManager},
+    logging,
+    utils::{
+        logging::Type,
+        metered,
+        network::{NetworkManager, ProxyType},
+    },
+};
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+const RELEASES_API: &str = "https://api.github.com/repos/MetaCubeX/mihomo/releases";
+
+/// 下载内核的超时时间，单位秒
+const DOWNLOAD_TIMEOUT: u64 = 300;
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    published_at: Option<String>,
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+    /// `sha256:<hex>`, published by github for each asset
+    digest: Option<String>,
+}
+
+/// Latest release of a core channel for this platform
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreRelease {
+    pub core: String,
+    pub version: String,
+    pub asset: String,
+    pub published_at: Option<String>,
+    /// a previous binary is kept and can be restored
+    pub can_rollback: bool,
+}
+
+/// Check the release of the channel behind `clash_core`
+pub async fn check_core_update(clash_core: &str) -> Result<CoreRelease> {
+    let (_, release) = fetch_release(clash_core).await?;
+    Ok(release)
+}
+
+/// Download the latest release of `clash_core`, verify it and swap it in while the core is stopped
+/// Updates go into the cores dir of the app, which is writable without elevation and takes
+/// precedence over the bundled core. The replaced binary is kept next to it for `rollback_core`
+pub async fn update_core(clash_core: &str) -> Result<CoreRelease> {
+    if metered::should_defer_updates().await {
+        bail!("core updates are paused on a metered network");
+    }
+    let (asset, release) = fetch_release(clash_core).await?;
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .ok_or_else(|| anyhow!("no checksum published for {}", asset.name))?
+        .to_lowercase();
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Downloading core {} {}",
+        clash_core,
+        release.version
+    );
+    let archive = download(&asset.browser_download_url).await?;
+    let actual = hex::encode(Sha256::digest(&archive));
+    if actual != expected {
+        bail!(
+            "checksum mismatch for {}: expected {expected}, got {actual}",
+            asset.name
+        );
+    }
+    let binary = extract_binary(&asset.name, &archive)?;
+
+    let bin_path = CoreManager::installed_core_path(clash_core)?;
+    if let Some(dir) = bin_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // 首次更新时保留内置内核的副本，以便回滚
+    let bundled_path = CoreManager::bundled_core_path(clash_core)?;
+    if !bin_path.exists() && bundled_path.is_file() {
+        fs::copy(&bundled_path, with_suffix(&bin_path, "bak"))?;
+    }
+
+    let new_path = with_suffix(&bin_path, "new");
+    let active = Config::verge().latest().get_valid_clash_core() == clash_core;
+    let res: Result<()> = async {
+        fs::write(&new_path, binary)?;
+        set_executable(&new_path)?;
+        swap_binary(clash_core, &bin_path, &new_path, active).await
+    }
+    .await;
+    if res.is_err() {
+        let _ = fs::remove_file(&new_path);
+    }
+    res?;
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "Core {} updated to {}",
+        clash_core,
+        release.version
+    );
+    handle::Handle::refresh_clash();
+    Ok(CoreRelease {
+        can_rollback: true,
+        ..release
+    })
+}
+
+/// Restore the binary replaced by the last update of `clash_core`
+pub async fn rollback_core(clash_core: &str) -> Result<()> {
+    let bin_path = CoreManager::installed_core_path(clash_core)?;
+    let backup_path = with_suffix(&bin_path, "bak");
+    if !backup_path.is_file() {
+        bail!("no previous binary of {clash_core} to restore");
+    }
+
+    let active = Config::verge().latest().get_valid_clash_core() == clash_core;
+    swap_binary(clash_core, &bin_path, &backup_path, active).await?;
+    logging!(info, Type::Core, true, "Core {} rolled back", clash_core);
+    handle::Handle::refresh_clash();
+    Ok(())
+}
+
+/// Replace `bin_path` with `new_path`, the old binary becomes the backup
+/// When the new binary fails to start, the old one is put back
+/// The hash of whichever binary ends up in place is recorded for the launch check
+async fn swap_binary(
+    clash_core: &str,
+    bin_path: &Path,
+    new_path: &Path,
+    active: bool,
+) -> Result<()> {
+    if active {
+        CoreManager::global().stop_core().await?;
+    }
+
+    let backup_path = with_suffix(bin_path, "bak");
+    let old_path = with_suffix(bin_path, "old");
+    if bin_path.exists() {
+        fs::rename(bin_path, &old_path)?;
+    }
+    if let Err(err) = fs::rename(new_path, bin_path) {
+        let _ = fs::rename(&old_path, bin_path);
+        if active {
+            CoreManager::global().start_core().await?;
+        }
+        return Err(err.into());
+    }
+    integrity::record(clash_core)?;
+
+    if active {
+        if let Err(err) = CoreManager::global().start_core().await {
+            logging!(
+                error,
+                Type::Core,
+                true,
+                "New core failed to start, restoring the previous binary: {}",
+                err
+            );
+            fs::rename(bin_path, new_path)?;
+            fs::rename(&old_path, bin_path)?;
+            integrity::record(clash_core)?;
+            CoreManager::global().start_core().await?;
+            return Err(err);
+        }
+    }
+
+    if old_path.exists() {
+        fs::rename(&old_path, &backup_path)?;
+    }
+    Ok(())
+}
+
+async fn fetch_release(clash_core: &str) -> Result<(GithubAsset, CoreRelease)> {
+    if !IVerge::VALID_CLASH_CORES.contains(&clash_core) {
+        bail!("invalid clash core: {clash_core}");
+    }
+    let url = if clash_core.ends_with("-alpha") {
+        format!("{RELEASES_API}/tags/Prerelease-Alpha")
+    } else {
+        format!("{RELEASES_API}/latest")
+    };
+
+    let resp = get(&url, Some(30)).await?;
+    if !resp.status().is_success() {
+        bail!("failed to fetch the release: {}", resp.status());
+    }
+    let release: GithubRelease = resp.json().await?;
+
+    let (os, arch) = platform()?;
+    let (asset, version) = select_asset(&release.assets, os, arch, &release.tag_name)
+        .ok_or_else(|| anyhow!("no {os}-{arch} binary in release {}", release.tag_name))?;
+
+    let backup_path = with_suffix(&CoreManager::installed_core_path(clash_core)?, "bak");
+    Ok((
+        asset.clone(),
+        CoreRelease {
+            core: clash_core.to_string(),
+            version,
+            asset: asset.name.clone(),
+            published_at: release.published_at,
+            can_rollback: backup_path.is_file(),
+        },
+    ))
+}
+
+/// Try through the local core first, then directly
+async fn get(url: &str, timeout: Option<u64>) -> Result<reqwest::Response> {
+    let mut last_err = None;
+    for proxy_type in [ProxyType::Localhost, ProxyType::None] {
+        match NetworkManager::global()
+            .get_with_interrupt(url, proxy_type, timeout, None, false, false, None)
+            .await
+        {
+            Ok(resp) => return Ok(resp),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow!("failed to fetch {url}")))
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let resp = get(url, Some(DOWNLOAD_TIMEOUT)).await?;
+    if !resp.status().is_success() {
+        bail!("failed to download the core: {}", resp.status());
+    }
+    Ok(resp.bytes().await?.to_vec())
+}
+
+/// mihomo names its platforms after go
+fn platform() -> Result<(&'static str, &'static str)> {
+    let os = match std::env::consts::OS {
+        "windows" => "windows",
+        "macos" => "darwin",
+        "linux" => "linux",
+        os => bail!("unsupported os: {os}"),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        arch => bail!("unsupported arch: {arch}"),
+    };
+    Ok((os, arch))
+}
+
+/// Pick the default build for the platform, skipping the `compatible` and cpu level variants
+/// e.g. `mihomo-linux-amd64-v1.19.10.gz` or `mihomo-windows-arm64-alpha-1a2b3c4.zip`
+fn select_asset<'a>(
+    assets: &'a [GithubAsset],
+    os: &str,
+    arch: &str,
+    tag: &str,
+) -> Option<(&'a GithubAsset, String)> {
+    let prefix = format!("mihomo-{os}-{arch}-");
+    let ext = if os == "windows" { ".zip" } else { ".gz" };
+
+    assets.iter().find_map(|asset| {
+        let version = asset.name.strip_prefix(&prefix)?.strip_suffix(ext)?;
+        let matched = version == tag
+            || version
+                .strip_prefix("alpha-")
+                .is_some_and(|hash| !hash.is_empty() && !hash.contains('-'));
+        matched.then(|| (asset, version.to_string()))
+    })
+}
+
+fn extract_binary(name: &str, archive: &[u8]) -> Result<Vec<u8>> {
+    let mut binary = Vec::new();
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive))?;
+        let index = (0..zip.len())
+            .find(|&i| {
+                zip.by_index(i)
+                    .is_ok_and(|file| file.is_file() && file.name().ends_with(".exe"))
+            })
+            .ok_or_else(|| anyhow!("no executable in {name}"))?;
+        zip.by_index(index)?.read_to_end(&mut binary)?;
+    } else {
+        GzDecoder::new(archive)
+            .read_to_end(&mut binary)
+            .with_context(|| format!("failed to decompress {name}"))?;
+    }
+
+    if binary.is_empty() {
+        bail!("empty binary in {name}");
+    }
+    Ok(binary)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[test]
+fn test_select_asset() {
+    let asset = |name: &str| GithubAsset {
+        name: name.into(),
+        browser_download_url: String::new(),
+        digest: None,
+    };
+    let assets = vec![
+        asset("mihomo-linux-amd64-compatible-v1.19.10.gz"),
+        asset("mihomo-linux-amd64-v3-v1.19.10.gz"),
+        asset("mihomo-linux-amd64-v1.19.10.gz"),
+        asset("mihomo-windows-amd64-v1.19.10.zip"),
+    ];
+    let (found, version) = select_asset(&assets, "linux", "amd64", "v1.19.10").unwrap();
+    assert_eq!(found.name, "mihomo-linux-amd64-v1.19.10.gz");
+    assert_eq!(version, "v1.19.10");
+    assert!(select_asset(&assets, "darwin", "arm64", "v1.19.10").is_none());
+
+    let assets = vec![
+        asset("mihomo-darwin-arm64-go120-alpha-1a2b3c4.gz"),
+        asset("mihomo-darwin-arm64-alpha-1a2b3c4.gz"),
+    ];
+    let (_, version) = select_asset(&assets, "darwin", "arm64", "Prerelease-Alpha").unwrap();
+    assert_eq!(version, "alpha-1a2b3c4");
+
+    assert_eq!(
+        with_suffix(Path::new("/opt/koala-mihomo"), "bak"),
+        PathBuf::from("/opt/koala-mihomo.bak")
+    );
+}
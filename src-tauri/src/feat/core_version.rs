@@ -0,0 +1,87 @@
+use crate::{
+    config::Config,
+    core::{handle, CoreManager},
+    logging,
+    utils::logging::Type,
+};
+use anyhow::{anyhow, bail, Result};
+
+/// Make sure the active core is at least `required`
+/// Switches to another installed core that is new enough, fails when there is none
+pub async fn ensure_core_version(required: &str) -> Result<()> {
+    if parse_version(required).is_none() {
+        bail!("invalid core version requirement: {required}");
+    }
+
+    let active = Config::verge().latest().get_valid_clash_core();
+    let active_version = CoreManager::global().core_version(&active).await?;
+    if satisfies(&active_version, required) {
+        return Ok(());
+    }
+
+    let mut installed = vec![format!("{active} {active_version}")];
+    for core in CoreManager::global().list_cores() {
+        if core.active || !core.installed {
+            continue;
+        }
+        let Ok(version) = CoreManager::global().core_version(&core.name).await else {
+            continue;
+        };
+        if satisfies(&version, required) {
+            logging!(
+                info,
+                Type::Core,
+                true,
+                "Profile requires core {}, switching to {} {}",
+                required,
+                core.name,
+                version
+            );
+            CoreManager::global()
+                .change_core(Some(core.name.clone()))
+                .await
+                .map_err(|err| anyhow!(err))?;
+            CoreManager::global().restart_core().await?;
+            handle::Handle::refresh_clash();
+            return Ok(());
+        }
+        installed.push(format!("{} {version}", core.name));
+    }
+
+    bail!(
+        "profile requires core {required} or newer, installed: {}",
+        installed.join(", ")
+    )
+}
+
+/// `v1.19.10` or `1.19` as major, minor and patch
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.trim().trim_start_matches('v');
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// alpha builds carry a commit instead of a version and follow the newest code
+fn satisfies(actual: &str, required: &str) -> bool {
+    match (parse_version(actual), parse_version(required)) {
+        (Some(actual), Some(required)) => actual >= required,
+        (None, _) => actual.starts_with("alpha"),
+        (_, None) => false,
+    }
+}
+
+#[test]
+fn test_satisfies() {
+    assert_eq!(parse_version("v1.19.10"), Some((1, 19, 10)));
+    assert_eq!(parse_version("1.18"), Some((1, 18, 0)));
+    assert_eq!(parse_version("latest"), None);
+
+    assert!(satisfies("v1.19.10", "v1.19.0"));
+    assert!(satisfies("v1.19.0", "1.19"));
+    assert!(!satisfies("v1.18.9", "v1.19.0"));
+    assert!(satisfies("alpha-1a2b3c4", "v1.19.0"));
+    assert!(!satisfies("unknown", "v1.19.0"));
+}
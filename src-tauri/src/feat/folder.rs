@@ -0,0 +1,210 @@
+use crate::{
+    config::{Config, IVerge, PrfItem},
+    core::{handle, ProfileWatcher},
+    logging,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use serde_yaml::Mapping;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// source files imported before, so a deleted profile is not imported again
+const IMPORT_RECORD: &str = "folder_imports.yaml";
+
+/// files modified more recently than this may still be written by a sync client
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// top-level keys of which at least one must be present in a Clash config
+const CLASH_KEYS: &[&str] = &["proxies", "proxy-providers", "proxy-groups", "rules"];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FolderImport {
+    /// uids of the created profiles
+    pub imported: Vec<String>,
+    /// `file: reason` for every file that was not imported
+    pub skipped: Vec<String>,
+}
+
+/// Create local profiles for the Clash configs in `dir` that were not imported before
+/// Only the top level of the folder is scanned
+pub fn import_profile_dir(dir: &str) -> Result<FolderImport> {
+    let dir = Path::new(dir);
+    if !dir.is_dir() {
+        bail!("\"{}\" is not a directory", dir.display());
+    }
+
+    let record_path = dirs::app_home_dir()?.join(IMPORT_RECORD);
+    let mut record: Vec<String> = help::read_yaml(&record_path).unwrap_or_default();
+    let mut imported_paths: HashSet<String> = record.iter().cloned().collect();
+    {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        if let Some(items) = profiles.get_items() {
+            imported_paths.extend(
+                items
+                    .iter()
+                    .filter_map(|item| item.option.as_ref()?.source_path.clone()),
+            );
+        }
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_yaml_file(path))
+        .collect();
+    files.sort();
+
+    let mut result = FolderImport::default();
+    for path in files {
+        let source = path.to_string_lossy().to_string();
+        if imported_paths.contains(&source) {
+            continue;
+        }
+        let settled = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age >= SETTLE_TIME);
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !settled {
+            result
+                .skipped
+                .push(format!("{file_name}: still being written"));
+            continue;
+        }
+
+        match import_file(&path, source.clone()) {
+            Ok(uid) => {
+                result.imported.push(uid);
+                record.push(source);
+            }
+            Err(err) => result.skipped.push(format!("{file_name}: {err}")),
+        }
+    }
+
+    if !result.imported.is_empty() {
+        help::save_yaml(&record_path, &record, None)?;
+        logging!(
+            info,
+            Type::Config,
+            true,
+            "Imported {} profiles from {}",
+            result.imported.len(),
+            dir.display()
+        );
+    }
+    Ok(result)
+}
+
+fn import_file(path: &Path, source: String) -> Result<String> {
+    let content = fs::read_to_string(path)?;
+    let config = serde_yaml::from_str::<Mapping>(&content)?;
+    if !is_clash_config(&config) {
+        bail!("not a Clash config");
+    }
+
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Local".into());
+    let mut item = PrfItem::from_local(
+        name,
+        format!("Imported from {}", path.display()),
+        Some(content),
+        None,
+    )?;
+    if let Some(option) = item.option.as_mut() {
+        option.source_path = Some(source);
+    }
+
+    let uid = item.uid.clone().unwrap_or_default();
+    Config::profiles().data().append_item(item)?;
+    Ok(uid)
+}
+
+fn is_yaml_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+}
+
+fn is_clash_config(config: &Mapping) -> bool {
+    CLASH_KEYS.iter().any(|key| config.contains_key(*key))
+}
+
+/// Add or remove a folder from the watched import folders
+pub fn watch_profile_dir(dir: String, watch: bool) -> Result<()> {
+    let mut folders = Config::verge()
+        .latest()
+        .profile_import_dirs
+        .clone()
+        .unwrap_or_default();
+    folders.retain(|d| *d != dir);
+    if watch {
+        folders.push(dir);
+    }
+
+    let patch = IVerge {
+        profile_import_dirs: Some(folders),
+        ..IVerge::default()
+    };
+    Config::verge().draft().patch_config(patch);
+    Config::verge().apply();
+    Config::verge().data().save_file()?;
+    ProfileWatcher::global().watch_import_dirs();
+    handle::Handle::refresh_verge();
+    Ok(())
+}
+
+/// Import new configs from every watched folder, called by the profile watcher
+pub fn import_watched_dirs() {
+    let folders = Config::verge()
+        .latest()
+        .profile_import_dirs
+        .clone()
+        .unwrap_or_default();
+
+    let mut imported = 0;
+    for dir in folders {
+        match import_profile_dir(&dir) {
+            Ok(result) => {
+                imported += result.imported.len();
+                for skipped in result.skipped {
+                    log::debug!(target: "app", "skip {skipped} in watched folder {dir}");
+                }
+            }
+            Err(err) => {
+                log::debug!(target: "app", "failed to scan watched folder {dir}: {err}");
+            }
+        }
+    }
+
+    if imported > 0 {
+        let current = Config::profiles()
+            .latest()
+            .get_current()
+            .unwrap_or_default();
+        handle::Handle::notify_profile_changed(current);
+    }
+}
+
+#[test]
+fn test_is_clash_config() {
+    let config: Mapping = serde_yaml::from_str("proxies: []\nrules: []").unwrap();
+    assert!(is_clash_config(&config));
+
+    let config: Mapping = serde_yaml::from_str("name: test\nversion: 1").unwrap();
+    assert!(!is_clash_config(&config));
+
+    assert!(is_yaml_file(Path::new("/tmp/a.YML")));
+    assert!(!is_yaml_file(Path::new("/tmp/a.json")));
+}
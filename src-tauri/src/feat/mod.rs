@@ -1,14 +1,36 @@
+mod app_rules;
 mod backup;
+mod bundle;
 mod clash;
 mod config;
+mod core_update;
+mod core_version;
+mod folder;
+mod network_reset;
+mod notice;
 mod profile;
 mod proxy;
+mod search;
+mod tun_bench;
+mod validate;
+mod webhook;
 mod window;
 
 // Re-export all functions from modules
+pub use app_rules::*;
 pub use backup::*;
+pub use bundle::*;
 pub use clash::*;
 pub use config::*;
+pub use core_update::*;
+pub use core_version::*;
+pub use folder::*;
+pub use network_reset::*;
+pub use notice::*;
 pub use profile::*;
 pub use proxy::*;
+pub use search::*;
+pub use tun_bench::*;
+pub use validate::*;
+pub use webhook::*;
 pub use window::*;
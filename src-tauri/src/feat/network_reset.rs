@@ -0,0 +1,252 @@
+use crate::{
+    config::{Config, IVerge},
+    core::{dns_check, sysopt::Sysopt},
+    logging,
+    utils::logging::Type,
+};
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// mihomo auto-route 默认使用的路由表
+#[cfg(target_os = "linux")]
+const ROUTE_TABLE: u64 = 2022;
+
+/// 默认的 fake-ip 网段，内核崩溃后可能残留指向它的路由
+#[cfg(target_os = "macos")]
+const FAKE_IP_NET: &str = "198.18.0.0";
+
+/// 重置网络中的一步
+#[derive(Debug, Clone, Serialize)]
+pub struct ResetStep {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    /// 重启系统后才生效
+    pub reboot_required: bool,
+}
+
+impl ResetStep {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        logging!(
+            info,
+            Type::Network,
+            true,
+            "Reset network: {} {} {}",
+            name,
+            if ok { "ok" } else { "failed" },
+            detail
+        );
+        Self {
+            name: name.into(),
+            ok,
+            detail,
+            reboot_required: false,
+        }
+    }
+
+    /// 成功时需要重启系统才能生效
+    #[cfg(windows)]
+    fn with_reboot(mut self) -> Self {
+        self.reboot_required = self.ok;
+        self
+    }
+}
+
+/// 清理内核崩溃后残留的网络设置
+///
+/// 关闭系统代理与 TUN 模式，清除系统代理设置、残留的 TUN 网卡与路由，并刷新 DNS 缓存。
+/// 部分步骤需要管理员权限，失败的步骤会在结果中标出，不会中断后续步骤
+pub async fn reset_network() -> Vec<ResetStep> {
+    let mut steps = Vec::new();
+
+    let patch = IVerge {
+        enable_system_proxy: Some(false),
+        enable_tun_mode: Some(false),
+        ..IVerge::default()
+    };
+    let result = super::patch_verge(patch, false).await;
+    steps.push(ResetStep::new(
+        "disable_proxy_modes",
+        result.map(|_| String::new()).map_err(|e| e.to_string()),
+    ));
+
+    let result = Sysopt::global().reset_sysproxy().await;
+    steps.push(ResetStep::new(
+        "system_proxy",
+        result.map(|_| String::new()).map_err(|e| e.to_string()),
+    ));
+
+    steps.extend(reset_platform(&tun_device()).await);
+
+    let flushed = dns_check::flush_cache().await;
+    steps.push(ResetStep::new(
+        "flush_dns",
+        if flushed {
+            Ok(String::new())
+        } else {
+            Err("no resolver cache could be flushed".into())
+        },
+    ));
+
+    steps
+}
+
+/// TUN 网卡名称
+fn tun_device() -> String {
+    Config::clash()
+        .latest()
+        .0
+        .get("tun")
+        .and_then(|tun| tun.get("device"))
+        .and_then(Value::as_str)
+        .filter(|device| !device.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "utun1024".into()
+            } else {
+                "Mihomo".into()
+            }
+        })
+}
+
+#[cfg(windows)]
+async fn reset_platform(device: &str) -> Vec<ResetStep> {
+    let mut steps = vec![
+        ResetStep::new(
+            "winhttp_proxy",
+            run("netsh", &["winhttp", "reset", "proxy"]).await,
+        ),
+        ResetStep::new("winsock", run("netsh", &["winsock", "reset"]).await).with_reboot(),
+    ];
+
+    // PowerShell 单引号字符串中的单引号要写两次
+    let alias = device.replace('\'', "''");
+    let exists = powershell(&format!(
+        "Get-NetAdapter -Name '{alias}' -IncludeHidden -ErrorAction Stop | Out-Null"
+    ))
+    .await
+    .is_ok();
+    if !exists {
+        return steps;
+    }
+    // auto-route 添加的 0.0.0.0/1、128.0.0.0/1 与 fake-ip 路由都指向 TUN 网卡
+    steps.push(ResetStep::new(
+        "tun_routes",
+        powershell(&format!(
+            "Get-NetRoute -InterfaceAlias '{alias}' -ErrorAction SilentlyContinue \
+             | Remove-NetRoute -Confirm:$false -ErrorAction Stop"
+        ))
+        .await,
+    ));
+    // 只禁用的 wintun 网卡仍然占用名称，内核下次无法创建同名网卡
+    steps.push(ResetStep::new(
+        "tun_interface",
+        powershell(&format!(
+            "$id = (Get-NetAdapter -Name '{alias}' -IncludeHidden -ErrorAction Stop).PnPDeviceID; \
+             pnputil /remove-device $id; exit $LASTEXITCODE"
+        ))
+        .await,
+    ));
+    steps
+}
+
+#[cfg(windows)]
+async fn powershell(script: &str) -> Result<String, String> {
+    run(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", script],
+    )
+    .await
+}
+
+#[cfg(target_os = "macos")]
+async fn reset_platform(device: &str) -> Vec<ResetStep> {
+    let mut steps = vec![ResetStep::new(
+        "tun_routes",
+        run(
+            "route",
+            &["-n", "delete", "-net", &format!("{FAKE_IP_NET}/16")],
+        )
+        .await,
+    )];
+    // auto-route 会把默认路由拆成多段指向 utun
+    let routes = run("netstat", &["-rn", "-f", "inet"])
+        .await
+        .unwrap_or_default();
+    for dest in routes
+        .lines()
+        .filter(|line| line.split_whitespace().last() == Some(device))
+        .filter_map(|line| line.split_whitespace().next())
+    {
+        steps.push(ResetStep::new(
+            "tun_routes",
+            run(
+                "route",
+                &["-n", "delete", "-net", dest, "-interface", device],
+            )
+            .await,
+        ));
+    }
+    steps
+}
+
+#[cfg(target_os = "linux")]
+async fn reset_platform(device: &str) -> Vec<ResetStep> {
+    let table = ROUTE_TABLE.to_string();
+    let mut steps = vec![
+        ResetStep::new(
+            "tun_routes",
+            run("ip", &["route", "flush", "table", &table]).await,
+        ),
+        ResetStep::new(
+            "tun_routes_v6",
+            run("ip", &["-6", "route", "flush", "table", &table]).await,
+        ),
+    ];
+    // 每次只删除一条规则，删到没有为止
+    for family in ["-4", "-6"] {
+        let mut removed = 0;
+        while run("ip", &[family, "rule", "del", "table", &table])
+            .await
+            .is_ok()
+        {
+            removed += 1;
+        }
+        steps.push(ResetStep::new(
+            "route_rules",
+            Ok(format!("{removed} rules removed ({family})")),
+        ));
+    }
+    let exists = run("ip", &["link", "show", device]).await.is_ok();
+    if exists {
+        steps.push(ResetStep::new(
+            "tun_interface",
+            run("ip", &["link", "delete", device]).await,
+        ));
+    }
+    steps
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args);
+    #[cfg(windows)]
+    {
+        #[allow(unused_imports)] // creation_flags必须
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = cmd.output().await.map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() { stdout } else { stderr })
+    }
+}
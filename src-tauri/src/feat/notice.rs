@@ -0,0 +1,55 @@
+use crate::{
+    config::{Config, PrfOption},
+    core::{handle, notifications::in_quiet_hours},
+};
+use chrono::Timelike;
+
+/// Subscription events with their own notice setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileNotice {
+    Success,
+    Retry,
+    Failure,
+    /// quota and expiry warnings, only silenced by the profile mute and quiet hours
+    Usage,
+}
+
+/// Show a subscription notice unless the settings, the profile or the quiet hours silence it
+pub fn notice_profile_event<M: Into<String>>(
+    event: ProfileNotice,
+    option: Option<&PrfOption>,
+    status: &str,
+    msg: M,
+) {
+    if option.is_some_and(|o| o.mute_notices.unwrap_or(false)) {
+        log::debug!(target: "app", "notice {status} muted for this profile");
+        return;
+    }
+
+    let (enabled, quiet_hours) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        let enabled = match event {
+            ProfileNotice::Success => verge.profile_notice_success,
+            ProfileNotice::Retry => verge.profile_notice_retry,
+            ProfileNotice::Failure => verge.profile_notice_failure,
+            ProfileNotice::Usage => None,
+        };
+        (
+            enabled.unwrap_or(true),
+            verge.profile_notice_quiet_hours.clone(),
+        )
+    };
+    if !enabled {
+        return;
+    }
+
+    let now = chrono::Local::now();
+    let minute = now.hour() * 60 + now.minute();
+    if quiet_hours.is_some_and(|spec| in_quiet_hours(&spec, minute)) {
+        log::debug!(target: "app", "notice {status} suppressed during quiet hours");
+        return;
+    }
+
+    handle::Handle::notice_message(status, msg);
+}
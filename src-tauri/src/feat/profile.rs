@@ -1,12 +1,76 @@
+use super::{notice_profile_event, send_update_webhook, ProfileNotice, ProfileUpdateReport};
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption},
+    config::{self, Config, PrfItem, PrfOption, ProfileDiff},
     core::{handle, CoreManager, *},
     logging,
     process::AsyncHandler,
-    utils::logging::Type,
+    utils::{dirs, help, logging::Type},
 };
 use anyhow::{bail, Result};
+use futures::{stream, FutureExt, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_yaml::Mapping;
+use std::{collections::HashMap, time::Duration};
+
+/// 流量使用超过该比例时提醒
+const QUOTA_WARNING_RATIO: f64 = 0.9;
+
+/// 距离到期少于该时间时提醒（3 天）
+const EXPIRY_WARNING_SECS: i64 = 3 * 24 * 60 * 60;
+
+/// 已提醒过的订阅，避免每次更新都重复提醒 (uid -> (quota, expiry))
+static USAGE_WARNED: Lazy<Mutex<HashMap<String, (bool, bool)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 已下载但尚未确认应用的订阅内容
+static PENDING_UPDATES: Lazy<Mutex<HashMap<String, PrfItem>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 批量更新订阅时的最大并发数
+const UPDATE_ALL_CONCURRENCY: usize = 4;
+
+/// Retry policy for subscription downloads
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    attempts: u32,
+    backoff_ms: u64,
+    jitter_ms: u64,
+}
+
+impl RetryPolicy {
+    fn from_verge() -> Self {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        Self {
+            attempts: verge.profile_retry_attempts.unwrap_or(3).clamp(1, 10),
+            backoff_ms: verge.profile_retry_backoff_ms.unwrap_or(1000),
+            jitter_ms: verge.profile_retry_jitter_ms.unwrap_or(500),
+        }
+    }
+
+    /// delay before the given retry (1-based), doubled each time plus random jitter
+    fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff_ms.saturating_mul(1 << (retry - 1).min(6));
+        let jitter = match self.jitter_ms {
+            0 => 0,
+            max => getrandom::u64().map(|r| r % (max + 1)).unwrap_or(0),
+        };
+        Duration::from_millis(backoff.saturating_add(jitter))
+    }
+}
+
+/// Result of a single profile in a batch update
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileUpdateResult {
+    pub uid: String,
+    pub name: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
 /// Toggle proxy profile
 pub fn toggle_proxy_profile(profile_index: String) {
@@ -23,6 +87,51 @@ pub fn toggle_proxy_profile(profile_index: String) {
     });
 }
 
+/// Warn once when the traffic quota is nearly used up or the subscription is about to expire
+fn check_subscription_usage(item: &PrfItem) {
+    let (Some(uid), Some(extra)) = (item.uid.as_ref(), item.extra.as_ref()) else {
+        return;
+    };
+    let name = item.name.clone().unwrap_or_else(|| uid.clone());
+
+    let quota_hit = extra
+        .usage_ratio()
+        .is_some_and(|ratio| ratio >= QUOTA_WARNING_RATIO);
+    let expiry_hit = extra.expires_within(chrono::Local::now().timestamp(), EXPIRY_WARNING_SECS);
+
+    let mut warned = USAGE_WARNED.lock();
+    let entry = warned.entry(uid.clone()).or_default();
+    if quota_hit && !entry.0 {
+        log::warn!(target: "app", "[Subscription Update] {uid} has used over {}% of its traffic", QUOTA_WARNING_RATIO * 100.0);
+        notice_profile_event(
+            ProfileNotice::Usage,
+            item.option.as_ref(),
+            "profile_quota_warning",
+            name.clone(),
+        );
+    }
+    if expiry_hit && !entry.1 {
+        log::warn!(target: "app", "[Subscription Update] {uid} expires soon");
+        notice_profile_event(
+            ProfileNotice::Usage,
+            item.option.as_ref(),
+            "profile_expiry_warning",
+            name,
+        );
+    }
+    // reset once the condition clears, e.g. after a renewal
+    *entry = (quota_hit, expiry_hit);
+}
+
+/// The options of a profile, notices honor its `mute_notices`
+fn profile_option(uid: &String) -> Option<PrfOption> {
+    Config::profiles()
+        .latest()
+        .get_item(uid)
+        .ok()
+        .and_then(|item| item.option.clone())
+}
+
 /// Update a profile
 /// If updating current profile, activate it
 /// auto_refresh: 是否自动更新配置和刷新前端
@@ -40,6 +149,19 @@ pub async fn update_profile(
     );
     let auto_refresh = auto_refresh.unwrap_or(true); // 默认为true，保持兼容性
 
+    let (is_composite, is_locked) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item = profiles.get_item(&uid)?;
+        (item.itype.as_deref() == Some("composite"), item.is_locked())
+    };
+    if is_locked {
+        bail!("the profile \"uid:{uid}\" is locked");
+    }
+    if is_composite {
+        return update_composite_profile(uid, auto_refresh).await;
+    }
+
     let url_opt = {
         let profiles = Config::profiles();
         let profiles = profiles.latest();
@@ -58,78 +180,213 @@ pub async fn update_profile(
                 uid,
                 item.url.clone().unwrap()
             );
-            Some((item.url.clone().unwrap(), item.option.clone()))
+            // 本地文件缺失或手动修改了选项时不发送缓存校验头
+            let file_exists = item
+                .file
+                .as_ref()
+                .and_then(|file| dirs::app_profiles_dir().ok().map(|dir| dir.join(file)))
+                .is_some_and(|path| path.exists());
+            let validators = if file_exists && option.is_none() {
+                (item.etag.clone(), item.last_modified.clone())
+            } else {
+                (None, None)
+            };
+            Some((
+                item.url.clone().unwrap(),
+                item.option.clone(),
+                item.fail_count.unwrap_or(0),
+                validators,
+            ))
         }
     };
 
     let should_update = match url_opt {
-        Some((url, opt)) => {
+        Some((url, opt, prev_failures, (etag, last_modified))) => {
             log::info!(target: "app", "[Subscription Update] Start downloading new subscription content");
             let merged_opt = PrfOption::merge(opt.clone(), option.clone());
+            let policy = RetryPolicy::from_verge();
+            // 已处于连续失败状态时不再重复弹出通知
+            let quiet = prev_failures > 0;
 
-            // 尝试使用正常设置更新
-            match PrfItem::from_url(&url, None, None, merged_opt.clone()).await {
-                Ok(item) => {
-                    log::info!(target: "app", "[Subscription Update] Subscription config updated successfully");
-                    let profiles = Config::profiles();
-                    let mut profiles = profiles.latest();
-                    profiles.update_item(uid.clone(), item)?;
+            // 指定了下载代理时始终通过该代理重试
+            let custom_proxy = merged_opt
+                .as_ref()
+                .is_some_and(|o| o.download_proxy.is_some());
 
-                    let is_current = Some(uid.clone()) == profiles.get_current();
-                    log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
-                    is_current && auto_refresh
+            // 创建使用Clash代理的选项
+            let mut fallback_opt = merged_opt.clone().unwrap_or_default();
+            if !custom_proxy {
+                fallback_opt.with_proxy = Some(false);
+                fallback_opt.self_proxy = Some(true);
+            }
+
+            // 主地址和镜像按响应速度排序，首次尝试时依次回退
+            let mirrors: Vec<String> = merged_opt
+                .as_ref()
+                .and_then(|o| o.mirrors.clone())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|mirror| mirror.trim().to_string())
+                .filter(|mirror| !mirror.is_empty() && *mirror != url)
+                .collect();
+            let sources = if mirrors.is_empty() {
+                vec![url.clone()]
+            } else {
+                let mut urls = vec![url.clone()];
+                for mirror in mirrors {
+                    if !urls.contains(&mirror) {
+                        urls.push(mirror);
+                    }
                 }
-                Err(err) => {
-                    // 首次更新失败，尝试使用Clash代理
-                    log::warn!(target: "app", "[Subscription Update] Normal update failed: {err}, trying to update via Clash proxy");
+                let ranked = PrfItem::rank_mirrors(&urls, merged_opt.as_ref()).await;
+                log::info!(target: "app", "[Subscription Update] Mirror order for {uid}: {ranked:?}");
+                ranked
+            };
 
-                    // 发送通知
-                    handle::Handle::notice_message("update_retry_with_clash", uid.clone());
+            let started = std::time::Instant::now();
+            let mut downloaded = None;
+            let mut last_err = None;
+            'attempts: for attempt in 0..policy.attempts {
+                if attempt > 0 {
+                    let delay = policy.delay(attempt);
+                    log::info!(target: "app", "[Subscription Update] Retry {attempt} for {uid} in {}ms", delay.as_millis());
+                    tokio::time::sleep(delay).await;
+                }
+                if attempt == 1 && !quiet && !custom_proxy {
+                    notice_profile_event(
+                        ProfileNotice::Retry,
+                        merged_opt.as_ref(),
+                        "update_retry_with_clash",
+                        uid.clone(),
+                    );
+                }
 
-                    // 保存原始代理设置
-                    let original_with_proxy = merged_opt.as_ref().and_then(|o| o.with_proxy);
-                    let original_self_proxy = merged_opt.as_ref().and_then(|o| o.self_proxy);
+                // 首次使用正常设置并尝试所有镜像，之后通过Clash代理重试主地址
+                let (attempt_opt, attempt_sources) = if attempt == 0 {
+                    (merged_opt.clone(), sources.as_slice())
+                } else {
+                    (Some(fallback_opt.clone()), std::slice::from_ref(&url))
+                };
+                for source in attempt_sources {
+                    match PrfItem::from_url_conditional(
+                        source,
+                        None,
+                        None,
+                        attempt_opt.clone(),
+                        etag.as_deref(),
+                        last_modified.as_deref(),
+                    )
+                    .await
+                    {
+                        Ok(mut item) => {
+                            // 镜像只用于下载，保留原始订阅地址
+                            if *source != url {
+                                log::info!(target: "app", "[Subscription Update] {uid} downloaded from mirror {source}");
+                                if let Some(item) = item.as_mut() {
+                                    item.url = Some(url.clone());
+                                }
+                            }
+                            downloaded = Some((item, attempt > 0 && !custom_proxy));
+                            break 'attempts;
+                        }
+                        Err(err) => {
+                            log::warn!(target: "app", "[Subscription Update] Attempt {} via {source} failed: {err}", attempt + 1);
+                            last_err = Some(err);
+                        }
+                    }
+                }
+            }
 
-                    // 创建使用Clash代理的选项
-                    let mut fallback_opt = merged_opt.unwrap_or_default();
-                    fallback_opt.with_proxy = Some(false);
-                    fallback_opt.self_proxy = Some(true);
+            match downloaded {
+                Some((None, _)) => {
+                    log::info!(target: "app", "[Subscription Update] {uid} is not modified, skipping update");
+                    let duration = started.elapsed().as_millis() as u64;
+                    Config::profiles()
+                        .latest()
+                        .touch_item(&uid, Some(duration))?;
+                    send_update_webhook(ProfileUpdateReport::new(
+                        &uid,
+                        None,
+                        "not_modified",
+                        started.elapsed(),
+                    ));
+                    false
+                }
+                Some((Some(mut item), via_clash)) => {
+                    if via_clash {
+                        log::info!(target: "app", "[Subscription Update] Update via Clash proxy succeeded");
 
-                    // 使用Clash代理重试
-                    match PrfItem::from_url(&url, None, None, Some(fallback_opt)).await {
-                        Ok(mut item) => {
-                            log::info!(target: "app", "[Subscription Update] Update via Clash proxy succeeded");
+                        // 恢复原始代理设置到item
+                        if let Some(option) = item.option.as_mut() {
+                            option.with_proxy = merged_opt.as_ref().and_then(|o| o.with_proxy);
+                            option.self_proxy = merged_opt.as_ref().and_then(|o| o.self_proxy);
+                        }
+                    } else {
+                        log::info!(target: "app", "[Subscription Update] Subscription config updated successfully");
+                    }
 
-                            // 恢复原始代理设置到item
-                            if let Some(option) = item.option.as_mut() {
-                                option.with_proxy = original_with_proxy;
-                                option.self_proxy = original_self_proxy;
-                            }
+                    // 获取配置名称用于通知
+                    let profile_name = item.name.clone().unwrap_or_else(|| uid.clone());
+                    let bytes = item.file_data.as_ref().map(|data| data.len());
+                    item.last_duration = Some(started.elapsed().as_millis() as u64);
 
-                            // 更新到配置
-                            let profiles = Config::profiles();
-                            let mut profiles = profiles.latest();
-                            profiles.update_item(uid.clone(), item.clone())?;
+                    let profiles = Config::profiles();
+                    let mut profiles = profiles.latest();
+                    profiles.update_item(uid.clone(), item)?;
+                    if let Ok(item) = profiles.get_item(&uid) {
+                        check_subscription_usage(item);
+                    }
+                    send_update_webhook(ProfileUpdateReport {
+                        bytes,
+                        ..ProfileUpdateReport::new(
+                            &uid,
+                            Some(profile_name.clone()),
+                            "success",
+                            started.elapsed(),
+                        )
+                    });
+
+                    // 发送通知告知用户自动更新使用了回退机制
+                    if via_clash && !quiet {
+                        notice_profile_event(
+                            ProfileNotice::Success,
+                            merged_opt.as_ref(),
+                            "update_with_clash_proxy",
+                            profile_name,
+                        );
+                    }
 
-                            // 获取配置名称用于通知
-                            let profile_name = item.name.clone().unwrap_or_else(|| uid.clone());
+                    let is_current = Some(uid.clone()) == profiles.get_current();
+                    log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
+                    is_current && auto_refresh
+                }
+                None => {
+                    let err =
+                        last_err.unwrap_or_else(|| anyhow::anyhow!("no download attempt was made"));
+                    log::error!(target: "app", "[Subscription Update] All {} attempts failed: {err}", policy.attempts);
 
-                            // 发送通知告知用户自动更新使用了回退机制
-                            handle::Handle::notice_message("update_with_clash_proxy", profile_name);
+                    send_update_webhook(ProfileUpdateReport {
+                        error: Some(err.to_string()),
+                        ..ProfileUpdateReport::new(&uid, None, "failed", started.elapsed())
+                    });
 
-                            let is_current = Some(uid.clone()) == profiles.get_current();
-                            log::info!(target: "app", "[Subscription Update] Is current active subscription: {is_current}");
-                            is_current && auto_refresh
-                        }
-                        Err(retry_err) => {
-                            log::error!(target: "app", "[Subscription Update] Update via Clash proxy still failed: {retry_err}");
-                            handle::Handle::notice_message(
-                                "update_failed_even_with_clash",
-                                format!("{retry_err}"),
-                            );
-                            return Err(retry_err);
-                        }
+                    let failures = Config::profiles()
+                        .latest()
+                        .record_update_failure(&uid)
+                        .unwrap_or(prev_failures + 1);
+                    if failures <= 1 {
+                        notice_profile_event(
+                            ProfileNotice::Failure,
+                            merged_opt.as_ref(),
+                            "update_failed_even_with_clash",
+                            format!("{err}"),
+                        );
+                    } else {
+                        log::warn!(target: "app", "[Subscription Update] {uid} failed {failures} times in a row, notice suppressed");
                     }
+                    // 下载失败时继续使用已有的文件，并在健康信息中标记为过期
+                    log::warn!(target: "app", "[Subscription Update] Keeping the cached file of {uid}, marked as stale");
+                    return Err(err);
                 }
             }
         }
@@ -161,7 +418,12 @@ pub async fn update_profile(
                     "[Subscription Update] Update failed: {}",
                     err
                 );
-                handle::Handle::notice_message("update_failed", format!("{err}"));
+                notice_profile_event(
+                    ProfileNotice::Failure,
+                    profile_option(&uid).as_ref(),
+                    "update_failed",
+                    format!("{err}"),
+                );
                 log::error!(target: "app", "{err}");
             }
         }
@@ -170,6 +432,256 @@ pub async fn update_profile(
     Ok(())
 }
 
+/// Update the sources of a composite profile, then regenerate its content
+async fn update_composite_profile(uid: String, auto_refresh: bool) -> Result<()> {
+    let sources = Config::profiles()
+        .latest()
+        .get_item(&uid)?
+        .option
+        .as_ref()
+        .and_then(|o| o.sources.clone())
+        .unwrap_or_default();
+
+    for source in sources.iter() {
+        // 单个来源失败时继续使用其本地已有的内容
+        // boxed as a `Send` trait object to break the recursive future type
+        let update = update_profile(source.clone(), None, Some(false)).boxed();
+        if let Err(err) = update.await {
+            log::warn!(target: "app", "[Subscription Update] Composite {uid} source {source} failed: {err}");
+        }
+    }
+
+    let (data, is_current) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let inputs = sources
+            .iter()
+            .filter_map(|source| {
+                let item = profiles.get_item(source).ok()?;
+                let content = item.read_file().ok()?;
+                let mapping = serde_yaml::from_str::<Mapping>(&content).ok()?;
+                Some((item.name.clone().unwrap_or_else(|| source.clone()), mapping))
+            })
+            .collect::<Vec<_>>();
+        if inputs.is_empty() {
+            bail!("none of the composite sources could be read");
+        }
+
+        let data = serde_yaml::to_string(&config::build_composite(&inputs))?;
+        (data, profiles.get_current() == Some(uid.clone()))
+    };
+
+    {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.latest();
+        profiles.get_item(&uid)?.save_file(data)?;
+        profiles.patch_item(
+            uid.clone(),
+            PrfItem {
+                updated: Some(chrono::Local::now().timestamp() as usize),
+                ..PrfItem::default()
+            },
+        )?;
+    }
+    log::info!(target: "app", "[Subscription Update] Composite {uid} regenerated from {} sources", sources.len());
+
+    if is_current && auto_refresh {
+        CoreManager::global().update_config().await?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(())
+}
+
+/// Update all remote profiles with bounded concurrency
+/// The core config is only regenerated once, and only if the current profile was updated
+pub async fn update_all_profiles() -> Result<Vec<ProfileUpdateResult>> {
+    let (targets, current) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let targets = profiles.items.as_ref().map_or_else(Vec::new, |items| {
+            items
+                .iter()
+                .filter(|item| item.itype.as_deref() == Some("remote") && item.url.is_some())
+                .filter(|item| !item.is_locked())
+                .filter_map(|item| Some((item.uid.clone()?, item.name.clone())))
+                .collect::<Vec<_>>()
+        });
+        (targets, profiles.get_current())
+    };
+
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "[Subscription Update] Updating {} remote profiles (concurrency {})",
+        targets.len(),
+        UPDATE_ALL_CONCURRENCY
+    );
+
+    let results = stream::iter(targets)
+        .map(|(uid, name)| async move {
+            handle::Handle::notify_profile_update_started(uid.clone());
+            let result = update_profile(uid.clone(), None, Some(false)).await;
+            handle::Handle::notify_profile_update_completed(uid.clone());
+
+            match result {
+                Ok(_) => ProfileUpdateResult {
+                    uid,
+                    name,
+                    success: true,
+                    error: None,
+                },
+                Err(err) => {
+                    log::warn!(target: "app", "[Subscription Update] {uid} failed in batch update: {err}");
+                    ProfileUpdateResult {
+                        uid,
+                        name,
+                        success: false,
+                        error: Some(err.to_string()),
+                    }
+                }
+            }
+        })
+        .buffer_unordered(UPDATE_ALL_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "[Subscription Update] Batch update finished, {} succeeded, {} failed",
+        results.len() - failed,
+        failed
+    );
+
+    let current_updated = current
+        .as_ref()
+        .is_some_and(|uid| results.iter().any(|r| r.success && &r.uid == uid));
+
+    if current_updated {
+        match CoreManager::global().update_config().await {
+            Ok(_) => handle::Handle::refresh_clash(),
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Config,
+                    true,
+                    "[Subscription Update] Update failed: {}",
+                    err
+                );
+                notice_profile_event(
+                    ProfileNotice::Failure,
+                    current.as_ref().and_then(profile_option).as_ref(),
+                    "update_failed",
+                    format!("{err}"),
+                );
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Download a remote profile without applying it, and diff it against the current file
+/// The downloaded content is kept until it is applied or discarded
+pub async fn preview_profile_update(uid: String, option: Option<PrfOption>) -> Result<ProfileDiff> {
+    let (url, opt, current_data) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item = profiles.get_item(&uid)?;
+        if item.itype.as_deref() != Some("remote") {
+            bail!("only remote profiles can be previewed");
+        }
+        let Some(url) = item.url.clone() else {
+            bail!("failed to get the profile item url");
+        };
+        (
+            url,
+            item.option.clone(),
+            item.read_file().unwrap_or_default(),
+        )
+    };
+
+    let merged_opt = PrfOption::merge(opt, option);
+    let item = PrfItem::from_url(&url, None, None, merged_opt).await?;
+
+    let old = serde_yaml::from_str::<Mapping>(&current_data).unwrap_or_default();
+    let new = item
+        .file_data
+        .as_deref()
+        .and_then(|data| serde_yaml::from_str::<Mapping>(data).ok())
+        .unwrap_or_default();
+    let diff = ProfileDiff::compute(&old, &new);
+
+    log::info!(target: "app", "[Subscription Update] Preview of {uid}: {diff:?}");
+    PENDING_UPDATES.lock().insert(uid, item);
+    Ok(diff)
+}
+
+/// Apply the content downloaded by `preview_profile_update`
+pub async fn apply_profile_update(uid: String) -> Result<()> {
+    Config::profiles().latest().check_unlocked(&uid)?;
+    let Some(item) = PENDING_UPDATES.lock().remove(&uid) else {
+        bail!("no pending update for profile \"{uid}\"");
+    };
+
+    let is_current = {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.latest();
+        profiles.update_item(uid.clone(), item)?;
+        profiles.get_current() == Some(uid)
+    };
+
+    if is_current {
+        CoreManager::global().update_config().await?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(())
+}
+
+/// Drop the content downloaded by `preview_profile_update`
+pub fn discard_profile_update(uid: &str) {
+    PENDING_UPDATES.lock().remove(uid);
+}
+
+/// Restore a profile file from one of its revisions
+/// The content being replaced is saved as a new revision first
+pub async fn rollback_profile_revision(uid: String, revision: String) -> Result<()> {
+    Config::profiles().latest().check_unlocked(&uid)?;
+    let data = config::read_profile_revision(&uid, &revision)?;
+    if serde_yaml::from_str::<Mapping>(&data).is_err() {
+        bail!("revision \"{revision}\" is not a valid profile");
+    }
+
+    let is_current = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let item = profiles.get_item(&uid)?;
+        if let Ok(previous) = item.read_file() {
+            config::snapshot_profile(&uid, &previous)?;
+        }
+        item.save_file(data)?;
+        profiles.get_current() == Some(uid.clone())
+    };
+
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "Profile {} rolled back to revision {}",
+        uid,
+        revision
+    );
+
+    if is_current {
+        CoreManager::global().update_config().await?;
+        handle::Handle::refresh_clash();
+    }
+    Ok(())
+}
+
 /// 增强配置
 pub async fn enhance_profiles() -> Result<()> {
     crate::core::CoreManager::global()
@@ -177,3 +689,171 @@ pub async fn enhance_profiles() -> Result<()> {
         .await
         .map(|_| ())
 }
+
+/// Switch the encryption of profile files at rest and rewrite the existing files to match
+/// returns the number of files rewritten
+pub fn migrate_profile_encryption(enable: bool) -> Result<usize> {
+    let patch = config::IVerge {
+        enable_profile_encryption: Some(enable),
+        ..config::IVerge::default()
+    };
+    Config::verge().draft().patch_config(patch);
+    Config::verge().apply();
+    Config::verge().data().save_file()?;
+
+    let (files, uids): (Vec<String>, Vec<String>) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let items = profiles.get_items().cloned().unwrap_or_default();
+        (
+            items.iter().filter_map(|item| item.file.clone()).collect(),
+            items.iter().filter_map(|item| item.uid.clone()).collect(),
+        )
+    };
+
+    let dir = dirs::app_profiles_dir()?;
+    let mut count = 0;
+    for file in files {
+        let path = dir.join(&file);
+        if !path.exists() {
+            continue;
+        }
+        match config::seal_profile_file(&path) {
+            Ok(()) => count += 1,
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Config,
+                    true,
+                    "Failed to rewrite profile {}: {}",
+                    file,
+                    err
+                );
+            }
+        }
+    }
+
+    // 历史版本也是订阅内容
+    for uid in uids {
+        match config::seal_profile_revisions(&uid) {
+            Ok(rewritten) => count += rewritten,
+            Err(err) => {
+                logging!(
+                    error,
+                    Type::Config,
+                    true,
+                    "Failed to rewrite revisions of {}: {}",
+                    uid,
+                    err
+                );
+            }
+        }
+    }
+
+    logging!(
+        info,
+        Type::Config,
+        true,
+        "Profile encryption {}, {} files rewritten",
+        if enable { "enabled" } else { "disabled" },
+        count
+    );
+    Ok(count)
+}
+
+/// Copy a profile under a new uid together with the merge and script items of its option chain
+/// The copy is not activated, returns its uid
+pub fn duplicate_profile(uid: &String) -> Result<String> {
+    let (source, chain) = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        let source = profiles.get_item(uid)?.clone();
+        let option = source.option.clone().unwrap_or_default();
+        let chain: Vec<PrfItem> = [
+            option.merge,
+            option.script,
+            option.rules,
+            option.proxies,
+            option.groups,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(option.merges.unwrap_or_default())
+        .filter_map(|chain_uid| profiles.get_item(&chain_uid).ok().cloned())
+        .collect();
+        (source, chain)
+    };
+
+    // 旧 uid -> 新 uid
+    let mut renamed = HashMap::new();
+    for item in chain {
+        let Some(old_uid) = item.uid.clone() else {
+            continue;
+        };
+        if renamed.contains_key(&old_uid) {
+            continue;
+        }
+        let copy = copy_item(&item, None)?;
+        renamed.insert(old_uid, copy.uid.clone().unwrap_or_default());
+        Config::profiles().data().append_item(copy)?;
+    }
+
+    let name = source.name.as_deref().unwrap_or("Profile");
+    let mut copy = copy_item(&source, Some(format!("{name} (copy)")))?;
+    if let Some(option) = copy.option.as_mut() {
+        let rename = |value: &mut Option<String>| {
+            if let Some(new_uid) = value.as_ref().and_then(|v| renamed.get(v)) {
+                *value = Some(new_uid.clone());
+            }
+        };
+        rename(&mut option.merge);
+        rename(&mut option.script);
+        rename(&mut option.rules);
+        rename(&mut option.proxies);
+        rename(&mut option.groups);
+        if let Some(merges) = option.merges.as_mut() {
+            for merge in merges.iter_mut() {
+                if let Some(new_uid) = renamed.get(merge) {
+                    *merge = new_uid.clone();
+                }
+            }
+        }
+    }
+    let new_uid = copy.uid.clone().unwrap_or_default();
+
+    {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.data();
+        // append_item activates new profiles, the copy should stay in the background
+        let current = profiles.current.clone();
+        profiles.append_item(copy)?;
+        profiles.current = current;
+        profiles.save_file()?;
+    }
+
+    log::info!(target: "app", "duplicated profile {uid} as {new_uid}");
+    Ok(new_uid)
+}
+
+fn copy_item(item: &PrfItem, name: Option<String>) -> Result<PrfItem> {
+    let prefix: String = item.uid.as_deref().unwrap_or("L").chars().take(1).collect();
+    let new_uid = help::get_uid(&prefix);
+    let extension = item
+        .file
+        .as_deref()
+        .and_then(|file| std::path::Path::new(file).extension())
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "yaml".into());
+
+    let mut copy = item.clone();
+    copy.file_data = Some(item.read_file()?);
+    copy.file = Some(format!("{new_uid}.{extension}"));
+    copy.uid = Some(new_uid);
+    copy.name = name.or(copy.name);
+    copy.updated = Some(chrono::Local::now().timestamp() as usize);
+    copy.locked = None;
+    copy.fail_count = None;
+    copy.last_failure = None;
+    copy.stale_since = None;
+    Ok(copy)
+}
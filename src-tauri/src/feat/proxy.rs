@@ -74,9 +74,16 @@ pub fn copy_clash_env() {
     });
 
     let app_handle = handle::Handle::global().app_handle().unwrap();
-    let port = { Config::verge().latest().verge_mixed_port.unwrap_or(7897) };
-    let http_proxy = format!("http://{clash_verge_rev_ip}:{port}");
-    let socks5_proxy = format!("socks5://{clash_verge_rev_ip}:{port}");
+    let (port, userinfo) = {
+        let verge = Config::verge();
+        let verge = verge.latest();
+        (
+            verge.verge_mixed_port.unwrap_or(7897),
+            verge.proxy_userinfo(),
+        )
+    };
+    let http_proxy = format!("http://{userinfo}{clash_verge_rev_ip}:{port}");
+    let socks5_proxy = format!("socks5://{userinfo}{clash_verge_rev_ip}:{port}");
 
     let cliboard = app_handle.clipboard();
     let env_type = { Config::verge().latest().env_type.clone() };
@@ -0,0 +1,197 @@
+use crate::{
+    config::{Config, PrfItem},
+    utils::dirs,
+};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, fs, time::SystemTime};
+
+/// matched proxy names returned per profile, the total is still counted
+const MAX_PROXY_MATCHES: usize = 5;
+
+/// proxy names per profile uid, reused until the file is modified
+static PROXY_NAMES: Lazy<Mutex<HashMap<String, (Option<SystemTime>, Vec<String>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileMatch {
+    pub uid: String,
+    pub name: Option<String>,
+    pub score: u32,
+    pub name_matched: bool,
+    pub url_matched: bool,
+    /// first matching proxy names
+    pub proxies: Vec<String>,
+    pub proxy_count: usize,
+}
+
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn new(query: &str, regex: bool) -> Result<Self> {
+        if regex {
+            let regex = RegexBuilder::new(query).case_insensitive(true).build()?;
+            Ok(Self::Regex(regex))
+        } else {
+            Ok(Self::Substring(query.to_lowercase()))
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(query) => text.to_lowercase().contains(query),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// exact and prefix matches of a name rank above other matches
+    fn name_score(&self, name: &str) -> Option<u32> {
+        match self {
+            Self::Substring(query) => {
+                let name = name.to_lowercase();
+                if name == *query {
+                    Some(100)
+                } else if name.starts_with(query.as_str()) {
+                    Some(80)
+                } else if name.contains(query.as_str()) {
+                    Some(60)
+                } else {
+                    None
+                }
+            }
+            Self::Regex(regex) => regex.is_match(name).then_some(60),
+        }
+    }
+}
+
+/// Search profile names, urls and the proxy names inside them
+/// Matching is case insensitive, the best matches come first
+pub fn search_profiles(
+    query: &str,
+    regex: bool,
+    limit: Option<usize>,
+) -> Result<Vec<ProfileMatch>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let matcher = Matcher::new(query, regex)?;
+
+    let items = Config::profiles()
+        .latest()
+        .get_items()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut matches: Vec<ProfileMatch> = items
+        .iter()
+        .filter(|item| matches!(item.itype.as_deref(), Some("remote" | "local")))
+        .filter_map(|item| match_item(item, &matcher))
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    if let Some(limit) = limit {
+        matches.truncate(limit);
+    }
+    Ok(matches)
+}
+
+fn match_item(item: &PrfItem, matcher: &Matcher) -> Option<ProfileMatch> {
+    let uid = item.uid.clone()?;
+    let name_score = item
+        .name
+        .as_deref()
+        .and_then(|name| matcher.name_score(name));
+    let url_matched = item.url.as_deref().is_some_and(|url| matcher.is_match(url));
+
+    let matched_proxies: Vec<String> = proxy_names(&uid, item)
+        .into_iter()
+        .filter(|name| matcher.is_match(name))
+        .collect();
+    let proxy_count = matched_proxies.len();
+
+    let mut score = name_score.unwrap_or(0);
+    if url_matched {
+        score = score.max(40);
+    }
+    if proxy_count > 0 {
+        // more matching nodes rank a profile higher among the node matches
+        score = score.max(20 + proxy_count.min(10) as u32);
+    }
+    if score == 0 {
+        return None;
+    }
+
+    Some(ProfileMatch {
+        uid,
+        name: item.name.clone(),
+        score,
+        name_matched: name_score.is_some(),
+        url_matched,
+        proxies: matched_proxies
+            .into_iter()
+            .take(MAX_PROXY_MATCHES)
+            .collect(),
+        proxy_count,
+    })
+}
+
+fn proxy_names(uid: &str, item: &PrfItem) -> Vec<String> {
+    let modified = item
+        .file
+        .as_ref()
+        .and_then(|file| dirs::app_profiles_dir().ok().map(|dir| dir.join(file)))
+        .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+    if let Some((cached, names)) = PROXY_NAMES.lock().get(uid) {
+        if modified.is_some() && *cached == modified {
+            return names.clone();
+        }
+    }
+
+    let names = item
+        .read_file()
+        .ok()
+        .and_then(|data| serde_yaml::from_str::<Mapping>(&data).ok())
+        .map(|config| parse_proxy_names(&config))
+        .unwrap_or_default();
+    PROXY_NAMES
+        .lock()
+        .insert(uid.to_string(), (modified, names.clone()));
+    names
+}
+
+fn parse_proxy_names(config: &Mapping) -> Vec<String> {
+    config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .map(|seq| {
+            seq.iter()
+                .filter_map(|proxy| proxy.get("name")?.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_matcher() {
+    let matcher = Matcher::new("hk", false).unwrap();
+    assert_eq!(matcher.name_score("HK"), Some(100));
+    assert_eq!(matcher.name_score("hk premium"), Some(80));
+    assert_eq!(matcher.name_score("My HK"), Some(60));
+    assert_eq!(matcher.name_score("Japan"), None);
+
+    let matcher = Matcher::new(r"^(hk|sg)-\d+$", true).unwrap();
+    assert!(matcher.is_match("SG-01"));
+    assert!(!matcher.is_match("JP-01"));
+    assert!(Matcher::new("(", true).is_err());
+
+    let config: Mapping =
+        serde_yaml::from_str("proxies:\n- { name: a, type: ss }\n- { type: ss }").unwrap();
+    assert_eq!(parse_proxy_names(&config), vec!["a"]);
+}
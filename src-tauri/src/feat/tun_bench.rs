@@ -0,0 +1,312 @@
+use crate::{
+    config::Config,
+    core::handle,
+    logging,
+    module::mihomo::MihomoManager,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use std::time::{Duration, Instant};
+
+/// 全局模式下选择节点的策略组
+const GLOBAL_GROUP: &str = "GLOBAL";
+
+/// 参与测试的 TUN 协议栈
+pub const TUN_STACKS: &[&str] = &["gvisor", "system", "mixed"];
+
+/// 延迟测试地址，返回 204 无内容
+const LATENCY_URL: &str = "https://cp.cloudflare.com/generate_204";
+/// 下载测速地址
+const THROUGHPUT_URL: &str = "https://speed.cloudflare.com/__down?bytes=10000000";
+
+const LATENCY_ROUNDS: usize = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// 切换协议栈后等待 TUN 设备重建
+const STACK_SETTLE: Duration = Duration::from_secs(3);
+
+const RESULT_FILE: &str = "tun-bench.yaml";
+
+/// 单个协议栈的测试结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TunStackResult {
+    pub stack: String,
+    /// 延迟中位数
+    pub latency_ms: Option<u64>,
+    /// 下载速度
+    pub throughput_mbps: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// 一次完整的测试记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TunBenchReport {
+    pub results: Vec<TunStackResult>,
+    /// 推荐的协议栈，全部失败时为空
+    pub recommended: Option<String>,
+    /// 测试前使用的协议栈
+    pub current: String,
+    /// 流量经过的测试节点
+    #[serde(default)]
+    pub node: String,
+    pub timestamp: i64,
+}
+
+/// 依次切换 TUN 协议栈，测试经过 TUN 和测试节点的延迟与下载速度
+///
+/// 协议栈和模式只通过控制接口临时修改，不写入配置，结束后恢复。
+/// 测试期间切换到全局模式并选中测试节点，未指定时使用 GLOBAL 当前选中的节点
+pub async fn bench_tun_stacks(node: Option<String>) -> Result<TunBenchReport> {
+    if !Config::verge().latest().enable_tun_mode.unwrap_or(false) {
+        bail!("TUN mode is not enabled");
+    }
+    let current = tun_config()
+        .get("stack")
+        .and_then(Value::as_str)
+        .unwrap_or("gvisor")
+        .to_lowercase();
+    let mode = Config::clash()
+        .latest()
+        .0
+        .get("mode")
+        .and_then(Value::as_str)
+        .unwrap_or("rule")
+        .to_string();
+
+    let client = MihomoManager::global();
+    let proxies = client
+        .get_refresh_proxies()
+        .await
+        .map_err(|err| anyhow!(err))?;
+    let selected = proxies["proxies"][GLOBAL_GROUP]["now"]
+        .as_str()
+        .map(str::to_string);
+    let node = node
+        .or_else(|| selected.clone())
+        .filter(|node| !matches!(node.as_str(), "DIRECT" | "REJECT" | "REJECT-DROP"))
+        .ok_or(anyhow!("no test node is selected"))?;
+    if proxies["proxies"][&node].is_null() {
+        bail!("the test node \"{node}\" does not exist");
+    }
+
+    let results = async {
+        client
+            .patch_configs(json!({ "mode": "global" }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+        client
+            .select_proxy(GLOBAL_GROUP, &node)
+            .await
+            .map_err(|err| anyhow!(err))?;
+        handle::Handle::refresh_clash();
+
+        // 不走系统代理，流量经由 TUN 转发到测试节点
+        let http = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let mut results = Vec::new();
+        for stack in TUN_STACKS {
+            logging!(info, Type::Core, true, "Benchmarking TUN stack {}", stack);
+            let result = match apply_stack(stack).await {
+                Ok(()) => {
+                    tokio::time::sleep(STACK_SETTLE).await;
+                    measure(&http, stack).await
+                }
+                Err(err) => TunStackResult {
+                    stack: stack.to_string(),
+                    error: Some(err.to_string()),
+                    ..TunStackResult::default()
+                },
+            };
+            results.push(result);
+        }
+        anyhow::Ok(results)
+    }
+    .await;
+
+    restore(&current, &mode, selected.as_deref()).await;
+    let results = results?;
+
+    let report = TunBenchReport {
+        recommended: recommend(&results),
+        results,
+        current,
+        node,
+        timestamp: chrono::Local::now().timestamp(),
+    };
+    help::save_yaml(&result_path()?, &report, None)?;
+    Ok(report)
+}
+
+/// 上次的测试记录
+pub fn last_tun_bench() -> Option<TunBenchReport> {
+    help::read_yaml(&result_path().ok()?).ok()
+}
+
+/// 将协议栈写入 TUN 配置并保存
+pub async fn apply_tun_stack(stack: String) -> Result<()> {
+    let stack = stack.to_lowercase();
+    if !TUN_STACKS.contains(&stack.as_str()) {
+        bail!("unknown TUN stack \"{stack}\"");
+    }
+    let mut tun = tun_config();
+    tun.insert("stack".into(), stack.into());
+    let mut patch = Mapping::new();
+    patch.insert("tun".into(), tun.into());
+    super::patch_clash(patch).await
+}
+
+fn tun_config() -> Mapping {
+    Config::clash()
+        .latest()
+        .0
+        .get("tun")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn result_path() -> Result<std::path::PathBuf> {
+    Ok(dirs::app_home_dir()?.join(RESULT_FILE))
+}
+
+/// 内核重新创建 TUN 设备，配置文件不变
+async fn apply_stack(stack: &str) -> Result<()> {
+    MihomoManager::global()
+        .patch_configs(json!({ "tun": { "stack": stack } }))
+        .await
+        .map_err(|err| anyhow!(err))
+}
+
+/// 恢复测试前的协议栈、模式和 GLOBAL 的选择
+async fn restore(stack: &str, mode: &str, selected: Option<&str>) {
+    let client = MihomoManager::global();
+    let patch = json!({ "tun": { "stack": stack }, "mode": mode });
+    if let Err(err) = client.patch_configs(patch).await {
+        logging!(
+            error,
+            Type::Core,
+            true,
+            "Failed to restore TUN stack {} and mode {}: {}",
+            stack,
+            mode,
+            err
+        );
+    }
+    if let Some(selected) = selected {
+        if let Err(err) = client.select_proxy(GLOBAL_GROUP, selected).await {
+            logging!(
+                error,
+                Type::Core,
+                true,
+                "Failed to restore the GLOBAL selection {}: {}",
+                selected,
+                err
+            );
+        }
+    }
+    handle::Handle::refresh_clash();
+}
+
+async fn measure(client: &reqwest::Client, stack: &str) -> TunStackResult {
+    let mut result = TunStackResult {
+        stack: stack.to_string(),
+        ..TunStackResult::default()
+    };
+
+    let mut latencies = Vec::new();
+    for _ in 0..LATENCY_ROUNDS {
+        let start = Instant::now();
+        match client.get(LATENCY_URL).send().await {
+            Ok(_) => latencies.push(start.elapsed().as_millis() as u64),
+            Err(err) => result.error = Some(err.to_string()),
+        }
+    }
+    result.latency_ms = median(&mut latencies);
+
+    let start = Instant::now();
+    let download = async {
+        let bytes = client
+            .get(THROUGHPUT_URL)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok::<_, reqwest::Error>(bytes.len())
+    };
+    match download.await {
+        Ok(len) => {
+            let secs = start.elapsed().as_secs_f64().max(0.001);
+            result.throughput_mbps = Some(len as f64 * 8.0 / secs / 1_000_000.0);
+        }
+        Err(err) => result.error = Some(err.to_string()),
+    }
+
+    logging!(
+        info,
+        Type::Core,
+        true,
+        "TUN stack {}: latency {:?} ms, throughput {:?} Mbps",
+        stack,
+        result.latency_ms,
+        result.throughput_mbps
+    );
+    result
+}
+
+fn median(values: &mut [u64]) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[values.len() / 2])
+}
+
+/// 速度与延迟各占一半，分别与最优者相比
+fn recommend(results: &[TunStackResult]) -> Option<String> {
+    let best_latency = results.iter().filter_map(|r| r.latency_ms).min()?.max(1) as f64;
+    let best_throughput = results
+        .iter()
+        .filter_map(|r| r.throughput_mbps)
+        .fold(0.0, f64::max);
+
+    results
+        .iter()
+        .filter_map(|r| {
+            let latency = best_latency / r.latency_ms?.max(1) as f64;
+            let throughput = match r.throughput_mbps {
+                Some(mbps) if best_throughput > 0.0 => mbps / best_throughput,
+                _ => 0.0,
+            };
+            Some((latency + throughput, &r.stack))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, stack)| stack.clone())
+}
+
+#[test]
+fn test_recommend() {
+    let result =
+        |stack: &str, latency_ms: Option<u64>, throughput_mbps: Option<f64>| TunStackResult {
+            stack: stack.into(),
+            latency_ms,
+            throughput_mbps,
+            error: None,
+        };
+    let results = vec![
+        result("gvisor", Some(40), Some(80.0)),
+        result("system", Some(30), Some(200.0)),
+        result("mixed", None, None),
+    ];
+    assert_eq!(recommend(&results).as_deref(), Some("system"));
+    assert_eq!(recommend(&results[2..]), None);
+
+    let mut values = vec![9, 1, 5];
+    assert_eq!(median(&mut values), Some(5));
+}
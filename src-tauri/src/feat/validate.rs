@@ -0,0 +1,128 @@
+use crate::{
+    config::Config,
+    core::CoreManager,
+    enhance, logging,
+    utils::{dirs, help, logging::Type},
+};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_yaml::Mapping;
+use std::fs;
+
+/// temporary file for dry-run validation, separate from the run and check configs
+const VALIDATE_CONFIG: &str = "koala-clash-validate.yaml";
+
+/// A single problem found while validating a profile
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    /// `profile`, `script` or `core`
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileValidation {
+    pub valid: bool,
+    pub errors: Vec<ValidationIssue>,
+}
+
+/// Run the enhancement pipeline for a profile and let the core check the result
+/// The running config and the current profile are left untouched
+pub async fn validate_profile(uid: String) -> Result<ProfileValidation> {
+    logging!(info, Type::Config, true, "Validating profile {}", uid);
+
+    let content = Config::profiles().latest().get_item(&uid)?.read_file()?;
+    if let Err(err) = serde_yaml::from_str::<Mapping>(&content) {
+        let location = err.location();
+        return Ok(ProfileValidation {
+            valid: false,
+            errors: vec![ValidationIssue {
+                source: "profile".into(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+                message: err.to_string(),
+            }],
+        });
+    }
+
+    let (config, _, logs, _) = enhance::enhance_profile(Some(uid)).await;
+
+    let mut errors: Vec<ValidationIssue> = logs
+        .into_values()
+        .flatten()
+        .filter(|(level, _)| level == "exception" || level == "error")
+        .map(|(_, message)| ValidationIssue {
+            source: "script".into(),
+            line: None,
+            column: None,
+            message,
+        })
+        .collect();
+
+    let path = dirs::app_home_dir()?.join(VALIDATE_CONFIG);
+    help::save_yaml(&path, &config, Some("# Generated by Koala Clash"))?;
+    let result = CoreManager::global()
+        .validate_config_file(dirs::path_to_str(&path)?, None)
+        .await;
+    let _ = fs::remove_file(&path);
+
+    let (valid, output) = result?;
+    if !valid {
+        errors.extend(parse_core_errors(&output));
+    }
+
+    Ok(ProfileValidation {
+        valid: errors.is_empty(),
+        errors,
+    })
+}
+
+/// Split the output of `mihomo -t` into issues, keeping line numbers when reported
+fn parse_core_errors(output: &str) -> Vec<ValidationIssue> {
+    static LINE_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"line (\d+)(?:, column (\d+))?").unwrap());
+    static MSG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"msg="((?:[^"\\]|\\.)*)""#).unwrap());
+
+    let mut issues: Vec<ValidationIssue> = output
+        .lines()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            lower.contains("fatal") || lower.contains("error")
+        })
+        .map(|line| {
+            let message = MSG_RE
+                .captures(line)
+                .map(|c| c[1].replace("\\\"", "\""))
+                .unwrap_or_else(|| line.trim().to_string());
+            let (line, column) = match LINE_RE.captures(&message) {
+                Some(c) => (
+                    c.get(1).and_then(|m| m.as_str().parse().ok()),
+                    c.get(2).and_then(|m| m.as_str().parse().ok()),
+                ),
+                None => (None, None),
+            };
+            ValidationIssue {
+                source: "core".into(),
+                line,
+                column,
+                message,
+            }
+        })
+        .collect();
+
+    if issues.is_empty() && !output.trim().is_empty() {
+        issues.push(ValidationIssue {
+            source: "core".into(),
+            line: None,
+            column: None,
+            message: output.trim().to_string(),
+        });
+    }
+    issues
+}
@@ -2,11 +2,30 @@
 use crate::AppHandleManager;
 use crate::{
     config::Config,
-    core::{event_driven_proxy::EventDrivenProxyManager, handle, sysopt, CoreManager},
+    core::{event_driven_proxy::EventDrivenProxyManager, handle, ipv6_guard, sysopt, CoreManager},
     logging,
     module::mihomo::MihomoManager,
     utils::logging::Type,
 };
+use std::time::Duration;
+
+// 退出时各清理步骤的超时，各步骤依次执行
+const TUN_TIMEOUT: Duration = Duration::from_secs(2);
+const SYSPROXY_TIMEOUT: Duration = Duration::from_secs(3);
+const CORE_TIMEOUT: Duration = Duration::from_secs(3);
+/// 没有服务时要等用户确认提权
+const IPV6_TIMEOUT: Duration = Duration::from_secs(30);
+const DNS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 等待全部清理完成的时间：各步骤超时之和，再留出余量
+const CLEAN_TIMEOUT: Duration = Duration::from_secs(
+    TUN_TIMEOUT.as_secs()
+        + SYSPROXY_TIMEOUT.as_secs()
+        + CORE_TIMEOUT.as_secs()
+        + IPV6_TIMEOUT.as_secs()
+        + DNS_TIMEOUT.as_secs()
+        + 2,
+);
 
 /// Open or close the dashboard window
 #[allow(dead_code)]
@@ -99,7 +118,7 @@ pub fn quit() {
 }
 
 async fn clean_async() -> bool {
-    use tokio::time::{timeout, Duration};
+    use tokio::time::timeout;
 
     logging!(
         info,
@@ -117,7 +136,7 @@ async fn clean_async() -> bool {
                 }
             });
             match timeout(
-                Duration::from_secs(2),
+                TUN_TIMEOUT,
                 MihomoManager::global().patch_configs(disable_tun),
             )
             .await
@@ -138,16 +157,25 @@ async fn clean_async() -> bool {
 
     // 2. 系统代理重置
     let proxy_task = async {
-        match timeout(
-            Duration::from_secs(3),
-            sysopt::Sysopt::global().reset_sysproxy(),
-        )
-        .await
-        {
-            Ok(_) => {
+        // Linux 与 macOS 直接恢复第一次修改前的设置，不等待系统代理的锁
+        #[cfg(not(target_os = "windows"))]
+        let reset = async {
+            tokio::task::spawn_blocking(sysopt::restore_original_proxy)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result)
+        };
+        #[cfg(target_os = "windows")]
+        let reset = sysopt::Sysopt::global().reset_sysproxy();
+        match timeout(SYSPROXY_TIMEOUT, reset).await {
+            Ok(Ok(())) => {
                 log::info!(target: "app", "System proxy reset");
                 true
             }
+            Ok(Err(err)) => {
+                log::warn!(target: "app", "Failed to reset system proxy: {err}");
+                false
+            }
             Err(_) => {
                 log::warn!(target: "app", "Timeout resetting system proxy");
                 false
@@ -157,7 +185,7 @@ async fn clean_async() -> bool {
 
     // 3. 核心服务停止
     let core_task = async {
-        match timeout(Duration::from_secs(3), CoreManager::global().stop_core()).await {
+        match timeout(CORE_TIMEOUT, CoreManager::global().stop_core()).await {
             Ok(_) => {
                 log::info!(target: "app", "Core service stopped");
                 true
@@ -169,15 +197,25 @@ async fn clean_async() -> bool {
         }
     };
 
-    // 4. DNS恢复（仅macOS）
+    // 4. 恢复物理网卡的 IPv6，没有服务时要等用户确认提权
+    let ipv6_task = async {
+        match timeout(IPV6_TIMEOUT, ipv6_guard::restore()).await {
+            Ok(Ok(())) => true,
+            Ok(Err(err)) => {
+                log::warn!(target: "app", "Failed to restore IPv6: {err}");
+                false
+            }
+            Err(_) => {
+                log::warn!(target: "app", "Timeout restoring IPv6");
+                false
+            }
+        }
+    };
+
+    // 5. DNS恢复（仅macOS）
     #[cfg(target_os = "macos")]
     let dns_task = async {
-        match timeout(
-            Duration::from_millis(1000),
-            crate::utils::resolve::restore_public_dns(),
-        )
-        .await
-        {
+        match timeout(DNS_TIMEOUT, crate::utils::resolve::restore_public_dns()).await {
             Ok(_) => {
                 log::info!(target: "app", "DNS settings restored");
                 true
@@ -189,24 +227,28 @@ async fn clean_async() -> bool {
         }
     };
 
-    // 并行执行所有清理任务
-    let (tun_success, proxy_success, core_success) = tokio::join!(tun_task, proxy_task, core_task);
+    // 按顺序执行：关闭 TUN 需要内核仍在运行，系统代理要在内核停止前恢复，避免指向已关闭的端口
+    let tun_success = tun_task.await;
+    let proxy_success = proxy_task.await;
+    let core_success = core_task.await;
+    let ipv6_success = ipv6_task.await;
 
     #[cfg(target_os = "macos")]
     let dns_success = dns_task.await;
     #[cfg(not(target_os = "macos"))]
     let dns_success = true;
 
-    let all_success = tun_success && proxy_success && core_success && dns_success;
+    let all_success = tun_success && proxy_success && core_success && ipv6_success && dns_success;
 
     logging!(
         info,
         Type::System,
         true,
-        "Asynchronous cleanup completed - TUN: {}, Proxy: {}, Core: {}, DNS: {}, Overall: {}",
+        "Asynchronous cleanup completed - TUN: {}, Proxy: {}, Core: {}, IPv6: {}, DNS: {}, Overall: {}",
         tun_success,
         proxy_success,
         core_success,
+        ipv6_success,
         dns_success,
         all_success
     );
@@ -229,7 +271,8 @@ pub fn clean() -> bool {
         let _ = tx.send(cleanup_result);
     });
 
-    match rx.recv_timeout(std::time::Duration::from_secs(8)) {
+    // 各清理步骤依次执行，等待时间按各步骤的超时之和计算
+    match rx.recv_timeout(CLEAN_TIMEOUT) {
         Ok(result) => {
             logging!(
                 info,
@@ -252,6 +295,40 @@ pub fn clean() -> bool {
     }
 }
 
+/// 系统注销、关机或收到终止信号时，在系统结束进程前完成清理
+/// 已经在退出流程中时直接返回
+pub async fn shutdown(reason: &str) {
+    let handle = handle::Handle::global();
+    if handle.is_exiting() {
+        return;
+    }
+    logging!(info, Type::System, true, "Shutting down: {}", reason);
+    handle.set_is_exiting();
+    EventDrivenProxyManager::global().notify_app_stopping();
+    crate::core::tray::Tray::global().unsubscribe_traffic();
+
+    let result = clean_async().await;
+    logging!(
+        info,
+        Type::System,
+        true,
+        "Shutdown cleanup result: {}",
+        result
+    );
+}
+
+/// 在异步运行时之外同步执行 shutdown，例如处理 Windows 的会话结束消息
+pub fn shutdown_blocking(reason: &'static str) {
+    use crate::process::AsyncHandler;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    AsyncHandler::spawn(move || async move {
+        shutdown(reason).await;
+        let _ = tx.send(());
+    });
+    let _ = rx.recv_timeout(CLEAN_TIMEOUT);
+}
+
 #[cfg(target_os = "macos")]
 pub fn hide() {
     use crate::module::lightweight::add_light_weight_timer;
@@ -141,6 +141,12 @@ pub fn run() {
 		.plugin(tauri_plugin_dialog::init())
 		.plugin(tauri_plugin_shell::init())
 		.plugin(tauri_plugin_deep_link::init())
+		.on_page_load(|webview, payload| {
+			// 重新加载后的页面不再持有之前打开的流
+			if webview.label() == "main" && payload.event() == tauri::webview::PageLoadEvent::Started {
+				module::mihomo::MihomoManager::close_all_streams();
+			}
+		})
 		.setup(|app| {
 			logging!(info, Type::Setup, true, "Starting app initialization...");
 
@@ -278,9 +284,24 @@ pub fn run() {
             // common
             cmd::get_sys_proxy,
             cmd::get_auto_proxy,
+            cmd::get_bypass_presets,
+            cmd::validate_bypass,
+            cmd::check_dns_hijack,
+            cmd::reset_network,
+            cmd::scan_conflicts,
+            cmd::get_lan_share,
+            cmd::set_lan_share,
+            cmd::get_lan_clients,
+            cmd::get_network_services,
+            cmd::get_current_networks,
+            cmd::get_captive_portal_bypass,
+            cmd::set_captive_portal_bypass,
             cmd::open_app_dir,
             cmd::open_logs_dir,
+            cmd::open_scripts_dir,
             cmd::open_web_url,
+            cmd::get_notice_history,
+            cmd::clear_notice_history,
             cmd::open_core_dir,
             cmd::get_portable_flag,
             cmd::get_network_interfaces,
@@ -307,16 +328,42 @@ pub fn run() {
             cmd::reinstall_service,
             cmd::repair_service,
             cmd::is_service_available,
+            cmd::get_service_status,
             // clash
             cmd::get_clash_info,
+            cmd::get_core_diagnosis,
+            cmd::clash_api_request,
+            cmd::clash_api_stream_open,
+            cmd::clash_api_stream_close,
             cmd::patch_clash_config,
             cmd::patch_clash_mode,
+            cmd::set_core_log_level,
             cmd::change_clash_core,
+            cmd::get_installed_cores,
+            cmd::trust_core_binary,
+            cmd::get_geodata_status,
+            cmd::update_geodata,
+            cmd::bench_tun_stacks,
+            cmd::get_tun_bench,
+            cmd::apply_tun_stack,
+            cmd::get_secondary_core_port,
+            cmd::check_core_update,
+            cmd::update_core,
+            cmd::rollback_core,
+            cmd::rotate_clash_secret,
+            cmd::get_core_telemetry,
+            cmd::get_core_health,
+            cmd::get_core_sandbox_status,
             cmd::get_runtime_config,
             cmd::get_runtime_yaml,
+            cmd::export_runtime_config,
             cmd::get_runtime_exists,
             cmd::get_runtime_logs,
+            cmd::get_runtime_merge_sources,
             cmd::invoke_uwp_tool,
+            cmd::get_uwp_apps,
+            cmd::set_uwp_loopback,
+            cmd::exempt_all_uwp_apps,
             cmd::copy_clash_env,
             cmd::get_proxies,
             cmd::force_refresh_proxies,
@@ -344,14 +391,50 @@ pub fn run() {
             cmd::patch_profile,
             cmd::create_profile,
             cmd::import_profile,
+            cmd::import_profiles,
+            cmd::export_profile_bundle,
+            cmd::import_profile_bundle,
             cmd::reorder_profile,
+            cmd::reorder_profile_merges,
+            cmd::create_profile_group,
+            cmd::rename_profile_group,
+            cmd::delete_profile_group,
+            cmd::move_profile_to_group,
+            cmd::vacuum_profiles,
+            cmd::set_profile_tags,
+            cmd::set_profile_sort_index,
             cmd::update_profile,
+            cmd::update_all_profiles,
+            cmd::validate_profile,
+            cmd::find_duplicate_profile,
+            cmd::deduplicate_profiles,
+            cmd::preview_profile_update,
+            cmd::duplicate_profile,
+            cmd::diff_profiles,
+            cmd::search_profiles,
+            cmd::apply_profile_update,
+            cmd::discard_profile_update,
+            cmd::list_profile_revisions,
+            cmd::rollback_profile_revision,
             cmd::delete_profile,
             cmd::read_profile_file,
             cmd::save_profile_file,
             cmd::get_next_update_time,
+            cmd::get_user_agent_presets,
             cmd::update_profiles_on_startup,
             cmd::create_profile_from_share_link,
+            cmd::import_share_links,
+            cmd::import_singbox_config,
+            cmd::import_from_clipboard,
+            cmd::import_profile_dir,
+            cmd::unwatch_profile_dir,
+            cmd::migrate_profile_encryption,
+            cmd::get_profiles_health,
+            cmd::set_profile_auth_password,
+            cmd::get_app_rules,
+            cmd::add_app_rule,
+            cmd::remove_app_rule,
+            cmd::get_running_apps,
             // script validation
             cmd::script_validate_notice,
             cmd::validate_script_file,
@@ -510,6 +593,7 @@ pub fn run() {
                         }
                     }
                     tauri::WindowEvent::Destroyed => {
+                        module::mihomo::MihomoManager::close_all_streams();
                         #[cfg(target_os = "macos")]
                         {
                             logging_error!(
@@ -262,6 +262,7 @@ fn setup_light_weight_timer() -> Result<()> {
         let timer_task = crate::core::timer::TimerTask {
             task_id,
             interval_minutes: once_by_minutes,
+            cron: None,
             last_run: chrono::Local::now().timestamp(),
         };
         timer_map.insert(LIGHT_WEIGHT_TASK_UID.to_string(), timer_task);
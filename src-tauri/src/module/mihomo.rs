@@ -1,13 +1,25 @@
-use crate::config::Config;
+use crate::{config::Config, core::handle, process::AsyncHandler};
 use mihomo_api;
 use once_cell::sync::Lazy;
 use parking_lot::{Mutex, RwLock};
-use std::time::{Duration, Instant};
-use tauri::http::HeaderMap;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
+use tauri::{async_runtime::JoinHandle, http::HeaderMap};
 
 // 缓存的最大有效期（5秒）
 const CACHE_TTL: Duration = Duration::from_secs(5);
 
+/// 流式接口断开后重新打开的间隔，非 WebSocket 的 /connections 只返回一次快照，相当于轮询
+const STREAM_REOPEN: Duration = Duration::from_secs(1);
+
+/// 为前端转发的流式接口
+static STREAMS: Lazy<Mutex<HashMap<u32, JoinHandle<()>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_STREAM_ID: AtomicU32 = AtomicU32::new(1);
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Rate {
     pub up: u64,
@@ -18,6 +30,8 @@ struct MihomoCache {
     manager: mihomo_api::MihomoManager,
     created_at: Instant,
     server: String,
+    /// 轮换 secret 后请求头不同，需要重新创建
+    headers: HeaderMap,
 }
 // 使用RwLock替代Mutex，允许多个读取操作并发进行
 pub struct MihomoManager {
@@ -41,11 +55,12 @@ impl MihomoManager {
         {
             let cache = instance.mihomo_cache.read();
             if let Some(cache_entry) = &*cache {
-                let (current_server, _) = MihomoManager::get_clash_client_info()
-                    .unwrap_or_else(|| (String::new(), HeaderMap::new()));
+                let (current_server, current_headers, _) = MihomoManager::get_clash_client_info()
+                    .unwrap_or_else(|| (String::new(), HeaderMap::new(), None));
 
                 // 检查缓存是否有效
                 if cache_entry.server == current_server
+                    && cache_entry.headers == current_headers
                     && cache_entry.created_at.elapsed() < CACHE_TTL
                 {
                     return cache_entry.manager.clone();
@@ -60,10 +75,11 @@ impl MihomoManager {
         {
             let cache = instance.mihomo_cache.read();
             if let Some(cache_entry) = &*cache {
-                let (current_server, _) = MihomoManager::get_clash_client_info()
-                    .unwrap_or_else(|| (String::new(), HeaderMap::new()));
+                let (current_server, current_headers, _) = MihomoManager::get_clash_client_info()
+                    .unwrap_or_else(|| (String::new(), HeaderMap::new(), None));
 
                 if cache_entry.server == current_server
+                    && cache_entry.headers == current_headers
                     && cache_entry.created_at.elapsed() < CACHE_TTL
                 {
                     return cache_entry.manager.clone();
@@ -72,9 +88,12 @@ impl MihomoManager {
         }
 
         // 创建新实例
-        let (current_server, headers) = MihomoManager::get_clash_client_info()
-            .unwrap_or_else(|| (String::new(), HeaderMap::new()));
-        let manager = mihomo_api::MihomoManager::new(current_server.clone(), headers);
+        let (current_server, headers, ipc) = MihomoManager::get_clash_client_info()
+            .unwrap_or_else(|| (String::new(), HeaderMap::new(), None));
+        let manager = match ipc {
+            Some(ipc) => mihomo_api::MihomoManager::new_ipc(ipc, headers.clone()),
+            None => mihomo_api::MihomoManager::new(current_server.clone(), headers.clone()),
+        };
 
         // 更新缓存
         {
@@ -83,6 +102,7 @@ impl MihomoManager {
                 manager: manager.clone(),
                 created_at: Instant::now(),
                 server: current_server,
+                headers,
             });
         }
 
@@ -91,9 +111,18 @@ impl MihomoManager {
 }
 
 impl MihomoManager {
-    pub fn get_clash_client_info() -> Option<(String, HeaderMap)> {
+    /// 控制接口地址、请求头，以及启用时的 unix socket 或命名管道
+    pub fn get_clash_client_info() -> Option<(String, HeaderMap, Option<String>)> {
         let client = { Config::clash().data().get_client_info() };
-        let server = format!("http://{}", client.server);
+        let client = client
+            .with_session_secret()
+            .with_controller_ipc()
+            .with_external_core();
+        // 套接字不同时缓存的实例也需要重新创建
+        let server = match &client.ipc {
+            Some(ipc) => ipc.clone(),
+            None => format!("http://{}", client.server),
+        };
         let mut headers = HeaderMap::new();
         headers.insert("Content-Type", "application/json".parse().unwrap());
         if let Some(secret) = client.secret {
@@ -101,8 +130,46 @@ impl MihomoManager {
             headers.insert("Authorization", secret);
         }
 
-        Some((server, headers))
+        Some((server, headers, client.ipc))
     }
 
     // 已移除未使用的 get_clash_client_info_or_default 和 get_traffic_ws_url 方法
 }
+
+impl MihomoManager {
+    /// 前端无法直接连接 unix socket 或命名管道，由这里读取流式接口并逐行推送
+    pub fn open_stream(path: String) -> u32 {
+        let id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+        let task = AsyncHandler::spawn(move || async move {
+            loop {
+                match MihomoManager::global().stream(&path).await {
+                    Ok(mut stream) => {
+                        while let Ok(Some(line)) = stream.next_line().await {
+                            handle::Handle::notify_controller_stream(id, Some(line));
+                        }
+                    }
+                    Err(err) => {
+                        log::debug!(target: "app", "failed to open controller stream {path}: {err}");
+                        handle::Handle::notify_controller_stream(id, None);
+                    }
+                }
+                tokio::time::sleep(STREAM_REOPEN).await;
+            }
+        });
+        STREAMS.lock().insert(id, task);
+        id
+    }
+
+    pub fn close_stream(id: u32) {
+        if let Some(task) = STREAMS.lock().remove(&id) {
+            task.abort();
+        }
+    }
+
+    /// 窗口销毁或页面重新加载时，前端没有机会关闭的流
+    pub fn close_all_streams() {
+        for (_, task) in STREAMS.lock().drain() {
+            task.abort();
+        }
+    }
+}
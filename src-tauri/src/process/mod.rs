@@ -1,2 +1,4 @@
 mod async_handler;
+mod task;
 pub use async_handler::AsyncHandler;
+pub use task::{LoopGeneration, StartOnce};
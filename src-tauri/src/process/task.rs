@@ -0,0 +1,63 @@
+use crate::core::handle;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Guards the start of a background task that runs for the whole session
+pub struct StartOnce(AtomicBool);
+
+impl StartOnce {
+    pub const fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+
+    /// Whether this is the first call, only the first call should start the task
+    pub fn first(&self) -> bool {
+        self.0
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+impl Default for StartOnce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generation of a background loop that is started and stopped by the settings
+///
+/// Bumped on every start and stop, an older loop sees it and ends.
+pub struct LoopGeneration(AtomicU64);
+
+impl LoopGeneration {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// Begin a new generation, the loops of older generations end
+    pub fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// End the running loop
+    pub fn stop(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether a loop of `generation` should keep running
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.0.load(Ordering::SeqCst) == generation && !handle::Handle::global().is_exiting()
+    }
+}
+
+impl Default for LoopGeneration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_start_once() {
+    let once = StartOnce::new();
+    assert!(once.first());
+    assert!(!once.first());
+}
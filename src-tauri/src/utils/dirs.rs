@@ -20,6 +20,27 @@ pub static CLASH_CONFIG: &str = "config.yaml";
 pub static VERGE_CONFIG: &str = "verge.yaml";
 pub static PROFILE_YAML: &str = "profiles.yaml";
 
+/// 内核控制接口的 unix socket 或命名管道
+/// socket 放在只有当前用户能访问的运行时目录，命名管道的名称每次启动随机生成，其他用户无法猜到
+pub fn controller_ipc() -> &'static str {
+    static CONTROLLER_IPC: OnceCell<String> = OnceCell::new();
+    CONTROLLER_IPC.get_or_init(|| {
+        if cfg!(windows) {
+            let mut bytes = [0u8; 16];
+            let _ = getrandom::fill(&mut bytes);
+            return format!(r"\\.\pipe\koala-clash-mihomo-{}", hex::encode(bytes));
+        }
+        // Linux 的 $XDG_RUNTIME_DIR 与 macOS 的 $TMPDIR 都是当前用户私有的目录
+        let dir = ::dirs::runtime_dir()
+            .or_else(|| cfg!(target_os = "macos").then(std::env::temp_dir))
+            .or_else(|| app_home_dir().ok())
+            .unwrap_or_else(std::env::temp_dir);
+        dir.join("koala-clash-mihomo.sock")
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
 /// init portable flag
 pub fn init_portable_flag() -> Result<()> {
     use tauri::utils::platform::current_exe;
@@ -138,11 +159,26 @@ pub fn app_profiles_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("profiles"))
 }
 
+/// profile revisions dir
+pub fn app_revisions_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("revisions"))
+}
+
+/// cores installed by the core updater, the install dir of the app is often not writable
+pub fn app_cores_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("cores"))
+}
+
 /// icons dir
 pub fn app_icons_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("icons"))
 }
 
+/// scripts dir, the scripts the tray menu can run
+pub fn app_scripts_dir() -> Result<PathBuf> {
+    Ok(app_home_dir()?.join("scripts"))
+}
+
 pub fn find_target_icons(target: &str) -> Result<Option<String>> {
     let icons_dir = app_icons_dir()?;
     let mut matching_files = Vec::new();
@@ -219,13 +255,102 @@ pub fn path_to_str(path: &PathBuf) -> Result<&str> {
     Ok(path_str)
 }
 
+/// The key encrypting secrets and profiles at rest, kept in the keychain of the OS
+///
+/// Keys from older versions are moved from the `.encryption_key` file into the keychain, the
+/// file is only deleted once the keychain gave the key back. A new key is only made while
+/// none was ever moved there, a keychain failing afterwards is an error rather than a reason
+/// to start over, which would leave everything encrypted so far unreadable.
+/// Portable installs keep the file so the data stays readable on another machine.
 pub fn get_encryption_key() -> Result<Vec<u8>> {
-    let app_dir = app_home_dir()?;
-    let key_path = app_dir.join(".encryption_key");
+    static KEY: OnceCell<Vec<u8>> = OnceCell::new();
+
+    KEY.get_or_try_init(|| {
+        let home = app_home_dir()?;
+        if *PORTABLE_FLAG.get().unwrap_or(&false) {
+            return file_encryption_key(&home.join(KEY_FILE));
+        }
+
+        let entry = keyring::Entry::new(APP_ID, "encryption-key")?;
+        keychain_encryption_key(&home, &entry)
+    })
+    .cloned()
+}
+
+/// the key of portable installs and of older versions
+const KEY_FILE: &str = ".encryption_key";
+
+/// left behind once the key was moved into the keychain
+const KEY_MOVED_FILE: &str = ".encryption_key.moved";
+
+/// The keychain entry holding the key, a trait so the tests can fail it
+trait KeyStore {
+    fn get(&self) -> keyring::Result<Vec<u8>>;
+    fn set(&self, key: &[u8]) -> keyring::Result<()>;
+}
+
+impl KeyStore for keyring::Entry {
+    fn get(&self) -> keyring::Result<Vec<u8>> {
+        self.get_secret()
+    }
+
+    fn set(&self, key: &[u8]) -> keyring::Result<()> {
+        self.set_secret(key)
+    }
+}
+
+fn keychain_encryption_key(home: &std::path::Path, store: &dyn KeyStore) -> Result<Vec<u8>> {
+    let key_path = home.join(KEY_FILE);
+    let moved_path = home.join(KEY_MOVED_FILE);
+
+    match store.get() {
+        Ok(key) => {
+            // 上次保存后未能确认，这次读回成功再删除文件
+            if fs::read(&key_path).is_ok_and(|file_key| file_key == key) {
+                let _ = fs::write(&moved_path, "");
+                let _ = fs::remove_file(&key_path);
+            }
+            return Ok(key);
+        }
+        Err(keyring::Error::NoEntry) => {}
+        // 密钥已在钥匙串中，不能另建一个
+        Err(err) if moved_path.exists() && !key_path.exists() => {
+            anyhow::bail!("failed to read the encryption key from the keychain: {err}");
+        }
+        Err(err) => {
+            log::warn!(target: "app", "keychain unavailable, using the key file: {err}");
+            return file_encryption_key(&key_path);
+        }
+    }
 
+    let key = if key_path.exists() {
+        fs::read(&key_path).map_err(|e| anyhow::anyhow!("Failed to read encryption key: {}", e))?
+    } else if moved_path.exists() {
+        anyhow::bail!("the encryption key is missing from the keychain");
+    } else {
+        // 首次启动，先写入文件，钥匙串确认保存后再删除
+        file_encryption_key(&key_path)?
+    };
+
+    match store.set(&key) {
+        Ok(()) if store.get().is_ok_and(|stored| stored == key) => {
+            fs::write(&moved_path, "")?;
+            let _ = fs::remove_file(&key_path);
+        }
+        Ok(()) => {
+            log::warn!(target: "app", "the keychain didn't return the stored key, keeping the key file");
+        }
+        Err(err) => {
+            log::warn!(target: "app", "failed to store the key in the keychain: {err}");
+        }
+    }
+    Ok(key)
+}
+
+fn file_encryption_key(key_path: &PathBuf) -> Result<Vec<u8>> {
     if key_path.exists() {
         // Read existing key
-        fs::read(&key_path).map_err(|e| anyhow::anyhow!("Failed to read encryption key: {}", e))
+        fs::read(key_path).map_err(|e| anyhow::anyhow!("Failed to read encryption key: {}", e))
     } else {
         // Generate and save new key
         let mut key = vec![0u8; 32];
@@ -237,8 +362,71 @@ pub fn get_encryption_key() -> Result<Vec<u8>> {
                 .map_err(|e| anyhow::anyhow!("Failed to create key directory: {}", e))?;
         }
         // Save key
-        fs::write(&key_path, &key)
+        fs::write(key_path, &key)
             .map_err(|e| anyhow::anyhow!("Failed to save encryption key: {}", e))?;
         Ok(key)
     }
 }
+
+#[test]
+fn test_keychain_encryption_key() {
+    use std::cell::RefCell;
+
+    /// a keychain that fails with `fail` while it is set
+    struct FakeStore {
+        key: RefCell<Option<Vec<u8>>>,
+        fail: RefCell<bool>,
+    }
+
+    impl KeyStore for FakeStore {
+        fn get(&self) -> keyring::Result<Vec<u8>> {
+            if *self.fail.borrow() {
+                return Err(keyring::Error::NoStorageAccess("locked".into()));
+            }
+            self.key.borrow().clone().ok_or(keyring::Error::NoEntry)
+        }
+
+        fn set(&self, key: &[u8]) -> keyring::Result<()> {
+            if *self.fail.borrow() {
+                return Err(keyring::Error::NoStorageAccess("locked".into()));
+            }
+            *self.key.borrow_mut() = Some(key.to_vec());
+            Ok(())
+        }
+    }
+
+    let home = tempfile::tempdir().unwrap();
+    let store = FakeStore {
+        key: RefCell::new(None),
+        fail: RefCell::new(false),
+    };
+
+    // 旧版本的密钥文件移入钥匙串
+    fs::write(home.path().join(KEY_FILE), [7u8; 32]).unwrap();
+    let key = keychain_encryption_key(home.path(), &store).unwrap();
+    assert_eq!(key, vec![7u8; 32]);
+    assert!(!home.path().join(KEY_FILE).exists());
+    assert!(home.path().join(KEY_MOVED_FILE).exists());
+
+    // 钥匙串不可用时报错，不生成新密钥
+    *store.fail.borrow_mut() = true;
+    assert!(keychain_encryption_key(home.path(), &store).is_err());
+    assert!(!home.path().join(KEY_FILE).exists());
+
+    // 钥匙串被清空时同样报错
+    *store.fail.borrow_mut() = false;
+    *store.key.borrow_mut() = None;
+    assert!(keychain_encryption_key(home.path(), &store).is_err());
+    assert!(store.key.borrow().is_none());
+
+    // 首次启动时钥匙串不可用，密钥留在文件中，之后再移入
+    let home = tempfile::tempdir().unwrap();
+    *store.fail.borrow_mut() = true;
+    let key = keychain_encryption_key(home.path(), &store).unwrap();
+    assert_eq!(fs::read(home.path().join(KEY_FILE)).unwrap(), key);
+    assert!(!home.path().join(KEY_MOVED_FILE).exists());
+    *store.fail.borrow_mut() = false;
+    assert_eq!(keychain_encryption_key(home.path(), &store).unwrap(), key);
+    assert_eq!(store.key.borrow().as_deref(), Some(key.as_slice()));
+    assert!(!home.path().join(KEY_FILE).exists());
+}
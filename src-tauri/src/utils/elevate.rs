@@ -0,0 +1,53 @@
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use deelevate::{PrivilegeLevel, Token};
+use std::os::windows::process::CommandExt;
+
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Run a PowerShell script with administrator rights, behind a single UAC
+/// prompt when the app itself is not elevated
+///
+/// The script goes base64 encoded on the command line of the elevated process.
+/// No script file is written that another process could swap before it runs.
+/// Commands stop at the first error, native programs have to check
+/// `$LASTEXITCODE` themselves.
+pub fn run_powershell(script: &str) -> Result<()> {
+    let script = format!("$ErrorActionPreference = 'Stop'\r\n{script}");
+    let utf16 = script
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    let encoded = STANDARD.encode(utf16);
+    let args = [
+        "-NoProfile",
+        "-NonInteractive",
+        "-ExecutionPolicy",
+        "Bypass",
+        "-EncodedCommand",
+        &encoded,
+    ];
+
+    let elevated =
+        Token::with_current_process()?.privilege_level()? != PrivilegeLevel::NotPrivileged;
+    let status = if elevated {
+        std::process::Command::new("powershell")
+            .args(args)
+            .creation_flags(CREATE_NO_WINDOW)
+            .status()?
+    } else {
+        runas::Command::new("powershell")
+            .args(&args)
+            .show(false)
+            .status()?
+    };
+    if !status.success() {
+        bail!("the elevated PowerShell script failed with {status}");
+    }
+    Ok(())
+}
+
+/// Quote a value for a single quoted PowerShell string
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
@@ -1,6 +1,8 @@
-use crate::{enhance::seq::SeqMap, logging, utils::logging::Type};
+use crate::{config::read_profile_file, enhance::seq::SeqMap, logging, utils::logging::Type};
 use anyhow::{anyhow, bail, Context, Result};
 use nanoid::nanoid;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_yaml::Mapping;
 use std::{fs, path::PathBuf, str::FromStr};
@@ -11,7 +13,7 @@ pub fn read_yaml<T: DeserializeOwned>(path: &PathBuf) -> Result<T> {
         bail!("file not found \"{}\"", path.display());
     }
 
-    let yaml_str = fs::read_to_string(path)
+    let yaml_str = read_profile_file(path)
         .with_context(|| format!("failed to read the file \"{}\"", path.display()))?;
 
     serde_yaml::from_str::<T>(&yaml_str).with_context(|| {
@@ -28,7 +30,7 @@ pub fn read_mapping(path: &PathBuf) -> Result<Mapping> {
         bail!("file not found \"{}\"", path.display());
     }
 
-    let yaml_str = fs::read_to_string(path)
+    let yaml_str = read_profile_file(path)
         .with_context(|| format!("failed to read the file \"{}\"", path.display()))?;
 
     // YAML语法检查
@@ -163,3 +165,24 @@ macro_rules! t {
         }
     };
 }
+
+/// Replace the urls in a message with their origin
+/// subscription urls carry the access token in the userinfo, path or query
+pub fn redact_urls(message: &str) -> String {
+    static URL_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"[a-zA-Z][a-zA-Z0-9+.-]*://[^\s"'<>()]+"#).unwrap());
+
+    URL_RE
+        .replace_all(
+            message,
+            |caps: &regex::Captures| match reqwest::Url::parse(&caps[0]) {
+                Ok(url) => {
+                    let port = url.port().map(|p| format!(":{p}")).unwrap_or_default();
+                    let host = url.host_str().unwrap_or_default();
+                    format!("{}://{host}{port}/***", url.scheme())
+                }
+                Err(_) => "***".into(),
+            },
+        )
+        .into_owned()
+}
@@ -4,7 +4,8 @@ use serde_json::Value;
 use std::{collections::HashMap, fs, path::PathBuf};
 use sys_locale;
 
-const DEFAULT_LANGUAGE: &str = "zh";
+// 缺少翻译时回退到英文，英文的翻译是完整的
+const DEFAULT_LANGUAGE: &str = "en";
 
 fn get_locales_dir() -> Option<PathBuf> {
     dirs::app_resources_dir()
@@ -85,3 +86,50 @@ pub fn t(key: &str) -> String {
 
     key.to_string()
 }
+
+#[test]
+fn test_backend_keys_translated() {
+    use std::path::Path;
+
+    // 收集源码中以字面量调用 t 的键，托盘与系统通知用到的文字都在其中
+    fn collect_keys(dir: &Path, keys: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_keys(&path, keys);
+                continue;
+            }
+            if !path.extension().is_some_and(|ext| ext == "rs") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            for (index, _) in source.match_indices("t(\"") {
+                let prefix = source[..index].chars().next_back();
+                if prefix.is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ':')) {
+                    continue;
+                }
+                let rest = &source[index + 3..];
+                if let Some(end) = rest.find("\")") {
+                    keys.push(rest[..end].to_string());
+                }
+            }
+        }
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut keys = Vec::new();
+    collect_keys(&root.join("src"), &mut keys);
+    assert!(keys.iter().any(|key| key == "Rule Mode"));
+
+    for entry in fs::read_dir(root.join("../src/locales")).unwrap().flatten() {
+        let content = fs::read_to_string(entry.path()).unwrap();
+        let translations: Value = serde_json::from_str(&content).unwrap();
+        for key in &keys {
+            assert!(
+                translations.get(key).is_some(),
+                "{} has no translation for \"{key}\"",
+                entry.file_name().to_string_lossy()
+            );
+        }
+    }
+}
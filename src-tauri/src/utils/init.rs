@@ -273,6 +273,12 @@ pub fn init_config() -> Result<()> {
         }
     }));
 
+    crate::log_err!(dirs::app_scripts_dir().map(|scripts_dir| {
+        if !scripts_dir.exists() {
+            let _ = fs::create_dir_all(&scripts_dir);
+        }
+    }));
+
     crate::log_err!(dirs::clash_path().map(|path| {
         if !path.exists() {
             help::save_yaml(&path, &IClashTemp::template().0, Some("# Koala Clash"))?;
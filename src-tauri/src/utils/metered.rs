@@ -0,0 +1,121 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// the detection spawns a process, so the result is reused for a while
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+static CACHE: Lazy<Mutex<Option<(Instant, bool)>>> = Lazy::new(|| Mutex::new(None));
+
+/// set while the GeoData auto update of the core is turned off for a metered network
+static GEO_AUTO_UPDATE_DEFERRED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the active connection is metered
+/// Unknown states and unsupported platforms count as not metered
+pub async fn is_metered() -> bool {
+    if let Some((checked, metered)) = *CACHE.lock() {
+        if checked.elapsed() < CACHE_TTL {
+            return metered;
+        }
+    }
+
+    // 检测会启动子进程，放到阻塞线程中执行，也不持有锁
+    let metered = tokio::task::spawn_blocking(detect)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    *CACHE.lock() = Some((Instant::now(), metered));
+    metered
+}
+
+/// Whether updates should wait because `skip_updates_on_metered` is on and the network is metered
+pub async fn should_defer_updates() -> bool {
+    let enabled = crate::config::Config::verge()
+        .latest()
+        .skip_updates_on_metered
+        .unwrap_or(false);
+    enabled && is_metered().await
+}
+
+/// Remember that the config turned the GeoData auto update of the core off
+pub fn defer_geo_auto_update() {
+    GEO_AUTO_UPDATE_DEFERRED.store(true, Ordering::SeqCst);
+}
+
+/// Whether the GeoData auto update was turned off, clearing the flag
+pub fn take_deferred_geo_auto_update() -> bool {
+    GEO_AUTO_UPDATE_DEFERRED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(target_os = "windows")]
+fn detect() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+
+    let script = "[void][Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]; \
+        $p = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+        if ($p) { $p.GetConnectionCost().NetworkCostType }";
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", script])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .ok()?;
+    parse_windows_cost(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> Option<bool> {
+    let output = std::process::Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_nm_metered(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn detect() -> Option<bool> {
+    None
+}
+
+/// `NetworkCostType`: Unknown, Unrestricted, Fixed or Variable
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_windows_cost(output: &str) -> Option<bool> {
+    match output.trim() {
+        "Fixed" | "Variable" => Some(true),
+        "Unrestricted" => Some(false),
+        _ => None,
+    }
+}
+
+/// NetworkManager `NMMetered` as printed by busctl, e.g. `u 1`
+/// 1 yes, 2 no, 3 guess-yes, 4 guess-no
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_nm_metered(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("u ")?.trim() {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_metered() {
+    assert_eq!(parse_windows_cost("Fixed\r\n"), Some(true));
+    assert_eq!(parse_windows_cost("Unrestricted"), Some(false));
+    assert_eq!(parse_windows_cost(""), None);
+    assert_eq!(parse_nm_metered("u 3\n"), Some(true));
+    assert_eq!(parse_nm_metered("u 4"), Some(false));
+    assert_eq!(parse_nm_metered("u 0"), None);
+}
@@ -1,13 +1,19 @@
 pub mod autostart;
 pub mod dirs;
+#[cfg(windows)]
+pub mod elevate;
 pub mod help;
 pub mod i18n;
 pub mod init;
 pub mod logging;
+pub mod metered;
 pub mod network;
 pub mod notification;
 pub mod resolve;
 pub mod server;
+pub mod share_link;
+pub mod shutdown;
+pub mod singbox;
 pub mod sys_info;
 pub mod tmpl;
 pub mod window_manager;
@@ -1,7 +1,11 @@
 use anyhow::Result;
 use lazy_static::lazy_static;
-use reqwest::{Client, ClientBuilder, Proxy, RequestBuilder, Response};
+use reqwest::{
+    header::CONTENT_TYPE, Client, ClientBuilder, Method, Proxy, RequestBuilder, Response,
+    StatusCode,
+};
 use std::{
+    collections::BTreeMap,
     sync::{Arc, Mutex, Once},
     time::{Duration, Instant},
 };
@@ -140,12 +144,18 @@ impl NetworkManager {
            let mut client_guard = self.self_proxy_client.lock().unwrap();
 
            if client_guard.is_none() {
-               let port = Config::verge()
-                   .latest()
-                   .verge_mixed_port
-                   .unwrap_or(Config::clash().data().get_mixed_port());
-
-               let proxy_scheme = format!("http://127.0.0.1:{port}");
+               let (port, userinfo) = {
+                   let verge = Config::verge();
+                   let verge = verge.latest();
+                   (
+                       verge
+                           .verge_mixed_port
+                           .unwrap_or(Config::clash().data().get_mixed_port()),
+                       verge.proxy_userinfo(),
+                   )
+               };
+
+               let proxy_scheme = format!("http://{userinfo}127.0.0.1:{port}");
 
                let mut builder = ClientBuilder::new()
                    .use_rustls_tls()
@@ -241,6 +251,7 @@ impl NetworkManager {
        }
     */
     /// 创建带有自定义选项的HTTP请求
+    #[allow(clippy::too_many_arguments)]
     pub fn create_request(
         &self,
         url: &str,
@@ -249,7 +260,8 @@ impl NetworkManager {
         user_agent: Option<String>,
         accept_invalid_certs: bool,
         use_hwid: bool,
-    ) -> RequestBuilder {
+        headers: Option<&BTreeMap<String, String>>,
+    ) -> Result<RequestBuilder> {
         if self.should_reset_clients() {
             self.reset_clients();
         }
@@ -279,12 +291,18 @@ impl NetworkManager {
                 builder = builder.no_proxy();
             }
             ProxyType::Localhost => {
-                let port = Config::verge()
-                    .latest()
-                    .verge_mixed_port
-                    .unwrap_or(Config::clash().data().get_mixed_port());
-
-                let proxy_scheme = format!("http://127.0.0.1:{port}");
+                let (port, userinfo) = {
+                    let verge = Config::verge();
+                    let verge = verge.latest();
+                    (
+                        verge
+                            .verge_mixed_port
+                            .unwrap_or(Config::clash().data().get_mixed_port()),
+                        verge.proxy_userinfo(),
+                    )
+                };
+
+                let proxy_scheme = format!("http://{userinfo}127.0.0.1:{port}");
 
                 if let Ok(proxy) = Proxy::http(&proxy_scheme) {
                     builder = builder.proxy(proxy);
@@ -296,11 +314,32 @@ impl NetworkManager {
                     builder = builder.proxy(proxy);
                 }
             }
+            ProxyType::Custom(proxy_url) => {
+                // 不能悄悄改为直连，错误信息中也不能带上可能含有密码的地址
+                let proxy = Proxy::all(proxy_url.as_str())
+                    .map_err(|_| anyhow::anyhow!("invalid proxy url"))?;
+                builder = builder.proxy(proxy);
+            }
             ProxyType::System => {
                 use sysproxy::Sysproxy;
 
                 if let Ok(p @ Sysproxy { enable: true, .. }) = Sysproxy::get_system_proxy() {
-                    let proxy_scheme = format!("http://{}:{}", p.host, p.port);
+                    // 系统代理指向自己的端口时带上认证信息，其他代理不能拿到密码
+                    let userinfo = {
+                        let verge = Config::verge();
+                        let verge = verge.latest();
+                        let own_port = verge
+                            .verge_mixed_port
+                            .unwrap_or(Config::clash().data().get_mixed_port());
+                        let local = matches!(p.host.as_str(), "127.0.0.1" | "localhost" | "::1")
+                            || p.host == verge.proxy_host.as_deref().unwrap_or("127.0.0.1");
+                        if local && p.port == own_port {
+                            verge.proxy_userinfo()
+                        } else {
+                            String::new()
+                        }
+                    };
+                    let proxy_scheme = format!("http://{userinfo}{}:{}", p.host, p.port);
 
                     if let Ok(proxy) = Proxy::http(&proxy_scheme) {
                         builder = builder.proxy(proxy);
@@ -344,7 +383,14 @@ impl NetworkManager {
                 .header("x-device-model", &sys_info.device_model);
         }
 
-        request_builder
+        // 用户自定义请求头，放在最后以便覆盖默认值
+        if let Some(headers) = headers {
+            for (name, value) in headers {
+                request_builder = request_builder.header(name.as_str(), value.as_str());
+            }
+        }
+
+        Ok(request_builder)
     }
 
     /*     /// 执行GET请求，添加错误跟踪
@@ -384,6 +430,7 @@ impl NetworkManager {
         }
     } */
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_with_interrupt(
         &self,
         url: &str,
@@ -392,6 +439,7 @@ impl NetworkManager {
         user_agent: Option<String>,
         accept_invalid_certs: bool,
         use_hwid: bool,
+        headers: Option<&BTreeMap<String, String>>,
     ) -> Result<Response> {
         let request = self.create_request(
             url,
@@ -400,7 +448,8 @@ impl NetworkManager {
             user_agent,
             accept_invalid_certs,
             use_hwid,
-        );
+            headers,
+        )?;
 
         let timeout_duration = timeout_secs.unwrap_or(20);
 
@@ -430,12 +479,84 @@ impl NetworkManager {
             }
         }
     }
+
+    /// 发送HEAD请求，只返回状态码，用于探测地址是否可用
+    #[allow(clippy::too_many_arguments)]
+    pub async fn head(
+        &self,
+        url: &str,
+        proxy_type: ProxyType,
+        timeout_secs: Option<u64>,
+        user_agent: Option<String>,
+        accept_invalid_certs: bool,
+        use_hwid: bool,
+        headers: Option<&BTreeMap<String, String>>,
+    ) -> Result<StatusCode> {
+        let (client, request) = self
+            .create_request(
+                url,
+                proxy_type,
+                timeout_secs,
+                user_agent,
+                accept_invalid_certs,
+                use_hwid,
+                headers,
+            )?
+            .build_split();
+        let mut request = request?;
+        *request.method_mut() = Method::HEAD;
+
+        match client.execute(request).await {
+            Ok(response) => Ok(response.status()),
+            Err(e) => {
+                self.record_connection_error(&e.to_string());
+                Err(anyhow::anyhow!("Failed to send HTTP request: {}", e))
+            }
+        }
+    }
+
+    /// 发送JSON格式的POST请求，用于订阅的登录接口
+    pub async fn post_json(
+        &self,
+        url: &str,
+        proxy_type: ProxyType,
+        timeout_secs: Option<u64>,
+        user_agent: Option<String>,
+        accept_invalid_certs: bool,
+        body: &serde_json::Value,
+    ) -> Result<Response> {
+        let (client, request) = self
+            .create_request(
+                url,
+                proxy_type,
+                timeout_secs,
+                user_agent,
+                accept_invalid_certs,
+                false,
+                None,
+            )?
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(body)?)
+            .build_split();
+        let mut request = request?;
+        *request.method_mut() = Method::POST;
+
+        match client.execute(request).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                self.record_connection_error(&e.to_string());
+                Err(anyhow::anyhow!("Failed to send HTTP request: {}", e))
+            }
+        }
+    }
 }
 
 /// 代理类型
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProxyType {
     None,
     Localhost,
     System,
+    /// 指定的代理地址，支持 http/https/socks5/socks5h，可带认证信息
+    Custom(String),
 }
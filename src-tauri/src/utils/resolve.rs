@@ -1,7 +1,7 @@
 #[cfg(target_os = "macos")]
 use crate::AppHandleManager;
 use crate::{
-    config::{Config, IVerge, PrfItem},
+    config::{Config, IClashTemp, IVerge, PrfItem},
     core::*,
     core::handle::Handle,
     logging, logging_error,
@@ -268,6 +268,9 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
         );
     }
 
+    // 每次启动生成新的控制接口 secret，保持固定 secret 时不会使用
+    logging_error!(Type::Config, true, IClashTemp::rotate_secret());
+    IClashTemp::commit_secret();
     logging!(trace, Type::Config, true, "Initializing configuration...");
     logging_error!(Type::Config, true, Config::init_config().await);
 
@@ -332,6 +335,49 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
     // 初始化定时器
     logging_error!(Type::System, true, timer::Timer::global().init());
 
+    // 监听本地订阅文件的修改
+    logging_error!(Type::Config, true, ProfileWatcher::global().init());
+
+    // 网络恢复后更新当前订阅
+    logging_error!(Type::Network, true, NetworkWatcher::global().init());
+
+    // 系统从休眠中唤醒后检查内核
+    #[cfg(not(windows))]
+    resume::listen();
+
+    // 其他程序修改系统代理后立即恢复
+    logging_error!(Type::System, true, ProxyWatcher::global().init());
+
+    // 提示可能冲突的代理、VPN 软件
+    AsyncHandler::spawn(|| conflicts::warn("at startup"));
+
+    // 服务握手失败或版本不符时提示修复
+    AsyncHandler::spawn(service::verify_at_startup);
+
+    // 恢复上次异常退出时关闭的 IPv6，或在 TUN 开启时关闭
+    AsyncHandler::spawn(ipv6_guard::update);
+
+    // 共享代理时记录局域网设备
+    AsyncHandler::spawn(lan_share::watch_clients);
+
+    // 托盘显示实时网速
+    tray::speed_rate::update();
+
+    // 托盘菜单中的代理组
+    tray::proxy_menu::update();
+
+    // 采样内核资源占用
+    logging_error!(Type::Core, true, CoreTelemetry::global().init());
+
+    // 定时检查内核控制接口
+    logging_error!(Type::Core, true, CoreHealth::global().init());
+
+    // 定时更新 GeoIP/GeoSite 数据库
+    logging_error!(Type::Core, true, GeoDataManager::global().init());
+
+    // 系统注销、关机时恢复系统代理并停止内核
+    crate::utils::shutdown::register();
+
     // 自动进入轻量模式
     auto_lightweight_mode_init();
 
@@ -363,9 +409,7 @@ pub async fn resolve_setup_async(app_handle: &AppHandle) {
 
 /// reset system proxy (异步)
 pub async fn resolve_reset_async() {
-    #[cfg(target_os = "macos")]
     logging!(info, Type::Tray, true, "Unsubscribing from traffic updates");
-    #[cfg(target_os = "macos")]
     tray::Tray::global().unsubscribe_traffic();
 
     logging_error!(
@@ -374,6 +418,7 @@ pub async fn resolve_reset_async() {
         sysopt::Sysopt::global().reset_sysproxy().await
     );
     logging_error!(Type::Core, true, CoreManager::global().stop_core().await);
+    logging_error!(Type::Network, true, ipv6_guard::restore().await);
     #[cfg(target_os = "macos")]
     {
         logging!(info, Type::System, true, "Restoring system DNS settings");
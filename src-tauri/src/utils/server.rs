@@ -2,10 +2,7 @@ extern crate warp;
 
 use super::resolve;
 use crate::{
-    config::{Config, IVerge, DEFAULT_PAC},
-    logging_error,
-    process::AsyncHandler,
-    utils::logging::Type,
+    config::IVerge, core::pac, logging_error, process::AsyncHandler, utils::logging::Type,
 };
 use anyhow::Result;
 use std::convert::Infallible;
@@ -16,8 +13,8 @@ struct QueryParam {
     param: String,
 }
 
-/// The embed server only be used to implement singleton process
-/// maybe it can be used as pac server later
+/// The embed server is used to implement singleton process
+/// and to serve the pac script
 pub fn embed_server() {
     let port = IVerge::get_singleton_port();
 
@@ -28,19 +25,9 @@ pub fn embed_server() {
         });
 
         let pac = warp::path!("commands" / "pac").map(move || {
-            let content = Config::verge()
-                .latest()
-                .pac_file_content
-                .clone()
-                .unwrap_or(DEFAULT_PAC.to_string());
-            let port = Config::verge()
-                .latest()
-                .verge_mixed_port
-                .unwrap_or(Config::clash().data().get_mixed_port());
-            let content = content.replace("%mixed-port%", &format!("{port}"));
             warp::http::Response::builder()
                 .header("Content-Type", "application/x-ns-proxy-autoconfig")
-                .body(content)
+                .body(pac::script())
                 .unwrap_or_default()
         });
         async fn scheme_handler(query: QueryParam) -> Result<impl warp::Reply, Infallible> {
@@ -0,0 +1,675 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{
+    engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Result of converting a list of share links
+#[derive(Debug, Default)]
+pub struct ShareLinkImport {
+    /// clash proxies, names are unique
+    pub proxies: Vec<Mapping>,
+    /// entries that could not be converted, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Convert pasted share links into clash proxies
+///
+/// The input is either one link per line (or separated by whitespace), or a
+/// base64 blob of such a list, as served by most subscription endpoints.
+pub fn parse_share_links(input: &str) -> ShareLinkImport {
+    let input = input.trim();
+    let text = if input.contains("://") {
+        input.to_string()
+    } else {
+        decode_base64(input).unwrap_or_else(|| input.to_string())
+    };
+
+    let mut result = ShareLinkImport::default();
+    let mut names = HashSet::new();
+    for link in text.split_whitespace() {
+        match parse_share_link(link) {
+            Ok(mut proxy) => {
+                let name = proxy
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("proxy")
+                    .to_string();
+                proxy.insert("name".into(), unique_name(&mut names, &name).into());
+                result.proxies.push(proxy);
+            }
+            Err(err) => result.skipped.push(format!("{}: {err}", truncate(link))),
+        }
+    }
+    result
+}
+
+/// Kind of pasted text, used to pick the importer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// a single subscription url
+    Url,
+    /// one subscription url per line
+    Urls,
+    /// share links, plain or base64 encoded
+    ShareLinks,
+    ClashYaml,
+    SingBox,
+    Unknown,
+}
+
+/// Guess what the text is, a base64 blob is decoded before looking at it
+pub fn detect_import_format(input: &str) -> ImportFormat {
+    let input = input.trim().trim_start_matches('\u{feff}');
+    if input.is_empty() {
+        return ImportFormat::Unknown;
+    }
+
+    let lines: Vec<&str> = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let is_http = |line: &str| {
+        Url::parse(line).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+            && !line.contains(char::is_whitespace)
+    };
+    if lines.iter().all(|line| is_http(line)) {
+        return if lines.len() == 1 {
+            ImportFormat::Url
+        } else {
+            ImportFormat::Urls
+        };
+    }
+
+    if input.starts_with('{') {
+        let is_singbox = serde_json::from_str::<serde_json::Value>(input)
+            .is_ok_and(|json| json.get("outbounds").is_some_and(|o| o.is_array()));
+        if is_singbox {
+            return ImportFormat::SingBox;
+        }
+    }
+    if let Ok(config) = serde_yaml::from_str::<Mapping>(input) {
+        if ["proxies", "proxy-providers", "proxy-groups", "rules"]
+            .iter()
+            .any(|key| config.contains_key(*key))
+        {
+            return ImportFormat::ClashYaml;
+        }
+    }
+
+    let text = if input.contains("://") {
+        Some(input.to_string())
+    } else {
+        decode_base64(input)
+    };
+    match text {
+        Some(text)
+            if text
+                .split_whitespace()
+                .any(|link| parse_share_link(link).is_ok()) =>
+        {
+            ImportFormat::ShareLinks
+        }
+        _ => ImportFormat::Unknown,
+    }
+}
+
+/// Build a minimal profile with a single `PROXY` group selecting every proxy
+pub fn build_share_link_profile(proxies: &[Mapping]) -> Mapping {
+    let members: Sequence = proxies
+        .iter()
+        .filter_map(|p| p.get("name").cloned())
+        .collect();
+
+    let mut group = Mapping::new();
+    group.insert("name".into(), "PROXY".into());
+    group.insert("type".into(), "select".into());
+    group.insert("proxies".into(), Value::Sequence(members));
+
+    let mut config = Mapping::new();
+    config.insert(
+        "proxies".into(),
+        Value::Sequence(proxies.iter().cloned().map(Value::Mapping).collect()),
+    );
+    config.insert(
+        "proxy-groups".into(),
+        Value::Sequence(vec![Value::Mapping(group)]),
+    );
+    config.insert("rules".into(), Value::Sequence(vec!["MATCH,PROXY".into()]));
+    config
+}
+
+/// Convert a single share link into a clash proxy
+pub fn parse_share_link(link: &str) -> Result<Mapping> {
+    let (scheme, _) = link
+        .split_once("://")
+        .ok_or_else(|| anyhow!("not a share link"))?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "ss" => parse_ss(link),
+        "vmess" => parse_vmess(link),
+        "vless" => parse_vless(link),
+        "trojan" => parse_trojan(link),
+        "hysteria2" | "hy2" => parse_hysteria2(link),
+        other => bail!("unsupported scheme \"{other}\""),
+    }
+}
+
+fn parse_ss(link: &str) -> Result<Mapping> {
+    let body = &link["ss://".len()..];
+    let (body, fragment) = split_fragment(body);
+
+    // SIP002: ss://base64(method:password)@host:port/?plugin=...
+    // legacy: ss://base64(method:password@host:port)
+    let url = if body.contains('@') {
+        Url::parse(&format!("ss://{body}")).context("invalid ss link")?
+    } else {
+        let decoded =
+            decode_base64(body.trim_end_matches('/')).ok_or_else(|| anyhow!("invalid ss link"))?;
+        Url::parse(&format!("ss://{decoded}")).context("invalid ss link")?
+    };
+
+    let userinfo = decode(url.username());
+    let userinfo = match url.password() {
+        // plain `method:password`, used by 2022 ciphers
+        Some(password) => format!("{userinfo}:{}", decode(password)),
+        None => decode_base64(&userinfo).unwrap_or(userinfo),
+    };
+    let (cipher, password) = userinfo
+        .split_once(':')
+        .ok_or_else(|| anyhow!("missing cipher or password"))?;
+
+    let mut proxy = base_proxy(&url, fragment, "ss")?;
+    proxy.insert("cipher".into(), cipher.into());
+    proxy.insert("password".into(), password.into());
+
+    let query = query_map(&url);
+    if let Some(plugin) = query.get("plugin") {
+        let mut parts = plugin.split(';');
+        let name = parts.next().unwrap_or_default();
+        let mut opts = Mapping::new();
+        for part in parts {
+            let (key, value) = part.split_once('=').unwrap_or((part, "true"));
+            match (name, key) {
+                ("obfs-local" | "simple-obfs", "obfs") => {
+                    opts.insert("mode".into(), value.into());
+                }
+                ("obfs-local" | "simple-obfs", "obfs-host") => {
+                    opts.insert("host".into(), value.into());
+                }
+                (_, "tls") => {
+                    opts.insert("tls".into(), true.into());
+                }
+                (_, "mode" | "host" | "path") => {
+                    opts.insert(key.into(), value.into());
+                }
+                _ => {}
+            }
+        }
+        match name {
+            "obfs-local" | "simple-obfs" => {
+                proxy.insert("plugin".into(), "obfs".into());
+            }
+            "v2ray-plugin" => {
+                proxy.insert("plugin".into(), "v2ray-plugin".into());
+                if !opts.contains_key("mode") {
+                    opts.insert("mode".into(), "websocket".into());
+                }
+            }
+            other => bail!("unsupported ss plugin \"{other}\""),
+        }
+        proxy.insert("plugin-opts".into(), Value::Mapping(opts));
+    }
+
+    Ok(proxy)
+}
+
+fn parse_vmess(link: &str) -> Result<Mapping> {
+    let body = &link["vmess://".len()..];
+    let (body, _) = split_fragment(body);
+    let json = decode_base64(body).ok_or_else(|| anyhow!("invalid vmess link"))?;
+    let params: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&json).context("invalid vmess json")?;
+
+    let get = |key: &str| -> Option<String> {
+        match params.get(key)? {
+            serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    };
+
+    let server = get("add").ok_or_else(|| anyhow!("missing server"))?;
+    let port: u16 = get("port")
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| anyhow!("missing or invalid port"))?;
+    let uuid = get("id").ok_or_else(|| anyhow!("missing uuid"))?;
+
+    let mut proxy = Mapping::new();
+    proxy.insert(
+        "name".into(),
+        get("ps")
+            .unwrap_or_else(|| format!("{server}:{port}"))
+            .into(),
+    );
+    proxy.insert("type".into(), "vmess".into());
+    proxy.insert("server".into(), server.into());
+    proxy.insert("port".into(), port.into());
+    proxy.insert("uuid".into(), uuid.into());
+    proxy.insert(
+        "alterId".into(),
+        get("aid")
+            .and_then(|a| a.parse::<u32>().ok())
+            .unwrap_or(0)
+            .into(),
+    );
+    proxy.insert(
+        "cipher".into(),
+        get("scy").unwrap_or_else(|| "auto".into()).into(),
+    );
+    proxy.insert("udp".into(), true.into());
+
+    if get("tls").as_deref() == Some("tls") {
+        proxy.insert("tls".into(), true.into());
+        if let Some(sni) = get("sni") {
+            proxy.insert("servername".into(), sni.into());
+        }
+        if let Some(fp) = get("fp") {
+            proxy.insert("client-fingerprint".into(), fp.into());
+        }
+        if let Some(alpn) = get("alpn") {
+            proxy.insert("alpn".into(), split_list(&alpn));
+        }
+    }
+
+    let network = get("net").unwrap_or_else(|| "tcp".into());
+    let host = get("host");
+    let path = get("path");
+    // `type` is the header type for tcp, and the service name is carried in `path` for grpc
+    let header_type = get("type");
+    insert_transport(
+        &mut proxy,
+        &network,
+        host.as_deref(),
+        path.as_deref(),
+        path.as_deref(),
+        header_type.as_deref(),
+    );
+
+    Ok(proxy)
+}
+
+fn parse_vless(link: &str) -> Result<Mapping> {
+    let url = Url::parse(link).context("invalid vless link")?;
+    let uuid = decode(url.username());
+    if uuid.is_empty() {
+        bail!("missing uuid");
+    }
+
+    let mut proxy = base_proxy(&url, url.fragment(), "vless")?;
+    proxy.insert("uuid".into(), uuid.into());
+
+    let query = query_map(&url);
+    if let Some(flow) = query.get("flow").filter(|f| !f.is_empty()) {
+        proxy.insert("flow".into(), flow.as_str().into());
+    }
+    insert_security(&mut proxy, &query);
+    insert_query_transport(&mut proxy, &query);
+
+    Ok(proxy)
+}
+
+fn parse_trojan(link: &str) -> Result<Mapping> {
+    let url = Url::parse(link).context("invalid trojan link")?;
+    let password = decode(url.username());
+    if password.is_empty() {
+        bail!("missing password");
+    }
+
+    let mut proxy = base_proxy(&url, url.fragment(), "trojan")?;
+    proxy.insert("password".into(), password.into());
+
+    let query = query_map(&url);
+    // trojan always uses tls, `sni` is the only setting that matters
+    if let Some(sni) = query.get("sni").or_else(|| query.get("peer")) {
+        proxy.insert("sni".into(), sni.as_str().into());
+    }
+    if let Some(fp) = query.get("fp") {
+        proxy.insert("client-fingerprint".into(), fp.as_str().into());
+    }
+    if let Some(alpn) = query.get("alpn") {
+        proxy.insert("alpn".into(), split_list(alpn));
+    }
+    if is_truthy(query.get("allowInsecure")) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    insert_query_transport(&mut proxy, &query);
+
+    Ok(proxy)
+}
+
+fn parse_hysteria2(link: &str) -> Result<Mapping> {
+    let url = Url::parse(link).context("invalid hysteria2 link")?;
+    let password = match url.password() {
+        Some(password) => format!("{}:{}", decode(url.username()), decode(password)),
+        None => decode(url.username()),
+    };
+
+    let mut proxy = base_proxy(&url, url.fragment(), "hysteria2")?;
+    proxy.insert("password".into(), password.into());
+
+    let query = query_map(&url);
+    if let Some(sni) = query.get("sni") {
+        proxy.insert("sni".into(), sni.as_str().into());
+    }
+    if let Some(obfs) = query.get("obfs").filter(|o| o.as_str() != "none") {
+        proxy.insert("obfs".into(), obfs.as_str().into());
+        if let Some(obfs_password) = query.get("obfs-password") {
+            proxy.insert("obfs-password".into(), obfs_password.as_str().into());
+        }
+    }
+    if let Some(ports) = query.get("mport") {
+        proxy.insert("ports".into(), ports.as_str().into());
+    }
+    if is_truthy(query.get("insecure")) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+
+    Ok(proxy)
+}
+
+/// name, type, server, port and udp shared by the url based links
+fn base_proxy(url: &Url, fragment: Option<&str>, proxy_type: &str) -> Result<Mapping> {
+    let server = url
+        .host_str()
+        .map(|h| h.trim_start_matches('[').trim_end_matches(']').to_string())
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| anyhow!("missing server"))?;
+    let port = url.port().ok_or_else(|| anyhow!("missing port"))?;
+    let name = fragment
+        .map(decode)
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| format!("{server}:{port}"));
+
+    let mut proxy = Mapping::new();
+    proxy.insert("name".into(), name.into());
+    proxy.insert("type".into(), proxy_type.into());
+    proxy.insert("server".into(), server.into());
+    proxy.insert("port".into(), port.into());
+    proxy.insert("udp".into(), true.into());
+    Ok(proxy)
+}
+
+/// `security=tls|reality` of vless links
+fn insert_security(proxy: &mut Mapping, query: &HashMap<String, String>) {
+    let security = query.get("security").map(String::as_str).unwrap_or("none");
+    if !matches!(security, "tls" | "reality") {
+        return;
+    }
+
+    proxy.insert("tls".into(), true.into());
+    if let Some(sni) = query.get("sni") {
+        proxy.insert("servername".into(), sni.as_str().into());
+    }
+    if let Some(fp) = query.get("fp") {
+        proxy.insert("client-fingerprint".into(), fp.as_str().into());
+    }
+    if let Some(alpn) = query.get("alpn") {
+        proxy.insert("alpn".into(), split_list(alpn));
+    }
+    if is_truthy(query.get("allowInsecure")) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+
+    if security == "reality" {
+        let mut opts = Mapping::new();
+        if let Some(pbk) = query.get("pbk") {
+            opts.insert("public-key".into(), pbk.as_str().into());
+        }
+        if let Some(sid) = query.get("sid") {
+            opts.insert("short-id".into(), sid.as_str().into());
+        }
+        proxy.insert("reality-opts".into(), Value::Mapping(opts));
+    }
+}
+
+/// `type=ws|grpc|h2|http` of vless and trojan links
+fn insert_query_transport(proxy: &mut Mapping, query: &HashMap<String, String>) {
+    let network = query.get("type").map(String::as_str).unwrap_or("tcp");
+    insert_transport(
+        proxy,
+        network,
+        query.get("host").map(String::as_str),
+        query.get("path").map(String::as_str),
+        query.get("serviceName").map(String::as_str),
+        query.get("headerType").map(String::as_str),
+    );
+}
+
+fn insert_transport(
+    proxy: &mut Mapping,
+    network: &str,
+    host: Option<&str>,
+    path: Option<&str>,
+    service_name: Option<&str>,
+    header_type: Option<&str>,
+) {
+    match network {
+        "ws" | "httpupgrade" => {
+            let mut opts = Mapping::new();
+            if let Some(path) = path {
+                opts.insert("path".into(), path.into());
+            }
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), host.into());
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            if network == "httpupgrade" {
+                opts.insert("v2ray-http-upgrade".into(), true.into());
+            }
+            proxy.insert("network".into(), "ws".into());
+            proxy.insert("ws-opts".into(), Value::Mapping(opts));
+        }
+        "grpc" => {
+            let mut opts = Mapping::new();
+            if let Some(name) = service_name {
+                opts.insert("grpc-service-name".into(), name.into());
+            }
+            proxy.insert("network".into(), "grpc".into());
+            proxy.insert("grpc-opts".into(), Value::Mapping(opts));
+        }
+        "h2" => {
+            let mut opts = Mapping::new();
+            if let Some(host) = host {
+                opts.insert("host".into(), split_list(host));
+            }
+            if let Some(path) = path {
+                opts.insert("path".into(), path.into());
+            }
+            proxy.insert("network".into(), "h2".into());
+            proxy.insert("h2-opts".into(), Value::Mapping(opts));
+        }
+        "tcp" if header_type == Some("http") => {
+            let mut opts = Mapping::new();
+            if let Some(path) = path {
+                opts.insert("path".into(), split_list(path));
+            }
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), split_list(host));
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            proxy.insert("network".into(), "http".into());
+            proxy.insert("http-opts".into(), Value::Mapping(opts));
+        }
+        _ => {}
+    }
+}
+
+fn query_map(url: &Url) -> HashMap<String, String> {
+    url.query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+fn split_fragment(body: &str) -> (&str, Option<&str>) {
+    match body.split_once('#') {
+        Some((body, fragment)) => (body, Some(fragment)),
+        None => (body, None),
+    }
+}
+
+fn split_list(value: &str) -> Value {
+    Value::Sequence(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Value::from)
+            .collect(),
+    )
+}
+
+fn is_truthy(value: Option<&String>) -> bool {
+    value.is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().to_string()
+}
+
+/// decode standard or url-safe base64, with or without padding
+fn decode_base64(value: &str) -> Option<String> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let value = value.trim_end_matches('=');
+    let bytes = STANDARD_NO_PAD
+        .decode(value)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(value))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn unique_name(names: &mut HashSet<String>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while names.contains(&candidate) {
+        candidate = format!("{name} {n}");
+        n += 1;
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+fn truncate(link: &str) -> String {
+    const MAX_LEN: usize = 48;
+    match link.char_indices().nth(MAX_LEN) {
+        Some((index, _)) => format!("{}...", &link[..index]),
+        None => link.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_share_links() {
+    let links = [
+        "ss://YWVzLTI1Ni1nY206cGFzcw@1.2.3.4:8388#hk%20ss",
+        "vless://d342d11e-d424-4583-b36e-524ab1f0afa4@example.com:443?security=reality&sni=www.microsoft.com&fp=chrome&pbk=PUBKEY&sid=ab&flow=xtls-rprx-vision&type=tcp#jp",
+        "trojan://secret@example.org:443?sni=example.org&type=ws&path=%2Fws&host=cdn.example.org#us",
+        "hy2://pass@[2001:db8::1]:8443?sni=example.net&insecure=1#hk%20ss",
+        "unknown://foo",
+    ]
+    .join("\n");
+
+    let result = parse_share_links(&links);
+    assert_eq!(result.proxies.len(), 4);
+    assert_eq!(result.skipped.len(), 1);
+
+    let ss = &result.proxies[0];
+    assert_eq!(ss["type"].as_str(), Some("ss"));
+    assert_eq!(ss["cipher"].as_str(), Some("aes-256-gcm"));
+    assert_eq!(ss["password"].as_str(), Some("pass"));
+    assert_eq!(ss["name"].as_str(), Some("hk ss"));
+
+    let vless = &result.proxies[1];
+    assert_eq!(vless["port"].as_u64(), Some(443));
+    assert_eq!(vless["reality-opts"]["public-key"].as_str(), Some("PUBKEY"));
+    assert_eq!(vless["servername"].as_str(), Some("www.microsoft.com"));
+
+    let trojan = &result.proxies[2];
+    assert_eq!(trojan["network"].as_str(), Some("ws"));
+    assert_eq!(trojan["ws-opts"]["path"].as_str(), Some("/ws"));
+    assert_eq!(
+        trojan["ws-opts"]["headers"]["Host"].as_str(),
+        Some("cdn.example.org")
+    );
+
+    let hy2 = &result.proxies[3];
+    assert_eq!(hy2["server"].as_str(), Some("2001:db8::1"));
+    assert_eq!(hy2["skip-cert-verify"].as_bool(), Some(true));
+    // duplicate names get a suffix
+    assert_eq!(hy2["name"].as_str(), Some("hk ss 2"));
+}
+
+#[test]
+fn test_parse_base64_share_links() {
+    use base64::engine::general_purpose::STANDARD;
+
+    let vmess = STANDARD.encode(
+        r#"{"v":"2","ps":"vm","add":"1.1.1.1","port":"443","id":"d342d11e-d424-4583-b36e-524ab1f0afa4","aid":0,"net":"grpc","path":"svc","tls":"tls","sni":"a.com"}"#,
+    );
+    let blob = STANDARD.encode(format!(
+        "vmess://{vmess}\nss://YWVzLTEyOC1nY206dGVzdA@2.2.2.2:443"
+    ));
+
+    let result = parse_share_links(&blob);
+    assert!(result.skipped.is_empty());
+    assert_eq!(result.proxies.len(), 2);
+
+    let vm = &result.proxies[0];
+    assert_eq!(vm["name"].as_str(), Some("vm"));
+    assert_eq!(vm["grpc-opts"]["grpc-service-name"].as_str(), Some("svc"));
+    assert_eq!(vm["servername"].as_str(), Some("a.com"));
+    assert_eq!(result.proxies[1]["name"].as_str(), Some("2.2.2.2:443"));
+
+    let config = build_share_link_profile(&result.proxies);
+    assert_eq!(
+        config["proxy-groups"][0]["proxies"]
+            .as_sequence()
+            .map(|p| p.len()),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_detect_import_format() {
+    assert_eq!(
+        detect_import_format("https://example.com/sub?token=1\n"),
+        ImportFormat::Url
+    );
+    assert_eq!(
+        detect_import_format("https://a.example.com/sub\nhttps://b.example.com/sub"),
+        ImportFormat::Urls
+    );
+    assert_eq!(
+        detect_import_format("proxies: []\nrules:\n- MATCH,DIRECT"),
+        ImportFormat::ClashYaml
+    );
+    assert_eq!(
+        detect_import_format(r#"{"outbounds": [{"type": "direct", "tag": "direct"}]}"#),
+        ImportFormat::SingBox
+    );
+
+    let link = "trojan://password@example.com:443#node";
+    assert_eq!(detect_import_format(link), ImportFormat::ShareLinks);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(link);
+    assert_eq!(detect_import_format(&encoded), ImportFormat::ShareLinks);
+
+    assert_eq!(detect_import_format("hello world"), ImportFormat::Unknown);
+    assert_eq!(detect_import_format(""), ImportFormat::Unknown);
+}
@@ -0,0 +1,165 @@
+/// 监听系统注销、关机和终止信号，在进程被结束前按顺序完成清理
+pub fn register() {
+    #[cfg(unix)]
+    listen_signals();
+
+    #[cfg(windows)]
+    windows::listen_session_end();
+}
+
+#[cfg(unix)]
+fn listen_signals() {
+    use crate::{core::handle, feat, logging, process::AsyncHandler, utils::logging::Type};
+    use tokio::signal::unix::{signal, SignalKind};
+
+    AsyncHandler::spawn(|| async {
+        let (Ok(mut term), Ok(mut hup), Ok(mut int)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+            signal(SignalKind::interrupt()),
+        ) else {
+            logging!(
+                warn,
+                Type::System,
+                true,
+                "Failed to register shutdown signal handlers"
+            );
+            return;
+        };
+
+        let reason = tokio::select! {
+            _ = term.recv() => "SIGTERM",
+            _ = hup.recv() => "SIGHUP",
+            _ = int.recv() => "SIGINT",
+        };
+        feat::shutdown(reason).await;
+        if let Some(app_handle) = handle::Handle::global().app_handle() {
+            app_handle.exit(0);
+        }
+    });
+}
+
+#[cfg(windows)]
+mod windows {
+    use crate::{
+        core::{handle, resume},
+        feat, logging,
+        utils::logging::Type,
+    };
+    use std::{iter::once, ptr::null_mut, thread};
+    use winapi::{
+        shared::{
+            minwindef::{LPARAM, LRESULT, TRUE, UINT, WPARAM},
+            windef::HWND,
+        },
+        um::{
+            libloaderapi::GetModuleHandleW,
+            winuser::{
+                CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+                ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy, TranslateMessage, MSG,
+                PBT_APMRESUMEAUTOMATIC, WM_ENDSESSION, WM_POWERBROADCAST, WM_QUERYENDSESSION,
+                WNDCLASSW,
+            },
+        },
+    };
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(once(0)).collect()
+    }
+
+    /// 会话结束和电源变化的消息只会广播给顶层窗口，所以创建一个不可见的窗口来接收
+    /// webview 窗口在轻量模式下会被销毁，不能依赖它
+    pub fn listen_session_end() {
+        let spawned = thread::Builder::new()
+            .name("session-end-listener".into())
+            .spawn(|| unsafe {
+                let class_name = wide("KoalaClashSessionEnd");
+                let instance = GetModuleHandleW(null_mut());
+                let class = WNDCLASSW {
+                    lpfnWndProc: Some(window_proc),
+                    hInstance: instance,
+                    lpszClassName: class_name.as_ptr(),
+                    ..std::mem::zeroed()
+                };
+                if RegisterClassW(&class) == 0 {
+                    logging!(
+                        warn,
+                        Type::System,
+                        true,
+                        "Failed to register session window class"
+                    );
+                    return;
+                }
+                let hwnd = CreateWindowExW(
+                    0,
+                    class_name.as_ptr(),
+                    class_name.as_ptr(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    null_mut(),
+                    null_mut(),
+                    instance,
+                    null_mut(),
+                );
+                if hwnd.is_null() {
+                    logging!(warn, Type::System, true, "Failed to create session window");
+                    return;
+                }
+
+                let mut msg: MSG = std::mem::zeroed();
+                while GetMessageW(&mut msg, null_mut(), 0, 0) > 0 {
+                    TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            });
+        if let Err(err) = spawned {
+            logging!(
+                warn,
+                Type::System,
+                true,
+                "Failed to start session listener: {}",
+                err
+            );
+        }
+    }
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match msg {
+            WM_QUERYENDSESSION => {
+                // 只登记原因，会话可能被取消，清理要等到 WM_ENDSESSION 确认结束
+                let reason = wide("Restoring system proxy settings");
+                ShutdownBlockReasonCreate(hwnd, reason.as_ptr());
+                TRUE as LRESULT
+            }
+            WM_ENDSESSION => {
+                // wParam 为 TRUE 时会话确实结束，返回后进程随时可能被结束
+                if wparam == TRUE as WPARAM {
+                    feat::shutdown_blocking("session end");
+                    ShutdownBlockReasonDestroy(hwnd);
+                    if let Some(app_handle) = handle::Handle::global().app_handle() {
+                        app_handle.exit(0);
+                    }
+                } else {
+                    ShutdownBlockReasonDestroy(hwnd);
+                }
+                0
+            }
+            WM_POWERBROADCAST => {
+                // 无论是否有用户操作，唤醒后都会收到
+                if wparam == PBT_APMRESUMEAUTOMATIC {
+                    resume::woke_up();
+                }
+                TRUE as LRESULT
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,567 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value as Json};
+use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::HashMap;
+
+/// Result of converting a sing-box config
+#[derive(Debug, Default)]
+pub struct SingBoxImport {
+    /// the generated mihomo profile
+    pub config: Mapping,
+    /// outbounds and rules that have no mihomo equivalent
+    pub unsupported: Vec<String>,
+}
+
+/// Translate the outbounds and route rules of a sing-box JSON config
+///
+/// Inbounds, dns and experimental settings are client specific and ignored.
+/// Built-in outbounds (`direct`, `block`, `dns`) map to `DIRECT`/`REJECT`.
+pub fn convert_singbox(json: &str) -> Result<SingBoxImport> {
+    let root: Json = serde_json::from_str(json).context("the sing-box config is not valid json")?;
+    let mut result = SingBoxImport::default();
+
+    // tag -> name used in the generated config
+    let mut targets: HashMap<String, String> = HashMap::new();
+    let mut proxies = Sequence::new();
+    let mut groups = Vec::new();
+
+    let outbounds = root
+        .get("outbounds")
+        .and_then(Json::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for outbound in outbounds.iter() {
+        let Some(outbound) = outbound.as_object() else {
+            continue;
+        };
+        let tag = str_of(outbound, "tag").unwrap_or_default();
+        let kind = str_of(outbound, "type").unwrap_or_default();
+        match kind.as_str() {
+            "direct" => {
+                targets.insert(tag, "DIRECT".into());
+            }
+            "block" => {
+                targets.insert(tag, "REJECT".into());
+            }
+            "dns" => {}
+            "selector" | "urltest" => {
+                targets.insert(tag.clone(), tag);
+                groups.push(outbound);
+            }
+            _ => match convert_outbound(outbound, &kind) {
+                Some(proxy) => {
+                    targets.insert(tag.clone(), tag);
+                    proxies.push(Value::Mapping(proxy));
+                }
+                None => result
+                    .unsupported
+                    .push(format!("outbound \"{tag}\" of type \"{kind}\"")),
+            },
+        }
+    }
+
+    let mut proxy_groups = Sequence::new();
+    for group in groups {
+        let tag = str_of(group, "tag").unwrap_or_default();
+        let members: Sequence = group
+            .get("outbounds")
+            .and_then(Json::as_array)
+            .map(|list| {
+                list.iter()
+                    .filter_map(Json::as_str)
+                    .filter_map(|t| targets.get(t).cloned())
+                    .map(Value::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut mapped = Mapping::new();
+        mapped.insert("name".into(), tag.into());
+        if str_of(group, "type").as_deref() == Some("urltest") {
+            mapped.insert("type".into(), "url-test".into());
+            mapped.insert(
+                "url".into(),
+                str_of(group, "url")
+                    .unwrap_or("https://www.gstatic.com/generate_204".into())
+                    .into(),
+            );
+            mapped.insert(
+                "interval".into(),
+                str_of(group, "interval")
+                    .and_then(|i| parse_duration_secs(&i))
+                    .unwrap_or(300)
+                    .into(),
+            );
+            if let Some(tolerance) = group.get("tolerance").and_then(Json::as_u64) {
+                mapped.insert("tolerance".into(), tolerance.into());
+            }
+        } else {
+            mapped.insert("type".into(), "select".into());
+        }
+        mapped.insert("proxies".into(), Value::Sequence(members));
+        proxy_groups.push(Value::Mapping(mapped));
+    }
+
+    let route = root.get("route").and_then(Json::as_object);
+    let mut rules = Sequence::new();
+    let rule_list = route
+        .and_then(|r| r.get("rules"))
+        .and_then(Json::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for (index, rule) in rule_list.iter().enumerate() {
+        let Some(rule) = rule.as_object() else {
+            continue;
+        };
+        match convert_rule(rule, &targets) {
+            Ok(converted) => rules.extend(converted.into_iter().map(Value::from)),
+            Err(reason) => result
+                .unsupported
+                .push(format!("route rule #{}: {reason}", index + 1)),
+        }
+    }
+
+    let fallback = route
+        .and_then(|r| str_of(r, "final"))
+        .and_then(|tag| targets.get(&tag).cloned())
+        .or_else(|| {
+            // sing-box uses the first outbound when `final` is not set
+            outbounds
+                .first()
+                .and_then(|o| o.get("tag"))
+                .and_then(Json::as_str)
+                .and_then(|tag| targets.get(tag).cloned())
+        })
+        .unwrap_or("DIRECT".into());
+    rules.push(format!("MATCH,{fallback}").into());
+
+    result
+        .config
+        .insert("proxies".into(), Value::Sequence(proxies));
+    result
+        .config
+        .insert("proxy-groups".into(), Value::Sequence(proxy_groups));
+    result.config.insert("rules".into(), Value::Sequence(rules));
+    Ok(result)
+}
+
+fn convert_outbound(outbound: &Map<String, Json>, kind: &str) -> Option<Mapping> {
+    let mut proxy = Mapping::new();
+    proxy.insert("name".into(), str_of(outbound, "tag")?.into());
+    let proxy_type = match kind {
+        "shadowsocks" => "ss",
+        "vmess" | "vless" | "trojan" | "hysteria" | "hysteria2" | "tuic" | "http" => kind,
+        "socks" => "socks5",
+        _ => return None,
+    };
+    proxy.insert("type".into(), proxy_type.into());
+    proxy.insert("server".into(), str_of(outbound, "server")?.into());
+    proxy.insert(
+        "port".into(),
+        outbound.get("server_port").and_then(Json::as_u64)?.into(),
+    );
+
+    match kind {
+        "shadowsocks" => {
+            proxy.insert("cipher".into(), str_of(outbound, "method")?.into());
+            proxy.insert("password".into(), str_of(outbound, "password")?.into());
+            if str_of(outbound, "plugin").as_deref() == Some("obfs-local") {
+                let opts = str_of(outbound, "plugin_opts").unwrap_or_default();
+                let mut plugin_opts = Mapping::new();
+                for part in opts.split(';') {
+                    match part.split_once('=') {
+                        Some(("obfs", mode)) => {
+                            plugin_opts.insert("mode".into(), mode.into());
+                        }
+                        Some(("obfs-host", host)) => {
+                            plugin_opts.insert("host".into(), host.into());
+                        }
+                        _ => {}
+                    }
+                }
+                proxy.insert("plugin".into(), "obfs".into());
+                proxy.insert("plugin-opts".into(), Value::Mapping(plugin_opts));
+            }
+        }
+        "vmess" => {
+            proxy.insert("uuid".into(), str_of(outbound, "uuid")?.into());
+            proxy.insert(
+                "alterId".into(),
+                outbound
+                    .get("alter_id")
+                    .and_then(Json::as_u64)
+                    .unwrap_or(0)
+                    .into(),
+            );
+            proxy.insert(
+                "cipher".into(),
+                str_of(outbound, "security").unwrap_or("auto".into()).into(),
+            );
+        }
+        "vless" => {
+            proxy.insert("uuid".into(), str_of(outbound, "uuid")?.into());
+            if let Some(flow) = str_of(outbound, "flow").filter(|f| !f.is_empty()) {
+                proxy.insert("flow".into(), flow.into());
+            }
+        }
+        "trojan" | "hysteria2" => {
+            proxy.insert("password".into(), str_of(outbound, "password")?.into());
+            if kind == "hysteria2" {
+                if let Some(obfs) = outbound.get("obfs").and_then(Json::as_object) {
+                    if let Some(obfs_type) = str_of(obfs, "type") {
+                        proxy.insert("obfs".into(), obfs_type.into());
+                    }
+                    if let Some(password) = str_of(obfs, "password") {
+                        proxy.insert("obfs-password".into(), password.into());
+                    }
+                }
+            }
+        }
+        "hysteria" => {
+            if let Some(auth) = str_of(outbound, "auth_str") {
+                proxy.insert("auth-str".into(), auth.into());
+            }
+            if let Some(up) = outbound.get("up_mbps").and_then(Json::as_u64) {
+                proxy.insert("up".into(), format!("{up} Mbps").into());
+            }
+            if let Some(down) = outbound.get("down_mbps").and_then(Json::as_u64) {
+                proxy.insert("down".into(), format!("{down} Mbps").into());
+            }
+        }
+        "tuic" => {
+            proxy.insert("uuid".into(), str_of(outbound, "uuid")?.into());
+            proxy.insert("password".into(), str_of(outbound, "password")?.into());
+            if let Some(cc) = str_of(outbound, "congestion_control") {
+                proxy.insert("congestion-controller".into(), cc.into());
+            }
+        }
+        _ => {
+            if let Some(username) = str_of(outbound, "username") {
+                proxy.insert("username".into(), username.into());
+            }
+            if let Some(password) = str_of(outbound, "password") {
+                proxy.insert("password".into(), password.into());
+            }
+        }
+    }
+
+    if !matches!(kind, "http" | "socks") {
+        proxy.insert("udp".into(), true.into());
+    }
+    if let Some(tls) = outbound.get("tls").and_then(Json::as_object) {
+        insert_tls(&mut proxy, kind, tls);
+    }
+    if let Some(transport) = outbound.get("transport").and_then(Json::as_object) {
+        insert_transport(&mut proxy, transport)?;
+    }
+
+    Some(proxy)
+}
+
+fn insert_tls(proxy: &mut Mapping, kind: &str, tls: &Map<String, Json>) {
+    if !tls.get("enabled").and_then(Json::as_bool).unwrap_or(false) {
+        return;
+    }
+
+    // trojan/hysteria/tuic always use tls and name the field `sni`
+    let sni_key = match kind {
+        "vmess" | "vless" | "http" | "socks" => {
+            proxy.insert("tls".into(), true.into());
+            "servername"
+        }
+        _ => "sni",
+    };
+    if let Some(server_name) = str_of(tls, "server_name") {
+        proxy.insert(sni_key.into(), server_name.into());
+    }
+    if tls.get("insecure").and_then(Json::as_bool).unwrap_or(false) {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    if let Some(alpn) = tls.get("alpn").and_then(Json::as_array) {
+        let alpn: Sequence = alpn
+            .iter()
+            .filter_map(Json::as_str)
+            .map(Value::from)
+            .collect();
+        proxy.insert("alpn".into(), Value::Sequence(alpn));
+    }
+    if let Some(utls) = tls.get("utls").and_then(Json::as_object) {
+        if let Some(fingerprint) = str_of(utls, "fingerprint") {
+            proxy.insert("client-fingerprint".into(), fingerprint.into());
+        }
+    }
+    if let Some(reality) = tls.get("reality").and_then(Json::as_object) {
+        if reality
+            .get("enabled")
+            .and_then(Json::as_bool)
+            .unwrap_or(false)
+        {
+            let mut opts = Mapping::new();
+            if let Some(public_key) = str_of(reality, "public_key") {
+                opts.insert("public-key".into(), public_key.into());
+            }
+            if let Some(short_id) = str_of(reality, "short_id") {
+                opts.insert("short-id".into(), short_id.into());
+            }
+            proxy.insert("reality-opts".into(), Value::Mapping(opts));
+        }
+    }
+}
+
+/// returns `None` for transports mihomo does not support
+fn insert_transport(proxy: &mut Mapping, transport: &Map<String, Json>) -> Option<()> {
+    let kind = str_of(transport, "type")?;
+    let host = transport
+        .get("headers")
+        .and_then(|h| h.get("Host"))
+        .and_then(Json::as_str)
+        .map(str::to_string);
+    match kind.as_str() {
+        "ws" | "httpupgrade" => {
+            let mut opts = Mapping::new();
+            if let Some(path) = str_of(transport, "path") {
+                opts.insert("path".into(), path.into());
+            }
+            if let Some(host) = host.or_else(|| str_of(transport, "host")) {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), host.into());
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            if kind == "httpupgrade" {
+                opts.insert("v2ray-http-upgrade".into(), true.into());
+            }
+            proxy.insert("network".into(), "ws".into());
+            proxy.insert("ws-opts".into(), Value::Mapping(opts));
+        }
+        "grpc" => {
+            let mut opts = Mapping::new();
+            if let Some(name) = str_of(transport, "service_name") {
+                opts.insert("grpc-service-name".into(), name.into());
+            }
+            proxy.insert("network".into(), "grpc".into());
+            proxy.insert("grpc-opts".into(), Value::Mapping(opts));
+        }
+        "http" => {
+            let mut opts = Mapping::new();
+            if let Some(hosts) = transport.get("host").and_then(Json::as_array) {
+                let hosts: Sequence = hosts
+                    .iter()
+                    .filter_map(Json::as_str)
+                    .map(Value::from)
+                    .collect();
+                opts.insert("host".into(), Value::Sequence(hosts));
+            }
+            if let Some(path) = str_of(transport, "path") {
+                opts.insert("path".into(), path.into());
+            }
+            proxy.insert("network".into(), "h2".into());
+            proxy.insert("h2-opts".into(), Value::Mapping(opts));
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Convert one route rule
+///
+/// A sing-box rule matches when every kind of field it has matches, the values within a kind
+/// are alternatives, e.g. `{"network": "udp", "port": 443}` is UDP to port 443. A rule with
+/// one kind of field becomes one Clash rule per value, others become an `AND` rule.
+fn convert_rule(
+    rule: &Map<String, Json>,
+    targets: &HashMap<String, String>,
+) -> std::result::Result<Vec<String>, String> {
+    let target = match str_of(rule, "action").as_deref() {
+        Some("reject") => "REJECT".to_string(),
+        Some("route") | None => {
+            let tag = str_of(rule, "outbound").ok_or("missing outbound")?;
+            targets
+                .get(&tag)
+                .cloned()
+                .ok_or_else(|| format!("unknown outbound \"{tag}\""))?
+        }
+        Some(action) => return Err(format!("unsupported action \"{action}\"")),
+    };
+
+    if rule.contains_key("type") {
+        return Err("logical rules are not supported".into());
+    }
+
+    // the field, its Clash rule type and the kind it is matched with
+    const FIELDS: &[(&str, &str, &str)] = &[
+        ("domain", "DOMAIN", "destination"),
+        ("domain_suffix", "DOMAIN-SUFFIX", "destination"),
+        ("domain_keyword", "DOMAIN-KEYWORD", "destination"),
+        ("domain_regex", "DOMAIN-REGEX", "destination"),
+        ("geosite", "GEOSITE", "destination"),
+        ("geoip", "GEOIP", "destination"),
+        ("ip_cidr", "IP-CIDR", "destination"),
+        ("source_ip_cidr", "SRC-IP-CIDR", "source"),
+        ("port", "DST-PORT", "port"),
+        ("source_port", "SRC-PORT", "source_port"),
+        ("process_name", "PROCESS-NAME", "process_name"),
+        ("process_path", "PROCESS-PATH", "process_path"),
+        ("network", "NETWORK", "network"),
+    ];
+    // handled above, every other field must be convertible
+    const IGNORED: &[&str] = &["action", "outbound", "invert"];
+
+    // `TYPE,value` and what follows the target, in the order of FIELDS
+    let mut kinds: Vec<(&str, Vec<(String, &str)>)> = Vec::new();
+    for (key, value) in rule {
+        if IGNORED.contains(&key.as_str()) {
+            if key == "invert" && value.as_bool() == Some(true) {
+                return Err("inverted rules are not supported".into());
+            }
+            continue;
+        }
+        if !FIELDS.iter().any(|(field, _, _)| field == key) {
+            return Err(format!("unsupported field \"{key}\""));
+        }
+    }
+    for (field, rule_type, kind) in FIELDS {
+        let values = match rule.get(*field) {
+            Some(Json::Array(list)) => list.clone(),
+            Some(other) => vec![other.clone()],
+            None => continue,
+        };
+        let suffix = if matches!(*rule_type, "IP-CIDR" | "GEOIP") {
+            ",no-resolve"
+        } else {
+            ""
+        };
+        let matchers = values.into_iter().filter_map(|value| match value {
+            Json::String(s) => Some((format!("{rule_type},{s}"), suffix)),
+            Json::Number(n) => Some((format!("{rule_type},{n}"), suffix)),
+            _ => None,
+        });
+        match kinds.iter_mut().find(|(existing, _)| existing == kind) {
+            Some((_, existing)) => existing.extend(matchers),
+            None => kinds.push((kind, matchers.collect())),
+        }
+    }
+    kinds.retain(|(_, matchers)| !matchers.is_empty());
+
+    match kinds.as_slice() {
+        [] => Err("no matcher could be converted".into()),
+        [(_, matchers)] => Ok(matchers
+            .iter()
+            .map(|(matcher, suffix)| format!("{matcher},{target}{suffix}"))
+            .collect()),
+        kinds => {
+            let parts: Vec<String> = kinds
+                .iter()
+                .map(|(_, matchers)| {
+                    let matchers: Vec<String> = matchers
+                        .iter()
+                        .map(|(matcher, suffix)| format!("({matcher}{suffix})"))
+                        .collect();
+                    match matchers.as_slice() {
+                        [matcher] => matcher.clone(),
+                        _ => format!("(OR,({}))", matchers.join(",")),
+                    }
+                })
+                .collect();
+            Ok(vec![format!("AND,({}),{target}", parts.join(","))])
+        }
+    }
+}
+
+fn str_of(object: &Map<String, Json>, key: &str) -> Option<String> {
+    match object.get(key)? {
+        Json::String(s) if !s.is_empty() => Some(s.clone()),
+        Json::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// parse sing-box durations such as `3m` or `30s`
+fn parse_duration_secs(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let number: u64 = number.parse().ok()?;
+    match unit {
+        "s" => Some(number),
+        "m" => Some(number * 60),
+        "h" => Some(number * 3600),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_convert_singbox() {
+    let json = r#"{
+        "outbounds": [
+            { "type": "selector", "tag": "proxy", "outbounds": ["auto", "hk", "direct"] },
+            { "type": "urltest", "tag": "auto", "outbounds": ["hk", "jp"], "interval": "3m" },
+            {
+                "type": "vless", "tag": "hk", "server": "hk.example.com", "server_port": 443,
+                "uuid": "d342d11e-d424-4583-b36e-524ab1f0afa4", "flow": "xtls-rprx-vision",
+                "tls": {
+                    "enabled": true, "server_name": "www.microsoft.com",
+                    "utls": { "enabled": true, "fingerprint": "chrome" },
+                    "reality": { "enabled": true, "public_key": "PUBKEY", "short_id": "ab" }
+                }
+            },
+            {
+                "type": "trojan", "tag": "jp", "server": "jp.example.com", "server_port": 443,
+                "password": "secret", "tls": { "enabled": true, "server_name": "jp.example.com" },
+                "transport": { "type": "ws", "path": "/ws" }
+            },
+            { "type": "wireguard", "tag": "wg", "server": "1.1.1.1", "server_port": 51820 },
+            { "type": "direct", "tag": "direct" },
+            { "type": "block", "tag": "block" }
+        ],
+        "route": {
+            "rules": [
+                { "domain_suffix": ["cn", "ru"], "outbound": "direct" },
+                { "ip_cidr": "10.0.0.0/8", "outbound": "direct" },
+                { "rule_set": "geosite-ads", "outbound": "block" },
+                { "domain": "ads.example.com", "action": "reject" },
+                { "network": "udp", "port": 443, "outbound": "block" },
+                { "domain_suffix": ["a.com", "b.com"], "ip_cidr": "1.0.0.0/8", "port": [80, 443], "outbound": "proxy" }
+            ],
+            "final": "proxy"
+        }
+    }"#;
+
+    let result = convert_singbox(json).unwrap();
+    let config = result.config;
+
+    let proxies = config["proxies"].as_sequence().unwrap();
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(
+        proxies[0]["reality-opts"]["public-key"].as_str(),
+        Some("PUBKEY")
+    );
+    assert_eq!(proxies[0]["servername"].as_str(), Some("www.microsoft.com"));
+    assert_eq!(proxies[1]["sni"].as_str(), Some("jp.example.com"));
+    assert_eq!(proxies[1]["ws-opts"]["path"].as_str(), Some("/ws"));
+
+    let groups = config["proxy-groups"].as_sequence().unwrap();
+    assert_eq!(groups[0]["proxies"].as_sequence().unwrap().len(), 3);
+    assert_eq!(groups[1]["type"].as_str(), Some("url-test"));
+    assert_eq!(groups[1]["interval"].as_u64(), Some(180));
+
+    let rules: Vec<&str> = config["rules"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    assert_eq!(
+        rules,
+        vec![
+            "DOMAIN-SUFFIX,cn,DIRECT",
+            "DOMAIN-SUFFIX,ru,DIRECT",
+            "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+            "DOMAIN,ads.example.com,REJECT",
+            "AND,((DST-PORT,443),(NETWORK,udp)),REJECT",
+            "AND,((OR,((DOMAIN-SUFFIX,a.com),(DOMAIN-SUFFIX,b.com),(IP-CIDR,1.0.0.0/8,no-resolve))),(OR,((DST-PORT,80),(DST-PORT,443)))),proxy",
+            "MATCH,proxy",
+        ]
+    );
+
+    // the wireguard outbound and the rule_set rule
+    assert_eq!(result.unsupported.len(), 2);
+}
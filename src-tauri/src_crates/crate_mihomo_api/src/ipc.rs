@@ -0,0 +1,357 @@
+//! A minimal HTTP/1.1 client over a unix socket or a Windows named pipe,
+//! for cores listening on `external-controller-unix` / `external-controller-pipe`
+
+use reqwest::{Method, header::HeaderMap};
+use std::{pin::Pin, time::Duration};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+trait IpcConn: AsyncRead + AsyncWrite + Send {}
+impl<T: AsyncRead + AsyncWrite + Send> IpcConn for T {}
+
+type Conn = Pin<Box<dyn IpcConn>>;
+
+#[cfg(unix)]
+async fn connect(path: &str) -> std::io::Result<Conn> {
+    Ok(Box::pin(tokio::net::UnixStream::connect(path).await?))
+}
+
+#[cfg(windows)]
+async fn connect(path: &str) -> std::io::Result<Conn> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    // 管道的所有实例都忙时稍后重试
+    const ERROR_PIPE_BUSY: i32 = 231;
+    let mut attempts = 0;
+    loop {
+        match ClientOptions::new().open(path) {
+            Ok(client) => return Ok(Box::pin(client)),
+            Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 20 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// A response read to the end
+#[derive(Debug)]
+pub struct IpcResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// A response whose body is read line by line, for the streaming endpoints
+pub struct IpcLines {
+    reader: BufReader<Conn>,
+    chunked: bool,
+    /// bytes left in the current chunk
+    remaining: usize,
+    pending: Vec<u8>,
+    done: bool,
+}
+
+/// The status, whether the body is chunked, its length and the reader positioned at the body
+type ResponseHead = (u16, bool, Option<usize>, BufReader<Conn>);
+
+async fn send(
+    socket: &str,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+) -> Result<ResponseHead, String> {
+    let conn = connect(socket).await.map_err(|e| e.to_string())?;
+    exchange(conn, method, path, headers, body).await
+}
+
+/// Write the request and read the response head
+/// The core may keep the connection open despite `Connection: close`, so the body is
+/// delimited by its length or the last chunk and only read to the end without either
+async fn exchange(
+    mut conn: Conn,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+) -> Result<ResponseHead, String> {
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n");
+    for (name, value) in headers {
+        if let Ok(value) = value.to_str() {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+    }
+    let body = body.unwrap_or_default();
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    conn.write_all(head.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    conn.write_all(body).await.map_err(|e| e.to_string())?;
+    conn.flush().await.map_err(|e| e.to_string())?;
+
+    let mut reader = BufReader::new(conn);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("invalid status line: {}", line.trim()))?;
+
+    let mut chunked = false;
+    let mut length = None;
+    loop {
+        line.clear();
+        if reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            } else if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse().ok();
+            }
+        }
+    }
+    Ok((status, chunked, length, reader))
+}
+
+/// Send one request and read the whole response
+pub async fn request(
+    socket: &str,
+    method: Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<IpcResponse, String> {
+    let fut = async { read_response(send(socket, &method, path, headers, body).await?).await };
+    tokio::time::timeout(timeout, fut)
+        .await
+        .map_err(|_| "request timed out".to_string())?
+}
+
+async fn read_response(head: ResponseHead) -> Result<IpcResponse, String> {
+    let (status, chunked, length, mut reader) = head;
+    let mut body = Vec::new();
+    if chunked {
+        let mut lines = IpcLines::new(reader, true);
+        while let Some(chunk) = lines.next_chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+    } else if let Some(length) = length {
+        body.resize(length, 0);
+        reader
+            .read_exact(&mut body)
+            .await
+            .map_err(|e| e.to_string())?;
+    } else {
+        reader
+            .read_to_end(&mut body)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(IpcResponse { status, body })
+}
+
+/// Open a streaming endpoint such as `/traffic` or `/logs`
+pub async fn stream(socket: &str, path: &str, headers: &HeaderMap) -> Result<IpcLines, String> {
+    let (status, chunked, _, reader) = send(socket, &Method::GET, path, headers, None).await?;
+    if !(200..300).contains(&status) {
+        return Err(format!("unexpected status {status}"));
+    }
+    Ok(IpcLines::new(reader, chunked))
+}
+
+impl IpcLines {
+    fn new(reader: BufReader<Conn>, chunked: bool) -> Self {
+        Self {
+            reader,
+            chunked,
+            remaining: 0,
+            pending: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// The next piece of the body, `None` at the end
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        if self.done {
+            return Ok(None);
+        }
+        if !self.chunked {
+            let mut buf = vec![0u8; 8192];
+            let read = self
+                .reader
+                .read(&mut buf)
+                .await
+                .map_err(|e| e.to_string())?;
+            if read == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+            buf.truncate(read);
+            return Ok(Some(buf));
+        }
+
+        if self.remaining == 0 {
+            let mut size = String::new();
+            if self
+                .reader
+                .read_line(&mut size)
+                .await
+                .map_err(|e| e.to_string())?
+                == 0
+            {
+                self.done = true;
+                return Ok(None);
+            }
+            let size = size.trim().split(';').next().unwrap_or_default();
+            self.remaining = usize::from_str_radix(size, 16)
+                .map_err(|_| format!("invalid chunk size: {size}"))?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+        }
+
+        let mut buf = vec![0u8; self.remaining.min(8192)];
+        let read = self
+            .reader
+            .read(&mut buf)
+            .await
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            self.done = true;
+            return Ok(None);
+        }
+        buf.truncate(read);
+        self.remaining -= read;
+        if self.remaining == 0 {
+            // 每个块后的 CRLF
+            let mut crlf = String::new();
+            self.reader
+                .read_line(&mut crlf)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(Some(buf))
+    }
+
+    /// The next non-empty line of the body, `None` when the stream ends
+    pub async fn next_line(&mut self) -> Result<Option<String>, String> {
+        loop {
+            if let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if line.is_empty() {
+                    continue;
+                }
+                return Ok(Some(line));
+            }
+            match self.next_chunk().await? {
+                Some(chunk) => self.pending.extend_from_slice(&chunk),
+                None if self.pending.is_empty() => return Ok(None),
+                None => {
+                    let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+                    self.pending.clear();
+                    return Ok((!line.is_empty()).then_some(line));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{DuplexStream, duplex};
+
+    /// Answer the request on the other end with `response` and keep the connection open
+    fn serve(response: &'static [u8]) -> (Conn, tokio::task::JoinHandle<DuplexStream>) {
+        let (client, mut server) = duplex(64 * 1024);
+        let task = tokio::spawn(async move {
+            let mut request = vec![0u8; 4096];
+            let _ = server.read(&mut request).await.unwrap();
+            server.write_all(response).await.unwrap();
+            server
+        });
+        (Box::pin(client), task)
+    }
+
+    async fn get(response: &'static [u8]) -> IpcResponse {
+        let (conn, server) = serve(response);
+        let head = exchange(conn, &Method::GET, "/version", &HeaderMap::new(), None)
+            .await
+            .unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(1), read_response(head))
+            .await
+            .expect("the body should not wait for the connection to close")
+            .unwrap();
+        drop(server);
+        response
+    }
+
+    #[tokio::test]
+    async fn test_content_length_keep_alive() {
+        let response = get(
+            b"HTTP/1.1 200 OK\r\nConnection: keep-alive\r\nContent-Length: 16\r\n\r\n{\"version\":\"v1\"}",
+        )
+        .await;
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"{\"version\":\"v1\"}");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_keep_alive() {
+        let response = get(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"Wikipedia");
+    }
+
+    #[tokio::test]
+    async fn test_empty_response() {
+        let response = get(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await;
+        assert_eq!(response.status, 204);
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_lines() {
+        let (conn, _server) = serve(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nc\r\n{\"up\":1}\n{\"u\r\n7\r\np\":2}\n\n\r\n",
+        );
+        let (status, chunked, _, reader) =
+            exchange(conn, &Method::GET, "/traffic", &HeaderMap::new(), None)
+                .await
+                .unwrap();
+        assert_eq!(status, 200);
+        assert!(chunked);
+        let mut lines = IpcLines::new(reader, chunked);
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "{\"up\":1}");
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "{\"up\":2}");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_status_line() {
+        let (conn, _server) = serve(b"garbage\r\n\r\n");
+        let head = exchange(conn, &Method::GET, "/", &HeaderMap::new(), None).await;
+        assert!(head.is_err());
+    }
+}
@@ -1,13 +1,49 @@
 use reqwest::{Method, header::HeaderMap};
 use serde_json::{Value, json};
-use std::time::Duration;
+use std::{net::IpAddr, time::Duration};
+pub mod ipc;
 pub mod model;
 pub use model::MihomoManager;
 
+/// the same timeout as the http client
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A streaming response read line by line
+pub enum MihomoStream {
+    Http {
+        response: reqwest::Response,
+        pending: Vec<u8>,
+    },
+    Ipc(ipc::IpcLines),
+}
+
+impl MihomoStream {
+    /// The next non-empty line, `None` when the stream ends
+    pub async fn next_line(&mut self) -> Result<Option<String>, String> {
+        match self {
+            MihomoStream::Ipc(lines) => lines.next_line().await,
+            MihomoStream::Http { response, pending } => loop {
+                if let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    let line = String::from_utf8_lossy(&line).trim().to_string();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    return Ok(Some(line));
+                }
+                match response.chunk().await.map_err(|e| e.to_string())? {
+                    Some(chunk) => pending.extend_from_slice(&chunk),
+                    None => return Ok(None),
+                }
+            },
+        }
+    }
+}
+
 impl MihomoManager {
     pub fn new(mihomo_server: String, headers: HeaderMap) -> Self {
         let client = reqwest::ClientBuilder::new()
-            .default_headers(headers)
+            .default_headers(headers.clone())
             .no_proxy()
             .timeout(Duration::from_secs(15))
             .pool_max_idle_per_host(5)
@@ -18,58 +54,128 @@ impl MihomoManager {
         Self {
             mihomo_server,
             client,
+            ipc_path: None,
+            headers,
+        }
+    }
+
+    /// Talk to the controller through a unix socket or a named pipe instead of tcp
+    pub fn new_ipc(ipc_path: String, headers: HeaderMap) -> Self {
+        Self {
+            ipc_path: Some(ipc_path),
+            ..Self::new(String::new(), headers)
+        }
+    }
+
+    /// Send `body` to `path` and return the status and the body of the response
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+        timeout: Option<Duration>,
+    ) -> Result<(u16, Vec<u8>), String> {
+        if let Some(ipc_path) = &self.ipc_path {
+            let response = ipc::request(
+                ipc_path,
+                method,
+                path,
+                &self.headers,
+                body.as_deref(),
+                timeout.unwrap_or(REQUEST_TIMEOUT),
+            )
+            .await?;
+            return Ok((response.status, response.body));
+        }
+
+        let url = format!("{}{}", self.mihomo_server, path);
+        let mut request = self.client.request(method, &url);
+        if let Some(body) = body {
+            request = request.body(body);
         }
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok((status, body.to_vec()))
+    }
+
+    /// Open a streaming endpoint such as `/traffic`, `/memory` or `/logs`
+    pub async fn stream(&self, path: &str) -> Result<MihomoStream, String> {
+        if let Some(ipc_path) = &self.ipc_path {
+            return Ok(MihomoStream::Ipc(
+                ipc::stream(ipc_path, path, &self.headers).await?,
+            ));
+        }
+
+        // 流式接口不会结束，不能使用带总超时的客户端
+        let client = reqwest::ClientBuilder::new()
+            .default_headers(self.headers.clone())
+            .no_proxy()
+            .connect_timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let url = format!("{}{}", self.mihomo_server, path);
+        let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("unexpected status {}", response.status()));
+        }
+        Ok(MihomoStream::Http {
+            response,
+            pending: Vec::new(),
+        })
     }
 
     async fn send_request(
         &self,
         method: Method,
-        url: String,
+        path: String,
         data: Option<serde_json::Value>,
     ) -> Result<serde_json::Value, String> {
-        let client_response = self
-            .client
-            .request(method.clone(), &url)
-            .json(&data.unwrap_or(json!({})))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+        let body = serde_json::to_vec(&data.unwrap_or(json!({}))).map_err(|e| e.to_string())?;
+        let (status, body) = self
+            .request_raw(method.clone(), &path, Some(body), None)
+            .await?;
 
+        if status == 204 {
+            return Ok(json!({"code": 204}));
+        }
         let response = match method {
-            Method::PATCH => {
-                let status = client_response.status();
-                if status.as_u16() == 204 {
-                    json!({"code": 204})
-                } else {
-                    client_response
-                        .json::<serde_json::Value>()
-                        .await
-                        .map_err(|e| e.to_string())?
-                }
-            }
-            Method::PUT => json!(client_response.text().await.map_err(|e| e.to_string())?),
-            _ => client_response
-                .json::<serde_json::Value>()
-                .await
-                .map_err(|e| e.to_string())?,
+            Method::PUT => json!(String::from_utf8_lossy(&body)),
+            _ => serde_json::from_slice::<serde_json::Value>(&body).map_err(|e| e.to_string())?,
         };
         Ok(response)
     }
 
     pub async fn get_refresh_proxies(&self) -> Result<Value, String> {
-        let url = format!("{}/proxies", self.mihomo_server);
+        let url = "/proxies".to_string();
         let proxies = self.send_request(Method::GET, url, None).await?;
         Ok(proxies)
     }
 
+    /// Switch the selector `group` to the proxy `name`
+    pub async fn select_proxy(&self, group: &str, name: &str) -> Result<(), String> {
+        let url = format!("/proxies/{}", encode_path_segment(group));
+        let response = self
+            .send_request(Method::PUT, url, Some(json!({ "name": name })))
+            .await?;
+        if response["code"] == 204 {
+            Ok(())
+        } else {
+            Err(response.as_str().unwrap_or("unknown error").to_string())
+        }
+    }
+
     pub async fn get_providers_proxies(&self) -> Result<Value, String> {
-        let url = format!("{}/providers/proxies", self.mihomo_server);
+        let url = "/providers/proxies".to_string();
         let providers_proxies = self.send_request(Method::GET, url, None).await?;
         Ok(providers_proxies)
     }
 
     pub async fn close_all_connections(&self) -> Result<(), String> {
-        let url = format!("{}/connections", self.mihomo_server);
+        let url = "/connections".to_string();
         let response = self.send_request(Method::DELETE, url, None).await?;
         if response["code"] == 204 {
             Ok(())
@@ -84,13 +190,58 @@ impl MihomoManager {
 
 impl MihomoManager {
     pub async fn is_mihomo_running(&self) -> Result<(), String> {
-        let url = format!("{}/version", self.mihomo_server);
+        let url = "/version".to_string();
         let _response = self.send_request(Method::GET, url, None).await?;
         Ok(())
     }
 
+    /// Bytes in use reported by the `/memory` stream
+    /// The stream starts with a zero sample, so the second one is read when needed
+    pub async fn get_memory(&self) -> Result<u64, String> {
+        let read = async {
+            let mut stream = self.stream("/memory").await?;
+            let mut samples = 0;
+            while let Some(line) = stream.next_line().await? {
+                let value: Value = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+                let inuse = value["inuse"].as_u64().unwrap_or(0);
+                samples += 1;
+                if inuse > 0 || samples >= 2 {
+                    return Ok(inuse);
+                }
+            }
+            Err("memory stream closed".to_string())
+        };
+        tokio::time::timeout(REQUEST_TIMEOUT, read)
+            .await
+            .map_err(|_| "memory stream timed out".to_string())?
+    }
+
+    /// GET `endpoint` with a short timeout, any successful status counts as alive
+    pub async fn ping(&self, endpoint: &str, timeout: Duration) -> Result<(), String> {
+        let (status, _) = self
+            .request_raw(Method::GET, endpoint, None, Some(timeout))
+            .await?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("unexpected status {status}"))
+        }
+    }
+
+    /// Drop the DNS cache of the core, `POST /cache/dns/flush`
+    pub async fn flush_dns_cache(&self) -> Result<(), String> {
+        let (status, _) = self
+            .request_raw(Method::POST, "/cache/dns/flush", None, None)
+            .await?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("unexpected status {status}"))
+        }
+    }
+
     pub async fn put_configs_force(&self, clash_config_path: &str) -> Result<(), String> {
-        let url = format!("{}/configs?force=true", self.mihomo_server);
+        let url = "/configs?force=true".to_string();
         let payload = serde_json::json!({
             "path": clash_config_path,
         });
@@ -98,8 +249,33 @@ impl MihomoManager {
         Ok(())
     }
 
+    /// Send the config itself instead of a path, for a core that cannot read our files
+    pub async fn put_configs_payload(&self, payload: &str) -> Result<(), String> {
+        let body = serde_json::to_vec(&json!({ "payload": payload })).map_err(|e| e.to_string())?;
+        let (status, body) = self
+            .request_raw(Method::PUT, "/configs?force=true", Some(body), None)
+            .await?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("{status}: {}", String::from_utf8_lossy(&body)))
+        }
+    }
+
+    /// Make the core reload its geo databases, `POST /configs/geo`
+    pub async fn update_geo_databases(&self) -> Result<(), String> {
+        let (status, body) = self
+            .request_raw(Method::POST, "/configs/geo", Some(b"{}".to_vec()), None)
+            .await?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("{status}: {}", String::from_utf8_lossy(&body)))
+        }
+    }
+
     pub async fn patch_configs(&self, config: serde_json::Value) -> Result<(), String> {
-        let url = format!("{}/configs", self.mihomo_server);
+        let url = "/configs".to_string();
         let response = self.send_request(Method::PATCH, url, Some(config)).await?;
         if response["code"] == 204 {
             Ok(())
@@ -119,21 +295,35 @@ impl MihomoManager {
     ) -> Result<serde_json::Value, String> {
         let test_url = test_url.unwrap_or("https://cp.cloudflare.com/generate_204".to_string());
         let url = format!(
-            "{}/proxies/{}/delay?url={}&timeout={}",
-            self.mihomo_server, name, test_url, timeout
+            "/proxies/{}/delay?url={}&timeout={}",
+            name, test_url, timeout
         );
         let response = self.send_request(Method::GET, url, None).await?;
         Ok(response)
     }
 
+    /// Resolve a host with the resolver of the core, `GET /dns/query`
+    ///
+    /// The core answers with real addresses here, the system resolver gets
+    /// fake ips while TUN is on.
+    pub async fn dns_query(&self, name: &str) -> Result<Vec<IpAddr>, String> {
+        let path = format!("/dns/query?name={}&type=A", encode_path_segment(name));
+        let (status, body) = self.request_raw(Method::GET, &path, None, None).await?;
+        if !(200..300).contains(&status) {
+            return Err(format!("unexpected status {status}"));
+        }
+        let answer: Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+        Ok(parse_dns_answer(&answer))
+    }
+
     pub async fn get_connections(&self) -> Result<serde_json::Value, String> {
-        let url = format!("{}/connections", self.mihomo_server);
+        let url = "/connections".to_string();
         let response = self.send_request(Method::GET, url, None).await?;
         Ok(response)
     }
 
     pub async fn delete_connection(&self, id: &str) -> Result<(), String> {
-        let url = format!("{}/connections/{}", self.mihomo_server, id);
+        let url = format!("/connections/{}", id);
         let response = self.send_request(Method::DELETE, url, None).await?;
         if response["code"] == 204 {
             Ok(())
@@ -145,3 +335,48 @@ impl MihomoManager {
         }
     }
 }
+
+/// The addresses in the `Answer` of a `/dns/query` response, CNAME records are skipped
+fn parse_dns_answer(answer: &Value) -> Vec<IpAddr> {
+    answer["Answer"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|record| record["data"].as_str()?.parse().ok())
+        .collect()
+}
+
+/// Group names may contain spaces and emoji, which are not valid in a request line
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_encode_path_segment() {
+    assert_eq!(encode_path_segment("Proxy"), "Proxy");
+    assert_eq!(encode_path_segment("🚀 Node/1"), "%F0%9F%9A%80%20Node%2F1");
+}
+
+#[test]
+fn test_parse_dns_answer() {
+    let answer = json!({
+        "Status": 0,
+        "Answer": [
+            { "name": "cp.cloudflare.com.", "type": 5, "data": "cp.cloudflare.com.cdn.cloudflare.net." },
+            { "name": "cp.cloudflare.com.", "type": 1, "data": "104.16.132.229" },
+        ],
+    });
+    assert_eq!(
+        parse_dns_answer(&answer),
+        ["104.16.132.229".parse::<IpAddr>().unwrap()]
+    );
+    assert!(parse_dns_answer(&json!({ "Status": 3 })).is_empty());
+}
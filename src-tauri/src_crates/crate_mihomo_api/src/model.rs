@@ -2,4 +2,7 @@
 pub struct MihomoManager {
     pub(crate) mihomo_server: String,
     pub(crate) client: reqwest::Client,
+    /// unix socket or named pipe of the controller, requests go there instead of `mihomo_server`
+    pub(crate) ipc_path: Option<String>,
+    pub(crate) headers: reqwest::header::HeaderMap,
 }
@@ -12,6 +12,7 @@ import {
   updateProfile,
   readProfileFile,
   saveProfileFile,
+  patchProfile,
 } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 import { GroupsEditorViewer } from "@/components/profile/groups-editor-viewer";
@@ -67,6 +68,8 @@ import {
   Infinity,
   RefreshCw,
   Network,
+  Lock,
+  LockOpen,
 } from "lucide-react";
 import { t } from "i18next";
 
@@ -197,6 +200,15 @@ export const ProfileItem = (props: Props) => {
     }
   });
 
+  const onToggleLock = useLockFn(async () => {
+    try {
+      await patchProfile(itemData.uid, { locked: !itemData.locked });
+      mutate("getProfiles");
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
   const onUpdate = useLockFn(async (updateType: 0 | 1 | 2): Promise<void> => {
     setLoadingCache((cache) => ({ ...cache, [itemData.uid]: true }));
     const updateOption: Partial<IProfileOption> = {};
@@ -269,6 +281,9 @@ export const ProfileItem = (props: Props) => {
     { label: "Edit Info", handler: onEditInfo, icon: Edit3 },
     { label: "Edit File", handler: onEditFile, icon: FileTextIcon },
     { label: "Open File", handler: onOpenFile, icon: FolderOpen },
+    itemData.locked
+      ? { label: "Unlock", handler: onToggleLock, icon: LockOpen }
+      : { label: "Lock", handler: onToggleLock, icon: Lock },
   ];
 
   const editMenuItems: MenuItemAction[] = [
@@ -296,6 +311,7 @@ export const ProfileItem = (props: Props) => {
     label: "Delete",
     handler: () => setConfirmOpen(true),
     icon: Trash2,
+    disabled: !!itemData.locked,
     isDestructive: true,
   };
 
@@ -447,6 +463,7 @@ export const ProfileItem = (props: Props) => {
           <ContextMenuSeparator />
           <ContextMenuItem
             onSelect={deleteMenuItem.handler}
+            disabled={deleteMenuItem.disabled}
             className={cn(
               deleteMenuItem.isDestructive &&
                 "text-destructive focus:text-destructive focus:bg-destructive/10",
@@ -0,0 +1,212 @@
+import { useMemo, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
+import useSWR from "swr";
+import { useAppData } from "@/providers/app-data-provider";
+import {
+  addAppRule,
+  getAppRules,
+  getRunningApps,
+  removeAppRule,
+} from "@/services/cmds";
+import { showNotice } from "@/services/noticeService";
+
+import { Button } from "@/components/ui/button";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+  DialogTrigger,
+  DialogClose,
+} from "@/components/ui/dialog";
+import { Badge } from "@/components/ui/badge";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import {
+  Tooltip,
+  TooltipContent,
+  TooltipProvider,
+  TooltipTrigger,
+} from "@/components/ui/tooltip";
+
+import { AppWindow, Trash2 } from "lucide-react";
+
+const BUILTIN_TARGETS = ["DIRECT", "REJECT"];
+
+export const AppRulesButton = () => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const { proxies, refreshRules } = useAppData();
+  const [value, setValue] = useState("");
+  const [byPath, setByPath] = useState(false);
+  const [target, setTarget] = useState("");
+
+  const { data: appRules = [], mutate: mutateAppRules } = useSWR(
+    open ? "getAppRules" : null,
+    getAppRules,
+  );
+  const { data: runningApps = [] } = useSWR(
+    open ? "getRunningApps" : null,
+    getRunningApps,
+  );
+
+  const targets = useMemo(() => {
+    const groups: string[] = (proxies?.groups ?? [])
+      .map((group: { name: string }) => group.name)
+      .filter((name: string) => name !== "GLOBAL");
+    return [...groups, ...BUILTIN_TARGETS];
+  }, [proxies]);
+
+  const onPickApp = (name: string) => {
+    const app = runningApps.find((item) => item.name === name);
+    if (!app) return;
+    setValue(byPath && app.path ? app.path : app.name);
+  };
+
+  const onAdd = useLockFn(async () => {
+    try {
+      await addAppRule({
+        kind: byPath ? "PROCESS-PATH" : "PROCESS-NAME",
+        value,
+        target,
+      });
+      setValue("");
+      await mutateAppRules();
+      await refreshRules();
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
+  const onRemove = useLockFn(async (rule: IAppRule) => {
+    try {
+      await removeAppRule(rule.kind, rule.value);
+      await mutateAppRules();
+      await refreshRules();
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
+  return (
+    <Dialog open={open} onOpenChange={setOpen}>
+      <TooltipProvider>
+        <Tooltip>
+          <TooltipTrigger asChild>
+            <DialogTrigger asChild>
+              <Button variant="ghost" size="icon">
+                <AppWindow className="h-5 w-5" />
+              </Button>
+            </DialogTrigger>
+          </TooltipTrigger>
+          <TooltipContent>
+            <p>{t("App Rules")}</p>
+          </TooltipContent>
+        </Tooltip>
+      </TooltipProvider>
+      <DialogContent className="max-w-2xl">
+        <DialogHeader>
+          <DialogTitle>{t("App Rules")}</DialogTitle>
+          <DialogDescription>{t("App Rules Info")}</DialogDescription>
+        </DialogHeader>
+
+        <div className="space-y-3 rounded-lg border p-3">
+          <div className="flex items-center gap-2">
+            <Select onValueChange={onPickApp}>
+              <SelectTrigger className="w-48">
+                <SelectValue placeholder={t("Running Apps")} />
+              </SelectTrigger>
+              <SelectContent className="max-h-72">
+                {runningApps.map((app) => (
+                  <SelectItem key={app.name} value={app.name}>
+                    {app.name}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Input
+              className="flex-1"
+              value={value}
+              placeholder={byPath ? "/usr/bin/app" : "app.exe"}
+              onChange={(e) => setValue(e.target.value)}
+            />
+          </div>
+          <div className="flex items-center gap-2">
+            <div className="flex items-center gap-2">
+              <Switch
+                id="app-rule-by-path"
+                checked={byPath}
+                onCheckedChange={setByPath}
+              />
+              <Label htmlFor="app-rule-by-path">{t("Match By Path")}</Label>
+            </div>
+            <Select value={target} onValueChange={setTarget}>
+              <SelectTrigger className="ml-auto w-48">
+                <SelectValue placeholder={t("Proxy Group")} />
+              </SelectTrigger>
+              <SelectContent className="max-h-72">
+                {targets.map((name) => (
+                  <SelectItem key={name} value={name}>
+                    {name}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Button
+              size="sm"
+              disabled={!value.trim() || !target}
+              onClick={onAdd}
+            >
+              {t("Add App Rule")}
+            </Button>
+          </div>
+        </div>
+
+        <div className="max-h-[40vh] overflow-y-auto -mx-6 px-6 space-y-2">
+          {appRules.length === 0 && (
+            <p className="text-sm text-muted-foreground text-center py-4">
+              {t("No App Rules")}
+            </p>
+          )}
+          {appRules.map((rule) => (
+            <div
+              key={`${rule.kind}:${rule.value}`}
+              className="flex items-center gap-2 rounded-lg border bg-card p-3"
+            >
+              <Badge variant="outline">{rule.kind}</Badge>
+              <p className="flex-1 min-w-0 truncate" title={rule.value}>
+                {rule.value}
+              </p>
+              <Badge variant="secondary">{rule.target}</Badge>
+              <Button
+                variant="ghost"
+                size="icon"
+                title={t("Delete")}
+                onClick={() => onRemove(rule)}
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          ))}
+        </div>
+
+        <DialogFooter>
+          <DialogClose asChild>
+            <Button variant="outline">{t("Close")}</Button>
+          </DialogClose>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+};
@@ -15,12 +15,22 @@ import {
   DialogClose,
 } from "@/components/ui/dialog";
 import { Badge } from "@/components/ui/badge";
-import { Loader2, Replace, RotateCw } from "lucide-react";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
+import { Textarea } from "@/components/ui/textarea";
+import { Download, Loader2, Replace, RotateCw } from "lucide-react";
 import { cn } from "@root/lib/utils";
 
 // Логика и сервисы
 import { useVerge } from "@/hooks/use-verge";
-import { changeClashCore, restartCore } from "@/services/cmds";
+import {
+  changeClashCore,
+  getSecondaryCorePort,
+  restartCore,
+  trustCoreBinary,
+  updateCore,
+} from "@/services/cmds";
 import { closeAllConnections, upgradeCore } from "@/services/api";
 import { showNotice } from "@/services/noticeService";
 
@@ -32,15 +42,44 @@ const VALID_CORE = [
 
 export const ClashCoreViewer = forwardRef<DialogRef>((props, ref) => {
   const { t } = useTranslation();
-  const { verge, mutateVerge } = useVerge();
+  const { verge, mutateVerge, patchVerge } = useVerge();
 
   const [open, setOpen] = useState(false);
   const [upgrading, setUpgrading] = useState(false);
   const [restarting, setRestarting] = useState(false);
   const [changingCore, setChangingCore] = useState<string | null>(null);
+  const [external, setExternal] = useState({
+    enable: false,
+    controller: "",
+    secret: "",
+  });
+  const [secondary, setSecondary] = useState({
+    enable: false,
+    port: "7898",
+    interfaceName: "",
+  });
+  const [secondaryPort, setSecondaryPort] = useState<number | null>(null);
+  const [launch, setLaunch] = useState({ args: "", env: "" });
 
   useImperativeHandle(ref, () => ({
-    open: () => setOpen(true),
+    open: () => {
+      setExternal({
+        enable: verge?.enable_external_core ?? false,
+        controller: verge?.external_controller ?? "",
+        secret: verge?.external_secret ?? "",
+      });
+      setSecondary({
+        enable: verge?.enable_secondary_core ?? false,
+        port: String(verge?.secondary_mixed_port ?? 7898),
+        interfaceName: verge?.secondary_interface_name ?? "",
+      });
+      setLaunch({
+        args: (verge?.core_extra_args ?? []).join(" "),
+        env: (verge?.core_extra_env ?? []).join("\n"),
+      });
+      getSecondaryCorePort().then(setSecondaryPort);
+      setOpen(true);
+    },
     close: () => setOpen(false),
   }));
 
@@ -81,10 +120,67 @@ export const ClashCoreViewer = forwardRef<DialogRef>((props, ref) => {
     }
   });
 
+  const onApplyExternal = useLockFn(async () => {
+    try {
+      setRestarting(true);
+      await patchVerge({
+        enable_external_core: external.enable,
+        external_controller: external.controller.trim(),
+        external_secret: external.secret,
+      });
+      await restartCore();
+      mutate("getClashConfig");
+      mutate("getVersion");
+      showNotice("success", t(`Clash Core Restarted`));
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    } finally {
+      setRestarting(false);
+    }
+  });
+
+  const onApplySecondary = useLockFn(async () => {
+    const port = Number(secondary.port);
+    if (!Number.isInteger(port) || port < 1 || port > 65535) {
+      showNotice("error", t("Invalid Port"));
+      return;
+    }
+    try {
+      await patchVerge({
+        enable_secondary_core: secondary.enable,
+        secondary_mixed_port: port,
+        secondary_interface_name: secondary.interfaceName.trim(),
+      });
+      setSecondaryPort(await getSecondaryCorePort());
+      showNotice("success", t("Saved Successfully"));
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
+  const onApplyLaunch = useLockFn(async () => {
+    try {
+      setRestarting(true);
+      await patchVerge({
+        core_extra_args: launch.args.split(/\s+/).filter(Boolean),
+        core_extra_env: launch.env
+          .split("\n")
+          .map((line) => line.trim())
+          .filter(Boolean),
+      });
+      showNotice("success", t(`Clash Core Restarted`));
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    } finally {
+      setRestarting(false);
+    }
+  });
+
   const onUpgrade = useLockFn(async () => {
     try {
       setUpgrading(true);
       await upgradeCore();
+      await trustCoreBinary(clash_core);
       showNotice("success", t(`Core Version Updated`));
     } catch (err: any) {
       const errMsg = err.response?.data?.message || err.toString();
@@ -97,6 +193,19 @@ export const ClashCoreViewer = forwardRef<DialogRef>((props, ref) => {
     }
   });
 
+  const onReinstall = useLockFn(async () => {
+    try {
+      setUpgrading(true);
+      const release = await updateCore(clash_core);
+      mutate("getVersion");
+      showNotice("success", `${t("Core Reinstalled")}: ${release.version}`);
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    } finally {
+      setUpgrading(false);
+    }
+  });
+
   return (
     <Dialog open={open} onOpenChange={setOpen}>
       <DialogContent className="sm:max-w-md">
@@ -118,6 +227,15 @@ export const ClashCoreViewer = forwardRef<DialogRef>((props, ref) => {
                 )}
                 {t("Upgrade")}
               </Button>
+              <Button
+                size="sm"
+                variant="outline"
+                title={t("Reinstall Core")}
+                disabled={upgrading || restarting || changingCore !== null}
+                onClick={onReinstall}
+              >
+                <Download className="h-4 w-4" />
+              </Button>
               <Button
                 size="sm"
                 disabled={upgrading || changingCore !== null}
@@ -172,6 +290,119 @@ export const ClashCoreViewer = forwardRef<DialogRef>((props, ref) => {
           })}
         </div>
 
+        <div className="space-y-3 border-t pt-4">
+          <div className="flex items-center justify-between">
+            <Label htmlFor="external-core">{t("External Core")}</Label>
+            <Switch
+              id="external-core"
+              checked={external.enable}
+              onCheckedChange={(enable) =>
+                setExternal((e) => ({ ...e, enable }))
+              }
+            />
+          </div>
+          {external.enable && (
+            <>
+              <Input
+                placeholder="127.0.0.1:9090"
+                value={external.controller}
+                onChange={(e) =>
+                  setExternal((v) => ({ ...v, controller: e.target.value }))
+                }
+              />
+              <Input
+                type="password"
+                placeholder={t("Core Secret")}
+                value={external.secret}
+                onChange={(e) =>
+                  setExternal((v) => ({ ...v, secret: e.target.value }))
+                }
+              />
+            </>
+          )}
+          <Button
+            size="sm"
+            className="w-full"
+            disabled={restarting || changingCore !== null}
+            onClick={onApplyExternal}
+          >
+            {t("Save")}
+          </Button>
+        </div>
+
+        <div className="space-y-3 border-t pt-4">
+          <div className="flex items-center justify-between">
+            <Label htmlFor="secondary-core">{t("Secondary Core")}</Label>
+            <div className="flex items-center gap-2">
+              {secondaryPort !== null && (
+                <Badge variant="secondary">:{secondaryPort}</Badge>
+              )}
+              <Switch
+                id="secondary-core"
+                checked={secondary.enable}
+                onCheckedChange={(enable) =>
+                  setSecondary((s) => ({ ...s, enable }))
+                }
+              />
+            </div>
+          </div>
+          {secondary.enable && (
+            <>
+              <Input
+                placeholder={t("Secondary Mixed Port")}
+                value={secondary.port}
+                onChange={(e) =>
+                  setSecondary((s) => ({
+                    ...s,
+                    port: e.target.value.replace(/\D/g, ""),
+                  }))
+                }
+              />
+              <Input
+                placeholder={t("Secondary Outbound Interface")}
+                value={secondary.interfaceName}
+                onChange={(e) =>
+                  setSecondary((s) => ({ ...s, interfaceName: e.target.value }))
+                }
+              />
+            </>
+          )}
+          <Button
+            size="sm"
+            className="w-full"
+            disabled={restarting || changingCore !== null}
+            onClick={onApplySecondary}
+          >
+            {t("Save")}
+          </Button>
+        </div>
+
+        <div className="space-y-3 border-t pt-4">
+          <Label htmlFor="core-extra-args">{t("Core Arguments")}</Label>
+          <Input
+            id="core-extra-args"
+            placeholder="-ext-ctl-unix /tmp/mihomo.sock"
+            value={launch.args}
+            onChange={(e) => setLaunch((l) => ({ ...l, args: e.target.value }))}
+          />
+          <Label htmlFor="core-extra-env">{t("Core Environment")}</Label>
+          <Textarea
+            id="core-extra-env"
+            rows={3}
+            placeholder="GOGC=50"
+            value={launch.env}
+            onChange={(e) => setLaunch((l) => ({ ...l, env: e.target.value }))}
+          />
+          <Button
+            size="sm"
+            className="w-full"
+            disabled={restarting || changingCore !== null}
+            onClick={onApplyLaunch}
+          >
+            {t("Save")}
+          </Button>
+        </div>
+
         <DialogFooter>
           <DialogClose asChild>
             <Button type="button" variant="outline">
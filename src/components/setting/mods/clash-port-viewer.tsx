@@ -108,7 +108,7 @@ const PortSettingRow = ({
 
 export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
   const { t } = useTranslation();
-  const { clashInfo, patchInfo } = useClashInfo();
+  const { clashInfo, mutateInfo } = useClashInfo();
   const { verge, patchVerge } = useVerge();
   const [open, setOpen] = useState(false);
 
@@ -121,11 +121,14 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
   const [redirEnabled, setRedirEnabled] = useState(false);
   const [tproxyPort, setTproxyPort] = useState(0);
   const [tproxyEnabled, setTproxyEnabled] = useState(false);
+  const [authUsername, setAuthUsername] = useState("");
+  const [authPassword, setAuthPassword] = useState("");
 
   const { loading, run: saveSettings } = useRequest(
-    async (params: { clashConfig: any; vergeConfig: any }) => {
-      const { clashConfig, vergeConfig } = params;
-      await Promise.all([patchInfo(clashConfig), patchVerge(vergeConfig)]);
+    // 后端先移动内核的监听端口，再让系统代理指向新端口，并同步到 clash 配置
+    async (vergeConfig: IVergeConfig) => {
+      await patchVerge(vergeConfig);
+      await mutateInfo();
     },
     {
       manual: true,
@@ -133,8 +136,11 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
         setOpen(false);
         showNotice("success", t("Port settings saved"));
       },
-      onError: () => {
-        showNotice("error", t("Failed to save settings"));
+      onError: (err: any) => {
+        showNotice(
+          "error",
+          `${t("Failed to save settings")}: ${err?.message || err}`,
+        );
       },
     },
   );
@@ -150,6 +156,9 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
       setRedirEnabled(verge?.verge_redir_enabled ?? false);
       setTproxyPort(verge?.verge_tproxy_port ?? 7894);
       setTproxyEnabled(verge?.verge_tproxy_enabled ?? false);
+      setAuthUsername(verge?.proxy_auth_username ?? "");
+      // 已保存的密码不会返回，留空表示不修改
+      setAuthPassword("");
       setOpen(true);
     },
     close: () => setOpen(false),
@@ -164,7 +173,11 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
       tproxyEnabled ? tproxyPort : -1,
     ].filter((p) => p > 0);
 
-    if (new Set(portList).size !== portList.length) {
+    const controllerPort = Number(clashInfo?.server?.split(":").pop());
+    if (
+      new Set(portList).size !== portList.length ||
+      portList.includes(controllerPort)
+    ) {
       showNotice("error", t("Port conflict detected"));
       return;
     }
@@ -175,15 +188,17 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
       return;
     }
 
-    const clashConfig = {
-      "mixed-port": mixedPort,
-      "socks-port": socksEnabled ? socksPort : 0,
-      port: httpEnabled ? httpPort : 0,
-      "redir-port": redirEnabled ? redirPort : 0,
-      "tproxy-port": tproxyEnabled ? tproxyPort : 0,
-    };
+    if (authUsername.includes(":")) {
+      showNotice("error", t("Proxy Username Invalid"));
+      return;
+    }
+    const passwordSet = verge?.proxy_auth_password_set ?? false;
+    if (!authUsername !== !(authPassword || passwordSet)) {
+      showNotice("error", t("Proxy Auth Incomplete"));
+      return;
+    }
 
-    const vergeConfig = {
+    const vergeConfig: IVergeConfig = {
       verge_mixed_port: mixedPort,
       verge_socks_port: socksPort,
       verge_socks_enabled: socksEnabled,
@@ -194,8 +209,23 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
       verge_tproxy_port: tproxyPort,
       verge_tproxy_enabled: tproxyEnabled,
     };
+    // 认证变化时才需要重新加载内核配置
+    if (!authUsername) {
+      if (verge?.proxy_auth_username || passwordSet) {
+        vergeConfig.proxy_auth_username = "";
+        vergeConfig.proxy_auth_password = "";
+      }
+    } else if (
+      authUsername !== (verge?.proxy_auth_username ?? "") ||
+      authPassword
+    ) {
+      vergeConfig.proxy_auth_username = authUsername;
+      if (authPassword) {
+        vergeConfig.proxy_auth_password = authPassword;
+      }
+    }
 
-    await saveSettings({ clashConfig, vergeConfig });
+    await saveSettings(vergeConfig);
   });
 
   return (
@@ -245,6 +275,39 @@ export const ClashPortViewer = forwardRef<ClashPortViewerRef>((props, ref) => {
               setIsEnabled={setTproxyEnabled}
             />
           )}
+
+          <div className="space-y-2 pt-3 border-t border-border">
+            <p className="text-sm font-medium">{t("Proxy Authentication")}</p>
+            <p className="text-xs text-muted-foreground">
+              {t("Proxy Authentication Info")}
+            </p>
+            <div className="flex gap-2">
+              <Input
+                className="h-8"
+                placeholder={t("Username")}
+                autoComplete="off"
+                value={authUsername}
+                onChange={(e) => setAuthUsername(e.target.value)}
+              />
+              <Input
+                className="h-8"
+                type="password"
+                placeholder={
+                  verge?.proxy_auth_password_set
+                    ? t("Password Unchanged")
+                    : t("Password")
+                }
+                autoComplete="new-password"
+                value={authPassword}
+                onChange={(e) => setAuthPassword(e.target.value)}
+              />
+            </div>
+            {OS === "windows" && authUsername && (
+              <p className="text-xs text-muted-foreground">
+                {t("Proxy Authentication Windows Hint")}
+              </p>
+            )}
+          </div>
         </div>
 
         <DialogFooter>
@@ -1,11 +1,14 @@
 import { forwardRef, useImperativeHandle, useState } from "react";
 import { useTranslation } from "react-i18next";
-import { getRuntimeYaml } from "@/services/cmds";
+import { save } from "@tauri-apps/plugin-dialog";
+import { exportRuntimeConfig, getRuntimeYaml } from "@/services/cmds";
+import { showNotice } from "@/services/noticeService";
 import { DialogRef } from "@/components/base";
 import { EditorViewer } from "@/components/profile/editor-viewer"; // Наш обновленный компонент
 
 // Новые импорты
 import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
 
 export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
   const { t } = useTranslation();
@@ -23,6 +26,20 @@ export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
     close: () => setOpen(false),
   }));
 
+  const onExport = async () => {
+    try {
+      const path = await save({
+        defaultPath: "koala-clash.yaml",
+        filters: [{ name: "YAML", extensions: ["yaml", "yml"] }],
+      });
+      if (!path) return;
+      await exportRuntimeConfig(path);
+      showNotice("success", t("Runtime Config Exported"));
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  };
+
   if (!open) return null;
 
   return (
@@ -34,6 +51,9 @@ export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
         <div className="flex items-center gap-2">
           <span>{t("Runtime Config")}</span>
           <Badge variant="secondary">{t("ReadOnly")}</Badge>
+          <Button size="sm" variant="outline" onClick={onExport}>
+            {t("Export")}
+          </Button>
         </div>
         // --- КОНЕЦ ИЗМЕНЕНИЙ ---
       }
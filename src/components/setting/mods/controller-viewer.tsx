@@ -2,6 +2,8 @@ import { forwardRef, useImperativeHandle, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { useLockFn } from "ahooks";
 import { useClashInfo } from "@/hooks/use-clash";
+import { useVerge } from "@/hooks/use-verge";
+import { rotateClashSecret } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 
 // Новые импорты
@@ -17,13 +19,14 @@ import {
 } from "@/components/ui/dialog";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
+import { Switch } from "@/components/ui/switch";
 import {
   Tooltip,
   TooltipContent,
   TooltipProvider,
   TooltipTrigger,
 } from "@/components/ui/tooltip";
-import { Copy, Loader2 } from "lucide-react";
+import { Copy, Loader2, RefreshCw } from "lucide-react";
 
 export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
   const { t } = useTranslation();
@@ -31,6 +34,10 @@ export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
   const [isSaving, setIsSaving] = useState(false);
 
   const { clashInfo, patchInfo } = useClashInfo();
+  const { verge, patchVerge } = useVerge();
+  const stableSecret = verge?.enable_stable_secret ?? true;
+  const controllerIpc = verge?.enable_controller_ipc ?? false;
+  const disableTcp = verge?.disable_tcp_controller ?? false;
   const [controller, setController] = useState("");
   const [secret, setSecret] = useState("");
 
@@ -55,7 +62,12 @@ export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
     // }
     try {
       setIsSaving(true);
-      await patchInfo({ "external-controller": controller, secret });
+      // Без фиксированного секрета он генерируется при каждом запуске
+      await patchInfo(
+        stableSecret
+          ? { "external-controller": controller, secret }
+          : { "external-controller": controller },
+      );
       showNotice("success", t("Configuration saved successfully"));
       setOpen(false);
     } catch (err: any) {
@@ -69,6 +81,32 @@ export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
     }
   });
 
+  const onRotate = useLockFn(async () => {
+    try {
+      await rotateClashSecret();
+      showNotice("success", t("Secret Rotated"));
+      setOpen(false);
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
+  const onStableSecret = useLockFn(async (enable: boolean) => {
+    try {
+      await patchVerge({ enable_stable_secret: enable });
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
+  const onControllerIpc = useLockFn(async (patch: Partial<IVergeConfig>) => {
+    try {
+      await patchVerge(patch);
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
   const handleCopyToClipboard = useLockFn(
     async (text: string, type: string) => {
       try {
@@ -136,7 +174,27 @@ export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
                 placeholder={t("Recommended")}
                 onChange={(e) => setSecret(e.target.value)}
                 disabled={isSaving}
+                readOnly={!stableSecret}
               />
+              {!stableSecret && (
+                <TooltipProvider>
+                  <Tooltip>
+                    <TooltipTrigger asChild>
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        onClick={onRotate}
+                        disabled={isSaving}
+                      >
+                        <RefreshCw className="h-4 w-4" />
+                      </Button>
+                    </TooltipTrigger>
+                    <TooltipContent>
+                      <p>{t("Rotate Secret")}</p>
+                    </TooltipContent>
+                  </Tooltip>
+                </TooltipProvider>
+              )}
               <TooltipProvider>
                 <Tooltip>
                   <TooltipTrigger asChild>
@@ -156,6 +214,59 @@ export const ControllerViewer = forwardRef<DialogRef>((props, ref) => {
               </TooltipProvider>
             </div>
           </div>
+
+          <div className="flex items-center justify-between">
+            <Label htmlFor="stable-secret">{t("Stable Secret")}</Label>
+            <Switch
+              id="stable-secret"
+              checked={stableSecret}
+              onCheckedChange={onStableSecret}
+              disabled={isSaving}
+            />
+          </div>
+          <p className="text-sm text-muted-foreground">
+            {t("Stable Secret Info")}
+          </p>
+
+          <div className="flex items-center justify-between">
+            <Label htmlFor="controller-ipc">
+              {t("Local Socket Controller")}
+            </Label>
+            <Switch
+              id="controller-ipc"
+              checked={controllerIpc}
+              onCheckedChange={(enable) =>
+                onControllerIpc(
+                  enable
+                    ? { enable_controller_ipc: true }
+                    : {
+                        enable_controller_ipc: false,
+                        disable_tcp_controller: false,
+                      },
+                )
+              }
+              disabled={isSaving}
+            />
+          </div>
+          <p className="text-sm text-muted-foreground">
+            {t("Local Socket Controller Info")}
+          </p>
+
+          {controllerIpc && (
+            <div className="flex items-center justify-between">
+              <Label htmlFor="disable-tcp">
+                {t("Disable TCP Controller")}
+              </Label>
+              <Switch
+                id="disable-tcp"
+                checked={disableTcp}
+                onCheckedChange={(disable) =>
+                  onControllerIpc({ disable_tcp_controller: disable })
+                }
+                disabled={isSaving}
+              />
+            </div>
+          )}
         </div>
 
         <DialogFooter>
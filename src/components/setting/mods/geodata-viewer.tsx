@@ -0,0 +1,184 @@
+import { forwardRef, useImperativeHandle, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
+import { useVerge } from "@/hooks/use-verge";
+import { showNotice } from "@/services/noticeService";
+
+import { DialogRef } from "@/components/base";
+import { Button } from "@/components/ui/button";
+import {
+  Dialog,
+  DialogContent,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+  DialogClose,
+} from "@/components/ui/dialog";
+import { Input } from "@/components/ui/input";
+import { Switch } from "@/components/ui/switch";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import { ArrowUp, Loader2, Plus, Trash2 } from "lucide-react";
+
+const DEFAULT_MIRRORS: IVergeGeoMirror[] = [
+  {
+    name: "GitHub",
+    url: "https://github.com/MetaCubeX/meta-rules-dat/releases/download/latest",
+    proxy: "auto",
+    enable: true,
+  },
+  {
+    name: "jsDelivr",
+    url: "https://cdn.jsdelivr.net/gh/MetaCubeX/meta-rules-dat@release",
+    proxy: "auto",
+    enable: true,
+  },
+];
+
+const PROXY_OPTIONS = ["auto", "core", "direct", "system"];
+
+export const GeoDataViewer = forwardRef<DialogRef>((props, ref) => {
+  const { t } = useTranslation();
+  const { verge, patchVerge } = useVerge();
+  const [open, setOpen] = useState(false);
+  const [isSaving, setIsSaving] = useState(false);
+  const [mirrors, setMirrors] = useState<IVergeGeoMirror[]>([]);
+
+  useImperativeHandle(ref, () => ({
+    open: () => {
+      const saved = verge?.geodata_mirrors;
+      setMirrors(saved?.length ? saved : DEFAULT_MIRRORS);
+      setOpen(true);
+    },
+    close: () => setOpen(false),
+  }));
+
+  const update = (index: number, patch: Partial<IVergeGeoMirror>) =>
+    setMirrors((list) =>
+      list.map((item, i) => (i === index ? { ...item, ...patch } : item)),
+    );
+
+  const moveUp = (index: number) =>
+    setMirrors((list) => {
+      const next = [...list];
+      [next[index - 1], next[index]] = [next[index], next[index - 1]];
+      return next;
+    });
+
+  const onSave = useLockFn(async () => {
+    try {
+      setIsSaving(true);
+      await patchVerge({
+        geodata_mirrors: mirrors.filter((item) => item.url?.trim()),
+      });
+      showNotice("success", t("Saved Successfully"));
+      setOpen(false);
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    } finally {
+      setIsSaving(false);
+    }
+  });
+
+  return (
+    <Dialog open={open} onOpenChange={setOpen}>
+      <DialogContent className="sm:max-w-xl">
+        <DialogHeader>
+          <DialogTitle>{t("GeoData Sources")}</DialogTitle>
+        </DialogHeader>
+
+        <div className="space-y-3 py-4">
+          <p className="text-sm text-muted-foreground">
+            {t("GeoData Sources Info")}
+          </p>
+          {mirrors.map((mirror, index) => (
+            <div key={index} className="flex items-center gap-2">
+              <Switch
+                checked={mirror.enable ?? true}
+                onCheckedChange={(enable) => update(index, { enable })}
+              />
+              <Input
+                className="w-28"
+                placeholder={t("Name")}
+                value={mirror.name ?? ""}
+                onChange={(e) => update(index, { name: e.target.value })}
+              />
+              <Input
+                className="flex-1"
+                placeholder="https://example.com/geo/{file}"
+                value={mirror.url ?? ""}
+                onChange={(e) => update(index, { url: e.target.value })}
+              />
+              <Select
+                value={
+                  PROXY_OPTIONS.includes(mirror.proxy ?? "auto")
+                    ? (mirror.proxy ?? "auto")
+                    : "auto"
+                }
+                onValueChange={(proxy) => update(index, { proxy })}
+              >
+                <SelectTrigger className="w-24 h-9">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  {PROXY_OPTIONS.map((option) => (
+                    <SelectItem key={option} value={option}>
+                      {t(`GeoData Proxy ${option}`)}
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+              <Button
+                variant="ghost"
+                size="icon"
+                disabled={index === 0}
+                onClick={() => moveUp(index)}
+              >
+                <ArrowUp className="h-4 w-4" />
+              </Button>
+              <Button
+                variant="ghost"
+                size="icon"
+                onClick={() =>
+                  setMirrors((list) => list.filter((_, i) => i !== index))
+                }
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          ))}
+          <Button
+            variant="outline"
+            size="sm"
+            onClick={() =>
+              setMirrors((list) => [
+                ...list,
+                { name: "", url: "", proxy: "auto", enable: true },
+              ])
+            }
+          >
+            <Plus className="mr-2 h-4 w-4" />
+            {t("New")}
+          </Button>
+        </div>
+
+        <DialogFooter>
+          <DialogClose asChild>
+            <Button type="button" variant="outline">
+              {t("Cancel")}
+            </Button>
+          </DialogClose>
+          <Button type="button" onClick={onSave} disabled={isSaving}>
+            {isSaving && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+            {t("Save")}
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+});
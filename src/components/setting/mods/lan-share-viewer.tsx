@@ -0,0 +1,271 @@
+import { forwardRef, useImperativeHandle, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
+import useSWR, { mutate } from "swr";
+import dayjs from "dayjs";
+import relativeTime from "dayjs/plugin/relativeTime";
+import { writeText } from "@tauri-apps/plugin-clipboard-manager";
+
+import { useVerge } from "@/hooks/use-verge";
+import { getLanClients, getLanShare, setLanShare } from "@/services/cmds";
+import { showNotice } from "@/services/noticeService";
+import { DialogRef, Switch } from "@/components/base";
+import { Button } from "@/components/ui/button";
+import { Textarea } from "@/components/ui/textarea";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+  DialogClose,
+} from "@/components/ui/dialog";
+import { Copy, Loader2, ShieldAlert } from "lucide-react";
+
+dayjs.extend(relativeTime);
+
+// 逗号、空格或换行分隔
+const parseList = (text: string) =>
+  text
+    .split(/[\s,]+/)
+    .map((entry) => entry.trim())
+    .filter(Boolean);
+
+export const LanShareViewer = forwardRef<DialogRef>((props, ref) => {
+  const { t } = useTranslation();
+  const { verge, patchVerge } = useVerge();
+  const [open, setOpen] = useState(false);
+  const [loading, setLoading] = useState(false);
+  const [allowed, setAllowed] = useState("");
+  const [disallowed, setDisallowed] = useState("");
+
+  useImperativeHandle(ref, () => ({
+    open: () => {
+      setAllowed((verge?.lan_allowed_ips ?? []).join("\n"));
+      setDisallowed((verge?.lan_disallowed_ips ?? []).join("\n"));
+      setOpen(true);
+    },
+    close: () => setOpen(false),
+  }));
+
+  const { data: share, mutate: mutateShare } = useSWR(
+    open ? "getLanShare" : null,
+    getLanShare,
+  );
+
+  const onToggle = useLockFn(async (enable: boolean) => {
+    setLoading(true);
+    try {
+      mutateShare(await setLanShare(enable), false);
+      // allow-lan 随之改变
+      mutate("getRuntimeConfig");
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    } finally {
+      setLoading(false);
+    }
+  });
+
+  const { data: clients = [] } = useSWR(
+    open && share?.enabled ? "getLanClients" : null,
+    getLanClients,
+    { refreshInterval: 3000 },
+  );
+
+  const saveAcl = async (allowList: string[], denyList: string[]) => {
+    try {
+      await patchVerge({
+        lan_allowed_ips: allowList,
+        lan_disallowed_ips: denyList,
+      });
+      setAllowed(allowList.join("\n"));
+      setDisallowed(denyList.join("\n"));
+      showNotice("success", t("Saved Successfully"));
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  };
+
+  const onSaveAcl = useLockFn(() =>
+    saveAcl(parseList(allowed), parseList(disallowed)),
+  );
+
+  const onAllowClient = useLockFn((ip: string) => {
+    const allowList = parseList(allowed);
+    return saveAcl(
+      allowList.includes(ip) ? allowList : [...allowList, ip],
+      parseList(disallowed).filter((entry) => entry !== ip),
+    );
+  });
+
+  const onBlockClient = useLockFn((ip: string) => {
+    const denyList = parseList(disallowed);
+    return saveAcl(
+      parseList(allowed).filter((entry) => entry !== ip),
+      denyList.includes(ip) ? denyList : [...denyList, ip],
+    );
+  });
+
+  const onCopy = useLockFn(async (url: string) => {
+    await writeText(url);
+    showNotice("success", t("Copy Success"));
+  });
+
+  const urls = share?.urls ?? [];
+
+  return (
+    <Dialog open={open} onOpenChange={setOpen}>
+      <DialogContent className="sm:max-w-md max-h-[85vh] overflow-y-auto">
+        <DialogHeader>
+          <DialogTitle>{t("Share to LAN")}</DialogTitle>
+          <DialogDescription>{t("Share to LAN Info")}</DialogDescription>
+        </DialogHeader>
+
+        <div className="flex items-center justify-between py-2">
+          <p className="text-sm font-medium">{t("Share to LAN")}</p>
+          <div className="flex items-center gap-2">
+            {loading && <Loader2 className="h-4 w-4 animate-spin" />}
+            <Switch
+              checked={share?.enabled ?? false}
+              disabled={!share || loading}
+              onCheckedChange={onToggle}
+            />
+          </div>
+        </div>
+
+        {share?.enabled && share.firewall_open === false && (
+          <div className="flex items-center justify-between gap-2 rounded-md border border-destructive/50 p-2 text-sm">
+            <span className="flex items-center gap-2 text-destructive">
+              <ShieldAlert className="h-4 w-4 shrink-0" />
+              {t("LAN Firewall Closed")}
+            </span>
+            <Button
+              size="sm"
+              variant="outline"
+              disabled={loading}
+              onClick={() => onToggle(true)}
+            >
+              {t("Open Firewall")}
+            </Button>
+          </div>
+        )}
+
+        {share?.enabled && (
+          <div className="space-y-3">
+            {urls.length === 0 ? (
+              <p className="text-sm text-muted-foreground">
+                {t("No LAN Address")}
+              </p>
+            ) : (
+              urls.map((url) => (
+                <div
+                  key={url}
+                  className="flex items-center justify-between rounded-md bg-muted px-2 py-1 text-sm"
+                >
+                  <span className="font-mono">{url}</span>
+                  <Button
+                    variant="ghost"
+                    size="icon"
+                    className="h-6 w-6"
+                    onClick={() => onCopy(url)}
+                  >
+                    <Copy className="h-3.5 w-3.5" />
+                  </Button>
+                </div>
+              ))
+            )}
+            {share.qr_code && (
+              <div className="flex flex-col items-center gap-2">
+                <img
+                  className="h-48 w-48 rounded-md bg-white"
+                  src={`data:image/svg+xml;utf8,${encodeURIComponent(share.qr_code)}`}
+                  alt={urls[0]}
+                />
+                <p className="text-xs text-muted-foreground text-center">
+                  {t("Share to LAN Hint", { port: share.port })}
+                </p>
+              </div>
+            )}
+
+            <div className="space-y-2">
+              <p className="text-sm font-medium">{t("LAN Access Control")}</p>
+              <p className="text-xs text-muted-foreground">
+                {t("LAN Access Control Info")}
+              </p>
+              <Textarea
+                rows={2}
+                placeholder={t("LAN Allowed IPs")}
+                value={allowed}
+                onChange={(e) => setAllowed(e.target.value)}
+              />
+              <Textarea
+                rows={2}
+                placeholder={t("LAN Disallowed IPs")}
+                value={disallowed}
+                onChange={(e) => setDisallowed(e.target.value)}
+              />
+              <div className="flex justify-end">
+                <Button size="sm" onClick={onSaveAcl}>
+                  {t("Save")}
+                </Button>
+              </div>
+            </div>
+
+            <div className="space-y-1">
+              <p className="text-sm font-medium">{t("LAN Clients")}</p>
+              {clients.length === 0 ? (
+                <p className="text-xs text-muted-foreground">
+                  {t("No LAN Clients")}
+                </p>
+              ) : (
+                clients.map((client) => (
+                  <div
+                    key={client.ip}
+                    className="flex items-center justify-between gap-2 text-sm"
+                  >
+                    <div className="min-w-0">
+                      <span className="font-mono">{client.ip}</span>
+                      <span className="ml-2 text-xs text-muted-foreground">
+                        {client.connections > 0
+                          ? t("LAN Client Connections", {
+                              count: client.connections,
+                            })
+                          : dayjs(client.last_seen * 1000).fromNow()}
+                      </span>
+                    </div>
+                    <div className="flex gap-1">
+                      <Button
+                        size="sm"
+                        variant="ghost"
+                        onClick={() => onAllowClient(client.ip)}
+                      >
+                        {t("Allow")}
+                      </Button>
+                      <Button
+                        size="sm"
+                        variant="ghost"
+                        className="text-destructive"
+                        onClick={() => onBlockClient(client.ip)}
+                      >
+                        {t("Block")}
+                      </Button>
+                    </div>
+                  </div>
+                ))
+              )}
+            </div>
+          </div>
+        )}
+
+        <DialogFooter>
+          <DialogClose asChild>
+            <Button type="button" variant="outline">
+              {t("Close")}
+            </Button>
+          </DialogClose>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+});
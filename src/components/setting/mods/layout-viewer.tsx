@@ -17,9 +17,10 @@ import { useVerge } from "@/hooks/use-verge";
 import { DialogRef, Switch } from "@/components/base";
 import { TooltipIcon } from "@/components/base/base-tooltip-icon";
 import { GuardState } from "./guard-state";
-import { copyIconFile, getAppDir } from "@/services/cmds";
+import { copyIconFile, getAppDir, openScriptsDir } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
 import {
   Dialog,
   DialogContent,
@@ -36,10 +37,25 @@ import {
   SelectValue,
 } from "@/components/ui/select";
 import getSystem from "@/utils/get-system";
-import { Loader2 } from "lucide-react";
+import { Loader2, Plus, Trash2 } from "lucide-react";
 
 const OS = getSystem();
 
+type TrayIconType = "common" | "sysproxy" | "tun" | "global" | "direct";
+
+// 托盘菜单自定义项可运行的内部命令，与快捷键的功能名一致
+const TRAY_COMMANDS = [
+  "open_or_close_dashboard",
+  "clash_mode_rule",
+  "clash_mode_global",
+  "clash_mode_direct",
+  "toggle_system_proxy",
+  "toggle_tun_mode",
+  "entry_lightweight_mode",
+  "restart_clash",
+  "restart_app",
+];
+
 const getIcons = async (icon_dir: string, name: string) => {
   const updateTime = localStorage.getItem(`icon_${name}_update_time`) || "";
   const icon_png = await join(icon_dir, `${name}-${updateTime}.png`);
@@ -77,6 +93,8 @@ export const LayoutViewer = forwardRef<DialogRef>((props, ref) => {
   const [commonIcon, setCommonIcon] = useState("");
   const [sysproxyIcon, setSysproxyIcon] = useState("");
   const [tunIcon, setTunIcon] = useState("");
+  const [globalIcon, setGlobalIcon] = useState("");
+  const [directIcon, setDirectIcon] = useState("");
 
   const initIconPath = useCallback(async () => {
     const appDir = await getAppDir();
@@ -97,6 +115,16 @@ export const LayoutViewer = forwardRef<DialogRef>((props, ref) => {
       (await exists(sysproxy_icon_ico)) ? sysproxy_icon_ico : sysproxy_icon_png,
     );
     setTunIcon((await exists(tun_icon_ico)) ? tun_icon_ico : tun_icon_png);
+    const { icon_png: global_icon_png, icon_ico: global_icon_ico } =
+      await getIcons(icon_dir, "global");
+    const { icon_png: direct_icon_png, icon_ico: direct_icon_ico } =
+      await getIcons(icon_dir, "direct");
+    setGlobalIcon(
+      (await exists(global_icon_ico)) ? global_icon_ico : global_icon_png,
+    );
+    setDirectIcon(
+      (await exists(direct_icon_ico)) ? direct_icon_ico : direct_icon_png,
+    );
   }, []);
 
   useEffect(() => {
@@ -115,26 +143,37 @@ export const LayoutViewer = forwardRef<DialogRef>((props, ref) => {
     setLocalConfig((prev) => ({ ...prev, ...patch }));
   };
 
-  const handleIconChange = useLockFn(
-    async (type: "common" | "sysproxy" | "tun") => {
-      const key = `${type}_tray_icon` as keyof IVergeConfig;
-      if (localConfig[key]) {
-        handleConfigChange({ [key]: false });
-      } else {
-        const selected = await openDialog({
-          directory: false,
-          multiple: false,
-          filters: [{ name: "Tray Icon Image", extensions: ["png", "ico"] }],
-        });
-        if (selected) {
-          const path = Array.isArray(selected) ? selected[0] : selected;
-          await copyIconFile(path, type);
-          await initIconPath();
-          handleConfigChange({ [key]: true });
-        }
+  const trayItems = localConfig.tray_custom_items ?? [];
+
+  const handleTrayItemChange = (
+    index: number,
+    patch: Partial<IVergeTrayItem>,
+  ) => {
+    handleConfigChange({
+      tray_custom_items: trayItems.map((item, i) =>
+        i === index ? { ...item, ...patch } : item,
+      ),
+    });
+  };
+
+  const handleIconChange = useLockFn(async (type: TrayIconType) => {
+    const key = `${type}_tray_icon` as keyof IVergeConfig;
+    if (localConfig[key]) {
+      handleConfigChange({ [key]: false });
+    } else {
+      const selected = await openDialog({
+        directory: false,
+        multiple: false,
+        filters: [{ name: "Tray Icon Image", extensions: ["png", "ico"] }],
+      });
+      if (selected) {
+        const path = Array.isArray(selected) ? selected[0] : selected;
+        await copyIconFile(path, type);
+        await initIconPath();
+        handleConfigChange({ [key]: true });
       }
-    },
-  );
+    }
+  });
 
   const handleSave = useLockFn(async () => {
     setLoading(true);
@@ -157,38 +196,108 @@ export const LayoutViewer = forwardRef<DialogRef>((props, ref) => {
         </DialogHeader>
 
         <div className="py-4 space-y-1">
+          <SettingRow label={t("Tray Icon")}>
+            <Select
+              onValueChange={(value) =>
+                handleConfigChange({ tray_icon: value as any })
+              }
+              value={
+                localConfig.tray_icon ??
+                (OS === "macos" ? "monochrome" : "colorful")
+              }
+            >
+              <SelectTrigger className="w-40 h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="monochrome">{t("Monochrome")}</SelectItem>
+                <SelectItem value="colorful">{t("Colorful")}</SelectItem>
+              </SelectContent>
+            </Select>
+          </SettingRow>
+
           {OS === "macos" && (
-            <>
-              <SettingRow label={t("Tray Icon")}>
-                <Select
-                  onValueChange={(value) =>
-                    handleConfigChange({ tray_icon: value as any })
-                  }
-                  value={localConfig.tray_icon ?? "monochrome"}
-                >
-                  <SelectTrigger className="w-40 h-8">
-                    <SelectValue />
-                  </SelectTrigger>
-                  <SelectContent>
-                    <SelectItem value="monochrome">
-                      {t("Monochrome")}
-                    </SelectItem>
-                    <SelectItem value="colorful">{t("Colorful")}</SelectItem>
-                  </SelectContent>
-                </Select>
-              </SettingRow>
+            <SettingRow
+              label={t("Tray Icon Rendering")}
+              extra={<TooltipIcon tooltip={t("Tray Icon Rendering Info")} />}
+            >
+              <Select
+                onValueChange={(value) =>
+                  handleConfigChange({ tray_icon_render: value as any })
+                }
+                value={localConfig.tray_icon_render ?? "auto"}
+              >
+                <SelectTrigger className="w-40 h-8">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value="auto">{t("Auto Render")}</SelectItem>
+                  <SelectItem value="template">{t("Template Icon")}</SelectItem>
+                  <SelectItem value="colored">{t("Colored Icon")}</SelectItem>
+                </SelectContent>
+              </Select>
+            </SettingRow>
+          )}
+
+          {OS === "macos" && (
+            <SettingRow label={t("Enable Tray Icon")}>
+              <Switch
+                checked={localConfig.enable_tray_icon ?? true}
+                onCheckedChange={(checked) =>
+                  handleConfigChange({ enable_tray_icon: checked })
+                }
+              />
+            </SettingRow>
+          )}
 
-              <SettingRow label={t("Enable Tray Icon")}>
-                <Switch
-                  checked={localConfig.enable_tray_icon ?? true}
-                  onCheckedChange={(checked) =>
-                    handleConfigChange({ enable_tray_icon: checked })
+          <SettingRow
+            label={t("Enable Tray Speed")}
+            extra={<TooltipIcon tooltip={t("Enable Tray Speed Info")} />}
+          >
+            <Switch
+              checked={localConfig.enable_tray_speed ?? false}
+              onCheckedChange={(checked) =>
+                handleConfigChange({ enable_tray_speed: checked })
+              }
+            />
+          </SettingRow>
+
+          {localConfig.enable_tray_speed && (
+            <SettingRow label={t("Tray Speed Interval")}>
+              <div className="flex items-center gap-2">
+                <Input
+                  type="number"
+                  min={1}
+                  className="w-20 h-8"
+                  value={localConfig.tray_speed_interval ?? 1}
+                  onChange={(e) =>
+                    handleConfigChange({
+                      tray_speed_interval: Math.max(
+                        1,
+                        parseInt(e.target.value) || 1,
+                      ),
+                    })
                   }
                 />
-              </SettingRow>
-            </>
+                <span className="text-sm text-muted-foreground">
+                  {t("seconds")}
+                </span>
+              </div>
+            </SettingRow>
           )}
 
+          <SettingRow
+            label={t("Enable Tray Proxies")}
+            extra={<TooltipIcon tooltip={t("Enable Tray Proxies Info")} />}
+          >
+            <Switch
+              checked={localConfig.enable_tray_proxies ?? false}
+              onCheckedChange={(checked) =>
+                handleConfigChange({ enable_tray_proxies: checked })
+              }
+            />
+          </SettingRow>
+
           <SettingRow label={t("Common Tray Icon")}>
             <Button
               variant="outline"
@@ -242,6 +351,153 @@ export const LayoutViewer = forwardRef<DialogRef>((props, ref) => {
               {localConfig.tun_tray_icon ? t("Clear") : t("Browse")}
             </Button>
           </SettingRow>
+
+          <SettingRow
+            label={t("Global Mode Tray Icon")}
+            extra={<TooltipIcon tooltip={t("Mode Tray Icon Info")} />}
+          >
+            <Button
+              variant="outline"
+              size="sm"
+              className="h-8"
+              onClick={() => handleIconChange("global")}
+            >
+              {localConfig.global_tray_icon && globalIcon && (
+                <img
+                  src={convertFileSrc(globalIcon)}
+                  className="h-5 mr-2"
+                  alt="global mode tray icon"
+                />
+              )}
+              {localConfig.global_tray_icon ? t("Clear") : t("Browse")}
+            </Button>
+          </SettingRow>
+
+          <SettingRow label={t("Direct Mode Tray Icon")}>
+            <Button
+              variant="outline"
+              size="sm"
+              className="h-8"
+              onClick={() => handleIconChange("direct")}
+            >
+              {localConfig.direct_tray_icon && directIcon && (
+                <img
+                  src={convertFileSrc(directIcon)}
+                  className="h-5 mr-2"
+                  alt="direct mode tray icon"
+                />
+              )}
+              {localConfig.direct_tray_icon ? t("Clear") : t("Browse")}
+            </Button>
+          </SettingRow>
+
+          <SettingRow
+            label={t("Tray Menu Items")}
+            extra={<TooltipIcon tooltip={t("Tray Menu Items Info")} />}
+          >
+            <div className="flex items-center gap-2">
+              <Button
+                variant="outline"
+                size="sm"
+                className="h-8"
+                onClick={() => openScriptsDir()}
+              >
+                {t("Scripts Dir")}
+              </Button>
+              <Button
+                variant="outline"
+                size="icon"
+                className="h-8 w-8"
+                title={t("New")}
+                onClick={() =>
+                  handleConfigChange({
+                    tray_custom_items: [
+                      ...trayItems,
+                      { label: "", action: "url", target: "" },
+                    ],
+                  })
+                }
+              >
+                <Plus className="h-4 w-4" />
+              </Button>
+            </div>
+          </SettingRow>
+
+          {trayItems.map((item, index) => (
+            <div key={index} className="flex items-center gap-2 py-1">
+              <Input
+                className="h-8 w-28"
+                placeholder={t("Name")}
+                value={item.label ?? ""}
+                onChange={(e) =>
+                  handleTrayItemChange(index, { label: e.target.value })
+                }
+              />
+              <Select
+                value={item.action ?? "url"}
+                onValueChange={(value) =>
+                  handleTrayItemChange(index, {
+                    action: value as IVergeTrayItem["action"],
+                    target: "",
+                  })
+                }
+              >
+                <SelectTrigger className="w-28 h-8">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value="url">{t("Open URL")}</SelectItem>
+                  <SelectItem value="command">{t("Run Command")}</SelectItem>
+                  <SelectItem value="script">{t("Run Script")}</SelectItem>
+                </SelectContent>
+              </Select>
+              {item.action === "command" ? (
+                <Select
+                  value={item.target || undefined}
+                  onValueChange={(value) =>
+                    handleTrayItemChange(index, { target: value })
+                  }
+                >
+                  <SelectTrigger className="flex-1 h-8">
+                    <SelectValue />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {TRAY_COMMANDS.map((command) => (
+                      <SelectItem key={command} value={command}>
+                        {t(command)}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+              ) : (
+                <Input
+                  className="flex-1 h-8"
+                  placeholder={
+                    item.action === "script" ? "backup.sh" : "https://"
+                  }
+                  value={item.target ?? ""}
+                  onChange={(e) =>
+                    handleTrayItemChange(index, { target: e.target.value })
+                  }
+                />
+              )}
+              <Button
+                variant="ghost"
+                size="icon"
+                className="h-8 w-8 shrink-0"
+                title={t("Delete")}
+                onClick={() =>
+                  handleConfigChange({
+                    tray_custom_items: trayItems.filter(
+                      (_, i) => i !== index,
+                    ),
+                  })
+                }
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          ))}
         </div>
 
         <DialogFooter>
@@ -33,6 +33,7 @@ import {
   ArchiveRestore,
   Link as LinkIcon,
   Timer,
+  BellOff,
 } from "lucide-react";
 
 interface Props {}
@@ -87,6 +88,7 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
     defaultLatencyTest: "",
     autoLogClean: 2,
     defaultLatencyTimeout: 10000,
+    noticeQuietHours: "",
   });
 
   useImperativeHandle(ref, () => ({
@@ -101,6 +103,7 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
         defaultLatencyTest: verge?.default_latency_test || "",
         autoLogClean: verge?.auto_log_clean || 0,
         defaultLatencyTimeout: verge?.default_latency_timeout || 10000,
+        noticeQuietHours: verge?.notice_quiet_hours ?? "",
       });
     },
     close: () => setOpen(false),
@@ -117,6 +120,7 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
         default_latency_test: values.defaultLatencyTest,
         default_latency_timeout: Number(values.defaultLatencyTimeout),
         auto_log_clean: values.autoLogClean as any,
+        notice_quiet_hours: values.noticeQuietHours.trim(),
       });
       setOpen(false);
       showNotice("success", t("Saved Successfully"));
@@ -292,6 +296,22 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
               </span>
             </div>
           </SettingRow>
+
+          <SettingRow
+            label={
+              <LabelWithIcon icon={BellOff} text={t("Notice Quiet Hours")} />
+            }
+            extra={<TooltipIcon tooltip={t("Notice Quiet Hours Info")} />}
+          >
+            <Input
+              className="w-32 h-8"
+              value={values.noticeQuietHours}
+              placeholder="22:00-07:00"
+              onChange={(e) =>
+                handleValueChange("noticeQuietHours", e.target.value)
+              }
+            />
+          </SettingRow>
         </div>
 
         <DialogFooter>
@@ -0,0 +1,118 @@
+import { forwardRef, useImperativeHandle, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
+import useSWR from "swr";
+import dayjs from "dayjs";
+
+import { clearNoticeHistory, getNoticeHistory } from "@/services/cmds";
+import { showNotice } from "@/services/noticeService";
+import { DialogRef } from "@/components/base";
+import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+  DialogClose,
+} from "@/components/ui/dialog";
+import { Loader2 } from "lucide-react";
+
+const STATE_LABELS: Record<INoticeRecord["state"], string> = {
+  shown: "Notice Shown",
+  merged: "Notice Merged",
+  quiet: "Notice Quiet",
+};
+
+export const NoticeHistoryViewer = forwardRef<DialogRef>((props, ref) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+
+  useImperativeHandle(ref, () => ({
+    open: () => setOpen(true),
+    close: () => setOpen(false),
+  }));
+
+  const {
+    data: records = [],
+    isLoading,
+    mutate: mutateRecords,
+  } = useSWR(open ? "getNoticeHistory" : null, getNoticeHistory, {
+    onError: (err) => showNotice("error", err?.message || err.toString()),
+  });
+
+  const onClear = useLockFn(async () => {
+    try {
+      await clearNoticeHistory();
+      await mutateRecords();
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
+  return (
+    <Dialog open={open} onOpenChange={setOpen}>
+      <DialogContent className="sm:max-w-lg">
+        <DialogHeader>
+          <DialogTitle>{t("Notice History")}</DialogTitle>
+          <DialogDescription>{t("Notice History Info")}</DialogDescription>
+        </DialogHeader>
+
+        <div className="max-h-[50vh] overflow-y-auto -mx-6 px-6">
+          {isLoading ? (
+            <div className="flex justify-center py-6">
+              <Loader2 className="h-5 w-5 animate-spin" />
+            </div>
+          ) : records.length === 0 ? (
+            <p className="text-sm text-muted-foreground text-center py-6">
+              {t("No Notices")}
+            </p>
+          ) : (
+            records.map((record, index) => (
+              <div
+                key={`${record.time}-${index}`}
+                className="py-2 border-b border-border last:border-b-0"
+              >
+                <div className="flex items-center justify-between gap-2">
+                  <p className="text-xs text-muted-foreground font-mono truncate">
+                    {record.status || "-"}
+                  </p>
+                  <div className="flex items-center gap-2 shrink-0">
+                    {record.state !== "shown" && (
+                      <Badge variant="secondary">
+                        {t(STATE_LABELS[record.state])}
+                      </Badge>
+                    )}
+                    <span className="text-xs text-muted-foreground">
+                      {dayjs(record.time).format("MM-DD HH:mm:ss")}
+                    </span>
+                  </div>
+                </div>
+                {record.message && (
+                  <p className="text-sm break-words">{record.message}</p>
+                )}
+              </div>
+            ))
+          )}
+        </div>
+
+        <DialogFooter>
+          <Button
+            variant="ghost"
+            disabled={records.length === 0}
+            onClick={onClear}
+          >
+            {t("Clear")}
+          </Button>
+          <DialogClose asChild>
+            <Button type="button" variant="outline">
+              {t("Close")}
+            </Button>
+          </DialogClose>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+});
@@ -20,10 +20,14 @@ import { useAppData } from "@/providers/app-data-provider";
 import { getClashConfig } from "@/services/api";
 import {
   getAutotemProxy,
+  getBypassPresets,
+  getCurrentNetworks,
   getNetworkInterfacesInfo,
+  getNetworkServices,
   getSystemHostname,
   getSystemProxy,
   patchVergeConfig,
+  validateBypass,
 } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 import getSystem from "@/utils/get-system";
@@ -57,20 +61,10 @@ import { TooltipIcon } from "@/components/base/base-tooltip-icon";
 
 // --- Вся ваша оригинальная логика, константы и хелперы ---
 const DEFAULT_PAC = `function FindProxyForURL(url, host) { return "PROXY %proxy_host%:%mixed-port%; SOCKS5 %proxy_host%:%mixed-port%; DIRECT;"; }`;
-const ipv4_part = String.raw`\d{1,3}`;
-const rDomainSimple =
-  String.raw`(?:[a-z0-9\-\*]+\.|\*)*` + String.raw`(?:\w{2,64}\*?|\*)`;
-const ipv6_part = "(?:[a-fA-F0-9:])+";
-const rLocal = `localhost|<local>|localdomain`;
-const getValidReg = (isWindows: boolean) => {
-  const rIPv4Unix = String.raw`(?:${ipv4_part}\.){3}${ipv4_part}(?:\/\d{1,2})?`;
-  const rIPv4Windows = String.raw`(?:${ipv4_part}\.){3}${ipv4_part}`;
-  const rIPv6Unix = String.raw`(?:${ipv6_part}:+)+${ipv6_part}(?:\/\d{1,3})?`;
-  const rIPv6Windows = String.raw`(?:${ipv6_part}:+)+${ipv6_part}`;
-  const rValidPart = `${rDomainSimple}|${isWindows ? rIPv4Windows : rIPv4Unix}|${isWindows ? rIPv6Windows : rIPv6Unix}|${rLocal}`;
-  const separator = isWindows ? ";" : ",";
-  const rValid = String.raw`^(${rValidPart})(?:${separator}\s?(${rValidPart}))*${separator}?$`;
-  return new RegExp(rValid);
+const BYPASS_PRESET_LABELS: Record<string, string> = {
+  china_mainland: "Bypass Preset China Mainland",
+  russia: "Bypass Preset Russia",
+  intranet: "Bypass Preset Intranet",
 };
 
 // --- Компонент Combobox для замены Autocomplete ---
@@ -151,7 +145,7 @@ const SettingRow = ({
 export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
   const { t } = useTranslation();
   const isWindows = getSystem() === "windows";
-  const validReg = useMemo(() => getValidReg(isWindows), [isWindows]);
+  const isMacOS = getSystem() === "macos";
 
   const [open, setOpen] = useState(false);
   const [editorOpen, setEditorOpen] = useState(false);
@@ -169,22 +163,76 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
     enable_system_proxy: enabled,
     proxy_auto_config,
     pac_file_content,
+    pac_from_rules,
     enable_proxy_guard,
+    core_down_disable_sysproxy,
     use_default_bypass,
+    bypass_reserved_ranges,
     system_proxy_bypass,
+    system_proxy_bypass_presets,
+    system_proxy_services,
+    enable_winhttp_proxy,
+    sysproxy_networks,
     proxy_guard_duration,
     proxy_host,
   } = verge ?? {};
 
   const [value, setValue] = useState({
     guard: enable_proxy_guard,
+    core_down: core_down_disable_sysproxy ?? true,
     bypass: system_proxy_bypass,
+    presets: system_proxy_bypass_presets ?? [],
+    services: system_proxy_services ?? [],
+    winhttp: enable_winhttp_proxy ?? false,
+    networks: (sysproxy_networks ?? []).join("\n"),
     duration: proxy_guard_duration ?? 10,
     use_default: use_default_bypass ?? true,
+    reserved: bypass_reserved_ranges ?? true,
     pac: proxy_auto_config,
     pac_content: pac_file_content ?? DEFAULT_PAC,
+    pac_rules: pac_from_rules ?? false,
     proxy_host: proxy_host ?? "127.0.0.1",
   });
+  const [invalidBypass, setInvalidBypass] = useState<string[]>([]);
+
+  const { data: bypassPresets = [] } = useSWR(
+    open ? "getBypassPresets" : null,
+    getBypassPresets,
+  );
+
+  useEffect(() => {
+    if (!value.bypass) {
+      setInvalidBypass([]);
+      return;
+    }
+    validateBypass(value.bypass)
+      .then(setInvalidBypass)
+      .catch(() => setInvalidBypass([]));
+  }, [value.bypass]);
+
+  const presetsChanged =
+    value.presets.join(",") !== (system_proxy_bypass_presets ?? []).join(",");
+
+  const { data: networkServices = [] } = useSWR(
+    open && isMacOS ? "getNetworkServices" : null,
+    getNetworkServices,
+  );
+
+  const servicesChanged =
+    value.services.join(",") !== (system_proxy_services ?? []).join(",");
+
+  const { data: currentNetworks = [] } = useSWR(
+    open ? "getCurrentNetworks" : null,
+    getCurrentNetworks,
+  );
+
+  // 每行一个 Wi-Fi 名称或网卡名称
+  const networkList = value.networks
+    .split("\n")
+    .map((name) => name.trim())
+    .filter(Boolean);
+  const networksChanged =
+    networkList.join("\n") !== (sysproxy_networks ?? []).join("\n");
 
   const defaultBypass = () => {
     if (isWindows)
@@ -292,11 +340,18 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
       setOpen(true);
       setValue({
         guard: enable_proxy_guard,
+        core_down: core_down_disable_sysproxy ?? true,
         bypass: system_proxy_bypass,
+        presets: system_proxy_bypass_presets ?? [],
+        services: system_proxy_services ?? [],
+        winhttp: enable_winhttp_proxy ?? false,
+        networks: (sysproxy_networks ?? []).join("\n"),
         duration: proxy_guard_duration ?? 10,
         use_default: use_default_bypass ?? true,
+        reserved: bypass_reserved_ranges ?? true,
         pac: proxy_auto_config,
         pac_content: pac_file_content ?? DEFAULT_PAC,
+        pac_rules: pac_from_rules ?? false,
         proxy_host: proxy_host ?? "127.0.0.1",
       });
       getSystemProxy().then(setSysproxy);
@@ -314,8 +369,12 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
       );
       return;
     }
-    if (value.bypass && !validReg.test(value.bypass)) {
-      showNotice("error", t("Invalid Bypass Format"));
+    const invalid = value.bypass ? await validateBypass(value.bypass) : [];
+    if (invalid.length > 0) {
+      showNotice(
+        "error",
+        `${t("Invalid Bypass Format")}: ${invalid.join(", ")}`,
+      );
       return;
     }
 
@@ -344,18 +403,39 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
     if (value.guard !== enable_proxy_guard) {
       patch.enable_proxy_guard = value.guard;
     }
+    if (value.core_down !== (core_down_disable_sysproxy ?? true)) {
+      patch.core_down_disable_sysproxy = value.core_down;
+    }
     if (value.duration !== proxy_guard_duration) {
       patch.proxy_guard_duration = value.duration;
     }
     if (value.bypass !== system_proxy_bypass) {
       patch.system_proxy_bypass = value.bypass;
     }
+    if (presetsChanged) {
+      patch.system_proxy_bypass_presets = value.presets;
+    }
+    if (servicesChanged) {
+      patch.system_proxy_services = value.services;
+    }
+    if (networksChanged) {
+      patch.sysproxy_networks = networkList;
+    }
+    if (value.winhttp !== (enable_winhttp_proxy ?? false)) {
+      patch.enable_winhttp_proxy = value.winhttp;
+    }
     if (value.pac !== proxy_auto_config) {
       patch.proxy_auto_config = value.pac;
     }
+    if (value.pac_rules !== (pac_from_rules ?? false)) {
+      patch.pac_from_rules = value.pac_rules;
+    }
     if (value.use_default !== use_default_bypass) {
       patch.use_default_bypass = value.use_default;
     }
+    if (value.reserved !== (bypass_reserved_ranges ?? true)) {
+      patch.bypass_reserved_ranges = value.reserved;
+    }
 
     let pacContent = value.pac_content;
     if (pacContent) {
@@ -388,8 +468,13 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
       value.pac !== proxy_auto_config ||
       proxyHost !== proxy_host ||
       pacContent !== pac_file_content ||
+      value.pac_rules !== (pac_from_rules ?? false) ||
       value.bypass !== system_proxy_bypass ||
-      value.use_default !== use_default_bypass;
+      presetsChanged ||
+      servicesChanged ||
+      value.winhttp !== (enable_winhttp_proxy ?? false) ||
+      value.use_default !== use_default_bypass ||
+      value.reserved !== (bypass_reserved_ranges ?? true);
 
     Promise.resolve().then(async () => {
       try {
@@ -501,6 +586,24 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
                 onCheckedChange={(e) => setValue((v) => ({ ...v, pac: e }))}
               />
             </SettingRow>
+            {value.pac && (
+              <SettingRow
+                label={
+                  <>
+                    {t("Generate PAC From Rules")}{" "}
+                    <TooltipIcon tooltip={t("Generate PAC From Rules Info")} />
+                  </>
+                }
+              >
+                <Switch
+                  disabled={!enabled}
+                  checked={value.pac_rules}
+                  onCheckedChange={(e) =>
+                    setValue((v) => ({ ...v, pac_rules: e }))
+                  }
+                />
+              </SettingRow>
+            )}
             <SettingRow
               label={
                 <>
@@ -532,7 +635,22 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
                 <span className="text-sm text-muted-foreground">s</span>
               </div>
             </SettingRow>
-            {!value.pac && (
+            <SettingRow
+              label={
+                <>
+                  {t("Disable When Core Is Down")}{" "}
+                  <TooltipIcon tooltip={t("Disable When Core Is Down Info")} />
+                </>
+              }
+            >
+              <Switch
+                checked={value.core_down}
+                onCheckedChange={(e) =>
+                  setValue((v) => ({ ...v, core_down: e }))
+                }
+              />
+            </SettingRow>
+            {(!value.pac || value.pac_rules) && (
               <SettingRow label={t("Always use Default Bypass")}>
                 <Switch
                   disabled={!enabled}
@@ -547,7 +665,22 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
                 />
               </SettingRow>
             )}
-            {!value.pac && !value.use_default && (
+            <SettingRow
+              label={
+                <>
+                  {t("Bypass Reserved Ranges")}{" "}
+                  <TooltipIcon tooltip={t("Bypass Reserved Ranges Info")} />
+                </>
+              }
+            >
+              <Switch
+                checked={value.reserved}
+                onCheckedChange={(e) =>
+                  setValue((v) => ({ ...v, reserved: e }))
+                }
+              />
+            </SettingRow>
+            {(!value.pac || value.pac_rules) && !value.use_default && (
               <div className="space-y-2">
                 <Label>{t("Proxy Bypass")}</Label>
                 <Textarea
@@ -560,14 +693,141 @@ export const SysproxyViewer = forwardRef<DialogRef>((props, ref) => {
                   }
                   // Вместо пропса `error` используем условные классы
                   className={cn(
-                    value.bypass &&
-                      !validReg.test(value.bypass) &&
+                    invalidBypass.length > 0 &&
                       "border-destructive focus-visible:ring-destructive",
                   )}
                 />
+                {invalidBypass.length > 0 && (
+                  <p className="text-xs text-destructive">
+                    {invalidBypass.join(", ")}
+                  </p>
+                )}
               </div>
             )}
-            {value.pac && (
+            {isWindows && (
+              <SettingRow
+                label={
+                  <>
+                    {t("Set WinHTTP Proxy")}{" "}
+                    <TooltipIcon tooltip={t("Set WinHTTP Proxy Info")} />
+                  </>
+                }
+              >
+                <Switch
+                  disabled={!enabled}
+                  checked={value.winhttp}
+                  onCheckedChange={(e) =>
+                    setValue((v) => ({ ...v, winhttp: e }))
+                  }
+                />
+              </SettingRow>
+            )}
+            {isMacOS && networkServices.length > 0 && (
+              <div className="space-y-2 py-3 border-b border-border">
+                <Label className="flex items-center gap-2">
+                  {t("Network Services")}
+                  <TooltipIcon tooltip={t("Network Services Info")} />
+                </Label>
+                {networkServices.map((service) => (
+                  <div
+                    key={service.name}
+                    className="flex items-center justify-between"
+                  >
+                    <span className="text-sm">
+                      {service.name}
+                      {service.device && (
+                        <span className="text-muted-foreground">
+                          {" "}
+                          ({service.device})
+                        </span>
+                      )}
+                      {service.active && ` · ${t("Active")}`}
+                    </span>
+                    <Switch
+                      disabled={!enabled}
+                      checked={value.services.includes(service.name)}
+                      onCheckedChange={(e) =>
+                        setValue((v) => ({
+                          ...v,
+                          services: e
+                            ? [...v.services, service.name]
+                            : v.services.filter(
+                                (name) => name !== service.name,
+                              ),
+                        }))
+                      }
+                    />
+                  </div>
+                ))}
+              </div>
+            )}
+            <div className="space-y-2 py-3 border-b border-border">
+              <Label className="flex items-center gap-2">
+                {t("Proxy Networks")}
+                <TooltipIcon tooltip={t("Proxy Networks Info")} />
+              </Label>
+              <Textarea
+                disabled={!enabled}
+                rows={2}
+                placeholder={t("All Networks")}
+                value={value.networks}
+                onChange={(e) =>
+                  setValue((v) => ({ ...v, networks: e.target.value }))
+                }
+              />
+              {currentNetworks.length > 0 && (
+                <div className="flex flex-wrap items-center gap-1">
+                  <span className="text-xs text-muted-foreground">
+                    {t("Current Networks")}:
+                  </span>
+                  {currentNetworks
+                    .filter((name) => !networkList.includes(name))
+                    .map((name) => (
+                      <Button
+                        key={name}
+                        size="sm"
+                        variant="outline"
+                        className="h-6 px-2 text-xs"
+                        disabled={!enabled}
+                        onClick={() =>
+                          setValue((v) => ({
+                            ...v,
+                            networks: [...networkList, name].join("\n"),
+                          }))
+                        }
+                      >
+                        + {name}
+                      </Button>
+                    ))}
+                </div>
+              )}
+            </div>
+            {(!value.pac || value.pac_rules) &&
+              bypassPresets.map((preset) => (
+                <SettingRow
+                  key={preset.id}
+                  label={
+                    <>
+                      {t(BYPASS_PRESET_LABELS[preset.id] ?? preset.id)}{" "}
+                      <TooltipIcon tooltip={preset.entries.join(", ")} />
+                    </>
+                  }
+                >
+                  <Switch
+                    disabled={!enabled}
+                    checked={value.presets.includes(preset.id)}
+                    onCheckedChange={(e) =>
+                      setValue((v) => ({
+                        ...v,
+                        presets: e
+                          ? [...v.presets, preset.id]
+                          : v.presets.filter((id) => id !== preset.id),
+                      }))
+                    }
+                  />
+                </SettingRow>
+              ))}
+            {value.pac && !value.pac_rules && (
               <SettingRow label={t("PAC Script Content")}>
                 <Button
                   variant="outline"
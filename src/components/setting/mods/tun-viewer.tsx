@@ -4,7 +4,14 @@ import { useLockFn, useRequest } from "ahooks";
 import { mutate } from "swr";
 import { useClash, useClashInfo } from "@/hooks/use-clash";
 import { useVerge } from "@/hooks/use-verge";
-import { enhanceProfiles, restartCore } from "@/services/cmds";
+import {
+  applyTunStack,
+  benchTunStacks,
+  checkDnsHijack,
+  enhanceProfiles,
+  getTunBench,
+  restartCore,
+} from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 import getSystem from "@/utils/get-system";
 
@@ -22,6 +29,13 @@ import {
 } from "@/components/ui/dialog";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
 import {
   RotateCcw,
   Layers,
@@ -31,10 +45,15 @@ import {
   Network,
   Dna,
   Gauge,
+  ShieldOff,
+  SearchCheck,
+  Timer,
+  Globe,
 } from "lucide-react";
 
 const OS = getSystem();
 type StackMode = "mixed" | "gvisor" | "system";
+type Ipv6Mode = NonNullable<IVergeConfig["tun_ipv6_mode"]>;
 
 // Компоненты-хелперы
 const SettingRow = ({
@@ -70,6 +89,7 @@ const LabelWithIcon = ({
 export const TunViewer = forwardRef<DialogRef>((props, ref) => {
   const { t } = useTranslation();
   const { clash, mutateClash, patchClash } = useClash();
+  const { verge, patchVerge } = useVerge();
 
   const [open, setOpen] = useState(false);
   const [values, setValues] = useState({
@@ -81,6 +101,10 @@ export const TunViewer = forwardRef<DialogRef>((props, ref) => {
     strictRoute: false,
     mtu: 1500,
   });
+  const [disableOnCoreDown, setDisableOnCoreDown] = useState(false);
+  const [ipv6Mode, setIpv6Mode] = useState<Ipv6Mode>("off");
+  const [bench, setBench] = useState<ITunBenchReport | null>(null);
+  const [benching, setBenching] = useState(false);
 
   useImperativeHandle(ref, () => ({
     open: () => {
@@ -97,10 +121,58 @@ export const TunViewer = forwardRef<DialogRef>((props, ref) => {
         strictRoute: clash?.tun["strict-route"] ?? false,
         mtu: clash?.tun.mtu ?? 1500,
       });
+      setDisableOnCoreDown(verge?.core_down_disable_tun ?? false);
+      setIpv6Mode(verge?.tun_ipv6_mode ?? "off");
+      getTunBench()
+        .then(setBench)
+        .catch(() => setBench(null));
     },
     close: () => setOpen(false),
   }));
 
+  const onCheckDns = useLockFn(async () => {
+    try {
+      const report = await checkDnsHijack(true);
+      switch (report.status) {
+        case "ok":
+          showNotice("success", t("DNS Hijack OK"));
+          break;
+        case "repaired":
+          showNotice("success", t("DNS Hijack Repaired"));
+          break;
+        case "broken":
+          showNotice("error", `${t("DNS Hijack Broken")}: ${report.detail}`);
+          break;
+        case "skipped":
+          showNotice("info", t("DNS Hijack Skipped"));
+          break;
+      }
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
+  const onBench = useLockFn(async () => {
+    setBenching(true);
+    try {
+      setBench(await benchTunStacks());
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    } finally {
+      setBenching(false);
+    }
+  });
+
+  const onApplyStack = useLockFn(async (stack: string) => {
+    try {
+      await applyTunStack(stack);
+      setValues((v) => ({ ...v, stack: stack as StackMode }));
+      showNotice("success", t("Settings Applied"));
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
   const resetToDefaults = () => {
     setValues({
       stack: "gvisor",
@@ -130,6 +202,12 @@ export const TunViewer = forwardRef<DialogRef>((props, ref) => {
         mtu: values.mtu ?? 1500,
       };
       await patchClash({ tun });
+      if (disableOnCoreDown !== (verge?.core_down_disable_tun ?? false)) {
+        await patchVerge({ core_down_disable_tun: disableOnCoreDown });
+      }
+      if (ipv6Mode !== (verge?.tun_ipv6_mode ?? "off")) {
+        await patchVerge({ tun_ipv6_mode: ipv6Mode });
+      }
       await mutateClash((old) => ({ ...(old! || {}), tun }), false);
       try {
         await enhanceProfiles();
@@ -236,6 +314,107 @@ export const TunViewer = forwardRef<DialogRef>((props, ref) => {
               }
             />
           </SettingRow>
+          <SettingRow
+            label={
+              <LabelWithIcon
+                icon={ShieldOff}
+                text={t("Disable TUN When Core Is Down")}
+              />
+            }
+          >
+            <Switch
+              checked={disableOnCoreDown}
+              onCheckedChange={setDisableOnCoreDown}
+            />
+          </SettingRow>
+          <SettingRow
+            label={<LabelWithIcon icon={Globe} text={t("IPv6 Handling")} />}
+          >
+            <Select
+              value={ipv6Mode}
+              onValueChange={(v) => setIpv6Mode(v as Ipv6Mode)}
+            >
+              <SelectTrigger className="w-40 h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="off">{t("IPv6 Keep Profile")}</SelectItem>
+                <SelectItem value="route">{t("IPv6 Route Via TUN")}</SelectItem>
+                <SelectItem value="disable">
+                  {t("IPv6 Disable On Adapters")}
+                </SelectItem>
+              </SelectContent>
+            </Select>
+          </SettingRow>
+          <SettingRow
+            label={
+              <LabelWithIcon icon={SearchCheck} text={t("Check DNS Hijack")} />
+            }
+          >
+            <Button
+              type="button"
+              variant="outline"
+              size="sm"
+              disabled={!verge?.enable_tun_mode}
+              onClick={onCheckDns}
+            >
+              {t("Check")}
+            </Button>
+          </SettingRow>
+          <SettingRow
+            label={<LabelWithIcon icon={Timer} text={t("Benchmark Stacks")} />}
+          >
+            <Button
+              type="button"
+              variant="outline"
+              size="sm"
+              disabled={!verge?.enable_tun_mode || benching}
+              onClick={onBench}
+            >
+              {benching ? t("Benchmarking...") : t("Run")}
+            </Button>
+          </SettingRow>
+          {bench && (
+            <div className="space-y-1 py-3 text-sm">
+              {bench.node && (
+                <div className="text-muted-foreground">
+                  {t("Test Node")}: {bench.node}
+                </div>
+              )}
+              {bench.results.map((result) => (
+                <div
+                  key={result.stack}
+                  className="flex items-center justify-between gap-2"
+                  title={result.error}
+                >
+                  <span className="font-medium">
+                    {result.stack}
+                    {result.stack === bench.recommended &&
+                      ` (${t("Recommended")})`}
+                  </span>
+                  <span className="text-muted-foreground">
+                    {result.latency_ms != null
+                      ? `${result.latency_ms} ms`
+                      : "-"}
+                    {" / "}
+                    {result.throughput_mbps != null
+                      ? `${result.throughput_mbps.toFixed(1)} Mbps`
+                      : "-"}
+                  </span>
+                </div>
+              ))}
+              {bench.recommended && bench.recommended !== values.stack && (
+                <Button
+                  type="button"
+                  size="sm"
+                  className="w-full"
+                  onClick={() => onApplyStack(bench.recommended!)}
+                >
+                  {t("Apply Recommended Stack")}: {bench.recommended}
+                </Button>
+              )}
+            </div>
+          )}
         </div>
 
         <DialogFooter>
@@ -0,0 +1,146 @@
+import { forwardRef, useImperativeHandle, useMemo, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
+import useSWR from "swr";
+
+import {
+  exemptAllUwpApps,
+  getUwpApps,
+  invoke_uwp_tool,
+  setUwpLoopback,
+} from "@/services/cmds";
+import { showNotice } from "@/services/noticeService";
+import { DialogRef, Switch } from "@/components/base";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+  DialogClose,
+} from "@/components/ui/dialog";
+import { Loader2 } from "lucide-react";
+
+export const UwpViewer = forwardRef<DialogRef>((props, ref) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const [search, setSearch] = useState("");
+
+  useImperativeHandle(ref, () => ({
+    open: () => setOpen(true),
+    close: () => setOpen(false),
+  }));
+
+  const {
+    data: apps = [],
+    isLoading,
+    mutate: mutateApps,
+  } = useSWR(open ? "getUwpApps" : null, getUwpApps, {
+    onError: (err) => showNotice("error", err?.message || err.toString()),
+  });
+
+  const filtered = useMemo(() => {
+    const keyword = search.trim().toLowerCase();
+    if (!keyword) return apps;
+    return apps.filter(
+      (app) =>
+        app.display_name.toLowerCase().includes(keyword) ||
+        app.name.toLowerCase().includes(keyword),
+    );
+  }, [apps, search]);
+
+  const onToggle = useLockFn(async (app: IUwpApp, exempt: boolean) => {
+    try {
+      await setUwpLoopback([app.sid], exempt);
+      await mutateApps();
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
+  const onExemptAll = useLockFn(async () => {
+    try {
+      const count = await exemptAllUwpApps();
+      await mutateApps();
+      showNotice("success", t("UWP Apps Exempted", { count }));
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
+  const exemptCount = apps.filter((app) => app.exempt).length;
+
+  return (
+    <Dialog open={open} onOpenChange={setOpen}>
+      <DialogContent className="sm:max-w-lg">
+        <DialogHeader>
+          <DialogTitle>{t("UWP Loopback")}</DialogTitle>
+          <DialogDescription>{t("UWP Loopback Info")}</DialogDescription>
+        </DialogHeader>
+
+        <div className="flex items-center gap-2">
+          <Input
+            placeholder={t("Search Apps")}
+            value={search}
+            onChange={(e) => setSearch(e.target.value)}
+          />
+          <Button
+            variant="outline"
+            disabled={isLoading || exemptCount === apps.length}
+            onClick={onExemptAll}
+          >
+            {t("Exempt All")}
+          </Button>
+        </div>
+
+        <div className="max-h-[50vh] overflow-y-auto -mx-6 px-6">
+          {isLoading ? (
+            <div className="flex justify-center py-6">
+              <Loader2 className="h-5 w-5 animate-spin" />
+            </div>
+          ) : (
+            filtered.map((app) => (
+              <div
+                key={app.sid}
+                className="flex items-center justify-between gap-2 py-2 border-b border-border last:border-b-0"
+              >
+                <div className="min-w-0">
+                  <p className="text-sm truncate">{app.display_name}</p>
+                  <p className="text-xs text-muted-foreground truncate">
+                    {app.name}
+                  </p>
+                </div>
+                <Switch
+                  checked={app.exempt}
+                  onCheckedChange={(e) => onToggle(app, e)}
+                />
+              </div>
+            ))
+          )}
+        </div>
+
+        <DialogFooter className="sm:justify-between">
+          <p className="text-sm text-muted-foreground self-center">
+            {t("UWP Apps Exempt Count", {
+              count: exemptCount,
+              total: apps.length,
+            })}
+          </p>
+          <div className="flex gap-2">
+            <Button variant="ghost" onClick={useLockFn(invoke_uwp_tool)}>
+              {t("Open UWP tool")}
+            </Button>
+            <DialogClose asChild>
+              <Button type="button" variant="outline">
+                {t("Close")}
+              </Button>
+            </DialogClose>
+          </div>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+});
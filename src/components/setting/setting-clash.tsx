@@ -2,6 +2,7 @@ import { useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import useSWR, { mutate } from "swr";
 import { useLockFn } from "ahooks";
+import dayjs from "dayjs";
 import { invoke } from "@tauri-apps/api/core";
 import getSystem from "@/utils/get-system";
 
@@ -11,7 +12,12 @@ import { useVerge } from "@/hooks/use-verge";
 import { updateGeoData, closeAllConnections } from "@/services/api";
 import { showNotice } from "@/services/noticeService";
 import { useServiceInstaller } from "@/hooks/useServiceInstaller";
-import { getRunningMode, invoke_uwp_tool } from "@/services/cmds";
+import {
+  getGeoDataStatus,
+  getRunningMode,
+  setCoreLogLevel,
+  updateGeoDataFiles,
+} from "@/services/cmds";
 
 // Компоненты
 import { DialogRef, Switch } from "@/components/base";
@@ -40,6 +46,7 @@ import {
   Cog,
   Repeat,
   Map as MapIcon,
+  Smartphone,
 } from "lucide-react";
 
 // Модальные окна
@@ -47,7 +54,10 @@ import { ClashCoreViewer } from "./mods/clash-core-viewer";
 import { ClashPortViewer } from "./mods/clash-port-viewer";
 import { ControllerViewer } from "./mods/controller-viewer";
 import { DnsViewer } from "./mods/dns-viewer";
+import { GeoDataViewer } from "./mods/geodata-viewer";
+import { LanShareViewer } from "./mods/lan-share-viewer";
 import { NetworkInterfaceViewer } from "./mods/network-interface-viewer";
+import { UwpViewer } from "./mods/uwp-viewer";
 import { WebUIViewer } from "./mods/web-ui-viewer";
 
 const isWIN = getSystem() === "windows";
@@ -110,7 +120,15 @@ const SettingClash = ({ onError }: Props) => {
     "unified-delay": unifiedDelay,
   } = clash ?? {};
 
-  const { verge_mixed_port } = verge ?? {};
+  const { verge_mixed_port, geodata_update_interval = 0 } = verge ?? {};
+
+  const { data: geoStatus, mutate: mutateGeoStatus } = useSWR(
+    "getGeoDataStatus",
+    getGeoDataStatus,
+  );
+  const geoUpdated = geoStatus?.files
+    .map((file) => file.updated ?? 0)
+    .reduce((a, b) => Math.min(a, b), Infinity);
 
   const [dnsSettingsEnabled, setDnsSettingsEnabled] = useState(() => {
     return verge?.enable_dns_settings ?? false;
@@ -122,6 +140,9 @@ const SettingClash = ({ onError }: Props) => {
   const coreRef = useRef<DialogRef>(null);
   const networkRef = useRef<DialogRef>(null);
   const dnsRef = useRef<DialogRef>(null);
+  const geoRef = useRef<DialogRef>(null);
+  const uwpRef = useRef<DialogRef>(null);
+  const lanShareRef = useRef<DialogRef>(null);
 
   const onSwitchFormat = (value: boolean) => value;
   const onSelectFormat = (value: string) => value;
@@ -132,7 +153,12 @@ const SettingClash = ({ onError }: Props) => {
 
   const onUpdateGeo = useLockFn(async () => {
     try {
-      await updateGeoData();
+      // Если файлов ещё нет, ядро скачает их само
+      if (geoStatus?.files.length) {
+        mutateGeoStatus(await updateGeoDataFiles(), false);
+      } else {
+        await updateGeoData();
+      }
       showNotice("success", t("GeoData Updated"));
     } catch (err: any) {
       showNotice("error", err?.response?.data?.message || err.toString());
@@ -167,6 +193,9 @@ const SettingClash = ({ onError }: Props) => {
         <ClashCoreViewer ref={coreRef} />
         <NetworkInterfaceViewer ref={networkRef} />
         <DnsViewer ref={dnsRef} />
+        <GeoDataViewer ref={geoRef} />
+        <LanShareViewer ref={lanShareRef} />
+        {isWIN && <UwpViewer ref={uwpRef} />}
 
         <SettingRow
           label={<LabelWithIcon icon={Network} text={t("Allow Lan")} />}
@@ -191,6 +220,12 @@ const SettingClash = ({ onError }: Props) => {
           </GuardState>
         </SettingRow>
 
+        <SettingRow
+          onClick={() => lanShareRef.current?.open()}
+          label={<LabelWithIcon icon={Smartphone} text={t("Share to LAN")} />}
+          extra={<TooltipIcon tooltip={t("Share to LAN Info")} />}
+        />
+
         <SettingRow
           label={<LabelWithIcon icon={Dna} text={t("DNS Overwrite")} />}
           extra={
@@ -248,7 +283,7 @@ const SettingClash = ({ onError }: Props) => {
             onChangeProps="onValueChange"
             onFormat={onSelectFormat}
             onChange={(e) => onChangeData({ "log-level": e })}
-            onGuard={(e) => patchClash({ "log-level": e })}
+            onGuard={(e) => setCoreLogLevel(e)}
             onCatch={onError}
           >
             <Select value={logLevel}>
@@ -315,7 +350,7 @@ const SettingClash = ({ onError }: Props) => {
 
         {isWIN && (
           <SettingRow
-            onClick={useLockFn(invoke_uwp_tool)}
+            onClick={() => uwpRef.current?.open()}
             label={
               <LabelWithIcon icon={Repeat} text={t("UWP Loopback Tool")} />
             }
@@ -326,7 +361,45 @@ const SettingClash = ({ onError }: Props) => {
         <SettingRow
           onClick={onUpdateGeo}
           label={<LabelWithIcon icon={MapIcon} text={t("Update GeoData")} />}
-        />
+          extra={
+            <TooltipIcon
+              tooltip={t("GeoData Sources")}
+              icon={<Settings className="h-4 w-4" />}
+              onClick={() => geoRef.current?.open()}
+            />
+          }
+        >
+          {geoUpdated && geoUpdated !== Infinity ? (
+            <p className="text-sm text-muted-foreground pr-2">
+              {dayjs(geoUpdated * 1000).format("YYYY-MM-DD HH:mm")}
+            </p>
+          ) : null}
+        </SettingRow>
+
+        <SettingRow
+          label={
+            <LabelWithIcon icon={Timer} text={t("GeoData Auto Update")} />
+          }
+        >
+          <Select
+            value={String(geodata_update_interval)}
+            onValueChange={(value) =>
+              patchVerge({ geodata_update_interval: Number(value) })
+                .then(() => mutateVerge())
+                .catch(onError)
+            }
+          >
+            <SelectTrigger className="w-28 h-8">
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value="0">{t("Off")}</SelectItem>
+              <SelectItem value="24">{t("Daily")}</SelectItem>
+              <SelectItem value="168">{t("Weekly")}</SelectItem>
+              <SelectItem value="720">{t("Monthly")}</SelectItem>
+            </SelectContent>
+          </Select>
+        </SettingRow>
       </div>
     </div>
   );
@@ -1,7 +1,7 @@
 import { useMemo, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { useLockFn } from "ahooks";
-import { mutate } from "swr";
+import useSWR, { mutate } from "swr";
 import { invoke } from "@tauri-apps/api/core";
 import getSystem from "@/utils/get-system";
 
@@ -11,10 +11,14 @@ import { useSystemProxyState } from "@/hooks/use-system-proxy-state"; // Ваш
 import { useSystemState } from "@/hooks/use-system-state";
 import { useServiceInstaller } from "@/hooks/useServiceInstaller";
 import {
+  getCaptivePortalBypass,
+  getServiceStatus,
+  repairService,
   uninstallService,
   restartCore,
   stopCore,
   invoke_uwp_tool,
+  setCaptivePortalBypass,
 } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 
@@ -44,6 +48,7 @@ import {
   BellOff,
   Repeat,
   Fingerprint,
+  Wifi,
 } from "lucide-react";
 
 // Модальные окна
@@ -122,6 +127,27 @@ const SettingSystem = ({ onError }: Props) => {
   const { isAdminMode, isServiceMode, mutateRunningMode } = useSystemState();
   const isTunAvailable = isServiceMode || isAdminMode;
 
+  const { data: serviceStatus, mutate: mutateServiceStatus } = useSWR(
+    "getServiceStatus",
+    getServiceStatus,
+  );
+  const needsRepair =
+    serviceStatus?.health === "outdated" ||
+    serviceStatus?.health === "unreachable";
+
+  const { data: portalBypass = false, mutate: mutatePortalBypass } = useSWR(
+    "getCaptivePortalBypass",
+    getCaptivePortalBypass,
+  );
+  const onEndPortalBypass = useLockFn(async () => {
+    try {
+      await setCaptivePortalBypass(false);
+      await mutatePortalBypass();
+    } catch (err: any) {
+      showNotice("error", err?.message || err.toString());
+    }
+  });
+
   const sysproxyRef = useRef<DialogRef>(null);
   const tunRef = useRef<DialogRef>(null);
 
@@ -154,6 +180,7 @@ const SettingSystem = ({ onError }: Props) => {
         showNotice("info", t("Restarting Core..."));
         await restartCore();
         await mutateRunningMode();
+        await mutateServiceStatus();
       } catch (err: any) {
         showNotice("error", err.message || err.toString());
         try {
@@ -167,6 +194,14 @@ const SettingSystem = ({ onError }: Props) => {
     },
   );
 
+  const onRepairService = () =>
+    handleServiceOperation({
+      beforeMsg: t("Stopping Core..."),
+      action: repairService,
+      actionMsg: t("Repairing Service..."),
+      successMsg: t("Service Repaired Successfully"),
+    });
+
   const onUninstallService = () =>
     handleServiceOperation({
       beforeMsg: t("Stopping Core..."),
@@ -203,7 +238,30 @@ const SettingSystem = ({ onError }: Props) => {
                   </Tooltip>
                 </TooltipProvider>
               )}
-              {!isServiceMode && !isAdminMode && (
+              {needsRepair && (
+                <TooltipProvider>
+                  <Tooltip>
+                    <TooltipTrigger asChild>
+                      <Button
+                        variant="outline"
+                        size="icon"
+                        className="h-7 w-7 text-amber-500"
+                        onClick={onRepairService}
+                      >
+                        <Wrench className="h-4 w-4" />
+                      </Button>
+                    </TooltipTrigger>
+                    <TooltipContent>
+                      <p>
+                        {t("Repair Service")}
+                        {serviceStatus?.version &&
+                          ` (${serviceStatus.version} → ${serviceStatus.required_version})`}
+                      </p>
+                    </TooltipContent>
+                  </Tooltip>
+                </TooltipProvider>
+              )}
+              {!isServiceMode && !isAdminMode && !needsRepair && (
                 <TooltipProvider>
                   <Tooltip>
                     <TooltipTrigger asChild>
@@ -402,6 +460,44 @@ const SettingSystem = ({ onError }: Props) => {
           </GuardState>
         </SettingRow>
 
+        <SettingRow
+          label={<LabelWithIcon icon={Wifi} text={t("Captive Portal")} />}
+          extra={
+            <div className="flex items-center gap-2">
+              <TooltipIcon tooltip={t("Captive Portal Info")} />
+              {portalBypass && (
+                <Button size="sm" variant="outline" onClick={onEndPortalBypass}>
+                  {t("End Bypass")}
+                </Button>
+              )}
+            </div>
+          }
+        >
+          <GuardState
+            value={verge?.captive_portal_mode ?? "ask"}
+            valueProps="value"
+            onChangeProps="onValueChange"
+            onFormat={(val) => val}
+            onGuard={(value) =>
+              patchVerge({
+                captive_portal_mode: value as "off" | "ask" | "auto",
+              })
+            }
+            onCatch={onError}
+          >
+            <Select>
+              <SelectTrigger className="w-[180px]">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="off">{t("Off")}</SelectItem>
+                <SelectItem value="ask">{t("Captive Portal Ask")}</SelectItem>
+                <SelectItem value="auto">{t("Captive Portal Auto")}</SelectItem>
+              </SelectContent>
+            </Select>
+          </GuardState>
+        </SettingRow>
+
         <SettingRow
           label={<LabelWithIcon icon={Fingerprint} text={t("Send HWID")} />}
         >
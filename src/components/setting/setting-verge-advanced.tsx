@@ -1,5 +1,6 @@
 import { useCallback, useRef } from "react";
 import { useTranslation } from "react-i18next";
+import { useLockFn } from "ahooks";
 import { check as checkUpdate } from "@tauri-apps/plugin-updater";
 import { version } from "@root/package.json";
 
@@ -11,6 +12,8 @@ import {
   openLogsDir,
   openDevTools,
   exportDiagnosticInfo,
+  resetNetwork,
+  scanConflicts,
 } from "@/services/cmds";
 import { showNotice } from "@/services/noticeService";
 
@@ -34,6 +37,9 @@ import {
   Feather,
   LogOut,
   ClipboardList,
+  Wrench,
+  ShieldAlert,
+  Bell,
 } from "lucide-react";
 
 // Модальные окна
@@ -45,6 +51,7 @@ import { LayoutViewer } from "./mods/layout-viewer";
 import { UpdateViewer } from "./mods/update-viewer";
 import { BackupViewer } from "./mods/backup-viewer";
 import { LiteModeViewer } from "./mods/lite-mode-viewer";
+import { NoticeHistoryViewer } from "./mods/notice-history-viewer";
 
 interface Props {
   onError?: (err: Error) => void;
@@ -86,6 +93,7 @@ const SettingVergeAdvanced = ({ onError }: Props) => {
   const updateRef = useRef<DialogRef>(null);
   const backupRef = useRef<DialogRef>(null);
   const liteModeRef = useRef<DialogRef>(null);
+  const noticeHistoryRef = useRef<DialogRef>(null);
 
   const onCheckUpdate = async () => {
     try {
@@ -105,6 +113,41 @@ const SettingVergeAdvanced = ({ onError }: Props) => {
     showNotice("success", t("Copy Success"), 1000);
   }, []);
 
+  const onScanConflicts = useLockFn(async () => {
+    try {
+      const conflicts = await scanConflicts();
+      if (conflicts.length === 0) {
+        showNotice("success", t("No Conflicting Software"));
+      } else {
+        const names = conflicts
+          .map((conflict) => `${conflict.name} (${conflict.detail})`)
+          .join(", ");
+        showNotice("error", `${t("Conflicting Software Found")}: ${names}`);
+      }
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
+  const onResetNetwork = useLockFn(async () => {
+    if (!window.confirm(t("Reset Network Confirm"))) return;
+    try {
+      const steps = await resetNetwork();
+      const failed = steps.filter((step) => !step.ok);
+      if (failed.length === 0) {
+        showNotice("success", t("Network Reset"));
+      } else {
+        const names = failed.map((step) => step.name).join(", ");
+        showNotice("error", `${t("Network Reset Partially Failed")}: ${names}`);
+      }
+      if (steps.some((step) => step.reboot_required)) {
+        showNotice("info", t("Network Reset Reboot Required"));
+      }
+    } catch (err: any) {
+      showNotice("error", err.message || err.toString());
+    }
+  });
+
   // Вспомогательная функция для создания лейбла с иконкой
   const LabelWithIcon = ({
     icon,
@@ -136,6 +179,7 @@ const SettingVergeAdvanced = ({ onError }: Props) => {
         <UpdateViewer ref={updateRef} />
         <BackupViewer ref={backupRef} />
         <LiteModeViewer ref={liteModeRef} />
+        <NoticeHistoryViewer ref={noticeHistoryRef} />
 
         {/* --- НАЧАЛО ИЗМЕНЕНИЙ 2: Добавляем иконки к каждому пункту --- */}
         <SettingRow
@@ -170,6 +214,22 @@ const SettingVergeAdvanced = ({ onError }: Props) => {
           onClick={openDevTools}
           label={<LabelWithIcon icon={Terminal} text={t("Open Dev Tools")} />}
         />
+        <SettingRow
+          onClick={() => noticeHistoryRef.current?.open()}
+          label={<LabelWithIcon icon={Bell} text={t("Notice History")} />}
+        />
+        <SettingRow
+          onClick={onScanConflicts}
+          label={
+            <LabelWithIcon icon={ShieldAlert} text={t("Check Conflicts")} />
+          }
+          extra={<TooltipIcon tooltip={t("Check Conflicts Info")} />}
+        />
+        <SettingRow
+          onClick={onResetNetwork}
+          label={<LabelWithIcon icon={Wrench} text={t("Reset Network")} />}
+          extra={<TooltipIcon tooltip={t("Reset Network Info")} />}
+        />
         <SettingRow
           label={
             <LabelWithIcon
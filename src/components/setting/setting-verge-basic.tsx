@@ -111,6 +111,7 @@ const SettingVergeBasic = ({ onError }: Props) => {
     theme_mode,
     language,
     tray_event,
+    tray_double_click_event,
     env_type,
     startup_script,
     start_page,
@@ -218,6 +219,45 @@ const SettingVergeBasic = ({ onError }: Props) => {
                     {t("System Proxy")}
                   </SelectItem>
                   <SelectItem value="tun_mode">{t("Tun Mode")}</SelectItem>
+                  <SelectItem value="web_ui">{t("Open Web UI")}</SelectItem>
+                  <SelectItem value="disable">{t("Disable")}</SelectItem>
+                </SelectContent>
+              </Select>
+            </GuardState>
+          </SettingRow>
+        )}
+
+        {OS === "windows" && (
+          <SettingRow
+            label={
+              <LabelWithIcon
+                icon={MousePointerClick}
+                text={t("Tray Double Click Event")}
+              />
+            }
+            extra={<TooltipIcon tooltip={t("Tray Double Click Event Info")} />}
+          >
+            <GuardState
+              value={tray_double_click_event ?? "disable"}
+              onCatch={onError}
+              onFormat={(v) => v}
+              onChange={(e) => onChangeData({ tray_double_click_event: e })}
+              onGuard={(e) => patchVerge({ tray_double_click_event: e })}
+              onChangeProps="onValueChange"
+            >
+              <Select>
+                <SelectTrigger className="w-40 h-8">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value="main_window">
+                    {t("Show Main Window")}
+                  </SelectItem>
+                  <SelectItem value="system_proxy">
+                    {t("System Proxy")}
+                  </SelectItem>
+                  <SelectItem value="tun_mode">{t("Tun Mode")}</SelectItem>
+                  <SelectItem value="web_ui">{t("Open Web UI")}</SelectItem>
                   <SelectItem value="disable">{t("Disable")}</SelectItem>
                 </SelectContent>
               </Select>
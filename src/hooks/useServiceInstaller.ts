@@ -74,6 +74,7 @@ export function useServiceInstaller() {
       await mutateRunningMode();
       const finalServiceStatus = await isServiceAvailable();
       mutate("isServiceAvailable", finalServiceStatus, false);
+      mutate("getServiceStatus");
 
       if (serviceReady && finalServiceStatus) {
         showNotice("success", t("Service is ready and core restarted"));
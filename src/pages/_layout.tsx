@@ -21,6 +21,8 @@ import { useClashInfo } from "@/hooks/use-clash";
 import { initGlobalLogService } from "@/services/global-log-service";
 import { invoke } from "@tauri-apps/api/core";
 import { showNotice } from "@/services/noticeService";
+import { setCaptivePortalBypass } from "@/services/cmds";
+import { toast } from "sonner";
 import { Toaster } from "@/components/ui/sonner";
 import { SidebarProvider, useSidebar } from "@/components/ui/sidebar";
 import { AppSidebar } from "@/components/layout/sidebar";
@@ -35,6 +37,26 @@ dayjs.extend(relativeTime);
 const OS = getSystem();
 
 // Notification Handler
+// 内核启动失败的原因及建议的修复方法
+const CORE_ERROR_HINTS: Record<ICoreErrorCode, string> = {
+  port_in_use: "Core Port In Use Hint",
+  tun_permission_denied: "Core TUN Permission Hint",
+  geodata_broken: "Core GeoData Broken Hint",
+  invalid_config: "Core Invalid Config Hint",
+  unknown: "Core Exited Unexpectedly",
+};
+
+const formatCoreDiagnosis = (msg: string, t: (key: string) => string) => {
+  try {
+    const diagnosis = JSON.parse(msg) as ICoreDiagnosis;
+    const hint = t(CORE_ERROR_HINTS[diagnosis.code]);
+    const detail = diagnosis.detail ? ` (${diagnosis.detail})` : "";
+    return `${hint}${detail}: ${diagnosis.message}`;
+  } catch {
+    return msg;
+  }
+};
+
 const handleNoticeMessage = (
   status: string,
   msg: string,
@@ -84,6 +106,15 @@ const handleNoticeMessage = (
     case "update_failed":
       showNotice("error", msg);
       break;
+    case "profile_quota_warning":
+      showNotice(
+        "info",
+        `${t("Subscription traffic almost used up")}: ${msg}`,
+      );
+      break;
+    case "profile_expiry_warning":
+      showNotice("info", `${t("Subscription expires soon")}: ${msg}`);
+      break;
     case "config_validate::boot_error":
       showNotice("error", `${t("Boot Config Validation Failed")} ${msg}`);
       break;
@@ -144,9 +175,117 @@ const handleNoticeMessage = (
     case "config_core::change_success":
       showNotice("success", `${t("Core Changed Successfully")}: ${msg}`);
       break;
+    case "config_core::version_error":
+      showNotice("error", `${t("Core Version Too Old")}: ${msg}`);
+      break;
     case "config_core::change_error":
       showNotice("error", `${t("Failed to Change Core")}: ${msg}`);
       break;
+    case "core_crash::exited":
+      showNotice("error", `${t("Core Exited Unexpectedly")}: ${msg}`);
+      break;
+    case "core_crash::diagnosed":
+      showNotice(
+        "error",
+        `${t("Core Failed To Start")}: ${formatCoreDiagnosis(msg, t)}`,
+        10000,
+      );
+      break;
+    case "core_crash::restarted":
+      showNotice("success", t("Core Restarted After Crash"));
+      break;
+    case "core_crash::gave_up":
+      showNotice("error", `${t("Core Restart Failed")}: ${msg}`);
+      break;
+    case "core_telemetry::memory_warning":
+      showNotice("info", `${t("Core Memory Usage High")}: ${msg}`);
+      break;
+    case "core_telemetry::memory_flushed":
+      showNotice("info", `${t("Core Memory Flushed")}: ${msg}`);
+      break;
+    case "core_telemetry::memory_restarted":
+      showNotice("success", t("Core Restarted Over Memory Limit"));
+      break;
+    case "core_health::unhealthy":
+      showNotice("error", `${t("Core Not Responding")}: ${msg}`);
+      break;
+    case "core_health::recovered":
+      showNotice("success", t("Core Responding Again"));
+      break;
+    case "core_sandbox::not_applied":
+      showNotice("error", `${t("Core Sandbox Not Applied")}: ${msg}`);
+      break;
+    case "core_launch::ignored_by_service":
+      showNotice("info", t("Core Launch Options Ignored By Service"));
+      break;
+    case "core_failsafe::sysproxy_suspended":
+      showNotice("error", `${t("System Proxy Suspended")}: ${msg}`);
+      break;
+    case "core_failsafe::sysproxy_restored":
+      showNotice("success", t("System Proxy Restored"));
+      break;
+    case "core_failsafe::tun_disabled":
+      showNotice("error", `${t("TUN Disabled Core Down")}: ${msg}`);
+      break;
+    case "service::repair_needed":
+      showNotice("error", `${t("Service Needs Repair")}: ${msg}`);
+      break;
+    case "conflicts::found":
+      showNotice("error", `${t("Conflicting Software Found")}: ${msg}`);
+      break;
+    case "proxy_guard::tampered":
+      showNotice("error", `${t("System Proxy Changed By")}: ${msg}`);
+      break;
+    case "proxy_guard::restored":
+      showNotice("info", `${t("System Proxy Restored")}: ${msg}`);
+      break;
+    case "dns_hijack::repaired":
+      showNotice("success", t("DNS Hijack Repaired"));
+      break;
+    case "dns_hijack::broken":
+      showNotice("error", t("DNS Hijack Broken"));
+      break;
+    case "core_resume::restarted":
+      showNotice("success", t("Core Restarted After Resume"));
+      break;
+    case "core_resume::failed":
+      showNotice("error", `${t("Core Restart Failed")}: ${msg}`);
+      break;
+    case "geodata::updated":
+      showNotice("success", t("GeoData Updated"));
+      break;
+    case "port_conflict::resolved":
+      showNotice("info", `${t("Ports Moved")}: ${msg}`);
+      break;
+    case "port_conflict::error":
+      showNotice("error", `${t("Port In Use")}: ${msg}`);
+      break;
+    case "captive_portal::detected":
+      toast.info(t("Captive Portal Detected"), {
+        duration: 30000,
+        action: {
+          label: t("Bypass Proxy"),
+          onClick: () =>
+            setCaptivePortalBypass(true).catch((err) =>
+              showNotice("error", err?.message || err.toString()),
+            ),
+        },
+      });
+      break;
+    case "captive_portal::bypassed":
+      showNotice("info", t("Captive Portal Bypassed"), 10000);
+      mutate("getCaptivePortalBypass");
+      break;
+    case "captive_portal::restored":
+      showNotice("success", t("Captive Portal Restored"));
+      mutate("getCaptivePortalBypass");
+      break;
+    case "winhttp_proxy::error":
+      showNotice("error", `${t("WinHTTP Proxy Not Set")}: ${msg}`);
+      break;
+    case "core_integrity::mismatch":
+      showNotice("error", `${t("Core Integrity Mismatch")}: ${msg}`);
+      break;
     default: // Optional: Log unhandled statuses
       console.warn(`[Notification Listener V2] Unprocessed state: ${status}`);
       break;
@@ -199,6 +338,7 @@ const Layout = () => {
     const listeners = [
       addListener("verge://refresh-clash-config", async () => {
         await getAxios(true);
+        mutate("getClashInfo");
         mutate("getProxies");
         mutate("getVersion");
         mutate("getClashConfig");
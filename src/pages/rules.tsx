@@ -14,6 +14,7 @@ import { cn } from "@root/lib/utils";
 import { BaseEmpty } from "@/components/base";
 import RuleItem from "@/components/rule/rule-item";
 import { ProviderButton } from "@/components/rule/provider-button";
+import { AppRulesButton } from "@/components/rule/app-rules-button";
 import { BaseSearchBox } from "@/components/base/base-search-box";
 import { ScrollTopButton } from "@/components/layout/scroll-top-button";
 
@@ -84,6 +85,7 @@ const RulesPage = () => {
             <div className="w-70">
               <BaseSearchBox onSearch={handleSearch} />
             </div>
+            <AppRulesButton />
             <ProviderButton />
           </div>
         </div>
@@ -1,12 +1,50 @@
-import axios, { AxiosInstance } from "axios";
+import axios, { AxiosAdapter, AxiosInstance } from "axios";
 import { getClashInfo } from "./cmds";
 import { invoke } from "@tauri-apps/api/core";
+import { setControllerIpc } from "@/utils/websocket";
+
+// 控制接口走 unix socket / 命名管道时，请求由后端转发
+const ipcAdapter: AxiosAdapter = async (config) => {
+  const url = new URL(axios.getUri(config), "http://localhost");
+  const { status, body } = await invoke<{ status: number; body: string }>(
+    "clash_api_request",
+    {
+      method: config.method ?? "get",
+      path: `${url.pathname}${url.search}`,
+      body: config.data ?? null,
+    },
+  );
+  let data: any = body;
+  try {
+    data = body ? JSON.parse(body) : {};
+  } catch {}
+
+  const response = {
+    data,
+    status,
+    statusText: `${status}`,
+    headers: {},
+    config,
+    request: null,
+  };
+  if (status >= 400) {
+    throw new axios.AxiosError(
+      data?.message ?? `Request failed with status code ${status}`,
+      `${status}`,
+      config,
+      null,
+      response,
+    );
+  }
+  return response;
+};
 
 let instancePromise: Promise<AxiosInstance> = null!;
 
 async function getInstancePromise() {
   let server = "";
   let secret = "";
+  let ipc = false;
 
   try {
     const info = await getClashInfo();
@@ -19,12 +57,15 @@ async function getInstancePromise() {
       else if (/^\d+$/.test(server)) server = `127.0.0.1:${server}`;
     }
     if (info?.secret) secret = info?.secret;
+    ipc = !!info?.ipc;
   } catch {}
+  setControllerIpc(ipc);
 
   const axiosIns = axios.create({
     baseURL: `http://${server}`,
     headers: secret ? { Authorization: `Bearer ${secret}` } : {},
     timeout: 15000,
+    ...(ipc ? { adapter: ipcAdapter } : {}),
   });
   axiosIns.interceptors.response.use((r) => r.data);
   return axiosIns;
@@ -50,10 +50,88 @@ export async function reorderProfile(activeId: string, overId: string) {
   });
 }
 
+export async function vacuumProfiles(mode: "report" | "delete" | "archive") {
+  return invoke<{
+    orphaned_files: string[];
+    dangling_items: string[];
+    archive_dir?: string;
+  }>("vacuum_profiles", { mode });
+}
+
+export async function setProfileTags(index: string, tags: string[]) {
+  return invoke<void>("set_profile_tags", { index, tags });
+}
+
+export async function setProfileSortIndex(index: string, sortIndex?: number) {
+  return invoke<void>("set_profile_sort_index", { index, sortIndex });
+}
+
+export async function reorderProfileMerges(index: string, merges: string[]) {
+  return invoke<void>("reorder_profile_merges", { index, merges });
+}
+
 export async function updateProfile(index: string, option?: IProfileOption) {
   return invoke<void>("update_profile", { index, option });
 }
 
+export async function validateProfile(uid: string) {
+  return invoke<{
+    valid: boolean;
+    errors: {
+      source: "profile" | "script" | "core";
+      line?: number;
+      column?: number;
+      message: string;
+    }[];
+  }>("validate_profile", { uid });
+}
+
+export async function findDuplicateProfile(
+  url?: string,
+  fileData?: string | null,
+) {
+  return invoke<string | null>("find_duplicate_profile", { url, fileData });
+}
+
+export async function duplicateProfile(index: string) {
+  return invoke<string>("duplicate_profile", { index });
+}
+
+export async function diffProfiles(uidA: string, uidB: string) {
+  return invoke<{
+    proxies_only_a: string[];
+    proxies_only_b: string[];
+    changed_proxies: string[];
+    groups_only_a: string[];
+    groups_only_b: string[];
+    changed_groups: string[];
+    rules_only_a: string[];
+    rules_only_b: string[];
+  }>("diff_profiles", { uidA, uidB });
+}
+
+export async function searchProfiles(
+  query: string,
+  regex?: boolean,
+  limit?: number,
+) {
+  return invoke<
+    {
+      uid: string;
+      name?: string;
+      score: number;
+      name_matched: boolean;
+      url_matched: boolean;
+      proxies: string[];
+      proxy_count: number;
+    }[]
+  >("search_profiles", { query, regex, limit });
+}
+
+export async function deduplicateProfiles() {
+  return invoke<string[]>("deduplicate_profiles");
+}
+
 export async function deleteProfile(index: string) {
   return invoke<void>("delete_profile", { index });
 }
@@ -78,6 +156,10 @@ export async function getRuntimeYaml() {
   return invoke<string | null>("get_runtime_yaml");
 }
 
+export async function exportRuntimeConfig(path: string) {
+  return invoke<void>("export_runtime_config", { path });
+}
+
 export async function getRuntimeExists() {
   return invoke<string[]>("get_runtime_exists");
 }
@@ -86,6 +168,10 @@ export async function getRuntimeLogs() {
   return invoke<Record<string, [string, string][]>>("get_runtime_logs");
 }
 
+export async function getRuntimeMergeSources() {
+  return invoke<Record<string, string>>("get_runtime_merge_sources");
+}
+
 export async function patchClashConfig(payload: Partial<IConfigData>) {
   return invoke<void>("patch_clash_config", { payload });
 }
@@ -94,6 +180,14 @@ export async function patchClashMode(payload: String) {
   return invoke<void>("patch_clash_mode", { payload });
 }
 
+export async function setCoreLogLevel(level: string) {
+  return invoke<void>("set_core_log_level", { level });
+}
+
+export async function getSecondaryCorePort() {
+  return invoke<number | null>("get_secondary_core_port");
+}
+
 export async function getVergeConfig() {
   return invoke<IVergeConfig>("get_verge_config");
 }
@@ -110,6 +204,54 @@ export async function getSystemProxy() {
   }>("get_sys_proxy");
 }
 
+export async function getBypassPresets() {
+  return invoke<{ id: string; entries: string[] }[]>("get_bypass_presets");
+}
+
+export async function getNetworkServices() {
+  return invoke<INetworkService[]>("get_network_services");
+}
+
+export async function getCurrentNetworks() {
+  return invoke<string[]>("get_current_networks");
+}
+
+export async function getCaptivePortalBypass() {
+  return invoke<boolean>("get_captive_portal_bypass");
+}
+
+export async function setCaptivePortalBypass(enable: boolean) {
+  return invoke<void>("set_captive_portal_bypass", { enable });
+}
+
+export async function validateBypass(bypass: string) {
+  return invoke<string[]>("validate_bypass", { bypass });
+}
+
+export async function checkDnsHijack(repair: boolean) {
+  return invoke<IDnsHijackReport>("check_dns_hijack", { repair });
+}
+
+export async function scanConflicts() {
+  return invoke<IConflict[]>("scan_conflicts");
+}
+
+export async function getLanShare() {
+  return invoke<ILanShare>("get_lan_share");
+}
+
+export async function setLanShare(enable: boolean) {
+  return invoke<ILanShare>("set_lan_share", { enable });
+}
+
+export async function getLanClients() {
+  return invoke<ILanClient[]>("get_lan_clients");
+}
+
+export async function resetNetwork() {
+  return invoke<IResetStep[]>("reset_network");
+}
+
 export async function getAutotemProxy() {
   try {
     console.log("[API] Start calling get_auto_proxy");
@@ -141,6 +283,66 @@ export async function changeClashCore(clashCore: string) {
   return invoke<string | null>("change_clash_core", { clashCore });
 }
 
+export async function benchTunStacks(node?: string) {
+  return invoke<ITunBenchReport>("bench_tun_stacks", { node });
+}
+
+export async function getTunBench() {
+  return invoke<ITunBenchReport | null>("get_tun_bench");
+}
+
+export async function applyTunStack(stack: string) {
+  return invoke<void>("apply_tun_stack", { stack });
+}
+
+export async function getInstalledCores() {
+  return invoke<ICoreInfo[]>("get_installed_cores");
+}
+
+export async function checkCoreUpdate(clashCore: string) {
+  return invoke<ICoreRelease>("check_core_update", { clashCore });
+}
+
+export async function updateCore(clashCore: string) {
+  return invoke<ICoreRelease>("update_core", { clashCore });
+}
+
+export async function getGeoDataStatus() {
+  return invoke<IGeoDataStatus>("get_geodata_status");
+}
+
+export async function updateGeoDataFiles() {
+  return invoke<IGeoDataStatus>("update_geodata");
+}
+
+export async function trustCoreBinary(clashCore: string) {
+  return invoke<void>("trust_core_binary", { clashCore });
+}
+
+export async function rollbackCore(clashCore: string) {
+  return invoke<void>("rollback_core", { clashCore });
+}
+
+export async function rotateClashSecret() {
+  return invoke<void>("rotate_clash_secret");
+}
+
+export async function getCoreTelemetry() {
+  return invoke<ICoreSample[]>("get_core_telemetry");
+}
+
+export async function getCoreHealth() {
+  return invoke<ICoreHealth>("get_core_health");
+}
+
+export async function getCoreSandboxStatus() {
+  return invoke<ICoreSandboxStatus>("get_core_sandbox_status");
+}
+
+export async function getCoreDiagnosis() {
+  return invoke<ICoreDiagnosis | null>("get_core_diagnosis");
+}
+
 export async function startCore() {
   return invoke<void>("start_core");
 }
@@ -179,6 +381,20 @@ export async function openLogsDir() {
   );
 }
 
+export async function openScriptsDir() {
+  return invoke<void>("open_scripts_dir").catch((err) =>
+    showNotice("error", err?.message || err.toString()),
+  );
+}
+
+export async function getNoticeHistory() {
+  return invoke<INoticeRecord[]>("get_notice_history");
+}
+
+export async function clearNoticeHistory() {
+  return invoke<void>("clear_notice_history");
+}
+
 export const openWebUrl = async (url: string) => {
   try {
     await invoke("open_web_url", { url });
@@ -246,6 +462,18 @@ export async function invoke_uwp_tool() {
   );
 }
 
+export async function getUwpApps() {
+  return invoke<IUwpApp[]>("get_uwp_apps");
+}
+
+export async function setUwpLoopback(sids: string[], exempt: boolean) {
+  return invoke<void>("set_uwp_loopback", { sids, exempt });
+}
+
+export async function exemptAllUwpApps() {
+  return invoke<number>("exempt_all_uwp_apps");
+}
+
 export async function getPortableFlag() {
   return invoke<boolean>("get_portable_flag");
 }
@@ -268,7 +496,7 @@ export async function getSystemInfo() {
 
 export async function copyIconFile(
   path: string,
-  name: "common" | "sysproxy" | "tun",
+  name: "common" | "sysproxy" | "tun" | "global" | "direct",
 ) {
   const key = `icon_${name}_update_time`;
   const previousTime = localStorage.getItem(key) || "";
@@ -380,6 +608,11 @@ export const isServiceAvailable = async () => {
     return false;
   }
 };
+
+// 系统服务的握手与版本状态
+export const getServiceStatus = async () => {
+  return invoke<IServiceStatus>("get_service_status");
+};
 export const entry_lightweight_mode = async () => {
   return invoke<void>("entry_lightweight_mode");
 };
@@ -407,3 +640,78 @@ export async function createProfileFromShareLink(
 ) {
   return invoke<void>("create_profile_from_share_link", { link, templateName });
 }
+
+export async function importShareLinks(data: string, name?: string) {
+  return invoke<string[]>("import_share_links", { data, name });
+}
+
+export async function importSingBoxConfig(data: string, name?: string) {
+  return invoke<string[]>("import_singbox_config", { data, name });
+}
+
+export async function importFromClipboard(dryRun?: boolean) {
+  return invoke<{
+    format:
+      | "url"
+      | "urls"
+      | "share_links"
+      | "clash_yaml"
+      | "sing_box"
+      | "unknown";
+    imported: boolean;
+    skipped: string[];
+  }>("import_from_clipboard", { dryRun });
+}
+
+export async function importProfileDir(dir: string, watch?: boolean) {
+  return invoke<{ imported: string[]; skipped: string[] }>(
+    "import_profile_dir",
+    { dir, watch },
+  );
+}
+
+export async function unwatchProfileDir(dir: string) {
+  return invoke<void>("unwatch_profile_dir", { dir });
+}
+
+export async function migrateProfileEncryption(enable: boolean) {
+  return invoke<number>("migrate_profile_encryption", { enable });
+}
+
+export async function getProfilesHealth() {
+  return invoke<
+    {
+      uid: string;
+      name?: string;
+      last_update?: number;
+      fail_count: number;
+      last_failure?: number;
+      last_duration?: number;
+      stale_since?: number;
+      stale: boolean;
+    }[]
+  >("get_profiles_health");
+}
+
+export async function setProfileAuthPassword(
+  auth: IProfileAuth,
+  password?: string,
+) {
+  return invoke<void>("set_profile_auth_password", { auth, password });
+}
+
+export async function getAppRules() {
+  return invoke<IAppRule[]>("get_app_rules");
+}
+
+export async function addAppRule(rule: IAppRule) {
+  return invoke<void>("add_app_rule", { rule });
+}
+
+export async function removeAppRule(kind: IAppRule["kind"], value: string) {
+  return invoke<void>("remove_app_rule", { kind, value });
+}
+
+export async function getRunningApps() {
+  return invoke<IRunningApp[]>("get_running_apps");
+}
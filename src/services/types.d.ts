@@ -178,12 +178,162 @@ interface IClashInfo {
   port?: number; // clash http port
   server?: string; // external-controller
   secret?: string;
+  ipc?: string; // external-controller-unix / external-controller-pipe
+}
+
+interface ICoreInfo {
+  name: string;
+  path: string;
+  installed: boolean;
+  active: boolean;
+}
+
+interface ICoreRelease {
+  core: string;
+  version: string;
+  asset: string;
+  published_at?: string;
+  can_rollback: boolean;
+}
+
+interface ICoreSample {
+  timestamp: number;
+  memory?: number;
+  rss?: number;
+  cpu?: number;
+}
+
+interface ICoreHealth {
+  healthy: boolean;
+  consecutive_failures: number;
+  last_error?: string;
+  last_check?: number;
+}
+
+interface ICoreSandboxStatus {
+  enabled: boolean;
+  applied: boolean;
+  reason?: string;
+}
+
+type ICoreErrorCode =
+  | "port_in_use"
+  | "tun_permission_denied"
+  | "geodata_broken"
+  | "invalid_config"
+  | "unknown";
+
+interface ICoreDiagnosis {
+  code: ICoreErrorCode;
+  fix?: "change_port" | "install_service" | "update_geodata" | "edit_profile";
+  detail?: string; // port, geodata file or config line
+  exit_code?: number;
+  message: string;
+}
+
+interface IAppRule {
+  kind: "PROCESS-NAME" | "PROCESS-PATH";
+  value: string;
+  target: string; // proxy group, DIRECT or REJECT
+}
+
+interface IRunningApp {
+  name: string;
+  path?: string;
+}
+
+interface IDnsHijackReport {
+  status: "ok" | "repaired" | "broken" | "skipped";
+  fixes: ("flush_cache" | "reload_config")[];
+  detail: string; // last answer of the system resolver
+}
+
+interface INetworkService {
+  name: string;
+  device: string;
+  active: boolean; // carries the default route
+}
+
+interface IServiceStatus {
+  health: "not_installed" | "healthy" | "outdated" | "unreachable";
+  version: string | null;
+  required_version: string;
+  last_error: string | null;
+}
+
+interface IUwpApp {
+  sid: string; // app container SID
+  name: string; // package family name
+  display_name: string;
+  exempt: boolean; // may reach the local proxy
+}
+
+interface IConflict {
+  kind: "proxy_client" | "dpi_bypass" | "vpn" | "foreign_proxy";
+  name: string;
+  detail: string; // process, interface or proxy address it was found by
+}
+
+interface INoticeRecord {
+  status: string;
+  message: string;
+  time: number; // unix ms
+  state: "shown" | "merged" | "quiet"; // merged into a summary, or held back
+}
+
+interface ILanShare {
+  enabled: boolean;
+  port: number;
+  urls: string[]; // http://192.168.1.5:7897 for every LAN address
+  qr_code: string | null; // SVG of the first url
+  firewall_open: boolean | null; // null where the firewall isn't managed
+}
+
+interface ILanClient {
+  ip: string;
+  connections: number; // open right now
+  last_seen: number; // unix seconds
+}
+
+interface IResetStep {
+  name: string;
+  ok: boolean;
+  detail: string;
+  reboot_required: boolean;
+}
+
+interface ITunStackResult {
+  stack: string;
+  latency_ms?: number;
+  throughput_mbps?: number;
+  error?: string;
+}
+
+interface ITunBenchReport {
+  results: ITunStackResult[];
+  recommended?: string;
+  current: string; // stack in use before the benchmark
+  node: string; // proxy the benchmark traffic went through
+  timestamp: number;
+}
+
+interface IGeoFileStatus {
+  name: string;
+  size: number;
+  updated?: number;
+}
+
+interface IGeoDataStatus {
+  files: IGeoFileStatus[];
+  last_check?: number;
+  last_error?: string;
+  updating: boolean;
 }
 
 interface IProfileItem {
   currentProfile: any;
   uid: string;
-  type?: "local" | "remote" | "merge" | "script";
+  type?: "local" | "remote" | "composite" | "merge" | "script";
   name?: string;
   desc?: string;
   file?: string;
@@ -204,27 +354,67 @@ interface IProfileItem {
   support_url?: string;
   announce?: string;
   announce_url?: string;
+  group?: string;
+  locked?: boolean;
+  fail_count?: number;
+  last_failure?: number;
+  last_duration?: number;
+  stale_since?: number;
+  tags?: string[];
+  sort_index?: number;
 }
 
 interface IProfileOption {
   user_agent?: string;
   with_proxy?: boolean;
   self_proxy?: boolean;
+  download_proxy?: string;
+  mirrors?: string[];
+  auth?: IProfileAuth;
   update_interval?: number;
+  update_cron?: string;
   update_always?: boolean;
+  update_mode?: "full" | "proxies";
+  mute_notices?: boolean;
+  min_core_version?: string;
+  dns?: {
+    nameserver?: string[];
+    fallback?: string[];
+    "default-nameserver"?: string[];
+    "fake-ip-filter"?: string[];
+  };
   timeout_seconds?: number;
   danger_accept_invalid_certs?: boolean;
+  headers?: Record<string, string>;
   merge?: string;
+  merges?: string[];
   script?: string;
   rules?: string;
   proxies?: string;
   groups?: string;
+  sources?: string[];
+}
+
+interface IProfileAuth {
+  login_url: string;
+  username?: string;
+  username_field?: string;
+  token_field?: string;
+  header?: string;
+  scheme?: string;
+}
+
+interface IProfileGroup {
+  uid: string;
+  name: string;
 }
 
 interface IProfilesConfig {
   current?: string;
   valid?: string[];
   items?: IProfileItem[];
+  groups?: IProfileGroup[];
+  recent?: string[];
 }
 
 interface IVergeTestItem {
@@ -233,6 +423,17 @@ interface IVergeTestItem {
   icon?: string;
   url: string;
 }
+interface IVergeGeoMirror {
+  name?: string;
+  url?: string;
+  proxy?: string;
+  enable?: boolean;
+}
+interface IVergeTrayItem {
+  label?: string;
+  action?: "url" | "command" | "script";
+  target?: string;
+}
 interface IAddress {
   V4?: {
     ip: string;
@@ -729,21 +930,60 @@ interface IVergeConfig {
     | "tray_menu"
     | "system_proxy"
     | "tun_mode"
+    | "web_ui"
+    | string;
+  tray_double_click_event?:
+    | "main_window"
+    | "system_proxy"
+    | "tun_mode"
+    | "web_ui"
+    | "disable"
     | string;
+  notice_quiet_hours?: string; // HH:MM-HH:MM, empty for none
   env_type?: "bash" | "cmd" | "powershell" | "fish" | string;
   startup_script?: string;
   start_page?: string;
   clash_core?: string;
+  enable_stable_secret?: boolean;
+  enable_controller_ipc?: boolean;
+  disable_tcp_controller?: boolean;
+  enable_external_core?: boolean;
+  external_controller?: string;
+  external_secret?: string;
+  core_startup_timeout?: number;
+  core_health_interval?: number;
+  core_health_endpoint?: string;
+  core_health_failures?: number;
+  core_down_disable_sysproxy?: boolean;
+  core_down_disable_tun?: boolean;
+  tun_ipv6_mode?: "off" | "route" | "disable";
+  enable_port_auto_resolve?: boolean;
+  lan_allowed_ips?: string[];
+  lan_disallowed_ips?: string[];
+  enable_core_sandbox?: boolean;
+  enable_secondary_core?: boolean;
+  secondary_mixed_port?: number;
+  secondary_interface_name?: string;
+  core_extra_args?: string[];
+  core_extra_env?: string[];
+  geodata_update_interval?: number;
+  geodata_mirrors?: IVergeGeoMirror[];
   theme_mode?: "light" | "dark" | "system";
   traffic_graph?: boolean;
   enable_memory_usage?: boolean;
   enable_group_icon?: boolean;
   menu_icon?: "monochrome" | "colorful" | "disable";
   tray_icon?: "monochrome" | "colorful";
+  tray_icon_render?: "auto" | "template" | "colored"; // macOS menu bar
   common_tray_icon?: boolean;
   sysproxy_tray_icon?: boolean;
   tun_tray_icon?: boolean;
+  global_tray_icon?: boolean;
+  direct_tray_icon?: boolean;
   enable_tray_speed?: boolean;
+  tray_speed_interval?: number;
+  tray_custom_items?: IVergeTrayItem[];
+  enable_tray_proxies?: boolean;
   enable_tray_icon?: boolean;
   enable_tun_mode?: boolean;
   enable_auto_light_weight_mode?: boolean;
@@ -757,7 +997,12 @@ interface IVergeConfig {
   enable_send_hwid?: boolean;
   proxy_auto_config?: boolean;
   pac_file_content?: string;
+  pac_from_rules?: boolean;
   proxy_host?: string;
+  proxy_auth_username?: string;
+  proxy_auth_password?: string;
+  /** only returned by the backend, the password itself isn't */
+  proxy_auth_password_set?: boolean;
   enable_random_port?: boolean;
   verge_mixed_port?: number;
   verge_socks_port?: number;
@@ -771,8 +1016,14 @@ interface IVergeConfig {
   enable_proxy_guard?: boolean;
   enable_bypass_check?: boolean;
   use_default_bypass?: boolean;
+  bypass_reserved_ranges?: boolean;
   proxy_guard_duration?: number;
   system_proxy_bypass?: string;
+  system_proxy_bypass_presets?: string[];
+  system_proxy_services?: string[];
+  enable_winhttp_proxy?: boolean;
+  sysproxy_networks?: string[];
+  captive_portal_mode?: "off" | "ask" | "auto";
   web_ui_list?: string[];
   hotkeys?: string[];
   theme_setting?: {
@@ -1,4 +1,13 @@
 import Sockette, { type SocketteOptions } from "sockette";
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// 控制接口走 unix socket / 命名管道时，流式接口由后端转发
+let controllerIpc = false;
+
+export const setControllerIpc = (enabled: boolean) => {
+  controllerIpc = enabled;
+};
 
 /**
  * A wrapper of Sockette that will automatically reconnect up to `maxError` before emitting an error event.
@@ -58,6 +67,10 @@ export const createAuthSockette = (
     url = `ws://${url}`;
   }
 
+  if (controllerIpc) {
+    return createIpcSocket(url, opt);
+  }
+
   // 重试控制
   let reconnectAttempts = 0;
   const MAX_RECONNECT = maxError;
@@ -260,3 +273,68 @@ function createDummySocket() {
     open: () => {},
   };
 }
+
+/**
+ * 通过后端转发的流式接口，接口与 createAuthSockette 的返回值一致
+ */
+function createIpcSocket(url: string, opt: SocketteOptions) {
+  let path = "/";
+  try {
+    const urlObj = new URL(url);
+    urlObj.searchParams.delete("token");
+    path = `${urlObj.pathname}${urlObj.search}`;
+  } catch {}
+
+  const anyOpt = opt as any;
+  let id: number | null = null;
+  let unlisten: UnlistenFn | null = null;
+  let closed = false;
+
+  async function connect() {
+    closed = false;
+    unlisten = await listen<{ id: number; data: string | null }>(
+      "verge://controller-stream",
+      ({ payload }) => {
+        if (payload.id !== id || payload.data === null) return;
+        anyOpt.onmessage?.(
+          new MessageEvent("message", { data: payload.data }),
+        );
+      },
+    );
+    try {
+      id = await invoke<number>("clash_api_stream_open", { path });
+      if (closed) {
+        cleanup();
+        return;
+      }
+      anyOpt.onopen?.(new Event("open"));
+    } catch (error) {
+      console.error(`[WebSocket] 创建转发连接失败: ${path}`, error);
+      anyOpt.onerror?.(new ErrorEvent("error", { message: `${error}` } as any));
+    }
+  }
+
+  function cleanup() {
+    closed = true;
+    unlisten?.();
+    unlisten = null;
+    if (id !== null) {
+      invoke("clash_api_stream_close", { id }).catch(() => {});
+      id = null;
+    }
+  }
+
+  connect();
+
+  return {
+    ws: null as WebSocket | null,
+    close: cleanup,
+    reconnect: () => {
+      cleanup();
+      connect();
+    },
+    json: () => {},
+    send: () => {},
+    open: connect,
+  };
+}